@@ -0,0 +1,38 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// One prompt of a batch. Its reply is written to a new conversation.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "batch_items")]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub batch_id: i32,
+    pub prompt: String,
+    /// The conversation holding the prompt and its reply, once the batch is completed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conversation_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::batches::Entity",
+        from = "Column::BatchId",
+        to = "super::batches::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Batches,
+}
+
+impl Related<super::batches::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Batches.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
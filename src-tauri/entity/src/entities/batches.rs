@@ -0,0 +1,65 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(Some(16))")]
+#[serde(rename_all = "camelCase")]
+pub enum BatchStatus {
+    /// Waiting to be submitted, again if submitting failed
+    #[sea_orm(string_value = "queued")]
+    Queued,
+    /// Submitted to the provider, waiting for its results
+    #[sea_orm(string_value = "submitted")]
+    Submitted,
+    #[sea_orm(string_value = "completed")]
+    Completed,
+    #[sea_orm(string_value = "failed")]
+    Failed,
+}
+
+/// Prompts sent together through the batch API of a provider, at a lower cost than one by one
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "batches")]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub model_id: i32,
+    /// Id of the batch at the provider, once submitted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote_id: Option<String>,
+    pub status: BatchStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub created_at: DateTimeLocal,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTimeLocal>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::models::Entity",
+        from = "Column::ModelId",
+        to = "super::models::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Models,
+    #[sea_orm(has_many = "super::batch_items::Entity")]
+    BatchItems,
+}
+
+impl Related<super::models::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Models.def()
+    }
+}
+
+impl Related<super::batch_items::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::BatchItems.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
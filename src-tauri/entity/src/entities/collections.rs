@@ -0,0 +1,63 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(Some(16))")]
+#[serde(rename_all = "camelCase")]
+pub enum SyncStatus {
+    /// Files were added or removed since the last sync
+    #[sea_orm(string_value = "pending")]
+    Pending,
+    #[sea_orm(string_value = "synced")]
+    Synced,
+    #[sea_orm(string_value = "failed")]
+    Failed,
+}
+
+/// A knowledge collection of local files, mirrored to a remote OpenAI vector store
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "collections")]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub name: String,
+    /// The model whose credentials are used to reach the vector store
+    pub model_id: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vector_store_id: Option<String>,
+    pub status: SyncStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_synced_at: Option<DateTimeLocal>,
+    pub created_at: DateTimeLocal,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::models::Entity",
+        from = "Column::ModelId",
+        to = "super::models::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Models,
+    #[sea_orm(has_many = "super::collection_files::Entity")]
+    CollectionFiles,
+}
+
+impl Related<super::models::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Models.def()
+    }
+}
+
+impl Related<super::collection_files::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CollectionFiles.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
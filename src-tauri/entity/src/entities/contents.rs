@@ -3,7 +3,7 @@
 use sea_orm::{
     entity::prelude::*,
     ActiveValue::{self, NotSet},
-    IntoActiveModel,
+    FromQueryResult, IntoActiveModel,
 };
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
@@ -62,6 +62,22 @@ impl Related<super::messages::Entity> for Entity {
 
 impl ActiveModelBehavior for ActiveModel {}
 
+/// A text content matching a full-text search, with the snippet around the match
+#[derive(Clone, Debug, FromQueryResult)]
+pub struct ContentMatch {
+    pub message_id: i32,
+    pub snippet: String,
+}
+
+/// A text content matching a full-text search across all conversations
+#[derive(Clone, Debug, FromQueryResult)]
+pub struct ConversationContentMatch {
+    pub conversation_id: i32,
+    pub conversation_subject: String,
+    pub message_id: i32,
+    pub snippet: String,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ContentDTO {
     pub r#type: ContentType,
@@ -0,0 +1,39 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Summary of the older messages of a conversation, sent in their place
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "conversation_summaries")]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    #[sea_orm(unique)]
+    pub conversation_id: i32,
+    pub summary: String,
+    /// Id of the most recent message the summary covers
+    pub last_message_id: i32,
+    pub created_at: DateTimeLocal,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTimeLocal>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::conversations::Entity",
+        from = "Column::ConversationId",
+        to = "super::conversations::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Conversations,
+}
+
+impl Related<super::conversations::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Conversations.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
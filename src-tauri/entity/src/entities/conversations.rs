@@ -3,8 +3,9 @@
 use sea_orm::{entity::prelude::*, ActiveValue::NotSet, FromQueryResult, Set};
 use serde::{Deserialize, Serialize};
 
-pub const DEFAULT_CONTEXT_LENGTH: u16 = 1;
+pub const DEFAULT_CONTEXT_LENGTH: u16 = 0;
 pub const DEFAULT_MAX_TOKENS: u32 = 256;
+pub const DEFAULT_MAX_CONTINUATIONS: u32 = 2;
 
 #[derive(Clone, Default, Debug, PartialEq, DeriveEntityModel, Eq, Deserialize, Serialize)]
 #[sea_orm(table_name = "conversations")]
@@ -30,6 +31,48 @@ pub struct Model {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(skip_deserializing)]
     pub last_message_at: Option<DateTimeLocal>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip_deserializing)]
+    pub summary: Option<String>,
+    /// Locked conversations are read-only
+    #[serde(skip_deserializing)]
+    pub is_locked: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip_deserializing)]
+    pub gist_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip_deserializing)]
+    pub gist_url: Option<String>,
+    /// Set when the conversation is backed by a remote OpenAI assistant thread
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip_deserializing)]
+    pub assistant_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip_deserializing)]
+    pub thread_id: Option<String>,
+    /// Set when the replies must match a JSON Schema
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip_deserializing)]
+    pub response_schema_id: Option<i32>,
+    /// Language the replies must be written in, e.g. "French" or "ja"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip_deserializing)]
+    pub language: Option<String>,
+    /// When the messages of the conversation were last seen in any window
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip_deserializing)]
+    pub read_at: Option<DateTimeLocal>,
+    /// JSON array of the ids of the models replying when the model is unavailable,
+    /// in order. Unset to use the global fallback models.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip_deserializing)]
+    pub fallback_model_ids: Option<String>,
+    /// Pinned conversations are listed first
+    #[serde(skip_deserializing)]
+    pub is_pinned: bool,
+    /// Archived conversations are left out of the list unless asked for
+    #[serde(skip_deserializing)]
+    pub is_archived: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -72,6 +115,48 @@ pub struct ConversationDetailsDTO {
     pub updated_at: Option<DateTimeLocal>,
     pub message_count: Option<i32>,
     pub model_provider: Option<String>,
+    pub summary: Option<String>,
+    pub is_locked: bool,
+    pub gist_id: Option<String>,
+    pub gist_url: Option<String>,
+    pub assistant_id: Option<String>,
+    pub thread_id: Option<String>,
+    pub response_schema_id: Option<i32>,
+    pub language: Option<String>,
+    pub last_message_at: Option<DateTimeLocal>,
+    pub read_at: Option<DateTimeLocal>,
+    pub fallback_model_ids: Option<String>,
+    pub is_pinned: bool,
+    pub is_archived: bool,
+}
+
+/// Order conversations are listed in, after the pinned ones
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConversationSort {
+    /// Most recent message first
+    #[default]
+    LastActivity,
+    /// Newest first
+    CreatedAt,
+    /// Longest first
+    MessageCount,
+}
+
+/// Which conversations to list, and which page of them
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversationFilter {
+    #[serde(default)]
+    pub include_archived: bool,
+    /// Only the conversations with this tag
+    pub tag_id: Option<i32>,
+    /// Only the conversations whose subject contains this text, ignoring case
+    pub query: Option<String>,
+    #[serde(default)]
+    pub sort: ConversationSort,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -101,6 +186,18 @@ impl From<UpdateConversationDTO> for ActiveModel {
             updated_at: NotSet,
             deleted_at: NotSet,
             last_message_at: NotSet,
+            summary: NotSet,
+            is_locked: NotSet,
+            gist_id: NotSet,
+            gist_url: NotSet,
+            assistant_id: NotSet,
+            thread_id: NotSet,
+            response_schema_id: NotSet,
+            language: NotSet,
+            read_at: NotSet,
+            fallback_model_ids: NotSet,
+            is_pinned: NotSet,
+            is_archived: NotSet,
         }
     }
 }
@@ -233,6 +330,8 @@ pub struct ClaudeOptions {
     pub user: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub show_reasoning: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking_budget: Option<u32>, // Tokens the model may think with, min: 1024. Off when unset or 0.
 }
 
 impl Default for ClaudeOptions {
@@ -245,6 +344,80 @@ impl Default for ClaudeOptions {
             top_p: Some(1.0),
             user: None,
             show_reasoning: None,
+            thinking_budget: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BedrockOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_length: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>, // Ranges and defaults depend on the model
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub show_reasoning: Option<bool>,
+}
+
+impl Options for BedrockOptions {}
+
+impl Default for BedrockOptions {
+    fn default() -> Self {
+        BedrockOptions {
+            context_length: None,
+            max_tokens: None,
+            stream: Some(false),
+            temperature: None,
+            top_p: None,
+            show_reasoning: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CohereOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_length: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>, // Defaults to 0.0. Ranges from 0.0 to 1.0.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>, // Defaults to 0.0. Ranges from 0.0 to 1.0.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>, // Defaults to 0.3. Ranges from 0.0 to 1.0.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>, // Defaults to 0.75. Ranges from 0.01 to 0.99.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>, // Defaults to 0 (off). Ranges from 0 to 500.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub web_search: Option<bool>, // Let the model search the web and cite its sources
+}
+
+impl Options for CohereOptions {}
+
+impl Default for CohereOptions {
+    fn default() -> Self {
+        CohereOptions {
+            context_length: None,
+            frequency_penalty: None,
+            max_tokens: None,
+            presence_penalty: None,
+            stream: Some(false),
+            temperature: Some(0.3),
+            top_p: None,
+            top_k: None,
+            web_search: None,
         }
     }
 }
@@ -305,6 +478,8 @@ pub struct DeepseekOptions {
     pub temperature: Option<f32>, // min: 0, max: 2, default: 1,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub top_p: Option<f32>, // min: 0, max: 1, default: 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub show_reasoning: Option<bool>, // Shows the chain of thought of deepseek-reasoner
 }
 
 impl Options for DeepseekOptions {}
@@ -319,6 +494,7 @@ impl Default for DeepseekOptions {
             stream: Some(false),
             temperature: Some(1.0),
             top_p: Some(1.0),
+            show_reasoning: None,
         }
     }
 }
@@ -375,6 +551,8 @@ pub struct GoogleOptions {
     pub temperature: Option<f32>, // min: 0, max: 2, default: 1,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub top_p: Option<f32>, // min: 0, max: 1, default: 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking_budget: Option<u32>, // Tokens the model may think with, 0 turns thinking off. Model default when unset.
 }
 
 impl Options for GoogleOptions {}
@@ -389,6 +567,7 @@ impl Default for GoogleOptions {
             stream: Some(false),
             temperature: Some(1.0),
             top_p: Some(1.0),
+            thinking_budget: None,
         }
     }
 }
\ No newline at end of file
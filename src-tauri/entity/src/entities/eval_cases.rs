@@ -0,0 +1,43 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A test prompt of an eval set
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "eval_cases")]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub eval_set_id: i32,
+    pub prompt: String,
+    /// The answer a reply must contain to pass, or the reference given to the judge
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::eval_sets::Entity",
+        from = "Column::EvalSetId",
+        to = "super::eval_sets::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    EvalSets,
+}
+
+impl Related<super::eval_sets::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::EvalSets.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewEvalCase {
+    pub prompt: String,
+    pub expected: Option<String>,
+}
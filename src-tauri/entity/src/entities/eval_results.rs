@@ -0,0 +1,49 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The reply of a model to an eval case, with its grade
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "eval_results")]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub eval_run_id: i32,
+    pub eval_case_id: i32,
+    pub model_id: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply: Option<String>,
+    /// Between 0 and 1, None when the case can't be graded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+    pub latency_ms: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_token: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completion_token: Option<i32>,
+    /// In USD, None when the prices of the model are unknown
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::eval_runs::Entity",
+        from = "Column::EvalRunId",
+        to = "super::eval_runs::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    EvalRuns,
+}
+
+impl Related<super::eval_runs::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::EvalRuns.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,43 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// One run of the prompts of an eval set against some models
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "eval_runs")]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub eval_set_id: i32,
+    pub created_at: DateTimeLocal,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<DateTimeLocal>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::eval_sets::Entity",
+        from = "Column::EvalSetId",
+        to = "super::eval_sets::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    EvalSets,
+    #[sea_orm(has_many = "super::eval_results::Entity")]
+    EvalResults,
+}
+
+impl Related<super::eval_sets::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::EvalSets.def()
+    }
+}
+
+impl Related<super::eval_results::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::EvalResults.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
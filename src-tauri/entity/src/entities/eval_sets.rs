@@ -0,0 +1,38 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A set of test prompts to compare models with
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "eval_sets")]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub name: String,
+    /// The model grading the replies, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub judge_model_id: Option<i32>,
+    pub created_at: DateTimeLocal,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::eval_cases::Entity")]
+    EvalCases,
+    #[sea_orm(has_many = "super::eval_runs::Entity")]
+    EvalRuns,
+}
+
+impl Related<super::eval_cases::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::EvalCases.def()
+    }
+}
+
+impl Related<super::eval_runs::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::EvalRuns.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,74 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(Some(16))")]
+#[serde(rename_all = "camelCase")]
+pub enum FinetuneJobStatus {
+    #[sea_orm(string_value = "validating_files")]
+    ValidatingFiles,
+    #[sea_orm(string_value = "queued")]
+    Queued,
+    #[sea_orm(string_value = "running")]
+    Running,
+    #[sea_orm(string_value = "succeeded")]
+    Succeeded,
+    #[sea_orm(string_value = "failed")]
+    Failed,
+    #[sea_orm(string_value = "cancelled")]
+    Cancelled,
+}
+
+impl FinetuneJobStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            FinetuneJobStatus::Succeeded | FinetuneJobStatus::Failed | FinetuneJobStatus::Cancelled
+        )
+    }
+}
+
+/// A fine-tuning job started at the provider of a model
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "finetune_jobs")]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    /// The model whose credentials and base model the job uses
+    pub model_id: i32,
+    pub remote_id: String,
+    pub training_file_id: String,
+    pub status: FinetuneJobStatus,
+    /// Name of the resulting model at the provider
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fine_tuned_model: Option<String>,
+    /// The model registered in the app once the job succeeded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result_model_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub created_at: DateTimeLocal,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTimeLocal>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::models::Entity",
+        from = "Column::ModelId",
+        to = "super::models::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Models,
+}
+
+impl Related<super::models::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Models.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,60 @@
+use sea_orm::{entity::prelude::*, FromQueryResult};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(Some(8))")]
+#[serde(rename_all = "camelCase")]
+pub enum Rating {
+    #[sea_orm(string_value = "up")]
+    Up,
+    #[sea_orm(string_value = "down")]
+    Down,
+}
+
+/// The user's rating of, or reaction to, a message
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "message_feedback")]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    #[sea_orm(unique)]
+    pub message_id: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rating: Option<Rating>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emoji: Option<String>,
+    pub created_at: DateTimeLocal,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTimeLocal>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::messages::Entity",
+        from = "Column::MessageId",
+        to = "super::messages::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Messages,
+}
+
+impl Related<super::messages::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Messages.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// Ratings received by a model, to compare models with each other
+#[derive(Clone, Debug, FromQueryResult, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelFeedbackStats {
+    pub model_id: i32,
+    pub model_alias: String,
+    pub thumbs_up: i64,
+    pub thumbs_down: i64,
+}
@@ -43,6 +43,8 @@ pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
     pub conversation_id: i32,
+    // message this one answers or follows, None for the first message
+    pub parent_message_id: Option<i32>,
     pub role: i32,
     pub reasoning: Option<String>,
     // token usage
@@ -50,6 +52,11 @@ pub struct Model {
     pub completion_token: Option<u32>,
     pub reasoning_token: Option<u32>,
     pub total_token: Option<u32>,
+    // JSON object with details about how the message was generated
+    pub metadata: Option<String>,
+    /// Messages of the branches not shown in the conversation
+    #[serde(skip_deserializing)]
+    pub is_hidden: bool,
     #[serde(skip_deserializing)]
     pub created_at: DateTimeLocal,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -111,6 +118,8 @@ pub struct MessageDTO {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub id: Option<i32>,
     pub conversation_id: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_message_id: Option<i32>,
     pub role: i32,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub reasoning: Option<String>,
@@ -122,6 +131,8 @@ pub struct MessageDTO {
     pub reasoning_token: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub total_token: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<String>,
     #[serde(skip_deserializing)]
     pub created_at: DateTimeLocal,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -152,12 +163,14 @@ impl From<(Model, Vec<super::contents::Model>)> for MessageDTO {
         MessageDTO {
             id: Some(message.id),
             conversation_id: message.conversation_id,
+            parent_message_id: message.parent_message_id,
             role: message.role,
             reasoning: message.reasoning,
             prompt_token: message.prompt_token,
             completion_token: message.completion_token,
             reasoning_token: message.reasoning_token,
             total_token: message.total_token,
+            metadata: message.metadata,
             created_at: message.created_at,
             updated_at: message.updated_at,
             deleted_at: message.deleted_at,
@@ -171,6 +184,9 @@ impl IntoActiveModel<ActiveModel> for MessageDTO {
         ActiveModel {
             id: self.id.map_or(NotSet, |id| Set(id)),
             conversation_id: Set(self.conversation_id),
+            parent_message_id: self
+                .parent_message_id
+                .map_or(NotSet, |parent_message_id| Set(Some(parent_message_id))),
             role: Set(self.role),
             reasoning: self
                 .reasoning
@@ -187,6 +203,9 @@ impl IntoActiveModel<ActiveModel> for MessageDTO {
             total_token: self
                 .total_token
                 .map_or(NotSet, |total_token| Set(Some(total_token))),
+            metadata: self
+                .metadata
+                .map_or(NotSet, |metadata| Set(Some(metadata))),
             ..Default::default()
         }
     }
@@ -219,6 +238,7 @@ mod tests {
         let dto = MessageDTO {
             id: Some(1),
             conversation_id: 1,
+            parent_message_id: None,
             role: 0,
             reasoning: None,
             content: vec![
@@ -237,6 +257,7 @@ mod tests {
             completion_token: None,
             reasoning_token: None,
             total_token: None,
+            metadata: None,
             created_at: Local::now(),
             updated_at: None,
             deleted_at: None,
@@ -247,6 +268,7 @@ mod tests {
         let dto_no_text = MessageDTO {
             id: Some(1),
             conversation_id: 1,
+            parent_message_id: None,
             role: 0,
             reasoning: None,
             content: vec![ContentDTO {
@@ -258,6 +280,7 @@ mod tests {
             completion_token: None,
             reasoning_token: None,
             total_token: None,
+            metadata: None,
             created_at: Local::now(),
             updated_at: None,
             deleted_at: None,
@@ -272,12 +295,15 @@ mod tests {
         let model = Model {
             id: 1,
             conversation_id: 2,
+            parent_message_id: None,
             role: 0,
             reasoning: Some("Test reasoning".to_string()),
             prompt_token: Some(10),
             completion_token: Some(20),
             reasoning_token: Some(10),
             total_token: Some(30),
+            metadata: None,
+            is_hidden: false,
             created_at: now,
             updated_at: None,
             deleted_at: None,
@@ -317,12 +343,14 @@ mod tests {
         let dto = MessageDTO {
             id: Some(1),
             conversation_id: 2,
+            parent_message_id: Some(3),
             role: 0,
             reasoning: Some("Test reasoning".to_string()),
             reasoning_token: Some(10),
             prompt_token: Some(10),
             completion_token: Some(20),
             total_token: Some(30),
+            metadata: None,
             content: vec![],
             created_at: now,
             updated_at: None,
@@ -333,6 +361,7 @@ mod tests {
 
         assert_eq!(Set(1), active_model.id);
         assert_eq!(Set(2), active_model.conversation_id);
+        assert_eq!(Set(Some(3)), active_model.parent_message_id);
         assert_eq!(Set(0), active_model.role);
         assert_eq!(Set(Some(10)), active_model.prompt_token);
         assert_eq!(Set(Some(20)), active_model.completion_token);
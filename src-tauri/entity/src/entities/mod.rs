@@ -2,9 +2,28 @@
 
 pub mod prelude;
 
+pub mod batch_items;
+pub mod batches;
+pub mod collection_files;
+pub mod collections;
 pub mod contents;
+pub mod conversation_summaries;
+pub mod conversation_tags;
 pub mod conversations;
+pub mod eval_cases;
+pub mod eval_results;
+pub mod eval_runs;
+pub mod eval_sets;
+pub mod finetune_jobs;
+pub mod message_feedback;
 pub mod messages;
+pub mod model_prices;
 pub mod models;
 pub mod prompts;
+pub mod response_cache;
+pub mod response_schemas;
 pub mod settings;
+pub mod snapshot_messages;
+pub mod snapshots;
+pub mod stats;
+pub mod tags;
@@ -0,0 +1,51 @@
+use sea_orm::{entity::prelude::*, FromQueryResult};
+use serde::{Deserialize, Serialize};
+
+/// Prices of a model set by the user, in USD per 1k tokens. They take precedence over
+/// the bundled prices.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "model_prices")]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    #[sea_orm(unique)]
+    pub model_id: i32,
+    pub input_price: f64,
+    pub output_price: f64,
+    pub updated_at: DateTimeLocal,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::models::Entity",
+        from = "Column::ModelId",
+        to = "super::models::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Models,
+}
+
+impl Related<super::models::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Models.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// Tokens used by the replies of a conversation on a day
+#[derive(Clone, Debug, PartialEq, FromQueryResult)]
+pub struct TokenUsage {
+    /// Formatted as YYYY-MM-DD
+    pub day: String,
+    pub conversation_id: i32,
+    pub conversation_subject: String,
+    pub model_id: Option<i32>,
+    pub model_alias: Option<String>,
+    pub model_config: Option<String>,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+}
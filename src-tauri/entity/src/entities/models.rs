@@ -12,6 +12,10 @@ pub enum Providers {
     Deepseek,
     Xai,
     Google,
+    Bedrock,
+    Cohere,
+    Together,
+    Fireworks,
     CUSTOM,
     Unknown,
 }
@@ -27,6 +31,10 @@ impl From<&str> for Providers {
             "Deepseek" => Providers::Deepseek,
             "Xai" => Providers::Xai,
             "Google" => Providers::Google,
+            "Bedrock" => Providers::Bedrock,
+            "Cohere" => Providers::Cohere,
+            "Together" => Providers::Together,
+            "Fireworks" => Providers::Fireworks,
             "CUSTOM" => Providers::CUSTOM,
             _ => Providers::Unknown,
         }
@@ -56,6 +64,10 @@ impl Into<String> for Providers {
             Providers::Deepseek => "Deepseek".to_owned(),
             Providers::Xai => "Xai".to_owned(),
             Providers::Google => "Google".to_owned(),
+            Providers::Bedrock => "Bedrock".to_owned(),
+            Providers::Cohere => "Cohere".to_owned(),
+            Providers::Together => "Together".to_owned(),
+            Providers::Fireworks => "Fireworks".to_owned(),
             Providers::CUSTOM => "CUSTOM".to_owned(),
             _ => "Unknown".to_owned(),
         }
@@ -71,6 +83,22 @@ pub struct Model {
     pub alias: String,
     pub provider: String,
     pub config: String,
+    /// Context window in tokens, None when unknown
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_length: Option<u32>,
+    /// Longest reply in tokens, None when unknown
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<u32>,
+    /// JSON object of the options new conversations with the model start with, over
+    /// the default options of its provider
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_options: Option<String>,
+    /// Seconds to wait for a connection to the provider, over the global setting
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connect_timeout: Option<u32>,
+    /// Seconds to wait for each read of the response, over the global setting
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_timeout: Option<u32>,
     #[serde(skip_deserializing)]
     pub created_at: Option<DateTimeLocal>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -108,4 +136,14 @@ pub struct NewModel {
     pub alias: String,
     pub provider: String,
     pub config: String,
+    #[serde(default)]
+    pub context_length: Option<u32>,
+    #[serde(default)]
+    pub max_output_tokens: Option<u32>,
+    #[serde(default)]
+    pub default_options: Option<String>,
+    #[serde(default)]
+    pub connect_timeout: Option<u32>,
+    #[serde(default)]
+    pub read_timeout: Option<u32>,
 }
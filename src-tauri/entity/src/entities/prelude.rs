@@ -1,8 +1,27 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.14
 
+pub use super::batch_items::Entity as BatchItems;
+pub use super::batches::Entity as Batches;
+pub use super::collection_files::Entity as CollectionFiles;
+pub use super::collections::Entity as Collections;
 pub use super::contents::Entity as Contents;
+pub use super::conversation_summaries::Entity as ConversationSummaries;
+pub use super::conversation_tags::Entity as ConversationTags;
 pub use super::conversations::Entity as Conversations;
+pub use super::eval_cases::Entity as EvalCases;
+pub use super::eval_results::Entity as EvalResults;
+pub use super::eval_runs::Entity as EvalRuns;
+pub use super::eval_sets::Entity as EvalSets;
+pub use super::finetune_jobs::Entity as FinetuneJobs;
+pub use super::message_feedback::Entity as MessageFeedback;
 pub use super::messages::Entity as Messages;
+pub use super::model_prices::Entity as ModelPrices;
 pub use super::models::Entity as Models;
 pub use super::prompts::Entity as Prompts;
+pub use super::response_cache::Entity as ResponseCache;
+pub use super::response_schemas::Entity as ResponseSchemas;
 pub use super::settings::Entity as Settings;
+pub use super::snapshot_messages::Entity as SnapshotMessages;
+pub use super::snapshots::Entity as Snapshots;
+pub use super::stats::Entity as Stats;
+pub use super::tags::Entity as Tags;
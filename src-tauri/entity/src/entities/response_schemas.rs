@@ -0,0 +1,29 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A JSON Schema the replies of a conversation must match
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "response_schemas")]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    /// Sent to the provider as the name of the schema, so it should be a short identifier
+    pub name: String,
+    /// The JSON Schema, as JSON
+    pub schema: String,
+    pub created_at: DateTimeLocal,
+    pub updated_at: Option<DateTimeLocal>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(DeriveIntoActiveModel, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct NewResponseSchema {
+    pub name: String,
+    pub schema: String,
+}
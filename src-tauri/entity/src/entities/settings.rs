@@ -4,10 +4,53 @@ use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
 pub const SETTING_NETWORK_PROXY: &str = "network:proxy";
+// Prefix of the proxy settings of providers, followed by the name of the provider,
+// used over the global proxy setting
+pub const SETTING_NETWORK_PROXY_PREFIX: &str = "network:proxy:";
+// JSON object of the seconds to wait for a connection and for each read, by default
+pub const SETTING_NETWORK_TIMEOUTS: &str = "network:timeouts";
 pub const SETTING_MODELS_CONTEXT_LENGTH: &str = "models:context_length";
 pub const SETTING_MODELS_MAX_TOKENS: &str = "models:max_tokens";
+pub const SETTING_MODELS_MAX_CONTINUATIONS: &str = "models:max_continuations";
+// How many times a request failing with a transient error is sent, the first time included
+pub const SETTING_MODELS_MAX_ATTEMPTS: &str = "models:max_attempts";
+// Minutes a reply is reused for identical requests, 0 to turn the cache off
+pub const SETTING_MODELS_CACHE_TTL: &str = "models:cache_ttl";
+// Estimated cost in USD above which a request must be confirmed, empty or 0 to turn it off
+pub const SETTING_MODELS_COST_THRESHOLD: &str = "models:cost_threshold";
+// JSON array of the ids of the models replying when the model of a conversation is unavailable
+pub const SETTING_MODELS_FALLBACK: &str = "models:fallback";
 pub const SETTING_USER_DEFAULT_MODEL: &str = "user:default_model";
 pub const SETTING_DISPLAY_LANGUAGE: &str = "display:language";
+pub const SETTING_NOTIFICATION_ON_REPLY: &str = "notification:on_reply";
+pub const SETTING_API_SERVER_ENABLED: &str = "api_server:enabled";
+pub const SETTING_API_SERVER_PORT: &str = "api_server:port";
+pub const SETTING_API_SERVER_TOKEN: &str = "api_server:token";
+pub const SETTING_UPDATE_CHANNEL: &str = "update:channel";
+pub const SETTING_CRASH_REPORTS_UPLOAD: &str = "crash_reports:upload";
+pub const SETTING_CRASH_REPORTS_ENDPOINT: &str = "crash_reports:endpoint";
+pub const SETTING_CLOSE_TO_TRAY: &str = "window:close_to_tray";
+pub const SETTING_APP_LOCK_HASH: &str = "security:lock_hash";
+pub const SETTING_APP_LOCK_IDLE_MINUTES: &str = "security:idle_minutes";
+pub const SETTING_JOBS_RUN_ON_BATTERY: &str = "jobs:run_on_battery";
+pub const SETTING_JOBS_DAILY_BACKUP: &str = "jobs:daily_backup";
+pub const SETTING_SUMMARY_MODEL: &str = "summary:model";
+pub const SETTING_REFINE_MODEL: &str = "refine:model";
+// Conversations are titled after their first reply unless set to "false"
+pub const SETTING_TITLE_AUTO: &str = "title:auto";
+pub const SETTING_TITLE_MODEL: &str = "title:model";
+pub const SETTING_MODERATION_ENABLED: &str = "moderation:enabled";
+pub const SETTING_MODERATION_MODEL: &str = "moderation:model";
+pub const SETTING_GITHUB_TOKEN: &str = "github:token";
+pub const SETTING_INSIGHTS_ENABLED: &str = "insights:enabled";
+// Date the defaults of the workspace were seeded, unset before the first launch
+pub const SETTING_APP_BOOTSTRAPPED: &str = "app:bootstrapped";
+// JSON array of regex patterns redacted in addition to the built-in ones
+pub const SETTING_PRIVACY_PATTERNS: &str = "privacy:patterns";
+// Followed by the provider name, JSON object of the default options of its models
+pub const SETTING_PROVIDER_OPTIONS_PREFIX: &str = "provider_options:";
+// Followed by the window label
+pub const SETTING_WINDOW_STATE_PREFIX: &str = "window:state:";
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Deserialize, Serialize)]
 #[sea_orm(table_name = "settings")]
@@ -23,7 +66,7 @@ pub enum Relation {}
 
 impl ActiveModelBehavior for ActiveModel {}
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ProxySetting {
     pub on: bool,
@@ -33,3 +76,13 @@ pub struct ProxySetting {
     pub username: Option<String>,
     pub password: Option<String>,
 }
+
+/// Seconds to wait for requests to providers, None to wait for as long as it takes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeoutSetting {
+    #[serde(default)]
+    pub connect: Option<u32>,
+    #[serde(default)]
+    pub read: Option<u32>,
+}
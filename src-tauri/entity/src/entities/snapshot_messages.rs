@@ -0,0 +1,47 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A message belonging to a snapshot
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "snapshot_messages")]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub snapshot_id: i32,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub message_id: i32,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::snapshots::Entity",
+        from = "Column::SnapshotId",
+        to = "super::snapshots::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Snapshots,
+    #[sea_orm(
+        belongs_to = "super::messages::Entity",
+        from = "Column::MessageId",
+        to = "super::messages::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Messages,
+}
+
+impl Related<super::snapshots::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Snapshots.def()
+    }
+}
+
+impl Related<super::messages::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Messages.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,42 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A checkpoint of the messages of a conversation, to roll the conversation back to
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "snapshots")]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub conversation_id: i32,
+    pub label: String,
+    pub created_at: DateTimeLocal,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::conversations::Entity",
+        from = "Column::ConversationId",
+        to = "super::conversations::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Conversations,
+    #[sea_orm(has_many = "super::snapshot_messages::Entity")]
+    SnapshotMessages,
+}
+
+impl Related<super::conversations::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Conversations.def()
+    }
+}
+
+impl Related<super::snapshot_messages::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::SnapshotMessages.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
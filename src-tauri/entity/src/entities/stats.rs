@@ -0,0 +1,57 @@
+use sea_orm::{entity::prelude::*, FromQueryResult};
+use serde::{Deserialize, Serialize};
+
+/// A local usage event. Only the name of the feature and its latency are kept.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "stats")]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub event: String,
+    pub latency_ms: Option<i64>,
+    pub created_at: DateTimeLocal,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Clone, Debug, Serialize, FromQueryResult)]
+#[serde(rename_all = "camelCase")]
+pub struct FeatureUsage {
+    pub event: String,
+    pub count: i64,
+    pub average_latency_ms: Option<f64>,
+}
+
+#[derive(Clone, Debug, Serialize, FromQueryResult)]
+#[serde(rename_all = "camelCase")]
+pub struct MonthlyActivity {
+    /// Formatted as YYYY-MM
+    pub month: String,
+    pub count: i64,
+}
+
+/// Activity of a day, for the heatmap of the timeline
+#[derive(Clone, Debug, PartialEq, Serialize, FromQueryResult)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyActivity {
+    /// Formatted as YYYY-MM-DD
+    pub day: String,
+    /// Conversations created that day
+    pub conversations: i64,
+    /// Messages the user sent that day
+    pub messages: i64,
+}
+
+/// What a rebuild of the derived data touched
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DerivedDataReport {
+    /// Conversations whose last message date was recomputed
+    pub conversations: u64,
+    /// Text contents added back to the search index
+    pub indexed_contents: u64,
+}
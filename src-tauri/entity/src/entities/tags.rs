@@ -0,0 +1,40 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A label grouping conversations, like a folder a conversation can be in many of
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "tags")]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    #[sea_orm(unique)]
+    pub name: String,
+    /// CSS color the tag is shown with, e.g. "#f59e0b"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(skip_deserializing)]
+    pub created_at: DateTimeLocal,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::conversation_tags::Entity")]
+    ConversationTags,
+}
+
+impl Related<super::conversation_tags::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ConversationTags.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(DeriveIntoActiveModel, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct NewTag {
+    pub name: String,
+    pub color: Option<String>,
+}
@@ -10,6 +10,33 @@ mod m20240101_100001_seed_settings;
 mod m20240101_100002_seed_prompts;
 mod m20240820_000001_conversations_add_last_message_at;
 mod m20250214_000001_messages_add_reasoning_fields;
+mod m20261017_000001_conversations_add_summary;
+mod m20261017_000002_create_stats;
+mod m20261017_000003_conversations_add_is_locked;
+mod m20261017_000004_create_message_feedback;
+mod m20261017_000005_conversations_add_gist_fields;
+mod m20261017_000006_create_batches;
+mod m20261017_000007_conversations_add_assistant_fields;
+mod m20261017_000008_create_finetune_jobs;
+mod m20261017_000009_create_collections;
+mod m20261017_000010_create_response_schemas;
+mod m20261017_000011_create_response_cache;
+mod m20261017_000012_create_evals;
+mod m20261017_000013_create_snapshots;
+mod m20261017_000014_messages_add_metadata;
+mod m20261017_000015_create_contents_fts;
+mod m20261017_000016_models_add_context_fields;
+mod m20261017_000017_conversations_add_language;
+mod m20261017_000018_conversations_add_read_at;
+mod m20261017_000019_conversations_add_fallback_model_ids;
+mod m20261017_000020_create_conversation_summaries;
+mod m20261017_000021_create_model_prices;
+mod m20261017_000022_messages_add_parent_message_id;
+mod m20261017_000023_conversations_add_pin_archive;
+mod m20261017_000024_create_tags;
+mod m20261017_000025_models_add_default_options;
+mod m20261017_000026_models_add_timeouts;
+mod m20261017_000027_messages_add_is_hidden;
 
 
 pub struct Migrator;
@@ -28,6 +55,33 @@ impl MigratorTrait for Migrator {
             Box::new(m20240101_100002_seed_prompts::Migration),
             Box::new(m20240820_000001_conversations_add_last_message_at::Migration),
             Box::new(m20250214_000001_messages_add_reasoning_fields::Migration),
+            Box::new(m20261017_000001_conversations_add_summary::Migration),
+            Box::new(m20261017_000002_create_stats::Migration),
+            Box::new(m20261017_000003_conversations_add_is_locked::Migration),
+            Box::new(m20261017_000004_create_message_feedback::Migration),
+            Box::new(m20261017_000005_conversations_add_gist_fields::Migration),
+            Box::new(m20261017_000006_create_batches::Migration),
+            Box::new(m20261017_000007_conversations_add_assistant_fields::Migration),
+            Box::new(m20261017_000008_create_finetune_jobs::Migration),
+            Box::new(m20261017_000009_create_collections::Migration),
+            Box::new(m20261017_000010_create_response_schemas::Migration),
+            Box::new(m20261017_000011_create_response_cache::Migration),
+            Box::new(m20261017_000012_create_evals::Migration),
+            Box::new(m20261017_000013_create_snapshots::Migration),
+            Box::new(m20261017_000014_messages_add_metadata::Migration),
+            Box::new(m20261017_000015_create_contents_fts::Migration),
+            Box::new(m20261017_000016_models_add_context_fields::Migration),
+            Box::new(m20261017_000017_conversations_add_language::Migration),
+            Box::new(m20261017_000018_conversations_add_read_at::Migration),
+            Box::new(m20261017_000019_conversations_add_fallback_model_ids::Migration),
+            Box::new(m20261017_000020_create_conversation_summaries::Migration),
+            Box::new(m20261017_000021_create_model_prices::Migration),
+            Box::new(m20261017_000022_messages_add_parent_message_id::Migration),
+            Box::new(m20261017_000023_conversations_add_pin_archive::Migration),
+            Box::new(m20261017_000024_create_tags::Migration),
+            Box::new(m20261017_000025_models_add_default_options::Migration),
+            Box::new(m20261017_000026_models_add_timeouts::Migration),
+            Box::new(m20261017_000027_messages_add_is_hidden::Migration),
         ]
     }
 }
@@ -0,0 +1,72 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum MessageFeedback {
+    Table,
+    Id,
+    MessageId,
+    Rating,
+    Emoji,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(MessageFeedback::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(MessageFeedback::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(MessageFeedback::MessageId)
+                            .integer()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(MessageFeedback::Rating).string().null())
+                    .col(ColumnDef::new(MessageFeedback::Emoji).string().null())
+                    .col(
+                        ColumnDef::new(MessageFeedback::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(MessageFeedback::UpdatedAt)
+                            .timestamp()
+                            .null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_message_feedback_messages")
+                            .from(MessageFeedback::Table, MessageFeedback::MessageId)
+                            .to(
+                                super::m20240101_000004_create_messages::Messages::Table,
+                                super::m20240101_000004_create_messages::Messages::Id,
+                            )
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MessageFeedback::Table).to_owned())
+            .await
+    }
+}
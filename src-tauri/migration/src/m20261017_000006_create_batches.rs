@@ -0,0 +1,104 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20240101_000001_create_models::Models;
+
+#[derive(DeriveIden)]
+enum Batches {
+    Table,
+    Id,
+    ModelId,
+    RemoteId,
+    Status,
+    Error,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum BatchItems {
+    Table,
+    Id,
+    BatchId,
+    Prompt,
+    ConversationId,
+    Error,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Batches::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Batches::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Batches::ModelId).integer().not_null())
+                    .col(ColumnDef::new(Batches::RemoteId).string().null())
+                    .col(ColumnDef::new(Batches::Status).string().not_null())
+                    .col(ColumnDef::new(Batches::Error).string().null())
+                    .col(
+                        ColumnDef::new(Batches::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(ColumnDef::new(Batches::UpdatedAt).timestamp().null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_batches_models")
+                            .from(Batches::Table, Batches::ModelId)
+                            .to(Models::Table, Models::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(BatchItems::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(BatchItems::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(BatchItems::BatchId).integer().not_null())
+                    .col(ColumnDef::new(BatchItems::Prompt).string().not_null())
+                    .col(ColumnDef::new(BatchItems::ConversationId).integer().null())
+                    .col(ColumnDef::new(BatchItems::Error).string().null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_batch_items_batches")
+                            .from(BatchItems::Table, BatchItems::BatchId)
+                            .to(Batches::Table, Batches::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(BatchItems::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Batches::Table).to_owned())
+            .await
+    }
+}
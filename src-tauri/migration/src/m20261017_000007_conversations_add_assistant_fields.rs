@@ -0,0 +1,43 @@
+use super::m20240101_000003_create_conversations::Conversations;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const ASSISTANT_ID_COL_NAME: &str = "assistant_id";
+const THREAD_ID_COL_NAME: &str = "thread_id";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for col_name in [ASSISTANT_ID_COL_NAME, THREAD_ID_COL_NAME] {
+            if !manager.has_column("conversations", col_name).await? {
+                manager
+                    .alter_table(
+                        Table::alter()
+                            .table(Conversations::Table)
+                            .add_column(ColumnDef::new(Alias::new(col_name)).string().null())
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for col_name in [ASSISTANT_ID_COL_NAME, THREAD_ID_COL_NAME] {
+            if manager.has_column("conversations", col_name).await? {
+                manager
+                    .alter_table(
+                        Table::alter()
+                            .table(Conversations::Table)
+                            .drop_column(Alias::new(col_name))
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20240101_000001_create_models::Models;
+
+#[derive(DeriveIden)]
+enum FinetuneJobs {
+    Table,
+    Id,
+    ModelId,
+    RemoteId,
+    TrainingFileId,
+    Status,
+    FineTunedModel,
+    ResultModelId,
+    Error,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(FinetuneJobs::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(FinetuneJobs::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(FinetuneJobs::ModelId).integer().not_null())
+                    .col(ColumnDef::new(FinetuneJobs::RemoteId).string().not_null())
+                    .col(
+                        ColumnDef::new(FinetuneJobs::TrainingFileId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(FinetuneJobs::Status).string().not_null())
+                    .col(ColumnDef::new(FinetuneJobs::FineTunedModel).string().null())
+                    .col(ColumnDef::new(FinetuneJobs::ResultModelId).integer().null())
+                    .col(ColumnDef::new(FinetuneJobs::Error).string().null())
+                    .col(
+                        ColumnDef::new(FinetuneJobs::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(ColumnDef::new(FinetuneJobs::UpdatedAt).timestamp().null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_finetune_jobs_models")
+                            .from(FinetuneJobs::Table, FinetuneJobs::ModelId)
+                            .to(Models::Table, Models::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(FinetuneJobs::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,120 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20240101_000001_create_models::Models;
+
+#[derive(DeriveIden)]
+enum Collections {
+    Table,
+    Id,
+    Name,
+    ModelId,
+    VectorStoreId,
+    Status,
+    Error,
+    LastSyncedAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum CollectionFiles {
+    Table,
+    Id,
+    CollectionId,
+    Path,
+    Fingerprint,
+    RemoteFileId,
+    DeletedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Collections::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Collections::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Collections::Name).string().not_null())
+                    .col(ColumnDef::new(Collections::ModelId).integer().not_null())
+                    .col(ColumnDef::new(Collections::VectorStoreId).string().null())
+                    .col(ColumnDef::new(Collections::Status).string().not_null())
+                    .col(ColumnDef::new(Collections::Error).string().null())
+                    .col(ColumnDef::new(Collections::LastSyncedAt).timestamp().null())
+                    .col(
+                        ColumnDef::new(Collections::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_collections_models")
+                            .from(Collections::Table, Collections::ModelId)
+                            .to(Models::Table, Models::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(CollectionFiles::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CollectionFiles::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(CollectionFiles::CollectionId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(CollectionFiles::Path).string().not_null())
+                    .col(ColumnDef::new(CollectionFiles::Fingerprint).string().null())
+                    .col(
+                        ColumnDef::new(CollectionFiles::RemoteFileId)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(CollectionFiles::DeletedAt)
+                            .timestamp()
+                            .null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_collection_files_collections")
+                            .from(CollectionFiles::Table, CollectionFiles::CollectionId)
+                            .to(Collections::Table, Collections::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(CollectionFiles::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Collections::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,89 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20240101_000003_create_conversations::Conversations;
+
+#[derive(DeriveIden)]
+enum ResponseSchemas {
+    Table,
+    Id,
+    Name,
+    Schema,
+    CreatedAt,
+    UpdatedAt,
+}
+
+const RESPONSE_SCHEMA_ID_COL_NAME: &str = "response_schema_id";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ResponseSchemas::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ResponseSchemas::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(ResponseSchemas::Name).string().not_null())
+                    .col(ColumnDef::new(ResponseSchemas::Schema).text().not_null())
+                    .col(
+                        ColumnDef::new(ResponseSchemas::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(ResponseSchemas::UpdatedAt)
+                            .timestamp()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        if !manager
+            .has_column("conversations", RESPONSE_SCHEMA_ID_COL_NAME)
+            .await?
+        {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Conversations::Table)
+                        .add_column(
+                            ColumnDef::new(Alias::new(RESPONSE_SCHEMA_ID_COL_NAME))
+                                .integer()
+                                .null(),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager
+            .has_column("conversations", RESPONSE_SCHEMA_ID_COL_NAME)
+            .await?
+        {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Conversations::Table)
+                        .drop_column(Alias::new(RESPONSE_SCHEMA_ID_COL_NAME))
+                        .to_owned(),
+                )
+                .await?;
+        }
+        manager
+            .drop_table(Table::drop().table(ResponseSchemas::Table).to_owned())
+            .await
+    }
+}
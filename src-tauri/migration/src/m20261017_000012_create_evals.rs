@@ -0,0 +1,189 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum EvalSets {
+    Table,
+    Id,
+    Name,
+    JudgeModelId,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum EvalCases {
+    Table,
+    Id,
+    EvalSetId,
+    Prompt,
+    Expected,
+}
+
+#[derive(DeriveIden)]
+enum EvalRuns {
+    Table,
+    Id,
+    EvalSetId,
+    CreatedAt,
+    FinishedAt,
+}
+
+#[derive(DeriveIden)]
+enum EvalResults {
+    Table,
+    Id,
+    EvalRunId,
+    EvalCaseId,
+    ModelId,
+    Reply,
+    Score,
+    LatencyMs,
+    PromptToken,
+    CompletionToken,
+    Cost,
+    Error,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(EvalSets::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(EvalSets::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(EvalSets::Name).string().not_null())
+                    .col(ColumnDef::new(EvalSets::JudgeModelId).integer().null())
+                    .col(
+                        ColumnDef::new(EvalSets::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(EvalCases::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(EvalCases::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(EvalCases::EvalSetId).integer().not_null())
+                    .col(ColumnDef::new(EvalCases::Prompt).text().not_null())
+                    .col(ColumnDef::new(EvalCases::Expected).text().null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_eval_cases_eval_sets")
+                            .from(EvalCases::Table, EvalCases::EvalSetId)
+                            .to(EvalSets::Table, EvalSets::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(EvalRuns::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(EvalRuns::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(EvalRuns::EvalSetId).integer().not_null())
+                    .col(
+                        ColumnDef::new(EvalRuns::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(ColumnDef::new(EvalRuns::FinishedAt).timestamp().null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_eval_runs_eval_sets")
+                            .from(EvalRuns::Table, EvalRuns::EvalSetId)
+                            .to(EvalSets::Table, EvalSets::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(EvalResults::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(EvalResults::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(EvalResults::EvalRunId).integer().not_null())
+                    .col(ColumnDef::new(EvalResults::EvalCaseId).integer().not_null())
+                    .col(ColumnDef::new(EvalResults::ModelId).integer().not_null())
+                    .col(ColumnDef::new(EvalResults::Reply).text().null())
+                    .col(ColumnDef::new(EvalResults::Score).double().null())
+                    .col(
+                        ColumnDef::new(EvalResults::LatencyMs)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(EvalResults::PromptToken).integer().null())
+                    .col(
+                        ColumnDef::new(EvalResults::CompletionToken)
+                            .integer()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(EvalResults::Cost).double().null())
+                    .col(ColumnDef::new(EvalResults::Error).string().null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_eval_results_eval_runs")
+                            .from(EvalResults::Table, EvalResults::EvalRunId)
+                            .to(EvalRuns::Table, EvalRuns::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(EvalResults::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(EvalRuns::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(EvalCases::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(EvalSets::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,115 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum Snapshots {
+    Table,
+    Id,
+    ConversationId,
+    Label,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum SnapshotMessages {
+    Table,
+    SnapshotId,
+    MessageId,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Snapshots::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Snapshots::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(Snapshots::ConversationId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Snapshots::Label).string().not_null())
+                    .col(
+                        ColumnDef::new(Snapshots::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_snapshots_conversations")
+                            .from(Snapshots::Table, Snapshots::ConversationId)
+                            .to(
+                                super::m20240101_000003_create_conversations::Conversations::Table,
+                                super::m20240101_000003_create_conversations::Conversations::Id,
+                            )
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(SnapshotMessages::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SnapshotMessages::SnapshotId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SnapshotMessages::MessageId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(SnapshotMessages::SnapshotId)
+                            .col(SnapshotMessages::MessageId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_snapshot_messages_snapshots")
+                            .from(SnapshotMessages::Table, SnapshotMessages::SnapshotId)
+                            .to(Snapshots::Table, Snapshots::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_snapshot_messages_messages")
+                            .from(SnapshotMessages::Table, SnapshotMessages::MessageId)
+                            .to(
+                                super::m20240101_000004_create_messages::Messages::Table,
+                                super::m20240101_000004_create_messages::Messages::Id,
+                            )
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SnapshotMessages::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Snapshots::Table).to_owned())
+            .await
+    }
+}
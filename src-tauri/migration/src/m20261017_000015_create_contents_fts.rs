@@ -0,0 +1,48 @@
+use sea_orm_migration::prelude::*;
+
+// Full-text index of the text contents, kept in sync with the contents table by triggers.
+// The rowid of an entry is the id of its content.
+const UP: &str = r#"
+CREATE VIRTUAL TABLE IF NOT EXISTS contents_fts USING fts5(
+    data,
+    message_id UNINDEXED,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+INSERT INTO contents_fts (rowid, data, message_id)
+    SELECT id, data, message_id FROM contents WHERE type = 0;
+CREATE TRIGGER IF NOT EXISTS contents_fts_insert AFTER INSERT ON contents
+WHEN new.type = 0 BEGIN
+    INSERT INTO contents_fts (rowid, data, message_id) VALUES (new.id, new.data, new.message_id);
+END;
+CREATE TRIGGER IF NOT EXISTS contents_fts_delete AFTER DELETE ON contents BEGIN
+    DELETE FROM contents_fts WHERE rowid = old.id;
+END;
+CREATE TRIGGER IF NOT EXISTS contents_fts_update AFTER UPDATE ON contents BEGIN
+    DELETE FROM contents_fts WHERE rowid = old.id;
+    INSERT INTO contents_fts (rowid, data, message_id)
+        SELECT new.id, new.data, new.message_id WHERE new.type = 0;
+END;
+"#;
+
+const DOWN: &str = r#"
+DROP TRIGGER IF EXISTS contents_fts_update;
+DROP TRIGGER IF EXISTS contents_fts_delete;
+DROP TRIGGER IF EXISTS contents_fts_insert;
+DROP TABLE IF EXISTS contents_fts;
+"#;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(UP).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(DOWN).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,44 @@
+use super::m20240101_000001_create_models::Models;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const CONTEXT_LENGTH_COL_NAME: &str = "context_length";
+const MAX_OUTPUT_TOKENS_COL_NAME: &str = "max_output_tokens";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite only adds one column per statement
+        for col_name in [CONTEXT_LENGTH_COL_NAME, MAX_OUTPUT_TOKENS_COL_NAME] {
+            if !manager.has_column("models", col_name).await? {
+                manager
+                    .alter_table(
+                        Table::alter()
+                            .table(Models::Table)
+                            .add_column(ColumnDef::new(Alias::new(col_name)).integer().null())
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for col_name in [CONTEXT_LENGTH_COL_NAME, MAX_OUTPUT_TOKENS_COL_NAME] {
+            if manager.has_column("models", col_name).await? {
+                manager
+                    .alter_table(
+                        Table::alter()
+                            .table(Models::Table)
+                            .drop_column(Alias::new(col_name))
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,66 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum ModelPrices {
+    Table,
+    Id,
+    ModelId,
+    InputPrice,
+    OutputPrice,
+    UpdatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ModelPrices::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ModelPrices::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ModelPrices::ModelId)
+                            .integer()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(ModelPrices::InputPrice).double().not_null())
+                    .col(ColumnDef::new(ModelPrices::OutputPrice).double().not_null())
+                    .col(
+                        ColumnDef::new(ModelPrices::UpdatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_model_prices_models")
+                            .from(ModelPrices::Table, ModelPrices::ModelId)
+                            .to(
+                                super::m20240101_000001_create_models::Models::Table,
+                                super::m20240101_000001_create_models::Models::Id,
+                            )
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ModelPrices::Table).to_owned())
+            .await
+    }
+}
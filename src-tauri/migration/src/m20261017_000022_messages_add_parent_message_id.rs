@@ -0,0 +1,65 @@
+use super::m20240101_000004_create_messages::Messages;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const PARENT_MESSAGE_ID_COL_NAME: &str = "parent_message_id";
+
+// Chain the existing messages of each conversation one after another, skipping the
+// system message and the deleted ones
+const BACKFILL: &str = r#"
+UPDATE messages SET parent_message_id = (
+    SELECT MAX(parent.id) FROM messages AS parent
+    WHERE parent.conversation_id = messages.conversation_id
+        AND parent.id < messages.id
+        AND parent.role != 2
+        AND parent.deleted_at IS NULL
+)
+WHERE role != 2 AND parent_message_id IS NULL;
+"#;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if !manager
+            .has_column("messages", PARENT_MESSAGE_ID_COL_NAME)
+            .await?
+        {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Messages::Table)
+                        .add_column(
+                            ColumnDef::new(Alias::new(PARENT_MESSAGE_ID_COL_NAME))
+                                .integer()
+                                .null(),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+            manager
+                .get_connection()
+                .execute_unprepared(BACKFILL)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager
+            .has_column("messages", PARENT_MESSAGE_ID_COL_NAME)
+            .await?
+        {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Messages::Table)
+                        .drop_column(Alias::new(PARENT_MESSAGE_ID_COL_NAME))
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+use super::m20240101_000003_create_conversations::Conversations;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const IS_PINNED_COL_NAME: &str = "is_pinned";
+const IS_ARCHIVED_COL_NAME: &str = "is_archived";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for col_name in [IS_PINNED_COL_NAME, IS_ARCHIVED_COL_NAME] {
+            if !manager.has_column("conversations", col_name).await? {
+                manager
+                    .alter_table(
+                        Table::alter()
+                            .table(Conversations::Table)
+                            .add_column(
+                                ColumnDef::new(Alias::new(col_name))
+                                    .boolean()
+                                    .not_null()
+                                    .default(false),
+                            )
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for col_name in [IS_PINNED_COL_NAME, IS_ARCHIVED_COL_NAME] {
+            if manager.has_column("conversations", col_name).await? {
+                manager
+                    .alter_table(
+                        Table::alter()
+                            .table(Conversations::Table)
+                            .drop_column(Alias::new(col_name))
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum Tags {
+    Table,
+    Id,
+    Name,
+    Color,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum ConversationTags {
+    Table,
+    ConversationId,
+    TagId,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Tags::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Tags::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Tags::Name).string().not_null().unique_key())
+                    .col(ColumnDef::new(Tags::Color).string().null())
+                    .col(
+                        ColumnDef::new(Tags::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(ConversationTags::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ConversationTags::ConversationId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(ConversationTags::TagId).integer().not_null())
+                    .primary_key(
+                        Index::create()
+                            .col(ConversationTags::ConversationId)
+                            .col(ConversationTags::TagId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_conversation_tags_conversations")
+                            .from(ConversationTags::Table, ConversationTags::ConversationId)
+                            .to(
+                                super::m20240101_000003_create_conversations::Conversations::Table,
+                                super::m20240101_000003_create_conversations::Conversations::Id,
+                            )
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_conversation_tags_tags")
+                            .from(ConversationTags::Table, ConversationTags::TagId)
+                            .to(Tags::Table, Tags::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ConversationTags::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Tags::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,38 @@
+use super::m20240101_000001_create_models::Models;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const COL_NAME: &str = "default_options";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if !manager.has_column("models", COL_NAME).await? {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Models::Table)
+                        .add_column(ColumnDef::new(Alias::new(COL_NAME)).text().null())
+                        .to_owned(),
+                )
+                .await?
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.has_column("models", COL_NAME).await? {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Models::Table)
+                        .drop_column(Alias::new(COL_NAME))
+                        .to_owned(),
+                )
+                .await?
+        }
+        Ok(())
+    }
+}
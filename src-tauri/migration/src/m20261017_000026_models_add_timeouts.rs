@@ -0,0 +1,44 @@
+use super::m20240101_000001_create_models::Models;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const CONNECT_TIMEOUT_COL_NAME: &str = "connect_timeout";
+const READ_TIMEOUT_COL_NAME: &str = "read_timeout";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite only adds one column per statement
+        for col_name in [CONNECT_TIMEOUT_COL_NAME, READ_TIMEOUT_COL_NAME] {
+            if !manager.has_column("models", col_name).await? {
+                manager
+                    .alter_table(
+                        Table::alter()
+                            .table(Models::Table)
+                            .add_column(ColumnDef::new(Alias::new(col_name)).integer().null())
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for col_name in [CONNECT_TIMEOUT_COL_NAME, READ_TIMEOUT_COL_NAME] {
+            if manager.has_column("models", col_name).await? {
+                manager
+                    .alter_table(
+                        Table::alter()
+                            .table(Models::Table)
+                            .drop_column(Alias::new(col_name))
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,43 @@
+use super::m20240101_000004_create_messages::Messages;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const COL_NAME: &str = "is_hidden";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if !manager.has_column("messages", COL_NAME).await? {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Messages::Table)
+                        .add_column(
+                            ColumnDef::new(Alias::new(COL_NAME))
+                                .boolean()
+                                .not_null()
+                                .default(false),
+                        )
+                        .to_owned(),
+                )
+                .await?
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.has_column("messages", COL_NAME).await? {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Messages::Table)
+                        .drop_column(Alias::new(COL_NAME))
+                        .to_owned(),
+                )
+                .await?
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,365 @@
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    sync::Mutex,
+};
+
+use axum::{
+    extract::{Path, Request, State},
+    http::{header::AUTHORIZATION, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use entity::entities::{
+    contents::{ContentDTO, ContentType},
+    conversations::{
+        ConversationDetailsDTO, ConversationFilter, Model as Conversation, NewConversationDTO,
+    },
+    messages::{MessageDTO, Roles},
+    settings::{
+        Model as Setting, SETTING_API_SERVER_ENABLED, SETTING_API_SERVER_PORT,
+        SETTING_API_SERVER_TOKEN,
+    },
+};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+use tauri::{AppHandle, Manager};
+use tokio::sync::oneshot;
+
+use crate::{
+    app_lock::AppLock,
+    services::{db::Repository, llm::context::ChatContext},
+};
+
+pub const DEFAULT_API_SERVER_PORT: u16 = 38123;
+const TOKEN_LENGTH: usize = 32;
+
+/// Connection details of the local API server, shown to the user so scripts can use it
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiServerInfo {
+    pub running: bool,
+    pub port: Option<u16>,
+    pub token: Option<String>,
+}
+
+struct RunningServer {
+    port: u16,
+    token: String,
+    shutdown: oneshot::Sender<()>,
+}
+
+/// Optional HTTP server exposing conversations to local scripts and editors.
+/// It only listens on the loopback interface and requires a bearer token.
+pub struct ApiServer {
+    running: Mutex<Option<RunningServer>>,
+}
+
+impl ApiServer {
+    pub fn new() -> Self {
+        ApiServer {
+            running: Mutex::new(None),
+        }
+    }
+
+    pub fn info(&self) -> ApiServerInfo {
+        let running = self
+            .running
+            .lock()
+            .expect("Failed to lock api server mutex");
+        match running.as_ref() {
+            Some(server) => ApiServerInfo {
+                running: true,
+                port: Some(server.port),
+                token: Some(server.token.clone()),
+            },
+            None => ApiServerInfo {
+                running: false,
+                port: None,
+                token: None,
+            },
+        }
+    }
+
+    pub async fn start(&self, app: AppHandle) -> Result<ApiServerInfo, String> {
+        if self.info().running {
+            return Ok(self.info());
+        }
+        let repo = app.state::<Repository>();
+        let port = repo
+            .get_setting(SETTING_API_SERVER_PORT)
+            .await
+            .and_then(|setting| setting.value.parse::<u16>().ok())
+            .unwrap_or(DEFAULT_API_SERVER_PORT);
+        let token = get_or_create_token(&repo).await?;
+        let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+        let listener = tokio::net::TcpListener::bind(addr)
+            .await
+            .map_err(|err| format!("Failed to bind API server to {}: {}", addr, err))?;
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let router = build_router(ApiState {
+            app: app.clone(),
+            token: token.clone(),
+        });
+        tauri::async_runtime::spawn(async move {
+            let result = axum::serve(listener, router)
+                .with_graceful_shutdown(async {
+                    let _ = shutdown_rx.await;
+                })
+                .await;
+            if let Err(err) = result {
+                log::error!("API server stopped with error: {}", err);
+            }
+        });
+        log::info!("API server listening on {}", addr);
+        *self
+            .running
+            .lock()
+            .expect("Failed to lock api server mutex") = Some(RunningServer {
+            port,
+            token,
+            shutdown: shutdown_tx,
+        });
+        Ok(self.info())
+    }
+
+    pub fn stop(&self) {
+        let running = self
+            .running
+            .lock()
+            .expect("Failed to lock api server mutex")
+            .take();
+        if let Some(server) = running {
+            let _ = server.shutdown.send(());
+            log::info!("API server on port {} stopped", server.port);
+        }
+    }
+}
+
+// Start the API server on launch if the user enabled it
+pub fn init_api_server(app: &tauri::App) {
+    let handle = app.handle().clone();
+    tauri::async_runtime::spawn(async move {
+        let repo = handle.state::<Repository>();
+        let enabled = repo
+            .get_setting(SETTING_API_SERVER_ENABLED)
+            .await
+            .map(|setting| setting.value == "true")
+            .unwrap_or(false);
+        if enabled {
+            if let Err(err) = handle.state::<ApiServer>().start(handle.clone()).await {
+                log::error!("Failed to start API server: {}", err);
+            }
+        }
+    });
+}
+
+async fn get_or_create_token(repo: &Repository) -> Result<String, String> {
+    if let Some(setting) = repo.get_setting(SETTING_API_SERVER_TOKEN).await {
+        if !setting.value.is_empty() {
+            return Ok(setting.value);
+        }
+    }
+    let token: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(TOKEN_LENGTH)
+        .map(char::from)
+        .collect();
+    repo.upsert_setting(Setting {
+        key: SETTING_API_SERVER_TOKEN.to_string(),
+        value: token.clone(),
+    })
+    .await?;
+    Ok(token)
+}
+
+/***** HTTP layer START *****/
+
+#[derive(Clone)]
+struct ApiState {
+    app: AppHandle,
+    token: String,
+}
+
+struct ApiFailure(StatusCode, String);
+
+impl ApiFailure {
+    fn internal(message: String) -> Self {
+        ApiFailure(StatusCode::INTERNAL_SERVER_ERROR, message)
+    }
+
+    fn bad_gateway(message: String) -> Self {
+        ApiFailure(StatusCode::BAD_GATEWAY, message)
+    }
+}
+
+// Locked conversations are read-only, for the API as well
+async fn ensure_unlocked(repo: &Repository, conversation_id: i32) -> Result<(), ApiFailure> {
+    let is_locked = repo
+        .is_conversation_locked(conversation_id)
+        .await
+        .map_err(ApiFailure::internal)?;
+    if is_locked {
+        return Err(ApiFailure(
+            StatusCode::CONFLICT,
+            format!("Conversation {} is locked", conversation_id),
+        ));
+    }
+    Ok(())
+}
+
+impl IntoResponse for ApiFailure {
+    fn into_response(self) -> Response {
+        (self.0, Json(serde_json::json!({ "error": self.1 }))).into_response()
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, ApiFailure>;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiModel {
+    id: i32,
+    alias: String,
+    provider: String,
+}
+
+#[derive(Deserialize)]
+struct NewMessageBody {
+    message: String,
+}
+
+fn build_router(state: ApiState) -> Router {
+    Router::new()
+        .route("/v1/models", get(list_models))
+        .route(
+            "/v1/conversations",
+            get(list_conversations).post(create_conversation),
+        )
+        .route(
+            "/v1/conversations/:id/messages",
+            get(list_messages).post(create_message),
+        )
+        .route("/v1/conversations/:id/completions", post(complete))
+        .layer(middleware::from_fn_with_state(state.clone(), authenticate))
+        .with_state(state)
+}
+
+async fn authenticate(State(state): State<ApiState>, request: Request, next: Next) -> Response {
+    let authorized = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        // Compared in constant time, so the token can't be guessed from response times
+        .map(|token| bool::from(token.as_bytes().ct_eq(state.token.as_bytes())))
+        .unwrap_or(false);
+    // Callers without the token aren't told whether the app is locked
+    if !authorized {
+        return ApiFailure(
+            StatusCode::UNAUTHORIZED,
+            "Invalid or missing token".to_string(),
+        )
+        .into_response();
+    }
+    if state.app.state::<AppLock>().is_locked() {
+        return ApiFailure(StatusCode::LOCKED, "App is locked".to_string()).into_response();
+    }
+    next.run(request).await
+}
+
+async fn list_models(State(state): State<ApiState>) -> ApiResult<Vec<ApiModel>> {
+    let repo = state.app.state::<Repository>();
+    let models = repo.list_models().await.map_err(ApiFailure::internal)?;
+    // Never expose model configs, they contain API keys
+    let result = models
+        .into_iter()
+        .map(|model| ApiModel {
+            id: model.id,
+            alias: model.alias,
+            provider: model.provider,
+        })
+        .collect();
+    Ok(Json(result))
+}
+
+async fn list_conversations(
+    State(state): State<ApiState>,
+) -> ApiResult<Vec<ConversationDetailsDTO>> {
+    let repo = state.app.state::<Repository>();
+    let result = repo
+        .list_conversations(ConversationFilter::default())
+        .await
+        .map_err(ApiFailure::internal)?;
+    Ok(Json(result))
+}
+
+async fn create_conversation(
+    State(state): State<ApiState>,
+    Json(body): Json<NewConversationDTO>,
+) -> ApiResult<Conversation> {
+    let repo = state.app.state::<Repository>();
+    let result = repo
+        .create_conversation_with_message(body.model_id, body.message)
+        .await
+        .map_err(ApiFailure::internal)?;
+    Ok(Json(result))
+}
+
+async fn list_messages(
+    State(state): State<ApiState>,
+    Path(conversation_id): Path<i32>,
+) -> ApiResult<Vec<MessageDTO>> {
+    let repo = state.app.state::<Repository>();
+    let result = repo
+        .list_messages(conversation_id)
+        .await
+        .map_err(ApiFailure::internal)?;
+    Ok(Json(result))
+}
+
+async fn create_message(
+    State(state): State<ApiState>,
+    Path(conversation_id): Path<i32>,
+    Json(body): Json<NewMessageBody>,
+) -> ApiResult<MessageDTO> {
+    let repo = state.app.state::<Repository>();
+    ensure_unlocked(&repo, conversation_id).await?;
+    let message = MessageDTO {
+        conversation_id,
+        role: Roles::User.into(),
+        content: vec![ContentDTO {
+            r#type: ContentType::Text,
+            mimetype: None,
+            data: body.message,
+        }],
+        ..Default::default()
+    };
+    let result = repo
+        .create_message(message)
+        .await
+        .map_err(ApiFailure::internal)?;
+    Ok(Json(result))
+}
+
+// Send the conversation to its model and store the reply as a bot message
+async fn complete(
+    State(state): State<ApiState>,
+    Path(conversation_id): Path<i32>,
+) -> ApiResult<MessageDTO> {
+    let repo = state.app.state::<Repository>();
+    ensure_unlocked(&repo, conversation_id).await?;
+    let ctx = ChatContext::load(&repo, conversation_id, None)
+        .await
+        .map_err(ApiFailure::internal)?;
+    let reply = ctx.complete().await.map_err(ApiFailure::bad_gateway)?;
+    let result = repo
+        .create_message(reply.into_message(conversation_id))
+        .await
+        .map_err(ApiFailure::internal)?;
+    Ok(Json(result))
+}
+
+/***** HTTP layer END *****/
@@ -0,0 +1,273 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use entity::entities::settings::{
+    Model as Setting, SETTING_APP_BOOTSTRAPPED, SETTING_APP_LOCK_HASH,
+    SETTING_APP_LOCK_IDLE_MINUTES,
+};
+use serde::Serialize;
+use tauri::{ipc::Invoke, App, AppHandle, Emitter, Manager, Wry};
+
+use crate::{errors::CommandError, services::db::Repository};
+
+pub const EVENT_LOCK_CHANGED: &str = "lock-changed";
+pub const EVENT_LOCK_NOW: &str = "lock-now";
+
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+// Commands the lock screen itself needs
+const ALLOWED_WHILE_LOCKED: &[&str] =
+    &["unlock", "get_lock_status", "list_settings", "get_sys_info"];
+
+// Settings the lock screen itself needs, the only ones list_settings returns while locked,
+// along with the display settings
+const LISTED_WHILE_LOCKED: &[&str] = &[SETTING_APP_BOOTSTRAPPED, SETTING_APP_LOCK_IDLE_MINUTES];
+const DISPLAY_SETTINGS_PREFIX: &str = "display:";
+
+// Slow down guessing
+const FAILED_UNLOCK_DELAY: Duration = Duration::from_secs(1);
+
+/// Whether the app is locked. Conversations can hold sensitive material,
+/// so while locked every command outside the lock screen is rejected.
+pub struct AppLock {
+    locked: AtomicBool,
+    last_activity: Mutex<Instant>,
+    /// Lock after this many seconds without commands, 0 to never auto-lock
+    idle_timeout_secs: AtomicU64,
+}
+
+impl AppLock {
+    pub fn new() -> Self {
+        AppLock {
+            locked: AtomicBool::new(false),
+            last_activity: Mutex::new(Instant::now()),
+            idle_timeout_secs: AtomicU64::new(0),
+        }
+    }
+
+    pub fn touch(&self) {
+        *self
+            .last_activity
+            .lock()
+            .expect("Failed to lock activity mutex") = Instant::now();
+    }
+
+    pub fn set_idle_minutes(&self, minutes: u64) {
+        self.idle_timeout_secs
+            .store(minutes.saturating_mul(60), Ordering::Relaxed);
+        self.touch();
+    }
+
+    fn is_idle(&self) -> bool {
+        let timeout = self.idle_timeout_secs.load(Ordering::Relaxed);
+        let last_activity = *self
+            .last_activity
+            .lock()
+            .expect("Failed to lock activity mutex");
+        timeout > 0 && last_activity.elapsed() >= Duration::from_secs(timeout)
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked.load(Ordering::SeqCst)
+    }
+
+    fn set_locked(&self, app: &AppHandle, locked: bool) {
+        self.locked.store(locked, Ordering::SeqCst);
+        if let Err(err) = app.emit(EVENT_LOCK_CHANGED, locked) {
+            log::error!("Error when sending event: {}", err);
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LockStatus {
+    pub enabled: bool,
+    pub locked: bool,
+}
+
+// Start locked when a passphrase is set, and watch for inactivity
+pub fn init_app_lock(app: &App) -> Result<(), String> {
+    let repo = app.state::<Repository>();
+    let (enabled, idle_minutes) = tauri::async_runtime::block_on(async {
+        (
+            get_hash(&repo).await.is_some(),
+            get_idle_minutes(&repo).await,
+        )
+    });
+    let app_lock = app.state::<AppLock>();
+    app_lock.locked.store(enabled, Ordering::SeqCst);
+    app_lock.set_idle_minutes(idle_minutes);
+
+    let handle = app.handle().clone();
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(IDLE_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let app_lock = handle.state::<AppLock>();
+            if app_lock.is_locked() || !app_lock.is_idle() {
+                continue;
+            }
+            // Auto-lock only protects anything if a passphrase is set
+            let repo = handle.state::<Repository>();
+            if get_hash(&repo).await.is_none() {
+                continue;
+            }
+            log::info!("Locking after inactivity");
+            app_lock.set_locked(&handle, true);
+            if let Err(err) = handle.emit(EVENT_LOCK_NOW, ()) {
+                log::error!("Error when sending event: {}", err);
+            }
+        }
+    });
+    Ok(())
+}
+
+pub async fn get_idle_minutes(repo: &Repository) -> u64 {
+    repo.get_setting(SETTING_APP_LOCK_IDLE_MINUTES)
+        .await
+        .and_then(|setting| setting.value.parse::<u64>().ok())
+        .unwrap_or(0)
+}
+
+// Reject the call if the app is locked, otherwise count it as activity
+// and hand it back to the command handler
+pub fn guard_invoke(invoke: Invoke<Wry>) -> Option<Invoke<Wry>> {
+    let webview = invoke.message.webview();
+    let app_lock = webview.state::<AppLock>();
+    if app_lock.is_locked() {
+        if !ALLOWED_WHILE_LOCKED.contains(&invoke.message.command()) {
+            log::warn!("Rejected {} while locked", invoke.message.command());
+            invoke.resolver.reject(CommandError::LockedError {
+                message: "App is locked".to_string(),
+            });
+            return None;
+        }
+    } else {
+        app_lock.touch();
+    }
+    Some(invoke)
+}
+
+// Settings list_settings returns while locked. Any other one may hold credentials.
+pub fn is_listed_while_locked(key: &str) -> bool {
+    LISTED_WHILE_LOCKED.contains(&key) || key.starts_with(DISPLAY_SETTINGS_PREFIX)
+}
+
+async fn get_hash(repo: &Repository) -> Option<String> {
+    repo.get_setting(SETTING_APP_LOCK_HASH)
+        .await
+        .map(|setting| setting.value)
+        .filter(|hash| !hash.is_empty())
+}
+
+fn hash_passphrase(passphrase: &str) -> Result<String, String> {
+    let salt = SaltString::generate(&mut rand::rngs::OsRng);
+    Argon2::default()
+        .hash_password(passphrase.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|err| format!("Failed to hash passphrase: {}", err))
+}
+
+fn verify_passphrase(passphrase: &str, hash: &str) -> bool {
+    PasswordHash::new(hash)
+        .map(|parsed| {
+            Argon2::default()
+                .verify_password(passphrase.as_bytes(), &parsed)
+                .is_ok()
+        })
+        .unwrap_or(false)
+}
+
+pub async fn get_status(app: &AppHandle) -> LockStatus {
+    let repo = app.state::<Repository>();
+    LockStatus {
+        enabled: get_hash(&repo).await.is_some(),
+        locked: app.state::<AppLock>().is_locked(),
+    }
+}
+
+// Set, change or remove (with None) the passphrase. The current one is required to change it.
+pub async fn set_passphrase(
+    app: &AppHandle,
+    current: Option<String>,
+    passphrase: Option<String>,
+) -> Result<LockStatus, String> {
+    let repo = app.state::<Repository>();
+    if let Some(hash) = get_hash(&repo).await {
+        if !verify_passphrase(&current.unwrap_or_default(), &hash) {
+            tokio::time::sleep(FAILED_UNLOCK_DELAY).await;
+            return Err("Current passphrase is incorrect".to_string());
+        }
+    }
+    let value = match passphrase {
+        Some(passphrase) if !passphrase.is_empty() => hash_passphrase(&passphrase)?,
+        Some(_) => return Err("Passphrase can't be empty".to_string()),
+        None => String::new(),
+    };
+    repo.upsert_setting(Setting {
+        key: SETTING_APP_LOCK_HASH.to_string(),
+        value,
+    })
+    .await?;
+    Ok(get_status(app).await)
+}
+
+pub async fn lock(app: &AppHandle) -> Result<(), String> {
+    let repo = app.state::<Repository>();
+    if get_hash(&repo).await.is_none() {
+        return Err("Set a passphrase before locking the app".to_string());
+    }
+    app.state::<AppLock>().set_locked(app, true);
+    Ok(())
+}
+
+pub async fn unlock(app: &AppHandle, passphrase: String) -> Result<(), String> {
+    let repo = app.state::<Repository>();
+    let unlocked = match get_hash(&repo).await {
+        Some(hash) => verify_passphrase(&passphrase, &hash),
+        None => true,
+    };
+    if !unlocked {
+        tokio::time::sleep(FAILED_UNLOCK_DELAY).await;
+        return Err("Incorrect passphrase".to_string());
+    }
+    let app_lock = app.state::<AppLock>();
+    app_lock.touch();
+    app_lock.set_locked(app, false);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_passphrase_hash() {
+        let hash = hash_passphrase("correct horse").unwrap();
+        assert!(verify_passphrase("correct horse", &hash));
+        assert!(!verify_passphrase("battery staple", &hash));
+        assert!(!verify_passphrase("correct horse", "not a hash"));
+    }
+
+    #[test]
+    fn test_is_listed_while_locked() {
+        assert!(is_listed_while_locked("display:language"));
+        assert!(is_listed_while_locked("display:darkmode"));
+        assert!(is_listed_while_locked(SETTING_APP_LOCK_IDLE_MINUTES));
+        assert!(!is_listed_while_locked(SETTING_APP_LOCK_HASH));
+        assert!(!is_listed_while_locked("api_server:token"));
+        assert!(!is_listed_while_locked("github:token"));
+        assert!(!is_listed_while_locked("network:proxy:OpenAI"));
+        assert!(!is_listed_while_locked("crash_reports:endpoint"));
+    }
+}
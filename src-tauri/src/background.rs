@@ -0,0 +1,95 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use entity::entities::settings::SETTING_CLOSE_TO_TRAY;
+use serde::Serialize;
+use tauri::{App, AppHandle, Manager, Window, WindowEvent};
+use tauri_plugin_autostart::ManagerExt;
+
+use crate::services::db::Repository;
+
+/// Argument passed by the login item, so the app starts without showing its window
+pub const ARG_BACKGROUND: &str = "--background";
+
+/// Whether closing the main window keeps the app running in the tray.
+/// Cached here because window events are handled synchronously.
+pub struct BackgroundMode {
+    close_to_tray: AtomicBool,
+}
+
+impl BackgroundMode {
+    pub fn new() -> Self {
+        BackgroundMode {
+            close_to_tray: AtomicBool::new(false),
+        }
+    }
+
+    pub fn close_to_tray(&self) -> bool {
+        self.close_to_tray.load(Ordering::Relaxed)
+    }
+
+    pub fn set_close_to_tray(&self, enabled: bool) {
+        self.close_to_tray.store(enabled, Ordering::Relaxed);
+    }
+}
+
+/// Background behaviours as shown in the settings page
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackgroundSettings {
+    pub autostart: bool,
+    pub close_to_tray: bool,
+}
+
+// Load the close-to-tray flag and stay hidden when started at login
+pub fn init_background_mode(app: &App) -> Result<(), String> {
+    let repo = app.state::<Repository>();
+    let close_to_tray = tauri::async_runtime::block_on(async {
+        repo.get_setting(SETTING_CLOSE_TO_TRAY)
+            .await
+            .map(|setting| setting.value == "true")
+            .unwrap_or(false)
+    });
+    app.state::<BackgroundMode>()
+        .set_close_to_tray(close_to_tray);
+    if std::env::args().any(|arg| arg == ARG_BACKGROUND) {
+        log::info!("Started in background mode");
+        if let Some(window) = app.get_webview_window("main") {
+            let _ = window.hide();
+        }
+    }
+    Ok(())
+}
+
+// Hide the main window instead of quitting when close to tray is on
+pub fn handle_window_event(window: &Window, event: &WindowEvent) {
+    if let WindowEvent::CloseRequested { api, .. } = event {
+        if window.label() == "main" && window.state::<BackgroundMode>().close_to_tray() {
+            api.prevent_close();
+            if let Err(err) = window.hide() {
+                log::error!("Failed to hide window: {}", err);
+            }
+        }
+    }
+}
+
+pub fn get_settings(app: &AppHandle) -> Result<BackgroundSettings, String> {
+    let autostart = app
+        .autolaunch()
+        .is_enabled()
+        .map_err(|err| format!("Failed to read autostart state: {}", err))?;
+    Ok(BackgroundSettings {
+        autostart,
+        close_to_tray: app.state::<BackgroundMode>().close_to_tray(),
+    })
+}
+
+// Register or remove the login item. The OS keeps this state, so it isn't stored in settings.
+pub fn set_autostart(app: &AppHandle, enabled: bool) -> Result<(), String> {
+    let autolaunch = app.autolaunch();
+    let result = if enabled {
+        autolaunch.enable()
+    } else {
+        autolaunch.disable()
+    };
+    result.map_err(|err| format!("Failed to update autostart: {}", err))
+}
@@ -0,0 +1,169 @@
+use std::io::Write;
+
+use tokio_stream::StreamExt;
+
+use crate::{
+    init::open_repository,
+    services::{
+        db::Repository,
+        llm::{chat::BotReply, context::ChatContext},
+    },
+    utils::with_stream,
+    workspaces,
+};
+
+const APP_IDENTIFIER: &str = "kassapp.com";
+const USAGE: &str = "Usage: kaas --ask <prompt> [--model <id or alias>]";
+
+/// Arguments of a headless run, which answers one prompt in the terminal without opening a window
+#[derive(Debug, PartialEq)]
+pub struct CliArgs {
+    pub prompt: String,
+    pub model: Option<String>,
+}
+
+// Parse the command line. Returns None when the app should start normally.
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Option<Result<CliArgs, String>> {
+    let args: Vec<String> = args.into_iter().collect();
+    if !args.iter().any(|arg| arg == "--ask") {
+        return None;
+    }
+    let mut prompt = None;
+    let mut model = None;
+    let mut iter = args.into_iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--ask" => prompt = iter.next(),
+            "--model" => match iter.next() {
+                Some(value) => model = Some(value),
+                None => return Some(Err(format!("Missing value for --model\n{}", USAGE))),
+            },
+            _ => return Some(Err(format!("Unknown argument: {}\n{}", arg, USAGE))),
+        }
+    }
+    let result = prompt
+        .filter(|prompt| !prompt.trim().is_empty())
+        .map(|prompt| CliArgs { prompt, model })
+        .ok_or(format!("Missing prompt for --ask\n{}", USAGE));
+    Some(result)
+}
+
+// Answer the prompt on stdout and return the process exit code.
+// Note: release builds on Windows use the GUI subsystem, so they have no console to print to.
+pub fn run(args: CliArgs) -> i32 {
+    let result = tauri::async_runtime::block_on(async move {
+        let repo = open_cli_repository()?;
+        ask(&repo, args).await
+    });
+    match result {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("{}", err);
+            1
+        }
+    }
+}
+
+// Open the same database the app uses, without a Tauri app to resolve its location
+fn open_cli_repository() -> Result<Repository, String> {
+    let app_data_dir = dirs::data_dir()
+        .ok_or("Failed to locate the data directory".to_string())?
+        .join(APP_IDENTIFIER);
+    std::fs::create_dir_all(&app_data_dir)
+        .map_err(|err| format!("Failed to create app data directory: {}", err))?;
+    let workspace_dir = workspaces::active_workspace_dir(&app_data_dir);
+    let workspace_dir_str = workspace_dir
+        .to_str()
+        .ok_or("Workspace path is not a valid string!".to_string())?;
+    open_repository(workspace_dir_str)
+}
+
+async fn ask(repo: &Repository, args: CliArgs) -> Result<(), String> {
+    let model = repo.resolve_model(args.model).await?;
+    let conversation = repo
+        .create_conversation_with_message(model.id, args.prompt)
+        .await?;
+    let ctx = ChatContext::load(repo, conversation.id, None).await?;
+    let client = ctx.client()?;
+    let global_settings = ctx.global_settings();
+    let mut privacy_filter = ctx.privacy_filter;
+    let mut stream = client
+        .chat_stream(
+            ctx.messages,
+            with_stream(ctx.options, true),
+            global_settings,
+        )
+        .await?;
+    let mut reply = BotReply::default();
+    let mut stdout = std::io::stdout();
+    while let Some(result) = stream.next().await {
+        let mut chunk = result.map_err(|err| format!("Error during stream: {}", err))?;
+        if let Some(filter) = privacy_filter.as_mut() {
+            chunk.message = filter.restore_chunk(&chunk.message);
+        }
+        print!("{}", chunk.message);
+        let _ = stdout.flush();
+        merge_chunk(&mut reply, chunk);
+    }
+    if let Some(filter) = privacy_filter.as_mut() {
+        let rest = filter.flush();
+        print!("{}", rest);
+        reply.message.push_str(&rest);
+    }
+    println!();
+    repo.create_message(reply.into_message(conversation.id))
+        .await?;
+    Ok(())
+}
+
+// Accumulate a streamed chunk into the full reply. Usage is only reported in some chunks.
+fn merge_chunk(reply: &mut BotReply, chunk: BotReply) {
+    reply.message.push_str(&chunk.message);
+    if let Some(reasoning) = chunk.reasoning {
+        reply
+            .reasoning
+            .get_or_insert_with(String::new)
+            .push_str(&reasoning);
+    }
+    reply.prompt_token = chunk.prompt_token.or(reply.prompt_token);
+    reply.completion_token = chunk.completion_token.or(reply.completion_token);
+    reply.reasoning_token = chunk.reasoning_token.or(reply.reasoning_token);
+    reply.total_token = chunk.total_token.or(reply.total_token);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(None, parse_args(args(&[])));
+        assert_eq!(
+            Some(Ok(CliArgs {
+                prompt: "Hello".to_string(),
+                model: Some("gpt-4o".to_string()),
+            })),
+            parse_args(args(&["--ask", "Hello", "--model", "gpt-4o"]))
+        );
+        assert_eq!(
+            Some(Ok(CliArgs {
+                prompt: "Hello".to_string(),
+                model: None,
+            })),
+            parse_args(args(&["--ask", "Hello"]))
+        );
+        assert!(matches!(parse_args(args(&["--ask"])), Some(Err(_))));
+        assert!(matches!(
+            parse_args(args(&["--ask", "Hello", "--model"])),
+            Some(Err(_))
+        ));
+        assert!(matches!(
+            parse_args(args(&["--ask", "Hello", "--verbose"])),
+            Some(Err(_))
+        ));
+    }
+}
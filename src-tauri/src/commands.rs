@@ -1,46 +1,122 @@
-use std::time::Instant;
+use std::{path::Path, time::Instant};
 
 use entity::entities::{
-    contents::{ContentType, Model as Content},
+    batch_items::Model as BatchItem,
+    batches::Model as Batch,
+    collection_files::Model as CollectionFile,
+    collections::Model as Collection,
+    contents::ContentDTO,
     conversations::{
-        ConversationDTO, ConversationDetailsDTO, GenericOptions, Model as Conversation,
-        NewConversationDTO, UpdateConversationDTO, DEFAULT_CONTEXT_LENGTH, DEFAULT_MAX_TOKENS,
+        ConversationDTO, ConversationDetailsDTO, ConversationFilter, GenericOptions,
+        Model as Conversation, NewConversationDTO, UpdateConversationDTO,
     },
-    messages::MessageDTO,
+    eval_cases::{Model as EvalCase, NewEvalCase},
+    eval_runs::Model as EvalRun,
+    eval_sets::Model as EvalSet,
+    finetune_jobs::Model as FinetuneJob,
+    message_feedback::{Model as MessageFeedback, ModelFeedbackStats, Rating},
+    messages::{MessageDTO, Roles},
+    model_prices::Model as ModelPrice,
     models::{GenericConfig, Model, NewModel},
     prompts::{Model as Prompt, NewPrompt},
+    response_schemas::{Model as ResponseSchema, NewResponseSchema},
     settings::{
-        Model as Setting, ProxySetting, SETTING_MODELS_CONTEXT_LENGTH, SETTING_MODELS_MAX_TOKENS,
-        SETTING_NETWORK_PROXY,
+        Model as Setting, ProxySetting, TimeoutSetting, SETTING_API_SERVER_ENABLED,
+        SETTING_APP_LOCK_HASH, SETTING_APP_LOCK_IDLE_MINUTES, SETTING_CLOSE_TO_TRAY,
+        SETTING_INSIGHTS_ENABLED,
     },
+    snapshots::Model as Snapshot,
+    stats::{DailyActivity, DerivedDataReport},
+    tags::{Model as Tag, NewTag},
 };
 
 use serde_json::json;
 use sysinfo::System;
 
-use tauri::{Emitter, Listener, State};
+use tauri::{Emitter, Listener, Manager, State};
+use tauri_plugin_clipboard_manager::ClipboardExt;
 use tokio_stream::StreamExt;
 
 use crate::{
-    errors::CommandError::{self, ApiError, DbError},
-    log_utils::{debug, error, info, trace},
+    api_server::{ApiServer, ApiServerInfo},
+    app_lock::{self, AppLock, LockStatus},
+    background::{self, BackgroundMode, BackgroundSettings},
+    crash::{self, CrashReport},
+    diagnostics::{self, DiagnosticsReport},
+    errors::CommandError::{
+        self, ApiError, ConversationLockedError, CostConfirmationRequired, DbError,
+        DuplicateRequest, InvalidOption, LockedError, StateError, UnknownError,
+    },
+    events::{
+        self, ConversationRead, ConversationTitled, EVENT_CONVERSATION_READ,
+        EVENT_CONVERSATION_TITLED, EVENT_MESSAGE_CREATED,
+    },
+    insights::{self, ActivityRange, Insights, LocalInsights},
+    log_utils::{self, debug, error, info, trace},
+    notifications,
     services::{
+        assistants, batch,
+        bootstrap::{self, BootstrapSummary},
+        branches::MessageBranch,
+        collections, cost_guard,
+        csv_import::{self, CsvImportMode},
         db::Repository,
+        evals::{self, EvalResults},
+        finetune::{self, FinetuneExport, FinetuneFilter},
+        finetune_jobs,
+        generation::{self, GenerationManager, StreamMeter, StreamMetrics},
+        gist,
         llm::{
-            chat::{BotReply, GlobalSettings}, client::LLMClient, models::RemoteModel
+            capabilities::ModelCapabilities,
+            chat::{BotReply, GlobalSettings},
+            client::LLMClient,
+            context::{
+                chat_with_continuations, continuation_messages, get_proxy_setting,
+                get_timeout_setting, ChatContext,
+            },
+            embeddings::{self, EmbeddingComparison},
+            fallback::{self, ChatTarget, FallbackModel, ModelFallback},
+            limits::ModelLimits,
+            memory,
+            models::RemoteModel,
+            moderation::{self, ModerationFlagged, EVENT_MODERATION_FLAGGED},
+            options::{self, ConversationOptions},
+            pricing,
+            proxy::{self, ProxyTest},
+            resume::{self, StreamRecovery, MAX_STREAM_RESUMES},
+            retry::{self, RequestRetry},
+            schema,
+            tasks::{self, RefinedPrompt, SummaryStyle},
+            tokenizer::{self, TokenCount},
         },
+        markdown,
+        privacy::PrivacyFilter,
+        provider_files::{self, ProviderFile},
+        response_cache,
+        search::{self, ConversationMessageMatch, MessageMatch, PaletteItem},
+        usage::UsageStats,
     },
-    utils::is_stream_enabled
+    tray,
+    updater::{self, UpdateInfo},
+    utils::{is_stream_enabled, open_in_file_manager},
+    workspaces::{self, Workspace, WorkspaceList},
 };
 
 type CommandResult<T = ()> = Result<T, CommandError>;
 
+const DEFAULT_PALETTE_LIMIT: usize = 20;
+const DEFAULT_SEARCH_LIMIT: u64 = 50;
+const DEFAULT_MESSAGE_PAGE_SIZE: u64 = 50;
+// Emojis with modifiers or joiners span several chars
+const MAX_EMOJI_CHARS: usize = 8;
+
 #[tauri::command]
 pub async fn create_model(
     new_model: NewModel,
     repo: State<'_, Repository>,
 ) -> CommandResult<Model> {
     log::debug!("Creating model: {:?}", new_model);
+    check_default_options(&new_model.default_options)?;
     let result = repo
         .create_model(new_model)
         .await
@@ -59,6 +135,7 @@ pub async fn list_models(repo: State<'_, Repository>) -> CommandResult<Vec<Model
 
 #[tauri::command]
 pub async fn update_model(model: Model, repo: State<'_, Repository>) -> CommandResult<Model> {
+    check_default_options(&model.default_options)?;
     let result = repo
         .update_model(model)
         .await
@@ -66,9 +143,37 @@ pub async fn update_model(model: Model, repo: State<'_, Repository>) -> CommandR
     Ok(result)
 }
 
+/// Delete a model. The conversations using it are moved to the model to reassign them
+/// to, and the model isn't deleted while they have none.
 #[tauri::command]
-pub async fn delete_model(model_id: i32, repo: State<'_, Repository>) -> CommandResult<Model> {
+pub async fn delete_model(
+    model_id: i32,
+    reassign_to: Option<i32>,
+    repo: State<'_, Repository>,
+) -> CommandResult<Model> {
     let now = Instant::now();
+    let conversation_ids = repo
+        .list_model_conversation_ids(model_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    if !conversation_ids.is_empty() {
+        let target_id = match reassign_to {
+            Some(target_id) if target_id != model_id => target_id,
+            _ => {
+                return Err(StateError {
+                    message: format!(
+                        "The model is used by {} conversations, choose another model for them",
+                        conversation_ids.len()
+                    ),
+                })
+            }
+        };
+        for conversation_id in conversation_ids {
+            repo.update_conversation_model(conversation_id, target_id)
+                .await
+                .map_err(|message| DbError { message })?;
+        }
+    }
     let result = repo
         .delete_model(model_id)
         .await
@@ -78,30 +183,34 @@ pub async fn delete_model(model_id: i32, repo: State<'_, Repository>) -> Command
     Ok(result)
 }
 
+/// Get what a model can do, such as its limits and the temperatures it accepts
+#[tauri::command]
+pub async fn get_model_capabilities(
+    model_id: i32,
+    repo: State<'_, Repository>,
+) -> CommandResult<ModelCapabilities> {
+    let model = repo
+        .get_model(model_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(ModelCapabilities::of(&model))
+}
+
 #[tauri::command]
 pub async fn list_remote_models(
     config: GenericConfig,
     repo: State<'_, Repository>,
 ) -> CommandResult<Vec<RemoteModel>> {
     let now = Instant::now();
-    let proxy_setting = repo
-        .get_setting(SETTING_NETWORK_PROXY)
-        .await
-        .map(|setting| {
-            if let Ok(p_setting) = serde_json::from_str::<ProxySetting>(&setting.value) {
-                Some(p_setting)
-            } else {
-                None
-            }
-        })
-        .unwrap_or(None);
-    let init_client_result = LLMClient::new(config, proxy_setting);
+    let proxy_setting = get_proxy_setting(&repo, &config.provider).await;
+    let timeouts = get_timeout_setting(&repo).await;
+    let init_client_result = LLMClient::new(config, proxy_setting, timeouts);
     match init_client_result {
         Ok(client) => {
             let result = client
                 .models()
                 .await
-                .map_err(|message| ApiError { message })?;
+                .map_err(|err| client.parse_error(err))?;
             let elapsed = now.elapsed();
             log::info!("[Timer][commands::list_remote_models]: {:.2?}", elapsed);
             Ok(result)
@@ -111,11 +220,21 @@ pub async fn list_remote_models(
 }
 
 #[tauri::command]
-pub async fn list_settings(repo: State<'_, Repository>) -> CommandResult<Vec<Setting>> {
+pub async fn list_settings(
+    repo: State<'_, Repository>,
+    app_lock: State<'_, AppLock>,
+) -> CommandResult<Vec<Setting>> {
+    let locked = app_lock.is_locked();
     let result = repo
         .list_settings()
         .await
-        .map_err(|message| DbError { message })?;
+        .map_err(|message| DbError { message })?
+        .into_iter()
+        // The lock screen can read settings, never hand it the passphrase hash
+        .filter(|setting| setting.key != SETTING_APP_LOCK_HASH)
+        // nor other settings than its own, which may hold credentials, until unlocked
+        .filter(|setting| !locked || app_lock::is_listed_while_locked(&setting.key))
+        .collect();
     Ok(result)
 }
 
@@ -123,11 +242,53 @@ pub async fn list_settings(repo: State<'_, Repository>) -> CommandResult<Vec<Set
 pub async fn upsert_setting(
     setting: Setting,
     repo: State<'_, Repository>,
+    background_mode: State<'_, BackgroundMode>,
+    app_lock: State<'_, AppLock>,
+    insights: State<'_, Insights>,
 ) -> CommandResult<Setting> {
+    // The passphrase can only be changed through set_lock_passphrase
+    if setting.key == SETTING_APP_LOCK_HASH {
+        return Err(UnknownError {
+            message: format!("Setting {} is read-only", setting.key),
+        });
+    }
     let result = repo
         .upsert_setting(setting)
         .await
         .map_err(|message| DbError { message })?;
+    // Keep the cached flag in sync, window events can't read the database
+    if result.key == SETTING_CLOSE_TO_TRAY {
+        background_mode.set_close_to_tray(result.value == "true");
+    }
+    if result.key == SETTING_APP_LOCK_IDLE_MINUTES {
+        app_lock.set_idle_minutes(result.value.parse::<u64>().unwrap_or(0));
+    }
+    if result.key == SETTING_INSIGHTS_ENABLED {
+        insights.set_enabled(result.value == "true");
+    }
+    Ok(result)
+}
+
+/// Get the default options of the models of a provider, if any
+#[tauri::command]
+pub async fn get_provider_options(
+    provider: String,
+    repo: State<'_, Repository>,
+) -> CommandResult<Option<serde_json::Value>> {
+    let result = options::get_provider_options(&repo, &provider).await;
+    Ok(result)
+}
+
+/// Set the default options of the models of a provider, used beneath the options of conversations
+#[tauri::command]
+pub async fn set_provider_options(
+    provider: String,
+    options: serde_json::Value,
+    repo: State<'_, Repository>,
+) -> CommandResult<Setting> {
+    let result = options::set_provider_options(&repo, &provider, options)
+        .await
+        .map_err(|message| DbError { message })?;
     Ok(result)
 }
 
@@ -136,19 +297,8 @@ pub async fn create_conversation(
     new_conversation: NewConversationDTO,
     repo: State<'_, Repository>,
 ) -> CommandResult<Conversation> {
-    // Assemble conversation & message models
-    let conversation = Conversation {
-        model_id: Some(new_conversation.model_id),
-        subject: new_conversation.message.clone(),
-        ..Default::default()
-    };
-    let content = Content {
-        r#type: ContentType::Text,
-        data: new_conversation.message,
-        ..Default::default()
-    };
-    let (conversation, _, _) = repo
-        .create_conversation_with_content(conversation, content)
+    let conversation = repo
+        .create_conversation_with_message(new_conversation.model_id, new_conversation.message)
         .await
         .map_err(|message| DbError { message })?;
 
@@ -170,11 +320,12 @@ pub async fn create_blank_conversation(
 
 #[tauri::command]
 pub async fn list_conversations(
+    filter: Option<ConversationFilter>,
     repo: State<'_, Repository>,
 ) -> CommandResult<Vec<ConversationDetailsDTO>> {
     let now = Instant::now();
     let result = repo
-        .list_conversations()
+        .list_conversations(filter.unwrap_or_default())
         .await
         .map_err(|message| DbError { message })?;
     let elapsed = now.elapsed();
@@ -198,337 +349,1964 @@ pub async fn delete_conversation(
 }
 
 #[tauri::command]
-pub async fn get_options(
-    conversation_id: i32,
+pub async fn list_trashed_conversations(
     repo: State<'_, Repository>,
-) -> CommandResult<GenericOptions> {
+) -> CommandResult<Vec<ConversationDetailsDTO>> {
     let now = Instant::now();
     let result = repo
-        .get_conversation_options(conversation_id)
+        .list_trashed_conversations()
         .await
         .map_err(|message| DbError { message })?;
     let elapsed = now.elapsed();
-    log::info!("[Timer][commands::get_options]: {:.2?}", elapsed);
+    log::info!(
+        "[Timer][commands::list_trashed_conversations]: {:.2?}",
+        elapsed
+    );
     Ok(result)
 }
 
 #[tauri::command]
-pub async fn update_options(
+pub async fn restore_conversation(
     conversation_id: i32,
-    options: String,
     repo: State<'_, Repository>,
-) -> CommandResult<GenericOptions> {
-    log::info!("[commands::update_options]: {}", options);
+) -> CommandResult<ConversationDetailsDTO> {
     let now = Instant::now();
     let result = repo
-        .update_conversation_options(conversation_id, options)
+        .restore_conversation(conversation_id)
         .await
         .map_err(|message| DbError { message })?;
     let elapsed = now.elapsed();
-    log::info!("[Timer][commands::update_options]: {:.2?}", elapsed);
+    log::info!("[Timer][commands::restore_conversation]: {:.2?}", elapsed);
     Ok(result)
 }
 
+/// Start a new conversation from the messages of another one up to the given message
 #[tauri::command]
-pub async fn update_subject(
+pub async fn fork_conversation(
     conversation_id: i32,
-    subject: String,
+    message_id: i32,
     repo: State<'_, Repository>,
-) -> CommandResult<String> {
+) -> CommandResult<Conversation> {
     let now = Instant::now();
     let result = repo
-        .update_conversation_subject(conversation_id, subject)
+        .fork_conversation(conversation_id, message_id)
         .await
         .map_err(|message| DbError { message })?;
     let elapsed = now.elapsed();
-    log::info!("[Timer][commands::update_subject]: {:.2?}", elapsed);
+    log::info!("[Timer][commands::fork_conversation]: {:.2?}", elapsed);
     Ok(result)
 }
 
+/// Copy a conversation with all its messages, e.g. to run them against another model
 #[tauri::command]
-pub async fn update_conversation_model(
+pub async fn duplicate_conversation(
     conversation_id: i32,
-    model_id: i32,
     repo: State<'_, Repository>,
-) -> CommandResult<ConversationDetailsDTO> {
+) -> CommandResult<Conversation> {
     let now = Instant::now();
     let result = repo
-        .update_conversation_model(conversation_id, model_id)
+        .duplicate_conversation(conversation_id)
         .await
         .map_err(|message| DbError { message })?;
     let elapsed = now.elapsed();
-    log::info!(
-        "[Timer][commands::update_conversation_model]: {:.2?}",
-        elapsed
-    );
+    log::info!("[Timer][commands::duplicate_conversation]: {:.2?}", elapsed);
     Ok(result)
 }
 
+/// Permanently delete the conversations in the trash, returning how many were deleted
 #[tauri::command]
-pub async fn update_conversation(
-    conversation: UpdateConversationDTO,
-    repo: State<'_, Repository>,
-) -> CommandResult<ConversationDetailsDTO> {
+pub async fn purge_trash(repo: State<'_, Repository>) -> CommandResult<u64> {
     let now = Instant::now();
     let result = repo
-        .update_conversation(conversation)
+        .purge_trash()
         .await
         .map_err(|message| DbError { message })?;
     let elapsed = now.elapsed();
-    log::info!("[Timer][commands::update_conversation]: {:.2?}", elapsed);
+    log::info!("[Timer][commands::purge_trash]: {:.2?}", elapsed);
     Ok(result)
 }
 
 #[tauri::command]
-pub async fn create_message(
-    message: MessageDTO,
+pub async fn set_conversation_locked(
+    conversation_id: i32,
+    is_locked: bool,
     repo: State<'_, Repository>,
-) -> CommandResult<MessageDTO> {
-    let now = Instant::now();
-    log::info!("create_message: message = {:?}", message);
+) -> CommandResult<ConversationDetailsDTO> {
     let result = repo
-        .create_message(message)
+        .update_conversation_locked(conversation_id, is_locked)
         .await
         .map_err(|message| DbError { message })?;
-    log::info!("create_message: result = {:?}", result);
-    let elapsed = now.elapsed();
-    log::info!("[Timer][commands::create_message]: {:.2?}", elapsed);
     Ok(result)
 }
 
 #[tauri::command]
-pub async fn list_messages(
+pub async fn pin_conversation(
     conversation_id: i32,
+    is_pinned: bool,
     repo: State<'_, Repository>,
-) -> CommandResult<Vec<MessageDTO>> {
-    let now = Instant::now();
+) -> CommandResult<ConversationDetailsDTO> {
     let result = repo
-        .list_messages(conversation_id)
+        .update_conversation_pinned(conversation_id, is_pinned)
         .await
         .map_err(|message| DbError { message })?;
-    let elapsed = now.elapsed();
-    log::info!("[Timer][commands::list_messages]: {:.2?}", elapsed);
     Ok(result)
 }
 
 #[tauri::command]
-pub async fn get_system_message(
+pub async fn archive_conversation(
     conversation_id: i32,
+    is_archived: bool,
     repo: State<'_, Repository>,
-) -> CommandResult<Option<MessageDTO>> {
-    let now = Instant::now();
-    let result: Option<MessageDTO> = repo
-        .get_system_message(conversation_id)
+) -> CommandResult<ConversationDetailsDTO> {
+    let result = repo
+        .update_conversation_archived(conversation_id, is_archived)
         .await
-        .map_err(|message| DbError { message })?
-        .and_then(|inner| Some(MessageDTO::from(inner)));
-    let elapsed = now.elapsed();
-    log::info!("[Timer][commands::get_system_message]: {:.2?}", elapsed);
+        .map_err(|message| DbError { message })?;
     Ok(result)
 }
 
 #[tauri::command]
-pub async fn update_message(
-    message: MessageDTO,
-    repo: State<'_, Repository>,
-) -> CommandResult<MessageDTO> {
-    let now = Instant::now();
+pub async fn create_tag(new_tag: NewTag, repo: State<'_, Repository>) -> CommandResult<Tag> {
+    let name = new_tag.name.trim().to_string();
+    if name.is_empty() {
+        return Err(StateError {
+            message: "Tag name can't be empty".to_string(),
+        });
+    }
     let result = repo
-        .update_message(message)
+        .create_tag(NewTag { name, ..new_tag })
         .await
         .map_err(|message| DbError { message })?;
-    let elapsed = now.elapsed();
-    log::info!("[Timer][commands::update_message]: {:.2?}", elapsed);
     Ok(result)
 }
 
 #[tauri::command]
-pub async fn hard_delete_messages(
-    conversation_id: i32,
-    repo: State<'_, Repository>,
-) -> CommandResult<()> {
-    let now = Instant::now();
-    repo.hard_delete_messages(conversation_id)
+pub async fn list_tags(repo: State<'_, Repository>) -> CommandResult<Vec<Tag>> {
+    let result = repo
+        .list_tags()
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn delete_tag(tag_id: i32, repo: State<'_, Repository>) -> CommandResult<()> {
+    repo.delete_tag(tag_id)
         .await
         .map_err(|message| DbError { message })?;
-    // TODO: delete all media files associated with these messages
-    let elapsed = now.elapsed();
-    log::info!("[Timer][commands::delete_messages]: {:.2?}", elapsed);
     Ok(())
 }
 
 #[tauri::command]
-pub async fn hard_delete_message(
-    message: MessageDTO,
+pub async fn list_conversation_tags(
+    conversation_id: i32,
     repo: State<'_, Repository>,
-) -> CommandResult<MessageDTO> {
-    let now = Instant::now();
+) -> CommandResult<Vec<Tag>> {
     let result = repo
-        .hard_delete_message(message)
+        .list_conversation_tags(conversation_id)
         .await
         .map_err(|message| DbError { message })?;
-    // TODO: delete all media files associated with this messge
-    let elapsed = now.elapsed();
-    log::info!("[Timer][commands::hard_delete_message]: {:.2?}", elapsed);
     Ok(result)
 }
 
 #[tauri::command]
-pub async fn call_bot(
+pub async fn add_conversation_tag(
     conversation_id: i32,
-    tag: String,
-    before_message_id: Option<i32>,
-    window: tauri::Window,
+    tag_id: i32,
     repo: State<'_, Repository>,
-) -> CommandResult<()> {
-    let now = Instant::now();
-    // Retrieve options, config and settings
-    let options = repo
-        .get_conversation_options(conversation_id)
+) -> CommandResult<Vec<Tag>> {
+    repo.add_conversation_tag(conversation_id, tag_id)
         .await
         .map_err(|message| DbError { message })?;
-    let config = repo
-        .get_conversation_config(conversation_id)
+    let result = repo
+        .list_conversation_tags(conversation_id)
         .await
         .map_err(|message| DbError { message })?;
-    let proxy_setting = repo
-        .get_setting(SETTING_NETWORK_PROXY)
-        .await
-        .map(|setting| {
-            if let Ok(p_setting) = serde_json::from_str::<ProxySetting>(&setting.value) {
-                Some(p_setting)
-            } else {
-                None
-            }
-        })
-        .unwrap_or(None);
-    let ctx_length_setting: u16 = repo
-        .get_setting(SETTING_MODELS_CONTEXT_LENGTH)
-        .await
-        .map(|setting| match setting.value.parse::<u16>() {
-            Ok(value) => value,
-            Err(_) => DEFAULT_CONTEXT_LENGTH,
-        })
-        .unwrap_or(DEFAULT_CONTEXT_LENGTH);
-    let max_token_setting: u32 = repo
-        .get_setting(SETTING_MODELS_MAX_TOKENS)
-        .await
-        .map(|setting| match setting.value.parse::<u32>() {
-            Ok(value) => value,
-            Err(_) => DEFAULT_MAX_TOKENS,
-        })
-        .unwrap_or(DEFAULT_MAX_TOKENS);
-    // Try to retrieve the context length from the conversation's options.
-    // If unsuccessful, fall back to the default context length setting.
-    let context_length = serde_json::from_str(&options.options)
-        .map(|options_json: serde_json::Value| {
-            if let Some(ctx_length) = options_json["contextLength"].as_u64() {
-                u16::try_from(ctx_length).unwrap_or(ctx_length_setting)
-            } else {
-                ctx_length_setting
-            }
-        })
-        .unwrap_or(ctx_length_setting);
-    // Retrieve system message
-    let sys_message = repo
-        .get_system_message(conversation_id)
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn remove_conversation_tag(
+    conversation_id: i32,
+    tag_id: i32,
+    repo: State<'_, Repository>,
+) -> CommandResult<Vec<Tag>> {
+    repo.remove_conversation_tag(conversation_id, tag_id)
         .await
         .map_err(|message| DbError { message })?;
-    // Retrieve message list as context
-    let mut context = repo
-        .get_last_messages(
-            conversation_id,
-            (context_length - 1) * 2 + 1,
-            before_message_id,
-        ) // get last N - 1 turns of conversation plus one to get the last user message
+    let result = repo
+        .list_conversation_tags(conversation_id)
         .await
         .map_err(|message| DbError { message })?;
-    if let Some(sys_m) = sys_message {
-        context.insert(0, sys_m);
-    }
-    log::info!("bot calling context: {:?}", context);
-    // delegate to one-off or stream function to send request
-    let is_stream_enabled = is_stream_enabled(&options);
-    if is_stream_enabled {
-        // stream response
-        call_bot_stream(
-            tag,
-            window,
-            context,
-            options,
-            config,
-            proxy_setting,
-            max_token_setting,
-        )
-        .await;
-    } else {
-        // one-off response
-        call_bot_one_off(
-            tag,
-            window,
-            context,
-            options,
-            config,
-            proxy_setting,
-            max_token_setting,
-        )
-        .await;
-    }
-    let elapsed = now.elapsed();
-    log::info!("[Timer][commands::call_bot]: {:.2?}", elapsed);
-    Ok(())
+    Ok(result)
 }
 
 #[tauri::command]
-pub async fn create_prompt(
-    new_prompt: NewPrompt,
+pub async fn get_options(
+    conversation_id: i32,
     repo: State<'_, Repository>,
-) -> CommandResult<Prompt> {
-    let log_tag = "create_prompt";
+) -> CommandResult<ConversationOptions> {
     let now = Instant::now();
-    info(log_tag, &format!("{:?}", new_prompt));
-    let result = repo
-        .create_prompt(new_prompt)
+    let model = repo
+        .get_conversation_model(conversation_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    let options = repo
+        .get_conversation_options(conversation_id)
         .await
         .map_err(|message| DbError { message })?;
+    let result = options::with_effective_options(&repo, &model, options).await;
     let elapsed = now.elapsed();
-    info(log_tag, &format!("[Timer]: {:.2?}", elapsed));
+    log::info!("[Timer][commands::get_options]: {:.2?}", elapsed);
     Ok(result)
 }
 
 #[tauri::command]
-pub async fn list_prompts(repo: State<'_, Repository>) -> CommandResult<Vec<Prompt>> {
-    let result = repo
-        .list_prompts()
+pub async fn update_options(
+    conversation_id: i32,
+    options: String,
+    repo: State<'_, Repository>,
+) -> CommandResult<ConversationOptions> {
+    log::info!("[commands::update_options]: {}", options);
+    let now = Instant::now();
+    ensure_unlocked(&repo, conversation_id).await?;
+    let model = repo
+        .get_conversation_model(conversation_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    let options =
+        options::validate_options(&model.provider, &options, &ModelCapabilities::of(&model))
+            .map_err(|err| InvalidOption {
+                option: err.option,
+                message: err.message,
+            })?;
+    let options = repo
+        .update_conversation_options(conversation_id, options)
         .await
         .map_err(|message| DbError { message })?;
+    let result = options::with_effective_options(&repo, &model, options).await;
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::update_options]: {:.2?}", elapsed);
     Ok(result)
 }
 
+/// Reset the options of a conversation to the default options of its model
 #[tauri::command]
-pub async fn update_prompt(prompt: Prompt, repo: State<'_, Repository>) -> CommandResult<Prompt> {
-    let result = repo
-        .update_prompt(prompt)
+pub async fn reset_options_to_model_default(
+    conversation_id: i32,
+    repo: State<'_, Repository>,
+) -> CommandResult<ConversationOptions> {
+    let now = Instant::now();
+    let model = repo
+        .get_conversation_model(conversation_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    let options = repo
+        .reset_conversation_options(conversation_id)
         .await
         .map_err(|message| DbError { message })?;
+    let result = options::with_effective_options(&repo, &model, options).await;
+    let elapsed = now.elapsed();
+    log::info!(
+        "[Timer][commands::reset_options_to_model_default]: {:.2?}",
+        elapsed
+    );
     Ok(result)
 }
 
 #[tauri::command]
-pub async fn delete_prompt(prompt_id: i32, repo: State<'_, Repository>) -> CommandResult<Prompt> {
+pub async fn update_subject(
+    conversation_id: i32,
+    subject: String,
+    repo: State<'_, Repository>,
+) -> CommandResult<String> {
+    let now = Instant::now();
     let result = repo
-        .delete_prompt(prompt_id)
+        .update_conversation_subject(conversation_id, subject)
         .await
         .map_err(|message| DbError { message })?;
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::update_subject]: {:.2?}", elapsed);
     Ok(result)
 }
 
 #[tauri::command]
-pub async fn get_sys_info() -> CommandResult<serde_json::Value> {
-    let mut sys = System::new_all();
-    sys.refresh_all();
-    let result = json!({
-        "os": System::name(),
+pub async fn update_conversation_model(
+    conversation_id: i32,
+    model_id: i32,
+    repo: State<'_, Repository>,
+) -> CommandResult<ConversationDetailsDTO> {
+    let now = Instant::now();
+    ensure_unlocked(&repo, conversation_id).await?;
+    let result = repo
+        .update_conversation_model(conversation_id, model_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    let elapsed = now.elapsed();
+    log::info!(
+        "[Timer][commands::update_conversation_model]: {:.2?}",
+        elapsed
+    );
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn update_conversation(
+    conversation: UpdateConversationDTO,
+    repo: State<'_, Repository>,
+) -> CommandResult<ConversationDetailsDTO> {
+    let now = Instant::now();
+    let result = repo
+        .update_conversation(conversation)
+        .await
+        .map_err(|message| DbError { message })?;
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::update_conversation]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn create_message(
+    message: MessageDTO,
+    app_handle: tauri::AppHandle,
+    repo: State<'_, Repository>,
+) -> CommandResult<MessageDTO> {
+    let now = Instant::now();
+    log::info!("create_message: message = {:?}", message);
+    ensure_unlocked(&repo, message.conversation_id).await?;
+    // Return the message sent a moment ago instead of storing it twice
+    if Roles::from(message.role) == Roles::User {
+        let last_message = repo
+            .get_last_messages(message.conversation_id, 1, None)
+            .await
+            .map_err(|message| DbError { message })?
+            .pop();
+        if let Some(last_message) = last_message {
+            if generation::is_repeated_message(&last_message, &message) {
+                log::info!("create_message: duplicate of message {:?}", last_message.id);
+                return Ok(last_message);
+            }
+        }
+    }
+    let result = repo
+        .create_message(message)
+        .await
+        .map_err(|message| DbError { message })?;
+    log::info!("create_message: result = {:?}", result);
+    events::broadcast(EVENT_MESSAGE_CREATED, result.clone());
+    // Title the conversation after its first reply and fold older messages into its
+    // summary once a reply is stored
+    if Roles::from(result.role) == Roles::Bot {
+        let conversation_id = result.conversation_id;
+        tauri::async_runtime::spawn(async move {
+            let repo = app_handle.state::<Repository>();
+            match tasks::auto_title(&repo, conversation_id).await {
+                Ok(Some(subject)) => events::broadcast(
+                    EVENT_CONVERSATION_TITLED,
+                    ConversationTitled {
+                        conversation_id,
+                        subject,
+                    },
+                ),
+                Ok(None) => {}
+                Err(err) => log::error!("Failed to title conversation: {}", err),
+            }
+            if let Err(err) = memory::summarize_history(&repo, conversation_id).await {
+                log::error!("Failed to summarize history: {}", err);
+            }
+        });
+    }
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::create_message]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+/// Record that a conversation was read and tell every window, so its unread state is the same everywhere
+#[tauri::command]
+pub async fn mark_conversation_read(
+    conversation_id: i32,
+    repo: State<'_, Repository>,
+) -> CommandResult<ConversationRead> {
+    let read_at = repo
+        .mark_conversation_read(conversation_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    let result = ConversationRead {
+        conversation_id,
+        read_at,
+    };
+    events::broadcast(EVENT_CONVERSATION_READ, result.clone());
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn list_messages(
+    conversation_id: i32,
+    before_id: Option<i32>,
+    limit: Option<u64>,
+    repo: State<'_, Repository>,
+) -> CommandResult<Vec<MessageDTO>> {
+    let now = Instant::now();
+    // All the messages unless a page is asked for
+    let result = if before_id.is_none() && limit.is_none() {
+        repo.list_messages(conversation_id).await
+    } else {
+        repo.list_messages_before(
+            conversation_id,
+            before_id,
+            limit.unwrap_or(DEFAULT_MESSAGE_PAGE_SIZE),
+        )
+        .await
+    }
+    .map_err(|message| DbError { message })?;
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::list_messages]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn get_message_count(
+    conversation_id: i32,
+    repo: State<'_, Repository>,
+) -> CommandResult<u64> {
+    let result = repo
+        .count_messages(conversation_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn get_system_message(
+    conversation_id: i32,
+    repo: State<'_, Repository>,
+) -> CommandResult<Option<MessageDTO>> {
+    let now = Instant::now();
+    let result: Option<MessageDTO> = repo
+        .get_system_message(conversation_id)
+        .await
+        .map_err(|message| DbError { message })?
+        .and_then(|inner| Some(MessageDTO::from(inner)));
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::get_system_message]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn set_system_prompt(
+    conversation_id: i32,
+    prompt: Option<String>,
+    repo: State<'_, Repository>,
+) -> CommandResult<Option<MessageDTO>> {
+    let now = Instant::now();
+    ensure_unlocked(&repo, conversation_id).await?;
+    let result = repo
+        .set_system_prompt(conversation_id, prompt)
+        .await
+        .map_err(|message| DbError { message })?;
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::set_system_prompt]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn update_message(
+    message: MessageDTO,
+    repo: State<'_, Repository>,
+) -> CommandResult<MessageDTO> {
+    let now = Instant::now();
+    ensure_unlocked(&repo, message.conversation_id).await?;
+    let result = repo
+        .update_message(message)
+        .await
+        .map_err(|message| DbError { message })?;
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::update_message]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+/// Fix the contents of a message, such as a typo in a prompt, marking it as edited
+#[tauri::command]
+pub async fn edit_message(
+    message_id: i32,
+    content: Vec<ContentDTO>,
+    repo: State<'_, Repository>,
+) -> CommandResult<MessageDTO> {
+    let now = Instant::now();
+    if content.iter().all(|item| item.data.trim().is_empty()) {
+        return Err(StateError {
+            message: "Message can't be empty".to_string(),
+        });
+    }
+    let message = repo
+        .get_message(message_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    ensure_unlocked(&repo, message.conversation_id).await?;
+    let result = repo
+        .update_message_content(message_id, content)
+        .await
+        .map_err(|message| DbError { message })?;
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::edit_message]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+/// Edit a message as a new version, keeping the old one and its replies as a branch
+#[tauri::command]
+pub async fn branch_message(
+    message_id: i32,
+    content: Vec<ContentDTO>,
+    repo: State<'_, Repository>,
+) -> CommandResult<MessageDTO> {
+    let now = Instant::now();
+    if content.iter().all(|item| item.data.trim().is_empty()) {
+        return Err(StateError {
+            message: "Message can't be empty".to_string(),
+        });
+    }
+    let message = repo
+        .get_message(message_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    ensure_unlocked(&repo, message.conversation_id).await?;
+    let result = repo
+        .branch_message(message_id, content)
+        .await
+        .map_err(|message| DbError { message })?;
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::branch_message]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn list_message_branches(
+    conversation_id: i32,
+    repo: State<'_, Repository>,
+) -> CommandResult<Vec<MessageBranch>> {
+    let result = repo
+        .list_message_branches(conversation_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+/// Show the branch of a message, returning the messages of the conversation as shown now
+#[tauri::command]
+pub async fn switch_message_branch(
+    conversation_id: i32,
+    message_id: i32,
+    repo: State<'_, Repository>,
+) -> CommandResult<Vec<MessageDTO>> {
+    let now = Instant::now();
+    ensure_unlocked(&repo, conversation_id).await?;
+    repo.switch_message_branch(conversation_id, message_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    let result = repo
+        .list_messages(conversation_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::switch_message_branch]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn set_message_feedback(
+    message_id: i32,
+    rating: Option<Rating>,
+    emoji: Option<String>,
+    repo: State<'_, Repository>,
+) -> CommandResult<Option<MessageFeedback>> {
+    let emoji = emoji.filter(|emoji| !emoji.trim().is_empty());
+    if emoji
+        .as_ref()
+        .map_or(false, |emoji| emoji.chars().count() > MAX_EMOJI_CHARS)
+    {
+        return Err(UnknownError {
+            message: "Reaction must be a single emoji".to_string(),
+        });
+    }
+    // Nothing left to keep, same as clearing
+    if rating.is_none() && emoji.is_none() {
+        repo.delete_message_feedback(message_id)
+            .await
+            .map_err(|message| DbError { message })?;
+        return Ok(None);
+    }
+    let result = repo
+        .upsert_message_feedback(message_id, rating, emoji)
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(Some(result))
+}
+
+#[tauri::command]
+pub async fn clear_message_feedback(
+    message_id: i32,
+    repo: State<'_, Repository>,
+) -> CommandResult<()> {
+    repo.delete_message_feedback(message_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_message_feedback(
+    conversation_id: i32,
+    repo: State<'_, Repository>,
+) -> CommandResult<Vec<MessageFeedback>> {
+    let result = repo
+        .list_message_feedback(conversation_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn get_model_feedback_stats(
+    repo: State<'_, Repository>,
+) -> CommandResult<Vec<ModelFeedbackStats>> {
+    let result = repo
+        .list_model_feedback_stats()
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn hard_delete_messages(
+    conversation_id: i32,
+    repo: State<'_, Repository>,
+) -> CommandResult<()> {
+    let now = Instant::now();
+    ensure_unlocked(&repo, conversation_id).await?;
+    repo.hard_delete_messages(conversation_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    // TODO: delete all media files associated with these messages
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::delete_messages]: {:.2?}", elapsed);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn hard_delete_message(
+    message: MessageDTO,
+    repo: State<'_, Repository>,
+) -> CommandResult<MessageDTO> {
+    let now = Instant::now();
+    ensure_unlocked(&repo, message.conversation_id).await?;
+    let result = repo
+        .hard_delete_message(message)
+        .await
+        .map_err(|message| DbError { message })?;
+    // TODO: delete all media files associated with this messge
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::hard_delete_message]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+/// Remove a bad turn from a conversation before continuing it
+#[tauri::command]
+pub async fn delete_message(
+    message_id: i32,
+    repo: State<'_, Repository>,
+) -> CommandResult<MessageDTO> {
+    let now = Instant::now();
+    let message = repo
+        .get_message(message_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    ensure_unlocked(&repo, message.conversation_id).await?;
+    let result = repo
+        .delete_message(message_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::delete_message]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+/// Remove a message and everything after it, returning how many messages were removed
+#[tauri::command]
+pub async fn delete_messages_from(
+    message_id: i32,
+    repo: State<'_, Repository>,
+) -> CommandResult<u64> {
+    let now = Instant::now();
+    let message = repo
+        .get_message(message_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    ensure_unlocked(&repo, message.conversation_id).await?;
+    let result = repo
+        .delete_messages_from(&message)
+        .await
+        .map_err(|message| DbError { message })?;
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::delete_messages_from]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+/// Record the current messages of a conversation as a checkpoint to restore later
+#[tauri::command]
+pub async fn snapshot_conversation(
+    conversation_id: i32,
+    label: String,
+    repo: State<'_, Repository>,
+) -> CommandResult<Snapshot> {
+    let result = repo
+        .create_snapshot(conversation_id, label)
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn list_snapshots(
+    conversation_id: i32,
+    repo: State<'_, Repository>,
+) -> CommandResult<Vec<Snapshot>> {
+    let result = repo
+        .list_snapshots(conversation_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+/// Roll a conversation back to a snapshot and return its messages
+#[tauri::command]
+pub async fn restore_snapshot(
+    snapshot_id: i32,
+    repo: State<'_, Repository>,
+) -> CommandResult<Vec<MessageDTO>> {
+    let snapshot = repo
+        .get_snapshot(snapshot_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    ensure_unlocked(&repo, snapshot.conversation_id).await?;
+    repo.restore_snapshot(snapshot_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    let result = repo
+        .list_messages(snapshot.conversation_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn delete_snapshot(snapshot_id: i32, repo: State<'_, Repository>) -> CommandResult<()> {
+    repo.delete_snapshot(snapshot_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn copy_message(
+    message_id: i32,
+    app_handle: tauri::AppHandle,
+    repo: State<'_, Repository>,
+) -> CommandResult<()> {
+    let message = repo
+        .get_message(message_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    write_to_clipboard(&app_handle, markdown::message_to_markdown(&message))
+}
+
+#[tauri::command]
+pub async fn copy_code_blocks(
+    message_id: i32,
+    index: Option<usize>,
+    app_handle: tauri::AppHandle,
+    repo: State<'_, Repository>,
+) -> CommandResult<usize> {
+    let message = repo
+        .get_message(message_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    let blocks = markdown::extract_code_blocks(&message.get_text().unwrap_or_default());
+    let (text, count) = match index {
+        Some(index) => {
+            let block = blocks.get(index).ok_or(UnknownError {
+                message: format!("Code block {} doesn't exist", index),
+            })?;
+            (block.code.clone(), 1)
+        }
+        None => {
+            if blocks.is_empty() {
+                return Err(UnknownError {
+                    message: "Message has no code blocks".to_string(),
+                });
+            }
+            let codes: Vec<&str> = blocks.iter().map(|block| block.code.as_str()).collect();
+            (codes.join("\n\n"), blocks.len())
+        }
+    };
+    write_to_clipboard(&app_handle, text)?;
+    Ok(count)
+}
+
+/// Write the code blocks of a message as files of a directory, returning the created paths
+#[tauri::command]
+pub async fn save_code_blocks(
+    message_id: i32,
+    directory: String,
+    repo: State<'_, Repository>,
+) -> CommandResult<Vec<String>> {
+    let message = repo
+        .get_message(message_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    let blocks = markdown::extract_code_blocks(&message.get_text().unwrap_or_default());
+    if blocks.is_empty() {
+        return Err(UnknownError {
+            message: "Message has no code blocks".to_string(),
+        });
+    }
+    let paths = markdown::save_code_blocks(&blocks, Path::new(&directory))
+        .map_err(|message| UnknownError { message })?;
+    Ok(paths
+        .into_iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect())
+}
+
+#[tauri::command]
+pub async fn copy_conversation_as_markdown(
+    conversation_id: i32,
+    app_handle: tauri::AppHandle,
+    repo: State<'_, Repository>,
+) -> CommandResult<()> {
+    let text = markdown::export_conversation(&repo, conversation_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    write_to_clipboard(&app_handle, text)
+}
+
+/// Create a conversation from a markdown transcript, split into messages at the role markers
+#[tauri::command]
+pub async fn import_markdown_conversation(
+    model_id: i32,
+    text: String,
+    patterns: Option<markdown::TranscriptPatterns>,
+    repo: State<'_, Repository>,
+) -> CommandResult<Conversation> {
+    let transcript = markdown::parse_transcript(&text, &patterns.unwrap_or_default())
+        .map_err(|message| UnknownError { message })?;
+    let subject = transcript.subject.unwrap_or_else(|| {
+        transcript
+            .messages
+            .iter()
+            .find_map(|message| message.get_text())
+            .unwrap_or_default()
+    });
+    let conversation = Conversation {
+        model_id: Some(model_id),
+        subject,
+        ..Default::default()
+    };
+    let result = repo
+        .create_conversation_with_messages(conversation, transcript.messages)
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+/// Create conversations from a CSV file of question, answer and optional tags columns
+#[tauri::command]
+pub async fn import_csv_conversations(
+    model_id: i32,
+    text: String,
+    mode: Option<CsvImportMode>,
+    subject: Option<String>,
+    repo: State<'_, Repository>,
+) -> CommandResult<Vec<Conversation>> {
+    let now = Instant::now();
+    let result =
+        csv_import::import_qa_pairs(&repo, model_id, &text, mode.unwrap_or_default(), subject)
+            .await
+            .map_err(|message| UnknownError { message })?;
+    let elapsed = now.elapsed();
+    log::info!(
+        "[Timer][commands::import_csv_conversations]: {:.2?}",
+        elapsed
+    );
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn share_to_gist(
+    conversation_id: i32,
+    public: bool,
+    repo: State<'_, Repository>,
+) -> CommandResult<ConversationDetailsDTO> {
+    let result = gist::share_conversation(&repo, conversation_id, public)
+        .await
+        .map_err(|message| ApiError { message })?;
+    Ok(result)
+}
+
+/// Back a conversation by a new thread of an OpenAI assistant
+#[tauri::command]
+pub async fn link_assistant(
+    conversation_id: i32,
+    assistant_id: String,
+    repo: State<'_, Repository>,
+) -> CommandResult<ConversationDetailsDTO> {
+    let result = assistants::link_assistant(&repo, conversation_id, assistant_id)
+        .await
+        .map_err(CommandError::from_provider)?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn unlink_assistant(
+    conversation_id: i32,
+    repo: State<'_, Repository>,
+) -> CommandResult<ConversationDetailsDTO> {
+    let result = assistants::unlink_assistant(&repo, conversation_id)
+        .await
+        .map_err(CommandError::from_provider)?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn revoke_gist_share(
+    conversation_id: i32,
+    repo: State<'_, Repository>,
+) -> CommandResult<ConversationDetailsDTO> {
+    let result = gist::revoke_share(&repo, conversation_id)
+        .await
+        .map_err(|message| ApiError { message })?;
+    Ok(result)
+}
+
+/// Stop the running bot calls of a conversation, returning how many were stopped
+#[tauri::command]
+pub async fn stop_generation(
+    conversation_id: i32,
+    generations: State<'_, GenerationManager>,
+) -> CommandResult<usize> {
+    Ok(generations.stop_conversation(conversation_id))
+}
+
+/// Stop every running bot call at once, returning how many were stopped
+#[tauri::command]
+pub async fn stop_all_generations(
+    generations: State<'_, GenerationManager>,
+) -> CommandResult<usize> {
+    Ok(generations.stop_all())
+}
+
+// Refuse to change a read-only conversation
+async fn ensure_unlocked(repo: &Repository, conversation_id: i32) -> CommandResult<()> {
+    let is_locked = repo
+        .is_conversation_locked(conversation_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    if is_locked {
+        return Err(ConversationLockedError {
+            conversation_id,
+            message: format!("Conversation {} is locked", conversation_id),
+        });
+    }
+    Ok(())
+}
+
+fn write_to_clipboard(app_handle: &tauri::AppHandle, text: String) -> CommandResult<()> {
+    app_handle
+        .clipboard()
+        .write_text(text)
+        .map_err(|err| UnknownError {
+            message: format!("Failed to write to clipboard: {}", err),
+        })
+}
+
+#[tauri::command]
+pub async fn call_bot(
+    conversation_id: i32,
+    tag: String,
+    before_message_id: Option<i32>,
+    skip_moderation: Option<bool>,
+    confirm_cost: Option<bool>,
+    window: tauri::Window,
+    app_handle: tauri::AppHandle,
+    repo: State<'_, Repository>,
+    generations: State<'_, GenerationManager>,
+) -> CommandResult<()> {
+    let now = Instant::now();
+    ensure_unlocked(&repo, conversation_id).await?;
+    let conversation = repo
+        .get_conversation_details(conversation_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    // Retrieve options, config, settings and message list as context
+    let ctx = ChatContext::load(&repo, conversation_id, before_message_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    if !ctx
+        .messages
+        .iter()
+        .any(|message| Roles::from(message.role) == Roles::User)
+    {
+        return Err(StateError {
+            message: "There is no user message to reply to".to_string(),
+        });
+    }
+    // Let the user confirm or cancel flagged content, sending it again with skip_moderation
+    if !skip_moderation.unwrap_or(false) {
+        match moderation::check_outgoing(&repo, &ctx.messages).await {
+            Ok(Some(result)) => {
+                log::info!("Message flagged by moderation: {:?}", result.categories);
+                let payload = ModerationFlagged {
+                    conversation_id,
+                    tag,
+                    categories: result.categories,
+                };
+                if let Err(err) = window.emit(EVENT_MODERATION_FLAGGED, payload) {
+                    log::error!("Error when sending event: {}", err);
+                }
+                return Ok(());
+            }
+            Ok(None) => {}
+            // A failing moderation API shouldn't block the conversation
+            Err(err) => log::error!("Moderation check failed, sending anyway: {}", err),
+        }
+    }
+    log::info!("bot calling context: {:?}", ctx.messages);
+    // Identical requests are answered from the cache when it's turned on, at no cost.
+    // Replies of an assistant depend on its thread, not only on the request.
+    let cache_key = match conversation.thread_id {
+        Some(_) => None,
+        None => response_cache::cache_key_for(&repo, &ctx).await,
+    };
+    if let Some(key) = &cache_key {
+        if let Some(reply) = response_cache::lookup(&repo, key).await {
+            log::info!("Reply served from cache");
+            emit_stream_start(&tag, &window);
+            emit_stream_data(&tag, &window, reply);
+            emit_stream_done(&tag, &window);
+            log::info!("[Timer][commands::call_bot]: {:.2?}", now.elapsed());
+            return Ok(());
+        }
+    }
+    // Expensive requests are only sent once the user confirmed them with confirm_cost
+    if !confirm_cost.unwrap_or(false) {
+        if let Some(estimate) = cost_guard::check(&repo, &ctx, conversation.model_id).await {
+            log::info!(
+                "Request estimated at ${:.2}, above the threshold of ${:.2}",
+                estimate.estimated_cost,
+                estimate.threshold
+            );
+            return Err(CostConfirmationRequired {
+                conversation_id,
+                estimated_cost: estimate.estimated_cost,
+                threshold: estimate.threshold,
+                message: format!(
+                    "This request may cost up to ${:.2}, above the threshold of ${:.2}",
+                    estimate.estimated_cost, estimate.threshold
+                ),
+            });
+        }
+    }
+    // The same request still being answered was sent twice, by a double-click or a retry race
+    let fingerprint = generation::request_fingerprint(&ctx.messages);
+    if let Some(original_tag) = generations.claim_request(conversation_id, fingerprint, &tag) {
+        log::info!("Duplicate request, following generation {}", original_tag);
+        return Err(DuplicateRequest {
+            conversation_id,
+            tag: original_tag,
+            message: "The same request is already being answered".to_string(),
+        });
+    }
+    // Conversations backed by an assistant thread are answered by the assistant
+    if conversation.thread_id.is_some() {
+        call_bot_assistant(
+            tag,
+            conversation_id,
+            before_message_id,
+            window,
+            app_handle.clone(),
+            &generations,
+        )
+        .await;
+        let elapsed = now.elapsed();
+        insights::record(&app_handle, insights::EVENT_REPLY, Some(elapsed));
+        log::info!("[Timer][commands::call_bot]: {:.2?}", elapsed);
+        return Ok(());
+    }
+    // delegate to one-off or stream function to send request
+    // Replies validated against a response schema are only shown once they match it
+    let is_stream_enabled = is_stream_enabled(&ctx.options) && ctx.response_schema.is_none();
+    let global_settings = ctx.global_settings();
+    let reply_text = if is_stream_enabled {
+        // stream response
+        call_bot_stream(
+            tag,
+            conversation_id,
+            window,
+            &generations,
+            ctx.messages,
+            ctx.options,
+            ctx.config,
+            ctx.proxy_setting,
+            ctx.timeouts,
+            global_settings,
+            ctx.max_continuations,
+            ctx.max_attempts,
+            ctx.privacy_filter,
+            ctx.fallbacks,
+        )
+        .await
+    } else {
+        // one-off response
+        call_bot_one_off(
+            tag,
+            conversation_id,
+            window,
+            &generations,
+            ctx.messages,
+            ctx.options,
+            ctx.config,
+            ctx.proxy_setting,
+            ctx.timeouts,
+            global_settings,
+            ctx.max_continuations,
+            ctx.max_attempts,
+            ctx.privacy_filter,
+            ctx.fallbacks,
+        )
+        .await
+    };
+    if let (Some(key), Some(reply_text)) = (cache_key, reply_text) {
+        response_cache::store(&repo, key, reply_text).await;
+    }
+    let elapsed = now.elapsed();
+    insights::record(&app_handle, insights::EVENT_REPLY, Some(elapsed));
+    log::info!("[Timer][commands::call_bot]: {:.2?}", elapsed);
+    Ok(())
+}
+
+/// The body of the request `call_bot` would send for a conversation, with secrets masked,
+/// to see exactly what the model receives
+#[tauri::command]
+pub async fn preview_request(
+    conversation_id: i32,
+    repo: State<'_, Repository>,
+) -> CommandResult<serde_json::Value> {
+    let now = Instant::now();
+    let ctx = ChatContext::load(&repo, conversation_id, None)
+        .await
+        .map_err(|message| DbError { message })?;
+    let mut result = ctx.preview().map_err(|message| UnknownError { message })?;
+    log_utils::redact_json_secrets(&mut result);
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::preview_request]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn translate_text(
+    text: String,
+    target_lang: String,
+    model_id: Option<i32>,
+    repo: State<'_, Repository>,
+) -> CommandResult<String> {
+    let now = Instant::now();
+    let result = tasks::translate_text(&repo, text, &target_lang, model_id)
+        .await
+        .map_err(CommandError::from_provider)?;
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::translate_text]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn summarize_conversation(
+    conversation_id: i32,
+    style: Option<SummaryStyle>,
+    repo: State<'_, Repository>,
+) -> CommandResult<ConversationDetailsDTO> {
+    let now = Instant::now();
+    let result = tasks::summarize_conversation(&repo, conversation_id, style.unwrap_or_default())
+        .await
+        .map_err(CommandError::from_provider)?;
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::summarize_conversation]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+/// Compare texts by the cosine similarity of their embeddings
+#[tauri::command]
+pub async fn compare_embeddings(
+    texts: Vec<String>,
+    model: Option<String>,
+    model_id: Option<i32>,
+    repo: State<'_, Repository>,
+) -> CommandResult<EmbeddingComparison> {
+    let now = Instant::now();
+    let result = embeddings::compare_embeddings(&repo, texts, model, model_id)
+        .await
+        .map_err(CommandError::from_provider)?;
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::compare_embeddings]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn refine_prompt(
+    draft: String,
+    repo: State<'_, Repository>,
+) -> CommandResult<RefinedPrompt> {
+    let now = Instant::now();
+    let result = tasks::refine_prompt(&repo, draft)
+        .await
+        .map_err(CommandError::from_provider)?;
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::refine_prompt]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+/// Count the tokens of the prompt the next reply of a conversation would be sent with
+#[tauri::command]
+pub async fn count_tokens(
+    conversation_id: i32,
+    repo: State<'_, Repository>,
+) -> CommandResult<TokenCount> {
+    let now = Instant::now();
+    let ctx = ChatContext::load(&repo, conversation_id, None)
+        .await
+        .map_err(|message| DbError { message })?;
+    let model = pricing::model_name(&ctx.config.config).unwrap_or_default();
+    let proxy_setting = get_proxy_setting(&repo, &ctx.config.provider).await;
+    let timeouts = get_timeout_setting(&repo).await;
+    let client = LLMClient::new(ctx.config.clone(), proxy_setting, timeouts)
+        .map_err(|message| ApiError { message })?;
+    let result = client.count_tokens(&ctx.messages, ctx.model_limits.or_catalog(&model));
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::count_tokens]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+/// Count the tokens of a text, such as a draft prompt, for a model
+#[tauri::command]
+pub async fn count_tokens_for_text(
+    text: String,
+    model_id: i32,
+    repo: State<'_, Repository>,
+) -> CommandResult<TokenCount> {
+    let now = Instant::now();
+    let model = repo
+        .get_model(model_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    let name = pricing::model_name(&model.config).unwrap_or_default();
+    let result =
+        tokenizer::count_text_tokens(&text, &name, ModelLimits::of(&model).or_catalog(&name));
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::count_tokens_for_text]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn get_local_insights(
+    year: Option<i32>,
+    repo: State<'_, Repository>,
+) -> CommandResult<LocalInsights> {
+    let result = insights::get_local_insights(&repo, year)
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn get_activity_timeline(
+    range: Option<ActivityRange>,
+    repo: State<'_, Repository>,
+) -> CommandResult<Vec<DailyActivity>> {
+    let now = Instant::now();
+    let result = insights::get_activity_timeline(&repo, range.unwrap_or_default())
+        .await
+        .map_err(|message| DbError { message })?;
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::get_activity_timeline]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn get_usage_stats(
+    range: Option<ActivityRange>,
+    repo: State<'_, Repository>,
+) -> CommandResult<UsageStats> {
+    let now = Instant::now();
+    let result = insights::get_usage_stats(&repo, range.unwrap_or_default())
+        .await
+        .map_err(|message| DbError { message })?;
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::get_usage_stats]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn list_model_prices(repo: State<'_, Repository>) -> CommandResult<Vec<ModelPrice>> {
+    let now = Instant::now();
+    let result = repo
+        .list_model_prices()
+        .await
+        .map_err(|message| DbError { message })?;
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::list_model_prices]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+/// Set the prices of a model in USD per 1k input and output tokens
+#[tauri::command]
+pub async fn set_model_price(
+    model_id: i32,
+    input_price: f64,
+    output_price: f64,
+    repo: State<'_, Repository>,
+) -> CommandResult<ModelPrice> {
+    let now = Instant::now();
+    if !(input_price >= 0.0 && output_price >= 0.0) {
+        return Err(StateError {
+            message: "Prices must be zero or more".to_string(),
+        });
+    }
+    let result = repo
+        .upsert_model_price(model_id, input_price, output_price)
+        .await
+        .map_err(|message| DbError { message })?;
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::set_model_price]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn delete_model_price(model_id: i32, repo: State<'_, Repository>) -> CommandResult<()> {
+    let now = Instant::now();
+    repo.delete_model_price(model_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::delete_model_price]: {:.2?}", elapsed);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_workspaces(app_handle: tauri::AppHandle) -> CommandResult<WorkspaceList> {
+    let app_data_dir =
+        workspaces::get_app_data_dir(&app_handle).map_err(|message| UnknownError { message })?;
+    Ok(workspaces::load_workspaces(&app_data_dir))
+}
+
+#[tauri::command]
+pub async fn create_workspace(
+    name: String,
+    app_handle: tauri::AppHandle,
+) -> CommandResult<Workspace> {
+    let app_data_dir =
+        workspaces::get_app_data_dir(&app_handle).map_err(|message| UnknownError { message })?;
+    let result = workspaces::create_workspace(&app_data_dir, name)
+        .map_err(|message| UnknownError { message })?;
+    Ok(result)
+}
+
+/// Switch to another workspace and restart the app to open its database
+#[tauri::command]
+pub async fn switch_workspace(
+    workspace_id: String,
+    app_handle: tauri::AppHandle,
+) -> CommandResult<()> {
+    let app_data_dir =
+        workspaces::get_app_data_dir(&app_handle).map_err(|message| UnknownError { message })?;
+    workspaces::set_active_workspace(&app_data_dir, &workspace_id)
+        .map_err(|message| UnknownError { message })?;
+    log::info!("Switching to workspace {}", workspace_id);
+    app_handle.restart();
+}
+
+/// Write a fine-tuning dataset in JSONL to the file chosen by the user
+#[tauri::command]
+pub async fn export_finetune_jsonl(
+    filter: FinetuneFilter,
+    path: String,
+    repo: State<'_, Repository>,
+) -> CommandResult<FinetuneExport> {
+    let now = Instant::now();
+    let result = finetune::export_jsonl(&repo, &filter)
+        .await
+        .map_err(|message| DbError { message })?;
+    std::fs::write(&path, &result.jsonl).map_err(|err| UnknownError {
+        message: format!("Failed to write {}: {}", path, err),
+    })?;
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::export_finetune_jsonl]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+/// List the files stored with the provider of the model
+#[tauri::command]
+pub async fn list_provider_files(
+    model_id: i32,
+    repo: State<'_, Repository>,
+) -> CommandResult<Vec<ProviderFile>> {
+    let result = provider_files::list_files(&repo, model_id)
+        .await
+        .map_err(CommandError::from_provider)?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn upload_provider_file(
+    model_id: i32,
+    path: String,
+    purpose: String,
+    repo: State<'_, Repository>,
+) -> CommandResult<ProviderFile> {
+    let now = Instant::now();
+    let result = provider_files::upload_file(&repo, model_id, path, purpose)
+        .await
+        .map_err(CommandError::from_provider)?;
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::upload_provider_file]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn delete_provider_file(
+    model_id: i32,
+    file_id: String,
+    repo: State<'_, Repository>,
+) -> CommandResult<()> {
+    provider_files::delete_file(&repo, model_id, file_id)
+        .await
+        .map_err(CommandError::from_provider)?;
+    Ok(())
+}
+
+/// Upload a training file to the provider of the model, returning the id of the file
+#[tauri::command]
+pub async fn upload_training_file(
+    model_id: i32,
+    path: String,
+    repo: State<'_, Repository>,
+) -> CommandResult<String> {
+    let now = Instant::now();
+    let result = finetune_jobs::upload_training_file(&repo, model_id, path)
+        .await
+        .map_err(CommandError::from_provider)?;
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::upload_training_file]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+/// Start a fine-tuning job. Once it succeeds, the resulting model is added to the models.
+#[tauri::command]
+pub async fn create_finetune_job(
+    model_id: i32,
+    training_file_id: String,
+    suffix: Option<String>,
+    repo: State<'_, Repository>,
+) -> CommandResult<FinetuneJob> {
+    let result = finetune_jobs::create_job(&repo, model_id, training_file_id, suffix)
+        .await
+        .map_err(CommandError::from_provider)?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn list_finetune_jobs(repo: State<'_, Repository>) -> CommandResult<Vec<FinetuneJob>> {
+    let result = repo
+        .list_finetune_jobs()
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+/// Create a set of test prompts, graded by the expected answers or by a judge model
+#[tauri::command]
+pub async fn create_eval_set(
+    name: String,
+    judge_model_id: Option<i32>,
+    cases: Vec<NewEvalCase>,
+    repo: State<'_, Repository>,
+) -> CommandResult<EvalSet> {
+    if cases.is_empty() {
+        return Err(UnknownError {
+            message: "An eval set needs at least one prompt".to_string(),
+        });
+    }
+    let result = repo
+        .create_eval_set(name, judge_model_id, cases)
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn list_eval_sets(repo: State<'_, Repository>) -> CommandResult<Vec<EvalSet>> {
+    let result = repo
+        .list_eval_sets()
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn list_eval_cases(
+    eval_set_id: i32,
+    repo: State<'_, Repository>,
+) -> CommandResult<Vec<EvalCase>> {
+    let result = repo
+        .list_eval_cases(eval_set_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn delete_eval_set(eval_set_id: i32, repo: State<'_, Repository>) -> CommandResult<()> {
+    repo.delete_eval_set(eval_set_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(())
+}
+
+/// Run the prompts of an eval set against the models and grade the replies
+#[tauri::command]
+pub async fn run_eval(
+    eval_set_id: i32,
+    model_ids: Vec<i32>,
+    repo: State<'_, Repository>,
+) -> CommandResult<EvalRun> {
+    let now = Instant::now();
+    let result = evals::run_eval(&repo, eval_set_id, model_ids)
+        .await
+        .map_err(CommandError::from_provider)?;
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::run_eval]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn list_eval_runs(
+    eval_set_id: i32,
+    repo: State<'_, Repository>,
+) -> CommandResult<Vec<EvalRun>> {
+    let result = repo
+        .list_eval_runs(eval_set_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+/// Get the results of an eval run, with the average score, latency and cost of each model
+#[tauri::command]
+pub async fn get_eval_results(
+    run_id: i32,
+    repo: State<'_, Repository>,
+) -> CommandResult<EvalResults> {
+    let result = evals::get_eval_results(&repo, run_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+/// Create a knowledge collection, mirrored to a vector store of the provider of the model
+#[tauri::command]
+pub async fn create_collection(
+    name: String,
+    model_id: i32,
+    repo: State<'_, Repository>,
+) -> CommandResult<Collection> {
+    let result = repo
+        .create_collection(name, model_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn list_collections(repo: State<'_, Repository>) -> CommandResult<Vec<Collection>> {
+    let result = repo
+        .list_collections()
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn list_collection_files(
+    collection_id: i32,
+    repo: State<'_, Repository>,
+) -> CommandResult<Vec<CollectionFile>> {
+    let result = repo
+        .list_collection_files(collection_id, false)
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+/// Add local files to a collection. They are uploaded by the next sync.
+#[tauri::command]
+pub async fn add_collection_files(
+    collection_id: i32,
+    paths: Vec<String>,
+    repo: State<'_, Repository>,
+) -> CommandResult<Vec<CollectionFile>> {
+    let result = repo
+        .add_collection_files(collection_id, paths)
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+/// Remove a file from a collection. It's removed from the vector store by the next sync.
+#[tauri::command]
+pub async fn remove_collection_file(
+    file_id: i32,
+    repo: State<'_, Repository>,
+) -> CommandResult<CollectionFile> {
+    let result = repo
+        .remove_collection_file(file_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+/// Sync a collection now instead of waiting for the background job
+#[tauri::command]
+pub async fn sync_collection(
+    collection_id: i32,
+    repo: State<'_, Repository>,
+) -> CommandResult<Collection> {
+    let now = Instant::now();
+    let result = collections::sync_collection(&repo, collection_id)
+        .await
+        .map_err(CommandError::from_provider)?;
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::sync_collection]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+/// Queue prompts to be answered by the model through its batch API
+#[tauri::command]
+pub async fn create_batch(
+    model_id: i32,
+    prompts: Vec<String>,
+    repo: State<'_, Repository>,
+) -> CommandResult<Batch> {
+    let result = batch::create_batch(&repo, model_id, prompts)
+        .await
+        .map_err(CommandError::from_provider)?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn list_batches(repo: State<'_, Repository>) -> CommandResult<Vec<Batch>> {
+    let result = repo
+        .list_batches()
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn list_batch_items(
+    batch_id: i32,
+    repo: State<'_, Repository>,
+) -> CommandResult<Vec<BatchItem>> {
+    let result = repo
+        .list_batch_items(batch_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn create_response_schema(
+    new_schema: NewResponseSchema,
+    repo: State<'_, Repository>,
+) -> CommandResult<ResponseSchema> {
+    check_response_schema(&new_schema.schema)?;
+    let result = repo
+        .create_response_schema(new_schema)
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn list_response_schemas(
+    repo: State<'_, Repository>,
+) -> CommandResult<Vec<ResponseSchema>> {
+    let result = repo
+        .list_response_schemas()
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn update_response_schema(
+    schema: ResponseSchema,
+    repo: State<'_, Repository>,
+) -> CommandResult<ResponseSchema> {
+    check_response_schema(&schema.schema)?;
+    let result = repo
+        .update_response_schema(schema)
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn delete_response_schema(
+    schema_id: i32,
+    repo: State<'_, Repository>,
+) -> CommandResult<()> {
+    repo.delete_response_schema(schema_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(())
+}
+
+/// Make the replies of a conversation match a response schema, or stop doing so with None
+#[tauri::command]
+pub async fn set_conversation_response_schema(
+    conversation_id: i32,
+    schema_id: Option<i32>,
+    repo: State<'_, Repository>,
+) -> CommandResult<ConversationDetailsDTO> {
+    let result = repo
+        .update_conversation_response_schema(conversation_id, schema_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+/// Make the replies of a conversation be written in a language, or let the model pick with None
+#[tauri::command]
+pub async fn set_conversation_language(
+    conversation_id: i32,
+    language: Option<String>,
+    repo: State<'_, Repository>,
+) -> CommandResult<ConversationDetailsDTO> {
+    ensure_unlocked(&repo, conversation_id).await?;
+    let language = language
+        .map(|language| language.trim().to_string())
+        .filter(|language| !language.is_empty());
+    let result = repo
+        .update_conversation_language(conversation_id, language)
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+/// Set the models replying in turn when the model of a conversation is unavailable,
+/// or use the ones of the settings with None. An empty list turns the fallback off.
+#[tauri::command]
+pub async fn set_conversation_fallback_models(
+    conversation_id: i32,
+    model_ids: Option<Vec<i32>>,
+    repo: State<'_, Repository>,
+) -> CommandResult<ConversationDetailsDTO> {
+    if let Some(model_ids) = &model_ids {
+        for model_id in model_ids {
+            repo.get_model(*model_id)
+                .await
+                .map_err(|message| DbError { message })?;
+        }
+    }
+    let fallback_model_ids = model_ids
+        .map(|model_ids| serde_json::to_string(&model_ids))
+        .transpose()
+        .map_err(|err| UnknownError {
+            message: err.to_string(),
+        })?;
+    let result = repo
+        .update_conversation_fallback_models(conversation_id, fallback_model_ids)
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+// Default options are merged with the provider's, which only works with JSON objects
+fn check_default_options(options: &Option<String>) -> CommandResult<()> {
+    match options
+        .as_deref()
+        .map(serde_json::from_str::<serde_json::Value>)
+    {
+        None | Some(Ok(serde_json::Value::Object(_))) => Ok(()),
+        _ => Err(UnknownError {
+            message: "The default options must be a JSON object".to_string(),
+        }),
+    }
+}
+
+// Schemas are sent to providers as is, so reject the ones that aren't JSON objects early
+fn check_response_schema(schema: &str) -> CommandResult<()> {
+    match serde_json::from_str::<serde_json::Value>(schema) {
+        Ok(value) if value.is_object() => Ok(()),
+        _ => Err(UnknownError {
+            message: "The schema must be a JSON object".to_string(),
+        }),
+    }
+}
+
+#[tauri::command]
+pub async fn create_prompt(
+    new_prompt: NewPrompt,
+    repo: State<'_, Repository>,
+) -> CommandResult<Prompt> {
+    let log_tag = "create_prompt";
+    let now = Instant::now();
+    info(log_tag, &format!("{:?}", new_prompt));
+    let result = repo
+        .create_prompt(new_prompt)
+        .await
+        .map_err(|message| DbError { message })?;
+    let elapsed = now.elapsed();
+    info(log_tag, &format!("[Timer]: {:.2?}", elapsed));
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn list_prompts(repo: State<'_, Repository>) -> CommandResult<Vec<Prompt>> {
+    let result = repo
+        .list_prompts()
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn update_prompt(prompt: Prompt, repo: State<'_, Repository>) -> CommandResult<Prompt> {
+    let result = repo
+        .update_prompt(prompt)
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn delete_prompt(prompt_id: i32, repo: State<'_, Repository>) -> CommandResult<Prompt> {
+    let result = repo
+        .delete_prompt(prompt_id)
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn palette_search(
+    query: String,
+    limit: Option<usize>,
+    repo: State<'_, Repository>,
+) -> CommandResult<Vec<PaletteItem>> {
+    let now = Instant::now();
+    let result = repo
+        .palette_search(&query, limit.unwrap_or(DEFAULT_PALETTE_LIMIT))
+        .await
+        .map_err(|message| DbError { message })?;
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::palette_search]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+/// Find the messages of a conversation containing all the words of the query
+#[tauri::command]
+pub async fn search_in_conversation(
+    conversation_id: i32,
+    query: String,
+    repo: State<'_, Repository>,
+) -> CommandResult<Vec<MessageMatch>> {
+    let fts_query = match search::fts_query(&query) {
+        Some(fts_query) => fts_query,
+        None => return Ok(vec![]),
+    };
+    let now = Instant::now();
+    let matches = repo
+        .search_messages(conversation_id, fts_query)
+        .await
+        .map_err(|message| DbError { message })?;
+    let result = matches
+        .into_iter()
+        .map(|content| search::highlight_snippet(content.message_id, &content.snippet))
+        .collect();
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::search_in_conversation]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+/// Find the messages of all conversations containing all the words of the query
+#[tauri::command]
+pub async fn search_messages(
+    query: String,
+    limit: Option<u64>,
+    repo: State<'_, Repository>,
+) -> CommandResult<Vec<ConversationMessageMatch>> {
+    let fts_query = match search::fts_query(&query) {
+        Some(fts_query) => fts_query,
+        None => return Ok(vec![]),
+    };
+    let now = Instant::now();
+    let matches = repo
+        .search_all_messages(fts_query, limit.unwrap_or(DEFAULT_SEARCH_LIMIT))
+        .await
+        .map_err(|message| DbError { message })?;
+    let result = matches
+        .into_iter()
+        .map(|content| ConversationMessageMatch {
+            conversation_id: content.conversation_id,
+            conversation_subject: content.conversation_subject,
+            message: search::highlight_snippet(content.message_id, &content.snippet),
+        })
+        .collect();
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::search_messages]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn rebuild_derived_data(repo: State<'_, Repository>) -> CommandResult<DerivedDataReport> {
+    let now = Instant::now();
+    let result = repo
+        .rebuild_derived_data()
+        .await
+        .map_err(|message| DbError { message })?;
+    log::info!(
+        "Rebuilt derived data of {} conversations and {} contents",
+        result.conversations,
+        result.indexed_contents
+    );
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::rebuild_derived_data]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+/// Seed the defaults of the workspace if it was never done, as on first launch
+#[tauri::command]
+pub async fn bootstrap_defaults(repo: State<'_, Repository>) -> CommandResult<BootstrapSummary> {
+    let result = bootstrap::bootstrap_defaults(&repo)
+        .await
+        .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn get_sys_info() -> CommandResult<serde_json::Value> {
+    let mut sys = System::new_all();
+    sys.refresh_all();
+    let result = json!({
+        "os": System::name(),
         "version": System::os_version(),
         "kernel": System::kernel_version(),
         "totalMemory": format!("{:.2} GB", sys.total_memory() as f64 / 1024.0 / 1024.0 / 1024.0),
@@ -537,18 +2315,214 @@ pub async fn get_sys_info() -> CommandResult<serde_json::Value> {
     Ok(result)
 }
 
+#[tauri::command]
+pub async fn run_diagnostics(
+    app_handle: tauri::AppHandle,
+    repo: State<'_, Repository>,
+) -> CommandResult<DiagnosticsReport> {
+    let now = Instant::now();
+    let app_version = app_handle.package_info().version.to_string();
+    let result = diagnostics::run_diagnostics(&repo, app_version).await;
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::run_diagnostics]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+/// Send a request through a proxy before saving it, to the given URL or else to OpenAI
+#[tauri::command]
+pub async fn test_proxy(setting: ProxySetting, url: Option<String>) -> CommandResult<ProxyTest> {
+    let now = Instant::now();
+    let result = proxy::test_proxy(setting, url)
+        .await
+        .map_err(|message| ApiError { message })?;
+    let elapsed = now.elapsed();
+    log::info!("[Timer][commands::test_proxy]: {:.2?}", elapsed);
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn check_for_updates(app_handle: tauri::AppHandle) -> CommandResult<Option<UpdateInfo>> {
+    let result = updater::check_for_updates(&app_handle)
+        .await
+        .map_err(|message| ApiError { message })?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn install_update(app_handle: tauri::AppHandle) -> CommandResult<()> {
+    updater::install_update(&app_handle)
+        .await
+        .map_err(|message| ApiError { message })?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_crash_reports(app_handle: tauri::AppHandle) -> CommandResult<Vec<CrashReport>> {
+    let crash_dir =
+        crash::get_crash_dir(&app_handle).map_err(|message| UnknownError { message })?;
+    let result = crash::list_reports(&crash_dir).map_err(|message| UnknownError { message })?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn delete_crash_reports(
+    ids: Option<Vec<String>>,
+    app_handle: tauri::AppHandle,
+) -> CommandResult<usize> {
+    let crash_dir =
+        crash::get_crash_dir(&app_handle).map_err(|message| UnknownError { message })?;
+    let result =
+        crash::delete_reports(&crash_dir, ids).map_err(|message| UnknownError { message })?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn get_recent_logs(
+    lines: usize,
+    level: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> CommandResult<Vec<String>> {
+    let log_dir = app_handle
+        .path()
+        .app_log_dir()
+        .map_err(|err| UnknownError {
+            message: err.to_string(),
+        })?;
+    let level = match level {
+        Some(level) => Some(level.parse::<log::Level>().map_err(|_| UnknownError {
+            message: format!("Invalid log level: {}", level),
+        })?),
+        None => None,
+    };
+    let result = log_utils::read_recent_logs(&log_dir, lines, level)
+        .map_err(|message| UnknownError { message })?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn open_log_folder(app_handle: tauri::AppHandle) -> CommandResult<()> {
+    let log_dir = app_handle
+        .path()
+        .app_log_dir()
+        .map_err(|err| UnknownError {
+            message: err.to_string(),
+        })?;
+    open_in_file_manager(&log_dir).map_err(|message| UnknownError { message })?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_background_settings(
+    app_handle: tauri::AppHandle,
+) -> CommandResult<BackgroundSettings> {
+    let result =
+        background::get_settings(&app_handle).map_err(|message| UnknownError { message })?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn set_autostart(enabled: bool, app_handle: tauri::AppHandle) -> CommandResult<()> {
+    background::set_autostart(&app_handle, enabled).map_err(|message| UnknownError { message })?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_lock_status(app_handle: tauri::AppHandle) -> CommandResult<LockStatus> {
+    Ok(app_lock::get_status(&app_handle).await)
+}
+
+#[tauri::command]
+pub async fn set_lock_passphrase(
+    current: Option<String>,
+    passphrase: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> CommandResult<LockStatus> {
+    let result = app_lock::set_passphrase(&app_handle, current, passphrase)
+        .await
+        .map_err(|message| LockedError { message })?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn lock(app_handle: tauri::AppHandle) -> CommandResult<()> {
+    app_lock::lock(&app_handle)
+        .await
+        .map_err(|message| LockedError { message })?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn unlock(passphrase: String, app_handle: tauri::AppHandle) -> CommandResult<()> {
+    app_lock::unlock(&app_handle, passphrase)
+        .await
+        .map_err(|message| LockedError { message })?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn start_api_server(
+    app_handle: tauri::AppHandle,
+    api_server: State<'_, ApiServer>,
+    repo: State<'_, Repository>,
+) -> CommandResult<ApiServerInfo> {
+    let result = api_server
+        .start(app_handle.clone())
+        .await
+        .map_err(|message| UnknownError { message })?;
+    repo.upsert_setting(Setting {
+        key: SETTING_API_SERVER_ENABLED.to_string(),
+        value: "true".to_string(),
+    })
+    .await
+    .map_err(|message| DbError { message })?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn stop_api_server(
+    api_server: State<'_, ApiServer>,
+    repo: State<'_, Repository>,
+) -> CommandResult<ApiServerInfo> {
+    api_server.stop();
+    repo.upsert_setting(Setting {
+        key: SETTING_API_SERVER_ENABLED.to_string(),
+        value: "false".to_string(),
+    })
+    .await
+    .map_err(|message| DbError { message })?;
+    Ok(api_server.info())
+}
+
+#[tauri::command]
+pub async fn get_api_server_info(api_server: State<'_, ApiServer>) -> CommandResult<ApiServerInfo> {
+    Ok(api_server.info())
+}
+
+#[tauri::command]
+pub async fn refresh_tray_menu(app_handle: tauri::AppHandle) -> CommandResult<()> {
+    tray::refresh_tray_with_state(app_handle).await;
+    Ok(())
+}
+
 /***** Functions for calling model API START *****/
 
-/// Calling chat bot in normal mode
+/// Calling chat bot in normal mode, returning the reply once it is complete
 async fn call_bot_one_off(
     tag: String,
+    conversation_id: i32,
     window: tauri::Window,
+    generations: &GenerationManager,
     messages: Vec<MessageDTO>,
     options: GenericOptions,
     config: GenericConfig,
     proxy_setting: Option<ProxySetting>,
-    max_token_setting: u32,
-) {
+    timeouts: TimeoutSetting,
+    global_settings: GlobalSettings,
+    max_continuations: u32,
+    max_attempts: u32,
+    privacy_filter: Option<PrivacyFilter>,
+    fallbacks: Vec<FallbackModel>,
+) -> Option<String> {
     log::info!("call_bot_one_off");
     let window_clone = window.clone();
     let window_clone_2 = window.clone();
@@ -556,62 +2530,166 @@ async fn call_bot_one_off(
     let task_handle = tokio::spawn(async move {
         // handle non-stream response
         log::info!("call_bot_one_off: thread start");
-        let init_client_result = LLMClient::new(config, proxy_setting);
+        let init_client_result = LLMClient::new(config, proxy_setting, timeouts);
         match init_client_result {
             Ok(client) => {
-                let result = client
-                    .chat(
-                        messages,
-                        options,
-                        GlobalSettings {
-                            max_tokens: max_token_setting,
-                        },
-                    )
-                    .await;
+                let on_continue = |continuation| emit_stream_continue(&tag, &window, continuation);
+                let on_fallback =
+                    |fallback: &ModelFallback| emit_stream_fallback(&tag, &window, fallback);
+                let on_retry = |retry: &RequestRetry| emit_stream_retrying(&tag, &window, retry);
+                let target = ChatTarget {
+                    client,
+                    options,
+                    global_settings,
+                };
+                let result = fallback::with_fallback(target, &fallbacks, on_fallback, |target| {
+                    let messages = messages.clone();
+                    async move {
+                        retry::with_retry(max_attempts, on_retry, || {
+                            let target = target.clone();
+                            let messages = messages.clone();
+                            async move {
+                                match target.global_settings.response_schema.clone() {
+                                    Some(schema) => {
+                                        schema::chat_with_schema(
+                                            &target.client,
+                                            messages,
+                                            target.options,
+                                            target.global_settings,
+                                            max_continuations,
+                                            &schema,
+                                            on_continue,
+                                        )
+                                        .await
+                                    }
+                                    None => {
+                                        chat_with_continuations(
+                                            &target.client,
+                                            messages,
+                                            target.options,
+                                            target.global_settings,
+                                            max_continuations,
+                                            on_continue,
+                                        )
+                                        .await
+                                    }
+                                }
+                            }
+                        })
+                        .await
+                    }
+                })
+                .await;
                 match result {
-                    Ok(reply) => {
+                    Ok(mut reply) => {
+                        if let Some(filter) = &privacy_filter {
+                            reply.message = filter.restore(&reply.message);
+                        }
                         // start receiving in frontend
                         emit_stream_start(&tag, &window);
                         log::info!("Bot call received: {:?}", reply);
+                        let reply_text = reply.message.clone();
                         emit_stream_data(&tag, &window, reply);
                         emit_stream_done(&tag, &window);
+                        notifications::notify_reply_finished(&window, conversation_id, &reply_text)
+                            .await;
                         log::info!("call_bot_one_off: thread done");
+                        Some(reply_text)
                     }
                     Err(msg) => {
-                        emit_stream_error(&tag, &window, &msg);
                         log::error!("call_bot_one_off: {}", &msg);
+                        emit_stream_error(&tag, &window, &CommandError::from_provider(msg));
+                        None
                     }
                 }
             }
             Err(msg) => {
-                emit_stream_error(&tag, &window, &msg);
                 log::error!("call_bot_one_off: {}", &msg);
+                emit_stream_error(&tag, &window, &ApiError { message: msg });
+                None
             }
         }
     });
     let abort_handle = task_handle.abort_handle();
+    generations.register(&tag_clone, conversation_id, abort_handle.clone());
+    let tag_clone_2 = tag_clone.clone();
     // Bind listener for cancel events
-    let event_handle = window_clone.listen("stop-bot", move |_| {
+    let event_handle = window_clone.listen(generation::stop_event(conversation_id), move |_| {
         log::info!("Bot call stopped!");
         abort_handle.abort();
         emit_stream_stopped(&tag_clone, &window_clone_2);
     });
+    // Run task, which has no result when stopped
+    let result = task_handle.await.ok().flatten();
+    generations.finish(&tag_clone_2);
+    // Unbind listener for cancel events before thread ends
+    window_clone.unlisten(event_handle);
+    result
+}
+
+/// Calling the assistant of a conversation backed by an assistant thread.
+/// Runs are polled until they end, so the reply is sent in one piece.
+async fn call_bot_assistant(
+    tag: String,
+    conversation_id: i32,
+    before_message_id: Option<i32>,
+    window: tauri::Window,
+    app_handle: tauri::AppHandle,
+    generations: &GenerationManager,
+) {
+    let window_clone = window.clone();
+    let window_clone_2 = window.clone();
+    let tag_clone = tag.clone();
+    let task_handle = tokio::spawn(async move {
+        let repo = app_handle.state::<Repository>();
+        match assistants::run_assistant(&repo, conversation_id, before_message_id).await {
+            Ok(reply) => {
+                emit_stream_start(&tag, &window);
+                let reply_text = reply.message.clone();
+                emit_stream_data(&tag, &window, reply);
+                emit_stream_done(&tag, &window);
+                notifications::notify_reply_finished(&window, conversation_id, &reply_text).await;
+            }
+            Err(msg) => {
+                log::error!("call_bot_assistant: {}", &msg);
+                emit_stream_error(&tag, &window, &CommandError::from_provider(msg));
+            }
+        }
+    });
+    let abort_handle = task_handle.abort_handle();
+    generations.register(&tag_clone, conversation_id, abort_handle.clone());
+    let tag_clone_2 = tag_clone.clone();
+    // Bind listener for cancel events
+    let event_handle = window_clone.listen(generation::stop_event(conversation_id), move |_| {
+        log::info!("Assistant call stopped!");
+        // Dropping the task cancels the run on the thread too
+        abort_handle.abort();
+        emit_stream_stopped(&tag_clone, &window_clone_2);
+    });
     // Run task
     let _ = task_handle.await;
+    generations.finish(&tag_clone_2);
     // Unbind listener for cancel events before thread ends
     window_clone.unlisten(event_handle);
 }
 
-/// Calling chat bot in streaming mode
+/// Calling chat bot in streaming mode, returning the reply once it is complete
 async fn call_bot_stream(
     tag: String,
+    conversation_id: i32,
     window: tauri::Window,
+    generations: &GenerationManager,
     messages: Vec<MessageDTO>,
     options: GenericOptions,
     config: GenericConfig,
     proxy_setting: Option<ProxySetting>,
-    max_token_setting: u32,
-) {
+    timeouts: TimeoutSetting,
+    global_settings: GlobalSettings,
+    max_continuations: u32,
+    max_attempts: u32,
+    mut privacy_filter: Option<PrivacyFilter>,
+    fallbacks: Vec<FallbackModel>,
+) -> Option<String> {
     let log_tag = "call_bot_stream";
     let window_clone = window.clone();
     let window_clone_2 = window.clone();
@@ -619,67 +2697,204 @@ async fn call_bot_stream(
     let task_handle = tokio::spawn(async move {
         // handle stream response
         log::info!("call_bot_stream: thread start");
-        let init_client_result = LLMClient::new(config, proxy_setting);
+        let init_client_result = LLMClient::new(config, proxy_setting, timeouts);
         match init_client_result {
             Ok(client) => {
-                let stream_result = client
-                    .chat_stream(
-                        messages,
-                        options,
-                        GlobalSettings {
-                            max_tokens: max_token_setting,
-                        },
-                    )
+                let on_fallback =
+                    |fallback: &ModelFallback| emit_stream_fallback(&tag, &window, fallback);
+                let on_retry = |retry: &RequestRetry| emit_stream_retrying(&tag, &window, retry);
+                let target = ChatTarget {
+                    client,
+                    options,
+                    global_settings,
+                };
+                let mut meter = StreamMeter::start();
+                let stream_result =
+                    fallback::with_fallback(target, &fallbacks, on_fallback, |target| {
+                        let messages = messages.clone();
+                        async move {
+                            retry::with_retry(max_attempts, on_retry, || {
+                                fallback::start_stream(target.clone(), messages.clone())
+                            })
+                            .await
+                        }
+                    })
                     .await;
-                                match stream_result {
-                    Ok(mut stream) => {
+                match stream_result {
+                    Ok((target, mut stream)) => {
+                        // The rest of the reply is requested from the model which replied,
+                        // which may be a fallback
+                        let ChatTarget {
+                            client,
+                            options,
+                            global_settings,
+                        } = target;
                         // start receiving in frontend
                         emit_stream_start(&tag, &window);
                         trace(log_tag, "Streaming started!");
-                        while let Some(result) = stream.next().await {
-                            trace(log_tag, "Streaming data...");
-                            match result {
-                                Ok(reply) => {
-                                    emit_stream_data(&tag, &window, reply);
+                        let mut reply_text = String::new();
+                        // The reply as written by the model, before restoring redacted data
+                        let mut model_text = String::new();
+                        let mut continuations = 0;
+                        let mut resumes = 0;
+                        let mut is_failed = false;
+                        loop {
+                            let mut is_truncated = false;
+                            while let Some(result) = stream.next().await {
+                                trace(log_tag, "Streaming data...");
+                                match result {
+                                    Ok(mut reply) => {
+                                        meter.record(&reply);
+                                        is_truncated = is_truncated || reply.truncated;
+                                        model_text.push_str(&reply.message);
+                                        if let Some(filter) = privacy_filter.as_mut() {
+                                            reply.message = filter.restore_chunk(&reply.message);
+                                        }
+                                        reply_text.push_str(&reply.message);
+                                        emit_stream_data(&tag, &window, reply);
+                                    }
+                                    Err(err)
+                                        if resumes < MAX_STREAM_RESUMES
+                                            && resume::is_transient(&err) =>
+                                    {
+                                        // The connection dropped, request the rest of the reply
+                                        // and keep streaming it under the same tag
+                                        resumes += 1;
+                                        let (resume_messages, strategy) = resume::resume_messages(
+                                            &messages,
+                                            &model_text,
+                                            client.supports_prefill(),
+                                        );
+                                        let recovery = StreamRecovery {
+                                            attempt: resumes,
+                                            strategy,
+                                            received_chars: model_text.chars().count(),
+                                            error: err.to_string(),
+                                        };
+                                        log::warn!("Stream dropped, resuming: {:?}", recovery);
+                                        emit_stream_resume(&tag, &window, &recovery);
+                                        let next_result = client
+                                            .chat_stream(
+                                                resume_messages,
+                                                options.clone(),
+                                                global_settings.clone(),
+                                            )
+                                            .await;
+                                        match next_result {
+                                            Ok(next_stream) => stream = next_stream,
+                                            Err(msg) => {
+                                                error(
+                                                    log_tag,
+                                                    &format!("Error resuming stream: {}", &msg),
+                                                );
+                                                emit_stream_error(
+                                                    &tag,
+                                                    &window,
+                                                    &CommandError::from_provider(msg),
+                                                );
+                                                is_failed = true;
+                                                break;
+                                            }
+                                        }
+                                    }
+                                    Err(err) => {
+                                        log::error!("Error during stream: {:?}", err);
+                                        error(log_tag, &format!("Error during stream: {}", err));
+                                        emit_stream_error(
+                                            &tag,
+                                            &window,
+                                            &CommandError::from_provider(err.to_string()),
+                                        );
+                                        is_failed = true;
+                                        break;
+                                    }
                                 }
-                                Err(err) => {
-                                    let err_reply = format!("[[ERROR]]{}", err);
-                                    emit_stream_error(&tag, &window, &err_reply);
-                                    log::error!("Error during stream: {:?}", err);
-                                    error(log_tag, &format!("Error during stream: {}", &err_reply));
+                            }
+                            if is_failed || !is_truncated || continuations >= max_continuations {
+                                break;
+                            }
+                            // The reply was cut off by the max tokens limit, request the rest
+                            // and keep streaming it under the same tag
+                            continuations += 1;
+                            emit_stream_continue(&tag, &window, continuations);
+                            let next_result = client
+                                .chat_stream(
+                                    continuation_messages(&messages, &model_text),
+                                    options.clone(),
+                                    global_settings.clone(),
+                                )
+                                .await;
+                            match next_result {
+                                Ok(next_stream) => stream = next_stream,
+                                Err(msg) => {
+                                    error(log_tag, &format!("Error continuing stream: {}", &msg));
+                                    emit_stream_error(
+                                        &tag,
+                                        &window,
+                                        &CommandError::from_provider(msg),
+                                    );
+                                    is_failed = true;
                                     break;
                                 }
                             }
                         }
                         trace(log_tag, "Streaming finished!");
+                        // Send text held back by the filter as a last chunk
+                        let rest = privacy_filter
+                            .as_mut()
+                            .map(|filter| filter.flush())
+                            .unwrap_or_default();
+                        if !is_failed && !rest.is_empty() {
+                            reply_text.push_str(&rest);
+                            let reply = BotReply {
+                                message: rest,
+                                ..Default::default()
+                            };
+                            emit_stream_data(&tag, &window, reply);
+                        }
                         // stop receiving in frontend
-                        emit_stream_done(&tag, &window);
+                        if is_failed {
+                            emit_stream_done(&tag, &window);
+                            return None;
+                        }
+                        match meter.finish() {
+                            Some(metrics) => emit_stream_done_with_metrics(&tag, &window, &metrics),
+                            None => emit_stream_done(&tag, &window),
+                        }
+                        notifications::notify_reply_finished(&window, conversation_id, &reply_text)
+                            .await;
+                        Some(reply_text)
                     }
                     Err(msg) => {
-                        let err_reply = format!("[[ERROR]]{}", msg);
-                        emit_stream_error(&tag, &window, &err_reply);
-                        error(log_tag, &format!("Error starting stream: {}", &err_reply));
+                        error(log_tag, &format!("Error starting stream: {}", &msg));
+                        emit_stream_error(&tag, &window, &CommandError::from_provider(msg));
+                        None
                     }
                 }
             }
             Err(msg) => {
-                emit_stream_error(&tag, &window, &msg);
                 log::error!("call_bot_stream: {}", &msg);
+                emit_stream_error(&tag, &window, &ApiError { message: msg });
+                None
             }
         }
     });
     let abort_handle = task_handle.abort_handle();
+    generations.register(&tag_clone, conversation_id, abort_handle.clone());
+    let tag_clone_2 = tag_clone.clone();
     // Bind listener for cancel events
-    let event_handle = window_clone.listen("stop-bot", move |_| {
+    let event_handle = window_clone.listen(generation::stop_event(conversation_id), move |_| {
         trace(log_tag, "call stopped");
         abort_handle.abort();
         emit_stream_stopped(&tag_clone, &window_clone_2);
     });
-    // Run task
-    let _ = task_handle.await;
+    // Run task, which has no result when stopped
+    let result = task_handle.await.ok().flatten();
+    generations.finish(&tag_clone_2);
     // Unbind listener for cancel events before thread ends
     window_clone.unlisten(event_handle);
     trace(log_tag, "exit");
+    result
 }
 /***** Functions for calling model API END *****/
 
@@ -718,12 +2933,62 @@ fn emit_stream_stopped(tag: &str, window: &tauri::Window) {
     }
 }
 
-fn emit_stream_error(tag: &str, window: &tauri::Window, err_message: &String) {
-    match window.emit(tag, format!("[[ERROR]]{}", err_message)) {
+// The frontend keeps the metrics in the metadata of the reply, under "throughput"
+fn emit_stream_done_with_metrics(tag: &str, window: &tauri::Window, metrics: &StreamMetrics) {
+    let data_str = serde_json::to_string(metrics).unwrap_or_default();
+    log::info!("emit_stream_done: {} {}", tag, data_str);
+    if let Err(err) = window.emit(tag, format!("[[DONE]]{}", data_str)) {
+        log::error!("Error when sending event: {}", err);
+        // simple retry, the reply is complete without its metrics
+        let _ = window.emit(tag, "[[DONE]]");
+    }
+}
+
+// Tell the frontend that the reply was cut off and its continuation number `continuation` is requested
+fn emit_stream_continue(tag: &str, window: &tauri::Window, continuation: u32) {
+    log::info!("emit_stream_continue: {} #{}", tag, continuation);
+    if let Err(err) = window.emit(tag, format!("[[CONTINUE]]{}", continuation)) {
+        log::error!("Error when sending event: {}", err);
+    }
+}
+
+// The frontend keeps the recoveries in the metadata of the reply, under "recoveries"
+fn emit_stream_resume(tag: &str, window: &tauri::Window, recovery: &StreamRecovery) {
+    let data_str = serde_json::to_string(recovery).unwrap_or_default();
+    log::info!("emit_stream_resume: {} {}", tag, data_str);
+    if let Err(err) = window.emit(tag, format!("[[RESUMED]]{}", data_str)) {
+        log::error!("Error when sending event: {}", err);
+    }
+}
+
+// The frontend keeps the model which replied in the metadata of the reply, under "fallback"
+fn emit_stream_fallback(tag: &str, window: &tauri::Window, fallback: &ModelFallback) {
+    let data_str = serde_json::to_string(fallback).unwrap_or_default();
+    log::info!("emit_stream_fallback: {} {}", tag, data_str);
+    if let Err(err) = window.emit(tag, format!("[[FALLBACK]]{}", data_str)) {
+        log::error!("Error when sending event: {}", err);
+    }
+}
+
+fn emit_stream_retrying(tag: &str, window: &tauri::Window, retry: &RequestRetry) {
+    let data_str = serde_json::to_string(retry).unwrap_or_default();
+    log::info!("emit_stream_retrying: {} {}", tag, data_str);
+    if let Err(err) = window.emit(tag, format!("[[RETRYING]]{}", data_str)) {
+        log::error!("Error when sending event: {}", err);
+    }
+}
+
+/// Send the error of a bot call as a coded error, so the frontend can tell its kind
+fn emit_stream_error(tag: &str, window: &tauri::Window, error: &CommandError) {
+    let payload = format!(
+        "[[ERROR]]{}",
+        serde_json::to_string(error).unwrap_or(error.to_string())
+    );
+    match window.emit(tag, &payload) {
         Err(err) => {
             log::error!("Error when sending event: {}", err);
             // retry
-            let _ = window.emit(tag, err_message);
+            let _ = window.emit(tag, payload);
         }
         _ => {}
     }
@@ -0,0 +1,216 @@
+use std::{
+    backtrace::Backtrace,
+    fs, panic,
+    path::{Path, PathBuf},
+};
+
+use entity::entities::settings::{SETTING_CRASH_REPORTS_ENDPOINT, SETTING_CRASH_REPORTS_UPLOAD};
+use serde::{Deserialize, Serialize};
+use sysinfo::System;
+use tauri::{App, Manager};
+
+use crate::services::db::Repository;
+
+const CRASH_DIR: &str = "crashes";
+const REPORT_EXTENSION: &str = "json";
+
+/// A crash report as stored on disk.
+/// Reports never include the panic message, which could quote conversation content;
+/// only the location and stack of the crash and a description of the environment are kept.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashReport {
+    #[serde(default)]
+    pub id: String,
+    pub created_at: String,
+    pub app_version: String,
+    pub os: String,
+    pub os_version: String,
+    pub arch: String,
+    pub thread: String,
+    pub location: Option<String>,
+    pub backtrace: String,
+    #[serde(default)]
+    pub uploaded: bool,
+}
+
+// Install a panic hook that saves a crash report before the default hook runs,
+// then upload pending reports if the user opted in
+pub fn init_crash_handler(app: &App) -> Result<(), String> {
+    let crash_dir = get_crash_dir(app)?;
+    fs::create_dir_all(&crash_dir)
+        .map_err(|err| format!("Failed to create crash report directory: {}", err))?;
+    let app_version = app.package_info().version.to_string();
+    let default_hook = panic::take_hook();
+    let hook_dir = crash_dir.clone();
+    panic::set_hook(Box::new(move |info| {
+        let location = info
+            .location()
+            .map(|location| format!("{}:{}", location.file(), location.line()));
+        let report = build_report(location, &app_version);
+        if let Err(err) = write_report(&hook_dir, &report) {
+            log::error!("Failed to write crash report: {}", err);
+        }
+        default_hook(info);
+    }));
+
+    let handle = app.handle().clone();
+    tauri::async_runtime::spawn(async move {
+        let repo = handle.state::<Repository>();
+        if let Err(err) = upload_reports(&repo, &crash_dir).await {
+            log::warn!("Failed to upload crash reports: {}", err);
+        }
+    });
+    Ok(())
+}
+
+pub fn get_crash_dir<M: Manager<tauri::Wry>>(manager: &M) -> Result<PathBuf, String> {
+    let mut crash_dir = manager
+        .path()
+        .app_data_dir()
+        .map_err(|err| format!("App data path doesn't exist: {}", err))?;
+    crash_dir.push(CRASH_DIR);
+    Ok(crash_dir)
+}
+
+fn build_report(location: Option<String>, app_version: &str) -> CrashReport {
+    let backtrace = Backtrace::force_capture().to_string();
+    CrashReport {
+        id: String::new(),
+        created_at: chrono::Local::now().to_rfc3339(),
+        app_version: app_version.to_string(),
+        os: System::name().unwrap_or_default(),
+        os_version: System::os_version().unwrap_or_default(),
+        arch: std::env::consts::ARCH.to_string(),
+        thread: std::thread::current()
+            .name()
+            .unwrap_or("<unnamed>")
+            .to_string(),
+        location,
+        backtrace: sanitize(&backtrace),
+        uploaded: false,
+    }
+}
+
+// Hide the user's home directory, which usually contains their name
+fn sanitize(text: &str) -> String {
+    match dirs::home_dir().and_then(|home| home.to_str().map(|home| home.to_string())) {
+        Some(home) if !home.is_empty() => text.replace(&home, "~"),
+        _ => text.to_string(),
+    }
+}
+
+fn write_report(crash_dir: &Path, report: &CrashReport) -> Result<(), String> {
+    let file_name = format!(
+        "crash-{}.{}",
+        chrono::Local::now().format("%Y%m%d-%H%M%S%.3f"),
+        REPORT_EXTENSION
+    );
+    save_report(&crash_dir.join(file_name), report)
+}
+
+fn save_report(path: &Path, report: &CrashReport) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(report).map_err(|err| err.to_string())?;
+    fs::write(path, content).map_err(|err| err.to_string())
+}
+
+// List the saved crash reports, newest first
+pub fn list_reports(crash_dir: &Path) -> Result<Vec<CrashReport>, String> {
+    if !crash_dir.exists() {
+        return Ok(vec![]);
+    }
+    let entries = fs::read_dir(crash_dir)
+        .map_err(|err| format!("Failed to read crash report directory: {}", err))?;
+    let mut reports: Vec<CrashReport> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .map_or(false, |ext| ext == REPORT_EXTENSION)
+        })
+        .filter_map(|path| {
+            let content = fs::read_to_string(&path).ok()?;
+            let mut report = serde_json::from_str::<CrashReport>(&content).ok()?;
+            report.id = path.file_name()?.to_str()?.to_string();
+            Some(report)
+        })
+        .collect();
+    reports.sort_by(|a, b| b.id.cmp(&a.id));
+    Ok(reports)
+}
+
+// Delete the given reports, or all of them when no ids are given
+pub fn delete_reports(crash_dir: &Path, ids: Option<Vec<String>>) -> Result<usize, String> {
+    let ids = match ids {
+        Some(ids) => ids,
+        None => list_reports(crash_dir)?
+            .into_iter()
+            .map(|report| report.id)
+            .collect(),
+    };
+    let mut deleted = 0;
+    for id in ids {
+        // Ids are file names, never paths
+        if id.contains('/') || id.contains('\\') || id.contains("..") {
+            return Err(format!("Invalid crash report id: {}", id));
+        }
+        let path = crash_dir.join(&id);
+        if path.exists() {
+            fs::remove_file(&path)
+                .map_err(|err| format!("Failed to delete crash report {}: {}", id, err))?;
+            deleted += 1;
+        }
+    }
+    Ok(deleted)
+}
+
+// Send reports that haven't been uploaded yet, only if the user opted in and an endpoint is set
+async fn upload_reports(repo: &Repository, crash_dir: &Path) -> Result<(), String> {
+    let opted_in = repo
+        .get_setting(SETTING_CRASH_REPORTS_UPLOAD)
+        .await
+        .map(|setting| setting.value == "true")
+        .unwrap_or(false);
+    if !opted_in {
+        return Ok(());
+    }
+    let endpoint = match repo.get_setting(SETTING_CRASH_REPORTS_ENDPOINT).await {
+        Some(setting) if !setting.value.is_empty() => setting.value,
+        _ => return Ok(()),
+    };
+    let client = reqwest::Client::new();
+    for mut report in list_reports(crash_dir)?
+        .into_iter()
+        .filter(|report| !report.uploaded)
+    {
+        let body = serde_json::to_string(&report).map_err(|err| err.to_string())?;
+        client
+            .post(&endpoint)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .map_err(|err| err.to_string())?;
+        report.uploaded = true;
+        save_report(&crash_dir.join(&report.id), &report)?;
+        log::info!("Crash report {} uploaded", report.id);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delete_rejects_paths() {
+        let crash_dir = std::env::temp_dir().join("kaas-crash-test");
+        assert!(delete_reports(&crash_dir, Some(vec!["../database.sqlite".to_string()])).is_err());
+        assert!(delete_reports(&crash_dir, Some(vec!["a/b.json".to_string()])).is_err());
+        assert_eq!(
+            Ok(0),
+            delete_reports(&crash_dir, Some(vec!["crash-1.json".to_string()]))
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use serde::Serialize;
+use tauri::{App, AppHandle, Emitter, Manager, Url};
+use tauri_plugin_deep_link::DeepLinkExt;
+
+use crate::{app_lock::AppLock, services::db::Repository, tray::show_main_window};
+
+pub const DEEP_LINK_SCHEME: &str = "kaas";
+pub const EVENT_DEEP_LINK_CONVERSATION: &str = "deep-link-conversation";
+
+/// Actions that can be triggered through a kaas:// URL
+#[derive(Debug, PartialEq)]
+pub enum DeepLinkAction {
+    /// kaas://new?prompt=...&model=...
+    NewConversation {
+        prompt: String,
+        model: Option<String>,
+    },
+}
+
+/// Payload sent to the frontend once a conversation has been created from a deep link
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeepLinkConversation {
+    pub conversation_id: i32,
+    pub start_generation: bool,
+}
+
+// Listen for kaas:// URLs, including the one the app may have been launched with
+pub fn init_deep_link(app: &App) -> Result<(), String> {
+    // Schemes are only registered by the installer on these platforms,
+    // so register at runtime to make deep links work in development builds too
+    #[cfg(any(target_os = "linux", all(debug_assertions, windows)))]
+    app.deep_link()
+        .register_all()
+        .map_err(|err| format!("Failed to register deep link schemes: {}", err))?;
+
+    let handle = app.handle().clone();
+    app.deep_link().on_open_url(move |event| {
+        for url in event.urls() {
+            handle_url(handle.clone(), url);
+        }
+    });
+    if let Ok(Some(urls)) = app.deep_link().get_current() {
+        for url in urls {
+            handle_url(app.handle().clone(), url);
+        }
+    }
+    Ok(())
+}
+
+// Execute the action of a deep link in the background
+pub fn handle_url(app: AppHandle, url: Url) {
+    log::info!("Deep link received: {}", url);
+    match parse_url(&url) {
+        Ok(action) => {
+            tauri::async_runtime::spawn(async move {
+                if let Err(err) = execute(&app, action).await {
+                    log::error!("Failed to handle deep link: {}", err);
+                }
+            });
+        }
+        Err(err) => log::warn!("Ignoring deep link {}: {}", url, err),
+    }
+}
+
+pub fn parse_url(url: &Url) -> Result<DeepLinkAction, String> {
+    if url.scheme() != DEEP_LINK_SCHEME {
+        return Err(format!("Unsupported scheme: {}", url.scheme()));
+    }
+    // kaas://new puts the action in the host, kaas:new in the path
+    let action = url
+        .host_str()
+        .unwrap_or_else(|| url.path())
+        .trim_matches('/');
+    match action {
+        "new" => {
+            let mut prompt = None;
+            let mut model = None;
+            for (key, value) in url.query_pairs() {
+                match key.as_ref() {
+                    "prompt" => prompt = Some(value.to_string()),
+                    "model" => model = Some(value.to_string()),
+                    _ => {}
+                }
+            }
+            let prompt = prompt
+                .filter(|prompt| !prompt.trim().is_empty())
+                .ok_or("Parameter prompt is missing".to_string())?;
+            Ok(DeepLinkAction::NewConversation { prompt, model })
+        }
+        _ => Err(format!("Unknown action: {}", action)),
+    }
+}
+
+async fn execute(app: &AppHandle, action: DeepLinkAction) -> Result<(), String> {
+    if app.state::<AppLock>().is_locked() {
+        show_main_window(app);
+        return Err("App is locked".to_string());
+    }
+    match action {
+        DeepLinkAction::NewConversation { prompt, model } => {
+            let repo = app.state::<Repository>();
+            let model_id = repo.resolve_model(model).await?.id;
+            let conversation = repo
+                .create_conversation_with_message(model_id, prompt)
+                .await?;
+            show_main_window(app);
+            // The frontend owns the reply listener, so it starts the bot call
+            app.emit(
+                EVENT_DEEP_LINK_CONVERSATION,
+                DeepLinkConversation {
+                    conversation_id: conversation.id,
+                    start_generation: true,
+                },
+            )
+            .map_err(|err| format!("Error when sending event: {}", err))?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_new_conversation() {
+        let url = Url::parse("kaas://new?prompt=Hello%20world&model=gpt-4o").unwrap();
+        assert_eq!(
+            DeepLinkAction::NewConversation {
+                prompt: "Hello world".to_string(),
+                model: Some("gpt-4o".to_string()),
+            },
+            parse_url(&url).unwrap()
+        );
+
+        let url = Url::parse("kaas://new/?prompt=Hi").unwrap();
+        assert_eq!(
+            DeepLinkAction::NewConversation {
+                prompt: "Hi".to_string(),
+                model: None,
+            },
+            parse_url(&url).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_urls() {
+        assert!(parse_url(&Url::parse("kaas://new").unwrap()).is_err());
+        assert!(parse_url(&Url::parse("kaas://new?prompt=%20").unwrap()).is_err());
+        assert!(parse_url(&Url::parse("kaas://delete?prompt=Hi").unwrap()).is_err());
+        assert!(parse_url(&Url::parse("https://new?prompt=Hi").unwrap()).is_err());
+    }
+}
@@ -0,0 +1,298 @@
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use entity::entities::{
+    models::{GenericConfig, Model, Providers},
+    settings::{ProxySetting, TimeoutSetting},
+};
+use serde::Serialize;
+use sysinfo::Disks;
+use tokio::{net::TcpStream, time::timeout};
+
+use crate::services::{
+    cache,
+    db::Repository,
+    llm::{
+        client::LLMClient,
+        context::{
+            get_global_proxy_setting, get_proxy_setting, get_timeout_setting, model_timeouts,
+        },
+    },
+};
+
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Free space left on the disk holding the attachments below which they may fail to be saved
+const LOW_DISK_SPACE: u64 = 1024 * 1024 * 1024;
+const CRITICAL_DISK_SPACE: u64 = 100 * 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CheckStatus {
+    Skipped,
+    Ok,
+    Warning,
+    Error,
+}
+
+/// The outcome of one check of the diagnostics
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticCheck {
+    pub name: String,
+    pub status: CheckStatus,
+    pub message: String,
+    pub duration_ms: u64,
+}
+
+/// Health of the database, credentials, network and disk, along with each configured model
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsReport {
+    pub created_at: String,
+    pub app_version: String,
+    /// The worst status of the checks
+    pub status: CheckStatus,
+    pub checks: Vec<DiagnosticCheck>,
+}
+
+/**
+ * Run every check, models being checked concurrently since each of them may wait for
+ * the timeout. Checks never fail the report, their errors are reported instead.
+ */
+pub async fn run_diagnostics(repo: &Repository, app_version: String) -> DiagnosticsReport {
+    let timeout_setting = get_timeout_setting(repo).await;
+    let mut checks = vec![
+        check("Database", check_database(repo)).await,
+        check_keychain(),
+        check("Proxy", check_proxy(get_global_proxy_setting(repo).await)).await,
+        check("Disk space", check_disk_space()).await,
+    ];
+    match repo.list_models().await {
+        Ok(models) => {
+            let mut handles = vec![];
+            for model in models {
+                let proxy_setting = get_proxy_setting(repo, &model.provider).await;
+                handles.push(tauri::async_runtime::spawn(async move {
+                    let name = format!("Model {}", model.alias);
+                    check(&name, check_model(model, proxy_setting, timeout_setting)).await
+                }));
+            }
+            for handle in handles {
+                match handle.await {
+                    Ok(model_check) => checks.push(model_check),
+                    Err(err) => log::error!("Failed to check model: {}", err),
+                }
+            }
+        }
+        Err(message) => checks.push(DiagnosticCheck {
+            name: "Models".to_string(),
+            status: CheckStatus::Error,
+            message,
+            duration_ms: 0,
+        }),
+    }
+    DiagnosticsReport {
+        created_at: chrono::Local::now().to_rfc3339(),
+        app_version,
+        status: worst_status(&checks),
+        checks,
+    }
+}
+
+pub fn worst_status(checks: &[DiagnosticCheck]) -> CheckStatus {
+    checks
+        .iter()
+        .map(|check| check.status)
+        .max()
+        .unwrap_or(CheckStatus::Skipped)
+}
+
+async fn check(
+    name: &str,
+    task: impl Future<Output = Result<(CheckStatus, String), String>>,
+) -> DiagnosticCheck {
+    let now = Instant::now();
+    let (status, message) = match timeout(CHECK_TIMEOUT, task).await {
+        Ok(Ok(outcome)) => outcome,
+        Ok(Err(message)) => (CheckStatus::Error, message),
+        Err(_) => (
+            CheckStatus::Error,
+            format!("No answer after {} seconds", CHECK_TIMEOUT.as_secs()),
+        ),
+    };
+    DiagnosticCheck {
+        name: name.to_string(),
+        status,
+        message,
+        duration_ms: now.elapsed().as_millis() as u64,
+    }
+}
+
+async fn check_database(repo: &Repository) -> Result<(CheckStatus, String), String> {
+    let problems = repo.check_integrity().await?;
+    if problems.is_empty() {
+        Ok((CheckStatus::Ok, "Integrity check passed".to_string()))
+    } else {
+        Ok((CheckStatus::Error, problems.join("\n")))
+    }
+}
+
+// Credentials are kept in the config of each model, in the database checked above
+fn check_keychain() -> DiagnosticCheck {
+    DiagnosticCheck {
+        name: "Keychain".to_string(),
+        status: CheckStatus::Skipped,
+        message: "API keys are stored in the database, the system keychain isn't used".to_string(),
+        duration_ms: 0,
+    }
+}
+
+async fn check_proxy(proxy_setting: Option<ProxySetting>) -> Result<(CheckStatus, String), String> {
+    let setting = match proxy_setting {
+        Some(setting) if setting.on => setting,
+        _ => return Ok((CheckStatus::Skipped, "No proxy is used".to_string())),
+    };
+    let (host, port) = proxy_address(&setting.server)?;
+    TcpStream::connect((host.as_str(), port))
+        .await
+        .map_err(|err| format!("Failed to connect to {}:{}: {}", host, port, err))?;
+    Ok((CheckStatus::Ok, format!("Connected to {}:{}", host, port)))
+}
+
+/// Host and port of a proxy server, the port defaulting to the one of its scheme
+pub fn proxy_address(server: &str) -> Result<(String, u16), String> {
+    let url = reqwest::Url::parse(server)
+        .map_err(|err| format!("Invalid proxy server {}: {}", server, err))?;
+    let host = url
+        .host_str()
+        .ok_or(format!("Proxy server {} has no host", server))?;
+    let port = url.port_or_known_default().unwrap_or(match url.scheme() {
+        "socks5" | "socks5h" => 1080,
+        _ => 80,
+    });
+    Ok((host.to_string(), port))
+}
+
+async fn check_disk_space() -> Result<(CheckStatus, String), String> {
+    let cache_dir = cache::get_cache_dir()?;
+    let disks = Disks::new_with_refreshed_list();
+    let mount_points: Vec<PathBuf> = disks
+        .list()
+        .iter()
+        .map(|disk| disk.mount_point().to_path_buf())
+        .collect();
+    let index = find_disk(&cache_dir, &mount_points)
+        .ok_or(format!("No disk found for {}", cache_dir.to_string_lossy()))?;
+    let available = disks.list()[index].available_space();
+    let message = format!(
+        "{:.2} GB available for attachments",
+        available as f64 / 1024.0 / 1024.0 / 1024.0
+    );
+    Ok((disk_space_status(available), message))
+}
+
+/// Index of the mount point a path is on, the deepest one when they're nested
+pub fn find_disk(path: &Path, mount_points: &[PathBuf]) -> Option<usize> {
+    mount_points
+        .iter()
+        .enumerate()
+        .filter(|(_, mount_point)| path.starts_with(mount_point))
+        .max_by_key(|(_, mount_point)| mount_point.components().count())
+        .map(|(index, _)| index)
+}
+
+pub fn disk_space_status(available: u64) -> CheckStatus {
+    if available < CRITICAL_DISK_SPACE {
+        CheckStatus::Error
+    } else if available < LOW_DISK_SPACE {
+        CheckStatus::Warning
+    } else {
+        CheckStatus::Ok
+    }
+}
+
+async fn check_model(
+    model: Model,
+    proxy_setting: Option<ProxySetting>,
+    timeout_setting: TimeoutSetting,
+) -> Result<(CheckStatus, String), String> {
+    if matches!(Providers::from(&model.provider), Providers::Azure) {
+        return Ok((
+            CheckStatus::Skipped,
+            "Azure doesn't list models to check the connection with".to_string(),
+        ));
+    }
+    let timeouts = model_timeouts(&model, timeout_setting);
+    let config = GenericConfig {
+        provider: model.provider,
+        config: model.config,
+    };
+    let client = LLMClient::new(config, proxy_setting, timeouts)?;
+    let models = client.models().await?;
+    Ok((
+        CheckStatus::Ok,
+        format!("Connected, {} models available", models.len()),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proxy_address() {
+        assert_eq!(
+            ("127.0.0.1".to_string(), 7890),
+            proxy_address("http://127.0.0.1:7890").unwrap()
+        );
+        assert_eq!(
+            ("proxy.local".to_string(), 443),
+            proxy_address("https://proxy.local").unwrap()
+        );
+        assert_eq!(
+            ("proxy.local".to_string(), 1080),
+            proxy_address("socks5://proxy.local").unwrap()
+        );
+        assert!(proxy_address("127.0.0.1:7890").is_err());
+    }
+
+    #[test]
+    fn test_find_disk() {
+        let mount_points = vec![
+            PathBuf::from("/"),
+            PathBuf::from("/home"),
+            PathBuf::from("/home/me/data"),
+        ];
+        assert_eq!(
+            Some(1),
+            find_disk(Path::new("/home/me/app/cache"), &mount_points)
+        );
+        assert_eq!(
+            Some(2),
+            find_disk(Path::new("/home/me/data/cache"), &mount_points)
+        );
+        assert_eq!(Some(0), find_disk(Path::new("/tmp"), &mount_points));
+        assert_eq!(None, find_disk(Path::new("/tmp"), &[]));
+    }
+
+    #[test]
+    fn test_worst_status() {
+        let check = |status| DiagnosticCheck {
+            name: String::new(),
+            status,
+            message: String::new(),
+            duration_ms: 0,
+        };
+        assert_eq!(CheckStatus::Skipped, worst_status(&[]));
+        assert_eq!(
+            CheckStatus::Warning,
+            worst_status(&[check(CheckStatus::Ok), check(CheckStatus::Warning)])
+        );
+        assert_eq!(CheckStatus::Error, disk_space_status(1024));
+        assert_eq!(CheckStatus::Ok, disk_space_status(LOW_DISK_SPACE));
+    }
+}
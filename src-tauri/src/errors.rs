@@ -6,11 +6,162 @@ pub enum CommandError {
     ApiError { message: String },
     #[error("DbError: {message}")]
     DbError { message: String },
-    // #[error("StateError: {message}")]
-    // StateError { message: String },
+    #[error("StateError: {message}")]
+    StateError { message: String },
     #[error("UnknownError: {message}")]
-    #[allow(dead_code)]
     UnknownError { message: String },
+    #[error("LockedError: {message}")]
+    LockedError { message: String },
+    #[error("ConversationLockedError: {message}")]
+    ConversationLockedError {
+        conversation_id: i32,
+        message: String,
+    },
+    #[error("CostConfirmationRequired: {message}")]
+    CostConfirmationRequired {
+        conversation_id: i32,
+        estimated_cost: f64,
+        threshold: f64,
+        message: String,
+    },
+    #[error("DuplicateRequest: {message}")]
+    DuplicateRequest {
+        conversation_id: i32,
+        /// Tag of the original request, whose reply is still coming
+        tag: String,
+        message: String,
+    },
+    #[error("InvalidOption: {message}")]
+    InvalidOption {
+        /// Name of the rejected option, None when the options can't be read at all
+        option: Option<String>,
+        message: String,
+    },
+    #[error("AuthError: {message}")]
+    AuthError { message: String },
+    #[error("RateLimited: {message}")]
+    RateLimited {
+        /// Seconds to wait before trying again, when the provider tells
+        retry_after: Option<u64>,
+        message: String,
+    },
+    #[error("ContextLengthExceeded: {message}")]
+    ContextLengthExceeded { message: String },
+    #[error("Timeout: {message}")]
+    Timeout { message: String },
+    #[error("ProviderError: {message}")]
+    ProviderError {
+        status: u16,
+        body: String,
+        message: String,
+    },
+}
+
+/// Markers of errors of the different kinds in the messages of the providers, lowercase
+const AUTH_MARKERS: &[&str] = &[
+    "invalid_api_key",
+    "incorrect api key",
+    "invalid api key",
+    "invalid x-api-key",
+    "authentication_error",
+    "permission_denied",
+    "unauthorized",
+];
+const RATE_LIMIT_MARKERS: &[&str] = &[
+    "rate_limit",
+    "rate limit",
+    "too many requests",
+    "resource_exhausted",
+];
+const CONTEXT_LENGTH_MARKERS: &[&str] = &[
+    "context_length_exceeded",
+    "maximum context length",
+    "prompt is too long",
+    "context window",
+    "input is too long",
+];
+const TIMEOUT_MARKERS: &[&str] = &["timed out", "timeout", "deadline exceeded"];
+
+impl CommandError {
+    /// Classify an error of a provider. The clients only keep the text of errors, so
+    /// the kind is told from the status code and the wording of the message. Errors
+    /// that can't be told apart, like unreachable hosts, stay ApiErrors.
+    pub fn from_provider(message: String) -> Self {
+        let text = message.to_lowercase();
+        let status = status_code(&message);
+        let has_marker = |markers: &[&str]| markers.iter().any(|marker| text.contains(marker));
+        if status == Some(408) || has_marker(TIMEOUT_MARKERS) {
+            CommandError::Timeout { message }
+        } else if matches!(status, Some(401) | Some(403)) || has_marker(AUTH_MARKERS) {
+            CommandError::AuthError { message }
+        } else if status == Some(429) || has_marker(RATE_LIMIT_MARKERS) {
+            CommandError::RateLimited {
+                retry_after: retry_after(&text),
+                message,
+            }
+        } else if has_marker(CONTEXT_LENGTH_MARKERS) {
+            CommandError::ContextLengthExceeded { message }
+        } else if let Some(status) = status {
+            let body = message
+                .split_once(&status.to_string())
+                .map_or(message.as_str(), |(_, body)| body)
+                .trim_start_matches(|c: char| !c.is_alphanumeric() && c != '{')
+                .to_string();
+            CommandError::ProviderError {
+                status,
+                body,
+                message,
+            }
+        } else {
+            CommandError::ApiError { message }
+        }
+    }
+}
+
+/// The HTTP status in an error message, like "Invalid status code: 429 Too Many Requests"
+/// or "HTTP status client error (401 Unauthorized)"
+fn status_code(message: &str) -> Option<u16> {
+    [
+        "status code: ",
+        "status code ",
+        "status: ",
+        "status client error (",
+        "status server error (",
+    ]
+    .iter()
+    .find_map(|prefix| {
+        let start = message.find(prefix)? + prefix.len();
+        let digits = message[start..]
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .collect::<String>();
+        digits.parse::<u16>().ok().filter(|status| *status >= 400)
+    })
+}
+
+/// The wait asked by a rate limit error, like "Please try again in 20s" or
+/// "retry after 3 seconds", rounded up to whole seconds
+fn retry_after(text: &str) -> Option<u64> {
+    ["try again in ", "retry after ", "retry-after: "]
+        .iter()
+        .find_map(|prefix| {
+            let start = text.find(prefix)? + prefix.len();
+            let rest = &text[start..];
+            let number = rest
+                .chars()
+                .take_while(|c| c.is_ascii_digit() || *c == '.')
+                .collect::<String>();
+            let value = number.parse::<f64>().ok()?;
+            let unit = rest[number.len()..].trim_start();
+            let seconds = if unit.starts_with("ms") {
+                value / 1000.0
+            } else if unit.starts_with('m') {
+                value * 60.0
+            } else {
+                value
+            };
+            Some(seconds.ceil() as u64)
+        })
 }
 
 impl Serialize for CommandError {
@@ -18,7 +169,7 @@ impl Serialize for CommandError {
     where
         S: Serializer,
     {
-        let mut sv = serializer.serialize_map(Some(2))?;
+        let mut sv = serializer.serialize_map(None)?;
         match *self {
             CommandError::ApiError { message: ref msg } => {
                 sv.serialize_entry("type", "ApiError")?;
@@ -28,15 +179,167 @@ impl Serialize for CommandError {
                 sv.serialize_entry("type", "DbError")?;
                 sv.serialize_entry("message", msg)?;
             }
-            // CommandError::StateError { message: ref msg } => {
-            //     sv.serialize_entry("type", "StateError")?;
-            //     sv.serialize_entry("message", msg)?;
-            // }
+            CommandError::StateError { message: ref msg } => {
+                sv.serialize_entry("type", "StateError")?;
+                sv.serialize_entry("message", msg)?;
+            }
             CommandError::UnknownError { message: ref msg } => {
                 sv.serialize_entry("type", "UnknownError")?;
                 sv.serialize_entry("message", msg)?;
             }
+            CommandError::LockedError { message: ref msg } => {
+                sv.serialize_entry("type", "LockedError")?;
+                sv.serialize_entry("message", msg)?;
+            }
+            CommandError::ConversationLockedError {
+                conversation_id,
+                message: ref msg,
+            } => {
+                sv.serialize_entry("type", "ConversationLockedError")?;
+                sv.serialize_entry("message", msg)?;
+                sv.serialize_entry("conversationId", &conversation_id)?;
+            }
+            CommandError::CostConfirmationRequired {
+                conversation_id,
+                estimated_cost,
+                threshold,
+                message: ref msg,
+            } => {
+                sv.serialize_entry("type", "CostConfirmationRequired")?;
+                sv.serialize_entry("message", msg)?;
+                sv.serialize_entry("conversationId", &conversation_id)?;
+                sv.serialize_entry("estimatedCost", &estimated_cost)?;
+                sv.serialize_entry("threshold", &threshold)?;
+            }
+            CommandError::DuplicateRequest {
+                conversation_id,
+                ref tag,
+                message: ref msg,
+            } => {
+                sv.serialize_entry("type", "DuplicateRequest")?;
+                sv.serialize_entry("message", msg)?;
+                sv.serialize_entry("conversationId", &conversation_id)?;
+                sv.serialize_entry("tag", tag)?;
+            }
+            CommandError::InvalidOption {
+                ref option,
+                message: ref msg,
+            } => {
+                sv.serialize_entry("type", "InvalidOption")?;
+                sv.serialize_entry("message", msg)?;
+                sv.serialize_entry("option", option)?;
+            }
+            CommandError::AuthError { message: ref msg } => {
+                sv.serialize_entry("type", "AuthError")?;
+                sv.serialize_entry("message", msg)?;
+            }
+            CommandError::RateLimited {
+                retry_after,
+                message: ref msg,
+            } => {
+                sv.serialize_entry("type", "RateLimited")?;
+                sv.serialize_entry("message", msg)?;
+                sv.serialize_entry("retryAfter", &retry_after)?;
+            }
+            CommandError::ContextLengthExceeded { message: ref msg } => {
+                sv.serialize_entry("type", "ContextLengthExceeded")?;
+                sv.serialize_entry("message", msg)?;
+            }
+            CommandError::Timeout { message: ref msg } => {
+                sv.serialize_entry("type", "Timeout")?;
+                sv.serialize_entry("message", msg)?;
+            }
+            CommandError::ProviderError {
+                status,
+                ref body,
+                message: ref msg,
+            } => {
+                sv.serialize_entry("type", "ProviderError")?;
+                sv.serialize_entry("message", msg)?;
+                sv.serialize_entry("status", &status)?;
+                sv.serialize_entry("body", body)?;
+            }
         }
         sv.end()
     }
 }
+
+/// Errors raised while bringing the database schema up to date on startup
+#[derive(Debug, thiserror::Error)]
+pub enum MigrationError {
+    #[error("Failed to back up database before migrating: {message}")]
+    BackupFailed { message: String },
+    #[error("Failed to read pending migrations: {message}")]
+    PendingUnknown { message: String },
+    #[error("Migration {migration} failed: {message} (database restored: {restored})")]
+    MigrationFailed {
+        migration: String,
+        message: String,
+        restored: bool,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_provider() {
+        assert!(matches!(
+            CommandError::from_provider(
+                "Failed to get chat completion response: Incorrect API key provided".to_string()
+            ),
+            CommandError::AuthError { .. }
+        ));
+        assert!(matches!(
+            CommandError::from_provider(
+                "Rate limit reached for gpt-4o. Please try again in 1.5s.".to_string()
+            ),
+            CommandError::RateLimited {
+                retry_after: Some(2),
+                ..
+            }
+        ));
+        assert!(matches!(
+            CommandError::from_provider(
+                "This model's maximum context length is 8192 tokens".to_string()
+            ),
+            CommandError::ContextLengthExceeded { .. }
+        ));
+        assert!(matches!(
+            CommandError::from_provider("http error: operation timed out".to_string()),
+            CommandError::Timeout { .. }
+        ));
+        match CommandError::from_provider(
+            "Error creating stream: Invalid status code: 503 Service Unavailable".to_string(),
+        ) {
+            CommandError::ProviderError { status, body, .. } => {
+                assert_eq!(503, status);
+                assert_eq!("Service Unavailable", body);
+            }
+            err => panic!("Unexpected error: {:?}", err),
+        }
+        match CommandError::from_provider(
+            "Bedrock returned status code: 500, Internal server error".to_string(),
+        ) {
+            CommandError::ProviderError { status, body, .. } => {
+                assert_eq!(500, status);
+                assert_eq!("Internal server error", body);
+            }
+            err => panic!("Unexpected error: {:?}", err),
+        }
+        assert!(matches!(
+            CommandError::from_provider("http error: connection refused".to_string()),
+            CommandError::ApiError { .. }
+        ));
+    }
+
+    #[test]
+    fn test_retry_after() {
+        assert_eq!(Some(20), retry_after("please try again in 20s."));
+        assert_eq!(Some(1), retry_after("please try again in 120ms."));
+        assert_eq!(Some(360), retry_after("please try again in 6m0s."));
+        assert_eq!(Some(3), retry_after("retry after 3 seconds"));
+        assert_eq!(None, retry_after("slow down"));
+    }
+}
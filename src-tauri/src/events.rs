@@ -0,0 +1,45 @@
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::core::handle::Handle;
+
+/// A message was stored, with the message as payload
+pub const EVENT_MESSAGE_CREATED: &str = "message-created";
+/// The messages of a conversation were seen, with a `ConversationRead` as payload
+pub const EVENT_CONVERSATION_READ: &str = "conversation-read";
+/// A conversation was titled after its first reply, with a `ConversationTitled` as payload
+pub const EVENT_CONVERSATION_TITLED: &str = "conversation-titled";
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversationRead {
+    pub conversation_id: i32,
+    pub read_at: DateTime<Local>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversationTitled {
+    pub conversation_id: i32,
+    pub subject: String,
+}
+
+/// Send an event to every window, so all views of the same data stay consistent
+/// without refetching it
+pub fn broadcast<S: Serialize + Clone>(event: &str, payload: S) {
+    if let Some(app_handle) = app_handle() {
+        if let Err(err) = app_handle.emit(event, payload) {
+            log::error!("Error when sending event: {}", err);
+        }
+    }
+}
+
+// The handle of the app, once it is set up
+fn app_handle() -> Option<AppHandle> {
+    Handle::global()
+        .app_handle
+        .lock()
+        .expect("Failed to lock app handle mutex")
+        .clone()
+}
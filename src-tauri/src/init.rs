@@ -1,9 +1,13 @@
 use std::fs;
+use std::path::{Path, PathBuf};
 
 use crate::core::handle::Handle;
+use crate::errors::MigrationError;
+use crate::services::bootstrap;
 use crate::services::db::Builder as RepoBuilder;
 use crate::services::db::Repository;
 use crate::utils::convert_locale_region_to_script;
+use crate::workspaces;
 use entity::entities::settings::Model as Setting;
 use entity::entities::settings::SETTING_DISPLAY_LANGUAGE;
 use tauri::{App, Manager};
@@ -17,6 +21,8 @@ pub fn init(app: &App) -> Result<(), String> {
     init_cache_dir(app)?;
     // Init settings
     init_settings(app)?;
+    // Seed the defaults of a new workspace
+    init_defaults(app)?;
 
     Ok(())
 }
@@ -53,22 +59,91 @@ fn init_db(app: &App) -> Result<(), String> {
     }
 
     log::info!("App data path: {}", &app_data_dir_str);
-    // Init repo
-    let repo = RepoBuilder::default()
-        .set_db_url(get_sqlite_path(&app_data_dir_str))
-        .build()?;
-    // Run migrations
-    repo.migrate()?;
+    // Each workspace has its own database
+    let workspace_dir = workspaces::active_workspace_dir(&_app_data_dir);
+    fs::create_dir_all(&workspace_dir)
+        .map_err(|err| format!("Failed to create workspace directory: {}", err))?;
+    let workspace_dir_str = workspace_dir
+        .to_str()
+        .expect("Workspace path is not a valid string!")
+        .to_string();
+    log::info!("Workspace path: {}", &workspace_dir_str);
+    // Init repo & run migrations
+    let repo = open_repository(&workspace_dir_str)?;
     // Manage repo as a Tauri state
     app.handle().manage(repo);
 
     Ok(())
 }
 
+// Open the database in the app data directory and bring its schema up to date
+pub fn open_repository(app_data_dir: &str) -> Result<Repository, String> {
+    let db_path = get_sqlite_path(app_data_dir);
+    let repo = RepoBuilder::default().set_db_url(db_path.clone()).build()?;
+    migrate_with_backup(repo, &db_path)
+}
+
+// Run pending migrations with a backup of the database taken beforehand.
+// If any migration fails, the backup is restored so the user's history stays intact.
+fn migrate_with_backup(repo: Repository, db_path: &str) -> Result<Repository, String> {
+    let pending = repo.pending_migrations().map_err(|err| err.to_string())?;
+    if pending.is_empty() {
+        log::info!("No pending migrations");
+        return Ok(repo);
+    }
+    log::info!("Pending migrations: {:?}", pending);
+    let backup_path = get_backup_path(db_path);
+    repo.backup_to(&backup_path)
+        .map_err(|message| MigrationError::BackupFailed { message }.to_string())?;
+    match repo.migrate() {
+        Ok(()) => Ok(repo),
+        Err(MigrationError::MigrationFailed {
+            migration, message, ..
+        }) => {
+            // Release the database file before restoring it
+            repo.close();
+            let restored = match restore_database(db_path, &backup_path) {
+                Ok(()) => true,
+                Err(err) => {
+                    log::error!("Failed to restore database: {}", err);
+                    false
+                }
+            };
+            let err = MigrationError::MigrationFailed {
+                migration,
+                message,
+                restored,
+            };
+            log::error!("{}", err);
+            Err(err.to_string())
+        }
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+// Replace the database file with its backup, dropping any leftover WAL files
+fn restore_database(db_path: &str, backup_path: &Path) -> Result<(), String> {
+    for suffix in ["-wal", "-shm"] {
+        let sidecar = format!("{}{}", db_path, suffix);
+        if Path::new(&sidecar).exists() {
+            fs::remove_file(&sidecar)
+                .map_err(|err| format!("Failed to remove {}: {}", sidecar, err))?;
+        }
+    }
+    fs::copy(backup_path, db_path)
+        .map_err(|err| format!("Failed to copy backup to {}: {}", db_path, err))?;
+    log::info!("Database restored from {}", backup_path.display());
+    Ok(())
+}
+
 // Initialize the cache dir for files such as images, pdfs, etc.
 fn init_cache_dir(app: &App) -> Result<(), String> {
     // get app data path
-    let mut cache_dir = app.path().app_data_dir().expect("App data path does't exist!");
+    let app_data_dir = app
+        .path()
+        .app_data_dir()
+        .expect("App data path does't exist!");
+    let mut cache_dir = workspaces::active_workspace_dir(&app_data_dir);
     cache_dir.push("cache");
     let cache_dir_str = cache_dir
         .to_str()
@@ -118,7 +193,28 @@ fn init_settings(app: &App) -> Result<(), String> {
     Ok(())
 }
 
+// Seed the settings, prompts and example conversation of a new workspace
+fn init_defaults(app: &App) -> Result<(), String> {
+    let handle = app.handle();
+    let db = handle.state::<Repository>();
+    tokio::runtime::Runtime::new()
+        .unwrap()
+        .block_on(async move {
+            if let Err(err) = bootstrap::bootstrap_defaults(&db).await {
+                // The app works without the defaults, they are seeded on the next launch
+                log::error!("Failed to seed defaults: {}", err);
+            }
+        });
+
+    Ok(())
+}
+
 // Get the path where the database file should be located.
 fn get_sqlite_path(app_data_dir: &str) -> String {
     app_data_dir.to_string() + "/database.sqlite"
 }
+
+// Get the path where the pre-migration backup of the database is written.
+fn get_backup_path(db_path: &str) -> PathBuf {
+    PathBuf::from(format!("{}.bak", db_path))
+}
@@ -0,0 +1,196 @@
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use chrono::{Days, Local, Months, NaiveDate, TimeZone};
+use entity::entities::{
+    settings::SETTING_INSIGHTS_ENABLED,
+    stats::{DailyActivity, FeatureUsage, MonthlyActivity},
+};
+use serde::{Deserialize, Serialize};
+use tauri::{ipc::Invoke, App, AppHandle, Manager, Runtime};
+
+use crate::services::{
+    db::Repository,
+    usage::{self, UsageStats},
+};
+
+/// Event recorded with the time the model took to reply
+pub const EVENT_REPLY: &str = "reply";
+
+/// Whether the user opted in to local usage stats. Cached here because
+/// commands are tracked synchronously. Stats never leave the machine.
+pub struct Insights {
+    enabled: AtomicBool,
+}
+
+impl Insights {
+    pub fn new() -> Self {
+        Insights {
+            enabled: AtomicBool::new(false),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+}
+
+/// Usage summary for the "year in review" view
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalInsights {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year: Option<i32>,
+    pub total_events: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub average_reply_latency_ms: Option<f64>,
+    pub features: Vec<FeatureUsage>,
+    pub monthly_activity: Vec<MonthlyActivity>,
+}
+
+/// How far back the activity timeline goes, from today
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ActivityRange {
+    Week,
+    Month,
+    /// The last 12 months, as shown by the heatmap
+    #[default]
+    Year,
+    All,
+}
+
+impl ActivityRange {
+    /// First day of the range ending on a given day, which is part of it
+    pub fn start(&self, today: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            ActivityRange::Week => today.checked_sub_days(Days::new(6)),
+            ActivityRange::Month => today
+                .checked_sub_months(Months::new(1))
+                .and_then(|day| day.checked_add_days(Days::new(1))),
+            ActivityRange::Year => today
+                .checked_sub_months(Months::new(12))
+                .and_then(|day| day.checked_add_days(Days::new(1))),
+            ActivityRange::All => None,
+        }
+    }
+}
+
+pub fn init_insights(app: &App) -> Result<(), String> {
+    let repo = app.state::<Repository>();
+    let enabled = tauri::async_runtime::block_on(async {
+        repo.get_setting(SETTING_INSIGHTS_ENABLED)
+            .await
+            .map(|setting| setting.value == "true")
+            .unwrap_or(false)
+    });
+    app.state::<Insights>().set_enabled(enabled);
+    Ok(())
+}
+
+// Count a command as feature usage. Reads like list_* and get_* run on every
+// page load, so they would drown the features users actually pick.
+pub fn track_invoke<R: Runtime>(invoke: &Invoke<R>) {
+    let command = invoke.message.command();
+    if command.starts_with("list_") || command.starts_with("get_") {
+        return;
+    }
+    record(invoke.message.webview().app_handle(), command, None);
+}
+
+// Store an event in the background, if the user opted in
+pub fn record<R: Runtime>(app: &AppHandle<R>, event: &str, latency: Option<Duration>) {
+    if !app.state::<Insights>().is_enabled() {
+        return;
+    }
+    let app = app.clone();
+    let event = event.to_string();
+    let latency_ms = latency.map(|latency| latency.as_millis() as i64);
+    tauri::async_runtime::spawn(async move {
+        let repo = app.state::<Repository>();
+        if let Err(err) = repo.create_stat(event, latency_ms).await {
+            log::warn!("{}", err);
+        }
+    });
+}
+
+/**
+ * Summarize the recorded usage, of a given year or of all time
+ */
+pub async fn get_local_insights(
+    repo: &Repository,
+    year: Option<i32>,
+) -> Result<LocalInsights, String> {
+    let (from, to) = match year {
+        Some(year) => (start_of_year(year), start_of_year(year + 1)),
+        None => (None, None),
+    };
+    let features = repo.list_feature_usage(from, to).await?;
+    let monthly_activity = repo.list_monthly_activity(from, to).await?;
+    Ok(LocalInsights {
+        year,
+        total_events: features.iter().map(|feature| feature.count).sum(),
+        average_reply_latency_ms: features
+            .iter()
+            .find(|feature| feature.event == EVENT_REPLY)
+            .and_then(|feature| feature.average_latency_ms),
+        features,
+        monthly_activity,
+    })
+}
+
+/**
+ * Count the conversations created and the messages sent per day, over a range
+ * ending today. Unlike the other insights, it doesn't need the user to opt in,
+ * as it's computed from the conversations themselves.
+ */
+pub async fn get_activity_timeline(
+    repo: &Repository,
+    range: ActivityRange,
+) -> Result<Vec<DailyActivity>, String> {
+    let from = range
+        .start(Local::now().date_naive())
+        .map(|day| day.format("%Y-%m-%d").to_string());
+    repo.list_daily_activity(from).await
+}
+
+/**
+ * Sum the tokens used by replies over a range ending today, with their estimated cost,
+ * per day, model and conversation
+ */
+pub async fn get_usage_stats(
+    repo: &Repository,
+    range: ActivityRange,
+) -> Result<UsageStats, String> {
+    let from = range
+        .start(Local::now().date_naive())
+        .map(|day| day.format("%Y-%m-%d").to_string());
+    let usage = repo.list_token_usage(from).await?;
+    let prices = repo.list_model_prices().await?;
+    Ok(usage::aggregate(&usage, &prices))
+}
+
+fn start_of_year(year: i32) -> Option<chrono::DateTime<Local>> {
+    Local.with_ymd_and_hms(year, 1, 1, 0, 0, 0).earliest()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_activity_range_start() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap();
+        let start = |range: ActivityRange| range.start(today).map(|day| day.to_string());
+        assert_eq!(Some("2024-03-25".to_string()), start(ActivityRange::Week));
+        assert_eq!(Some("2024-03-01".to_string()), start(ActivityRange::Month));
+        assert_eq!(Some("2023-04-01".to_string()), start(ActivityRange::Year));
+        assert_eq!(None, start(ActivityRange::All));
+    }
+}
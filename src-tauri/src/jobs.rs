@@ -0,0 +1,250 @@
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    time::Duration,
+};
+
+use entity::entities::settings::{SETTING_JOBS_DAILY_BACKUP, SETTING_JOBS_RUN_ON_BATTERY};
+use starship_battery::{Manager as BatteryManager, State as BatteryState};
+use tauri::{App, AppHandle, Manager};
+
+use crate::{
+    services::{batch, collections, db::Repository, finetune_jobs, response_cache},
+    updater, workspaces,
+};
+
+// Heavy jobs deferred because of the power state are retried this often
+const DEFER_RETRY: Duration = Duration::from_secs(15 * 60);
+const LOW_BATTERY_RATIO: f32 = 0.2;
+const BACKUP_DIR: &str = "backups";
+const KEEP_BACKUPS: usize = 7;
+// Batches take up to a day, no need to check them often
+const BATCH_POLL_INTERVAL: Duration = Duration::from_secs(10 * 60);
+const FINETUNE_POLL_INTERVAL: Duration = Duration::from_secs(5 * 60);
+// Unchanged files are skipped, so frequent syncs only cost reading file metadata
+const COLLECTION_SYNC_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+type JobFuture = Pin<Box<dyn Future<Output = Result<(), String>> + Send>>;
+type JobFn = Arc<dyn Fn(AppHandle) -> JobFuture + Send + Sync>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JobWeight {
+    /// Cheap jobs, like network checks, always run
+    Light,
+    /// Jobs that keep the CPU or disk busy, deferred on battery unless the user overrides it
+    Heavy,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PowerState {
+    OnAc,
+    OnBattery { ratio: f32 },
+    Unknown,
+}
+
+impl PowerState {
+    pub fn is_constrained(&self) -> bool {
+        matches!(self, PowerState::OnBattery { .. })
+    }
+}
+
+struct Job {
+    name: &'static str,
+    weight: JobWeight,
+    initial_delay: Duration,
+    interval: Duration,
+    run: JobFn,
+}
+
+/// Runs periodic background jobs while the app is open, including when its window is hidden
+pub struct JobScheduler {
+    jobs: Vec<Job>,
+}
+
+impl JobScheduler {
+    pub fn new() -> Self {
+        JobScheduler { jobs: vec![] }
+    }
+
+    pub fn job<F, Fut>(
+        mut self,
+        name: &'static str,
+        weight: JobWeight,
+        initial_delay: Duration,
+        interval: Duration,
+        run: F,
+    ) -> Self
+    where
+        F: Fn(AppHandle) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        self.jobs.push(Job {
+            name,
+            weight,
+            initial_delay,
+            interval,
+            run: Arc::new(move |app| Box::pin(run(app))),
+        });
+        self
+    }
+
+    pub fn start(self, app: &AppHandle) {
+        for job in self.jobs {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                let mut delay = job.initial_delay;
+                loop {
+                    tokio::time::sleep(delay).await;
+                    if job.weight == JobWeight::Heavy {
+                        if let Some(reason) = defer_reason(&app).await {
+                            log::info!("Deferring job {}: {}", job.name, reason);
+                            delay = DEFER_RETRY;
+                            continue;
+                        }
+                    }
+                    log::debug!("Running job {}", job.name);
+                    if let Err(err) = (job.run)(app.clone()).await {
+                        log::warn!("Job {} failed: {}", job.name, err);
+                    }
+                    delay = job.interval;
+                }
+            });
+        }
+    }
+}
+
+// Register and start the background jobs
+pub fn init_jobs(app: &App) {
+    JobScheduler::new()
+        .job(
+            "check-updates",
+            JobWeight::Light,
+            Duration::ZERO,
+            updater::CHECK_INTERVAL,
+            |app| async move { updater::check_for_updates(&app).await.map(|_| ()) },
+        )
+        .job(
+            "daily-backup",
+            JobWeight::Heavy,
+            Duration::from_secs(10 * 60),
+            Duration::from_secs(24 * 60 * 60),
+            |app| async move { backup_database(app).await },
+        )
+        .job(
+            "poll-batches",
+            JobWeight::Light,
+            Duration::from_secs(60),
+            BATCH_POLL_INTERVAL,
+            |app| async move { batch::poll_batches(&app.state::<Repository>()).await },
+        )
+        .job(
+            "poll-finetune-jobs",
+            JobWeight::Light,
+            Duration::from_secs(90),
+            FINETUNE_POLL_INTERVAL,
+            |app| async move { finetune_jobs::poll_jobs(&app.state::<Repository>()).await },
+        )
+        .job(
+            "prune-response-cache",
+            JobWeight::Light,
+            Duration::from_secs(5 * 60),
+            Duration::from_secs(60 * 60),
+            |app| async move { response_cache::prune(&app.state::<Repository>()).await },
+        )
+        .job(
+            "sync-collections",
+            JobWeight::Heavy,
+            Duration::from_secs(2 * 60),
+            COLLECTION_SYNC_INTERVAL,
+            |app| async move { collections::sync_collections(&app.state::<Repository>()).await },
+        )
+        .start(app.handle());
+}
+
+// Read the battery state. Desktops without battery are always on AC.
+pub fn power_state() -> PowerState {
+    let batteries = match BatteryManager::new().and_then(|manager| manager.batteries()) {
+        Ok(batteries) => batteries,
+        Err(err) => {
+            log::debug!("Failed to read power state: {}", err);
+            return PowerState::Unknown;
+        }
+    };
+    let mut state = PowerState::OnAc;
+    for battery in batteries.flatten() {
+        if battery.state() == BatteryState::Discharging {
+            state = PowerState::OnBattery {
+                ratio: battery.state_of_charge().value,
+            };
+        }
+    }
+    state
+}
+
+async fn defer_reason(app: &AppHandle) -> Option<String> {
+    let repo = app.state::<Repository>();
+    let run_on_battery = repo
+        .get_setting(SETTING_JOBS_RUN_ON_BATTERY)
+        .await
+        .map(|setting| setting.value == "true")
+        .unwrap_or(false);
+    if run_on_battery {
+        return None;
+    }
+    match power_state() {
+        PowerState::OnBattery { ratio } if ratio < LOW_BATTERY_RATIO => {
+            Some(format!("battery is low ({:.0}%)", ratio * 100.0))
+        }
+        state if state.is_constrained() => Some("running on battery".to_string()),
+        _ => None,
+    }
+}
+
+// Copy the database to the backups folder if the user turned daily backups on
+async fn backup_database(app: AppHandle) -> Result<(), String> {
+    let repo = app.state::<Repository>();
+    let enabled = repo
+        .get_setting(SETTING_JOBS_DAILY_BACKUP)
+        .await
+        .map(|setting| setting.value == "true")
+        .unwrap_or(false);
+    if !enabled {
+        return Ok(());
+    }
+    let app_data_dir = workspaces::get_app_data_dir(&app)?;
+    let mut backup_dir = workspaces::active_workspace_dir(&app_data_dir);
+    backup_dir.push(BACKUP_DIR);
+    std::fs::create_dir_all(&backup_dir)
+        .map_err(|err| format!("Failed to create backup directory: {}", err))?;
+    let backup_path = backup_dir.join(format!(
+        "database-{}.sqlite",
+        chrono::Local::now().format("%Y%m%d")
+    ));
+    // Repository::backup_to blocks on the async runtime, so keep it off the worker threads
+    let handle = app.clone();
+    let path = backup_path.clone();
+    tokio::task::spawn_blocking(move || handle.state::<Repository>().backup_to(&path))
+        .await
+        .map_err(|err| err.to_string())??;
+    log::info!("Database backed up to {}", backup_path.display());
+    prune_backups(&backup_dir)
+}
+
+fn prune_backups(backup_dir: &Path) -> Result<(), String> {
+    let mut backups: Vec<PathBuf> = std::fs::read_dir(backup_dir)
+        .map_err(|err| format!("Failed to read backup directory: {}", err))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "sqlite"))
+        .collect();
+    // Names end with the date, so they sort chronologically
+    backups.sort();
+    let excess = backups.len().saturating_sub(KEEP_BACKUPS);
+    for path in backups.into_iter().take(excess) {
+        std::fs::remove_file(&path)
+            .map_err(|err| format!("Failed to remove {}: {}", path.display(), err))?;
+    }
+    Ok(())
+}
@@ -1,25 +1,62 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod api_server;
+mod app_lock;
+mod background;
+mod cli;
 mod commands;
 mod core;
+mod crash;
+mod deep_link;
+mod diagnostics;
 mod errors;
+mod events;
 mod init;
+mod insights;
+mod jobs;
 mod utils;
 mod log_utils;
+mod notifications;
 mod services;
+mod shortcuts;
+mod shutdown;
+mod single_instance;
+mod tray;
+mod updater;
+mod window_state;
+mod workspaces;
 
 use chrono::Local;
+use api_server::ApiServer;
+use app_lock::AppLock;
+use background::BackgroundMode;
+use insights::Insights;
 use log::LevelFilter;
+use notifications::PendingNotification;
+use services::generation::GenerationManager;
 use tauri::Manager;
+use tauri_plugin_autostart::MacosLauncher;
 use tauri_plugin_log::{
     fern::colors::{Color, ColoredLevelConfig},
+    RotationStrategy,
     Target, 
     TargetKind,
 };
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // Answer in the terminal and exit when launched with --ask
+    if let Some(args) = cli::parse_args(std::env::args().skip(1)) {
+        let code = match args {
+            Ok(args) => cli::run(args),
+            Err(err) => {
+                eprintln!("{}", err);
+                2
+            }
+        };
+        std::process::exit(code);
+    }
     let colors = ColoredLevelConfig {
         error: Color::Red,
         warn: Color::Yellow,
@@ -27,45 +64,193 @@ pub fn run() {
         info: Color::Blue,
         trace: Color::White,
     };
+    let handler = tauri::generate_handler![
+        commands::create_model,
+        commands::list_models,
+        commands::update_model,
+        commands::delete_model,
+        commands::get_model_capabilities,
+        commands::list_remote_models,
+        commands::list_settings,
+        commands::upsert_setting,
+        commands::get_provider_options,
+        commands::set_provider_options,
+        commands::create_conversation,
+        commands::create_blank_conversation,
+        commands::list_conversations,
+        commands::delete_conversation,
+        commands::list_trashed_conversations,
+        commands::restore_conversation,
+        commands::purge_trash,
+        commands::fork_conversation,
+        commands::duplicate_conversation,
+        commands::set_conversation_locked,
+        commands::pin_conversation,
+        commands::archive_conversation,
+        commands::create_tag,
+        commands::list_tags,
+        commands::delete_tag,
+        commands::list_conversation_tags,
+        commands::add_conversation_tag,
+        commands::remove_conversation_tag,
+        commands::update_conversation,
+        commands::get_options,
+        commands::update_options,
+        commands::reset_options_to_model_default,
+        commands::update_subject,
+        commands::update_conversation_model,
+        commands::create_message,
+        commands::mark_conversation_read,
+        commands::list_messages,
+        commands::get_message_count,
+        commands::get_system_message,
+        commands::set_system_prompt,
+        commands::update_message,
+        commands::edit_message,
+        commands::branch_message,
+        commands::list_message_branches,
+        commands::switch_message_branch,
+        commands::set_message_feedback,
+        commands::clear_message_feedback,
+        commands::list_message_feedback,
+        commands::get_model_feedback_stats,
+        commands::export_finetune_jsonl,
+        commands::list_provider_files,
+        commands::upload_provider_file,
+        commands::delete_provider_file,
+        commands::upload_training_file,
+        commands::create_finetune_job,
+        commands::list_finetune_jobs,
+        commands::create_eval_set,
+        commands::list_eval_sets,
+        commands::list_eval_cases,
+        commands::delete_eval_set,
+        commands::run_eval,
+        commands::list_eval_runs,
+        commands::get_eval_results,
+        commands::create_collection,
+        commands::list_collections,
+        commands::list_collection_files,
+        commands::add_collection_files,
+        commands::remove_collection_file,
+        commands::sync_collection,
+        commands::create_batch,
+        commands::list_batches,
+        commands::list_batch_items,
+        commands::hard_delete_messages,
+        commands::hard_delete_message,
+        commands::delete_message,
+        commands::delete_messages_from,
+        commands::snapshot_conversation,
+        commands::list_snapshots,
+        commands::restore_snapshot,
+        commands::delete_snapshot,
+        commands::copy_message,
+        commands::copy_code_blocks,
+        commands::save_code_blocks,
+        commands::copy_conversation_as_markdown,
+        commands::import_markdown_conversation,
+        commands::import_csv_conversations,
+        commands::share_to_gist,
+        commands::revoke_gist_share,
+        commands::link_assistant,
+        commands::unlink_assistant,
+        commands::call_bot,
+        commands::preview_request,
+        commands::translate_text,
+        commands::summarize_conversation,
+        commands::refine_prompt,
+        commands::count_tokens,
+        commands::count_tokens_for_text,
+        commands::compare_embeddings,
+        commands::list_workspaces,
+        commands::create_workspace,
+        commands::switch_workspace,
+        commands::get_local_insights,
+        commands::get_activity_timeline,
+        commands::get_usage_stats,
+        commands::list_model_prices,
+        commands::set_model_price,
+        commands::delete_model_price,
+        commands::create_response_schema,
+        commands::list_response_schemas,
+        commands::update_response_schema,
+        commands::delete_response_schema,
+        commands::set_conversation_response_schema,
+        commands::set_conversation_language,
+        commands::set_conversation_fallback_models,
+        commands::create_prompt,
+        commands::list_prompts,
+        commands::update_prompt,
+        commands::delete_prompt,
+        commands::get_sys_info,
+        commands::run_diagnostics,
+        commands::test_proxy,
+        commands::palette_search,
+        commands::search_in_conversation,
+        commands::search_messages,
+        commands::rebuild_derived_data,
+        commands::bootstrap_defaults,
+        commands::refresh_tray_menu,
+        commands::start_api_server,
+        commands::stop_api_server,
+        commands::get_api_server_info,
+        commands::get_lock_status,
+        commands::set_lock_passphrase,
+        commands::lock,
+        commands::unlock,
+        commands::check_for_updates,
+        commands::install_update,
+        commands::get_background_settings,
+        commands::set_autostart,
+        commands::list_crash_reports,
+        commands::delete_crash_reports,
+        commands::get_recent_logs,
+        commands::open_log_folder,
+        commands::stop_generation,
+        commands::stop_all_generations,
+    ];
     tauri::Builder::default()
+        // Must be registered first so a second launch exits before initializing anything
+        .plugin(tauri_plugin_single_instance::init(
+            single_instance::on_second_instance,
+        ))
+        .manage(GenerationManager::new())
+        .manage(PendingNotification::new())
+        .manage(ApiServer::new())
+        .manage(BackgroundMode::new())
+        .manage(AppLock::new())
+        .manage(Insights::new())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_clipboard_manager::init())
-        .invoke_handler(tauri::generate_handler![
-            commands::create_model,
-            commands::list_models,
-            commands::update_model,
-            commands::delete_model,
-            commands::list_remote_models,
-            commands::list_settings,
-            commands::upsert_setting,
-            commands::create_conversation,
-            commands::create_blank_conversation,
-            commands::list_conversations,
-            commands::delete_conversation,
-            commands::update_conversation,
-            commands::get_options,
-            commands::update_options,
-            commands::update_subject,
-            commands::update_conversation_model,
-            commands::create_message,
-            commands::list_messages,
-            commands::get_system_message,
-            commands::update_message,
-            commands::hard_delete_messages,
-            commands::hard_delete_message,
-            commands::call_bot,
-            commands::create_prompt,
-            commands::list_prompts,
-            commands::update_prompt,
-            commands::delete_prompt,
-            commands::get_sys_info,
-        ])
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_deep_link::init())
+        .plugin(tauri_plugin_autostart::init(
+            MacosLauncher::LaunchAgent,
+            Some(vec![background::ARG_BACKGROUND]),
+        ))
+        // Every command goes through the app lock first
+        .invoke_handler(move |invoke| match app_lock::guard_invoke(invoke) {
+            Some(invoke) => {
+                insights::track_invoke(&invoke);
+                handler(invoke)
+            }
+            None => true,
+        })
         .plugin(
             tauri_plugin_log::Builder::default()
-                .targets([Target::new(TargetKind::Stdout), Target::new(TargetKind::Webview)])
+                .targets([
+                    Target::new(TargetKind::Stdout),
+                    Target::new(TargetKind::Webview),
+                    Target::new(TargetKind::LogDir {
+                        file_name: Some(log_utils::LOG_FILE_NAME.to_string()),
+                    }),
+                ])
+                .max_file_size(log_utils::MAX_LOG_FILE_SIZE)
+                .rotation_strategy(RotationStrategy::KeepAll)
                 .level(LevelFilter::Debug)
                 .format(move |out, message, record| {
                     out.finish(format_args!(
@@ -73,7 +258,7 @@ pub fn run() {
                         Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
                         colors.color(record.level()),
                         record.target(),
-                        message
+                        log_utils::redact_secrets(&message.to_string())
                     ))
                 })
                 .build(),
@@ -87,9 +272,43 @@ pub fn run() {
             }
             // Initialization
             init::init(app).expect("Failed to initialize app");
+            // Start locked if a passphrase is set
+            app_lock::init_app_lock(app).expect("Failed to initialize app lock");
+            // Local usage stats
+            insights::init_insights(app).expect("Failed to initialize insights");
+            // Old log files
+            if let Ok(log_dir) = app.path().app_log_dir() {
+                if let Err(err) = log_utils::prune_rotated_logs(&log_dir) {
+                    log::warn!("{}", err);
+                }
+            }
+            // Crash reports
+            crash::init_crash_handler(app).expect("Failed to initialize crash handler");
+            // System tray
+            tray::init_tray(app).expect("Failed to initialize tray");
+            // Window size & position
+            window_state::init_window_state(app).expect("Failed to restore window state");
+            // Start at login & close to tray
+            background::init_background_mode(app).expect("Failed to initialize background mode");
+            // Panic button stopping all generations
+            if let Err(err) = shortcuts::init_shortcuts(app) {
+                log::warn!("{}", err);
+            }
+            // kaas:// URLs
+            deep_link::init_deep_link(app).expect("Failed to initialize deep links");
+            // Local REST API
+            api_server::init_api_server(app);
+            // Background jobs such as update checks
+            jobs::init_jobs(app);
 
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .on_window_event(|window, event| {
+            notifications::handle_window_event(window, event);
+            window_state::handle_window_event(window, event);
+            background::handle_window_event(window, event);
+        })
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(shutdown::handle_run_event);
 }
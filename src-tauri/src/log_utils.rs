@@ -19,3 +19,161 @@ pub fn warn<S: Into<String>>(tag: &str, message: S) {
 pub fn error<S: Into<String>>(tag: &str, message: S) {
     log::error!("[{}]: {}", tag, message.into());
 }
+
+/***** Log files START *****/
+
+use std::{fs, path::Path};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+pub const LOG_FILE_NAME: &str = "kaas";
+pub const MAX_LOG_FILE_SIZE: u128 = 5 * 1024 * 1024;
+const KEEP_ROTATED_LOGS: usize = 5;
+const REDACTED: &str = "[REDACTED]";
+
+static SECRET_PATTERNS: Lazy<Vec<(Regex, &'static str)>> = Lazy::new(|| {
+    vec![
+        // "apiKey": "...", api_key=..., Authorization: ...
+        (
+            Regex::new(r#"(?i)("?\b(?:api[_-]?key|access[_-]?token|token|secret|password|authorization)\b"?\s*[:=]\s*"?)(?:bearer\s+)?[^"\s,}]+"#)
+                .unwrap(),
+            "${1}[REDACTED]",
+        ),
+        (
+            Regex::new(r"(?i)(bearer\s+)[A-Za-z0-9\-._~+/]+=*").unwrap(),
+            "${1}[REDACTED]",
+        ),
+        // Well-known key formats of supported providers
+        (
+            Regex::new(r"\b(?:sk|xai|gsk)-[A-Za-z0-9_\-]{16,}").unwrap(),
+            REDACTED,
+        ),
+        (Regex::new(r"\bAIza[0-9A-Za-z_\-]{35}").unwrap(), REDACTED),
+    ]
+});
+
+static ANSI_ESCAPES: Lazy<Regex> = Lazy::new(|| Regex::new(r"\x1b\[[0-9;]*m").unwrap());
+
+// Mask API keys and tokens so logs can be shared safely
+pub fn redact_secrets(message: &str) -> String {
+    let mut result = message.to_string();
+    for (pattern, replacement) in SECRET_PATTERNS.iter() {
+        result = pattern.replace_all(&result, *replacement).into_owned();
+    }
+    result
+}
+
+// Mask API keys and tokens in the strings of a JSON value, keeping it valid JSON
+pub fn redact_json_secrets(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::String(text) => *text = redact_secrets(text),
+        serde_json::Value::Array(items) => items.iter_mut().for_each(redact_json_secrets),
+        serde_json::Value::Object(fields) => fields.values_mut().for_each(redact_json_secrets),
+        _ => {}
+    }
+}
+
+// Remove the oldest rotated log files, keeping only the most recent ones
+pub fn prune_rotated_logs(log_dir: &Path) -> Result<(), String> {
+    if !log_dir.exists() {
+        return Ok(());
+    }
+    let current = format!("{}.log", LOG_FILE_NAME);
+    let mut rotated: Vec<_> = fs::read_dir(log_dir)
+        .map_err(|err| format!("Failed to read log directory: {}", err))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .map_or(false, |name| {
+                    name != current && name.starts_with(LOG_FILE_NAME) && name.ends_with(".log")
+                })
+        })
+        .collect();
+    // Rotated files are suffixed with a timestamp, so names sort chronologically
+    rotated.sort();
+    let excess = rotated.len().saturating_sub(KEEP_ROTATED_LOGS);
+    for path in rotated.into_iter().take(excess) {
+        if let Err(err) = fs::remove_file(&path) {
+            log::warn!("Failed to remove old log file {}: {}", path.display(), err);
+        }
+    }
+    Ok(())
+}
+
+// Read the last lines of the current log file, keeping only entries at or above the given level
+pub fn read_recent_logs(
+    log_dir: &Path,
+    lines: usize,
+    level: Option<log::Level>,
+) -> Result<Vec<String>, String> {
+    let path = log_dir.join(format!("{}.log", LOG_FILE_NAME));
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let content =
+        fs::read_to_string(&path).map_err(|err| format!("Failed to read log file: {}", err))?;
+    let mut result: Vec<String> = content
+        .lines()
+        .map(|line| ANSI_ESCAPES.replace_all(line, "").into_owned())
+        .filter(|line| match level {
+            Some(level) => line_level(line).map_or(false, |line_level| line_level <= level),
+            None => true,
+        })
+        .collect();
+    let start = result.len().saturating_sub(lines);
+    Ok(result.split_off(start))
+}
+
+// Lines are formatted as [date][LEVEL][target] message
+fn line_level(line: &str) -> Option<log::Level> {
+    line.split(']')
+        .nth(1)
+        .and_then(|part| part.trim_start_matches('[').parse::<log::Level>().ok())
+}
+
+/***** Log files END *****/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_secrets() {
+        assert_eq!(
+            r#"{"apiKey":"[REDACTED]","model":"gpt-4o"}"#,
+            redact_secrets(r#"{"apiKey":"sk-abcdefghijklmnopqrstuvwxyz","model":"gpt-4o"}"#)
+        );
+        assert_eq!(
+            "Authorization: [REDACTED]",
+            redact_secrets("Authorization: Bearer abc.def")
+        );
+        assert_eq!(
+            "key [REDACTED] is invalid",
+            redact_secrets("key sk-proj-1234567890abcdefghij is invalid")
+        );
+        assert_eq!("nothing to hide", redact_secrets("nothing to hide"));
+    }
+
+    #[test]
+    fn test_redact_json_secrets() {
+        let mut body = serde_json::json!({
+            "model": "gpt-4o",
+            "messages": [{ "role": "user", "content": "My key is sk-abcdefghijklmnopqrstuvwxyz" }],
+        });
+        redact_json_secrets(&mut body);
+        assert_eq!("gpt-4o", body["model"]);
+        assert_eq!("My key is [REDACTED]", body["messages"][0]["content"]);
+    }
+
+    #[test]
+    fn test_line_level() {
+        assert_eq!(
+            Some(log::Level::Warn),
+            line_level("[2024-01-01 00:00:00.000][WARN][kaas] message")
+        );
+        assert_eq!(None, line_level("continuation of a previous line"));
+    }
+}
@@ -0,0 +1,87 @@
+use std::sync::Mutex;
+
+use entity::entities::settings::SETTING_NOTIFICATION_ON_REPLY;
+use tauri::{Emitter, Manager, Window, WindowEvent};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::{services::db::Repository, tray::EVENT_OPEN_CONVERSATION};
+
+const PREVIEW_MAX_CHARS: usize = 120;
+
+/// The conversation of the last notification the user hasn't reacted to yet.
+/// Desktop notifications don't report clicks, so the conversation is opened
+/// when the window regains focus, which is what clicking the notification does.
+pub struct PendingNotification(Mutex<Option<i32>>);
+
+impl PendingNotification {
+    pub fn new() -> Self {
+        PendingNotification(Mutex::new(None))
+    }
+
+    fn set(&self, conversation_id: i32) {
+        *self.0.lock().expect("Failed to lock notification mutex") = Some(conversation_id);
+    }
+
+    fn take(&self) -> Option<i32> {
+        self.0
+            .lock()
+            .expect("Failed to lock notification mutex")
+            .take()
+    }
+}
+
+// Notify the user that a reply is ready, if they are looking at another app
+pub async fn notify_reply_finished(window: &Window, conversation_id: i32, reply: &str) {
+    if window.is_focused().unwrap_or(true) {
+        return;
+    }
+    let app = window.app_handle();
+    let repo = app.state::<Repository>();
+    if !is_enabled(&repo).await {
+        return;
+    }
+    let subject = repo
+        .get_conversation_details(conversation_id)
+        .await
+        .map(|details| details.subject)
+        .unwrap_or_else(|_| "Kaas".to_string());
+    let result = app
+        .notification()
+        .builder()
+        .title(subject)
+        .body(preview(reply))
+        .show();
+    match result {
+        Ok(()) => app.state::<PendingNotification>().set(conversation_id),
+        Err(err) => log::error!("Failed to show notification: {}", err),
+    }
+}
+
+// Open the notified conversation when the user comes back to the app
+pub fn handle_window_event(window: &Window, event: &WindowEvent) {
+    if let WindowEvent::Focused(true) = event {
+        if let Some(conversation_id) = window.state::<PendingNotification>().take() {
+            if let Err(err) = window.emit(EVENT_OPEN_CONVERSATION, conversation_id) {
+                log::error!("Error when sending event: {}", err);
+            }
+        }
+    }
+}
+
+// Notifications are on unless the user turned them off
+async fn is_enabled(repo: &Repository) -> bool {
+    repo.get_setting(SETTING_NOTIFICATION_ON_REPLY)
+        .await
+        .map(|setting| setting.value != "false")
+        .unwrap_or(true)
+}
+
+fn preview(reply: &str) -> String {
+    let reply = reply.trim();
+    if reply.chars().count() > PREVIEW_MAX_CHARS {
+        let truncated: String = reply.chars().take(PREVIEW_MAX_CHARS).collect();
+        format!("{}...", truncated)
+    } else {
+        reply.to_string()
+    }
+}
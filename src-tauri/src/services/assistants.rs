@@ -0,0 +1,310 @@
+use std::time::Duration;
+
+use async_openai::{
+    config::OpenAIConfig,
+    types::{
+        CreateMessageRequestArgs, CreateRunRequestArgs, CreateThreadRequest, MessageContent,
+        MessageRole, RunObject, RunStatus,
+    },
+    Client,
+};
+use entity::entities::{
+    conversations::ConversationDetailsDTO,
+    messages::{MessageDTO, Roles},
+};
+
+use super::{
+    db::Repository,
+    llm::{chat::BotReply, client::openai_client, context::get_conversation_privacy_filter},
+    privacy::PrivacyFilter,
+};
+
+// Runs take a few seconds at least, no need to poll them more often
+const RUN_POLL_INTERVAL: Duration = Duration::from_millis(1000);
+
+// Assistants are only available with OpenAI models
+async fn get_assistant_client(
+    repo: &Repository,
+    conversation_id: i32,
+) -> Result<Client<OpenAIConfig>, String> {
+    let model_id = repo
+        .get_conversation_details(conversation_id)
+        .await?
+        .model_id
+        .ok_or(format!(
+            "Conversation with id = {} has no model",
+            conversation_id
+        ))?;
+    let (client, _) = openai_client(repo, model_id).await?;
+    Ok(client)
+}
+
+/**
+ * Back a conversation by a new thread of the assistant, starting with the messages so far.
+ * Later messages of the conversation are answered by runs of the assistant on this thread.
+ */
+pub async fn link_assistant(
+    repo: &Repository,
+    conversation_id: i32,
+    assistant_id: String,
+) -> Result<ConversationDetailsDTO, String> {
+    let client = get_assistant_client(repo, conversation_id).await?;
+    let thread = client
+        .threads()
+        .create(CreateThreadRequest::default())
+        .await
+        .map_err(|err| format!("Failed to create thread: {}", err))?;
+    if let Err(err) = sync_history(repo, &client, conversation_id, &thread.id).await {
+        let _ = client.threads().delete(&thread.id).await;
+        return Err(err);
+    }
+    log::info!(
+        "Conversation {} linked to thread {} of assistant {}",
+        conversation_id,
+        thread.id,
+        assistant_id
+    );
+    repo.update_conversation_assistant(conversation_id, Some(assistant_id), Some(thread.id))
+        .await
+}
+
+/**
+ * Stop using the assistant in a conversation. The remote thread is deleted.
+ */
+pub async fn unlink_assistant(
+    repo: &Repository,
+    conversation_id: i32,
+) -> Result<ConversationDetailsDTO, String> {
+    let conversation = repo.get_conversation_details(conversation_id).await?;
+    if let Some(thread_id) = &conversation.thread_id {
+        let client = get_assistant_client(repo, conversation_id).await?;
+        if let Err(err) = client.threads().delete(thread_id).await {
+            // The thread may be gone already, unlink anyway
+            log::warn!("Failed to delete thread {}: {}", thread_id, err);
+        }
+    }
+    repo.update_conversation_assistant(conversation_id, None, None)
+        .await
+}
+
+/**
+ * Send the last user message of the conversation to its thread and wait for the reply of the assistant.
+ * When regenerating a reply, up to `before_message_id` or after a failed one, the user message is
+ * on the thread already and only a new run is started.
+ */
+pub async fn run_assistant(
+    repo: &Repository,
+    conversation_id: i32,
+    before_message_id: Option<i32>,
+) -> Result<BotReply, String> {
+    let conversation = repo.get_conversation_details(conversation_id).await?;
+    let (assistant_id, thread_id) = match (conversation.assistant_id, conversation.thread_id) {
+        (Some(assistant_id), Some(thread_id)) => (assistant_id, thread_id),
+        _ => {
+            return Err(format!(
+                "Conversation {} is not linked to an assistant",
+                conversation_id
+            ))
+        }
+    };
+    let client = get_assistant_client(repo, conversation_id).await?;
+    let (messages, privacy_filter) =
+        get_redacted_history(repo, conversation_id, before_message_id).await?;
+    if !messages
+        .iter()
+        .any(|message| Roles::from(message.role) == Roles::User)
+    {
+        return Err("No message to send to the assistant".to_string());
+    }
+    // A user message followed by replies, or older than the one regenerated, was sent already
+    if let Some(text) = messages
+        .last()
+        .filter(|message| before_message_id.is_none() && Roles::from(message.role) == Roles::User)
+        .and_then(|message| message.get_text())
+    {
+        let message = CreateMessageRequestArgs::default()
+            .role(MessageRole::User)
+            .content(text)
+            .build()
+            .map_err(|err| err.to_string())?;
+        client
+            .threads()
+            .messages(&thread_id)
+            .create(message)
+            .await
+            .map_err(|err| format!("Failed to add message to thread: {}", err))?;
+    }
+    let request = CreateRunRequestArgs::default()
+        .assistant_id(assistant_id)
+        .build()
+        .map_err(|err| err.to_string())?;
+    let run = client
+        .threads()
+        .runs(&thread_id)
+        .create(request)
+        .await
+        .map_err(|err| format!("Failed to start run: {}", err))?;
+    let mut guard = RunGuard {
+        client: client.clone(),
+        thread_id: thread_id.clone(),
+        run_id: Some(run.id.clone()),
+    };
+    let run = wait_for_run(&client, &thread_id, run).await;
+    guard.run_id = None;
+    let run = run?;
+    let mut reply = get_last_reply(&client, &thread_id).await?;
+    if let Some(filter) = &privacy_filter {
+        reply = filter.restore(&reply);
+    }
+    Ok(BotReply {
+        message: reply,
+        prompt_token: run.usage.as_ref().map(|usage| usage.prompt_tokens),
+        completion_token: run.usage.as_ref().map(|usage| usage.completion_tokens),
+        total_token: run.usage.as_ref().map(|usage| usage.total_tokens),
+        ..Default::default()
+    })
+}
+
+// The messages of the conversation as they are sent to the thread, before the given one
+// if any, and the filter restoring their personal data. The whole history is redacted
+// every time, so each value gets the same placeholder as in the messages sent before.
+async fn get_redacted_history(
+    repo: &Repository,
+    conversation_id: i32,
+    before_message_id: Option<i32>,
+) -> Result<(Vec<MessageDTO>, Option<PrivacyFilter>), String> {
+    let mut messages = repo.list_messages(conversation_id).await?;
+    messages.retain(|message| {
+        Roles::from(message.role) != Roles::System
+            && before_message_id.map_or(true, |id| message.id.map_or(true, |mid| mid < id))
+    });
+    let mut privacy_filter = get_conversation_privacy_filter(repo, conversation_id).await?;
+    if let Some(filter) = privacy_filter.as_mut() {
+        filter.redact_messages(&mut messages);
+    }
+    Ok((messages, privacy_filter))
+}
+
+// Copy the messages of the conversation to the thread, so the assistant knows the history.
+// A last user message is left to run_assistant, which sends it with the next run.
+async fn sync_history(
+    repo: &Repository,
+    client: &Client<OpenAIConfig>,
+    conversation_id: i32,
+    thread_id: &str,
+) -> Result<(), String> {
+    let (mut messages, _) = get_redacted_history(repo, conversation_id, None).await?;
+    if messages
+        .last()
+        .is_some_and(|message| Roles::from(message.role) == Roles::User)
+    {
+        messages.pop();
+    }
+    for message in messages {
+        let role = match Roles::from(message.role) {
+            Roles::User => MessageRole::User,
+            Roles::Bot => MessageRole::Assistant,
+            Roles::System => continue,
+        };
+        let Some(text) = message.get_text().filter(|text| !text.is_empty()) else {
+            continue;
+        };
+        let request = CreateMessageRequestArgs::default()
+            .role(role)
+            .content(text)
+            .build()
+            .map_err(|err| err.to_string())?;
+        client
+            .threads()
+            .messages(thread_id)
+            .create(request)
+            .await
+            .map_err(|err| format!("Failed to add message to thread: {}", err))?;
+    }
+    Ok(())
+}
+
+// Cancels the run when dropped before it ended, as when the task waiting for the
+// reply is aborted because the user stopped it
+struct RunGuard {
+    client: Client<OpenAIConfig>,
+    thread_id: String,
+    run_id: Option<String>,
+}
+
+impl Drop for RunGuard {
+    fn drop(&mut self) {
+        if let Some(run_id) = self.run_id.take() {
+            let client = self.client.clone();
+            let thread_id = self.thread_id.clone();
+            tauri::async_runtime::spawn(async move {
+                match client.threads().runs(&thread_id).cancel(&run_id).await {
+                    Ok(_) => log::info!("Run {} of thread {} cancelled", run_id, thread_id),
+                    Err(err) => log::warn!("Failed to cancel run {}: {}", run_id, err),
+                }
+            });
+        }
+    }
+}
+
+// Poll the run until it ends
+async fn wait_for_run(
+    client: &Client<OpenAIConfig>,
+    thread_id: &str,
+    mut run: RunObject,
+) -> Result<RunObject, String> {
+    loop {
+        match run.status {
+            RunStatus::Completed => return Ok(run),
+            RunStatus::RequiresAction => {
+                // The app has no tools to answer tool calls with, so the run can't go on
+                let _ = client.threads().runs(thread_id).cancel(&run.id).await;
+                return Err("The assistant called a tool, which is not supported yet".to_string());
+            }
+            RunStatus::Failed
+            | RunStatus::Cancelled
+            | RunStatus::Cancelling
+            | RunStatus::Expired
+            | RunStatus::Incomplete => {
+                let reason = run
+                    .last_error
+                    .map(|error| error.message)
+                    .unwrap_or(format!("{:?}", run.status));
+                return Err(format!("Assistant run ended: {}", reason));
+            }
+            RunStatus::Queued | RunStatus::InProgress => {
+                tokio::time::sleep(RUN_POLL_INTERVAL).await;
+                run = client
+                    .threads()
+                    .runs(thread_id)
+                    .retrieve(&run.id)
+                    .await
+                    .map_err(|err| format!("Failed to get run {}: {}", run.id, err))?;
+            }
+        }
+    }
+}
+
+async fn get_last_reply(client: &Client<OpenAIConfig>, thread_id: &str) -> Result<String, String> {
+    let messages = client
+        .threads()
+        .messages(thread_id)
+        .list(&[("limit", "1"), ("order", "desc")])
+        .await
+        .map_err(|err| format!("Failed to list messages of thread: {}", err))?;
+    let message = messages
+        .data
+        .into_iter()
+        .find(|message| message.role == MessageRole::Assistant)
+        .ok_or("The assistant didn't reply".to_string())?;
+    let text = message
+        .content
+        .into_iter()
+        .filter_map(|content| match content {
+            MessageContent::Text(text) => Some(text.text.value),
+            _ => None,
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+    Ok(text)
+}
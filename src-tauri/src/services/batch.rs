@@ -0,0 +1,269 @@
+use async_openai::{
+    config::OpenAIConfig,
+    types::{
+        BatchCompletionWindow, BatchEndpoint, BatchRequest, BatchStatus as RemoteBatchStatus,
+        CreateFileRequest, FileInput, FilePurpose,
+    },
+    Client,
+};
+use entity::entities::{
+    batch_items::Model as BatchItem,
+    batches::{BatchStatus, Model as Batch},
+};
+use serde_json::json;
+
+use super::{
+    db::Repository,
+    llm::{chat::BotReply, client::openai_client, context::get_max_tokens_setting},
+};
+
+const CHAT_COMPLETIONS_URL: &str = "/v1/chat/completions";
+
+/// The reply of the model to one item of a batch, or why it failed
+#[derive(Debug, PartialEq)]
+struct ItemResult {
+    item_id: i32,
+    reply: Result<BotReply, String>,
+}
+
+/**
+ * Queue prompts as a batch of the model and try to submit it right away.
+ * If submitting fails, the batch stays queued and is submitted again by the polling job.
+ */
+pub async fn create_batch(
+    repo: &Repository,
+    model_id: i32,
+    prompts: Vec<String>,
+) -> Result<Batch, String> {
+    let prompts: Vec<String> = prompts
+        .into_iter()
+        .filter(|prompt| !prompt.trim().is_empty())
+        .collect();
+    if prompts.is_empty() {
+        return Err("A batch needs at least one prompt".to_string());
+    }
+    let batch = repo.create_batch(model_id, prompts).await?;
+    match submit_batch(repo, &batch).await {
+        Ok(batch) => Ok(batch),
+        Err(err) => {
+            log::warn!("Failed to submit batch {}: {}", batch.id, err);
+            repo.update_batch_status(batch.id, BatchStatus::Queued, None, Some(err))
+                .await
+        }
+    }
+}
+
+/**
+ * Submit queued batches and write the results of the completed ones back as conversations.
+ * Run periodically by the job scheduler.
+ */
+pub async fn poll_batches(repo: &Repository) -> Result<(), String> {
+    for batch in repo.list_batches_by_status(BatchStatus::Queued).await? {
+        if let Err(err) = submit_batch(repo, &batch).await {
+            log::warn!("Failed to submit batch {}: {}", batch.id, err);
+        }
+    }
+    for batch in repo.list_batches_by_status(BatchStatus::Submitted).await? {
+        if let Err(err) = check_batch(repo, &batch).await {
+            log::warn!("Failed to check batch {}: {}", batch.id, err);
+        }
+    }
+    Ok(())
+}
+
+async fn submit_batch(repo: &Repository, batch: &Batch) -> Result<Batch, String> {
+    // Only OpenAI supports the batch API for now
+    let (client, model_name) = openai_client(repo, batch.model_id).await?;
+    let model_name = model_name.ok_or(format!("Batch {} has no model name", batch.id))?;
+    let items = repo.list_batch_items(batch.id).await?;
+    let max_tokens = get_max_tokens_setting(repo).await;
+    let jsonl = build_batch_input(&model_name, max_tokens, &items);
+    let file = client
+        .files()
+        .create(CreateFileRequest {
+            file: FileInput::from_vec_u8(format!("batch-{}.jsonl", batch.id), jsonl.into_bytes()),
+            purpose: FilePurpose::Batch,
+        })
+        .await
+        .map_err(|err| format!("Failed to upload batch input: {}", err))?;
+    let remote = client
+        .batches()
+        .create(BatchRequest {
+            input_file_id: file.id,
+            endpoint: BatchEndpoint::V1ChatCompletions,
+            completion_window: BatchCompletionWindow::W24H,
+            metadata: None,
+        })
+        .await
+        .map_err(|err| format!("Failed to create batch: {}", err))?;
+    log::info!("Batch {} submitted as {}", batch.id, remote.id);
+    repo.update_batch_status(batch.id, BatchStatus::Submitted, Some(remote.id), None)
+        .await
+}
+
+async fn check_batch(repo: &Repository, batch: &Batch) -> Result<(), String> {
+    let remote_id = batch
+        .remote_id
+        .clone()
+        .ok_or(format!("Batch {} has no remote id", batch.id))?;
+    let (client, _) = openai_client(repo, batch.model_id).await?;
+    let remote = client
+        .batches()
+        .retrieve(&remote_id)
+        .await
+        .map_err(|err| format!("Failed to get batch {}: {}", remote_id, err))?;
+    match remote.status {
+        RemoteBatchStatus::Completed => {
+            let output = match &remote.output_file_id {
+                Some(file_id) => download_file(&client, file_id).await?,
+                None => String::default(),
+            };
+            let errors = match &remote.error_file_id {
+                Some(file_id) => download_file(&client, file_id).await?,
+                None => String::default(),
+            };
+            write_results(repo, batch, &format!("{}\n{}", output, errors)).await?;
+            log::info!("Batch {} completed", batch.id);
+        }
+        RemoteBatchStatus::Failed
+        | RemoteBatchStatus::Expired
+        | RemoteBatchStatus::Cancelling
+        | RemoteBatchStatus::Cancelled => {
+            let error = format!("Batch ended with status {:?}", remote.status);
+            repo.update_batch_status(batch.id, BatchStatus::Failed, None, Some(error))
+                .await?;
+        }
+        _ => {
+            // still running
+        }
+    }
+    Ok(())
+}
+
+async fn download_file(client: &Client<OpenAIConfig>, file_id: &str) -> Result<String, String> {
+    let bytes = client
+        .files()
+        .content(file_id)
+        .await
+        .map_err(|err| format!("Failed to download file {}: {}", file_id, err))?;
+    String::from_utf8(bytes.to_vec()).map_err(|err| err.to_string())
+}
+
+// Each item becomes a new conversation holding its prompt and the reply
+async fn write_results(repo: &Repository, batch: &Batch, jsonl: &str) -> Result<(), String> {
+    let items = repo.list_batch_items(batch.id).await?;
+    let results = parse_batch_output(jsonl);
+    let item_results = items
+        .into_iter()
+        .map(|item| {
+            let result = results
+                .iter()
+                .find(|result| result.item_id == item.id)
+                .map(|result| result.reply.clone())
+                .unwrap_or(Err("No result returned".to_string()));
+            (item, result)
+        })
+        .collect();
+    repo.complete_batch(batch, item_results).await
+}
+
+// One chat completion request per line, identified by the id of its item
+fn build_batch_input(model: &str, max_tokens: u32, items: &[BatchItem]) -> String {
+    items
+        .iter()
+        .map(|item| {
+            json!({
+                "custom_id": item.id.to_string(),
+                "method": "POST",
+                "url": CHAT_COMPLETIONS_URL,
+                "body": {
+                    "model": model,
+                    "messages": [{ "role": "user", "content": item.prompt }],
+                    "max_tokens": max_tokens,
+                },
+            })
+            .to_string()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// Read the lines of the output and error files. Lines that can't be matched to an item are skipped.
+fn parse_batch_output(jsonl: &str) -> Vec<ItemResult> {
+    jsonl
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .filter_map(|value| {
+            let item_id = value["custom_id"].as_str()?.parse::<i32>().ok()?;
+            Some(ItemResult {
+                item_id,
+                reply: parse_batch_response(&value),
+            })
+        })
+        .collect()
+}
+
+fn parse_batch_response(value: &serde_json::Value) -> Result<BotReply, String> {
+    if let Some(message) = value["error"]["message"].as_str() {
+        return Err(message.to_string());
+    }
+    let body = &value["response"]["body"];
+    if let Some(message) = body["error"]["message"].as_str() {
+        return Err(message.to_string());
+    }
+    let message = body["choices"][0]["message"]["content"]
+        .as_str()
+        .ok_or("Response has no message".to_string())?;
+    let usage = &body["usage"];
+    let token = |key: &str| usage[key].as_u64().map(|count| count as u32);
+    Ok(BotReply {
+        message: message.to_string(),
+        prompt_token: token("prompt_tokens"),
+        completion_token: token("completion_tokens"),
+        total_token: token("total_tokens"),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_batch_input() {
+        let items = vec![BatchItem {
+            id: 7,
+            batch_id: 1,
+            prompt: "Hello".to_string(),
+            conversation_id: None,
+            error: None,
+        }];
+        let line: serde_json::Value =
+            serde_json::from_str(&build_batch_input("gpt-4o-mini", 256, &items)).unwrap();
+        assert_eq!("7", line["custom_id"]);
+        assert_eq!(CHAT_COMPLETIONS_URL, line["url"]);
+        assert_eq!("gpt-4o-mini", line["body"]["model"]);
+        assert_eq!("Hello", line["body"]["messages"][0]["content"]);
+    }
+
+    #[test]
+    fn test_parse_batch_output() {
+        let jsonl = r#"{"id":"r1","custom_id":"1","response":{"status_code":200,"body":{"choices":[{"message":{"role":"assistant","content":"Hi!"}}],"usage":{"prompt_tokens":5,"completion_tokens":2,"total_tokens":7}}},"error":null}
+{"id":"r2","custom_id":"2","response":null,"error":{"code":"invalid_request","message":"Bad request"}}
+not json"#;
+        let results = parse_batch_output(jsonl);
+        assert_eq!(2, results.len());
+        assert_eq!(
+            Ok(BotReply {
+                message: "Hi!".to_string(),
+                prompt_token: Some(5),
+                completion_token: Some(2),
+                total_token: Some(7),
+                ..Default::default()
+            }),
+            results[0].reply
+        );
+        assert_eq!(2, results[1].item_id);
+        assert_eq!(Err("Bad request".to_string()), results[1].reply);
+    }
+}
@@ -0,0 +1,177 @@
+use entity::entities::{
+    conversations::{
+        ConversationFilter, Model as Conversation, DEFAULT_CONTEXT_LENGTH,
+        DEFAULT_MAX_CONTINUATIONS, DEFAULT_MAX_TOKENS,
+    },
+    messages::Roles,
+    models::{Model, NewModel, Providers},
+    prompts::NewPrompt,
+    settings::{
+        Model as Setting, SETTING_APP_BOOTSTRAPPED, SETTING_MODELS_CACHE_TTL,
+        SETTING_MODELS_CONTEXT_LENGTH, SETTING_MODELS_MAX_ATTEMPTS,
+        SETTING_MODELS_MAX_CONTINUATIONS, SETTING_MODELS_MAX_TOKENS,
+    },
+};
+use serde::Serialize;
+
+use super::{
+    db::Repository,
+    llm::{context::text_message, retry::DEFAULT_MAX_ATTEMPTS},
+};
+
+/// Prompts of the library of a new workspace, as alias and content
+const STARTER_PROMPTS: &[(&str, &str)] = &[
+    (
+        "Explain code",
+        "Explain what the following code does, step by step, and point out anything surprising:\n\n",
+    ),
+    (
+        "Proofread",
+        "Fix the spelling, grammar and punctuation of the following text. \
+        Keep its meaning and tone, and only reply with the corrected text:\n\n",
+    ),
+    (
+        "Summarize",
+        "Summarize the following text in a few bullet points:\n\n",
+    ),
+    (
+        "Translate to English",
+        "Translate the following text to English, keeping its formatting:\n\n",
+    ),
+];
+
+// The local model the example conversation uses when no model was added yet
+const EXAMPLE_MODEL_ALIAS: &str = "Llama 3.2 (local)";
+const EXAMPLE_MODEL_CONFIG: &str = r#"{"endpoint":"http://localhost:11434","model":"llama3.2"}"#;
+const EXAMPLE_SUBJECT: &str = "Welcome to Kaas";
+const EXAMPLE_QUESTION: &str = "What can I do here?";
+const EXAMPLE_REPLY: &str =
+    "Hi! This is an example conversation, stored on your computer only.\n\n\
+- Add the models of your providers, or a local model served by Ollama, in the Models page.\n\
+- Start a conversation with any of them, and switch between them at any time.\n\
+- Save the prompts you use often in the Prompts page, and insert them with `/`.\n\n\
+Delete this conversation whenever you like.";
+
+/// What a first launch seeded
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BootstrapSummary {
+    /// Settings rows added with their default value
+    pub settings: usize,
+    pub prompts: usize,
+    /// The example conversation, when one was created
+    pub conversation_id: Option<i32>,
+}
+
+/**
+ * Seed the defaults of a new workspace: the settings rows, a starter prompt library
+ * and an example conversation. Runs once per workspace, and each step only fills
+ * what is missing, so it's safe to call again. Prices of models are a catalog built
+ * into the app, so there is nothing to seed for them.
+ */
+pub async fn bootstrap_defaults(repo: &Repository) -> Result<BootstrapSummary, String> {
+    if repo.get_setting(SETTING_APP_BOOTSTRAPPED).await.is_some() {
+        return Ok(BootstrapSummary::default());
+    }
+    let summary = BootstrapSummary {
+        settings: seed_settings(repo).await?,
+        prompts: seed_prompts(repo).await?,
+        conversation_id: seed_example_conversation(repo).await?,
+    };
+    repo.upsert_setting(Setting {
+        key: SETTING_APP_BOOTSTRAPPED.to_string(),
+        value: chrono::Local::now().to_rfc3339(),
+    })
+    .await?;
+    log::info!("Workspace bootstrapped: {:?}", summary);
+    Ok(summary)
+}
+
+async fn seed_settings(repo: &Repository) -> Result<usize, String> {
+    let defaults = [
+        (
+            SETTING_MODELS_CONTEXT_LENGTH,
+            DEFAULT_CONTEXT_LENGTH.to_string(),
+        ),
+        (SETTING_MODELS_MAX_TOKENS, DEFAULT_MAX_TOKENS.to_string()),
+        (
+            SETTING_MODELS_MAX_CONTINUATIONS,
+            DEFAULT_MAX_CONTINUATIONS.to_string(),
+        ),
+        (SETTING_MODELS_CACHE_TTL, "0".to_string()),
+        (
+            SETTING_MODELS_MAX_ATTEMPTS,
+            DEFAULT_MAX_ATTEMPTS.to_string(),
+        ),
+    ];
+    let existing = repo.list_settings().await?;
+    let mut count = 0;
+    for (key, value) in defaults {
+        if existing.iter().any(|setting| setting.key == key) {
+            continue;
+        }
+        repo.upsert_setting(Setting {
+            key: key.to_string(),
+            value,
+        })
+        .await?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+// Only an empty library is filled, so prompts the user deleted don't come back
+async fn seed_prompts(repo: &Repository) -> Result<usize, String> {
+    if !repo.list_prompts().await?.is_empty() {
+        return Ok(0);
+    }
+    for (alias, content) in STARTER_PROMPTS {
+        repo.create_prompt(NewPrompt {
+            alias: alias.to_string(),
+            content: content.to_string(),
+        })
+        .await?;
+    }
+    Ok(STARTER_PROMPTS.len())
+}
+
+async fn seed_example_conversation(repo: &Repository) -> Result<Option<i32>, String> {
+    let filter = ConversationFilter {
+        include_archived: true,
+        ..Default::default()
+    };
+    if !repo.list_conversations(filter).await?.is_empty() {
+        return Ok(None);
+    }
+    let model = example_model(repo).await?;
+    let messages = vec![
+        text_message(Roles::User, EXAMPLE_QUESTION.to_string()),
+        text_message(Roles::Bot, EXAMPLE_REPLY.to_string()),
+    ];
+    let conversation = Conversation {
+        model_id: Some(model.id),
+        subject: EXAMPLE_SUBJECT.to_string(),
+        ..Default::default()
+    };
+    let conversation = repo
+        .create_conversation_with_messages(conversation, messages)
+        .await?;
+    Ok(Some(conversation.id))
+}
+
+async fn example_model(repo: &Repository) -> Result<Model, String> {
+    if let Some(model) = repo.list_models().await?.into_iter().next() {
+        return Ok(model);
+    }
+    repo.create_model(NewModel {
+        alias: EXAMPLE_MODEL_ALIAS.to_string(),
+        provider: Providers::Ollama.into(),
+        config: EXAMPLE_MODEL_CONFIG.to_string(),
+        context_length: None,
+        max_output_tokens: None,
+        default_options: None,
+        connect_timeout: None,
+        read_timeout: None,
+    })
+    .await
+}
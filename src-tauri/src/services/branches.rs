@@ -0,0 +1,176 @@
+use entity::entities::messages::Model as Message;
+use serde::Serialize;
+
+/// Messages sharing the same parent, e.g. a prompt and its edited versions, of which
+/// only the active one is shown in the conversation
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageBranch {
+    pub parent_message_id: Option<i32>,
+    pub message_ids: Vec<i32>,
+    pub active_message_id: Option<i32>,
+}
+
+fn children(messages: &[Message], parent_message_id: Option<i32>) -> Vec<&Message> {
+    messages
+        .iter()
+        .filter(|message| message.parent_message_id == parent_message_id)
+        .collect()
+}
+
+/// List the places of a conversation where the messages split into several branches,
+/// in order. The messages are all the non system messages of the conversation,
+/// including the hidden ones but not the deleted ones.
+pub fn list_branches(messages: &[Message]) -> Vec<MessageBranch> {
+    let mut parent_ids: Vec<Option<i32>> = vec![];
+    for message in messages {
+        if !parent_ids.contains(&message.parent_message_id) {
+            parent_ids.push(message.parent_message_id);
+        }
+    }
+    parent_ids
+        .into_iter()
+        .filter_map(|parent_message_id| {
+            let siblings = children(messages, parent_message_id);
+            if siblings.len() < 2 {
+                return None;
+            }
+            Some(MessageBranch {
+                parent_message_id,
+                message_ids: siblings.iter().map(|message| message.id).collect(),
+                active_message_id: siblings
+                    .iter()
+                    .rev()
+                    .find(|message| !message.is_hidden)
+                    .map(|message| message.id),
+            })
+        })
+        .collect()
+}
+
+/// Ids of a message and of all the messages following it, in any branch
+pub fn descendants(messages: &[Message], message_id: i32) -> Vec<i32> {
+    let mut result = vec![message_id];
+    let mut index = 0;
+    while index < result.len() {
+        let parent_message_id = Some(result[index]);
+        result.extend(
+            children(messages, parent_message_id)
+                .iter()
+                .map(|message| message.id),
+        );
+        index += 1;
+    }
+    result
+}
+
+/// Ids of a message and of the messages shown after it when switching to its branch,
+/// following the latest reply at each step
+pub fn branch_path(messages: &[Message], message_id: i32) -> Vec<i32> {
+    let mut result = vec![message_id];
+    while let Some(next) = children(messages, result.last().copied())
+        .iter()
+        .map(|message| message.id)
+        .max()
+    {
+        result.push(next);
+    }
+    result
+}
+
+/// Ids of the messages to hide and of the messages to show when switching to the
+/// branch of a message. The message's ancestors are shown as well, in case its branch
+/// starts within a hidden one.
+pub fn switch_branch(messages: &[Message], message_id: i32) -> (Vec<i32>, Vec<i32>) {
+    let mut hidden_ids = vec![];
+    let mut shown_ids = branch_path(messages, message_id);
+    let mut current = messages.iter().find(|message| message.id == message_id);
+    while let Some(message) = current {
+        for sibling in children(messages, message.parent_message_id) {
+            if sibling.id != message.id {
+                hidden_ids.extend(descendants(messages, sibling.id));
+            }
+        }
+        current = message.parent_message_id.and_then(|parent_message_id| {
+            messages
+                .iter()
+                .find(|message| message.id == parent_message_id)
+        });
+        if let Some(parent) = current {
+            shown_ids.insert(0, parent.id);
+        }
+    }
+    (hidden_ids, shown_ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(id: i32, parent_message_id: Option<i32>, hidden: bool) -> Message {
+        Message {
+            id,
+            conversation_id: 1,
+            parent_message_id,
+            role: if id % 2 == 1 { 0 } else { 1 },
+            is_hidden: hidden,
+            ..Default::default()
+        }
+    }
+
+    // 1 - 2 - 3 - 4
+    //       \ 5 - 6
+    //           \ 7
+    fn tree() -> Vec<Message> {
+        vec![
+            message(1, None, false),
+            message(2, Some(1), false),
+            message(3, Some(2), true),
+            message(4, Some(3), true),
+            message(5, Some(2), false),
+            message(6, Some(5), true),
+            message(7, Some(5), false),
+        ]
+    }
+
+    #[test]
+    fn test_list_branches() {
+        assert_eq!(
+            vec![
+                MessageBranch {
+                    parent_message_id: Some(2),
+                    message_ids: vec![3, 5],
+                    active_message_id: Some(5),
+                },
+                MessageBranch {
+                    parent_message_id: Some(5),
+                    message_ids: vec![6, 7],
+                    active_message_id: Some(7),
+                },
+            ],
+            list_branches(&tree())
+        );
+        assert!(list_branches(&tree()[..4]).is_empty());
+    }
+
+    #[test]
+    fn test_descendants() {
+        assert_eq!(vec![3, 4], descendants(&tree(), 3));
+        assert_eq!(vec![5, 6, 7], descendants(&tree(), 5));
+        assert_eq!(vec![4], descendants(&tree(), 4));
+    }
+
+    #[test]
+    fn test_branch_path() {
+        assert_eq!(vec![3, 4], branch_path(&tree(), 3));
+        assert_eq!(vec![5, 7], branch_path(&tree(), 5));
+        assert_eq!(vec![2, 5, 7], branch_path(&tree(), 2));
+    }
+
+    #[test]
+    fn test_switch_branch() {
+        assert_eq!((vec![5, 6, 7], vec![1, 2, 3, 4]), switch_branch(&tree(), 3));
+        assert_eq!((vec![7, 3, 4], vec![1, 2, 5, 6]), switch_branch(&tree(), 6));
+        assert_eq!((vec![], vec![1, 2, 5, 7]), switch_branch(&tree(), 1));
+    }
+}
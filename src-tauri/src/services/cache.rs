@@ -4,6 +4,7 @@ use tauri::Manager;
 use std::{io::Read, path::PathBuf};
 
 use crate::core::handle::Handle;
+use crate::workspaces;
 
 pub fn read_as_data_url(file_name: &str, mimetype: Option<&str>) -> Result<String, String> {
     let (mime, data) = read_as_base64_with_mime(file_name, mimetype)?;
@@ -42,7 +43,11 @@ pub fn get_cache_dir() -> Result<PathBuf, String> {
         .clone()
         .expect("App handle is not initialized");
     // get app data path
-    let mut cache_dir = app_handle.path().app_data_dir().map_err(|e| format!("App data path does't exist! {}", e))?;
+    let app_data_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("App data path does't exist! {}", e))?;
+    let mut cache_dir = workspaces::active_workspace_dir(&app_data_dir);
     cache_dir.push("cache");
     return Ok(cache_dir);
 }
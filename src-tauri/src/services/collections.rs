@@ -0,0 +1,194 @@
+use std::{path::Path, time::UNIX_EPOCH};
+
+use async_openai::{
+    config::OpenAIConfig,
+    types::{
+        CreateFileRequest, CreateVectorStoreFileRequestArgs, CreateVectorStoreRequestArgs,
+        FileInput, FilePurpose,
+    },
+    Client,
+};
+use entity::entities::{
+    collection_files::Model as CollectionFile,
+    collections::{Model as Collection, SyncStatus},
+};
+
+use super::{db::Repository, llm::client::openai_client};
+
+/**
+ * Mirror a collection to its vector store: upload new and changed files, remove the removed ones.
+ * The outcome is recorded as the status of the collection.
+ */
+pub async fn sync_collection(repo: &Repository, collection_id: i32) -> Result<Collection, String> {
+    let collection = repo.get_collection(collection_id).await?;
+    match sync_files(repo, &collection).await {
+        Ok(vector_store_id) => {
+            log::info!(
+                "Collection {} synced to vector store {}",
+                collection_id,
+                vector_store_id
+            );
+            repo.update_collection_status(
+                collection_id,
+                SyncStatus::Synced,
+                Some(vector_store_id),
+                None,
+            )
+            .await
+        }
+        Err(err) => {
+            log::warn!("Failed to sync collection {}: {}", collection_id, err);
+            repo.update_collection_status(collection_id, SyncStatus::Failed, None, Some(err))
+                .await
+        }
+    }
+}
+
+/**
+ * Sync all collections. Unchanged files are skipped, so this is cheap when nothing changed.
+ * Run periodically by the job scheduler.
+ */
+pub async fn sync_collections(repo: &Repository) -> Result<(), String> {
+    for collection in repo.list_collections().await? {
+        sync_collection(repo, collection.id).await?;
+    }
+    Ok(())
+}
+
+// Returns the id of the vector store, which is created on the first sync
+async fn sync_files(repo: &Repository, collection: &Collection) -> Result<String, String> {
+    // Vector stores are only available with OpenAI models
+    let (client, _) = openai_client(repo, collection.model_id).await?;
+    let vector_store_id = match &collection.vector_store_id {
+        Some(vector_store_id) => vector_store_id.clone(),
+        None => {
+            let request = CreateVectorStoreRequestArgs::default()
+                .name(&collection.name)
+                .build()
+                .map_err(|err| err.to_string())?;
+            let vector_store = client
+                .vector_stores()
+                .create(request)
+                .await
+                .map_err(|err| format!("Failed to create vector store: {}", err))?;
+            // Keep the id right away, so a failed sync doesn't create another store next time
+            repo.update_collection_status(
+                collection.id,
+                SyncStatus::Pending,
+                Some(vector_store.id.clone()),
+                None,
+            )
+            .await?;
+            vector_store.id
+        }
+    };
+    // Sync every file, then report all the files that failed
+    let mut errors = vec![];
+    for file in repo.list_collection_files(collection.id, true).await? {
+        if let Err(err) = sync_file(repo, &client, &vector_store_id, file).await {
+            errors.push(err);
+        }
+    }
+    if errors.is_empty() {
+        Ok(vector_store_id)
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
+async fn sync_file(
+    repo: &Repository,
+    client: &Client<OpenAIConfig>,
+    vector_store_id: &str,
+    file: CollectionFile,
+) -> Result<(), String> {
+    if file.deleted_at.is_some() {
+        if let Some(remote_file_id) = &file.remote_file_id {
+            remove_remote_file(client, vector_store_id, remote_file_id).await;
+        }
+        return repo.hard_delete_collection_file(file.id).await;
+    }
+    let fingerprint = file_fingerprint(Path::new(&file.path))?;
+    if file.remote_file_id.is_some() && file.fingerprint.as_ref() == Some(&fingerprint) {
+        return Ok(());
+    }
+    let uploaded = client
+        .files()
+        .create(CreateFileRequest {
+            file: FileInput::from(file.path.as_str()),
+            purpose: FilePurpose::Assistants,
+        })
+        .await
+        .map_err(|err| format!("Failed to upload {}: {}", file.path, err))?;
+    let request = CreateVectorStoreFileRequestArgs::default()
+        .file_id(&uploaded.id)
+        .build()
+        .map_err(|err| err.to_string())?;
+    client
+        .vector_stores()
+        .files(vector_store_id)
+        .create(request)
+        .await
+        .map_err(|err| format!("Failed to add {} to vector store: {}", file.path, err))?;
+    // The previous version of a changed file is replaced
+    if let Some(remote_file_id) = &file.remote_file_id {
+        remove_remote_file(client, vector_store_id, remote_file_id).await;
+    }
+    log::info!("Collection file {} uploaded as {}", file.path, uploaded.id);
+    repo.update_collection_file_upload(file.id, fingerprint, uploaded.id)
+        .await
+}
+
+// The remote file may be gone already, so failures are only logged
+async fn remove_remote_file(
+    client: &Client<OpenAIConfig>,
+    vector_store_id: &str,
+    remote_file_id: &str,
+) {
+    if let Err(err) = client
+        .vector_stores()
+        .files(vector_store_id)
+        .delete(remote_file_id)
+        .await
+    {
+        log::warn!(
+            "Failed to remove file {} from vector store {}: {}",
+            remote_file_id,
+            vector_store_id,
+            err
+        );
+    }
+    if let Err(err) = client.files().delete(remote_file_id).await {
+        log::warn!("Failed to delete file {}: {}", remote_file_id, err);
+    }
+}
+
+// Size and modification time of a file, which change whenever its content does
+fn file_fingerprint(path: &Path) -> Result<String, String> {
+    let metadata = std::fs::metadata(path)
+        .map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_millis())
+        .unwrap_or_default();
+    Ok(format!("{}-{}", metadata.len(), modified))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_fingerprint() {
+        let path = std::env::temp_dir().join("kaas-test-file-fingerprint.txt");
+        std::fs::write(&path, "hello").unwrap();
+        let before = file_fingerprint(&path).unwrap();
+        assert_eq!(before, file_fingerprint(&path).unwrap());
+        std::fs::write(&path, "hello world").unwrap();
+        assert_ne!(before, file_fingerprint(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert!(file_fingerprint(&path).is_err());
+    }
+}
@@ -0,0 +1,104 @@
+use entity::entities::{
+    model_prices::Model as ModelPrice, settings::SETTING_MODELS_COST_THRESHOLD,
+};
+
+use super::{
+    db::Repository,
+    llm::{context::ChatContext, limits, pricing},
+    usage,
+};
+
+/// Estimate of a request costing more than the threshold of the settings
+#[derive(Clone, Debug, PartialEq)]
+pub struct CostEstimate {
+    /// In USD
+    pub estimated_cost: f64,
+    /// In USD
+    pub threshold: f64,
+}
+
+/**
+ * Estimate what the request of a context to a model may cost at most, and return the
+ * estimate when it's above the threshold of the settings. Returns None when the threshold
+ * is not set or the prices of the model are unknown.
+ */
+pub async fn check(
+    repo: &Repository,
+    ctx: &ChatContext,
+    model_id: Option<i32>,
+) -> Option<CostEstimate> {
+    let threshold = get_threshold_setting(repo).await?;
+    let price = match model_id {
+        Some(model_id) => usage::get_model_price(repo, model_id).await,
+        None => None,
+    };
+    let estimated_cost = estimate(ctx, price.as_ref())?;
+    if estimated_cost > threshold {
+        Some(CostEstimate {
+            estimated_cost,
+            threshold,
+        })
+    } else {
+        None
+    }
+}
+
+async fn get_threshold_setting(repo: &Repository) -> Option<f64> {
+    repo.get_setting(SETTING_MODELS_COST_THRESHOLD)
+        .await
+        .and_then(|setting| setting.value.parse::<f64>().ok())
+        .filter(|threshold| *threshold > 0.0)
+}
+
+// The prompt plus the longest reply allowed, counting every continuation of a
+// reply cut off by the max tokens limit, at the prices used for the usage stats
+fn estimate(ctx: &ChatContext, price: Option<&ModelPrice>) -> Option<f64> {
+    let model = pricing::model_name(&ctx.config.config).unwrap_or_default();
+    let prompt_tokens = limits::estimate_tokens(&ctx.messages, ctx.language.as_deref());
+    let max_tokens = serde_json::from_str::<serde_json::Value>(&ctx.options.options)
+        .ok()
+        .and_then(|options| options["maxTokens"].as_u64())
+        .map(|max_tokens| max_tokens as u32)
+        .unwrap_or_else(|| ctx.global_settings().max_tokens_for(&model, &ctx.messages));
+    let completion_tokens = max_tokens.saturating_mul(ctx.max_continuations + 1);
+    usage::model_cost(price, &ctx.config.config, prompt_tokens, completion_tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use entity::entities::messages::Roles;
+
+    use crate::services::llm::context::text_message;
+
+    use super::*;
+
+    fn context(model: &str, options: &str, max_continuations: u32) -> ChatContext {
+        let messages = vec![text_message(Roles::User, "a".repeat(3_984))];
+        ChatContext {
+            max_continuations,
+            ..ChatContext::for_test(model, options, messages)
+        }
+    }
+
+    #[test]
+    fn test_estimate() {
+        // 1,000 prompt tokens and 1,000 completion tokens at $30 and $60 per million
+        let cost = estimate(&context("gpt-4", "{}", 0), None).unwrap();
+        assert!((cost - 0.09).abs() < 1e-9);
+        let cost = estimate(&context("gpt-4", r#"{"maxTokens":4000}"#, 0), None).unwrap();
+        assert!((cost - 0.27).abs() < 1e-9);
+        let cost = estimate(&context("gpt-4", "{}", 2), None).unwrap();
+        assert!((cost - 0.21).abs() < 1e-9);
+        assert_eq!(None, estimate(&context("llama3.2", "{}", 0), None));
+        // Prices set by the user are per thousand tokens, and known for any model
+        let price = ModelPrice {
+            id: 1,
+            model_id: 1,
+            input_price: 0.01,
+            output_price: 0.02,
+            updated_at: chrono::Local::now(),
+        };
+        let cost = estimate(&context("llama3.2", "{}", 0), Some(&price)).unwrap();
+        assert!((cost - 0.03).abs() < 1e-9);
+    }
+}
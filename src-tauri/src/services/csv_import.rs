@@ -0,0 +1,230 @@
+use entity::entities::{conversations::Model as Conversation, messages::Roles};
+use serde::Deserialize;
+
+use super::{db::Repository, llm::context::text_message};
+
+/// How the rows of a CSV file of Q&A pairs become conversations
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum CsvImportMode {
+    /// A conversation of a question and its answer per row
+    #[default]
+    PerRow,
+    /// A single conversation with a turn per row
+    SingleConversation,
+}
+
+/// A row of a CSV file of Q&A pairs
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QaPair {
+    pub question: String,
+    /// May be empty, only the question is imported then
+    pub answer: String,
+    pub tags: Vec<String>,
+}
+
+/**
+ * Split CSV text into rows of fields. Fields may be quoted with `"`, a quoted field may
+ * contain commas, line breaks and `""` for a quote. Blank lines are skipped.
+ */
+fn parse_csv(text: &str) -> Result<Vec<Vec<String>>, String> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut rows = vec![];
+    let mut row: Vec<String> = vec![];
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut quote_line = 1;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    field.push(c)
+                }
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => {
+                in_quotes = true;
+                quote_line = line;
+            }
+            ',' => row.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                if row.iter().any(|field| !field.trim().is_empty()) {
+                    rows.push(std::mem::take(&mut row));
+                }
+                row.clear();
+                line += 1;
+            }
+            _ => field.push(c),
+        }
+    }
+    if in_quotes {
+        return Err(format!("Unclosed quote at line {}", quote_line));
+    }
+    row.push(field);
+    if row.iter().any(|field| !field.trim().is_empty()) {
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/**
+ * Read the Q&A pairs of a CSV file, of the question, answer and optional tags columns.
+ * A first row naming the columns is used to find them, in any order. Tags are separated
+ * by `;`, `|` or `,`.
+ */
+pub fn parse_qa_pairs(text: &str) -> Result<Vec<QaPair>, String> {
+    let mut rows = parse_csv(text)?.into_iter().peekable();
+    let position = |header: &[String], name: &str| {
+        header
+            .iter()
+            .position(|column| column.trim().eq_ignore_ascii_case(name))
+    };
+    let (question, answer, tags) = match rows.peek() {
+        Some(header) if position(header, "question").is_some() => {
+            let columns = (
+                position(header, "question").unwrap_or(0),
+                position(header, "answer").ok_or("The answer column is missing".to_string())?,
+                position(header, "tags"),
+            );
+            rows.next();
+            columns
+        }
+        _ => (0, 1, Some(2)),
+    };
+    let pairs: Vec<QaPair> = rows
+        .map(|row| {
+            let cell = |index: usize| row.get(index).map(|cell| cell.trim()).unwrap_or_default();
+            QaPair {
+                question: cell(question).to_string(),
+                answer: cell(answer).to_string(),
+                tags: tags
+                    .map(cell)
+                    .unwrap_or_default()
+                    .split([';', '|', ','])
+                    .map(|tag| tag.trim().to_string())
+                    .filter(|tag| !tag.is_empty())
+                    .collect(),
+            }
+        })
+        .filter(|pair| !pair.question.is_empty())
+        .collect();
+    if pairs.is_empty() {
+        return Err("No questions found in the CSV".to_string());
+    }
+    Ok(pairs)
+}
+
+// The question, followed by the tags as hashtags so they can be searched for
+fn subject_of(pair: &QaPair) -> String {
+    let mut subject = pair.question.lines().next().unwrap_or_default().to_string();
+    for tag in &pair.tags {
+        subject.push_str(&format!(" #{}", tag.replace(char::is_whitespace, "-")));
+    }
+    subject
+}
+
+/**
+ * Create conversations with the given model from the Q&A pairs of a CSV file, either one
+ * per row or a single one, titled with the subject or the first question. The whole file
+ * is read before anything is stored, so a malformed file imports nothing.
+ */
+pub async fn import_qa_pairs(
+    repo: &Repository,
+    model_id: i32,
+    text: &str,
+    mode: CsvImportMode,
+    subject: Option<String>,
+) -> Result<Vec<Conversation>, String> {
+    let pairs = parse_qa_pairs(text)?;
+    let turns = |pair: &QaPair| {
+        let mut messages = vec![text_message(Roles::User, pair.question.clone())];
+        if !pair.answer.is_empty() {
+            messages.push(text_message(Roles::Bot, pair.answer.clone()));
+        }
+        messages
+    };
+    let conversations = match mode {
+        CsvImportMode::PerRow => pairs
+            .iter()
+            .map(|pair| (subject_of(pair), turns(pair)))
+            .collect(),
+        CsvImportMode::SingleConversation => {
+            let subject = subject
+                .filter(|subject| !subject.trim().is_empty())
+                .unwrap_or_else(|| subject_of(&pairs[0]));
+            vec![(subject, pairs.iter().flat_map(turns).collect())]
+        }
+    };
+    let mut result = vec![];
+    for (subject, messages) in conversations {
+        let conversation = Conversation {
+            model_id: Some(model_id),
+            subject,
+            ..Default::default()
+        };
+        result.push(
+            repo.create_conversation_with_messages(conversation, messages)
+                .await?,
+        );
+    }
+    log::info!("Imported {} conversations from CSV", result.len());
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_csv() {
+        let text = "\u{feff}a,\"b, \"\"quoted\"\"\nand more\",c\r\n\r\n,,\nd,e";
+        assert_eq!(
+            vec![vec!["a", "b, \"quoted\"\nand more", "c"], vec!["d", "e"],],
+            parse_csv(text).unwrap()
+        );
+        assert_eq!(
+            Err("Unclosed quote at line 2".to_string()),
+            parse_csv("a,b\nc,\"d\n")
+        );
+    }
+
+    #[test]
+    fn test_parse_qa_pairs() {
+        let pair = |question: &str, answer: &str, tags: &[&str]| QaPair {
+            question: question.to_string(),
+            answer: answer.to_string(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        };
+        assert_eq!(
+            vec![
+                pair("How?", "Like this", &["faq", "billing"]),
+                pair("Why?", "", &[]),
+            ],
+            parse_qa_pairs("How?,Like this,faq;billing\nWhy?\n,orphan answer").unwrap()
+        );
+        assert_eq!(
+            vec![pair("How?", "Like this", &["faq"])],
+            parse_qa_pairs("Tags,Answer,Question\nfaq,Like this,How?").unwrap()
+        );
+        assert!(parse_qa_pairs("question,tags\nHow?,faq").is_err());
+        assert!(parse_qa_pairs("question,answer\n").is_err());
+        assert_eq!(
+            "How? #faq #sign-in",
+            subject_of(&pair("How?\nDetails", "", &["faq", "sign in"]))
+        );
+    }
+}
@@ -1,50 +1,208 @@
-use entity::entities::contents::{self, ActiveModel as ActiveContent, Model as Content};
+use entity::entities::batch_items::{self, Model as BatchItem};
+use entity::entities::batches::{self, BatchStatus, Model as Batch};
+use entity::entities::collection_files::{self, Model as CollectionFile};
+use entity::entities::collections::{self, Model as Collection, SyncStatus};
+use entity::entities::contents::{
+    self, ActiveModel as ActiveContent, ContentDTO, ContentMatch, ConversationContentMatch,
+    Model as Content,
+};
+use entity::entities::conversation_summaries::{self, Model as ConversationSummary};
+use entity::entities::conversation_tags;
 use entity::entities::conversations::{
-    self, ActiveModel as ActiveConversation, AzureOptions, ClaudeOptions, ConversationDTO,
-    ConversationDetailsDTO, GenericOptions, Model as Conversation, OllamaOptions, OpenAIOptions,
-    UpdateConversationDTO,
+    self, ActiveModel as ActiveConversation, AzureOptions, ClaudeOptions, CohereOptions,
+    ConversationDTO, ConversationDetailsDTO, ConversationFilter, ConversationSort, DeepseekOptions,
+    GenericOptions, Model as Conversation, OllamaOptions, OpenAIOptions, UpdateConversationDTO,
+};
+use entity::entities::eval_cases::{self, Model as EvalCase, NewEvalCase};
+use entity::entities::eval_results::{self, Model as EvalResult};
+use entity::entities::eval_runs::{self, Model as EvalRun};
+use entity::entities::eval_sets::{self, Model as EvalSet};
+use entity::entities::finetune_jobs::{self, FinetuneJobStatus, Model as FinetuneJob};
+use entity::entities::message_feedback::{
+    self, Model as MessageFeedback, ModelFeedbackStats, Rating,
 };
 use entity::entities::messages::{
     self, ActiveModel as ActiveMessage, MessageDTO, Model as Message,
 };
+use entity::entities::model_prices::{self, Model as ModelPrice, TokenUsage};
 use entity::entities::models::{self, GenericConfig, Model, NewModel, Providers};
 use entity::entities::prompts::{self, Model as Prompt, NewPrompt};
+use entity::entities::response_cache::{self, Model as CachedReply};
+use entity::entities::response_schemas::{self, Model as ResponseSchema, NewResponseSchema};
 use entity::entities::settings::{self, Model as Setting};
+use entity::entities::snapshot_messages;
+use entity::entities::snapshots::{self, Model as Snapshot};
+use entity::entities::stats::{
+    self, DailyActivity, DerivedDataReport, FeatureUsage, MonthlyActivity,
+};
+use entity::entities::tags::{self, Model as Tag, NewTag};
 use log::{error, info};
 use migration::{Migrator, MigratorTrait};
 use sea_orm::entity::ModelTrait;
 use sea_orm::{
     sea_query, ActiveModelTrait,
     ActiveValue::{self, Set},
-    ColumnTrait, Database, DatabaseConnection, EntityTrait, RelationTrait, TransactionTrait,
+    ColumnTrait, ConnectionTrait, Database, DatabaseConnection, DatabaseTransaction, DbBackend,
+    EntityTrait, FromQueryResult, RelationTrait, Statement, TransactionTrait,
 };
 use sea_orm::{
-    DbErr, IntoActiveModel, JoinType, LoaderTrait, Order, QueryFilter, QueryOrder, QuerySelect,
+    DbErr, IntoActiveModel, JoinType, LoaderTrait, Order, PaginatorTrait, QueryFilter, QueryOrder,
+    QuerySelect, QueryTrait,
 };
 use sqlx::migrate::MigrateDatabase;
+use std::path::Path;
+
+use crate::errors::MigrationError;
+use crate::services::branches::{self, MessageBranch};
+use crate::services::llm::{chat::BotReply, limits::ModelLimits, options, pricing};
+use crate::services::search::{
+    fuzzy_score, rank, PaletteItem, PaletteItemKind, SNIPPET_MATCH_END, SNIPPET_MATCH_START,
+};
 
 type Db = sqlx::sqlite::Sqlite;
 
+// The model which wrote a reply, for queries on messages m of conversations c: the
+// fallback model the frontend keeps in its metadata when one replied, otherwise the
+// model of the conversation
+const REPLY_MODEL_ID_SQL: &str = "COALESCE(CASE WHEN json_valid(m.metadata) \
+    THEN json_extract(m.metadata, '$.fallback.modelId') END, c.model_id)";
+
 pub struct Repository {
     connection: DatabaseConnection,
 }
 
 impl Repository {
-    pub fn migrate(&self) -> Result<(), String> {
+    /**
+     * Get the names of migrations that haven't been applied yet
+     */
+    pub fn pending_migrations(&self) -> Result<Vec<String>, MigrationError> {
         tauri::async_runtime::block_on(async move {
-            Migrator::up(&self.connection, None).await.map_err(|err| {
-                error!("Failed to migrate database: {:?}", err);
-                format!("Failed to migrate database! Reason: {}", err)
-            })?;
+            let pending = Migrator::get_pending_migrations(&self.connection)
+                .await
+                .map_err(|err| {
+                    error!("Failed to get pending migrations: {:?}", err);
+                    MigrationError::PendingUnknown {
+                        message: err.to_string(),
+                    }
+                })?;
+            Ok(pending
+                .iter()
+                .map(|migration| migration.name().to_string())
+                .collect())
+        })
+    }
+
+    /**
+     * Apply pending migrations one at a time, so a failure can be traced back to the migration that caused it
+     */
+    pub fn migrate(&self) -> Result<(), MigrationError> {
+        let pending = self.pending_migrations()?;
+        tauri::async_runtime::block_on(async move {
+            for migration in pending {
+                Migrator::up(&self.connection, Some(1))
+                    .await
+                    .map_err(|err| {
+                        error!("Failed to apply migration {}: {:?}", migration, err);
+                        MigrationError::MigrationFailed {
+                            migration: migration.clone(),
+                            message: err.to_string(),
+                            restored: false,
+                        }
+                    })?;
+                info!("Migration {} applied", migration);
+            }
             info!("Database migrated");
             Ok(())
         })
     }
 
+    /**
+     * Write a consistent copy of the database to the given path
+     */
+    pub fn backup_to(&self, backup_path: &Path) -> Result<(), String> {
+        if backup_path.exists() {
+            // VACUUM INTO refuses to overwrite an existing file
+            std::fs::remove_file(backup_path)
+                .map_err(|err| format!("Failed to remove stale backup: {}", err))?;
+        }
+        let backup_path_str = backup_path
+            .to_str()
+            .ok_or("Backup path is not a valid string!".to_string())?
+            .replace('\'', "''");
+        tauri::async_runtime::block_on(async move {
+            self.connection
+                .execute_unprepared(&format!("VACUUM INTO '{}'", backup_path_str))
+                .await
+                .map_err(|err| {
+                    error!("Failed to back up database: {:?}", err);
+                    err.to_string()
+                })?;
+            info!("Database backed up to {}", backup_path_str);
+            Ok(())
+        })
+    }
+
+    /**
+     * Run SQLite's quick integrity check, returning the problems found
+     */
+    pub async fn check_integrity(&self) -> Result<Vec<String>, String> {
+        let rows = self
+            .connection
+            .query_all(Statement::from_string(
+                DbBackend::Sqlite,
+                "PRAGMA quick_check".to_owned(),
+            ))
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                "Failed to check database integrity".to_string()
+            })?;
+        let problems = rows
+            .iter()
+            .filter_map(|row| row.try_get_by_index::<String>(0).ok())
+            .filter(|line| line != "ok")
+            .collect();
+        Ok(problems)
+    }
+
+    /**
+     * Close the underlying connection pool
+     */
+    pub fn close(self) {
+        tauri::async_runtime::block_on(async move {
+            if let Err(err) = self.connection.close().await {
+                error!("Failed to close database connection: {}", err);
+            }
+        })
+    }
+
+    /**
+     * Close the connection pool on exit, waiting for the connections in use, and so the
+     * transactions in progress, to be released
+     */
+    pub fn shutdown(&self) {
+        let connection = self.connection.clone();
+        tauri::async_runtime::block_on(async move {
+            if let Err(err) = connection.close().await {
+                error!("Failed to close database connection: {}", err);
+            }
+        })
+    }
+
     /**
      * Insert a new model
      */
-    pub async fn create_model(&self, new_model: NewModel) -> Result<Model, String> {
+    pub async fn create_model(&self, mut new_model: NewModel) -> Result<Model, String> {
+        // Limits not given, e.g. by the models API of the provider, come from the catalog
+        if let Some(name) = pricing::model_name(&new_model.config) {
+            let limits = ModelLimits {
+                context_length: new_model.context_length,
+                max_output_tokens: new_model.max_output_tokens,
+            }
+            .or_catalog(&name);
+            new_model.context_length = limits.context_length;
+            new_model.max_output_tokens = limits.max_output_tokens;
+        }
         let mut active_model = new_model.into_active_model();
         active_model.created_at = Set(Some(chrono::Local::now()));
         let result = active_model.insert(&self.connection).await.map_err(|err| {
@@ -85,6 +243,35 @@ impl Repository {
         Ok(result)
     }
 
+    /**
+     * Get the model chosen as default by the user, falling back to the first available model
+     */
+    pub async fn get_default_model(&self) -> Result<Model, String> {
+        let default_model_id = self
+            .get_setting(settings::SETTING_USER_DEFAULT_MODEL)
+            .await
+            .and_then(|setting| setting.value.parse::<i32>().ok());
+        let models = self.list_models().await?;
+        default_model_id
+            .and_then(|model_id| models.iter().find(|model| model.id == model_id).cloned())
+            .or_else(|| models.into_iter().next())
+            .ok_or("No model has been configured yet".to_string())
+    }
+
+    /**
+     * Find a model by id or alias, falling back to the default model when none is given
+     */
+    pub async fn resolve_model(&self, model: Option<String>) -> Result<Model, String> {
+        if let Some(model) = model {
+            let models = self.list_models().await?;
+            return models
+                .into_iter()
+                .find(|m| m.id.to_string() == model || m.alias.eq_ignore_ascii_case(model.trim()))
+                .ok_or(format!("Model {} doesn't exist", model));
+        }
+        self.get_default_model().await
+    }
+
     /**
      * Update a model
      */
@@ -92,6 +279,11 @@ impl Repository {
         let mut active_model: models::ActiveModel = model.into();
         active_model.reset(models::Column::Alias); // mark alias as dirty
         active_model.reset(models::Column::Config); // mark config as dirty
+        active_model.reset(models::Column::ContextLength);
+        active_model.reset(models::Column::MaxOutputTokens);
+        active_model.reset(models::Column::DefaultOptions);
+        active_model.reset(models::Column::ConnectTimeout);
+        active_model.reset(models::Column::ReadTimeout);
         active_model.updated_at = Set(Some(chrono::Local::now()));
         let result = active_model.update(&self.connection).await.map_err(|err| {
             error!("{}", err);
@@ -122,6 +314,24 @@ impl Repository {
         Ok(result)
     }
 
+    /**
+     * List the ids of the conversations using a model, trashed ones included
+     */
+    pub async fn list_model_conversation_ids(&self, model_id: i32) -> Result<Vec<i32>, String> {
+        let result = conversations::Entity::find()
+            .select_only()
+            .column(conversations::Column::Id)
+            .filter(conversations::Column::ModelId.eq(model_id))
+            .into_tuple::<i32>()
+            .all(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!("Failed to list conversations of model with id {}", model_id)
+            })?;
+        Ok(result)
+    }
+
     /**
      * List all settings
      */
@@ -181,28 +391,7 @@ impl Repository {
         active_model.id = ActiveValue::NotSet;
         if let Some(model_id) = conversation.model_id {
             let model = self.get_model(model_id).await?;
-            match model.provider.into() {
-                Providers::Azure => {
-                    let options_str = serde_json::to_string(&AzureOptions::default())
-                        .unwrap_or(String::default());
-                    active_model.options = Set(Some(options_str));
-                }
-                Providers::Claude => {
-                    let options_str = serde_json::to_string(&ClaudeOptions::default())
-                        .unwrap_or(String::default());
-                    active_model.options = Set(Some(options_str));
-                }
-                Providers::Ollama => {
-                    let options_str = serde_json::to_string(&OllamaOptions::default())
-                        .unwrap_or(String::default());
-                    active_model.options = Set(Some(options_str));
-                }
-                _ => {
-                    let options_str = serde_json::to_string(&OpenAIOptions::default())
-                        .unwrap_or(String::default());
-                    active_model.options = Set(Some(options_str));
-                }
-            }
+            active_model.options = Set(Some(model_options(&model)));
         }
 
         active_model.created_at = Set(chrono::Local::now());
@@ -232,28 +421,7 @@ impl Repository {
                 Box::pin(async move {
                     let mut conv_am: ActiveConversation = conversation.into();
                     conv_am.id = ActiveValue::NotSet;
-                    match model.provider.into() {
-                        Providers::Azure => {
-                            let options_str = serde_json::to_string(&AzureOptions::default())
-                                .unwrap_or(String::default());
-                            conv_am.options = Set(Some(options_str));
-                        }
-                        Providers::Claude => {
-                            let options_str = serde_json::to_string(&ClaudeOptions::default())
-                                .unwrap_or(String::default());
-                            conv_am.options = Set(Some(options_str));
-                        }
-                        Providers::Ollama => {
-                            let options_str = serde_json::to_string(&OllamaOptions::default())
-                                .unwrap_or(String::default());
-                            conv_am.options = Set(Some(options_str));
-                        }
-                        _ => {
-                            let options_str = serde_json::to_string(&OpenAIOptions::default())
-                                .unwrap_or(String::default());
-                            conv_am.options = Set(Some(options_str));
-                        }
-                    }
+                    conv_am.options = Set(Some(model_options(&model)));
                     conv_am.created_at = Set(chrono::Local::now());
                     // Set last message at to created at, so new conversation is shown at the top of the list
                     conv_am.last_message_at = Set(Some(chrono::Local::now()));
@@ -286,18 +454,288 @@ impl Repository {
     }
 
     /**
-     * List all conversations
+     * Insert a new conversation whose first message is a user message with the given text
      */
-    pub async fn list_conversations(&self) -> Result<Vec<ConversationDetailsDTO>, String> {
-        let result = conversations::Entity::find()
-            .filter(conversations::Column::DeletedAt.is_null())
-            .join(JoinType::LeftJoin, conversations::Relation::Messages.def())
+    pub async fn create_conversation_with_message(
+        &self,
+        model_id: i32,
+        message: String,
+    ) -> Result<Conversation, String> {
+        let conversation = Conversation {
+            model_id: Some(model_id),
+            subject: message.clone(),
+            ..Default::default()
+        };
+        let content = Content {
+            r#type: contents::ContentType::Text,
+            data: message,
+            ..Default::default()
+        };
+        let (conversation, _, _) = self
+            .create_conversation_with_content(conversation, content)
+            .await?;
+        Ok(conversation)
+    }
+
+    /**
+     * Insert a new conversation with its messages, e.g. imported from a transcript
+     */
+    pub async fn create_conversation_with_messages(
+        &self,
+        conversation: Conversation,
+        messages: Vec<MessageDTO>,
+    ) -> Result<Conversation, String> {
+        let model_id = conversation
+            .model_id
+            .ok_or("Model id is missing".to_owned())?;
+        let model = self.get_model(model_id).await?;
+        let result = self
+            .connection
+            .transaction::<_, Conversation, DbErr>(|txn| {
+                Box::pin(async move {
+                    let mut conv_am: ActiveConversation = conversation.into();
+                    conv_am.id = ActiveValue::NotSet;
+                    conv_am.options = Set(Some(model_options(&model)));
+                    conv_am.created_at = Set(chrono::Local::now());
+                    conv_am.last_message_at = Set(Some(chrono::Local::now()));
+                    let conv_m: Conversation = conv_am.insert(txn).await?;
+
+                    for message in messages {
+                        let contents = message.content.clone();
+                        let mut msg_am = message.into_active_model();
+                        msg_am.id = ActiveValue::NotSet;
+                        msg_am.conversation_id = Set(conv_m.id);
+                        msg_am.created_at = Set(chrono::Local::now());
+                        let msg_m = msg_am.insert(txn).await?;
+                        if !contents.is_empty() {
+                            let ctnt_ams = contents.into_iter().map(|content| {
+                                let mut ctnt_am: contents::ActiveModel =
+                                    content.into_active_model();
+                                ctnt_am.message_id = Set(msg_m.id);
+                                ctnt_am
+                            });
+                            contents::Entity::insert_many(ctnt_ams).exec(txn).await?;
+                        }
+                    }
+                    Ok(conv_m)
+                })
+            })
+            .await
+            .map_err(|err| {
+                error!("Failed to create conversation with messages: {}", err);
+                err.to_string()
+            })?;
+        Ok(result)
+    }
+
+    /**
+     * Copy a conversation and its messages up to the given one into a new conversation,
+     * keeping its model and options
+     */
+    pub async fn fork_conversation(
+        &self,
+        conversation_id: i32,
+        message_id: i32,
+    ) -> Result<Conversation, String> {
+        let conversation = self.get_copyable_conversation(conversation_id).await?;
+        let mut messages = self.list_messages(conversation_id).await?;
+        let position = messages
+            .iter()
+            .position(|message| message.id == Some(message_id))
+            .ok_or(format!(
+                "Message with id {} isn't part of conversation {}",
+                message_id, conversation_id
+            ))?;
+        messages.truncate(position + 1);
+        if let Some(system_message) = self.get_system_message(conversation_id).await? {
+            messages.insert(0, system_message);
+        }
+        self.copy_conversation(conversation, messages).await
+    }
+
+    /**
+     * Copy a conversation, its model, its options and all its messages into a new
+     * conversation, e.g. to run the same prompts against another model
+     */
+    pub async fn duplicate_conversation(
+        &self,
+        conversation_id: i32,
+    ) -> Result<Conversation, String> {
+        let conversation = self.get_copyable_conversation(conversation_id).await?;
+        let mut messages = self.list_messages(conversation_id).await?;
+        if let Some(system_message) = self.get_system_message(conversation_id).await? {
+            messages.insert(0, system_message);
+        }
+        self.copy_conversation(conversation, messages).await
+    }
+
+    async fn get_copyable_conversation(
+        &self,
+        conversation_id: i32,
+    ) -> Result<Conversation, String> {
+        let conversation = conversations::Entity::find_by_id(conversation_id)
+            .one(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!("Failed to get conversation with id = {}", conversation_id)
+            })?
+            .ok_or(format!(
+                "Conversation with id {} doesn't exist",
+                conversation_id
+            ))?;
+        // The messages of assistant threads are kept by OpenAI
+        if conversation.thread_id.is_some() {
+            return Err("Conversations of an assistant can't be copied".to_string());
+        }
+        Ok(conversation)
+    }
+
+    /**
+     * Insert a copy of a conversation with the given messages. The token usage of the
+     * messages isn't copied, as their replies aren't paid for again.
+     */
+    async fn copy_conversation(
+        &self,
+        conversation: Conversation,
+        messages: Vec<MessageDTO>,
+    ) -> Result<Conversation, String> {
+        let copy = Conversation {
+            updated_at: None,
+            deleted_at: None,
+            is_locked: false,
+            is_pinned: false,
+            is_archived: false,
+            gist_id: None,
+            gist_url: None,
+            read_at: None,
+            ..conversation
+        };
+        let result = self
+            .connection
+            .transaction::<_, Conversation, DbErr>(|txn| {
+                Box::pin(async move {
+                    let mut conv_am: ActiveConversation = copy.into();
+                    conv_am.id = ActiveValue::NotSet;
+                    conv_am.created_at = Set(chrono::Local::now());
+                    conv_am.last_message_at = Set(Some(chrono::Local::now()));
+                    let conv_m: Conversation = conv_am.insert(txn).await?;
+
+                    // Only the shown branch is copied, chained one message after another
+                    let mut parent_message_id = None;
+                    for message in messages {
+                        let created_at = message.created_at;
+                        let contents = message.content.clone();
+                        let is_system =
+                            messages::Roles::from(message.role) == messages::Roles::System;
+                        let mut msg_am = MessageDTO {
+                            id: None,
+                            conversation_id: conv_m.id,
+                            parent_message_id: if is_system { None } else { parent_message_id },
+                            prompt_token: None,
+                            completion_token: None,
+                            reasoning_token: None,
+                            total_token: None,
+                            ..message
+                        }
+                        .into_active_model();
+                        msg_am.created_at = Set(created_at);
+                        let msg_m = msg_am.insert(txn).await?;
+                        if !is_system {
+                            parent_message_id = Some(msg_m.id);
+                        }
+                        if !contents.is_empty() {
+                            let ctnt_ams = contents.into_iter().map(|content| {
+                                let mut ctnt_am: contents::ActiveModel =
+                                    content.into_active_model();
+                                ctnt_am.message_id = Set(msg_m.id);
+                                ctnt_am
+                            });
+                            contents::Entity::insert_many(ctnt_ams).exec(txn).await?;
+                        }
+                    }
+                    Ok(conv_m)
+                })
+            })
+            .await
+            .map_err(|err| {
+                error!("Failed to copy conversation: {}", err);
+                err.to_string()
+            })?;
+        Ok(result)
+    }
+
+    /**
+     * List the conversations matching a filter, pinned ones first
+     */
+    pub async fn list_conversations(
+        &self,
+        filter: ConversationFilter,
+    ) -> Result<Vec<ConversationDetailsDTO>, String> {
+        let mut query =
+            conversations::Entity::find().filter(conversations::Column::DeletedAt.is_null());
+        if !filter.include_archived {
+            query = query.filter(conversations::Column::IsArchived.eq(false));
+        }
+        if let Some(tag_id) = filter.tag_id {
+            query = query.filter(
+                conversations::Column::Id.in_subquery(
+                    sea_query::Query::select()
+                        .column(conversation_tags::Column::ConversationId)
+                        .from(conversation_tags::Entity)
+                        .and_where(conversation_tags::Column::TagId.eq(tag_id))
+                        .to_owned(),
+                ),
+            );
+        }
+        if let Some(text) = filter.query.as_deref().map(str::trim) {
+            if !text.is_empty() {
+                // LIKE ignores the case of ASCII letters in SQLite
+                query = query.filter(conversations::Column::Subject.contains(text));
+            }
+        }
+        // Only the messages shown in the conversation are counted, not its system message
+        let shown_messages = conversations::Relation::Messages
+            .def()
+            .on_condition(|_, right| {
+                sea_query::Condition::all()
+                    .add(
+                        sea_query::Expr::col((right.clone(), messages::Column::DeletedAt))
+                            .is_null(),
+                    )
+                    .add(
+                        sea_query::Expr::col((right.clone(), messages::Column::IsHidden)).eq(false),
+                    )
+                    .add(
+                        sea_query::Expr::col((right, messages::Column::Role))
+                            .ne(Into::<i32>::into(messages::Roles::System)),
+                    )
+            });
+        query = query
+            .join(JoinType::LeftJoin, shown_messages)
             .join(JoinType::LeftJoin, conversations::Relation::Models.def())
             .column_as(models::Column::Provider, "model_provider")
             .column_as(messages::Column::Id.count(), "message_count")
             .group_by(conversations::Column::Id)
-            .order_by(conversations::Column::LastMessageAt, Order::Desc)
-            .order_by(conversations::Column::CreatedAt, Order::Desc)
+            .order_by(conversations::Column::IsPinned, Order::Desc);
+        query = match filter.sort {
+            ConversationSort::LastActivity => query
+                .order_by(conversations::Column::LastMessageAt, Order::Desc)
+                .order_by(conversations::Column::CreatedAt, Order::Desc),
+            ConversationSort::CreatedAt => {
+                query.order_by(conversations::Column::CreatedAt, Order::Desc)
+            }
+            ConversationSort::MessageCount => query
+                .order_by(messages::Column::Id.count(), Order::Desc)
+                .order_by(conversations::Column::LastMessageAt, Order::Desc),
+        };
+        if let Some(limit) = filter.limit {
+            query = query.limit(limit);
+        }
+        if let Some(offset) = filter.offset {
+            query = query.offset(offset);
+        }
+        let result = query
             .into_model::<ConversationDetailsDTO>()
             .all(&self.connection)
             .await
@@ -339,6 +777,70 @@ impl Repository {
         Ok(result)
     }
 
+    /**
+     * List the soft deleted conversations, the most recently deleted first
+     */
+    pub async fn list_trashed_conversations(&self) -> Result<Vec<ConversationDetailsDTO>, String> {
+        let result = conversations::Entity::find()
+            .filter(conversations::Column::DeletedAt.is_not_null())
+            .join(JoinType::LeftJoin, conversations::Relation::Messages.def())
+            .join(JoinType::LeftJoin, conversations::Relation::Models.def())
+            .column_as(models::Column::Provider, "model_provider")
+            .column_as(messages::Column::Id.count(), "message_count")
+            .group_by(conversations::Column::Id)
+            .order_by(conversations::Column::DeletedAt, Order::Desc)
+            .into_model::<ConversationDetailsDTO>()
+            .all(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                "Failed to list trashed conversations".to_string()
+            })?;
+        Ok(result)
+    }
+
+    /**
+     * Bring a soft deleted conversation back
+     */
+    pub async fn restore_conversation(
+        &self,
+        conversation_id: i32,
+    ) -> Result<ConversationDetailsDTO, String> {
+        conversations::ActiveModel {
+            id: Set(conversation_id),
+            deleted_at: Set(None),
+            ..Default::default()
+        }
+        .update(&self.connection)
+        .await
+        .map_err(|err| {
+            error!("{}", err);
+            format!(
+                "Failed to restore conversation with id = {}",
+                conversation_id
+            )
+        })?;
+        self.get_conversation_details(conversation_id).await
+    }
+
+    /**
+     * Permanently delete the soft deleted conversations, returning how many were deleted.
+     * Their messages and everything attached to them go along through foreign keys.
+     * Soft deleted messages of other conversations are kept, as snapshots may bring
+     * them back.
+     */
+    pub async fn purge_trash(&self) -> Result<u64, String> {
+        let result = conversations::Entity::delete_many()
+            .filter(conversations::Column::DeletedAt.is_not_null())
+            .exec(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                "Failed to empty the trash".to_string()
+            })?;
+        Ok(result.rows_affected)
+    }
+
     /**
      * Get model provider and requeset options of a conversation
      */
@@ -369,131 +871,135 @@ impl Repository {
     }
 
     /**
-     * Get model provider and config of a conversation
+     * Get the context length and max output tokens of the model of a conversation
      */
-    pub async fn get_conversation_config(
+    pub async fn get_conversation_model_limits(
         &self,
         conversation_id: i32,
-    ) -> Result<GenericConfig, String> {
+    ) -> Result<(Option<u32>, Option<u32>), String> {
         let result = conversations::Entity::find_by_id(conversation_id)
             .select_only()
             .join(JoinType::InnerJoin, conversations::Relation::Models.def())
-            .column(models::Column::Provider)
-            .column(models::Column::Config)
-            .into_model::<GenericConfig>()
+            .column(models::Column::ContextLength)
+            .column(models::Column::MaxOutputTokens)
+            .into_tuple::<(Option<u32>, Option<u32>)>()
             .one(&self.connection)
             .await
             .map_err(|err| {
                 error!("{}", err);
                 format!(
-                    "Failed to get model config of conversation with id = {}",
+                    "Failed to get model limits of conversation with id = {}",
                     conversation_id
                 )
-            })?
-            .ok_or(format!(
-                "Cannot retrieve model config of conversation with id = {}",
-                conversation_id
-            ))?;
-        Ok(result)
+            })?;
+        Ok(result.unwrap_or_default())
     }
+
     /**
-     * Update options of a conversation
+     * Get model provider and config of a conversation
      */
-    pub async fn update_conversation_options(
+    pub async fn get_conversation_config(
         &self,
         conversation_id: i32,
-        options: String,
-    ) -> Result<GenericOptions, String> {
-        // Get conversation model
-        let conversation = conversations::Entity::find_by_id(conversation_id)
+    ) -> Result<GenericConfig, String> {
+        let result = conversations::Entity::find_by_id(conversation_id)
+            .select_only()
+            .join(JoinType::InnerJoin, conversations::Relation::Models.def())
+            .column(models::Column::Provider)
+            .column(models::Column::Config)
+            .into_model::<GenericConfig>()
             .one(&self.connection)
             .await
             .map_err(|err| {
                 error!("{}", err);
-                format!("Failed to find conversation with id = {}", conversation_id)
+                format!(
+                    "Failed to get model config of conversation with id = {}",
+                    conversation_id
+                )
             })?
             .ok_or(format!(
-                "Conversation with id {} doesn't exist",
+                "Cannot retrieve model config of conversation with id = {}",
                 conversation_id
             ))?;
-        // Convert to active model
-        let model_id = conversation
-            .model_id
-            .ok_or("Model id is missing".to_owned())?;
-        let mut c_am: conversations::ActiveModel = conversation.into();
-        // Get provider string
-        let provider: String = models::Entity::find_by_id(model_id)
-            .select_only()
-            .column(models::Column::Provider)
-            .into_tuple()
+        Ok(result)
+    }
+    /**
+     * Get the model of a conversation
+     */
+    pub async fn get_conversation_model(&self, conversation_id: i32) -> Result<Model, String> {
+        let result = conversations::Entity::find_by_id(conversation_id)
+            .find_also_related(models::Entity)
             .one(&self.connection)
             .await
-            .map_err(|_| {
+            .map_err(|err| {
+                error!("{}", err);
                 format!(
-                    "Failed to get provider of conversation with id = {}",
+                    "Failed to get model of conversation with id = {}",
                     conversation_id
                 )
-            })?
-            .unwrap_or(Providers::Unknown.into());
-        // Validate & set options string of active model
-        let options_str;
-        match provider.clone().into() {
-            Providers::Azure => {
-                // Deserialize & serialize the options as validation
-                let azure_options: AzureOptions = serde_json::from_str(&options)
-                    .unwrap_or_else(|err| {
-                        // record error and return default
-                        error!("db::update_conversation_options: Error deserializing Azure options: {}", err);
-                        AzureOptions::default()
-                    });
-                options_str = serde_json::to_string(&azure_options).unwrap_or(String::default());
-                c_am.options = Set(Some(options_str.clone()));
-            }
-            Providers::Claude => {
-                // Deserialize & serialize the options as validation
-                let claude_options: ClaudeOptions = serde_json::from_str(&options)
-                    .unwrap_or_else(|err| {
-                        // record error and return default
-                        error!("db::update_conversation_options: Error deserializing Claude options: {}", err);
-                        ClaudeOptions::default()
-                    });
-                options_str = serde_json::to_string(&claude_options).unwrap_or(String::default());
-                c_am.options = Set(Some(options_str.clone()));
-            }
-            Providers::Ollama => {
-                // Deserialize & serialize the options as validation
-                let ollama_options: OllamaOptions = serde_json::from_str(&options)
-                    .unwrap_or_else(|err| {
-                        // record error and return default
-                        error!("db::update_conversation_options: Error deserializing Ollama options: {}", err);
-                        OllamaOptions::default()
-                    });
-                options_str = serde_json::to_string(&ollama_options).unwrap_or(String::default());
-                c_am.options = Set(Some(options_str.clone()));
-            }
-            _ => {
-                // Deserialize & serialize the options as validation
-                let openai_options: OpenAIOptions = serde_json::from_str(&options)
-                    .unwrap_or_else(|err| {
-                        // record error and return default
-                        error!("db::update_conversation_options: Error deserializing OpenAI options: {}", err);
-                        OpenAIOptions::default()
-                    });
-                options_str = serde_json::to_string(&openai_options).unwrap_or(String::default());
-                c_am.options = Set(Some(options_str.clone()));
-            }
+            })?;
+        match result {
+            Some((_, Some(model))) => Ok(model),
+            _ => Err(format!(
+                "Cannot retrieve model of conversation with id = {}",
+                conversation_id
+            )),
         }
-        // Update DB
-        c_am.update(&self.connection).await.map_err(|err| {
+    }
+
+    /**
+     * Update the options of a conversation, once validated for its model
+     */
+    pub async fn update_conversation_options(
+        &self,
+        conversation_id: i32,
+        options: String,
+    ) -> Result<GenericOptions, String> {
+        let active_model = conversations::ActiveModel {
+            id: Set(conversation_id),
+            options: Set(Some(options)),
+            ..Default::default()
+        };
+        active_model.update(&self.connection).await.map_err(|err| {
             error!("{}", err);
             format!(
                 "Failed to update options of conversation with id = {}",
                 conversation_id
             )
         })?;
+        self.get_conversation_options(conversation_id).await
+    }
+
+    /**
+     * Reset the options of a conversation to the ones new conversations with its model
+     * start with
+     */
+    pub async fn reset_conversation_options(
+        &self,
+        conversation_id: i32,
+    ) -> Result<GenericOptions, String> {
+        let conversation = self.get_conversation_details(conversation_id).await?;
+        let model_id = conversation
+            .model_id
+            .ok_or("Model id is missing".to_owned())?;
+        let model = self.get_model(model_id).await?;
+        let options = model_options(&model);
+        let active_model = conversations::ActiveModel {
+            id: Set(conversation_id),
+            options: Set(Some(options.clone())),
+            updated_at: Set(Some(chrono::Local::now())),
+            ..Default::default()
+        };
+        active_model.update(&self.connection).await.map_err(|err| {
+            error!("{}", err);
+            format!(
+                "Failed to reset options of conversation with id = {}",
+                conversation_id
+            )
+        })?;
         Ok(GenericOptions {
-            provider,
-            options: options_str,
+            provider: model.provider,
+            options,
         })
     }
 
@@ -540,33 +1046,29 @@ impl Repository {
         model_id: i32,
     ) -> Result<ConversationDetailsDTO, String> {
         let model = self.get_model(model_id).await?;
+        let conversation = conversations::Entity::find_by_id(conversation_id)
+            .one(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!("Failed to get conversation with id = {}", conversation_id)
+            })?
+            .ok_or(format!(
+                "Conversation with id {} doesn't exist",
+                conversation_id
+            ))?;
+        // Reset the options to the defaults of the new model, keeping the conversation's
+        // own settings like its context length
+        let options = options::carry_options(
+            conversation.options.as_deref().unwrap_or_default(),
+            &model_options(&model),
+        );
         let mut active_model = conversations::ActiveModel {
             id: Set(conversation_id),
             model_id: Set(Some(model_id)),
+            options: Set(Some(options)),
             ..Default::default()
         };
-        match (&model.provider).into() {
-            Providers::Azure => {
-                let options_str =
-                    serde_json::to_string(&AzureOptions::default()).unwrap_or(String::default());
-                active_model.options = Set(Some(options_str));
-            }
-            Providers::Claude => {
-                let options_str =
-                    serde_json::to_string(&ClaudeOptions::default()).unwrap_or(String::default());
-                active_model.options = Set(Some(options_str));
-            }
-            Providers::Ollama => {
-                let options_str =
-                    serde_json::to_string(&OllamaOptions::default()).unwrap_or(String::default());
-                active_model.options = Set(Some(options_str));
-            }
-            _ => {
-                let options_str =
-                    serde_json::to_string(&OpenAIOptions::default()).unwrap_or(String::default());
-                active_model.options = Set(Some(options_str));
-            }
-        }
         active_model.updated_at = Set(Some(chrono::Local::now()));
         active_model.update(&self.connection).await.map_err(|err| {
             error!("{}", err);
@@ -596,198 +1098,923 @@ impl Repository {
     }
 
     /**
-     * Get details of a conversation
+     * Store the user-facing summary of a conversation
      */
-    pub async fn get_conversation_details(
+    pub async fn update_conversation_summary(
         &self,
         conversation_id: i32,
+        summary: String,
     ) -> Result<ConversationDetailsDTO, String> {
-        let result = conversations::Entity::find_by_id(conversation_id)
-            .join(JoinType::LeftJoin, conversations::Relation::Messages.def())
-            .join(JoinType::LeftJoin, conversations::Relation::Models.def())
-            .column_as(models::Column::Provider, "model_provider")
-            .column_as(messages::Column::Id.count(), "message_count")
-            .group_by(conversations::Column::Id)
-            .into_model::<ConversationDetailsDTO>()
-            .one(&self.connection)
-            .await
-            .map_err(|err| {
-                error!("{}", err);
-                "Failed to list conversations".to_string()
-            })?
-            .ok_or(format!(
-                "Conversation with id {} doesn't exist",
+        let conversation = conversations::ActiveModel {
+            id: Set(conversation_id),
+            summary: Set(Some(summary)),
+            ..Default::default()
+        };
+        conversation.update(&self.connection).await.map_err(|err| {
+            error!("{}", err);
+            format!(
+                "Failed to update summary of conversation with id = {}",
                 conversation_id
-            ))?;
-        Ok(result)
+            )
+        })?;
+        self.get_conversation_details(conversation_id).await
     }
 
     /**
-     * Get the last n messages of a conversation
+     * Get the summary of the older messages of a conversation, sent in their place
      */
-    pub async fn get_last_messages(
+    pub async fn get_history_summary(
         &self,
         conversation_id: i32,
-        n: u16,
-        before_message_id: Option<i32>,
-    ) -> Result<Vec<MessageDTO>, String> {
-        let mut query =
-            messages::Entity::find().filter(messages::Column::ConversationId.eq(conversation_id));
-        if let Some(mid) = before_message_id {
-            query = query.filter(messages::Column::Id.lt(mid));
-        }
-        let messages = query
-            .cursor_by(messages::Column::Id)
-            .last(n as u64)
-            .all(&self.connection)
-            .await
-            .map_err(|err| {
-                error!("{}", err);
-                format!(
-                    "Failed to find last {} messages with conversation id = {}",
-                    n, conversation_id
-                )
-            })?;
-        let contents = messages
-            .load_many(contents::Entity, &self.connection)
+    ) -> Result<Option<ConversationSummary>, String> {
+        let result = conversation_summaries::Entity::find()
+            .filter(conversation_summaries::Column::ConversationId.eq(conversation_id))
+            .one(&self.connection)
             .await
             .map_err(|err| {
                 error!("{}", err);
                 format!(
-                    "Failed to find contents of the last {} messages with conversation id = {}",
-                    n, conversation_id
+                    "Failed to get history summary of conversation with id = {}",
+                    conversation_id
                 )
             })?;
-        let result: Vec<(Message, Vec<Content>)> =
-            messages.into_iter().zip(contents.into_iter()).collect();
-        let dtos: Vec<MessageDTO> = result.into_iter().map(|data| data.into()).collect();
-
-        Ok(dtos)
-    }
-
-    /**
-     * Insert a new message
-     */
-    pub async fn create_message(&self, message: MessageDTO) -> Result<MessageDTO, String> {
-        let contents = message.content.clone();
-        let conversation_id = message.conversation_id;
-        let mut msg_am = message.into_active_model();
-        msg_am.created_at = Set(chrono::Local::now());
-        let result = self
-            .connection
-            .transaction::<_, MessageDTO, DbErr>(|txn| {
-                Box::pin(async move {
-                    // Insert message first
-                    let msg_m = msg_am.insert(txn).await?;
-                    let ctnt_ams: Vec<contents::ActiveModel> = contents
-                        .into_iter()
-                        .map(|content| {
-                            let mut ctnt_am: contents::ActiveModel = content.into_active_model();
-                            ctnt_am.message_id = Set(msg_m.id);
-                            ctnt_am
-                        })
-                        .collect();
-                    // Insert contents
-                    contents::Entity::insert_many(ctnt_ams).exec(txn).await?;
-                    // Retrieve newly inserted contents
-                    let contents = msg_m.find_related(contents::Entity).all(txn).await?;
-                    // Update conversation's last message at
-                    conversations::Entity::update_many()
-                        .filter(conversations::Column::Id.eq(conversation_id))
-                        .col_expr(
-                            conversations::Column::LastMessageAt,
-                            sea_query::Expr::value(chrono::Local::now()),
-                        )
-                        .exec(txn)
-                        .await?;
-                    // Return DTO
-                    let dto = MessageDTO::from((msg_m, contents));
-                    Ok(dto)
-                })
-            })
-            .await
-            .map_err(|err| {
-                error!("Failed to create message with contents: {}", err);
-                err.to_string()
-            })?;
-
         Ok(result)
     }
 
     /**
-     * List all messages of a conversation
+     * Store the summary of the messages of a conversation up to `last_message_id`,
+     * replacing the previous one
      */
-    pub async fn list_messages(&self, conversation_id: i32) -> Result<Vec<MessageDTO>, String> {
-        // Retrieve all Messages from DB with conversation_id
-        // By default, filter out all system messages
-        let result = messages::Entity::find()
-            .find_with_related(contents::Entity)
-            .filter(messages::Column::ConversationId.eq(conversation_id))
-            .filter(messages::Column::Role.ne(Into::<i32>::into(messages::Roles::System)))
-            .filter(messages::Column::DeletedAt.is_null())
-            .all(&self.connection)
+    pub async fn upsert_history_summary(
+        &self,
+        conversation_id: i32,
+        summary: String,
+        last_message_id: i32,
+    ) -> Result<(), String> {
+        let now = chrono::Local::now();
+        let active_model = conversation_summaries::ActiveModel {
+            conversation_id: Set(conversation_id),
+            summary: Set(summary),
+            last_message_id: Set(last_message_id),
+            created_at: Set(now),
+            updated_at: Set(Some(now)),
+            ..Default::default()
+        };
+        conversation_summaries::Entity::insert(active_model)
+            .on_conflict(
+                sea_query::OnConflict::column(conversation_summaries::Column::ConversationId)
+                    .update_columns([
+                        conversation_summaries::Column::Summary,
+                        conversation_summaries::Column::LastMessageId,
+                        conversation_summaries::Column::UpdatedAt,
+                    ])
+                    .to_owned(),
+            )
+            .exec(&self.connection)
             .await
-            // .unwrap();
             .map_err(|err| {
                 error!("{}", err);
                 format!(
-                    "Failed to list messages of conversation with id = {}",
+                    "Failed to store history summary of conversation with id = {}",
                     conversation_id
                 )
-            })?
-            .into_iter()
-            .map(|data| MessageDTO::from(data))
-            .collect();
-        Ok(result)
+            })?;
+        Ok(())
     }
 
     /**
-     * Get the system message of a conversation
+     * Drop the history summary of a conversation when it covers messages from `message_id`
+     * on, which were edited, hidden or deleted since. The summary would otherwise be sent
+     * in place of messages it no longer matches. The next one is written from the messages
+     * as they are now.
      */
-    pub async fn get_system_message(
+    async fn invalidate_history_summary(
         &self,
         conversation_id: i32,
-    ) -> Result<Option<MessageDTO>, String> {
-        let mut result = messages::Entity::find()
-            .find_with_related(contents::Entity)
-            .filter(messages::Column::ConversationId.eq(conversation_id))
-            .filter(messages::Column::Role.eq(Into::<i32>::into(messages::Roles::System)))
-            .all(&self.connection)
+        message_id: i32,
+    ) -> Result<(), String> {
+        let result = conversation_summaries::Entity::delete_many()
+            .filter(conversation_summaries::Column::ConversationId.eq(conversation_id))
+            .filter(conversation_summaries::Column::LastMessageId.gte(message_id))
+            .exec(&self.connection)
             .await
             .map_err(|err| {
                 error!("{}", err);
                 format!(
-                    "Failed to get system message of conversation with id = {}",
+                    "Failed to invalidate history summary of conversation with id = {}",
                     conversation_id
                 )
             })?;
-        let dto = result.pop().map(|data| MessageDTO::from(data));
-        Ok(dto)
+        if result.rows_affected > 0 {
+            log::info!(
+                "History summary of conversation {} dropped, message {} changed",
+                conversation_id,
+                message_id
+            );
+        }
+        Ok(())
     }
 
     /**
-     * Update the system message of a conversation
+     * Lock or unlock a conversation against new and edited messages
      */
-    pub async fn update_message(&self, message: MessageDTO) -> Result<MessageDTO, String> {
-        let message_id = message.id.ok_or("Message id is missing")?;
-        let contents = message.content.clone();
-        let mut msg_am = message.into_active_model();
-        msg_am.updated_at = Set(Some(chrono::Local::now()));
-        let result = self
-            .connection
-            .transaction::<_, MessageDTO, DbErr>(|txn| {
-                Box::pin(async move {
-                    // Update messge first
-                    let msg_m = msg_am.update(txn).await?;
-                    // Delete old content (hard delete)
-                    contents::Entity::delete_many()
-                        .filter(contents::Column::MessageId.eq(msg_m.id))
-                        .exec(txn)
-                        .await?;
-                    // Insert new content
-                    let ctnt_ams: Vec<contents::ActiveModel> = contents
-                        .into_iter()
-                        .map(|content| {
+    pub async fn update_conversation_locked(
+        &self,
+        conversation_id: i32,
+        is_locked: bool,
+    ) -> Result<ConversationDetailsDTO, String> {
+        let conversation = conversations::ActiveModel {
+            id: Set(conversation_id),
+            is_locked: Set(is_locked),
+            ..Default::default()
+        };
+        conversation.update(&self.connection).await.map_err(|err| {
+            error!("{}", err);
+            format!(
+                "Failed to update lock of conversation with id = {}",
+                conversation_id
+            )
+        })?;
+        self.get_conversation_details(conversation_id).await
+    }
+
+    /**
+     * Pin a conversation to the top of the list, or unpin it
+     */
+    pub async fn update_conversation_pinned(
+        &self,
+        conversation_id: i32,
+        is_pinned: bool,
+    ) -> Result<ConversationDetailsDTO, String> {
+        let conversation = conversations::ActiveModel {
+            id: Set(conversation_id),
+            is_pinned: Set(is_pinned),
+            ..Default::default()
+        };
+        conversation.update(&self.connection).await.map_err(|err| {
+            error!("{}", err);
+            format!(
+                "Failed to update pin of conversation with id = {}",
+                conversation_id
+            )
+        })?;
+        self.get_conversation_details(conversation_id).await
+    }
+
+    /**
+     * Archive a conversation out of the list, or bring it back
+     */
+    pub async fn update_conversation_archived(
+        &self,
+        conversation_id: i32,
+        is_archived: bool,
+    ) -> Result<ConversationDetailsDTO, String> {
+        let conversation = conversations::ActiveModel {
+            id: Set(conversation_id),
+            is_archived: Set(is_archived),
+            ..Default::default()
+        };
+        conversation.update(&self.connection).await.map_err(|err| {
+            error!("{}", err);
+            format!(
+                "Failed to update archive of conversation with id = {}",
+                conversation_id
+            )
+        })?;
+        self.get_conversation_details(conversation_id).await
+    }
+
+    /**
+     * Insert a new tag
+     */
+    pub async fn create_tag(&self, new_tag: NewTag) -> Result<Tag, String> {
+        let mut active_model = new_tag.into_active_model();
+        active_model.created_at = Set(chrono::Local::now());
+        let result = active_model.insert(&self.connection).await.map_err(|err| {
+            error!("{}", err);
+            "Failed to create tag, its name may already be taken".to_string()
+        })?;
+        Ok(result)
+    }
+
+    /**
+     * List all tags
+     */
+    pub async fn list_tags(&self) -> Result<Vec<Tag>, String> {
+        let result = tags::Entity::find()
+            .order_by_asc(tags::Column::Name)
+            .all(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                "Failed to list tags".to_string()
+            })?;
+        Ok(result)
+    }
+
+    /**
+     * Delete a tag, removing it from its conversations
+     */
+    pub async fn delete_tag(&self, tag_id: i32) -> Result<(), String> {
+        tags::Entity::delete_by_id(tag_id)
+            .exec(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!("Failed to delete tag with id = {}", tag_id)
+            })?;
+        Ok(())
+    }
+
+    /**
+     * List the tags of a conversation
+     */
+    pub async fn list_conversation_tags(&self, conversation_id: i32) -> Result<Vec<Tag>, String> {
+        let result = tags::Entity::find()
+            .join(JoinType::InnerJoin, tags::Relation::ConversationTags.def())
+            .filter(conversation_tags::Column::ConversationId.eq(conversation_id))
+            .order_by_asc(tags::Column::Name)
+            .all(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!(
+                    "Failed to list tags of conversation with id = {}",
+                    conversation_id
+                )
+            })?;
+        Ok(result)
+    }
+
+    /**
+     * Assign a tag to a conversation, doing nothing when it already has it
+     */
+    pub async fn add_conversation_tag(
+        &self,
+        conversation_id: i32,
+        tag_id: i32,
+    ) -> Result<(), String> {
+        let active_model = conversation_tags::ActiveModel {
+            conversation_id: Set(conversation_id),
+            tag_id: Set(tag_id),
+        };
+        let result = conversation_tags::Entity::insert(active_model)
+            .on_conflict(
+                sea_query::OnConflict::columns([
+                    conversation_tags::Column::ConversationId,
+                    conversation_tags::Column::TagId,
+                ])
+                .do_nothing()
+                .to_owned(),
+            )
+            .exec(&self.connection)
+            .await;
+        match result {
+            // The conversation already has the tag
+            Ok(_) | Err(DbErr::RecordNotInserted) => Ok(()),
+            Err(err) => {
+                error!("{}", err);
+                Err(format!(
+                    "Failed to add tag with id = {} to conversation with id = {}",
+                    tag_id, conversation_id
+                ))
+            }
+        }
+    }
+
+    /**
+     * Remove a tag from a conversation
+     */
+    pub async fn remove_conversation_tag(
+        &self,
+        conversation_id: i32,
+        tag_id: i32,
+    ) -> Result<(), String> {
+        conversation_tags::Entity::delete_by_id((conversation_id, tag_id))
+            .exec(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!(
+                    "Failed to remove tag with id = {} from conversation with id = {}",
+                    tag_id, conversation_id
+                )
+            })?;
+        Ok(())
+    }
+
+    /**
+     * Record the gist a conversation is shared as, or clear it with None
+     */
+    pub async fn update_conversation_gist(
+        &self,
+        conversation_id: i32,
+        gist_id: Option<String>,
+        gist_url: Option<String>,
+    ) -> Result<ConversationDetailsDTO, String> {
+        let conversation = conversations::ActiveModel {
+            id: Set(conversation_id),
+            gist_id: Set(gist_id),
+            gist_url: Set(gist_url),
+            ..Default::default()
+        };
+        conversation.update(&self.connection).await.map_err(|err| {
+            error!("{}", err);
+            format!(
+                "Failed to update gist of conversation with id = {}",
+                conversation_id
+            )
+        })?;
+        self.get_conversation_details(conversation_id).await
+    }
+
+    /**
+     * Link a conversation to an assistant thread, or unlink it with None
+     */
+    pub async fn update_conversation_assistant(
+        &self,
+        conversation_id: i32,
+        assistant_id: Option<String>,
+        thread_id: Option<String>,
+    ) -> Result<ConversationDetailsDTO, String> {
+        let conversation = conversations::ActiveModel {
+            id: Set(conversation_id),
+            assistant_id: Set(assistant_id),
+            thread_id: Set(thread_id),
+            ..Default::default()
+        };
+        conversation.update(&self.connection).await.map_err(|err| {
+            error!("{}", err);
+            format!(
+                "Failed to update assistant of conversation with id = {}",
+                conversation_id
+            )
+        })?;
+        self.get_conversation_details(conversation_id).await
+    }
+
+    /**
+     * Attach a response schema to a conversation, or detach it with None
+     */
+    pub async fn update_conversation_response_schema(
+        &self,
+        conversation_id: i32,
+        response_schema_id: Option<i32>,
+    ) -> Result<ConversationDetailsDTO, String> {
+        let conversation = conversations::ActiveModel {
+            id: Set(conversation_id),
+            response_schema_id: Set(response_schema_id),
+            ..Default::default()
+        };
+        conversation.update(&self.connection).await.map_err(|err| {
+            error!("{}", err);
+            format!(
+                "Failed to update response schema of conversation with id = {}",
+                conversation_id
+            )
+        })?;
+        self.get_conversation_details(conversation_id).await
+    }
+
+    /**
+     * Set the language the replies of a conversation must be written in, or unset it with None
+     */
+    pub async fn update_conversation_language(
+        &self,
+        conversation_id: i32,
+        language: Option<String>,
+    ) -> Result<ConversationDetailsDTO, String> {
+        let conversation = conversations::ActiveModel {
+            id: Set(conversation_id),
+            language: Set(language),
+            ..Default::default()
+        };
+        conversation.update(&self.connection).await.map_err(|err| {
+            error!("{}", err);
+            format!(
+                "Failed to update language of conversation with id = {}",
+                conversation_id
+            )
+        })?;
+        self.get_conversation_details(conversation_id).await
+    }
+
+    /**
+     * Set the models replying in turn when the model of a conversation is unavailable,
+     * as a JSON array of their ids, or use the global ones with None
+     */
+    pub async fn update_conversation_fallback_models(
+        &self,
+        conversation_id: i32,
+        fallback_model_ids: Option<String>,
+    ) -> Result<ConversationDetailsDTO, String> {
+        let conversation = conversations::ActiveModel {
+            id: Set(conversation_id),
+            fallback_model_ids: Set(fallback_model_ids),
+            ..Default::default()
+        };
+        conversation.update(&self.connection).await.map_err(|err| {
+            error!("{}", err);
+            format!(
+                "Failed to update fallback models of conversation with id = {}",
+                conversation_id
+            )
+        })?;
+        self.get_conversation_details(conversation_id).await
+    }
+
+    /**
+     * Record that the messages of a conversation were seen, returning when
+     */
+    pub async fn mark_conversation_read(
+        &self,
+        conversation_id: i32,
+    ) -> Result<chrono::DateTime<chrono::Local>, String> {
+        let read_at = chrono::Local::now();
+        let conversation = conversations::ActiveModel {
+            id: Set(conversation_id),
+            read_at: Set(Some(read_at)),
+            ..Default::default()
+        };
+        conversation.update(&self.connection).await.map_err(|err| {
+            error!("{}", err);
+            format!(
+                "Failed to mark conversation with id = {} as read",
+                conversation_id
+            )
+        })?;
+        Ok(read_at)
+    }
+
+    /**
+     * Get the response schema attached to a conversation, if any
+     */
+    pub async fn get_conversation_response_schema(
+        &self,
+        conversation_id: i32,
+    ) -> Result<Option<ResponseSchema>, String> {
+        let conversation = self.get_conversation_details(conversation_id).await?;
+        match conversation.response_schema_id {
+            Some(schema_id) => response_schemas::Entity::find_by_id(schema_id)
+                .one(&self.connection)
+                .await
+                .map_err(|err| {
+                    error!("{}", err);
+                    format!("Failed to get response schema with id {}", schema_id)
+                }),
+            None => Ok(None),
+        }
+    }
+
+    /**
+     * Whether a conversation is read-only
+     */
+    pub async fn is_conversation_locked(&self, conversation_id: i32) -> Result<bool, String> {
+        let conversation = conversations::Entity::find_by_id(conversation_id)
+            .one(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!("Failed to get conversation with id = {}", conversation_id)
+            })?
+            .ok_or(format!(
+                "Conversation with id {} doesn't exist",
+                conversation_id
+            ))?;
+        Ok(conversation.is_locked)
+    }
+
+    /**
+     * Get details of a conversation
+     */
+    pub async fn get_conversation_details(
+        &self,
+        conversation_id: i32,
+    ) -> Result<ConversationDetailsDTO, String> {
+        let result = conversations::Entity::find_by_id(conversation_id)
+            .join(JoinType::LeftJoin, conversations::Relation::Messages.def())
+            .join(JoinType::LeftJoin, conversations::Relation::Models.def())
+            .column_as(models::Column::Provider, "model_provider")
+            .column_as(messages::Column::Id.count(), "message_count")
+            .group_by(conversations::Column::Id)
+            .into_model::<ConversationDetailsDTO>()
+            .one(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                "Failed to list conversations".to_string()
+            })?
+            .ok_or(format!(
+                "Conversation with id {} doesn't exist",
+                conversation_id
+            ))?;
+        Ok(result)
+    }
+
+    /**
+     * Get the last n messages of a conversation
+     */
+    pub async fn get_last_messages(
+        &self,
+        conversation_id: i32,
+        n: u16,
+        before_message_id: Option<i32>,
+    ) -> Result<Vec<MessageDTO>, String> {
+        let mut query =
+            messages::Entity::find().filter(messages::Column::ConversationId.eq(conversation_id));
+        if let Some(mid) = before_message_id {
+            query = query.filter(messages::Column::Id.lt(mid));
+        }
+        query = query
+            .filter(messages::Column::DeletedAt.is_null())
+            .filter(messages::Column::IsHidden.eq(false));
+        let messages = query
+            .cursor_by(messages::Column::Id)
+            .last(n as u64)
+            .all(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!(
+                    "Failed to find last {} messages with conversation id = {}",
+                    n, conversation_id
+                )
+            })?;
+        let contents = messages
+            .load_many(contents::Entity, &self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!(
+                    "Failed to find contents of the last {} messages with conversation id = {}",
+                    n, conversation_id
+                )
+            })?;
+        let result: Vec<(Message, Vec<Content>)> =
+            messages.into_iter().zip(contents.into_iter()).collect();
+        let dtos: Vec<MessageDTO> = result.into_iter().map(|data| data.into()).collect();
+
+        Ok(dtos)
+    }
+
+    /**
+     * Find the messages of a conversation matching a full-text query, in order,
+     * with a snippet of the matching text whose matched terms are wrapped in markers
+     */
+    pub async fn search_messages(
+        &self,
+        conversation_id: i32,
+        fts_query: String,
+    ) -> Result<Vec<ContentMatch>, String> {
+        let sql = format!(
+            "SELECT contents_fts.message_id AS message_id, \
+            snippet(contents_fts, 0, '{}', '{}', '…', 16) AS snippet \
+            FROM contents_fts JOIN messages ON messages.id = contents_fts.message_id \
+            WHERE contents_fts MATCH ? AND messages.conversation_id = ? \
+            AND messages.deleted_at IS NULL AND NOT messages.is_hidden \
+            ORDER BY messages.id",
+            SNIPPET_MATCH_START, SNIPPET_MATCH_END
+        );
+        let result = ContentMatch::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            &sql,
+            [fts_query.into(), conversation_id.into()],
+        ))
+        .all(&self.connection)
+        .await
+        .map_err(|err| {
+            error!("{}", err);
+            format!(
+                "Failed to search messages of conversation with id = {}",
+                conversation_id
+            )
+        })?;
+        Ok(result)
+    }
+
+    /**
+     * Full-text search the messages of all conversations, best matches first
+     */
+    pub async fn search_all_messages(
+        &self,
+        fts_query: String,
+        limit: u64,
+    ) -> Result<Vec<ConversationContentMatch>, String> {
+        let sql = format!(
+            "SELECT messages.conversation_id AS conversation_id, \
+            conversations.subject AS conversation_subject, \
+            contents_fts.message_id AS message_id, \
+            snippet(contents_fts, 0, '{}', '{}', '…', 16) AS snippet \
+            FROM contents_fts JOIN messages ON messages.id = contents_fts.message_id \
+            JOIN conversations ON conversations.id = messages.conversation_id \
+            WHERE contents_fts MATCH ? AND messages.deleted_at IS NULL \
+            AND NOT messages.is_hidden AND conversations.deleted_at IS NULL \
+            ORDER BY contents_fts.rank LIMIT ?",
+            SNIPPET_MATCH_START, SNIPPET_MATCH_END
+        );
+        let result = ConversationContentMatch::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            &sql,
+            [fts_query.into(), limit.into()],
+        ))
+        .all(&self.connection)
+        .await
+        .map_err(|err| {
+            error!("{}", err);
+            "Failed to search messages".to_string()
+        })?;
+        Ok(result)
+    }
+
+    /**
+     * Recompute the data derived from other tables: the date of the last message of
+     * each conversation and the search index of the contents. Used to recover after
+     * imports or edits of the database made outside of the app.
+     */
+    pub async fn rebuild_derived_data(&self) -> Result<DerivedDataReport, String> {
+        self.connection
+            .transaction::<_, DerivedDataReport, DbErr>(|txn| {
+                Box::pin(async move {
+                    let conversations = txn
+                        .execute_unprepared(
+                            "UPDATE conversations SET last_message_at = COALESCE(\
+                            (SELECT MAX(messages.created_at) FROM messages \
+                            WHERE messages.conversation_id = conversations.id \
+                            AND messages.deleted_at IS NULL), created_at)",
+                        )
+                        .await?
+                        .rows_affected();
+                    txn.execute_unprepared("DELETE FROM contents_fts").await?;
+                    let indexed_contents = txn
+                        .execute_unprepared(
+                            "INSERT INTO contents_fts (rowid, data, message_id) \
+                            SELECT id, data, message_id FROM contents WHERE type = 0",
+                        )
+                        .await?
+                        .rows_affected();
+                    Ok(DerivedDataReport {
+                        conversations,
+                        indexed_contents,
+                    })
+                })
+            })
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                "Failed to rebuild derived data".to_string()
+            })
+    }
+
+    /**
+     * Insert a new message
+     */
+    pub async fn create_message(&self, mut message: MessageDTO) -> Result<MessageDTO, String> {
+        // Follow the latest shown message unless told which message it follows
+        if message.parent_message_id.is_none()
+            && messages::Roles::from(message.role) != messages::Roles::System
+        {
+            message.parent_message_id = self.get_last_message_id(message.conversation_id).await?;
+        }
+        let result = self
+            .connection
+            .transaction::<_, MessageDTO, DbErr>(|txn| {
+                Box::pin(async move { insert_message(txn, message).await })
+            })
+            .await
+            .map_err(|err| {
+                error!("Failed to create message with contents: {}", err);
+                err.to_string()
+            })?;
+
+        Ok(result)
+    }
+
+    /**
+     * List all messages of a conversation
+     */
+    pub async fn list_messages(&self, conversation_id: i32) -> Result<Vec<MessageDTO>, String> {
+        // Retrieve all Messages from DB with conversation_id
+        // By default, filter out all system messages
+        let result = messages::Entity::find()
+            .find_with_related(contents::Entity)
+            .filter(messages::Column::ConversationId.eq(conversation_id))
+            .filter(messages::Column::Role.ne(Into::<i32>::into(messages::Roles::System)))
+            .filter(messages::Column::DeletedAt.is_null())
+            .filter(messages::Column::IsHidden.eq(false))
+            .all(&self.connection)
+            .await
+            // .unwrap();
+            .map_err(|err| {
+                error!("{}", err);
+                format!(
+                    "Failed to list messages of conversation with id = {}",
+                    conversation_id
+                )
+            })?
+            .into_iter()
+            .map(|data| MessageDTO::from(data))
+            .collect();
+        Ok(result)
+    }
+
+    /**
+     * List a page of the messages of a conversation: the last ones before the given
+     * message, or the last ones when None, in order
+     */
+    pub async fn list_messages_before(
+        &self,
+        conversation_id: i32,
+        before_message_id: Option<i32>,
+        limit: u64,
+    ) -> Result<Vec<MessageDTO>, String> {
+        let mut query = messages::Entity::find()
+            .filter(messages::Column::ConversationId.eq(conversation_id))
+            .filter(messages::Column::Role.ne(Into::<i32>::into(messages::Roles::System)))
+            .filter(messages::Column::DeletedAt.is_null())
+            .filter(messages::Column::IsHidden.eq(false));
+        if let Some(mid) = before_message_id {
+            query = query.filter(messages::Column::Id.lt(mid));
+        }
+        let messages = query
+            .cursor_by(messages::Column::Id)
+            .last(limit)
+            .all(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!(
+                    "Failed to list messages of conversation with id = {}",
+                    conversation_id
+                )
+            })?;
+        let contents = messages
+            .load_many(contents::Entity, &self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!(
+                    "Failed to find contents of messages of conversation with id = {}",
+                    conversation_id
+                )
+            })?;
+        let result = messages
+            .into_iter()
+            .zip(contents.into_iter())
+            .map(MessageDTO::from)
+            .collect();
+        Ok(result)
+    }
+
+    /**
+     * Count the messages of a conversation, as listed by `list_messages`
+     */
+    pub async fn count_messages(&self, conversation_id: i32) -> Result<u64, String> {
+        messages::Entity::find()
+            .filter(messages::Column::ConversationId.eq(conversation_id))
+            .filter(messages::Column::Role.ne(Into::<i32>::into(messages::Roles::System)))
+            .filter(messages::Column::DeletedAt.is_null())
+            .filter(messages::Column::IsHidden.eq(false))
+            .count(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!(
+                    "Failed to count messages of conversation with id = {}",
+                    conversation_id
+                )
+            })
+    }
+
+    /**
+     * Get a message by its id
+     */
+    pub async fn get_message(&self, message_id: i32) -> Result<MessageDTO, String> {
+        let result = messages::Entity::find_by_id(message_id)
+            .find_with_related(contents::Entity)
+            .filter(messages::Column::DeletedAt.is_null())
+            .all(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!("Failed to get message with id = {}", message_id)
+            })?
+            .pop()
+            .map(|data| MessageDTO::from(data))
+            .ok_or(format!("Message with id {} doesn't exist", message_id))?;
+        Ok(result)
+    }
+
+    /**
+     * Get the system message of a conversation
+     */
+    pub async fn get_system_message(
+        &self,
+        conversation_id: i32,
+    ) -> Result<Option<MessageDTO>, String> {
+        let mut result = messages::Entity::find()
+            .find_with_related(contents::Entity)
+            .filter(messages::Column::ConversationId.eq(conversation_id))
+            .filter(messages::Column::Role.eq(Into::<i32>::into(messages::Roles::System)))
+            .filter(messages::Column::DeletedAt.is_null())
+            .all(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!(
+                    "Failed to get system message of conversation with id = {}",
+                    conversation_id
+                )
+            })?;
+        let dto = result.pop().map(|data| MessageDTO::from(data));
+        Ok(dto)
+    }
+
+    /**
+     * Set the text of the system message of a conversation, creating the message when
+     * there is none yet and removing it when the text is empty
+     */
+    pub async fn set_system_prompt(
+        &self,
+        conversation_id: i32,
+        prompt: Option<String>,
+    ) -> Result<Option<MessageDTO>, String> {
+        let prompt = prompt.filter(|prompt| !prompt.trim().is_empty());
+        let system_message = self.get_system_message(conversation_id).await?;
+        match (system_message, prompt) {
+            (Some(message), Some(prompt)) => {
+                let message = MessageDTO {
+                    content: vec![ContentDTO {
+                        r#type: contents::ContentType::Text,
+                        mimetype: None,
+                        data: prompt,
+                    }],
+                    ..message
+                };
+                self.update_message(message).await.map(Some)
+            }
+            (None, Some(prompt)) => {
+                let message = MessageDTO {
+                    conversation_id,
+                    role: messages::Roles::System.into(),
+                    content: vec![ContentDTO {
+                        r#type: contents::ContentType::Text,
+                        mimetype: None,
+                        data: prompt,
+                    }],
+                    ..Default::default()
+                };
+                self.create_message(message).await.map(Some)
+            }
+            (Some(message), None) => {
+                let message_id = message.id.ok_or("Message id is missing")?;
+                messages::ActiveModel {
+                    id: Set(message_id),
+                    deleted_at: Set(Some(chrono::Local::now())),
+                    ..Default::default()
+                }
+                .update(&self.connection)
+                .await
+                .map_err(|err| {
+                    error!("{}", err);
+                    format!(
+                        "Failed to remove system message of conversation with id = {}",
+                        conversation_id
+                    )
+                })?;
+                Ok(None)
+            }
+            (None, None) => Ok(None),
+        }
+    }
+
+    /**
+     * Update the system message of a conversation
+     */
+    pub async fn update_message(&self, message: MessageDTO) -> Result<MessageDTO, String> {
+        let message_id = message.id.ok_or("Message id is missing")?;
+        // The system message is never part of the summary
+        if messages::Roles::from(message.role) != messages::Roles::System {
+            self.invalidate_history_summary(message.conversation_id, message_id)
+                .await?;
+        }
+        let contents = message.content.clone();
+        let mut msg_am = message.into_active_model();
+        msg_am.updated_at = Set(Some(chrono::Local::now()));
+        let result = self
+            .connection
+            .transaction::<_, MessageDTO, DbErr>(|txn| {
+                Box::pin(async move {
+                    // Update messge first
+                    let msg_m = msg_am.update(txn).await?;
+                    // Delete old content (hard delete)
+                    contents::Entity::delete_many()
+                        .filter(contents::Column::MessageId.eq(msg_m.id))
+                        .exec(txn)
+                        .await?;
+                    // Insert new content
+                    let ctnt_ams: Vec<contents::ActiveModel> = contents
+                        .into_iter()
+                        .map(|content| {
                             let mut ctnt_am: contents::ActiveModel = content.into_active_model();
                             ctnt_am.message_id = Set(msg_m.id);
                             ctnt_am
@@ -803,56 +2030,1211 @@ impl Repository {
             })
             .await
             .map_err(|err| {
-                error!(
-                    "Failed to update message with contents (id={}): {}",
-                    message_id, err
-                );
-                err.to_string()
-            })?;
-        // let mut active_model = message.into_active_model();
-        // // active_model.reset(messages::Column::Content);
-        // active_model.updated_at = Set(Some(chrono::Local::now()));
-        // let result = active_model
-        //     .update(&self.connection)
-        //     .await
-        //     .map_err(|err| {
-        //         error!("{}", err);
-        //         "Failed to update message".to_string()
-        //     })?;
-        Ok(result)
+                error!(
+                    "Failed to update message with contents (id={}): {}",
+                    message_id, err
+                );
+                err.to_string()
+            })?;
+        // let mut active_model = message.into_active_model();
+        // // active_model.reset(messages::Column::Content);
+        // active_model.updated_at = Set(Some(chrono::Local::now()));
+        // let result = active_model
+        //     .update(&self.connection)
+        //     .await
+        //     .map_err(|err| {
+        //         error!("{}", err);
+        //         "Failed to update message".to_string()
+        //     })?;
+        Ok(result)
+    }
+
+    /**
+     * Replace the contents of a message, keeping the rest of it as stored
+     */
+    pub async fn update_message_content(
+        &self,
+        message_id: i32,
+        content: Vec<ContentDTO>,
+    ) -> Result<MessageDTO, String> {
+        let message = self.get_message(message_id).await?;
+        self.update_message(MessageDTO { content, ..message }).await
+    }
+
+    /**
+     * Hard delete all messages of a conversation
+     */
+    pub async fn hard_delete_messages(&self, conversation_id: i32) -> Result<(), String> {
+        self.invalidate_history_summary(conversation_id, 0).await?;
+        messages::Entity::delete_many()
+            .filter(messages::Column::ConversationId.eq(conversation_id))
+            .exec(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!(
+                    "Failed to delete messages of conversation with id {}",
+                    conversation_id
+                )
+            })?;
+        Ok(())
+    }
+
+    /**
+     * Hard delete a message
+     */
+    pub async fn hard_delete_message(&self, message: MessageDTO) -> Result<MessageDTO, String> {
+        let message_id = message.id.ok_or("Message id is missing")?;
+        self.invalidate_history_summary(message.conversation_id, message_id)
+            .await?;
+        messages::Entity::delete_by_id(message_id)
+            .exec(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                "Failed to delete message".to_string()
+            })?;
+        Ok(message)
+    }
+
+    /**
+     * Remove a message from its conversation
+     */
+    pub async fn delete_message(&self, message_id: i32) -> Result<MessageDTO, String> {
+        let message = self.get_message(message_id).await?;
+        self.invalidate_history_summary(message.conversation_id, message_id)
+            .await?;
+        messages::ActiveModel {
+            id: Set(message_id),
+            deleted_at: Set(Some(chrono::Local::now())),
+            ..Default::default()
+        }
+        .update(&self.connection)
+        .await
+        .map_err(|err| {
+            error!("{}", err);
+            format!("Failed to delete message with id = {}", message_id)
+        })?;
+        Ok(message)
+    }
+
+    /**
+     * Remove a message and every message sent after it in its conversation, keeping
+     * the system message. Returns the number of removed messages.
+     */
+    pub async fn delete_messages_from(&self, message: &MessageDTO) -> Result<u64, String> {
+        let message_id = message.id.ok_or("Message id is missing")?;
+        self.invalidate_history_summary(message.conversation_id, message_id)
+            .await?;
+        let result = messages::Entity::update_many()
+            .col_expr(
+                messages::Column::DeletedAt,
+                sea_query::Expr::value(chrono::Local::now()),
+            )
+            .filter(messages::Column::ConversationId.eq(message.conversation_id))
+            .filter(messages::Column::Role.ne(Into::<i32>::into(messages::Roles::System)))
+            .filter(messages::Column::Id.gte(message_id))
+            .filter(messages::Column::DeletedAt.is_null())
+            .filter(messages::Column::IsHidden.eq(false))
+            .exec(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!(
+                    "Failed to delete messages of conversation with id = {} from message {}",
+                    message.conversation_id, message_id
+                )
+            })?;
+        Ok(result.rows_affected)
+    }
+
+    /**
+     * Get the id of the latest shown message of a conversation, other than its
+     * system message
+     */
+    async fn get_last_message_id(&self, conversation_id: i32) -> Result<Option<i32>, String> {
+        messages::Entity::find()
+            .select_only()
+            .column(messages::Column::Id)
+            .filter(messages::Column::ConversationId.eq(conversation_id))
+            .filter(messages::Column::Role.ne(Into::<i32>::into(messages::Roles::System)))
+            .filter(messages::Column::DeletedAt.is_null())
+            .filter(messages::Column::IsHidden.eq(false))
+            .order_by_desc(messages::Column::Id)
+            .into_tuple()
+            .one(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!(
+                    "Failed to get last message of conversation with id = {}",
+                    conversation_id
+                )
+            })
+    }
+
+    /**
+     * List the messages of all the branches of a conversation, without their contents.
+     * Deleted messages aren't part of any branch.
+     */
+    async fn list_message_tree(&self, conversation_id: i32) -> Result<Vec<Message>, String> {
+        messages::Entity::find()
+            .filter(messages::Column::ConversationId.eq(conversation_id))
+            .filter(messages::Column::Role.ne(Into::<i32>::into(messages::Roles::System)))
+            .filter(messages::Column::DeletedAt.is_null())
+            .order_by_asc(messages::Column::Id)
+            .all(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!(
+                    "Failed to list message tree of conversation with id = {}",
+                    conversation_id
+                )
+            })
+    }
+
+    /**
+     * List the places of a conversation where its messages split into branches
+     */
+    pub async fn list_message_branches(
+        &self,
+        conversation_id: i32,
+    ) -> Result<Vec<MessageBranch>, String> {
+        let tree = self.list_message_tree(conversation_id).await?;
+        Ok(branches::list_branches(&tree))
+    }
+
+    /**
+     * Replace a message with a new version in a branch of its own, hiding the message
+     * and everything after it
+     */
+    pub async fn branch_message(
+        &self,
+        message_id: i32,
+        content: Vec<ContentDTO>,
+    ) -> Result<MessageDTO, String> {
+        let message = self.get_message(message_id).await?;
+        self.invalidate_history_summary(message.conversation_id, message_id)
+            .await?;
+        let tree = self.list_message_tree(message.conversation_id).await?;
+        let hidden_ids = branches::descendants(&tree, message_id);
+        let new_message = MessageDTO {
+            conversation_id: message.conversation_id,
+            parent_message_id: message.parent_message_id,
+            role: message.role,
+            content,
+            ..Default::default()
+        };
+        self.connection
+            .transaction::<_, MessageDTO, DbErr>(|txn| {
+                Box::pin(async move {
+                    messages::Entity::update_many()
+                        .col_expr(messages::Column::IsHidden, sea_query::Expr::value(true))
+                        .filter(messages::Column::Id.is_in(hidden_ids))
+                        .exec(txn)
+                        .await?;
+                    insert_message(txn, new_message).await
+                })
+            })
+            .await
+            .map_err(|err| {
+                error!("Failed to branch message: {}", err);
+                format!("Failed to branch message with id = {}", message_id)
+            })
+    }
+
+    /**
+     * Show the branch of a message instead of its siblings', following the latest
+     * reply of each message down the branch
+     */
+    pub async fn switch_message_branch(
+        &self,
+        conversation_id: i32,
+        message_id: i32,
+    ) -> Result<(), String> {
+        let tree = self.list_message_tree(conversation_id).await?;
+        if !tree.iter().any(|message| message.id == message_id) {
+            return Err(format!(
+                "Message with id {} isn't part of conversation {}",
+                message_id, conversation_id
+            ));
+        }
+        let (hidden_ids, shown_ids) = branches::switch_branch(&tree, message_id);
+        if let Some(first_id) = hidden_ids.iter().chain(shown_ids.iter()).min() {
+            self.invalidate_history_summary(conversation_id, *first_id)
+                .await?;
+        }
+        self.connection
+            .transaction::<_, (), DbErr>(|txn| {
+                Box::pin(async move {
+                    messages::Entity::update_many()
+                        .col_expr(messages::Column::IsHidden, sea_query::Expr::value(true))
+                        .filter(messages::Column::Id.is_in(hidden_ids))
+                        .exec(txn)
+                        .await?;
+                    messages::Entity::update_many()
+                        .col_expr(messages::Column::IsHidden, sea_query::Expr::value(false))
+                        .filter(messages::Column::Id.is_in(shown_ids))
+                        .exec(txn)
+                        .await?;
+                    Ok(())
+                })
+            })
+            .await
+            .map_err(|err| {
+                error!("Failed to switch branch: {}", err);
+                format!(
+                    "Failed to switch to branch of message with id = {}",
+                    message_id
+                )
+            })
+    }
+
+    /**
+     * Rate or react to a message, replacing its previous feedback
+     */
+    pub async fn upsert_message_feedback(
+        &self,
+        message_id: i32,
+        rating: Option<Rating>,
+        emoji: Option<String>,
+    ) -> Result<MessageFeedback, String> {
+        let active_model = message_feedback::ActiveModel {
+            message_id: Set(message_id),
+            rating: Set(rating),
+            emoji: Set(emoji),
+            created_at: Set(chrono::Local::now()),
+            updated_at: Set(Some(chrono::Local::now())),
+            ..Default::default()
+        };
+        message_feedback::Entity::insert(active_model)
+            .on_conflict(
+                sea_query::OnConflict::column(message_feedback::Column::MessageId)
+                    .update_columns([
+                        message_feedback::Column::Rating,
+                        message_feedback::Column::Emoji,
+                        message_feedback::Column::UpdatedAt,
+                    ])
+                    .to_owned(),
+            )
+            .exec(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!(
+                    "Failed to save feedback of message with id = {}",
+                    message_id
+                )
+            })?;
+        message_feedback::Entity::find()
+            .filter(message_feedback::Column::MessageId.eq(message_id))
+            .one(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!("Failed to get feedback of message with id = {}", message_id)
+            })?
+            .ok_or(format!("Feedback of message {} doesn't exist", message_id))
+    }
+
+    /**
+     * Remove the feedback of a message
+     */
+    pub async fn delete_message_feedback(&self, message_id: i32) -> Result<(), String> {
+        message_feedback::Entity::delete_many()
+            .filter(message_feedback::Column::MessageId.eq(message_id))
+            .exec(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!(
+                    "Failed to delete feedback of message with id = {}",
+                    message_id
+                )
+            })?;
+        Ok(())
+    }
+
+    /**
+     * List the feedback given to messages of a conversation
+     */
+    pub async fn list_message_feedback(
+        &self,
+        conversation_id: i32,
+    ) -> Result<Vec<MessageFeedback>, String> {
+        let result = message_feedback::Entity::find()
+            .join(
+                JoinType::InnerJoin,
+                message_feedback::Relation::Messages.def(),
+            )
+            .filter(messages::Column::ConversationId.eq(conversation_id))
+            .all(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!(
+                    "Failed to list feedback of conversation with id = {}",
+                    conversation_id
+                )
+            })?;
+        Ok(result)
+    }
+
+    /**
+     * Count thumbs up and down received by each model
+     */
+    pub async fn list_model_feedback_stats(&self) -> Result<Vec<ModelFeedbackStats>, String> {
+        // Ratings of replies written by a fallback model go to that model
+        let sql = format!(
+            "SELECT mo.id AS model_id, mo.alias AS model_alias, \
+            SUM(CASE WHEN f.rating = 'up' THEN 1 ELSE 0 END) AS thumbs_up, \
+            SUM(CASE WHEN f.rating = 'down' THEN 1 ELSE 0 END) AS thumbs_down \
+            FROM message_feedback f \
+            JOIN messages m ON m.id = f.message_id \
+            JOIN conversations c ON c.id = m.conversation_id \
+            JOIN models mo ON mo.id = {} \
+            WHERE f.rating IS NOT NULL \
+            GROUP BY mo.id",
+            REPLY_MODEL_ID_SQL
+        );
+        let result =
+            ModelFeedbackStats::find_by_statement(Statement::from_string(DbBackend::Sqlite, sql))
+                .all(&self.connection)
+                .await
+                .map_err(|err| {
+                    error!("{}", err);
+                    "Failed to list model feedback stats".to_string()
+                })?;
+        Ok(result)
+    }
+
+    /**
+     * Record the current messages of a conversation as a snapshot
+     */
+    pub async fn create_snapshot(
+        &self,
+        conversation_id: i32,
+        label: String,
+    ) -> Result<Snapshot, String> {
+        let result = self
+            .connection
+            .transaction::<_, Snapshot, DbErr>(|txn| {
+                Box::pin(async move {
+                    let snapshot = snapshots::ActiveModel {
+                        conversation_id: Set(conversation_id),
+                        label: Set(label),
+                        created_at: Set(chrono::Local::now()),
+                        ..Default::default()
+                    }
+                    .insert(txn)
+                    .await?;
+                    let message_ids: Vec<i32> = messages::Entity::find()
+                        .select_only()
+                        .column(messages::Column::Id)
+                        .filter(messages::Column::ConversationId.eq(conversation_id))
+                        .filter(messages::Column::DeletedAt.is_null())
+                        .filter(messages::Column::IsHidden.eq(false))
+                        .into_tuple()
+                        .all(txn)
+                        .await?;
+                    if !message_ids.is_empty() {
+                        let snapshot_messages = message_ids.into_iter().map(|message_id| {
+                            snapshot_messages::ActiveModel {
+                                snapshot_id: Set(snapshot.id),
+                                message_id: Set(message_id),
+                            }
+                        });
+                        snapshot_messages::Entity::insert_many(snapshot_messages)
+                            .exec(txn)
+                            .await?;
+                    }
+                    Ok(snapshot)
+                })
+            })
+            .await
+            .map_err(|err| {
+                error!("Failed to create snapshot: {}", err);
+                format!(
+                    "Failed to create snapshot of conversation with id = {}",
+                    conversation_id
+                )
+            })?;
+        Ok(result)
+    }
+
+    /**
+     * List the snapshots of a conversation, newest first
+     */
+    pub async fn list_snapshots(&self, conversation_id: i32) -> Result<Vec<Snapshot>, String> {
+        let result = snapshots::Entity::find()
+            .filter(snapshots::Column::ConversationId.eq(conversation_id))
+            .order_by_desc(snapshots::Column::Id)
+            .all(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!(
+                    "Failed to list snapshots of conversation with id = {}",
+                    conversation_id
+                )
+            })?;
+        Ok(result)
+    }
+
+    pub async fn get_snapshot(&self, snapshot_id: i32) -> Result<Snapshot, String> {
+        snapshots::Entity::find_by_id(snapshot_id)
+            .one(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!("Failed to get snapshot with id {}", snapshot_id)
+            })?
+            .ok_or(format!("Snapshot with id {} doesn't exist", snapshot_id))
+    }
+
+    /**
+     * Roll the conversation of a snapshot back to it: messages added since are soft
+     * deleted and messages deleted since are brought back. Soft deleted messages stay
+     * in the snapshots they belong to, so restoring a later snapshot brings them back.
+     */
+    pub async fn restore_snapshot(&self, snapshot_id: i32) -> Result<Snapshot, String> {
+        let snapshot = self.get_snapshot(snapshot_id).await?;
+        let conversation_id = snapshot.conversation_id;
+        self.invalidate_history_summary(conversation_id, 0).await?;
+        self.connection
+            .transaction::<_, (), DbErr>(|txn| {
+                Box::pin(async move {
+                    let message_ids: Vec<i32> = snapshot_messages::Entity::find()
+                        .select_only()
+                        .column(snapshot_messages::Column::MessageId)
+                        .filter(snapshot_messages::Column::SnapshotId.eq(snapshot_id))
+                        .into_tuple()
+                        .all(txn)
+                        .await?;
+                    messages::Entity::update_many()
+                        .col_expr(
+                            messages::Column::DeletedAt,
+                            sea_query::Expr::value(chrono::Local::now()),
+                        )
+                        .filter(messages::Column::ConversationId.eq(conversation_id))
+                        .filter(messages::Column::Id.is_not_in(message_ids.clone()))
+                        .filter(messages::Column::DeletedAt.is_null())
+                        .exec(txn)
+                        .await?;
+                    messages::Entity::update_many()
+                        .col_expr(
+                            messages::Column::DeletedAt,
+                            sea_query::Expr::value(Option::<chrono::DateTime<chrono::Local>>::None),
+                        )
+                        .col_expr(messages::Column::IsHidden, sea_query::Expr::value(false))
+                        .filter(messages::Column::Id.is_in(message_ids))
+                        .exec(txn)
+                        .await?;
+                    Ok(())
+                })
+            })
+            .await
+            .map_err(|err| {
+                error!("Failed to restore snapshot: {}", err);
+                format!("Failed to restore snapshot with id = {}", snapshot_id)
+            })?;
+        Ok(snapshot)
+    }
+
+    pub async fn delete_snapshot(&self, snapshot_id: i32) -> Result<(), String> {
+        snapshots::Entity::delete_by_id(snapshot_id)
+            .exec(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!("Failed to delete snapshot with id = {}", snapshot_id)
+            })?;
+        Ok(())
+    }
+
+    /**
+     * Queue prompts to be sent to a model as one batch
+     */
+    pub async fn create_batch(&self, model_id: i32, prompts: Vec<String>) -> Result<Batch, String> {
+        let result = self
+            .connection
+            .transaction::<_, Batch, DbErr>(|txn| {
+                Box::pin(async move {
+                    let batch = batches::ActiveModel {
+                        model_id: Set(model_id),
+                        status: Set(BatchStatus::Queued),
+                        created_at: Set(chrono::Local::now()),
+                        ..Default::default()
+                    }
+                    .insert(txn)
+                    .await?;
+                    let items = prompts.into_iter().map(|prompt| batch_items::ActiveModel {
+                        batch_id: Set(batch.id),
+                        prompt: Set(prompt),
+                        ..Default::default()
+                    });
+                    batch_items::Entity::insert_many(items).exec(txn).await?;
+                    Ok(batch)
+                })
+            })
+            .await
+            .map_err(|err| {
+                error!("Failed to create batch: {}", err);
+                "Failed to create batch".to_string()
+            })?;
+        Ok(result)
+    }
+
+    /**
+     * List all batches, newest first
+     */
+    pub async fn list_batches(&self) -> Result<Vec<Batch>, String> {
+        let result = batches::Entity::find()
+            .order_by_desc(batches::Column::CreatedAt)
+            .all(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                "Failed to list batches".to_string()
+            })?;
+        Ok(result)
+    }
+
+    /**
+     * List the batches with the given status, oldest first
+     */
+    pub async fn list_batches_by_status(&self, status: BatchStatus) -> Result<Vec<Batch>, String> {
+        let result = batches::Entity::find()
+            .filter(batches::Column::Status.eq(status))
+            .order_by_asc(batches::Column::CreatedAt)
+            .all(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                "Failed to list batches".to_string()
+            })?;
+        Ok(result)
+    }
+
+    pub async fn get_batch(&self, batch_id: i32) -> Result<Batch, String> {
+        batches::Entity::find_by_id(batch_id)
+            .one(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!("Failed to get batch with id {}", batch_id)
+            })?
+            .ok_or(format!("Batch with id {} doesn't exist", batch_id))
+    }
+
+    /**
+     * List the prompts of a batch
+     */
+    pub async fn list_batch_items(&self, batch_id: i32) -> Result<Vec<BatchItem>, String> {
+        let result = batch_items::Entity::find()
+            .filter(batch_items::Column::BatchId.eq(batch_id))
+            .order_by_asc(batch_items::Column::Id)
+            .all(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!("Failed to list items of batch with id = {}", batch_id)
+            })?;
+        Ok(result)
+    }
+
+    /**
+     * Update the status of a batch, with its remote id once submitted and the error if any
+     */
+    pub async fn update_batch_status(
+        &self,
+        batch_id: i32,
+        status: BatchStatus,
+        remote_id: Option<String>,
+        error: Option<String>,
+    ) -> Result<Batch, String> {
+        let mut active_model = batches::ActiveModel {
+            id: Set(batch_id),
+            status: Set(status),
+            error: Set(error),
+            updated_at: Set(Some(chrono::Local::now())),
+            ..Default::default()
+        };
+        if remote_id.is_some() {
+            active_model.remote_id = Set(remote_id);
+        }
+        let result = active_model.update(&self.connection).await.map_err(|err| {
+            error!("{}", err);
+            format!("Failed to update batch with id = {}", batch_id)
+        })?;
+        Ok(result)
+    }
+
+    /**
+     * Record the results of a batch and mark it as completed, in one transaction so a
+     * failure leaves the batch to be written again by the next poll.
+     * Each reply becomes a new conversation holding the prompt of its item.
+     */
+    pub async fn complete_batch(
+        &self,
+        batch: &Batch,
+        results: Vec<(BatchItem, Result<BotReply, String>)>,
+    ) -> Result<(), String> {
+        let batch_id = batch.id;
+        let model = self.get_model(batch.model_id).await?;
+        self.connection
+            .transaction::<_, (), DbErr>(|txn| {
+                Box::pin(async move {
+                    for (item, result) in results {
+                        let (conversation_id, error) = match result {
+                            Ok(reply) => {
+                                let mut conv_am: ActiveConversation = Conversation {
+                                    model_id: Some(model.id),
+                                    subject: item.prompt.clone(),
+                                    ..Default::default()
+                                }
+                                .into();
+                                conv_am.id = ActiveValue::NotSet;
+                                conv_am.options = Set(Some(model_options(&model)));
+                                conv_am.created_at = Set(chrono::Local::now());
+                                conv_am.last_message_at = Set(Some(chrono::Local::now()));
+                                let conversation = conv_am.insert(txn).await?;
+                                let prompt = insert_message(
+                                    txn,
+                                    MessageDTO {
+                                        conversation_id: conversation.id,
+                                        role: messages::Roles::User.into(),
+                                        content: vec![ContentDTO {
+                                            r#type: contents::ContentType::Text,
+                                            mimetype: None,
+                                            data: item.prompt,
+                                        }],
+                                        ..Default::default()
+                                    },
+                                )
+                                .await?;
+                                insert_message(
+                                    txn,
+                                    MessageDTO {
+                                        parent_message_id: prompt.id,
+                                        ..reply.into_message(conversation.id)
+                                    },
+                                )
+                                .await?;
+                                (Some(conversation.id), None)
+                            }
+                            Err(err) => (None, Some(err)),
+                        };
+                        batch_items::ActiveModel {
+                            id: Set(item.id),
+                            conversation_id: Set(conversation_id),
+                            error: Set(error),
+                            ..Default::default()
+                        }
+                        .update(txn)
+                        .await?;
+                    }
+                    batches::ActiveModel {
+                        id: Set(batch_id),
+                        status: Set(BatchStatus::Completed),
+                        error: Set(None),
+                        updated_at: Set(Some(chrono::Local::now())),
+                        ..Default::default()
+                    }
+                    .update(txn)
+                    .await?;
+                    Ok(())
+                })
+            })
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!(
+                    "Failed to write the results of batch with id = {}",
+                    batch_id
+                )
+            })
+    }
+
+    /**
+     * Record a fine-tuning job started at the provider of a model
+     */
+    pub async fn create_finetune_job(
+        &self,
+        model_id: i32,
+        remote_id: String,
+        training_file_id: String,
+        status: FinetuneJobStatus,
+    ) -> Result<FinetuneJob, String> {
+        let result = finetune_jobs::ActiveModel {
+            model_id: Set(model_id),
+            remote_id: Set(remote_id),
+            training_file_id: Set(training_file_id),
+            status: Set(status),
+            created_at: Set(chrono::Local::now()),
+            ..Default::default()
+        }
+        .insert(&self.connection)
+        .await
+        .map_err(|err| {
+            error!("{}", err);
+            "Failed to create fine-tuning job".to_string()
+        })?;
+        Ok(result)
+    }
+
+    /**
+     * List all fine-tuning jobs, newest first
+     */
+    pub async fn list_finetune_jobs(&self) -> Result<Vec<FinetuneJob>, String> {
+        let result = finetune_jobs::Entity::find()
+            .order_by_desc(finetune_jobs::Column::CreatedAt)
+            .all(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                "Failed to list fine-tuning jobs".to_string()
+            })?;
+        Ok(result)
+    }
+
+    /**
+     * Update a fine-tuning job with its latest state
+     */
+    pub async fn update_finetune_job(&self, job: FinetuneJob) -> Result<FinetuneJob, String> {
+        let job_id = job.id;
+        let mut active_model: finetune_jobs::ActiveModel = job.into();
+        active_model.reset(finetune_jobs::Column::Status);
+        active_model.reset(finetune_jobs::Column::FineTunedModel);
+        active_model.reset(finetune_jobs::Column::ResultModelId);
+        active_model.reset(finetune_jobs::Column::Error);
+        active_model.updated_at = Set(Some(chrono::Local::now()));
+        let result = active_model.update(&self.connection).await.map_err(|err| {
+            error!("{}", err);
+            format!("Failed to update fine-tuning job with id = {}", job_id)
+        })?;
+        Ok(result)
+    }
+
+    /**
+     * Create an empty knowledge collection, synced with the credentials of a model
+     */
+    pub async fn create_collection(
+        &self,
+        name: String,
+        model_id: i32,
+    ) -> Result<Collection, String> {
+        let result = collections::ActiveModel {
+            name: Set(name),
+            model_id: Set(model_id),
+            status: Set(SyncStatus::Pending),
+            created_at: Set(chrono::Local::now()),
+            ..Default::default()
+        }
+        .insert(&self.connection)
+        .await
+        .map_err(|err| {
+            error!("{}", err);
+            "Failed to create collection".to_string()
+        })?;
+        Ok(result)
+    }
+
+    /**
+     * List all knowledge collections with their sync status
+     */
+    pub async fn list_collections(&self) -> Result<Vec<Collection>, String> {
+        let result = collections::Entity::find()
+            .order_by_asc(collections::Column::Id)
+            .all(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                "Failed to list collections".to_string()
+            })?;
+        Ok(result)
+    }
+
+    pub async fn get_collection(&self, collection_id: i32) -> Result<Collection, String> {
+        collections::Entity::find_by_id(collection_id)
+            .one(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!("Failed to get collection with id {}", collection_id)
+            })?
+            .ok_or(format!(
+                "Collection with id {} doesn't exist",
+                collection_id
+            ))
+    }
+
+    /**
+     * Update the sync status of a collection, with its vector store id once created and the error if any
+     */
+    pub async fn update_collection_status(
+        &self,
+        collection_id: i32,
+        status: SyncStatus,
+        vector_store_id: Option<String>,
+        error: Option<String>,
+    ) -> Result<Collection, String> {
+        let mut active_model = collections::ActiveModel {
+            id: Set(collection_id),
+            error: Set(error),
+            ..Default::default()
+        };
+        if status == SyncStatus::Synced {
+            active_model.last_synced_at = Set(Some(chrono::Local::now()));
+        }
+        active_model.status = Set(status);
+        if vector_store_id.is_some() {
+            active_model.vector_store_id = Set(vector_store_id);
+        }
+        let result = active_model.update(&self.connection).await.map_err(|err| {
+            error!("{}", err);
+            format!("Failed to update collection with id = {}", collection_id)
+        })?;
+        Ok(result)
+    }
+
+    /**
+     * Add local files to a collection. They are uploaded by the next sync.
+     */
+    pub async fn add_collection_files(
+        &self,
+        collection_id: i32,
+        paths: Vec<String>,
+    ) -> Result<Vec<CollectionFile>, String> {
+        let result = self
+            .connection
+            .transaction::<_, Vec<CollectionFile>, DbErr>(|txn| {
+                Box::pin(async move {
+                    let mut files = vec![];
+                    for path in paths {
+                        let file = collection_files::ActiveModel {
+                            collection_id: Set(collection_id),
+                            path: Set(path),
+                            ..Default::default()
+                        }
+                        .insert(txn)
+                        .await?;
+                        files.push(file);
+                    }
+                    collections::ActiveModel {
+                        id: Set(collection_id),
+                        status: Set(SyncStatus::Pending),
+                        ..Default::default()
+                    }
+                    .update(txn)
+                    .await?;
+                    Ok(files)
+                })
+            })
+            .await
+            .map_err(|err| {
+                error!("Failed to add files to collection: {}", err);
+                format!(
+                    "Failed to add files to collection with id = {}",
+                    collection_id
+                )
+            })?;
+        Ok(result)
+    }
+
+    /**
+     * List the files of a collection. Removed files waiting for the next sync are only listed on demand.
+     */
+    pub async fn list_collection_files(
+        &self,
+        collection_id: i32,
+        include_removed: bool,
+    ) -> Result<Vec<CollectionFile>, String> {
+        let mut query = collection_files::Entity::find()
+            .filter(collection_files::Column::CollectionId.eq(collection_id));
+        if !include_removed {
+            query = query.filter(collection_files::Column::DeletedAt.is_null());
+        }
+        let result = query
+            .order_by_asc(collection_files::Column::Id)
+            .all(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!(
+                    "Failed to list files of collection with id = {}",
+                    collection_id
+                )
+            })?;
+        Ok(result)
+    }
+
+    /**
+     * Remove a file from its collection. The row is kept until the next sync removes it remotely.
+     */
+    pub async fn remove_collection_file(&self, file_id: i32) -> Result<CollectionFile, String> {
+        let file = collection_files::ActiveModel {
+            id: Set(file_id),
+            deleted_at: Set(Some(chrono::Local::now())),
+            ..Default::default()
+        }
+        .update(&self.connection)
+        .await
+        .map_err(|err| {
+            error!("{}", err);
+            format!("Failed to remove collection file with id = {}", file_id)
+        })?;
+        collections::ActiveModel {
+            id: Set(file.collection_id),
+            status: Set(SyncStatus::Pending),
+            ..Default::default()
+        }
+        .update(&self.connection)
+        .await
+        .map_err(|err| {
+            error!("{}", err);
+            format!(
+                "Failed to update collection with id = {}",
+                file.collection_id
+            )
+        })?;
+        Ok(file)
+    }
+
+    /**
+     * Record the upload of a collection file
+     */
+    pub async fn update_collection_file_upload(
+        &self,
+        file_id: i32,
+        fingerprint: String,
+        remote_file_id: String,
+    ) -> Result<(), String> {
+        collection_files::ActiveModel {
+            id: Set(file_id),
+            fingerprint: Set(Some(fingerprint)),
+            remote_file_id: Set(Some(remote_file_id)),
+            ..Default::default()
+        }
+        .update(&self.connection)
+        .await
+        .map_err(|err| {
+            error!("{}", err);
+            format!("Failed to update collection file with id = {}", file_id)
+        })?;
+        Ok(())
+    }
+
+    /**
+     * Delete a removed collection file for good, once it's gone from the vector store
+     */
+    pub async fn hard_delete_collection_file(&self, file_id: i32) -> Result<(), String> {
+        collection_files::Entity::delete_by_id(file_id)
+            .exec(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!("Failed to delete collection file with id = {}", file_id)
+            })?;
+        Ok(())
+    }
+
+    /**
+     * Create an eval set with its test prompts
+     */
+    pub async fn create_eval_set(
+        &self,
+        name: String,
+        judge_model_id: Option<i32>,
+        cases: Vec<NewEvalCase>,
+    ) -> Result<EvalSet, String> {
+        let result = self
+            .connection
+            .transaction::<_, EvalSet, DbErr>(|txn| {
+                Box::pin(async move {
+                    let eval_set = eval_sets::ActiveModel {
+                        name: Set(name),
+                        judge_model_id: Set(judge_model_id),
+                        created_at: Set(chrono::Local::now()),
+                        ..Default::default()
+                    }
+                    .insert(txn)
+                    .await?;
+                    let cases = cases.into_iter().map(|case| eval_cases::ActiveModel {
+                        eval_set_id: Set(eval_set.id),
+                        prompt: Set(case.prompt),
+                        expected: Set(case.expected),
+                        ..Default::default()
+                    });
+                    eval_cases::Entity::insert_many(cases).exec(txn).await?;
+                    Ok(eval_set)
+                })
+            })
+            .await
+            .map_err(|err| {
+                error!("Failed to create eval set: {}", err);
+                "Failed to create eval set".to_string()
+            })?;
+        Ok(result)
+    }
+
+    /**
+     * List all eval sets, newest first
+     */
+    pub async fn list_eval_sets(&self) -> Result<Vec<EvalSet>, String> {
+        let result = eval_sets::Entity::find()
+            .order_by_desc(eval_sets::Column::CreatedAt)
+            .all(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                "Failed to list eval sets".to_string()
+            })?;
+        Ok(result)
+    }
+
+    pub async fn get_eval_set(&self, eval_set_id: i32) -> Result<EvalSet, String> {
+        eval_sets::Entity::find_by_id(eval_set_id)
+            .one(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!("Failed to get eval set with id {}", eval_set_id)
+            })?
+            .ok_or(format!("Eval set with id {} doesn't exist", eval_set_id))
+    }
+
+    /**
+     * Delete an eval set with its cases, runs and results
+     */
+    pub async fn delete_eval_set(&self, eval_set_id: i32) -> Result<(), String> {
+        eval_sets::Entity::delete_by_id(eval_set_id)
+            .exec(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!("Failed to delete eval set with id = {}", eval_set_id)
+            })?;
+        Ok(())
+    }
+
+    /**
+     * List the test prompts of an eval set
+     */
+    pub async fn list_eval_cases(&self, eval_set_id: i32) -> Result<Vec<EvalCase>, String> {
+        let result = eval_cases::Entity::find()
+            .filter(eval_cases::Column::EvalSetId.eq(eval_set_id))
+            .order_by_asc(eval_cases::Column::Id)
+            .all(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!("Failed to list cases of eval set with id = {}", eval_set_id)
+            })?;
+        Ok(result)
+    }
+
+    /**
+     * Start recording a run of an eval set
+     */
+    pub async fn create_eval_run(&self, eval_set_id: i32) -> Result<EvalRun, String> {
+        let result = eval_runs::ActiveModel {
+            eval_set_id: Set(eval_set_id),
+            created_at: Set(chrono::Local::now()),
+            ..Default::default()
+        }
+        .insert(&self.connection)
+        .await
+        .map_err(|err| {
+            error!("{}", err);
+            "Failed to create eval run".to_string()
+        })?;
+        Ok(result)
+    }
+
+    pub async fn finish_eval_run(&self, run_id: i32) -> Result<EvalRun, String> {
+        let result = eval_runs::ActiveModel {
+            id: Set(run_id),
+            finished_at: Set(Some(chrono::Local::now())),
+            ..Default::default()
+        }
+        .update(&self.connection)
+        .await
+        .map_err(|err| {
+            error!("{}", err);
+            format!("Failed to update eval run with id = {}", run_id)
+        })?;
+        Ok(result)
+    }
+
+    pub async fn get_eval_run(&self, run_id: i32) -> Result<EvalRun, String> {
+        eval_runs::Entity::find_by_id(run_id)
+            .one(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!("Failed to get eval run with id {}", run_id)
+            })?
+            .ok_or(format!("Eval run with id {} doesn't exist", run_id))
     }
 
     /**
-     * Hard delete all messages of a conversation
+     * List the runs of an eval set, newest first
      */
-    pub async fn hard_delete_messages(&self, conversation_id: i32) -> Result<(), String> {
-        messages::Entity::delete_many()
-            .filter(messages::Column::ConversationId.eq(conversation_id))
-            .exec(&self.connection)
+    pub async fn list_eval_runs(&self, eval_set_id: i32) -> Result<Vec<EvalRun>, String> {
+        let result = eval_runs::Entity::find()
+            .filter(eval_runs::Column::EvalSetId.eq(eval_set_id))
+            .order_by_desc(eval_runs::Column::CreatedAt)
+            .all(&self.connection)
             .await
             .map_err(|err| {
                 error!("{}", err);
-                format!(
-                    "Failed to delete messages of conversation with id {}",
-                    conversation_id
-                )
+                format!("Failed to list runs of eval set with id = {}", eval_set_id)
             })?;
-        Ok(())
+        Ok(result)
     }
 
     /**
-     * Hard delete a message
+     * Record the reply of a model to an eval case. The id of the result is ignored.
      */
-    pub async fn hard_delete_message(&self, message: MessageDTO) -> Result<MessageDTO, String> {
-        let message_id = message.id.ok_or("Message id is missing")?;
-        messages::Entity::delete_by_id(message_id)
-            .exec(&self.connection)
+    pub async fn create_eval_result(&self, result: EvalResult) -> Result<EvalResult, String> {
+        let result = eval_results::ActiveModel {
+            eval_run_id: Set(result.eval_run_id),
+            eval_case_id: Set(result.eval_case_id),
+            model_id: Set(result.model_id),
+            reply: Set(result.reply),
+            score: Set(result.score),
+            latency_ms: Set(result.latency_ms),
+            prompt_token: Set(result.prompt_token),
+            completion_token: Set(result.completion_token),
+            cost: Set(result.cost),
+            error: Set(result.error),
+            ..Default::default()
+        }
+        .insert(&self.connection)
+        .await
+        .map_err(|err| {
+            error!("{}", err);
+            "Failed to create eval result".to_string()
+        })?;
+        Ok(result)
+    }
+
+    /**
+     * List the results of an eval run
+     */
+    pub async fn list_eval_results(&self, run_id: i32) -> Result<Vec<EvalResult>, String> {
+        let result = eval_results::Entity::find()
+            .filter(eval_results::Column::EvalRunId.eq(run_id))
+            .order_by_asc(eval_results::Column::Id)
+            .all(&self.connection)
             .await
             .map_err(|err| {
                 error!("{}", err);
-                "Failed to delete message".to_string()
+                format!("Failed to list results of eval run with id = {}", run_id)
             })?;
-        Ok(message)
+        Ok(result)
     }
 
     /**
@@ -919,6 +3301,515 @@ impl Repository {
         })?;
         Ok(result)
     }
+
+    /**
+     * Insert a new response schema
+     */
+    pub async fn create_response_schema(
+        &self,
+        new_schema: NewResponseSchema,
+    ) -> Result<ResponseSchema, String> {
+        let mut active_model = new_schema.into_active_model();
+        active_model.created_at = Set(chrono::Local::now());
+        let result = active_model.insert(&self.connection).await.map_err(|err| {
+            error!("{}", err);
+            "Failed to create response schema".to_string()
+        })?;
+        Ok(result)
+    }
+
+    /**
+     * List all response schemas
+     */
+    pub async fn list_response_schemas(&self) -> Result<Vec<ResponseSchema>, String> {
+        let result = response_schemas::Entity::find()
+            .order_by_asc(response_schemas::Column::Name)
+            .all(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                "Failed to list response schemas".to_string()
+            })?;
+        Ok(result)
+    }
+
+    /**
+     * Update the name and schema of a response schema
+     */
+    pub async fn update_response_schema(
+        &self,
+        schema: ResponseSchema,
+    ) -> Result<ResponseSchema, String> {
+        let mut active_model: response_schemas::ActiveModel = schema.into();
+        active_model.reset(response_schemas::Column::Name);
+        active_model.reset(response_schemas::Column::Schema);
+        active_model.updated_at = Set(Some(chrono::Local::now()));
+        let result = active_model.update(&self.connection).await.map_err(|err| {
+            error!("{}", err);
+            "Failed to update response schema".to_string()
+        })?;
+        Ok(result)
+    }
+
+    /**
+     * Delete a response schema and detach it from the conversations using it
+     */
+    pub async fn delete_response_schema(&self, schema_id: i32) -> Result<(), String> {
+        self.connection
+            .transaction::<_, (), DbErr>(|txn| {
+                Box::pin(async move {
+                    conversations::Entity::update_many()
+                        .col_expr(
+                            conversations::Column::ResponseSchemaId,
+                            sea_query::Expr::value(Option::<i32>::None),
+                        )
+                        .filter(conversations::Column::ResponseSchemaId.eq(schema_id))
+                        .exec(txn)
+                        .await?;
+                    response_schemas::Entity::delete_by_id(schema_id)
+                        .exec(txn)
+                        .await?;
+                    Ok(())
+                })
+            })
+            .await
+            .map_err(|err| {
+                error!("Failed to delete response schema: {}", err);
+                format!("Failed to delete response schema with id = {}", schema_id)
+            })?;
+        Ok(())
+    }
+
+    /**
+     * Get the reply cached for a request key, if it was stored after `since`
+     */
+    pub async fn get_cached_reply(
+        &self,
+        key: &str,
+        since: chrono::DateTime<chrono::Local>,
+    ) -> Result<Option<CachedReply>, String> {
+        let result = response_cache::Entity::find()
+            .filter(response_cache::Column::Key.eq(key))
+            .filter(response_cache::Column::CreatedAt.gt(since))
+            .one(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                "Failed to read response cache".to_string()
+            })?;
+        Ok(result)
+    }
+
+    /**
+     * Cache the reply to a request key, replacing the previous one
+     */
+    pub async fn put_cached_reply(&self, key: String, message: String) -> Result<(), String> {
+        let active_model = response_cache::ActiveModel {
+            key: Set(key),
+            message: Set(message),
+            created_at: Set(chrono::Local::now()),
+            ..Default::default()
+        };
+        response_cache::Entity::insert(active_model)
+            .on_conflict(
+                sea_query::OnConflict::column(response_cache::Column::Key)
+                    .update_columns([
+                        response_cache::Column::Message,
+                        response_cache::Column::CreatedAt,
+                    ])
+                    .to_owned(),
+            )
+            .exec(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                "Failed to write response cache".to_string()
+            })?;
+        Ok(())
+    }
+
+    /**
+     * Delete the cached replies stored before a date, returning how many were deleted
+     */
+    pub async fn prune_response_cache(
+        &self,
+        before: chrono::DateTime<chrono::Local>,
+    ) -> Result<u64, String> {
+        let result = response_cache::Entity::delete_many()
+            .filter(response_cache::Column::CreatedAt.lte(before))
+            .exec(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                "Failed to prune response cache".to_string()
+            })?;
+        Ok(result.rows_affected)
+    }
+
+    /**
+     * Record a local usage event
+     */
+    pub async fn create_stat(&self, event: String, latency_ms: Option<i64>) -> Result<(), String> {
+        let stat = stats::ActiveModel {
+            event: Set(event),
+            latency_ms: Set(latency_ms),
+            created_at: Set(chrono::Local::now()),
+            ..Default::default()
+        };
+        stat.insert(&self.connection).await.map_err(|err| {
+            error!("{}", err);
+            "Failed to record usage event".to_string()
+        })?;
+        Ok(())
+    }
+
+    /**
+     * Count usage events and average their latency per feature, most used first
+     */
+    pub async fn list_feature_usage(
+        &self,
+        from: Option<chrono::DateTime<chrono::Local>>,
+        to: Option<chrono::DateTime<chrono::Local>>,
+    ) -> Result<Vec<FeatureUsage>, String> {
+        let result = stats::Entity::find()
+            .select_only()
+            .column(stats::Column::Event)
+            .column_as(stats::Column::Id.count(), "count")
+            .column_as(
+                sea_query::Func::avg(sea_query::Expr::col(stats::Column::LatencyMs)),
+                "average_latency_ms",
+            )
+            .apply_if(from, |query, from| {
+                query.filter(stats::Column::CreatedAt.gte(from))
+            })
+            .apply_if(to, |query, to| {
+                query.filter(stats::Column::CreatedAt.lt(to))
+            })
+            .group_by(stats::Column::Event)
+            .order_by_desc(stats::Column::Id.count())
+            .into_model::<FeatureUsage>()
+            .all(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                "Failed to list feature usage".to_string()
+            })?;
+        Ok(result)
+    }
+
+    /**
+     * Count usage events per month
+     */
+    pub async fn list_monthly_activity(
+        &self,
+        from: Option<chrono::DateTime<chrono::Local>>,
+        to: Option<chrono::DateTime<chrono::Local>>,
+    ) -> Result<Vec<MonthlyActivity>, String> {
+        // Timestamps are stored as text starting with YYYY-MM
+        let month = sea_query::Expr::cust("substr(created_at, 1, 7)");
+        let result = stats::Entity::find()
+            .select_only()
+            .column_as(month.clone(), "month")
+            .column_as(stats::Column::Id.count(), "count")
+            .apply_if(from, |query, from| {
+                query.filter(stats::Column::CreatedAt.gte(from))
+            })
+            .apply_if(to, |query, to| {
+                query.filter(stats::Column::CreatedAt.lt(to))
+            })
+            .group_by(month.clone())
+            .order_by_asc(month)
+            .into_model::<MonthlyActivity>()
+            .all(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                "Failed to list monthly activity".to_string()
+            })?;
+        Ok(result)
+    }
+
+    /**
+     * Count the conversations created and the messages sent by the user per day,
+     * from a day formatted as YYYY-MM-DD, or of all time. Days without any are left out.
+     */
+    pub async fn list_daily_activity(
+        &self,
+        from: Option<String>,
+    ) -> Result<Vec<DailyActivity>, String> {
+        // Timestamps are stored as text starting with YYYY-MM-DD, and days compare as text
+        let from = from.unwrap_or_default();
+        let sql = "SELECT day, SUM(conversations) AS conversations, SUM(messages) AS messages \
+            FROM (\
+            SELECT substr(created_at, 1, 10) AS day, 1 AS conversations, 0 AS messages \
+            FROM conversations WHERE deleted_at IS NULL \
+            UNION ALL \
+            SELECT substr(created_at, 1, 10) AS day, 0 AS conversations, 1 AS messages \
+            FROM messages WHERE deleted_at IS NULL AND role = ?\
+            ) WHERE day >= ? GROUP BY day ORDER BY day";
+        let user_role: i32 = messages::Roles::User.into();
+        let result = DailyActivity::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            sql,
+            [user_role.into(), from.into()],
+        ))
+        .all(&self.connection)
+        .await
+        .map_err(|err| {
+            error!("{}", err);
+            "Failed to list daily activity".to_string()
+        })?;
+        Ok(result)
+    }
+
+    /**
+     * List the prices of models set by the user
+     */
+    pub async fn list_model_prices(&self) -> Result<Vec<ModelPrice>, String> {
+        let result = model_prices::Entity::find()
+            .all(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                "Failed to list model prices".to_string()
+            })?;
+        Ok(result)
+    }
+
+    /**
+     * Set the prices of a model in USD per 1k tokens, replacing the previous ones
+     */
+    pub async fn upsert_model_price(
+        &self,
+        model_id: i32,
+        input_price: f64,
+        output_price: f64,
+    ) -> Result<ModelPrice, String> {
+        let active_model = model_prices::ActiveModel {
+            model_id: Set(model_id),
+            input_price: Set(input_price),
+            output_price: Set(output_price),
+            updated_at: Set(chrono::Local::now()),
+            ..Default::default()
+        };
+        model_prices::Entity::insert(active_model)
+            .on_conflict(
+                sea_query::OnConflict::column(model_prices::Column::ModelId)
+                    .update_columns([
+                        model_prices::Column::InputPrice,
+                        model_prices::Column::OutputPrice,
+                        model_prices::Column::UpdatedAt,
+                    ])
+                    .to_owned(),
+            )
+            .exec(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!("Failed to set prices of model with id = {}", model_id)
+            })?;
+        model_prices::Entity::find()
+            .filter(model_prices::Column::ModelId.eq(model_id))
+            .one(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!("Failed to get prices of model with id = {}", model_id)
+            })?
+            .ok_or(format!("Prices of model with id {} don't exist", model_id))
+    }
+
+    /**
+     * Remove the prices set for a model, its bundled prices being used again
+     */
+    pub async fn delete_model_price(&self, model_id: i32) -> Result<(), String> {
+        model_prices::Entity::delete_many()
+            .filter(model_prices::Column::ModelId.eq(model_id))
+            .exec(&self.connection)
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                format!("Failed to delete prices of model with id = {}", model_id)
+            })?;
+        Ok(())
+    }
+
+    /**
+     * Sum the tokens used by replies per day and conversation, from a day formatted as
+     * YYYY-MM-DD, or of all time. Replies without usage are left out.
+     */
+    pub async fn list_token_usage(&self, from: Option<String>) -> Result<Vec<TokenUsage>, String> {
+        // Timestamps are stored as text starting with YYYY-MM-DD, and days compare as text
+        let from = from.unwrap_or_default();
+        // Replies of fallback models are counted, and priced, for the model which wrote them
+        let sql = format!(
+            "SELECT substr(m.created_at, 1, 10) AS day, \
+            c.id AS conversation_id, c.subject AS conversation_subject, \
+            mo.id AS model_id, mo.alias AS model_alias, mo.config AS model_config, \
+            SUM(COALESCE(m.prompt_token, 0)) AS prompt_tokens, \
+            SUM(COALESCE(m.completion_token, 0)) AS completion_tokens \
+            FROM messages m \
+            JOIN conversations c ON c.id = m.conversation_id \
+            LEFT JOIN models mo ON mo.id = {} \
+            WHERE m.deleted_at IS NULL AND m.role = ? \
+            AND (m.prompt_token IS NOT NULL OR m.completion_token IS NOT NULL) \
+            AND substr(m.created_at, 1, 10) >= ? \
+            GROUP BY day, c.id, mo.id ORDER BY day",
+            REPLY_MODEL_ID_SQL
+        );
+        let bot_role: i32 = messages::Roles::Bot.into();
+        let result = TokenUsage::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            sql,
+            [bot_role.into(), from.into()],
+        ))
+        .all(&self.connection)
+        .await
+        .map_err(|err| {
+            error!("{}", err);
+            "Failed to list token usage".to_string()
+        })?;
+        Ok(result)
+    }
+
+    /**
+     * Fuzzy search conversations, prompts, models and settings in one ranked list
+     */
+    pub async fn palette_search(
+        &self,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<PaletteItem>, String> {
+        let mut items = vec![];
+        // Archived conversations can still be found
+        let filter = ConversationFilter {
+            include_archived: true,
+            ..Default::default()
+        };
+        for conversation in self.list_conversations(filter).await? {
+            if let Some(score) = fuzzy_score(query, &conversation.subject) {
+                items.push(PaletteItem {
+                    kind: PaletteItemKind::Conversation,
+                    id: conversation.id.to_string(),
+                    title: conversation.subject,
+                    subtitle: conversation.model_provider,
+                    score,
+                });
+            }
+        }
+        for prompt in self.list_prompts().await? {
+            // Matches in the alias count more than matches in the content
+            let score = fuzzy_score(query, &prompt.alias)
+                .into_iter()
+                .chain(fuzzy_score(query, &prompt.content).map(|score| score / 2))
+                .max();
+            if let Some(score) = score {
+                items.push(PaletteItem {
+                    kind: PaletteItemKind::Prompt,
+                    id: prompt.id.to_string(),
+                    title: prompt.alias,
+                    subtitle: Some(prompt.content.chars().take(80).collect()),
+                    score,
+                });
+            }
+        }
+        for model in self.list_models().await? {
+            if let Some(score) = fuzzy_score(query, &model.alias) {
+                items.push(PaletteItem {
+                    kind: PaletteItemKind::Model,
+                    id: model.id.to_string(),
+                    title: model.alias,
+                    subtitle: Some(model.provider),
+                    score,
+                });
+            }
+        }
+        // Only keys are searched, values may hold secrets
+        for setting in self.list_settings().await? {
+            if setting.key == settings::SETTING_API_SERVER_TOKEN {
+                continue;
+            }
+            if let Some(score) = fuzzy_score(query, &setting.key) {
+                items.push(PaletteItem {
+                    kind: PaletteItemKind::Setting,
+                    id: setting.key.clone(),
+                    title: setting.key,
+                    subtitle: None,
+                    score,
+                });
+            }
+        }
+        Ok(rank(items, limit))
+    }
+}
+
+/// The options new conversations with a model start with: the default options of its
+/// provider, overridden by the default options of the model
+pub fn model_options(model: &Model) -> String {
+    let provider_options = default_options(&model.provider);
+    let model_options = model
+        .default_options
+        .as_deref()
+        .and_then(|options| serde_json::from_str(options).ok());
+    match (serde_json::from_str(&provider_options), model_options) {
+        (Ok(provider_options), Some(model_options)) => {
+            options::merge_options(&[provider_options, model_options]).to_string()
+        }
+        _ => provider_options,
+    }
+}
+
+/**
+ * Default request options of a provider, serialized
+ */
+pub fn default_options(provider: &str) -> String {
+    let result = match Providers::from(provider) {
+        Providers::Azure => serde_json::to_string(&AzureOptions::default()),
+        Providers::Claude => serde_json::to_string(&ClaudeOptions::default()),
+        Providers::Ollama => serde_json::to_string(&OllamaOptions::default()),
+        Providers::Deepseek => serde_json::to_string(&DeepseekOptions::default()),
+        Providers::Cohere => serde_json::to_string(&CohereOptions::default()),
+        _ => serde_json::to_string(&OpenAIOptions::default()),
+    };
+    result.unwrap_or(String::default())
+}
+
+/**
+ * Insert a message with its contents as part of a transaction
+ */
+async fn insert_message(
+    txn: &DatabaseTransaction,
+    message: MessageDTO,
+) -> Result<MessageDTO, DbErr> {
+    let contents = message.content.clone();
+    let conversation_id = message.conversation_id;
+    let mut msg_am = message.into_active_model();
+    msg_am.created_at = Set(chrono::Local::now());
+    // Insert message first
+    let msg_m = msg_am.insert(txn).await?;
+    let ctnt_ams: Vec<contents::ActiveModel> = contents
+        .into_iter()
+        .map(|content| {
+            let mut ctnt_am: contents::ActiveModel = content.into_active_model();
+            ctnt_am.message_id = Set(msg_m.id);
+            ctnt_am
+        })
+        .collect();
+    // Insert contents
+    contents::Entity::insert_many(ctnt_ams).exec(txn).await?;
+    // Retrieve newly inserted contents
+    let contents = msg_m.find_related(contents::Entity).all(txn).await?;
+    // Update conversation's last message at
+    conversations::Entity::update_many()
+        .filter(conversations::Column::Id.eq(conversation_id))
+        .col_expr(
+            conversations::Column::LastMessageAt,
+            sea_query::Expr::value(chrono::Local::now()),
+        )
+        .exec(txn)
+        .await?;
+    // Return DTO
+    Ok(MessageDTO::from((msg_m, contents)))
 }
 
 #[derive(Default)]
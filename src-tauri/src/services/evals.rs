@@ -0,0 +1,299 @@
+use std::time::Instant;
+
+use entity::entities::{
+    eval_cases::Model as EvalCase,
+    eval_results::Model as EvalResult,
+    eval_runs::Model as EvalRun,
+    messages::{MessageDTO, Roles},
+    models::Model,
+};
+use serde::Serialize;
+
+use super::{
+    db::Repository,
+    llm::{
+        chat::BotReply,
+        context::{text_message, ChatContext},
+    },
+    usage,
+};
+
+const JUDGE_INSTRUCTION: &str = "You grade the replies of AI assistants. \
+The user gives you a prompt, the reply of an assistant and sometimes a reference answer. \
+Grade how correct and helpful the reply is, from 0 (useless or wrong) to 10 (perfect). \
+Reply with the grade only, as a number.";
+
+/// How a model did on the cases of an eval run
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvalSummary {
+    pub model_id: i32,
+    pub cases: usize,
+    pub errors: usize,
+    /// Average of the graded cases, None when none could be graded
+    pub average_score: Option<f64>,
+    pub average_latency_ms: i64,
+    /// In USD, None when the prices of the model are unknown
+    pub total_cost: Option<f64>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvalResults {
+    pub run: EvalRun,
+    pub results: Vec<EvalResult>,
+    pub summaries: Vec<EvalSummary>,
+}
+
+/**
+ * Send every prompt of an eval set to each model and grade the replies, against the
+ * expected answer or by the judge model of the set. Failed requests are recorded as
+ * results with their error, so one failing model doesn't stop the run.
+ */
+pub async fn run_eval(
+    repo: &Repository,
+    eval_set_id: i32,
+    model_ids: Vec<i32>,
+) -> Result<EvalRun, String> {
+    let eval_set = repo.get_eval_set(eval_set_id).await?;
+    let cases = repo.list_eval_cases(eval_set_id).await?;
+    if cases.is_empty() {
+        return Err("The eval set has no prompts".to_string());
+    }
+    let judge = match eval_set.judge_model_id {
+        Some(judge_model_id) => Some(repo.get_model(judge_model_id).await?),
+        None => None,
+    };
+    // A missing model fails the run before it starts, instead of leaving it unfinished
+    let mut models = vec![];
+    for model_id in model_ids {
+        models.push(repo.get_model(model_id).await?);
+    }
+    let run = repo.create_eval_run(eval_set_id).await?;
+    for model in &models {
+        for case in &cases {
+            let result = run_case(repo, &run, model, case, judge.as_ref()).await;
+            repo.create_eval_result(result).await?;
+        }
+    }
+    log::info!("Eval run {} of set {} finished", run.id, eval_set_id);
+    repo.finish_eval_run(run.id).await
+}
+
+/**
+ * The results of an eval run, with a summary per model
+ */
+pub async fn get_eval_results(repo: &Repository, run_id: i32) -> Result<EvalResults, String> {
+    let run = repo.get_eval_run(run_id).await?;
+    let results = repo.list_eval_results(run_id).await?;
+    let summaries = summarize(&results);
+    Ok(EvalResults {
+        run,
+        results,
+        summaries,
+    })
+}
+
+async fn run_case(
+    repo: &Repository,
+    run: &EvalRun,
+    model: &Model,
+    case: &EvalCase,
+    judge: Option<&Model>,
+) -> EvalResult {
+    let mut result = EvalResult {
+        id: 0,
+        eval_run_id: run.id,
+        eval_case_id: case.id,
+        model_id: model.id,
+        reply: None,
+        score: None,
+        latency_ms: 0,
+        prompt_token: None,
+        completion_token: None,
+        cost: None,
+        error: None,
+    };
+    let now = Instant::now();
+    let reply = match ask(
+        repo,
+        model,
+        vec![text_message(Roles::User, case.prompt.clone())],
+    )
+    .await
+    {
+        Ok(reply) => reply,
+        Err(err) => {
+            result.latency_ms = now.elapsed().as_millis() as i64;
+            result.error = Some(err);
+            return result;
+        }
+    };
+    result.latency_ms = now.elapsed().as_millis() as i64;
+    result.prompt_token = reply.prompt_token.map(|count| count as i32);
+    result.completion_token = reply.completion_token.map(|count| count as i32);
+    result.cost = usage::model_cost(
+        usage::get_model_price(repo, model.id).await.as_ref(),
+        &model.config,
+        reply.prompt_token.unwrap_or_default(),
+        reply.completion_token.unwrap_or_default(),
+    );
+    result.score = match judge {
+        Some(judge) => match ask_judge(repo, judge, case, &reply.message).await {
+            Ok(score) => score,
+            Err(err) => {
+                result.error = Some(format!("Failed to grade the reply: {}", err));
+                None
+            }
+        },
+        None => case
+            .expected
+            .as_ref()
+            .map(|expected| match_score(&reply.message, expected)),
+    };
+    result.reply = Some(reply.message);
+    result
+}
+
+async fn ask(
+    repo: &Repository,
+    model: &Model,
+    messages: Vec<MessageDTO>,
+) -> Result<BotReply, String> {
+    ChatContext::one_off(repo, model.clone(), messages)
+        .await?
+        .complete()
+        .await
+}
+
+async fn ask_judge(
+    repo: &Repository,
+    judge: &Model,
+    case: &EvalCase,
+    reply: &str,
+) -> Result<Option<f64>, String> {
+    let mut request = format!("## Prompt\n\n{}\n\n## Reply\n\n{}", case.prompt, reply);
+    if let Some(expected) = &case.expected {
+        request.push_str(&format!("\n\n## Reference answer\n\n{}", expected));
+    }
+    let messages = vec![
+        text_message(Roles::System, JUDGE_INSTRUCTION.to_string()),
+        text_message(Roles::User, request),
+    ];
+    let grade = ask(repo, judge, messages).await?;
+    Ok(parse_judge_score(&grade.message))
+}
+
+// A reply passes when it contains the expected answer, ignoring case and spacing
+fn match_score(reply: &str, expected: &str) -> f64 {
+    let normalize = |text: &str| {
+        text.split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ")
+            .to_lowercase()
+    };
+    if normalize(reply).contains(&normalize(expected)) {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+// The first number of the judge's reply, as a grade out of 10
+fn parse_judge_score(text: &str) -> Option<f64> {
+    let number: String = text
+        .chars()
+        .skip_while(|c| !c.is_ascii_digit())
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+    let grade: f64 = number.trim_end_matches('.').parse().ok()?;
+    Some((grade / 10.0).clamp(0.0, 1.0))
+}
+
+fn summarize(results: &[EvalResult]) -> Vec<EvalSummary> {
+    let mut model_ids: Vec<i32> = results.iter().map(|result| result.model_id).collect();
+    // Results are stored case by case, so the ids of the models interleave
+    model_ids.sort_unstable();
+    model_ids.dedup();
+    model_ids
+        .into_iter()
+        .map(|model_id| {
+            let results: Vec<&EvalResult> = results
+                .iter()
+                .filter(|result| result.model_id == model_id)
+                .collect();
+            let scores: Vec<f64> = results.iter().filter_map(|result| result.score).collect();
+            let costs: Vec<f64> = results.iter().filter_map(|result| result.cost).collect();
+            EvalSummary {
+                model_id,
+                cases: results.len(),
+                errors: results
+                    .iter()
+                    .filter(|result| result.error.is_some())
+                    .count(),
+                average_score: if scores.is_empty() {
+                    None
+                } else {
+                    Some(scores.iter().sum::<f64>() / scores.len() as f64)
+                },
+                average_latency_ms: results.iter().map(|result| result.latency_ms).sum::<i64>()
+                    / results.len() as i64,
+                total_cost: if costs.is_empty() {
+                    None
+                } else {
+                    Some(costs.iter().sum())
+                },
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(model_id: i32, score: Option<f64>, latency_ms: i64) -> EvalResult {
+        EvalResult {
+            id: 0,
+            eval_run_id: 1,
+            eval_case_id: 1,
+            model_id,
+            reply: None,
+            score,
+            latency_ms,
+            prompt_token: None,
+            completion_token: None,
+            cost: None,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_match_score() {
+        assert_eq!(1.0, match_score("The answer is  Paris.", "paris"));
+        assert_eq!(0.0, match_score("The answer is Lyon.", "Paris"));
+    }
+
+    #[test]
+    fn test_parse_judge_score() {
+        assert_eq!(Some(0.8), parse_judge_score("8"));
+        assert_eq!(Some(0.75), parse_judge_score("Grade: 7.5."));
+        assert_eq!(Some(1.0), parse_judge_score("12"));
+        assert_eq!(None, parse_judge_score("excellent"));
+    }
+
+    #[test]
+    fn test_summarize() {
+        let results = vec![
+            result(1, Some(1.0), 100),
+            result(2, None, 50),
+            result(1, Some(0.0), 300),
+        ];
+        let summaries = summarize(&results);
+        assert_eq!(2, summaries.len());
+        assert_eq!(Some(0.5), summaries[0].average_score);
+        assert_eq!(200, summaries[0].average_latency_ms);
+        assert_eq!(None, summaries[1].average_score);
+        assert_eq!(None, summaries[1].total_cost);
+    }
+}
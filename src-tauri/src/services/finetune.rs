@@ -0,0 +1,259 @@
+use std::collections::HashSet;
+
+use entity::entities::{
+    conversations::ConversationFilter,
+    message_feedback::Rating,
+    messages::{MessageDTO, Roles},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::db::Repository;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum FinetuneFormat {
+    /// {"messages": [{"role": "system" | "user" | "assistant", "content": ...}]}
+    #[default]
+    OpenAI,
+    /// {"system": ..., "messages": [{"role": "user" | "assistant", "content": ...}]}
+    Anthropic,
+}
+
+/// Which messages go into the dataset
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FinetuneFilter {
+    #[serde(default)]
+    pub format: FinetuneFormat,
+    /// Conversations to export, all of them if not set
+    pub conversation_ids: Option<Vec<i32>>,
+    /// Only export these replies, each with the user message before it
+    pub message_ids: Option<Vec<i32>>,
+    /// Only export replies rated thumbs up, each with the user message before it
+    #[serde(default)]
+    pub thumbs_up_only: bool,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FinetuneExport {
+    pub examples: usize,
+    pub duplicates: usize,
+    /// The dataset, one JSON example per line
+    #[serde(skip)]
+    pub jsonl: String,
+}
+
+// A message reduced to what training formats need
+#[derive(Clone, Debug, PartialEq)]
+struct Turn {
+    role: Roles,
+    text: String,
+}
+
+/**
+ * Build a fine-tuning dataset out of the conversations and messages matching the filter
+ */
+pub async fn export_jsonl(
+    repo: &Repository,
+    filter: &FinetuneFilter,
+) -> Result<FinetuneExport, String> {
+    let conversation_ids = match &filter.conversation_ids {
+        Some(ids) => ids.clone(),
+        None => repo
+            .list_conversations(ConversationFilter {
+                include_archived: true,
+                ..Default::default()
+            })
+            .await?
+            .into_iter()
+            .map(|conversation| conversation.id)
+            .collect(),
+    };
+    let mut export = FinetuneExport::default();
+    let mut seen = HashSet::new();
+    for conversation_id in conversation_ids {
+        let system = repo
+            .get_system_message(conversation_id)
+            .await?
+            .and_then(|message| message.get_text())
+            .filter(|text| !text.trim().is_empty());
+        let messages = repo.list_messages(conversation_id).await?;
+        let selected = if filter.thumbs_up_only {
+            let liked: Vec<i32> = repo
+                .list_message_feedback(conversation_id)
+                .await?
+                .into_iter()
+                .filter(|feedback| feedback.rating == Some(Rating::Up))
+                .map(|feedback| feedback.message_id)
+                .collect();
+            Some(liked)
+        } else {
+            filter.message_ids.clone()
+        };
+        for turns in select_examples(&messages, selected.as_deref()) {
+            let example = match to_example(filter.format, system.as_deref(), turns) {
+                Some(example) => example.to_string(),
+                None => continue,
+            };
+            if seen.insert(example.clone()) {
+                export.jsonl.push_str(&example);
+                export.jsonl.push('\n');
+                export.examples += 1;
+            } else {
+                export.duplicates += 1;
+            }
+        }
+    }
+    Ok(export)
+}
+
+// The whole conversation is one example, unless replies are selected:
+// then each selected reply makes an example with the user message before it
+fn select_examples(messages: &[MessageDTO], selected: Option<&[i32]>) -> Vec<Vec<Turn>> {
+    let turns: Vec<(Option<i32>, Turn)> = messages
+        .iter()
+        .filter_map(|message| {
+            let text = message.get_text()?;
+            Some((
+                message.id,
+                Turn {
+                    role: Roles::from(message.role),
+                    text,
+                },
+            ))
+        })
+        .collect();
+    let selected = match selected {
+        Some(selected) => selected,
+        None => return vec![turns.into_iter().map(|(_, turn)| turn).collect()],
+    };
+    turns
+        .iter()
+        .enumerate()
+        .filter(|(_, (id, turn))| {
+            turn.role == Roles::Bot && id.is_some_and(|id| selected.contains(&id))
+        })
+        .filter_map(|(index, (_, reply))| {
+            let (_, prompt) = turns[..index]
+                .iter()
+                .rev()
+                .find(|(_, turn)| turn.role == Roles::User)?;
+            Some(vec![prompt.clone(), reply.clone()])
+        })
+        .collect()
+}
+
+// Map roles and merge consecutive turns of the same role, as both formats expect
+// user and assistant to alternate. Examples must have a prompt and end with a reply.
+fn to_example(format: FinetuneFormat, system: Option<&str>, turns: Vec<Turn>) -> Option<Value> {
+    let mut merged: Vec<Turn> = vec![];
+    for turn in turns.into_iter().filter(|turn| turn.role != Roles::System) {
+        match merged.last_mut() {
+            Some(last) if last.role == turn.role => {
+                last.text.push_str("\n\n");
+                last.text.push_str(&turn.text);
+            }
+            _ => merged.push(turn),
+        }
+    }
+    // Training starts from a user message and ends on a reply
+    while merged.first().is_some_and(|turn| turn.role != Roles::User) {
+        merged.remove(0);
+    }
+    while merged.last().is_some_and(|turn| turn.role != Roles::Bot) {
+        merged.pop();
+    }
+    if merged.is_empty() {
+        return None;
+    }
+    let messages: Vec<Value> = merged
+        .iter()
+        .map(|turn| {
+            let role = if turn.role == Roles::User {
+                "user"
+            } else {
+                "assistant"
+            };
+            json!({ "role": role, "content": turn.text })
+        })
+        .collect();
+    let example = match (format, system) {
+        (FinetuneFormat::OpenAI, Some(system)) => {
+            let mut with_system = vec![json!({ "role": "system", "content": system })];
+            with_system.extend(messages);
+            json!({ "messages": with_system })
+        }
+        (FinetuneFormat::OpenAI, None) => json!({ "messages": messages }),
+        (FinetuneFormat::Anthropic, Some(system)) => {
+            json!({ "system": system, "messages": messages })
+        }
+        (FinetuneFormat::Anthropic, None) => json!({ "messages": messages }),
+    };
+    Some(example)
+}
+
+#[cfg(test)]
+mod tests {
+    use entity::entities::contents::{ContentDTO, ContentType};
+
+    use super::*;
+
+    fn message(id: i32, role: Roles, text: &str) -> MessageDTO {
+        MessageDTO {
+            id: Some(id),
+            role: role.into(),
+            content: vec![ContentDTO {
+                r#type: ContentType::Text,
+                mimetype: None,
+                data: text.to_string(),
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_to_example() {
+        let messages = vec![
+            message(1, Roles::Bot, "Hi, how can I help?"),
+            message(2, Roles::User, "Name a color"),
+            message(3, Roles::User, "Just one"),
+            message(4, Roles::Bot, "Blue"),
+            message(5, Roles::User, "Thanks"),
+        ];
+        let turns = select_examples(&messages, None).remove(0);
+        assert_eq!(
+            json!({ "messages": [
+                { "role": "system", "content": "Be brief" },
+                { "role": "user", "content": "Name a color\n\nJust one" },
+                { "role": "assistant", "content": "Blue" },
+            ] }),
+            to_example(FinetuneFormat::OpenAI, Some("Be brief"), turns.clone()).unwrap()
+        );
+        assert_eq!(
+            json!({ "system": "Be brief", "messages": [
+                { "role": "user", "content": "Name a color\n\nJust one" },
+                { "role": "assistant", "content": "Blue" },
+            ] }),
+            to_example(FinetuneFormat::Anthropic, Some("Be brief"), turns).unwrap()
+        );
+        assert!(select_examples(&messages[..1], None)
+            .into_iter()
+            .all(|turns| to_example(FinetuneFormat::OpenAI, None, turns).is_none()));
+    }
+
+    #[test]
+    fn test_select_replies() {
+        let messages = vec![
+            message(1, Roles::User, "Name a color"),
+            message(2, Roles::Bot, "Blue"),
+            message(3, Roles::User, "Another one"),
+            message(4, Roles::Bot, "Red"),
+        ];
+        let examples = select_examples(&messages, Some(&[4]));
+        assert_eq!(1, examples.len());
+        assert_eq!("Another one", examples[0][0].text);
+        assert_eq!("Red", examples[0][1].text);
+    }
+}
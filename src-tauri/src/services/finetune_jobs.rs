@@ -0,0 +1,134 @@
+use async_openai::types::{
+    CreateFileRequest, CreateFineTuningJobRequestArgs, FileInput, FilePurpose, FineTuningJob,
+    FineTuningJobStatus,
+};
+use entity::entities::{
+    finetune_jobs::{FinetuneJobStatus, Model as FinetuneJob},
+    models::{Model, NewModel},
+};
+
+use super::{db::Repository, llm::client::openai_client};
+
+/**
+ * Upload a JSONL training file, such as the one of `export_finetune_jsonl`, and return its id
+ */
+pub async fn upload_training_file(
+    repo: &Repository,
+    model_id: i32,
+    path: String,
+) -> Result<String, String> {
+    // Fine-tuning is only available with OpenAI models
+    let (client, _) = openai_client(repo, model_id).await?;
+    let file = client
+        .files()
+        .create(CreateFileRequest {
+            file: FileInput::from(path.as_str()),
+            purpose: FilePurpose::FineTune,
+        })
+        .await
+        .map_err(|err| format!("Failed to upload {}: {}", path, err))?;
+    log::info!("Training file {} uploaded as {}", path, file.id);
+    Ok(file.id)
+}
+
+/**
+ * Start fine-tuning the model of a `models` row with an uploaded training file
+ */
+pub async fn create_job(
+    repo: &Repository,
+    model_id: i32,
+    training_file_id: String,
+    suffix: Option<String>,
+) -> Result<FinetuneJob, String> {
+    let (client, model_name) = openai_client(repo, model_id).await?;
+    let model_name = model_name.ok_or("The model has no name to fine-tune".to_string())?;
+    let mut request = CreateFineTuningJobRequestArgs::default();
+    request.model(model_name).training_file(&training_file_id);
+    if let Some(suffix) = suffix.filter(|suffix| !suffix.trim().is_empty()) {
+        request.suffix(suffix);
+    }
+    let request = request.build().map_err(|err| err.to_string())?;
+    let remote = client
+        .fine_tuning()
+        .create(request)
+        .await
+        .map_err(|err| format!("Failed to create fine-tuning job: {}", err))?;
+    log::info!("Fine-tuning job {} created", remote.id);
+    repo.create_finetune_job(
+        model_id,
+        remote.id,
+        training_file_id,
+        to_status(&remote.status),
+    )
+    .await
+}
+
+/**
+ * Update the unfinished jobs and register the models of the succeeded ones.
+ * Run periodically by the job scheduler.
+ */
+pub async fn poll_jobs(repo: &Repository) -> Result<(), String> {
+    let jobs = repo.list_finetune_jobs().await?;
+    for job in jobs.into_iter().filter(|job| !job.status.is_finished()) {
+        let job_id = job.id;
+        if let Err(err) = check_job(repo, job).await {
+            log::warn!("Failed to check fine-tuning job {}: {}", job_id, err);
+        }
+    }
+    Ok(())
+}
+
+async fn check_job(repo: &Repository, mut job: FinetuneJob) -> Result<(), String> {
+    let (client, _) = openai_client(repo, job.model_id).await?;
+    let remote: FineTuningJob = client
+        .fine_tuning()
+        .retrieve(&job.remote_id)
+        .await
+        .map_err(|err| format!("Failed to get fine-tuning job {}: {}", job.remote_id, err))?;
+    let status = to_status(&remote.status);
+    if status == job.status {
+        return Ok(());
+    }
+    job.status = status;
+    job.fine_tuned_model = remote.fine_tuned_model;
+    job.error = remote.error.map(|error| error.message);
+    if job.status == FinetuneJobStatus::Succeeded {
+        if let Some(name) = &job.fine_tuned_model {
+            let model = repo.get_model(job.model_id).await?;
+            let new_model = register_model(repo, &model, name).await?;
+            job.result_model_id = Some(new_model.id);
+        }
+    }
+    log::info!("Fine-tuning job {} is {:?}", job.remote_id, job.status);
+    repo.update_finetune_job(job).await?;
+    Ok(())
+}
+
+// Add the fine-tuned model with the credentials of its base model
+async fn register_model(repo: &Repository, base: &Model, name: &str) -> Result<Model, String> {
+    let mut config: serde_json::Value = serde_json::from_str(&base.config)
+        .map_err(|_| format!("Failed to parse model config: {}", &base.config))?;
+    config["model"] = serde_json::Value::String(name.to_string());
+    repo.create_model(NewModel {
+        alias: name.to_string(),
+        provider: base.provider.clone(),
+        config: config.to_string(),
+        context_length: base.context_length,
+        max_output_tokens: base.max_output_tokens,
+        default_options: base.default_options.clone(),
+        connect_timeout: base.connect_timeout,
+        read_timeout: base.read_timeout,
+    })
+    .await
+}
+
+fn to_status(status: &FineTuningJobStatus) -> FinetuneJobStatus {
+    match status {
+        FineTuningJobStatus::ValidatingFiles => FinetuneJobStatus::ValidatingFiles,
+        FineTuningJobStatus::Queued => FinetuneJobStatus::Queued,
+        FineTuningJobStatus::Running => FinetuneJobStatus::Running,
+        FineTuningJobStatus::Succeeded => FinetuneJobStatus::Succeeded,
+        FineTuningJobStatus::Failed => FinetuneJobStatus::Failed,
+        FineTuningJobStatus::Cancelled => FinetuneJobStatus::Cancelled,
+    }
+}
@@ -0,0 +1,339 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use entity::entities::messages::MessageDTO;
+use serde::Serialize;
+use serde_json::json;
+use tokio::task::AbortHandle;
+
+use crate::{
+    events,
+    services::llm::{chat::BotReply, limits},
+};
+
+pub const EVENT_GENERATIONS_CHANGED: &str = "generations-changed";
+/// Followed by the conversation id, stops the bot calls of that conversation only
+pub const EVENT_STOP_BOT_PREFIX: &str = "stop-bot:";
+
+/// How long after a request the same request to the same conversation is taken for a duplicate
+const DUPLICATE_WINDOW: Duration = Duration::from_secs(10);
+
+/// A bot call that is currently running
+struct Generation {
+    conversation_id: i32,
+    abort_handle: AbortHandle,
+    started_at: Instant,
+}
+
+/// A request about to be sent or being answered, to detect duplicates
+struct PendingRequest {
+    conversation_id: i32,
+    fingerprint: u64,
+    tag: String,
+    requested_at: Instant,
+}
+
+/// Speed of a streamed reply, sent with the end of the stream and kept in the metadata
+/// of the reply, under "throughput"
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamMetrics {
+    /// From sending the request to the first chunk of text or reasoning
+    pub time_to_first_token_ms: u64,
+    /// From the first chunk to the last one
+    pub generation_ms: u64,
+    pub completion_tokens: u32,
+    /// Whether the completion tokens were estimated from the text, when not reported
+    pub estimated_tokens: bool,
+    /// None when the reply came in a single chunk
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tokens_per_second: Option<f64>,
+}
+
+/// Times a streamed reply, from its request to its last chunk
+pub struct StreamMeter {
+    started_at: Instant,
+    first_chunk_at: Option<Instant>,
+    last_chunk_at: Option<Instant>,
+    completion_tokens: Option<u32>,
+    chars: usize,
+}
+
+impl StreamMeter {
+    /// Start timing, right before the request is sent
+    pub fn start() -> Self {
+        StreamMeter {
+            started_at: Instant::now(),
+            first_chunk_at: None,
+            last_chunk_at: None,
+            completion_tokens: None,
+            chars: 0,
+        }
+    }
+
+    /// Record a chunk of the reply as it's received
+    pub fn record(&mut self, chunk: &BotReply) {
+        self.record_at(chunk, Instant::now());
+    }
+
+    fn record_at(&mut self, chunk: &BotReply, now: Instant) {
+        // Usage is only reported by some chunks, the last reported one is the total
+        self.completion_tokens = chunk.completion_token.or(self.completion_tokens);
+        let chars = chunk.message.chars().count()
+            + chunk
+                .reasoning
+                .as_deref()
+                .map_or(0, |text| text.chars().count());
+        if chars == 0 {
+            return;
+        }
+        self.chars += chars;
+        self.first_chunk_at.get_or_insert(now);
+        self.last_chunk_at = Some(now);
+    }
+
+    /// The metrics of the reply, None when nothing was received
+    pub fn finish(&self) -> Option<StreamMetrics> {
+        let first_chunk_at = self.first_chunk_at?;
+        let generation = self.last_chunk_at?.duration_since(first_chunk_at);
+        let completion_tokens = self
+            .completion_tokens
+            .unwrap_or_else(|| self.chars.div_ceil(limits::chars_per_token(None)) as u32);
+        Some(StreamMetrics {
+            time_to_first_token_ms: first_chunk_at.duration_since(self.started_at).as_millis()
+                as u64,
+            generation_ms: generation.as_millis() as u64,
+            completion_tokens,
+            estimated_tokens: self.completion_tokens.is_none(),
+            tokens_per_second: Some(generation.as_secs_f64())
+                .filter(|seconds| *seconds > 0.0)
+                .map(|seconds| completion_tokens as f64 / seconds),
+        })
+    }
+}
+
+/// Keeps track of all in-flight bot calls, keyed by their event tag
+pub struct GenerationManager {
+    generations: Mutex<HashMap<String, Generation>>,
+    requests: Mutex<Vec<PendingRequest>>,
+}
+
+impl GenerationManager {
+    pub fn new() -> Self {
+        GenerationManager {
+            generations: Mutex::new(HashMap::new()),
+            requests: Mutex::new(vec![]),
+        }
+    }
+
+    /**
+     * Record a request about to be sent, unless the same request to the same conversation
+     * is already in flight, e.g. after a double-click. Returns the tag of the original
+     * request then, so its reply can be followed instead of paying for a second one.
+     */
+    pub fn claim_request(
+        &self,
+        conversation_id: i32,
+        fingerprint: u64,
+        tag: &str,
+    ) -> Option<String> {
+        let mut requests = self.requests.lock().expect("Failed to lock requests mutex");
+        requests.retain(|request| request.requested_at.elapsed() < DUPLICATE_WINDOW);
+        let original = requests.iter().find(|request| {
+            request.conversation_id == conversation_id
+                && request.fingerprint == fingerprint
+                && request.tag != tag
+        });
+        if let Some(original) = original {
+            return Some(original.tag.clone());
+        }
+        requests.push(PendingRequest {
+            conversation_id,
+            fingerprint,
+            tag: tag.to_string(),
+            requested_at: Instant::now(),
+        });
+        None
+    }
+
+    /// Record a newly spawned bot call
+    pub fn register(&self, tag: &str, conversation_id: i32, abort_handle: AbortHandle) {
+        let count = {
+            let mut generations = self
+                .generations
+                .lock()
+                .expect("Failed to lock generations mutex");
+            generations.insert(
+                tag.to_string(),
+                Generation {
+                    conversation_id,
+                    abort_handle,
+                    started_at: Instant::now(),
+                },
+            );
+            generations.len()
+        };
+        notify_changed(count);
+    }
+
+    /// Remove a bot call once it has finished, failed or been stopped
+    pub fn finish(&self, tag: &str) {
+        self.requests
+            .lock()
+            .expect("Failed to lock requests mutex")
+            .retain(|request| request.tag != tag);
+        let (removed, count) = {
+            let mut generations = self
+                .generations
+                .lock()
+                .expect("Failed to lock generations mutex");
+            let removed = generations.remove(tag);
+            (removed, generations.len())
+        };
+        if let Some(generation) = removed {
+            log::info!(
+                "Generation {} of conversation {} finished in {:.2?}",
+                tag,
+                generation.conversation_id,
+                generation.started_at.elapsed()
+            );
+            notify_changed(count);
+        }
+    }
+
+    /**
+     * Stop every running bot call, telling the frontend each one was stopped.
+     * The calls are removed by `finish` once their task ends. Returns how many were stopped.
+     */
+    pub fn stop_all(&self) -> usize {
+        self.stop_where(|_| true)
+    }
+
+    /// Stop the running bot calls of a conversation, leaving the others running
+    pub fn stop_conversation(&self, conversation_id: i32) -> usize {
+        self.stop_where(|generation| generation.conversation_id == conversation_id)
+    }
+
+    fn stop_where(&self, predicate: impl Fn(&Generation) -> bool) -> usize {
+        let tags: Vec<String> = {
+            let generations = self
+                .generations
+                .lock()
+                .expect("Failed to lock generations mutex");
+            generations
+                .iter()
+                .filter(|(_, generation)| predicate(generation))
+                .map(|(tag, generation)| {
+                    generation.abort_handle.abort();
+                    tag.clone()
+                })
+                .collect()
+        };
+        for tag in &tags {
+            events::broadcast(tag, "[[STOPPED]]");
+        }
+        log::info!("Stopped {} generations", tags.len());
+        tags.len()
+    }
+
+    /// Number of bot calls currently running
+    pub fn active_count(&self) -> usize {
+        self.generations
+            .lock()
+            .expect("Failed to lock generations mutex")
+            .len()
+    }
+}
+
+/// Broadcast the number of running bot calls, so the tray and the frontend can show an indicator
+fn notify_changed(count: usize) {
+    events::broadcast(EVENT_GENERATIONS_CHANGED, count);
+}
+
+/// Name of the event stopping the bot calls of a conversation
+pub fn stop_event(conversation_id: i32) -> String {
+    format!("{}{}", EVENT_STOP_BOT_PREFIX, conversation_id)
+}
+
+/// Identify a request by the messages it sends, ignoring their ids and dates
+pub fn request_fingerprint(messages: &[MessageDTO]) -> u64 {
+    let messages: Vec<serde_json::Value> = messages
+        .iter()
+        .map(|message| json!({ "role": message.role, "content": message.content }))
+        .collect();
+    let mut hasher = DefaultHasher::new();
+    serde_json::Value::Array(messages)
+        .to_string()
+        .hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Whether a new message repeats the last one of its conversation within the duplicate
+/// window, as when a double-click sends it twice
+pub fn is_repeated_message(last: &MessageDTO, message: &MessageDTO) -> bool {
+    last.role == message.role
+        && last.content == message.content
+        && (chrono::Local::now() - last.created_at)
+            .to_std()
+            .map_or(false, |elapsed| elapsed < DUPLICATE_WINDOW)
+}
+
+#[cfg(test)]
+mod tests {
+    use entity::entities::messages::Roles;
+
+    use crate::services::llm::context::text_message;
+
+    use super::*;
+
+    #[test]
+    fn test_claim_request() {
+        let generations = GenerationManager::new();
+        let hello = request_fingerprint(&[text_message(Roles::User, "Hello".to_string())]);
+        let bye = request_fingerprint(&[text_message(Roles::User, "Bye".to_string())]);
+        assert_eq!(None, generations.claim_request(1, hello, "first"));
+        assert_eq!(
+            Some("first".to_string()),
+            generations.claim_request(1, hello, "second")
+        );
+        assert_eq!(
+            None,
+            generations.claim_request(2, hello, "other-conversation")
+        );
+        assert_eq!(None, generations.claim_request(1, bye, "other-request"));
+        // Once answered, the same request can be sent again
+        generations.finish("first");
+        assert_eq!(None, generations.claim_request(1, hello, "third"));
+    }
+
+    #[test]
+    fn test_stream_meter() {
+        let chunk = |message: &str, completion_token: Option<u32>| BotReply {
+            message: message.to_string(),
+            completion_token,
+            ..Default::default()
+        };
+        let mut meter = StreamMeter::start();
+        assert_eq!(None, meter.finish());
+        let start = meter.started_at;
+        meter.record_at(&chunk("Hello", None), start + Duration::from_millis(300));
+        meter.record_at(&chunk(" world", None), start + Duration::from_millis(1_300));
+        let metrics = meter.finish().unwrap();
+        assert_eq!(300, metrics.time_to_first_token_ms);
+        assert_eq!(1_000, metrics.generation_ms);
+        // 11 characters at 4 per token
+        assert_eq!(3, metrics.completion_tokens);
+        assert!(metrics.estimated_tokens);
+        // The usage chunk comes last, without text
+        meter.record_at(&chunk("", Some(20)), start + Duration::from_millis(5_000));
+        let metrics = meter.finish().unwrap();
+        assert_eq!(1_000, metrics.generation_ms);
+        assert_eq!(20, metrics.completion_tokens);
+        assert!(!metrics.estimated_tokens);
+        assert_eq!(Some(20.0), metrics.tokens_per_second);
+    }
+}
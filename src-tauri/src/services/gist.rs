@@ -0,0 +1,135 @@
+use entity::entities::{conversations::ConversationDetailsDTO, settings::SETTING_GITHUB_TOKEN};
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{db::Repository, llm::context::get_global_http_client, markdown};
+
+const GISTS_API: &str = "https://api.github.com/gists";
+const USER_AGENT: &str = "Kaas";
+
+#[derive(Debug, Deserialize)]
+struct GistResponse {
+    id: String,
+    html_url: String,
+    public: bool,
+}
+
+async fn get_token(repo: &Repository) -> Result<String, String> {
+    repo.get_setting(SETTING_GITHUB_TOKEN)
+        .await
+        .map(|setting| setting.value)
+        .filter(|token| !token.trim().is_empty())
+        .ok_or("Set a GitHub token to share conversations".to_string())
+}
+
+fn github_request(request: reqwest::RequestBuilder, token: &str) -> reqwest::RequestBuilder {
+    request
+        .bearer_auth(token)
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+        .header(reqwest::header::USER_AGENT, USER_AGENT)
+}
+
+/**
+ * Upload the markdown export of a conversation as a gist and record it on the conversation.
+ * Sharing again updates the same gist. GitHub can't change the visibility of a gist,
+ * so a new gist replaces it when the visibility changes.
+ */
+pub async fn share_conversation(
+    repo: &Repository,
+    conversation_id: i32,
+    public: bool,
+) -> Result<ConversationDetailsDTO, String> {
+    let token = get_token(repo).await?;
+    let conversation = repo.get_conversation_details(conversation_id).await?;
+    let content = markdown::export_conversation(repo, conversation_id).await?;
+    let body = json!({
+        "description": conversation.subject,
+        "public": public,
+        "files": { format!("conversation-{}.md", conversation_id): { "content": content } },
+    });
+    let client = get_global_http_client(repo).await;
+    let current = match &conversation.gist_id {
+        Some(gist_id) => get_gist(&client, &token, gist_id).await?,
+        None => None,
+    };
+    let request = match &current {
+        Some(current) if current.public == public => {
+            client.patch(format!("{}/{}", GISTS_API, current.id))
+        }
+        _ => client.post(GISTS_API),
+    };
+    let gist = github_request(request, &token)
+        .json(&body)
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .map_err(|err| format!("Failed to upload gist: {}", err))?
+        .json::<GistResponse>()
+        .await
+        .map_err(|err| format!("Failed to read gist response: {}", err))?;
+    if let Some(current) = current.filter(|current| current.id != gist.id) {
+        delete_gist(&client, &token, &current.id).await?;
+    }
+    log::info!(
+        "Conversation {} shared as gist {}",
+        conversation_id,
+        gist.id
+    );
+    repo.update_conversation_gist(conversation_id, Some(gist.id), Some(gist.html_url))
+        .await
+}
+
+/**
+ * Delete the gist a conversation was shared as
+ */
+pub async fn revoke_share(
+    repo: &Repository,
+    conversation_id: i32,
+) -> Result<ConversationDetailsDTO, String> {
+    let conversation = repo.get_conversation_details(conversation_id).await?;
+    let gist_id = match conversation.gist_id {
+        Some(gist_id) => gist_id,
+        None => return Ok(conversation),
+    };
+    let token = get_token(repo).await?;
+    let client = get_global_http_client(repo).await;
+    delete_gist(&client, &token, &gist_id).await?;
+    repo.update_conversation_gist(conversation_id, None, None)
+        .await
+}
+
+// The gist, None when it was deleted on GitHub
+async fn get_gist(
+    client: &reqwest::Client,
+    token: &str,
+    gist_id: &str,
+) -> Result<Option<GistResponse>, String> {
+    let response = github_request(client.get(format!("{}/{}", GISTS_API, gist_id)), token)
+        .send()
+        .await
+        .map_err(|err| format!("Failed to get gist: {}", err))?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    let gist = response
+        .error_for_status()
+        .map_err(|err| format!("Failed to get gist: {}", err))?
+        .json::<GistResponse>()
+        .await
+        .map_err(|err| format!("Failed to read gist response: {}", err))?;
+    Ok(Some(gist))
+}
+
+async fn delete_gist(client: &reqwest::Client, token: &str, gist_id: &str) -> Result<(), String> {
+    let response = github_request(client.delete(format!("{}/{}", GISTS_API, gist_id)), token)
+        .send()
+        .await
+        .map_err(|err| format!("Failed to delete gist: {}", err))?;
+    // Already deleted on GitHub is as good as revoked
+    if response.status() != reqwest::StatusCode::NOT_FOUND {
+        response
+            .error_for_status()
+            .map_err(|err| format!("Failed to delete gist: {}", err))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,113 @@
+use entity::entities::models::Model;
+use serde::Serialize;
+
+use super::{limits::ModelLimits, options, pricing};
+
+/// Model families that read images, matched by prefix of the model name. More specific
+/// prefixes come first, so the exceptions of a family can be listed before it.
+const VISION_MODELS: &[(&str, bool)] = &[
+    ("gpt-4o", true),
+    ("gpt-4.1", true),
+    ("gpt-4-turbo", true),
+    ("gpt-4", false),
+    ("gpt-3.5", false),
+    ("o1-mini", false),
+    ("o3-mini", false),
+    ("o1", true),
+    ("o3", true),
+    ("o4", true),
+    ("claude", true),
+    ("gemini", true),
+    ("grok-2-vision", true),
+    ("grok-4", true),
+    ("llava", true),
+    ("llama3.2-vision", true),
+    ("pixtral", true),
+];
+/// Model families that call tools, matched the same way
+const TOOL_MODELS: &[(&str, bool)] = &[
+    ("o1-mini", false),
+    ("gpt-4", true),
+    ("gpt-3.5-turbo", true),
+    ("o1", true),
+    ("o3", true),
+    ("o4", true),
+    ("claude", true),
+    ("gemini", true),
+    ("deepseek-reasoner", false),
+    ("deepseek", true),
+    ("grok", true),
+    ("mistral", true),
+    ("llama3.1", true),
+    ("llama3.2", true),
+    ("llama3.3", true),
+    ("qwen2.5", true),
+    ("command-r", true),
+];
+
+/// What a model can do, so options and requests can be checked before reaching the
+/// provider
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelCapabilities {
+    pub context_length: Option<u32>,
+    pub max_output_tokens: Option<u32>,
+    pub supports_vision: bool,
+    pub supports_tools: bool,
+    /// Lowest and highest temperature accepted by the provider
+    pub temperature_range: (f64, f64),
+}
+
+impl ModelCapabilities {
+    /// The capabilities of a model, from its stored limits and the bundled catalogs
+    pub fn of(model: &Model) -> Self {
+        let name = pricing::model_name(&model.config).unwrap_or_default();
+        let limits = ModelLimits::of(model).or_catalog(&name);
+        ModelCapabilities {
+            context_length: limits.context_length,
+            max_output_tokens: limits.max_output_tokens,
+            supports_vision: supports(VISION_MODELS, &name),
+            supports_tools: supports(TOOL_MODELS, &name),
+            temperature_range: temperature_range(&model.provider),
+        }
+    }
+}
+
+/// The temperature range of a provider
+pub fn temperature_range(provider: &str) -> (f64, f64) {
+    options::option_ranges(provider)
+        .iter()
+        .find(|(key, _, _)| *key == "temperature")
+        .map_or((0.0, 2.0), |(_, min, max)| (*min, *max))
+}
+
+fn supports(families: &[(&str, bool)], model: &str) -> bool {
+    // Ignore the organization of names like "openai/gpt-4o" used by OpenRouter
+    let name = model.rsplit('/').next().unwrap_or(model).to_lowercase();
+    families
+        .iter()
+        .find(|(prefix, _)| name.starts_with(prefix))
+        .is_some_and(|(_, supported)| *supported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_supports() {
+        assert!(supports(VISION_MODELS, "gpt-4o-mini"));
+        assert!(supports(VISION_MODELS, "anthropic/claude-3-5-sonnet"));
+        assert!(!supports(VISION_MODELS, "o1-mini"));
+        assert!(!supports(VISION_MODELS, "gpt-4"));
+        assert!(supports(TOOL_MODELS, "gpt-4"));
+        assert!(!supports(TOOL_MODELS, "deepseek-reasoner"));
+        assert!(!supports(TOOL_MODELS, "unknown-model"));
+    }
+
+    #[test]
+    fn test_temperature_range() {
+        assert_eq!((0.0, 1.0), temperature_range("Claude"));
+        assert_eq!((0.0, 2.0), temperature_range("OpenAI"));
+    }
+}
@@ -1,6 +1,6 @@
-use std::pin::Pin;
+use std::{future::Future, pin::Pin};
 
-use crate::log_utils::warn;
+use crate::{errors::CommandError, log_utils::warn};
 use async_openai::{
     config::{AzureConfig, Config, OpenAIConfig},
     error::OpenAIError,
@@ -8,29 +8,46 @@ use async_openai::{
     Client,
 };
 use entity::entities::{
-    conversations::{AzureOptions, ClaudeOptions, DeepseekOptions, GenericOptions, GoogleOptions, OllamaOptions, OpenAIOptions, XaiOptions},
-    messages::MessageDTO,
+    contents::{ContentDTO, ContentType},
+    conversations::{AzureOptions, BedrockOptions, ClaudeOptions, CohereOptions, DeepseekOptions, GenericOptions, GoogleOptions, OllamaOptions, OpenAIOptions, XaiOptions},
+    messages::{MessageDTO, Roles},
+    response_schemas::Model as ResponseSchema,
 };
 use serde::Serialize;
 use tokio_stream::{Stream, StreamExt};
 
 use super::{
+    limits::{self, ModelLimits},
     providers::{
+        aggregator::{chat::AggregatorChat, config::AggregatorConfig},
+        bedrock::{
+            chat::{messages_to_bedrock_request, BedrockChat, BedrockConverseRequest, BedrockInferenceConfig},
+            client::BedrockClient,
+        },
         claude::{
             chat::{
                 ClaudeChat, ClaudeChatCompletionRequest, ClaudeChatCompletionResponseStream,
                 ClaudeChatCompletionStreamResponse, ClaudeMessage, ClaudeMetadata,
-                ClaudeResponseMessageContent, ContentBlockDelta,
+                ClaudeResponseMessageContent, ClaudeThinking, ContentBlockDelta,
             },
             config::ClaudeConfig,
-        }, deepseek::{chat::{DeepseekChat, DeepseekChatCompletionRequest, DeepseekChatCompletionResponseStream}, config::DeepseekConfig}, google::{chat::{GoogleChat, GoogleChatCompletionContentPart, GoogleChatCompletionRequest, GoogleChatCompletionRequestGenerationConfig}, config::GoogleConfig}, ollama::{
+        },
+        cohere::{
+            chat::{
+                messages_to_cohere_request, CohereChat, CohereChatRequest, CohereConnector,
+                CohereStreamEvent, COHERE_WEB_SEARCH_CONNECTOR,
+            },
+            config::CohereConfig,
+        }, custom::config::CustomConfig, deepseek::{chat::{DeepseekChat, DeepseekChatCompletionRequest, DeepseekChatCompletionResponseStream}, config::DeepseekConfig}, google::{chat::{GoogleChat, GoogleChatCompletionFinishReason, GoogleChatCompletionRequest, GoogleChatCompletionRequestGenerationConfig, GoogleThinkingConfig}, config::GoogleConfig}, ollama::{
             chat::{
                 OllamaChat, OllamaChatCompletionRequest, OllamaChatCompletionResponseStream,
                 OllamaMessage,
             },
             config::OllamaConfig,
-        }, openai::chat::{OpenAIChat, OpenAIChatCompletionRequest, OpenAIChatCompletionResponseStream}, openrouter::chat::{OpenrouterChat, OpenrouterChatCompletionRequest, OpenrouterChatCompletionResponseStream}, types::{ChatCompletionRequestCommon, ChatCompletionStreamOptions}, xai::{chat::{XaiChat, XaiChatCompletionRequest, XaiChatCompletionResponseStream}, config::XaiConfig}
+        }, openai::chat::{OpenAIChat, OpenAIChatCompletionRequest, OpenAIChatCompletionResponseStream, OpenAIChatCompletionStreamResponse}, openrouter::chat::{OpenrouterChat, OpenrouterChatCompletionRequest, OpenrouterChatCompletionResponseStream}, types::{ChatCompletionJsonSchema, ChatCompletionRequestCommon, ChatCompletionResponseFormat, ChatCompletionResponseFormatType, ChatCompletionStreamOptions, FinishReason}, xai::{chat::{XaiChat, XaiChatCompletionRequest, XaiChatCompletionResponseStream}, config::XaiConfig}
     },
+    schema,
+    tokenizer::{self, TokenCount},
     utils::{message_to_google_request_message, message_to_openai_request_message, sum_option},
 };
 
@@ -53,78 +70,179 @@ pub struct BotReply {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(skip_deserializing)]
     pub total_token: Option<u32>,
+    /// Whether the model stopped because the max tokens limit was reached
+    #[serde(skip)]
+    pub truncated: bool,
+}
+
+impl BotReply {
+    /// Convert the reply into a bot message of the given conversation, ready to be stored
+    pub fn into_message(self, conversation_id: i32) -> MessageDTO {
+        MessageDTO {
+            conversation_id,
+            role: Roles::Bot.into(),
+            reasoning: self.reasoning,
+            prompt_token: self.prompt_token,
+            completion_token: self.completion_token,
+            reasoning_token: self.reasoning_token,
+            total_token: self.total_token,
+            content: vec![ContentDTO {
+                r#type: ContentType::Text,
+                mimetype: None,
+                data: self.message,
+            }],
+            ..Default::default()
+        }
+    }
+
+    /// Append the continuation of a truncated reply, adding up the token usage of both requests
+    pub fn append(&mut self, part: BotReply) {
+        self.message.push_str(&part.message);
+        if let Some(reasoning) = part.reasoning {
+            self.reasoning.get_or_insert_with(String::new).push_str(&reasoning);
+        }
+        self.prompt_token = sum_option(self.prompt_token, part.prompt_token);
+        self.completion_token = sum_option(self.completion_token, part.completion_token);
+        self.reasoning_token = sum_option(self.reasoning_token, part.reasoning_token);
+        self.total_token = sum_option(self.total_token, part.total_token);
+        self.truncated = part.truncated;
+    }
 }
 
 pub type BotReplyStream = Pin<Box<dyn Stream<Item = Result<BotReply, OpenAIError>> + Send>>;
 
+/// Smallest thinking budget accepted by Claude
+const CLAUDE_MIN_THINKING_BUDGET: u32 = 1024;
+
+#[derive(Clone)]
 pub struct GlobalSettings {
     pub max_tokens: u32,
+    /// Limits stored with the model, completed by the catalog when unknown
+    pub model_limits: ModelLimits,
+    /// Language the replies must be written in, if set on the conversation
+    pub language: Option<String>,
+    /// The JSON Schema the reply must match, if any
+    pub response_schema: Option<ResponseSchema>,
 }
 
-pub enum ChatRequestExecutor<'c> {
-    OpenAIChatRequestExecutor(&'c Client<OpenAIConfig>, OpenAIChatCompletionRequest),
-    AzureChatRequestExecutor(&'c Client<AzureConfig>, OpenAIChatCompletionRequest),
-    ClaudeChatRequestExecutor(&'c Client<ClaudeConfig>, ClaudeChatCompletionRequest),
-    OllamaChatRequestExecutor(&'c Client<OllamaConfig>, OllamaChatCompletionRequest),
-    OpenrouterChatRequestExecutor(&'c Client<OpenAIConfig>, OpenrouterChatCompletionRequest),
-    DeepseekChatRequestExecutor(&'c Client<DeepseekConfig>, DeepseekChatCompletionRequest),
-    XaiChatRequestExecutor(&'c Client<XaiConfig>, XaiChatCompletionRequest),
-    GoogleChatRequestExecutor(&'c Client<GoogleConfig>, GoogleChatCompletionRequest),
+impl GlobalSettings {
+    /// Max tokens of a request without its own value: the global setting, lowered to the
+    /// limits of the model that are known
+    pub fn max_tokens_for(&self, model: &str, messages: &[MessageDTO]) -> u32 {
+        limits::max_reply_tokens(
+            self.model_limits.or_catalog(model),
+            self.max_tokens,
+            messages,
+            self.language.as_deref(),
+        )
+    }
+
+    /// Structured outputs constraint for providers supporting JSON Schemas
+    fn json_schema_format(&self) -> Option<ChatCompletionResponseFormat> {
+        let schema = self.response_schema.as_ref()?;
+        let value = serde_json::from_str(&schema.schema).ok()?;
+        Some(ChatCompletionResponseFormat {
+            r#type: ChatCompletionResponseFormatType::JsonSchema,
+            json_schema: Some(ChatCompletionJsonSchema {
+                name: schema::format_name(&schema.name),
+                schema: value,
+                strict: None,
+            }),
+        })
+    }
+
+    /// JSON mode for providers without JSON Schema support, the schema being in the prompt
+    fn json_object_format(&self) -> Option<ChatCompletionResponseFormat> {
+        self.response_schema.as_ref().map(|_| ChatCompletionResponseFormat {
+            r#type: ChatCompletionResponseFormatType::JsonObject,
+            json_schema: None,
+        })
+    }
 }
 
-impl<'c> ChatRequestExecutor<'c> {
-    pub fn openai(
-        client: &'c Client<OpenAIConfig>,
+/// A provider's chat API: how its requests are built from the messages of a conversation,
+/// and how its replies are read, whole or streamed
+pub trait ChatProvider {
+    /// The body of the chat requests of the provider
+    type Request: Serialize + Send;
+
+    /// Build the request of a reply to the messages by the model
+    fn build_request(
+        &self,
         messages: Vec<MessageDTO>,
         options: GenericOptions,
         global_settings: GlobalSettings,
         model: String,
-    ) -> Result<ChatRequestExecutor, String> {
-        let request: OpenAIChatCompletionRequest;
-        // set messages
-        let req_messages = messages
-            .into_iter()
-            .map(message_to_openai_request_message)
-            .collect();
-        // set options
-        let options: OpenAIOptions = serde_json::from_str(&options.options)
-            .map_err(|_| format!("Failed to parse conversation options: {}", &options.options))?;
-        // build request
-        request = OpenAIChatCompletionRequest {
-            common: ChatCompletionRequestCommon {
-                model: model.to_string(),
-                frequency_penalty: options.frequency_penalty,
-                max_tokens: options.max_tokens.or(Some(global_settings.max_tokens)),
-                presence_penalty: options.presence_penalty,
-                stream: options.stream,
-                stream_options: if options.stream.unwrap_or(false) {
-                    // default to return usage when streaming
-                    Some(ChatCompletionStreamOptions {
-                        include_usage: true
-                    })
-                } else {
-                    None
-                },
-                temperature: options.temperature,
-                top_p: options.top_p,
-                ..Default::default()
-            },
-            reasoning_effort: options.reasoning_effort.map(|x| x.into()),
-            messages: req_messages,
-            user: options.user,
-            ..Default::default()
-        };
-        Ok(ChatRequestExecutor::OpenAIChatRequestExecutor(client, request))
+    ) -> Result<Self::Request, String>;
+
+    /// Send the request and wait for the whole reply
+    fn complete(
+        &self,
+        request: Self::Request,
+    ) -> impl Future<Output = Result<BotReply, String>> + Send;
+
+    /// Send the request and stream the reply
+    fn stream(
+        &self,
+        request: Self::Request,
+    ) -> impl Future<Output = Result<BotReplyStream, String>> + Send;
+
+    /// Tell the kind of an error of the provider from its message
+    fn parse_error(&self, error: String) -> CommandError {
+        CommandError::from_provider(error)
+    }
+
+    /// Count the tokens of a prompt to the model. Providers without a public tokenizer are
+    /// counted with the one of recent OpenAI models.
+    fn count_tokens(
+        &self,
+        messages: &[MessageDTO],
+        model: &str,
+        limits: ModelLimits,
+    ) -> TokenCount {
+        tokenizer::count_message_tokens(messages, model, limits)
+    }
+}
+
+pub struct OpenAIProvider<'c>(pub &'c Client<OpenAIConfig>);
+
+impl ChatProvider for OpenAIProvider<'_> {
+    type Request = OpenAIChatCompletionRequest;
+
+    fn build_request(
+        &self,
+        messages: Vec<MessageDTO>,
+        options: GenericOptions,
+        global_settings: GlobalSettings,
+        model: String,
+    ) -> Result<Self::Request, String> {
+        openai_request(messages, options, global_settings, model)
+    }
+
+    async fn complete(&self, request: Self::Request) -> Result<BotReply, String> {
+        complete_openai_compatible(self.0, request).await
+    }
+
+    async fn stream(&self, request: Self::Request) -> Result<BotReplyStream, String> {
+        stream_openai_compatible(self.0, request).await
     }
+}
+
+pub struct AzureProvider<'c>(pub &'c Client<AzureConfig>);
+
+impl ChatProvider for AzureProvider<'_> {
+    type Request = OpenAIChatCompletionRequest;
 
-    pub fn azure(
-        client: &'c Client<AzureConfig>,
+    fn build_request(
+        &self,
         messages: Vec<MessageDTO>,
         options: GenericOptions,
         global_settings: GlobalSettings,
-        _model: String,
-    ) -> Result<ChatRequestExecutor, String> {
+        model: String,
+    ) -> Result<Self::Request, String> {
         let request: OpenAIChatCompletionRequest;
+        // default to what is left of the context window after the prompt
+        let max_tokens = global_settings.max_tokens_for(&model, &messages);
         // set messages
         let req_messages = messages
             .into_iter()
@@ -137,28 +255,45 @@ impl<'c> ChatRequestExecutor<'c> {
         request = OpenAIChatCompletionRequest {
             common: ChatCompletionRequestCommon {
                 frequency_penalty: options.frequency_penalty,
-                max_tokens: options.max_tokens.or(Some(global_settings.max_tokens)),
+                max_tokens: options.max_tokens.or(Some(max_tokens)),
                 presence_penalty: options.presence_penalty,
                 stream: options.stream,
                 temperature: options.temperature,
                 top_p: options.top_p,
+                response_format: global_settings.json_schema_format(),
                 ..Default::default()
             },
             messages: req_messages,
             user: options.user,
             ..Default::default()
         };
-        Ok(ChatRequestExecutor::AzureChatRequestExecutor(client, request))
+        Ok(request)
+    }
+
+    async fn complete(&self, request: Self::Request) -> Result<BotReply, String> {
+        complete_openai_compatible(self.0, request).await
     }
 
-    pub fn claude(
-        client: &'c Client<ClaudeConfig>,
+    async fn stream(&self, request: Self::Request) -> Result<BotReplyStream, String> {
+        stream_openai_compatible(self.0, request).await
+    }
+}
+
+pub struct ClaudeProvider<'c>(pub &'c Client<ClaudeConfig>);
+
+impl ChatProvider for ClaudeProvider<'_> {
+    type Request = ClaudeChatCompletionRequest;
+
+    fn build_request(
+        &self,
         messages: Vec<MessageDTO>,
         options: GenericOptions,
         global_settings: GlobalSettings,
         model: String,
-    ) -> Result<ChatRequestExecutor, String> {
+    ) -> Result<Self::Request, String> {
         let request: ClaudeChatCompletionRequest;
+        // default to what is left of the context window after the prompt
+        let max_tokens = global_settings.max_tokens_for(&model, &messages);
         // set messages
         let req_messages: Vec<ClaudeMessage> = messages
             .into_iter()
@@ -167,30 +302,130 @@ impl<'c> ChatRequestExecutor<'c> {
         // set options
         let options: ClaudeOptions = serde_json::from_str(&options.options)
             .map_err(|_| format!("Failed to parse conversation options: {}", &options.options))?;
+        let max_tokens = options.max_tokens.unwrap_or(max_tokens);
+        // thinking budget comes on top of the answer's max_tokens, as it must be lower than max_tokens
+        let thinking_budget = options
+            .thinking_budget
+            .filter(|budget| *budget > 0)
+            .map(|budget| budget.max(CLAUDE_MIN_THINKING_BUDGET));
         // build request
         request = ClaudeChatCompletionRequest {
             common: ChatCompletionRequestCommon {
                 model: model.to_string(),
-                max_tokens: options.max_tokens.or(Some(global_settings.max_tokens)), // Claude requires max_tokens
+                max_tokens: Some(max_tokens + thinking_budget.unwrap_or(0)), // Claude requires max_tokens
                 stream: options.stream,
-                temperature: options.temperature,
+                // thinking isn't compatible with temperature changes
+                temperature: if thinking_budget.is_some() {
+                    None
+                } else {
+                    options.temperature
+                },
                 top_p: options.top_p,
                 ..Default::default()
             },
             messages: req_messages,
             metadata: options.user.map(|user| ClaudeMetadata { user_id: user }),
+            thinking: thinking_budget.map(|budget_tokens| ClaudeThinking::Enabled { budget_tokens }),
             ..Default::default()
         };
-        Ok(ChatRequestExecutor::ClaudeChatRequestExecutor(client, request))
+        Ok(request)
+    }
+
+    async fn complete(&self, request: Self::Request) -> Result<BotReply, String> {
+        let response = ClaudeChat::new(self.0)
+            .create(request)
+            .await
+            .map_err(|err| {
+                log::error!("execute ChatRequest::ClaudeChatRequest: {:?}", err);
+                format!("Failed to get chat completion response: {}", err)
+            })?;
+        // extract data & build reply
+        if response.content.is_empty() {
+            return Err("Api returned empty content".to_string());
+        }
+        // thinking blocks come before the text of the answer
+        let mut message = String::default();
+        let mut reasoning: Option<String> = None;
+        for content in response.content.iter() {
+            match content {
+                ClaudeResponseMessageContent::Text(text) => message.push_str(&text.text),
+                ClaudeResponseMessageContent::ToolUse(_) => {
+                    message.push_str("ToolUse is not implemented yet")
+                }
+                ClaudeResponseMessageContent::Thinking(thinking) => reasoning
+                    .get_or_insert_with(String::new)
+                    .push_str(&thinking.thinking),
+                ClaudeResponseMessageContent::RedactedThinking(_) => {}
+            }
+        }
+        let usage = response.usage;
+
+        Ok(BotReply {
+            message,
+            reasoning,
+            prompt_token: usage.input_tokens,
+            completion_token: usage.output_tokens,
+            reasoning_token: None,
+            total_token: sum_option(usage.input_tokens, usage.output_tokens),
+            truncated: response.stop_reason.as_deref() == Some("max_tokens"),
+        })
+    }
+
+    async fn stream(&self, request: Self::Request) -> Result<BotReplyStream, String> {
+        let stream: ClaudeChatCompletionResponseStream = ClaudeChat::new(self.0)
+            .create_stream(request)
+            .await
+            .map_err(|err| format!("Error creating stream: {}", err.to_string()))?;
+        let result = stream.map(|item| {
+            item.map(|resp| {
+                match resp {
+                    ClaudeChatCompletionStreamResponse::ContentBlockDelta(
+                        content_delta,
+                    ) => {
+                        match content_delta.delta {
+                            ContentBlockDelta::TextDelta(text_delta) => BotReply {
+                                message: text_delta.text.clone(),
+                                ..Default::default()
+                            },
+                            ContentBlockDelta::ThinkingDelta(thinking_delta) => BotReply {
+                                reasoning: Some(thinking_delta.thinking.clone()),
+                                ..Default::default()
+                            },
+                            _ => BotReply::default(),
+                        }
+                    },
+                    ClaudeChatCompletionStreamResponse::MessageDelta(message_delta) => {
+                        // return empty string as message
+                        BotReply {
+                            prompt_token: message_delta.usage.input_tokens,
+                            completion_token: message_delta.usage.output_tokens,
+                            total_token: sum_option(
+                                message_delta.usage.input_tokens,
+                                message_delta.usage.output_tokens,
+                            ),
+                            truncated: message_delta.delta.stop_reason == "max_tokens",
+                            ..Default::default()
+                        }
+                    }
+                }
+            })
+        });
+        Ok(Box::pin(result))
     }
+}
+
+pub struct OllamaProvider<'c>(pub &'c Client<OllamaConfig>);
+
+impl ChatProvider for OllamaProvider<'_> {
+    type Request = OllamaChatCompletionRequest;
 
-    pub fn ollama(
-        client: &'c Client<OllamaConfig>,
+    fn build_request(
+        &self,
         messages: Vec<MessageDTO>,
         options: GenericOptions,
         _global_settings: GlobalSettings,
         model: String,
-    ) -> Result<ChatRequestExecutor, String> {
+    ) -> Result<Self::Request, String> {
         let request: OllamaChatCompletionRequest;
         // set messages
         let req_messages: Vec<OllamaMessage> = messages
@@ -213,16 +448,118 @@ impl<'c> ChatRequestExecutor<'c> {
             options: Some(options.into()),
             ..Default::default()
         };
-        Ok(ChatRequestExecutor::OllamaChatRequestExecutor(client, request))
+        Ok(request)
+    }
+
+    async fn complete(&self, request: Self::Request) -> Result<BotReply, String> {
+        let log_tag = "ChatProvider::complete";
+        let response = OllamaChat::new(self.0)
+            .create(request)
+            .await
+            .map_err(|err| {
+                log::error!("execute ChatRequest::OllamaChatRequest: {:?}", err);
+                format!("Failed to get chat completion response: {}", err)
+            })?;
+        let message: String = match response.message {
+            Some(response_message) => match response_message {
+                OllamaMessage::Assistant(content) => content.content,
+                _ => {
+                    warn(
+                        log_tag,
+                        "OllamaChat::create returned a non-assistant message",
+                    );
+                    String::default()
+                }
+            },
+            _ => {
+                warn(log_tag, "OllamaChat::create returned an empty message");
+                String::default()
+            }
+        };
+        // extract data & build reply
+        Ok(BotReply {
+            message,
+            reasoning: None,
+            prompt_token: response.prompt_eval_count,
+            completion_token: response.eval_count,
+            reasoning_token: None,
+            total_token: sum_option(response.prompt_eval_count, response.eval_count),
+            truncated: response.done_reason.as_deref() == Some("length"),
+        })
+    }
+
+    async fn stream(&self, request: Self::Request) -> Result<BotReplyStream, String> {
+        let log_tag = "ChatProvider::stream";
+        let stream: OllamaChatCompletionResponseStream = OllamaChat::new(self.0)
+            .create_stream(request)
+            .await
+            .map_err(|err| format!("Error creating stream: {}", err.to_string()))?;
+        let mut is_reasoning = false;
+        let result = stream.map(move |item| {
+            item.map(|response| {
+                let content: String = match response.message {
+                    Some(response_message) => match response_message{
+                        OllamaMessage::Assistant(content) => {
+                            // check for reasoning content
+                            // return empty content for <think> and </think>
+                            if content.content.contains("<think>") {
+                                is_reasoning = true;
+                                String::default()
+                            } else if content.content.contains("</think>") {
+                                is_reasoning = false;
+                                String::default()
+                            } else {
+                                content.content
+                            }
+                        },
+                        _ => {
+                            warn(log_tag, "OllamaChat::create_stream returned a non-assistant message");
+                            String::default()
+                        }
+                    },
+                    _ => {
+                        // normally the last message of the stream
+                        String::default()
+                    }
+                };
+
+                BotReply {
+                    message: if is_reasoning {
+                        String::default()
+                    } else {
+                        content.clone()
+                    },
+                    reasoning: if is_reasoning {
+                        Some(content)
+                    } else {
+                        None
+                    },
+                    prompt_token: response.prompt_eval_count,
+                    completion_token: response.eval_count,
+                    reasoning_token: None,
+                    total_token: sum_option(response.prompt_eval_count, response.eval_count),
+                    truncated: response.done_reason.as_deref() == Some("length"),
+                }
+            })
+        });
+        Ok(Box::pin(result))
     }
+}
 
-    pub fn openrouter(
-        client: &'c Client<OpenAIConfig>,
+pub struct OpenrouterProvider<'c>(pub &'c Client<OpenAIConfig>);
+
+impl ChatProvider for OpenrouterProvider<'_> {
+    type Request = OpenrouterChatCompletionRequest;
+
+    fn build_request(
+        &self,
         messages: Vec<MessageDTO>,
         options: GenericOptions,
         global_settings: GlobalSettings,
         model: String,
-    ) -> Result<ChatRequestExecutor, String> {
+    ) -> Result<Self::Request, String> {
+        // default to what is left of the context window after the prompt
+        let max_tokens = global_settings.max_tokens_for(&model, &messages);
         // set messages
         let req_messages: Vec<ChatCompletionRequestMessage> = messages
             .into_iter()
@@ -238,83 +575,119 @@ impl<'c> ChatRequestExecutor<'c> {
                 stream: options.stream,
                 temperature: options.temperature,
                 top_p: options.top_p,
-                max_tokens: options.max_tokens.or(Some(global_settings.max_tokens)),
+                max_tokens: options.max_tokens.or(Some(max_tokens)),
                 frequency_penalty: options.frequency_penalty,
                 presence_penalty: options.presence_penalty,
+                response_format: global_settings.json_schema_format(),
                 ..Default::default()
             },
             messages: req_messages,
             include_reasoning: Some(true),
             ..Default::default()
         };
-        Ok(ChatRequestExecutor::OpenrouterChatRequestExecutor(client, request))
+        Ok(request)
     }
 
-    pub fn deepseek(
-        client: &'c Client<DeepseekConfig>,
-        messages: Vec<MessageDTO>,
-        options: GenericOptions,
-        global_settings: GlobalSettings,
-        model: String,
-    ) -> Result<ChatRequestExecutor, String> {
-        let request: DeepseekChatCompletionRequest;
-        // set messages
-        let req_messages = messages
-            .into_iter()
-            .map(message_to_openai_request_message)
-            .collect();
-        // set options
-        let options: DeepseekOptions = serde_json::from_str(&options.options)
-            .map_err(|_| format!("Failed to parse conversation options: {}", &options.options))?;
-        // build request
-        request = DeepseekChatCompletionRequest {
-            common: ChatCompletionRequestCommon {
-                model: model.to_string(),
-                stream: options.stream,
-                temperature: options.temperature,
-                top_p: options.top_p,
-                max_tokens: options.max_tokens.or(Some(global_settings.max_tokens)),
-                frequency_penalty: options.frequency_penalty,
-                presence_penalty: options.presence_penalty,
-                stream_options: if options.stream.unwrap_or(false) {
-                    // default to return usage when streaming
-                    Some(ChatCompletionStreamOptions {
-                        include_usage: true
-                    })
-                } else {
-                    None
-                },
-                ..Default::default()
-            },
-            messages: req_messages,
+    async fn complete(&self, request: Self::Request) -> Result<BotReply, String> {
+        let response = OpenrouterChat::new(self.0)
+            .create(request)
+            .await
+            .map_err(|err| {
+                log::error!("execute ChatRequest::OpenrouterChatRequest: {:?}", err);
+                format!("Failed to get chat completion response: {}", err)
+            })?;
+        // extract data & build reply
+        let choice = response
+            .choices
+            .first()
+            .ok_or("Api returned empty choices".to_string())?;
+        let message = choice
+            .message
+            .content
+            .as_ref()
+            .ok_or("Api returned empty message".to_string())?
+            .to_string();
+        let usage = response.common.usage;
+        let reply = BotReply {
+            message,
+            reasoning: choice.message.reasoning.clone(),
+            prompt_token: usage.as_ref().map(|usage| usage.prompt_tokens),
+            completion_token: usage.as_ref().map(|usage| usage.completion_tokens),
+            reasoning_token: usage.as_ref().map(|usage| usage.reasoning_tokens.unwrap_or(0)),
+            total_token: usage.as_ref().map(|usage| usage.total_tokens),
+            truncated: choice.finish_reason == Some(FinishReason::Length),
         };
-        Ok(ChatRequestExecutor::DeepseekChatRequestExecutor(client, request))
+
+        Ok(reply)
+    }
+
+    async fn stream(&self, request: Self::Request) -> Result<BotReplyStream, String> {
+        let stream: OpenrouterChatCompletionResponseStream = OpenrouterChat::new(self.0)
+            .create_stream(request)
+            .await
+            .map_err(|err| format!("Error creating stream: {}", err.to_string()))?;
+        let result = stream.map(|item| {
+            item.map(|resp| {
+                let first_choice =
+                    resp.choices.first()
+                    .map_or(BotReply::default(), |choice| {
+                        let usage = resp.common.usage.clone();
+                        let delta = choice.delta.clone();
+                        BotReply {
+                            message: delta.content
+                                .clone()
+                                .unwrap_or(String::default()),
+                            reasoning: delta.reasoning
+                                .clone(),
+                            prompt_token: usage.as_ref().map(|usage| usage.prompt_tokens),
+                            completion_token: usage
+                                .as_ref()
+                                .map(|usage| usage.completion_tokens),
+                            reasoning_token: usage
+                                .as_ref()
+                                .map(|usage| usage.reasoning_tokens.unwrap_or(0)),
+                            total_token: usage.as_ref().map(|usage| usage.total_tokens),
+                            truncated: choice.finish_reason == Some(FinishReason::Length),
+                        }
+                    });
+                first_choice
+            })
+        });
+        Ok(Box::pin(result))
     }
+}
+
+pub struct DeepseekProvider<'c>(pub &'c Client<DeepseekConfig>);
 
-    pub fn xai(
-        client: &'c Client<XaiConfig>,
+impl ChatProvider for DeepseekProvider<'_> {
+    type Request = DeepseekChatCompletionRequest;
+
+    fn build_request(
+        &self,
         messages: Vec<MessageDTO>,
         options: GenericOptions,
         global_settings: GlobalSettings,
         model: String,
-    ) -> Result<ChatRequestExecutor, String> {
-        let request: XaiChatCompletionRequest;
+    ) -> Result<Self::Request, String> {
+        let request: DeepseekChatCompletionRequest;
+        // default to what is left of the context window after the prompt
+        let max_tokens = global_settings.max_tokens_for(&model, &messages);
         // set messages
         let req_messages = messages
             .into_iter()
             .map(message_to_openai_request_message)
             .collect();
         // set options
-        let options: XaiOptions = serde_json::from_str(&options.options)
+        let options: DeepseekOptions = serde_json::from_str(&options.options)
             .map_err(|_| format!("Failed to parse conversation options: {}", &options.options))?;
         // build request
-        request = XaiChatCompletionRequest {
+        request = DeepseekChatCompletionRequest {
             common: ChatCompletionRequestCommon {
                 model: model.to_string(),
                 stream: options.stream,
                 temperature: options.temperature,
                 top_p: options.top_p,
-                max_tokens: options.max_tokens.or(Some(global_settings.max_tokens)),
+                max_tokens: options.max_tokens.or(Some(max_tokens)),
                 frequency_penalty: options.frequency_penalty,
                 presence_penalty: options.presence_penalty,
                 stream_options: if options.stream.unwrap_or(false) {
@@ -325,57 +698,19 @@ impl<'c> ChatRequestExecutor<'c> {
                 } else {
                     None
                 },
+                response_format: global_settings.json_object_format(),
                 ..Default::default()
             },
             messages: req_messages,
         };
-        Ok(ChatRequestExecutor::XaiChatRequestExecutor(client, request))
+        Ok(request)
     }
 
-    pub fn google(
-        client: &'c Client<GoogleConfig>,
-        messages: Vec<MessageDTO>,
-        options: GenericOptions,
-        global_settings: GlobalSettings,
-        _model: String,
-    ) -> Result<ChatRequestExecutor, String> {
-        let request: GoogleChatCompletionRequest;
-        // set messages
-        let req_messages = messages
-            .into_iter()
-            .map(message_to_google_request_message)
-            .collect();
-        // set options
-        let options: GoogleOptions = serde_json::from_str(&options.options)
-            .map_err(|_| format!("Failed to parse conversation options: {}", &options.options))?;
-        // build request
-        request = GoogleChatCompletionRequest {
-            contents: req_messages,
-            system_instruction: None,
-            generation_config: Some(GoogleChatCompletionRequestGenerationConfig {
-                max_output_tokens: options.max_tokens.or(Some(global_settings.max_tokens)),
-                temperature: options.temperature,
-                top_p: options.top_p,
-                presence_penalty: options.presence_penalty,
-                frequency_penalty: options.frequency_penalty,
-                ..Default::default()
-            }),
-        };
-        Ok(ChatRequestExecutor::GoogleChatRequestExecutor(client, request))
-    }
-
-    async fn execute_openai_compatible_request<C: Config>(
-        &self,
-        client: &Client<C>,
-        request: OpenAIChatCompletionRequest,
-    ) -> Result<BotReply, String> {
-        let response = OpenAIChat::new(client)
+    async fn complete(&self, request: Self::Request) -> Result<BotReply, String> {
+        let response = DeepseekChat::new(self.0)
             .create(request)
             .await
-            .map_err(|err| {
-                log::error!("execute_chat_complete_request: {:?}", err);
-                format!("Failed to get chat completion response: {}", err)
-            })?;
+            .map_err(|err| format!("Error creating stream: {}", err.to_string()))?;
         // extract data & build reply
         let choice = response
             .choices
@@ -387,10 +722,14 @@ impl<'c> ChatRequestExecutor<'c> {
             .as_ref()
             .ok_or("Api returned empty message".to_string())?
             .to_string();
+        let reasoning = choice
+            .message
+            .reasoning
+            .clone();
         let usage = response.common.usage;
         let reply = BotReply {
             message,
-            reasoning: None, // OpenAI doesn't return reasoning text yet
+            reasoning,
             prompt_token: usage.as_ref().map(|usage| usage.prompt_tokens),
             completion_token: usage.as_ref().map(|usage| usage.completion_tokens),
             reasoning_token: usage
@@ -405,40 +744,33 @@ impl<'c> ChatRequestExecutor<'c> {
                         .unwrap_or(0)
                 }),
             total_token: usage.as_ref().map(|usage| usage.total_tokens),
+            truncated: choice.finish_reason == Some(FinishReason::Length),
         };
 
         Ok(reply)
     }
 
-    async fn execute_openai_compatible_stream_request<C: Config>(
-        &self,
-        client: &Client<C>,
-        request: OpenAIChatCompletionRequest,
-    ) -> Result<BotReplyStream, String> {
-        let stream: OpenAIChatCompletionResponseStream = OpenAIChat::new(client)
+    async fn stream(&self, request: Self::Request) -> Result<BotReplyStream, String> {
+        let stream: DeepseekChatCompletionResponseStream = DeepseekChat::new(self.0)
             .create_stream(request)
             .await
             .map_err(|err| format!("Error creating stream: {}", err.to_string()))?;
         let result = stream.map(|item| {
             let reply = item.map(|resp| {
-                // OpenAI returns usage in the last chunk with an empty message/choice
-                let message = resp
-                    .choices
-                    .first()
-                    .map(|choice| {
-                        choice.delta.content
-                            .clone()
-                            .unwrap_or(String::default())
-                    })
+                // the last chunk only carries the usage, without any choice
+                let choice = resp.choices.first();
+                let message = choice
+                    .and_then(|choice| choice.delta.content.clone())
                     .unwrap_or(String::default());
+                // deepseek-reasoner streams its chain of thought before the answer
+                let reasoning = choice
+                    .and_then(|choice| choice.delta.reasoning.clone());
                 let usage = resp.common.usage;
                 BotReply {
                     message,
-                    reasoning: None, // OpenAI doesn't return reasoning text yet
+                    reasoning,
                     prompt_token: usage.as_ref().map(|usage| usage.prompt_tokens),
-                    completion_token: usage
-                        .as_ref()
-                        .map(|usage| usage.completion_tokens),
+                    completion_token: usage.as_ref().map(|usage| usage.completion_tokens),
                     reasoning_token: usage
                         .as_ref()
                         .map(|usage| {
@@ -451,185 +783,128 @@ impl<'c> ChatRequestExecutor<'c> {
                                 .unwrap_or(0)
                         }),
                     total_token: usage.as_ref().map(|usage| usage.total_tokens),
+                    truncated: choice.and_then(|choice| choice.finish_reason) == Some(FinishReason::Length),
+                    ..Default::default()
                 }
             });
             reply
         });
         Ok(Box::pin(result))
     }
+}
 
-    pub async fn execute(&self) -> Result<BotReply, String> {
-        let log_tag = "ChatRequest::execute";
-        match self {
-            ChatRequestExecutor::OpenAIChatRequestExecutor(client, request) => {
-                return self
-                    .execute_openai_compatible_request(client, request.clone())
-                    .await;
-            }
-            ChatRequestExecutor::AzureChatRequestExecutor(client, request) => {
-                return self
-                    .execute_openai_compatible_request(client, request.clone())
-                    .await;
-            }
-            ChatRequestExecutor::ClaudeChatRequestExecutor(client, request) => {
-                let response = ClaudeChat::new(client)
-                    .create(request.clone())
-                    .await
-                    .map_err(|err| {
-                        log::error!("execute ChatRequest::ClaudeChatRequest: {:?}", err);
-                        format!("Failed to get chat completion response: {}", err)
-                    })?;
-                // extract data & build reply
-                let content = response
-                    .content
-                    .first()
-                    .ok_or("Api returned empty content".to_string())?;
-                let message = match content {
-                    ClaudeResponseMessageContent::Text(text) => text.text.clone(),
-                    ClaudeResponseMessageContent::ToolUse(_) => {
-                        "ToolUse is not implemented yet".to_string()
-                    }
-                };
-                let usage = response.usage;
+pub struct XaiProvider<'c>(pub &'c Client<XaiConfig>);
 
-                Ok(BotReply {
-                    message,
-                    reasoning: None,
-                    prompt_token: usage.input_tokens,
-                    completion_token: usage.output_tokens,
-                    reasoning_token: None,
-                    total_token: sum_option(usage.input_tokens, usage.output_tokens),
-                })
-            }
-            ChatRequestExecutor::OllamaChatRequestExecutor(client, request) => {
-                let response = OllamaChat::new(client)
-                    .create(request.clone())
-                    .await
-                    .map_err(|err| {
-                        log::error!("execute ChatRequest::OllamaChatRequest: {:?}", err);
-                        format!("Failed to get chat completion response: {}", err)
-                    })?;
-                let message: String = match response.message {
-                    Some(response_message) => match response_message {
-                        OllamaMessage::Assistant(content) => content.content,
-                        _ => {
-                            warn(
-                                log_tag,
-                                "OllamaChat::create returned a non-assistant message",
-                            );
-                            String::default()
-                        }
-                    },
-                    _ => {
-                        warn(log_tag, "OllamaChat::create returned an empty message");
-                        String::default()
-                    }
-                };
-                // extract data & build reply
-                Ok(BotReply {
-                    message,
-                    reasoning: None,
-                    prompt_token: response.prompt_eval_count,
-                    completion_token: response.eval_count,
-                    reasoning_token: None,
-                    total_token: sum_option(response.prompt_eval_count, response.eval_count),
-                })
-            }
-            ChatRequestExecutor::OpenrouterChatRequestExecutor(client, request) => {
-                let response = OpenrouterChat::new(client)
-                    .create(request.clone())
-                    .await
-                    .map_err(|err| {
-                        log::error!("execute ChatRequest::OpenrouterChatRequest: {:?}", err);
-                        format!("Failed to get chat completion response: {}", err)
-                    })?;
-                // extract data & build reply
-                let choice = response
-                    .choices
-                    .first()
-                    .ok_or("Api returned empty choices".to_string())?;
-                let message = choice
-                    .message
-                    .content
-                    .as_ref()
-                    .ok_or("Api returned empty message".to_string())?
-                    .to_string();
-                let usage = response.common.usage;
-                let reply = BotReply {
-                    message,
-                    reasoning: choice.message.reasoning.clone(),
-                    prompt_token: usage.as_ref().map(|usage| usage.prompt_tokens),
-                    completion_token: usage.as_ref().map(|usage| usage.completion_tokens),
-                    reasoning_token: usage.as_ref().map(|usage| usage.reasoning_tokens.unwrap_or(0)),
-                    total_token: usage.as_ref().map(|usage| usage.total_tokens),
-                };
+impl ChatProvider for XaiProvider<'_> {
+    type Request = XaiChatCompletionRequest;
 
-                Ok(reply)
-            }
-            ChatRequestExecutor::DeepseekChatRequestExecutor(client, request) => {
-                let response = DeepseekChat::new(client)
-                    .create(request.clone())
-                    .await
-                    .map_err(|err| format!("Error creating stream: {}", err.to_string()))?;
-                // extract data & build reply
-                let choice = response
-                    .choices
-                    .first()
-                    .ok_or("Api returned empty choices".to_string())?;
-                let message = choice
-                    .message
-                    .content
-                    .as_ref()
-                    .ok_or("Api returned empty message".to_string())?
-                    .to_string();
-                let reasoning = choice
-                    .message
-                    .reasoning
-                    .clone();
-                let usage = response.common.usage;
-                let reply = BotReply {
-                    message,
-                    reasoning,
-                    prompt_token: usage.as_ref().map(|usage| usage.prompt_tokens),
-                    completion_token: usage.as_ref().map(|usage| usage.completion_tokens),
-                    reasoning_token: usage
+    fn build_request(
+        &self,
+        messages: Vec<MessageDTO>,
+        options: GenericOptions,
+        global_settings: GlobalSettings,
+        model: String,
+    ) -> Result<Self::Request, String> {
+        let request: XaiChatCompletionRequest;
+        // default to what is left of the context window after the prompt
+        let max_tokens = global_settings.max_tokens_for(&model, &messages);
+        // set messages
+        let req_messages = messages
+            .into_iter()
+            .map(message_to_openai_request_message)
+            .collect();
+        // set options
+        let options: XaiOptions = serde_json::from_str(&options.options)
+            .map_err(|_| format!("Failed to parse conversation options: {}", &options.options))?;
+        // build request
+        request = XaiChatCompletionRequest {
+            common: ChatCompletionRequestCommon {
+                model: model.to_string(),
+                stream: options.stream,
+                temperature: options.temperature,
+                top_p: options.top_p,
+                max_tokens: options.max_tokens.or(Some(max_tokens)),
+                frequency_penalty: options.frequency_penalty,
+                presence_penalty: options.presence_penalty,
+                stream_options: if options.stream.unwrap_or(false) {
+                    // default to return usage when streaming
+                    Some(ChatCompletionStreamOptions {
+                        include_usage: true
+                    })
+                } else {
+                    None
+                },
+                response_format: global_settings.json_schema_format(),
+                ..Default::default()
+            },
+            messages: req_messages,
+        };
+        Ok(request)
+    }
+
+    async fn complete(&self, request: Self::Request) -> Result<BotReply, String> {
+        let response = XaiChat::new(self.0)
+            .create(request)
+            .await
+            .map_err(|err| format!("Error creating stream: {}", err.to_string()))?;
+        // extract data & build reply
+        let choice = response
+            .choices
+            .first()
+            .ok_or("Api returned empty choices".to_string())?;
+        let message = choice
+            .message
+            .content
+            .as_ref()
+            .ok_or("Api returned empty message".to_string())?
+            .to_string();
+        let reasoning = choice
+            .message
+            .reasoning
+            .clone();
+        let usage = response.common.usage;
+        let reply = BotReply {
+            message,
+            reasoning,
+            prompt_token: usage.as_ref().map(|usage| usage.prompt_tokens),
+            completion_token: usage.as_ref().map(|usage| usage.completion_tokens),
+            reasoning_token: usage
+                .as_ref()
+                .map(|usage| {
+                    usage
+                        .completion_tokens_details
                         .as_ref()
-                        .map(|usage| {
-                            usage
-                                .completion_tokens_details
-                                .as_ref()
-                                .map(|details| {
-                                    details.reasoning_tokens.unwrap_or(0)
-                                })
-                                .unwrap_or(0)
-                        }),
-                    total_token: usage.as_ref().map(|usage| usage.total_tokens),
-                };
+                        .map(|details| {
+                            details.reasoning_tokens.unwrap_or(0)
+                        })
+                        .unwrap_or(0)
+                }),
+            total_token: usage.as_ref().map(|usage| usage.total_tokens),
+            truncated: choice.finish_reason == Some(FinishReason::Length),
+        };
 
-                Ok(reply)
-            }
-            ChatRequestExecutor::XaiChatRequestExecutor(client, request) => {
-                let response = XaiChat::new(client)
-                    .create(request.clone())
-                    .await
-                    .map_err(|err| format!("Error creating stream: {}", err.to_string()))?;
-                // extract data & build reply
-                let choice = response
-                    .choices
-                    .first()
-                    .ok_or("Api returned empty choices".to_string())?;
+        Ok(reply)
+    }
+
+    async fn stream(&self, request: Self::Request) -> Result<BotReplyStream, String> {
+        let stream: XaiChatCompletionResponseStream = XaiChat::new(self.0)
+            .create_stream(request)
+            .await
+            .map_err(|err| format!("Error creating stream: {}", err.to_string()))?;
+        let result = stream.map(|item| {
+            let reply = item.map(|resp| {
+                let choice = resp.choices.first().unwrap();
                 let message = choice
-                    .message
+                    .delta
                     .content
-                    .as_ref()
-                    .ok_or("Api returned empty message".to_string())?
-                    .to_string();
+                    .clone()
+                    .unwrap_or(String::default());
                 let reasoning = choice
-                    .message
+                    .delta
                     .reasoning
                     .clone();
-                let usage = response.common.usage;
-                let reply = BotReply {
+                let usage = resp.common.usage;
+                BotReply {
                     message,
                     reasoning,
                     prompt_token: usage.as_ref().map(|usage| usage.prompt_tokens),
@@ -646,313 +921,478 @@ impl<'c> ChatRequestExecutor<'c> {
                                 .unwrap_or(0)
                         }),
                     total_token: usage.as_ref().map(|usage| usage.total_tokens),
-                };
+                    truncated: choice.finish_reason == Some(FinishReason::Length),
+                    ..Default::default()
+                }
+            });
+            reply
+        });
+        Ok(Box::pin(result))
+    }
+}
 
-                Ok(reply)
-            }
-            ChatRequestExecutor::GoogleChatRequestExecutor(client, request) => {
-                let response = GoogleChat::new(client)
-                    .create(request.clone())
-                    .await
-                    .map_err(|err| format!("Error creating stream: {}", err.to_string()))?;
-                // extract data & build reply
-                let candidate = response
-                    .candidates
-                    .first()
-                    .ok_or("Api returned empty candidates".to_string())?;
-                let message = candidate
-                    .content
-                    .parts
-                    .as_ref()
-                    .map(|part_vec| {
-                        part_vec
-                            .iter()
-                            .map(|part| {
-                                match part {
-                                    GoogleChatCompletionContentPart::Text(text) => text.clone(),
-                                    GoogleChatCompletionContentPart::FileData(_) => String::default(),
-                                }
-                            })
-                            .collect::<Vec<String>>()
-                            .join("")
-                    })
-                    .unwrap_or(String::default());
-                let usage = response.usage_metadata;
+pub struct GoogleProvider<'c>(pub &'c Client<GoogleConfig>);
+
+impl ChatProvider for GoogleProvider<'_> {
+    type Request = GoogleChatCompletionRequest;
+
+    fn build_request(
+        &self,
+        messages: Vec<MessageDTO>,
+        options: GenericOptions,
+        global_settings: GlobalSettings,
+        model: String,
+    ) -> Result<Self::Request, String> {
+        let request: GoogleChatCompletionRequest;
+        // default to what is left of the context window after the prompt
+        let max_tokens = global_settings.max_tokens_for(&model, &messages);
+        // set messages
+        let req_messages = messages
+            .into_iter()
+            .map(message_to_google_request_message)
+            .collect();
+        // set options
+        let options: GoogleOptions = serde_json::from_str(&options.options)
+            .map_err(|_| format!("Failed to parse conversation options: {}", &options.options))?;
+        // build request
+        request = GoogleChatCompletionRequest {
+            contents: req_messages,
+            system_instruction: None,
+            generation_config: Some(GoogleChatCompletionRequestGenerationConfig {
+                max_output_tokens: options.max_tokens.or(Some(max_tokens)),
+                temperature: options.temperature,
+                top_p: options.top_p,
+                presence_penalty: options.presence_penalty,
+                frequency_penalty: options.frequency_penalty,
+                // thoughts are returned separately whenever a budget is set
+                thinking_config: options.thinking_budget.map(|thinking_budget| GoogleThinkingConfig {
+                    thinking_budget: Some(thinking_budget),
+                    include_thoughts: Some(thinking_budget > 0),
+                }),
+                ..Default::default()
+            }),
+        };
+        Ok(request)
+    }
+
+    async fn complete(&self, request: Self::Request) -> Result<BotReply, String> {
+        let response = GoogleChat::new(self.0)
+            .create(request)
+            .await
+            .map_err(|err| format!("Error creating stream: {}", err.to_string()))?;
+        // extract data & build reply
+        let candidate = response
+            .candidates
+            .first()
+            .ok_or("Api returned empty candidates".to_string())?;
+        let message = candidate.content.text(false);
+        let reasoning = Some(candidate.content.text(true)).filter(|text| !text.is_empty());
+        let usage = response.usage_metadata;
 
-                Ok(BotReply {
+        Ok(BotReply {
+            message,
+            reasoning,
+            prompt_token: usage.prompt_token_count,
+            completion_token: usage.candidates_token_count,
+            reasoning_token: usage.thoughts_token_count,
+            total_token: usage.total_token_count,
+            truncated: candidate.finish_reason == Some(GoogleChatCompletionFinishReason::MaxTokens),
+        })
+    }
+
+    async fn stream(&self, request: Self::Request) -> Result<BotReplyStream, String> {
+        let stream = GoogleChat::new(self.0)
+            .create_stream(request)
+            .await
+            .map_err(|err| format!("Error creating stream: {}", err.to_string()))?;
+        let result = stream.map(move |item| {
+            item.map(|resp| {
+                // thought deltas are kept apart from the answer deltas
+                let message = resp.candidates.first().map(|candidate| candidate.content.text(false)).unwrap_or(String::default());
+                let reasoning = resp.candidates.first().map(|candidate| candidate.content.text(true)).filter(|text| !text.is_empty());
+                BotReply {
                     message,
-                    reasoning: None,
-                    prompt_token: usage.prompt_token_count,
-                    completion_token: usage.candidates_token_count,
-                    reasoning_token: usage.thoughts_token_count,
-                    total_token: usage.total_token_count,
-                })
-            }
+                    reasoning,
+                    prompt_token: resp.usage_metadata.prompt_token_count,
+                    completion_token: resp.usage_metadata.candidates_token_count,
+                    reasoning_token: resp.usage_metadata.thoughts_token_count,
+                    total_token: resp.usage_metadata.total_token_count,
+                    truncated: resp.candidates.first().is_some_and(|candidate| candidate.finish_reason == Some(GoogleChatCompletionFinishReason::MaxTokens)),
+                }
+            })
+        });
+        Ok(Box::pin(result))
+    }
+}
+
+/// OpenAI compatible servers take the same request as OpenAI
+pub struct CustomProvider<'c>(pub &'c Client<CustomConfig>);
+
+impl ChatProvider for CustomProvider<'_> {
+    type Request = OpenAIChatCompletionRequest;
+
+    fn build_request(
+        &self,
+        messages: Vec<MessageDTO>,
+        options: GenericOptions,
+        global_settings: GlobalSettings,
+        model: String,
+    ) -> Result<Self::Request, String> {
+        openai_request(messages, options, global_settings, model)
+    }
+
+    async fn complete(&self, request: Self::Request) -> Result<BotReply, String> {
+        complete_openai_compatible(self.0, request).await
+    }
+
+    async fn stream(&self, request: Self::Request) -> Result<BotReplyStream, String> {
+        stream_openai_compatible(self.0, request).await
+    }
+}
+
+pub struct BedrockProvider<'c>(pub &'c BedrockClient);
+
+impl ChatProvider for BedrockProvider<'_> {
+    type Request = BedrockConverseRequest;
+
+    fn build_request(
+        &self,
+        messages: Vec<MessageDTO>,
+        options: GenericOptions,
+        global_settings: GlobalSettings,
+        model: String,
+    ) -> Result<Self::Request, String> {
+        // default to what is left of the context window after the prompt
+        let max_tokens = global_settings.max_tokens_for(&model, &messages);
+        // set messages, system messages are sent apart
+        let (system, req_messages) = messages_to_bedrock_request(messages);
+        // set options
+        let options: BedrockOptions = serde_json::from_str(&options.options)
+            .map_err(|_| format!("Failed to parse conversation options: {}", &options.options))?;
+        // build request
+        let request = BedrockConverseRequest {
+            model_id: model,
+            messages: req_messages,
+            system,
+            inference_config: BedrockInferenceConfig {
+                max_tokens: options.max_tokens.or(Some(max_tokens)),
+                temperature: options.temperature,
+                top_p: options.top_p,
+            },
+        };
+        Ok(request)
+    }
+
+    async fn complete(&self, request: Self::Request) -> Result<BotReply, String> {
+        let response = BedrockChat::new(self.0)
+            .create(request)
+            .await?;
+        let usage = response.usage.clone().unwrap_or_default();
+        Ok(BotReply {
+            message: response.text(),
+            reasoning: response.reasoning(),
+            prompt_token: usage.input_tokens,
+            completion_token: usage.output_tokens,
+            reasoning_token: None,
+            total_token: usage.total_tokens,
+            truncated: response.stop_reason.as_deref() == Some("max_tokens"),
+        })
+    }
+
+    async fn stream(&self, request: Self::Request) -> Result<BotReplyStream, String> {
+        let stream = BedrockChat::new(self.0)
+            .create_stream(request)
+            .await
+            .map_err(|err| format!("Error creating stream: {}", err))?;
+        let result = stream.map(|item| {
+            item.map(|event| {
+                // deltas, the stop reason and the usage come in separate events
+                let delta = event.delta.unwrap_or_default();
+                let usage = event.usage.unwrap_or_default();
+                BotReply {
+                    message: delta.text.unwrap_or_default(),
+                    reasoning: delta.reasoning_content.map(|reasoning| reasoning.text),
+                    prompt_token: usage.input_tokens,
+                    completion_token: usage.output_tokens,
+                    reasoning_token: None,
+                    total_token: usage.total_tokens,
+                    truncated: event.stop_reason.as_deref() == Some("max_tokens"),
+                }
+            })
+        });
+        Ok(Box::pin(result))
+    }
+}
+
+pub struct CohereProvider<'c>(pub &'c Client<CohereConfig>);
+
+impl ChatProvider for CohereProvider<'_> {
+    type Request = CohereChatRequest;
+
+    fn build_request(
+        &self,
+        messages: Vec<MessageDTO>,
+        options: GenericOptions,
+        global_settings: GlobalSettings,
+        model: String,
+    ) -> Result<Self::Request, String> {
+        // default to what is left of the context window after the prompt
+        let max_tokens = global_settings.max_tokens_for(&model, &messages);
+        // set messages, the last user message is sent apart from the history
+        let (preamble, chat_history, message) = messages_to_cohere_request(messages);
+        // set options
+        let options: CohereOptions = serde_json::from_str(&options.options)
+            .map_err(|_| format!("Failed to parse conversation options: {}", &options.options))?;
+        // build request
+        let request = CohereChatRequest {
+            model,
+            message,
+            chat_history,
+            preamble,
+            stream: options.stream,
+            max_tokens: options.max_tokens.or(Some(max_tokens)),
+            temperature: options.temperature,
+            p: options.top_p,
+            k: options.top_k,
+            frequency_penalty: options.frequency_penalty,
+            presence_penalty: options.presence_penalty,
+            connectors: options.web_search.filter(|web_search| *web_search).map(|_| {
+                vec![CohereConnector {
+                    id: COHERE_WEB_SEARCH_CONNECTOR.to_string(),
+                }]
+            }),
+        };
+        Ok(request)
+    }
+
+    async fn complete(&self, request: Self::Request) -> Result<BotReply, String> {
+        let response = CohereChat::new(self.0)
+            .create(request)
+            .await
+            .map_err(|err| {
+                log::error!("execute ChatRequest::CohereChatRequest: {:?}", err);
+                format!("Failed to get chat completion response: {}", err)
+            })?;
+        // the sources cited are listed after the answer
+        let mut message = response.text.clone();
+        if let Some(sources) = response.sources() {
+            message.push_str(&sources);
         }
+        let usage = response.usage();
+        Ok(BotReply {
+            message,
+            reasoning: None,
+            prompt_token: usage.input_tokens,
+            completion_token: usage.output_tokens,
+            reasoning_token: None,
+            total_token: sum_option(usage.input_tokens, usage.output_tokens),
+            truncated: response.finish_reason.as_deref() == Some("MAX_TOKENS"),
+        })
     }
 
-    pub async fn execute_stream(&self) -> Result<BotReplyStream, String> {
-        let log_tag = "ChatRequest::execute_stream";
-        match self {
-            ChatRequestExecutor::OpenAIChatRequestExecutor(client, request) => {
-                return self
-                    .execute_openai_compatible_stream_request(client, request.clone())
-                    .await;
-            }
-            ChatRequestExecutor::AzureChatRequestExecutor(client, request) => {
-                return self
-                    .execute_openai_compatible_stream_request(client, request.clone())
-                    .await;
-            }
-            ChatRequestExecutor::ClaudeChatRequestExecutor(client, request) => {
-                let stream: ClaudeChatCompletionResponseStream = ClaudeChat::new(client)
-                    .create_stream(request.clone())
-                    .await
-                    .map_err(|err| format!("Error creating stream: {}", err.to_string()))?;
-                let result = stream.map(|item| {
-                    item.map(|resp| {
-                        match resp {
-                            ClaudeChatCompletionStreamResponse::ContentBlockDelta(
-                                content_delta,
-                            ) => {
-                                match content_delta.delta {
-                                    ContentBlockDelta::TextDelta(text_delta) => BotReply {
-                                        message: text_delta.text.clone(),
-                                        ..Default::default()
-                                    },
-                                    ContentBlockDelta::ThinkingDelta(thinking_delta) => BotReply {
-                                        reasoning: Some(thinking_delta.thinking.clone()),
-                                        ..Default::default()
-                                    },
-                                    _ => BotReply::default(),
-                                }
-                            },
-                            ClaudeChatCompletionStreamResponse::MessageDelta(message_delta) => {
-                                // return empty string as message
-                                BotReply {
-                                    prompt_token: message_delta.usage.input_tokens,
-                                    completion_token: message_delta.usage.output_tokens,
-                                    total_token: sum_option(
-                                        message_delta.usage.input_tokens,
-                                        message_delta.usage.output_tokens,
-                                    ),
-                                    ..Default::default()
-                                }
-                            }
-                        }
-                    })
-                });
-                Ok(Box::pin(result))
-            }
-            ChatRequestExecutor::OllamaChatRequestExecutor(client, request) => {
-                let stream: OllamaChatCompletionResponseStream = OllamaChat::new(client)
-                    .create_stream(request.clone())
-                    .await
-                    .map_err(|err| format!("Error creating stream: {}", err.to_string()))?;
-                let mut is_reasoning = false;
-                let result = stream.map(move |item| {
-                    item.map(|response| {
-                        let content: String = match response.message {
-                            Some(response_message) => match response_message{
-                                OllamaMessage::Assistant(content) => {
-                                    // check for reasoning content
-                                    // return empty content for <think> and </think>
-                                    if content.content.contains("<think>") {
-                                        is_reasoning = true;
-                                        String::default()
-                                    } else if content.content.contains("</think>") {
-                                        is_reasoning = false;
-                                        String::default()
-                                    } else {
-                                        content.content
-                                    }
-                                },
-                                _ => {
-                                    warn(log_tag, "OllamaChat::create_stream returned a non-assistant message");
-                                    String::default()
-                                }
-                            },
-                            _ => {
-                                // normally the last message of the stream
-                                String::default()
-                            }
-                        };
+    async fn stream(&self, request: Self::Request) -> Result<BotReplyStream, String> {
+        let stream = CohereChat::new(self.0)
+            .create_stream(request)
+            .await
+            .map_err(|err| format!("Error creating stream: {}", err))?;
+        let result = stream.map(|item| {
+            item.map(|event| match event {
+                CohereStreamEvent::TextGeneration { text } => BotReply {
+                    message: text,
+                    ..Default::default()
+                },
+                // the end repeats the whole reply, with its sources and usage
+                CohereStreamEvent::StreamEnd {
+                    finish_reason,
+                    response,
+                } => {
+                    let usage = response.usage();
+                    BotReply {
+                        message: response.sources().unwrap_or_default(),
+                        prompt_token: usage.input_tokens,
+                        completion_token: usage.output_tokens,
+                        total_token: sum_option(usage.input_tokens, usage.output_tokens),
+                        truncated: finish_reason.as_deref() == Some("MAX_TOKENS"),
+                        ..Default::default()
+                    }
+                }
+                CohereStreamEvent::Other => BotReply::default(),
+            })
+        });
+        Ok(Box::pin(result))
+    }
+}
 
-                        BotReply {
-                            message: if is_reasoning {
-                                String::default()
-                            } else {
-                                content.clone()
-                            },
-                            reasoning: if is_reasoning {
-                                Some(content)
-                            } else {
-                                None
-                            },
-                            prompt_token: response.prompt_eval_count,
-                            completion_token: response.eval_count,
-                            reasoning_token: None,
-                            total_token: sum_option(response.prompt_eval_count, response.eval_count),
-                        }
-                    })
-                });
-                Ok(Box::pin(result))
-            }
-            ChatRequestExecutor::OpenrouterChatRequestExecutor(client, request) => {
-                let stream: OpenrouterChatCompletionResponseStream = OpenrouterChat::new(&client)
-                    .create_stream(request.clone())
-                    .await
-                    .map_err(|err| format!("Error creating stream: {}", err.to_string()))?;
-                let result = stream.map(|item| {
-                    item.map(|resp| {
-                        let first_choice =
-                            resp.choices.first()
-                            .map_or(BotReply::default(), |choice| {
-                                let usage = resp.common.usage.clone();
-                                let delta = choice.delta.clone();
-                                BotReply {
-                                    message: delta.content
-                                        .clone()
-                                        .unwrap_or(String::default()),
-                                    reasoning: delta.reasoning
-                                        .clone(),
-                                    prompt_token: usage.as_ref().map(|usage| usage.prompt_tokens),
-                                    completion_token: usage
-                                        .as_ref()
-                                        .map(|usage| usage.completion_tokens),
-                                    reasoning_token: usage
-                                        .as_ref()
-                                        .map(|usage| usage.reasoning_tokens.unwrap_or(0)),
-                                    total_token: usage.as_ref().map(|usage| usage.total_tokens),
-                                }
-                            });
-                        first_choice
+/// Together and Fireworks take the same request as OpenAI, and always send the usage
+/// with the last chunk of a stream
+pub struct AggregatorProvider<'c>(pub &'c Client<AggregatorConfig>);
+
+impl ChatProvider for AggregatorProvider<'_> {
+    type Request = OpenAIChatCompletionRequest;
+
+    fn build_request(
+        &self,
+        messages: Vec<MessageDTO>,
+        options: GenericOptions,
+        global_settings: GlobalSettings,
+        model: String,
+    ) -> Result<Self::Request, String> {
+        let mut request = openai_request(messages, options, global_settings, model)?;
+        request.common.stream_options = None;
+        Ok(request)
+    }
+
+    async fn complete(&self, request: Self::Request) -> Result<BotReply, String> {
+        complete_openai_compatible(self.0, request).await
+    }
+
+    async fn stream(&self, request: Self::Request) -> Result<BotReplyStream, String> {
+        let stream = AggregatorChat::new(self.0)
+            .create_stream(request)
+            .await
+            .map_err(|err| format!("Error creating stream: {}", err))?;
+        let result = stream.map(|item| item.map(openai_stream_reply));
+        Ok(Box::pin(result))
+    }
+}
+
+// The request of OpenAI, also taken by the OpenAI compatible providers
+fn openai_request(
+    messages: Vec<MessageDTO>,
+    options: GenericOptions,
+    global_settings: GlobalSettings,
+    model: String,
+) -> Result<OpenAIChatCompletionRequest, String> {
+    let request: OpenAIChatCompletionRequest;
+    // default to what is left of the context window after the prompt
+    let max_tokens = global_settings.max_tokens_for(&model, &messages);
+    // set messages
+    let req_messages = messages
+        .into_iter()
+        .map(message_to_openai_request_message)
+        .collect();
+    // set options
+    let options: OpenAIOptions = serde_json::from_str(&options.options)
+        .map_err(|_| format!("Failed to parse conversation options: {}", &options.options))?;
+    // build request
+    request = OpenAIChatCompletionRequest {
+        common: ChatCompletionRequestCommon {
+            model: model.to_string(),
+            frequency_penalty: options.frequency_penalty,
+            max_tokens: options.max_tokens.or(Some(max_tokens)),
+            presence_penalty: options.presence_penalty,
+            stream: options.stream,
+            stream_options: if options.stream.unwrap_or(false) {
+                // default to return usage when streaming
+                Some(ChatCompletionStreamOptions {
+                    include_usage: true
+                })
+            } else {
+                None
+            },
+            temperature: options.temperature,
+            top_p: options.top_p,
+            response_format: global_settings.json_schema_format(),
+            ..Default::default()
+        },
+        reasoning_effort: options.reasoning_effort.map(|x| x.into()),
+        messages: req_messages,
+        user: options.user,
+        ..Default::default()
+    };
+    Ok(request)
+}
+
+// OpenAI compatible providers reply in the same format
+async fn complete_openai_compatible<C: Config>(
+    client: &Client<C>,
+    request: OpenAIChatCompletionRequest,
+) -> Result<BotReply, String> {
+    let response = OpenAIChat::new(client)
+        .create(request)
+        .await
+        .map_err(|err| {
+            log::error!("execute_chat_complete_request: {:?}", err);
+            format!("Failed to get chat completion response: {}", err)
+        })?;
+    // extract data & build reply
+    let choice = response
+        .choices
+        .first()
+        .ok_or("Api returned empty choices".to_string())?;
+    let message = choice
+        .message
+        .content
+        .as_ref()
+        .ok_or("Api returned empty message".to_string())?
+        .to_string();
+    let usage = response.common.usage;
+    let reply = BotReply {
+        message,
+        reasoning: None, // OpenAI doesn't return reasoning text yet
+        prompt_token: usage.as_ref().map(|usage| usage.prompt_tokens),
+        completion_token: usage.as_ref().map(|usage| usage.completion_tokens),
+        reasoning_token: usage
+            .as_ref()
+            .map(|usage| {
+                usage
+                    .completion_tokens_details
+                    .as_ref()
+                    .map(|details| {
+                        details.reasoning_tokens.unwrap_or(0)
                     })
-                });
-                Ok(Box::pin(result))
-            }
-            ChatRequestExecutor::DeepseekChatRequestExecutor(client, request) => {
-                let stream: DeepseekChatCompletionResponseStream = DeepseekChat::new(client)
-                    .create_stream(request.clone())
-                    .await
-                    .map_err(|err| format!("Error creating stream: {}", err.to_string()))?;
-                let result = stream.map(|item| {
-                    let reply = item.map(|resp| {
-                        let choice = resp.choices.first().unwrap();
-                        let message = choice
-                            .delta
-                            .content
-                            .clone()
-                            .unwrap_or(String::default());
-                        let reasoning = choice
-                            .delta
-                            .reasoning
-                            .clone();
-                        let usage = resp.common.usage;
-                        BotReply {
-                            message,
-                            reasoning,
-                            prompt_token: usage.as_ref().map(|usage| usage.prompt_tokens),
-                            completion_token: usage.as_ref().map(|usage| usage.completion_tokens),
-                            reasoning_token: usage
-                                .as_ref()
-                                .map(|usage| {
-                                    usage
-                                        .completion_tokens_details
-                                        .as_ref()
-                                        .map(|details| {
-                                            details.reasoning_tokens.unwrap_or(0)
-                                        })
-                                        .unwrap_or(0)
-                                }),
-                            total_token: usage.as_ref().map(|usage| usage.total_tokens),
-                            ..Default::default()
-                        }
-                    });
-                    reply
-                });
-                Ok(Box::pin(result))
-            }
-            ChatRequestExecutor::XaiChatRequestExecutor(client, request) => {
-                let stream: XaiChatCompletionResponseStream = XaiChat::new(client)
-                    .create_stream(request.clone())
-                    .await
-                    .map_err(|err| format!("Error creating stream: {}", err.to_string()))?;
-                let result = stream.map(|item| {
-                    let reply = item.map(|resp| {
-                        let choice = resp.choices.first().unwrap();
-                        let message = choice
-                            .delta
-                            .content
-                            .clone()
-                            .unwrap_or(String::default());
-                        let reasoning = choice
-                            .delta
-                            .reasoning
-                            .clone();
-                        let usage = resp.common.usage;
-                        BotReply {
-                            message,
-                            reasoning,
-                            prompt_token: usage.as_ref().map(|usage| usage.prompt_tokens),
-                            completion_token: usage.as_ref().map(|usage| usage.completion_tokens),
-                            reasoning_token: usage
-                                .as_ref()
-                                .map(|usage| {
-                                    usage
-                                        .completion_tokens_details
-                                        .as_ref()
-                                        .map(|details| {
-                                            details.reasoning_tokens.unwrap_or(0)
-                                        })
-                                        .unwrap_or(0)
-                                }),
-                            total_token: usage.as_ref().map(|usage| usage.total_tokens),
-                            ..Default::default()
-                        }
-                    });
-                    reply
-                });
-                Ok(Box::pin(result))
-            }
-            ChatRequestExecutor::GoogleChatRequestExecutor(client, request) => {
-                let stream = GoogleChat::new(client)
-                    .create_stream(request.clone())
-                    .await
-                    .map_err(|err| format!("Error creating stream: {}", err.to_string()))?;
-                let result = stream.map(move |item| {
-                    item.map(|resp| {
-                        let message = resp.candidates.first().map(|candidate| {
-                            let m = candidate
-                                .content
-                                .parts
-                                .as_ref()
-                                .map(|part_vec| {
-                                    part_vec
-                                        .iter()
-                                        .map(|part| {
-                                            match part {
-                                                GoogleChatCompletionContentPart::Text(text) => text.clone(),
-                                                GoogleChatCompletionContentPart::FileData(_) => String::default(),
-                                            }
-                                        }).collect::<Vec<String>>()
-                                        .join("")
-                                })
-                                .unwrap_or(String::default());
-                            m
-                        }).unwrap_or(String::default());
-                        BotReply {
-                            message,
-                            reasoning: None,
-                            prompt_token: resp.usage_metadata.prompt_token_count,
-                            completion_token: resp.usage_metadata.candidates_token_count,
-                            reasoning_token: resp.usage_metadata.thoughts_token_count,
-                            total_token: resp.usage_metadata.total_token_count,
-                        }
+                    .unwrap_or(0)
+            }),
+        total_token: usage.as_ref().map(|usage| usage.total_tokens),
+        truncated: choice.finish_reason == Some(FinishReason::Length),
+    };
+
+    Ok(reply)
+}
+
+async fn stream_openai_compatible<C: Config>(
+    client: &Client<C>,
+    request: OpenAIChatCompletionRequest,
+) -> Result<BotReplyStream, String> {
+    let stream: OpenAIChatCompletionResponseStream = OpenAIChat::new(client)
+        .create_stream(request)
+        .await
+        .map_err(|err| format!("Error creating stream: {}", err.to_string()))?;
+    let result = stream.map(|item| item.map(openai_stream_reply));
+    Ok(Box::pin(result))
+}
+
+fn openai_stream_reply(resp: OpenAIChatCompletionStreamResponse) -> BotReply {
+    // OpenAI returns usage in the last chunk with an empty message/choice
+    let message = resp
+        .choices
+        .first()
+        .map(|choice| {
+            choice.delta.content
+                .clone()
+                .unwrap_or(String::default())
+        })
+        .unwrap_or(String::default());
+    let usage = resp.common.usage;
+    BotReply {
+        message,
+        reasoning: None, // OpenAI doesn't return reasoning text yet
+        prompt_token: usage.as_ref().map(|usage| usage.prompt_tokens),
+        completion_token: usage
+            .as_ref()
+            .map(|usage| usage.completion_tokens),
+        reasoning_token: usage
+            .as_ref()
+            .map(|usage| {
+                usage
+                    .completion_tokens_details
+                    .as_ref()
+                    .map(|details| {
+                        details.reasoning_tokens.unwrap_or(0)
                     })
-                });
-                Ok(Box::pin(result))
-            }
-        }
+                    .unwrap_or(0)
+            }),
+        total_token: usage.as_ref().map(|usage| usage.total_tokens),
+        truncated: resp.choices.first().and_then(|choice| choice.finish_reason) == Some(FinishReason::Length),
     }
 }
@@ -1,21 +1,105 @@
 use async_openai::{
-    config::{AzureConfig, Config, OpenAIConfig},
+    config::{AzureConfig, OpenAIConfig},
     Client,
 };
 use entity::entities::{
     conversations::GenericOptions,
     messages::MessageDTO,
     models::{GenericConfig, Providers},
-    settings::ProxySetting,
+    settings::{ProxySetting, TimeoutSetting},
 };
 use reqwest;
 
+use crate::{errors::CommandError, services::db::Repository};
+
+use super::context::{get_proxy_setting, get_timeout_setting, model_timeouts};
 use super::{
-    chat::{BotReply, BotReplyStream, ChatRequestExecutor, GlobalSettings}, models::{ListModelsRequestExecutor, RemoteModel}, providers::{
-        claude::config::ClaudeConfig, deepseek::config::DeepseekConfig, google::config::GoogleConfig, ollama::config::OllamaConfig, openrouter::config::DEFAULT_OPENROUTER_API_BASE, xai::config::XaiConfig
-    }, types::{RawAzureConfig, RawClaudeConfig, RawDeepseekConfig, RawGoogleConfig, RawOllamaConfig, RawOpenAIConfig, RawXaiConfig}, utils::build_http_client
+    chat::{
+        AggregatorProvider, AzureProvider, BedrockProvider, BotReply, BotReplyStream, ChatProvider,
+        ClaudeProvider, CohereProvider, CustomProvider, DeepseekProvider, GlobalSettings,
+        GoogleProvider, OllamaProvider, OpenAIProvider, OpenrouterProvider, XaiProvider,
+    },
+    limits::ModelLimits,
+    models::{ListModelsRequestExecutor, RemoteModel},
+    providers::{
+        aggregator::config::{
+            AggregatorConfig, DEFAULT_FIREWORKS_API_BASE, DEFAULT_TOGETHER_API_BASE,
+        },
+        bedrock::client::BedrockClient,
+        claude::config::ClaudeConfig,
+        cohere::config::CohereConfig,
+        custom::config::CustomConfig,
+        deepseek::config::DeepseekConfig,
+        google::config::GoogleConfig,
+        ollama::config::OllamaConfig,
+        openrouter::config::DEFAULT_OPENROUTER_API_BASE,
+        xai::config::XaiConfig,
+    },
+    tokenizer::TokenCount,
+    types::{
+        RawAggregatorConfig, RawAzureConfig, RawBedrockConfig, RawClaudeConfig, RawCohereConfig,
+        RawCustomConfig, RawDeepseekConfig, RawGoogleConfig, RawOllamaConfig, RawOpenAIConfig,
+        RawXaiConfig,
+    },
+    utils::build_http_client,
 };
 
+/// Evaluate `$body` with the `ChatProvider` of a client and the name of its model
+macro_rules! with_provider {
+    ($client:expr, |$provider:ident, $model:ident| $body:expr) => {
+        match $client {
+            LLMClient::OpenAIClient(client, $model) => {
+                let $provider = OpenAIProvider(client);
+                $body
+            }
+            LLMClient::AzureClient(client, $model) => {
+                let $provider = AzureProvider(client);
+                $body
+            }
+            LLMClient::ClaudeClient(client, $model) => {
+                let $provider = ClaudeProvider(client);
+                $body
+            }
+            LLMClient::OllamaClient(client, $model) => {
+                let $provider = OllamaProvider(client);
+                $body
+            }
+            LLMClient::OpenrouterClient(client, $model) => {
+                let $provider = OpenrouterProvider(client);
+                $body
+            }
+            LLMClient::DeepseekClient(client, $model) => {
+                let $provider = DeepseekProvider(client);
+                $body
+            }
+            LLMClient::XaiClient(client, $model) => {
+                let $provider = XaiProvider(client);
+                $body
+            }
+            LLMClient::GoogleClient(client, $model) => {
+                let $provider = GoogleProvider(client);
+                $body
+            }
+            LLMClient::CustomClient(client, $model) => {
+                let $provider = CustomProvider(client);
+                $body
+            }
+            LLMClient::BedrockClient(client, $model) => {
+                let $provider = BedrockProvider(client);
+                $body
+            }
+            LLMClient::CohereClient(client, $model) => {
+                let $provider = CohereProvider(client);
+                $body
+            }
+            LLMClient::AggregatorClient(client, $model) => {
+                let $provider = AggregatorProvider(client);
+                $body
+            }
+        }
+    };
+}
+
 /// Wrapper of async-openai's Client struct
 #[derive(Debug, Clone)]
 pub enum LLMClient {
@@ -27,12 +111,21 @@ pub enum LLMClient {
     DeepseekClient(Client<DeepseekConfig>, Option<String>),
     XaiClient(Client<XaiConfig>, Option<String>),
     GoogleClient(Client<GoogleConfig>, Option<String>),
+    CustomClient(Client<CustomConfig>, Option<String>),
+    BedrockClient(BedrockClient, Option<String>),
+    CohereClient(Client<CohereConfig>, Option<String>),
+    /// Together and Fireworks, which differ by their endpoint only
+    AggregatorClient(Client<AggregatorConfig>, Option<String>),
 }
 
 impl LLMClient {
     /// Build client from config
-    pub fn new(config: GenericConfig, proxy_setting: Option<ProxySetting>) -> Result<Self, String> {
-        let http_client: reqwest::Client = build_http_client(proxy_setting);
+    pub fn new(
+        config: GenericConfig,
+        proxy_setting: Option<ProxySetting>,
+        timeouts: TimeoutSetting,
+    ) -> Result<Self, String> {
+        let http_client: reqwest::Client = build_http_client(proxy_setting, timeouts);
         match config.provider.as_str().into() {
             Providers::Azure => {
                 let raw_config: RawAzureConfig = serde_json::from_str(&config.config)
@@ -40,7 +133,7 @@ impl LLMClient {
                 let client = Client::with_config(raw_config.into()).with_http_client(http_client);
                 Ok(LLMClient::AzureClient(client, Some(String::default()))) // Azure doesn't require model, so use a blank string here
             }
-            Providers::OpenAI | Providers::CUSTOM => {
+            Providers::OpenAI => {
                 let raw_config: RawOpenAIConfig = serde_json::from_str(&config.config)
                     .map_err(|_| format!("Failed to parse model config: {}", &config.config))?;
                 let model = raw_config.model.clone();
@@ -91,54 +184,103 @@ impl LLMClient {
                 let client = Client::with_config(raw_config.into()).with_http_client(http_client);
                 Ok(LLMClient::GoogleClient(client, model))
             }
-            _ => Err(format!(
-                "{} is not supported yet",
-                config.provider.as_str()
-            )),
+            Providers::CUSTOM => {
+                let raw_config: RawCustomConfig = serde_json::from_str(&config.config)
+                    .map_err(|_| format!("Failed to parse model config: {}", &config.config))?;
+                let model = raw_config.model.clone();
+                let client = Client::with_config(raw_config.into()).with_http_client(http_client);
+                Ok(LLMClient::CustomClient(client, model))
+            }
+            Providers::Bedrock => {
+                let raw_config: RawBedrockConfig = serde_json::from_str(&config.config)
+                    .map_err(|_| format!("Failed to parse model config: {}", &config.config))?;
+                let model = raw_config.model.clone();
+                // Bedrock's requests are signed with their body, so they don't go through async-openai
+                let client = BedrockClient::new(http_client, raw_config.try_into()?);
+                Ok(LLMClient::BedrockClient(client, model))
+            }
+            Providers::Cohere => {
+                let raw_config: RawCohereConfig = serde_json::from_str(&config.config)
+                    .map_err(|_| format!("Failed to parse model config: {}", &config.config))?;
+                let model = raw_config.model.clone();
+                let client = Client::with_config(raw_config.into()).with_http_client(http_client);
+                Ok(LLMClient::CohereClient(client, model))
+            }
+            Providers::Together | Providers::Fireworks => {
+                let raw_config: RawAggregatorConfig = serde_json::from_str(&config.config)
+                    .map_err(|_| format!("Failed to parse model config: {}", &config.config))?;
+                let model = raw_config.model.clone();
+                let default_api_base = match config.provider.as_str().into() {
+                    Providers::Fireworks => DEFAULT_FIREWORKS_API_BASE,
+                    _ => DEFAULT_TOGETHER_API_BASE,
+                };
+                let client = Client::with_config(raw_config.into_config(default_api_base))
+                    .with_http_client(http_client);
+                Ok(LLMClient::AggregatorClient(client, model))
+            }
+            _ => Err(format!("{} is not supported yet", config.provider.as_str())),
         }
     }
 
-    async fn execute_chat_request<'c, F, C>(
-        client: &'c Client<C>, // Replace ClientType with the actual type
+    async fn execute_chat_request<P: ChatProvider>(
+        provider: P,
         messages: Vec<MessageDTO>,
         options: GenericOptions,
         global_settings: GlobalSettings,
-        model: &'c Option<String>,
-        executor: F,
-    ) -> Result<BotReply, String>
-    where
-        F: FnOnce(&'c Client<C>, Vec<MessageDTO>, GenericOptions, GlobalSettings, String) -> Result<ChatRequestExecutor<'c>, String>,
-        C: Config,
-    {
+        model: &Option<String>,
+    ) -> Result<BotReply, String> {
         match model {
             Some(model_str) => {
-                let reply = executor(client, messages, options, global_settings, model_str.to_string())?
-                    .execute()
-                    .await?;
-                Ok(reply)
+                let request = provider.build_request(
+                    messages,
+                    options,
+                    global_settings,
+                    model_str.to_string(),
+                )?;
+                provider.complete(request).await
             }
             None => Err(format!("Model not set for chat")),
         }
     }
 
-    async fn execute_chat_request_stream<'c, F, C>(
-        client: &'c Client<C>, // Replace ClientType with the actual type
+    fn build_chat_request_body<P: ChatProvider>(
+        provider: P,
         messages: Vec<MessageDTO>,
         options: GenericOptions,
         global_settings: GlobalSettings,
-        model: &'c Option<String>,
-        executor: F,
-    ) -> Result<BotReplyStream, String>
-    where
-        F: FnOnce(&'c Client<C>, Vec<MessageDTO>, GenericOptions, GlobalSettings, String) -> Result<ChatRequestExecutor<'c>, String>,
-        C: Config,
-    {
+        model: &Option<String>,
+    ) -> Result<serde_json::Value, String> {
         match model {
             Some(model_str) => {
-                let stream = executor(client, messages, options, global_settings, model_str.to_string())?
-                    .execute_stream()
-                    .await?;
-                Ok(stream)
+                let request = provider.build_request(
+                    messages,
+                    options,
+                    global_settings,
+                    model_str.to_string(),
+                )?;
+                serde_json::to_value(request)
+                    .map_err(|err| format!("Failed to serialize request: {}", err))
+            }
+            None => Err(format!("Model not set for chat")),
+        }
+    }
+
+    async fn execute_chat_request_stream<P: ChatProvider>(
+        provider: P,
+        messages: Vec<MessageDTO>,
+        options: GenericOptions,
+        global_settings: GlobalSettings,
+        model: &Option<String>,
+    ) -> Result<BotReplyStream, String> {
+        match model {
+            Some(model_str) => {
+                let request = provider.build_request(
+                    messages,
+                    options,
+                    global_settings,
+                    model_str.to_string(),
+                )?;
+                provider.stream(request).await
             }
             None => Err(format!("Model not set for chat")),
         }
@@ -150,32 +292,14 @@ impl LLMClient {
         options: GenericOptions,
         global_settings: GlobalSettings,
     ) -> Result<BotReply, String> {
-        match self {
-            LLMClient::OpenAIClient(client, model) => {
-                Self::execute_chat_request(client, messages, options, global_settings, model, ChatRequestExecutor::openai).await
-            },
-            LLMClient::AzureClient(client, model) => {
-                Self::execute_chat_request(client, messages, options, global_settings, model, ChatRequestExecutor::azure).await
-            }
-            LLMClient::ClaudeClient(client, model) => {
-                Self::execute_chat_request(client, messages, options, global_settings, model, ChatRequestExecutor::claude).await
-            },
-            LLMClient::OllamaClient(client, model) => {
-                Self::execute_chat_request(client, messages, options, global_settings, model, ChatRequestExecutor::ollama).await
-            },
-            LLMClient::OpenrouterClient(client, model) => {
-                Self::execute_chat_request(client, messages, options, global_settings, model, ChatRequestExecutor::openrouter).await
-            },
-            LLMClient::DeepseekClient(client, model) => {
-                Self::execute_chat_request(client, messages, options, global_settings, model, ChatRequestExecutor::deepseek).await
-            },
-            LLMClient::XaiClient(client, model) => {
-                Self::execute_chat_request(client, messages, options, global_settings, model, ChatRequestExecutor::xai).await
-            },
-            LLMClient::GoogleClient(client, model) => {
-                Self::execute_chat_request(client, messages, options, global_settings, model, ChatRequestExecutor::google).await
-            },
-        }
+        with_provider!(self, |provider, model| Self::execute_chat_request(
+            provider,
+            messages,
+            options,
+            global_settings,
+            model
+        )
+        .await)
     }
 
     pub async fn chat_stream(
@@ -184,32 +308,49 @@ impl LLMClient {
         options: GenericOptions,
         global_settings: GlobalSettings,
     ) -> Result<BotReplyStream, String> {
-        match self {
-            LLMClient::OpenAIClient(client, model) => {
-                Self::execute_chat_request_stream(client, messages, options, global_settings, model, ChatRequestExecutor::openai).await
-            },
-            LLMClient::AzureClient(client, model) => {
-                Self::execute_chat_request_stream(client, messages, options, global_settings, model, ChatRequestExecutor::azure).await
-            }
-            LLMClient::ClaudeClient(client, model) => {
-                Self::execute_chat_request_stream(client, messages, options, global_settings, model, ChatRequestExecutor::claude).await
-            },
-            LLMClient::OllamaClient(client, model) => {
-                Self::execute_chat_request_stream(client, messages, options, global_settings, model, ChatRequestExecutor::ollama).await
-            },
-            LLMClient::OpenrouterClient(client, model) => {
-                Self::execute_chat_request_stream(client, messages, options, global_settings, model, ChatRequestExecutor::openrouter).await
-            },
-            LLMClient::DeepseekClient(client, model) => {
-                Self::execute_chat_request_stream(client, messages, options, global_settings, model, ChatRequestExecutor::deepseek).await
-            },
-            LLMClient::XaiClient(client, model) => {
-                Self::execute_chat_request_stream(client, messages, options, global_settings, model, ChatRequestExecutor::xai).await
-            },
-            LLMClient::GoogleClient(client, model) => {
-                Self::execute_chat_request_stream(client, messages, options, global_settings, model, ChatRequestExecutor::google).await
-            },
-        }
+        with_provider!(self, |provider, model| Self::execute_chat_request_stream(
+            provider,
+            messages,
+            options,
+            global_settings,
+            model
+        )
+        .await)
+    }
+
+    /// The body of the chat request that would be sent, without sending it
+    pub fn preview_chat(
+        &self,
+        messages: Vec<MessageDTO>,
+        options: GenericOptions,
+        global_settings: GlobalSettings,
+    ) -> Result<serde_json::Value, String> {
+        with_provider!(self, |provider, model| Self::build_chat_request_body(
+            provider,
+            messages,
+            options,
+            global_settings,
+            model
+        ))
+    }
+
+    /// Tell the kind of an error of a request to the provider
+    pub fn parse_error(&self, error: String) -> CommandError {
+        with_provider!(self, |provider, _model| provider.parse_error(error))
+    }
+
+    /// Count the tokens of a prompt to the model of the client
+    pub fn count_tokens(&self, messages: &[MessageDTO], limits: ModelLimits) -> TokenCount {
+        with_provider!(self, |provider, model| provider.count_tokens(
+            messages,
+            model.as_deref().unwrap_or_default(),
+            limits
+        ))
+    }
+
+    /// Whether the provider continues a partial assistant message sent as the last message
+    pub fn supports_prefill(&self) -> bool {
+        matches!(self, LLMClient::ClaudeClient(..))
     }
 
     pub async fn models(&self) -> Result<Vec<RemoteModel>, String> {
@@ -231,11 +372,15 @@ impl LLMClient {
                 Ok(result)
             }
             LLMClient::OpenrouterClient(client, _) => {
-                let result = ListModelsRequestExecutor::openrouter(client).execute().await?;
+                let result = ListModelsRequestExecutor::openrouter(client)
+                    .execute()
+                    .await?;
                 Ok(result)
             }
             LLMClient::DeepseekClient(client, _) => {
-                let result = ListModelsRequestExecutor::deepseek(client).execute().await?;
+                let result = ListModelsRequestExecutor::deepseek(client)
+                    .execute()
+                    .await?;
                 Ok(result)
             }
             LLMClient::XaiClient(client, _) => {
@@ -246,6 +391,46 @@ impl LLMClient {
                 let result = ListModelsRequestExecutor::google(client).execute().await?;
                 Ok(result)
             }
+            LLMClient::CustomClient(client, _) => {
+                let result = ListModelsRequestExecutor::custom(client).execute().await?;
+                Ok(result)
+            }
+            LLMClient::BedrockClient(client, _) => {
+                let result = ListModelsRequestExecutor::bedrock(client).execute().await?;
+                Ok(result)
+            }
+            LLMClient::CohereClient(client, _) => {
+                let result = ListModelsRequestExecutor::cohere(client).execute().await?;
+                Ok(result)
+            }
+            LLMClient::AggregatorClient(client, _) => {
+                let result = ListModelsRequestExecutor::aggregator(client)
+                    .execute()
+                    .await?;
+                Ok(result)
+            }
         }
     }
 }
+
+/// Build the client of an OpenAI model and return it with the model name, for the APIs
+/// only OpenAI offers
+pub async fn openai_client(
+    repo: &Repository,
+    model_id: i32,
+) -> Result<(Client<OpenAIConfig>, Option<String>), String> {
+    let model = repo.get_model(model_id).await?;
+    let timeouts = model_timeouts(&model, get_timeout_setting(repo).await);
+    let config = GenericConfig {
+        provider: model.provider,
+        config: model.config,
+    };
+    let proxy_setting = get_proxy_setting(repo, &config.provider).await;
+    match LLMClient::new(config, proxy_setting, timeouts)? {
+        LLMClient::OpenAIClient(client, model_name) => Ok((client, model_name)),
+        _ => Err(format!(
+            "Model with id = {} isn't an OpenAI model, which this feature requires",
+            model_id
+        )),
+    }
+}
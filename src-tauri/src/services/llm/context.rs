@@ -0,0 +1,599 @@
+use entity::entities::{
+    contents::{ContentDTO, ContentType},
+    conversations::{
+        ConversationDetailsDTO, GenericOptions, DEFAULT_CONTEXT_LENGTH, DEFAULT_MAX_CONTINUATIONS,
+        DEFAULT_MAX_TOKENS,
+    },
+    messages::{MessageDTO, Roles},
+    models::{GenericConfig, Model},
+    response_schemas::Model as ResponseSchema,
+    settings::{
+        ProxySetting, TimeoutSetting, SETTING_MODELS_CONTEXT_LENGTH, SETTING_MODELS_FALLBACK,
+        SETTING_MODELS_MAX_ATTEMPTS, SETTING_MODELS_MAX_CONTINUATIONS, SETTING_MODELS_MAX_TOKENS,
+        SETTING_NETWORK_PROXY, SETTING_NETWORK_PROXY_PREFIX, SETTING_NETWORK_TIMEOUTS,
+        SETTING_PRIVACY_PATTERNS,
+    },
+};
+
+use crate::{
+    services::{
+        db::{default_options, Repository},
+        privacy::PrivacyFilter,
+    },
+    utils::with_stream,
+};
+
+use super::{
+    chat::{BotReply, GlobalSettings},
+    client::LLMClient,
+    fallback::{self, FallbackModel},
+    limits::ModelLimits,
+    memory, options, pricing,
+    retry::{self, DEFAULT_MAX_ATTEMPTS},
+    schema,
+    truncation::{self, TruncationStrategy},
+    utils::build_http_client,
+};
+
+/// Everything needed to send a conversation to its model
+pub struct ChatContext {
+    pub options: GenericOptions,
+    pub config: GenericConfig,
+    pub proxy_setting: Option<ProxySetting>,
+    /// Timeouts of the model, over the global setting
+    pub timeouts: TimeoutSetting,
+    pub max_token_setting: u32,
+    /// How many times a reply cut off by the max tokens limit is continued
+    pub max_continuations: u32,
+    /// How many times a request failing with a transient error is sent
+    pub max_attempts: u32,
+    pub messages: Vec<MessageDTO>,
+    /// Set when the conversation redacts personal data, to restore it in the reply
+    pub privacy_filter: Option<PrivacyFilter>,
+    /// Set when the replies of the conversation must match a JSON Schema
+    pub response_schema: Option<ResponseSchema>,
+    pub model_limits: ModelLimits,
+    /// Language the replies must be written in
+    pub language: Option<String>,
+    /// Models replying in turn when the model of the conversation is unavailable
+    pub fallbacks: Vec<FallbackModel>,
+    /// Set when a reply is generated again, in place of an existing one or after a failed one
+    pub regenerating: bool,
+}
+
+impl ChatContext {
+    /// Load options, config, settings and the message history of a conversation
+    pub async fn load(
+        repo: &Repository,
+        conversation_id: i32,
+        before_message_id: Option<i32>,
+    ) -> Result<Self, String> {
+        let model = repo.get_conversation_model(conversation_id).await?;
+        let options = options::resolve_options(
+            repo,
+            &model,
+            repo.get_conversation_options(conversation_id).await?,
+        )
+        .await;
+        let config = repo.get_conversation_config(conversation_id).await?;
+        // Token window of the model, unlike the context length in messages below
+        let (window_tokens, max_output_tokens) =
+            repo.get_conversation_model_limits(conversation_id).await?;
+        let model_limits = ModelLimits {
+            context_length: window_tokens,
+            max_output_tokens,
+        };
+        let proxy_setting = get_proxy_setting(repo, &config.provider).await;
+        let timeouts = model_timeouts(&model, get_timeout_setting(repo).await);
+        let max_token_setting = get_max_tokens_setting(repo).await;
+        let max_continuations = get_max_continuations_setting(repo).await;
+        let max_attempts = get_max_attempts_setting(repo).await;
+        let ctx_length_setting: u16 = repo
+            .get_setting(SETTING_MODELS_CONTEXT_LENGTH)
+            .await
+            .map(|setting| match setting.value.parse::<u16>() {
+                Ok(value) => value,
+                Err(_) => DEFAULT_CONTEXT_LENGTH,
+            })
+            .unwrap_or(DEFAULT_CONTEXT_LENGTH);
+        // Try to retrieve the context length from the conversation's options.
+        // If unsuccessful, fall back to the default context length setting.
+        let context_length = serde_json::from_str(&options.options)
+            .map(|options_json: serde_json::Value| {
+                if let Some(ctx_length) = options_json["contextLength"].as_u64() {
+                    u16::try_from(ctx_length).unwrap_or(ctx_length_setting)
+                } else {
+                    ctx_length_setting
+                }
+            })
+            .unwrap_or(ctx_length_setting);
+        // Retrieve system message
+        let sys_message = repo.get_system_message(conversation_id).await?;
+        // Retrieve message list as context
+        let mut messages =
+            get_history(repo, conversation_id, context_length, before_message_id).await?;
+        let history_length = messages.len();
+        if !trim_to_last_user_turn(&mut messages) {
+            log::warn!(
+                "No user message to reply to in conversation with id = {}",
+                conversation_id
+            );
+        }
+        let regenerating = before_message_id.is_some() || messages.len() < history_length;
+        // Older messages are sent as their summary once the conversation has one
+        if let Some(summary) = repo.get_history_summary(conversation_id).await? {
+            memory::apply_summary(&mut messages, &summary);
+        }
+        if let Some(sys_m) = sys_message {
+            messages.insert(0, sys_m);
+        }
+        let conversation = repo.get_conversation_details(conversation_id).await?;
+        let language = conversation.language.clone();
+        if let Some(language) = &language {
+            messages.insert(0, language_instruction(language));
+        }
+        let response_schema = repo
+            .get_conversation_response_schema(conversation_id)
+            .await?;
+        // Providers with structured outputs get the schema in the request instead
+        if let Some(schema) = response_schema
+            .as_ref()
+            .filter(|_| !schema::has_structured_outputs(&config.provider))
+        {
+            let position = messages
+                .iter()
+                .take_while(|message| Roles::from(message.role) == Roles::System)
+                .count();
+            messages.insert(position, schema::schema_instruction(schema));
+        }
+        let catalog_limits = match pricing::model_name(&config.config) {
+            Some(name) => model_limits.or_catalog(&name),
+            None => model_limits,
+        };
+        truncation::truncate(
+            &mut messages,
+            TruncationStrategy::from_options(&options.options),
+            truncation::prompt_budget(catalog_limits, max_token_setting),
+            language.as_deref(),
+        );
+        let mut privacy_filter = get_privacy_filter(repo, &options).await;
+        if let Some(filter) = privacy_filter.as_mut() {
+            filter.redact_messages(&mut messages);
+        }
+        let fallbacks = get_fallback_models(repo, &conversation, &options).await;
+        Ok(ChatContext {
+            options,
+            config,
+            proxy_setting,
+            timeouts,
+            max_token_setting,
+            max_continuations,
+            max_attempts,
+            messages,
+            privacy_filter,
+            response_schema,
+            model_limits,
+            language,
+            fallbacks,
+            regenerating,
+        })
+    }
+
+    /// Build a context for a one-off request to a model, outside of any conversation
+    pub async fn one_off(
+        repo: &Repository,
+        model: Model,
+        messages: Vec<MessageDTO>,
+    ) -> Result<Self, String> {
+        let options = GenericOptions {
+            provider: model.provider.clone(),
+            options: default_options(&model.provider),
+        };
+        let model_limits = ModelLimits::of(&model);
+        let proxy_setting = get_proxy_setting(repo, &model.provider).await;
+        let timeouts = model_timeouts(&model, get_timeout_setting(repo).await);
+        Ok(ChatContext {
+            options: options::resolve_options(repo, &model, options).await,
+            config: GenericConfig {
+                provider: model.provider,
+                config: model.config,
+            },
+            proxy_setting,
+            timeouts,
+            max_token_setting: get_max_tokens_setting(repo).await,
+            max_continuations: 0,
+            max_attempts: get_max_attempts_setting(repo).await,
+            messages,
+            privacy_filter: None,
+            response_schema: None,
+            model_limits,
+            language: None,
+            fallbacks: vec![],
+            regenerating: false,
+        })
+    }
+
+    /// Build a context for an OpenAI model with default settings, for tests
+    #[cfg(test)]
+    pub fn for_test(model: &str, options: &str, messages: Vec<MessageDTO>) -> Self {
+        ChatContext {
+            options: GenericOptions {
+                provider: "OpenAI".to_string(),
+                options: options.to_string(),
+            },
+            config: GenericConfig {
+                provider: "OpenAI".to_string(),
+                config: format!(r#"{{"model":"{}"}}"#, model),
+            },
+            proxy_setting: None,
+            timeouts: TimeoutSetting::default(),
+            max_token_setting: 1_000,
+            max_continuations: 0,
+            max_attempts: 1,
+            messages,
+            privacy_filter: None,
+            response_schema: None,
+            model_limits: ModelLimits::default(),
+            language: None,
+            fallbacks: vec![],
+            regenerating: false,
+        }
+    }
+
+    pub fn client(&self) -> Result<LLMClient, String> {
+        LLMClient::new(
+            self.config.clone(),
+            self.proxy_setting.clone(),
+            self.timeouts,
+        )
+    }
+
+    pub fn global_settings(&self) -> GlobalSettings {
+        GlobalSettings {
+            max_tokens: self.max_token_setting,
+            model_limits: self.model_limits,
+            language: self.language.clone(),
+            response_schema: self.response_schema.clone(),
+        }
+    }
+
+    /// The body of the request this context sends, without sending it. Replies checked
+    /// against a response schema aren't streamed, as in `call_bot`.
+    pub fn preview(&self) -> Result<serde_json::Value, String> {
+        let options = match &self.response_schema {
+            Some(_) => with_stream(self.options.clone(), false),
+            None => self.options.clone(),
+        };
+        self.client()?
+            .preview_chat(self.messages.clone(), options, self.global_settings())
+    }
+
+    /// Send the context to the model and wait for the full reply
+    pub async fn complete(self) -> Result<BotReply, String> {
+        let client = self.client()?;
+        let global_settings = self.global_settings();
+        let options = with_stream(self.options, false);
+        let schema = self.response_schema.as_ref();
+        let max_continuations = self.max_continuations;
+        let mut reply = retry::with_retry(
+            self.max_attempts,
+            |_| {},
+            || {
+                let client = &client;
+                let messages = self.messages.clone();
+                let options = options.clone();
+                let global_settings = global_settings.clone();
+                async move {
+                    match schema {
+                        Some(schema) => {
+                            schema::chat_with_schema(
+                                client,
+                                messages,
+                                options,
+                                global_settings,
+                                max_continuations,
+                                schema,
+                                |_| {},
+                            )
+                            .await
+                        }
+                        None => {
+                            chat_with_continuations(
+                                client,
+                                messages,
+                                options,
+                                global_settings,
+                                max_continuations,
+                                |_| {},
+                            )
+                            .await
+                        }
+                    }
+                }
+            },
+        )
+        .await?;
+        if let Some(filter) = &self.privacy_filter {
+            reply.message = filter.restore(&reply.message);
+        }
+        Ok(reply)
+    }
+}
+
+/// The system message asking for replies in the language of the conversation,
+/// so they don't drift into English
+pub fn language_instruction(language: &str) -> MessageDTO {
+    text_message(
+        Roles::System,
+        format!(
+            "Always reply in {}, whatever the language of the messages, unless asked to translate.",
+            language
+        ),
+    )
+}
+
+/// A message with a single text content, for requests built by the app itself
+pub fn text_message(role: Roles, text: String) -> MessageDTO {
+    MessageDTO {
+        role: role.into(),
+        content: vec![ContentDTO {
+            r#type: ContentType::Text,
+            mimetype: None,
+            data: text,
+        }],
+        ..Default::default()
+    }
+}
+
+/**
+ * The messages of a conversation sent as context, oldest first: the whole conversation
+ * when the context length is 0, otherwise its last N - 1 turns plus one message to get
+ * the last user message. System messages are left out, the current one being added by
+ * the caller.
+ */
+async fn get_history(
+    repo: &Repository,
+    conversation_id: i32,
+    context_length: u16,
+    before_message_id: Option<i32>,
+) -> Result<Vec<MessageDTO>, String> {
+    let mut messages = if context_length == 0 {
+        repo.list_messages(conversation_id).await?
+    } else {
+        let n = (context_length - 1).saturating_mul(2).saturating_add(1);
+        repo.get_last_messages(conversation_id, n, before_message_id)
+            .await?
+    };
+    messages.retain(|message| {
+        Roles::from(message.role) != Roles::System
+            && before_message_id.map_or(true, |id| message.id.map_or(true, |mid| mid < id))
+    });
+    Ok(messages)
+}
+
+/// Leave out the messages following the most recent user message, so the reply answers it
+/// even when bot messages come after it, as when regenerating a reply that failed.
+/// Returns false when there is no user message to reply to.
+pub fn trim_to_last_user_turn(messages: &mut Vec<MessageDTO>) -> bool {
+    match messages
+        .iter()
+        .rposition(|message| Roles::from(message.role) == Roles::User)
+    {
+        Some(index) => {
+            messages.truncate(index + 1);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Prompt sent after a reply was cut off by the max tokens limit
+const CONTINUE_PROMPT: &str =
+    "Your previous reply was cut off. Continue exactly where it stopped, \
+without repeating anything and without any introduction.";
+
+/// The messages requesting the rest of a truncated reply
+pub fn continuation_messages(messages: &[MessageDTO], partial_reply: &str) -> Vec<MessageDTO> {
+    let mut result = messages.to_vec();
+    result.push(text_message(Roles::Bot, partial_reply.to_string()));
+    result.push(text_message(Roles::User, CONTINUE_PROMPT.to_string()));
+    result
+}
+
+/// Send a one-off request and request the rest of the reply as long as it is cut off by the
+/// max tokens limit, at most `max_continuations` times. The parts are stitched into one reply.
+/// `on_continue` is called with the number of each continuation before it is requested.
+pub async fn chat_with_continuations(
+    client: &LLMClient,
+    messages: Vec<MessageDTO>,
+    options: GenericOptions,
+    global_settings: GlobalSettings,
+    max_continuations: u32,
+    on_continue: impl Fn(u32),
+) -> Result<BotReply, String> {
+    let mut reply = client
+        .chat(messages.clone(), options.clone(), global_settings.clone())
+        .await?;
+    let mut continuations = 0;
+    while reply.truncated && continuations < max_continuations {
+        continuations += 1;
+        on_continue(continuations);
+        let part = client
+            .chat(
+                continuation_messages(&messages, &reply.message),
+                options.clone(),
+                global_settings.clone(),
+            )
+            .await?;
+        reply.append(part);
+    }
+    Ok(reply)
+}
+
+/// Key of the setting holding the proxy of a provider
+pub fn proxy_setting_key(provider: &str) -> String {
+    format!("{}{}", SETTING_NETWORK_PROXY_PREFIX, provider)
+}
+
+/// The proxy of a provider: its own setting when it has one, even turned off, or else
+/// the global one
+pub async fn get_proxy_setting(repo: &Repository, provider: &str) -> Option<ProxySetting> {
+    match read_proxy_setting(repo, &proxy_setting_key(provider)).await {
+        Some(setting) => Some(setting),
+        None => get_global_proxy_setting(repo).await,
+    }
+}
+
+/// The proxy of the providers without their own
+pub async fn get_global_proxy_setting(repo: &Repository) -> Option<ProxySetting> {
+    read_proxy_setting(repo, SETTING_NETWORK_PROXY).await
+}
+
+async fn read_proxy_setting(repo: &Repository, key: &str) -> Option<ProxySetting> {
+    repo.get_setting(key)
+        .await
+        .and_then(|setting| serde_json::from_str::<ProxySetting>(&setting.value).ok())
+}
+
+pub async fn get_timeout_setting(repo: &Repository) -> TimeoutSetting {
+    repo.get_setting(SETTING_NETWORK_TIMEOUTS)
+        .await
+        .and_then(|setting| serde_json::from_str(&setting.value).ok())
+        .unwrap_or_default()
+}
+
+/// A client for requests to other services than models, like GitHub, going through the
+/// global proxy with the timeouts of the settings
+pub async fn get_global_http_client(repo: &Repository) -> reqwest::Client {
+    build_http_client(
+        get_global_proxy_setting(repo).await,
+        get_timeout_setting(repo).await,
+    )
+}
+
+/// The timeouts of requests to a model: its own ones, or else the ones of the settings
+pub fn model_timeouts(model: &Model, defaults: TimeoutSetting) -> TimeoutSetting {
+    TimeoutSetting {
+        connect: model.connect_timeout.or(defaults.connect),
+        read: model.read_timeout.or(defaults.read),
+    }
+}
+
+/// The privacy filter of a conversation, set when it redacts personal data
+pub async fn get_conversation_privacy_filter(
+    repo: &Repository,
+    conversation_id: i32,
+) -> Result<Option<PrivacyFilter>, String> {
+    let options = options::resolve_options(
+        repo,
+        &repo.get_conversation_model(conversation_id).await?,
+        repo.get_conversation_options(conversation_id).await?,
+    )
+    .await;
+    Ok(get_privacy_filter(repo, &options).await)
+}
+
+// Redaction is turned on per conversation with the redactPii option. Patterns
+// of the redactPatterns option are used along with the ones of the settings.
+async fn get_privacy_filter(repo: &Repository, options: &GenericOptions) -> Option<PrivacyFilter> {
+    let options_json: serde_json::Value = serde_json::from_str(&options.options).ok()?;
+    if !options_json["redactPii"].as_bool().unwrap_or(false) {
+        return None;
+    }
+    let mut patterns: Vec<String> = repo
+        .get_setting(SETTING_PRIVACY_PATTERNS)
+        .await
+        .and_then(|setting| serde_json::from_str(&setting.value).ok())
+        .unwrap_or_default();
+    if let Some(extra) = options_json["redactPatterns"].as_array() {
+        patterns.extend(
+            extra
+                .iter()
+                .filter_map(|pattern| pattern.as_str().map(String::from)),
+        );
+    }
+    Some(PrivacyFilter::new(&patterns))
+}
+
+pub async fn get_max_tokens_setting(repo: &Repository) -> u32 {
+    repo.get_setting(SETTING_MODELS_MAX_TOKENS)
+        .await
+        .map(|setting| match setting.value.parse::<u32>() {
+            Ok(value) => value,
+            Err(_) => DEFAULT_MAX_TOKENS,
+        })
+        .unwrap_or(DEFAULT_MAX_TOKENS)
+}
+
+/**
+ * The models replying when the model of a conversation is unavailable: the ones of the
+ * conversation, or else the ones of the settings. Models of the same provider are sent
+ * the options of the conversation, the others the default options of their provider.
+ */
+async fn get_fallback_models(
+    repo: &Repository,
+    conversation: &ConversationDetailsDTO,
+    options: &GenericOptions,
+) -> Vec<FallbackModel> {
+    let model_ids = match &conversation.fallback_model_ids {
+        Some(model_ids) => fallback::parse_model_ids(model_ids),
+        None => repo
+            .get_setting(SETTING_MODELS_FALLBACK)
+            .await
+            .map(|setting| fallback::parse_model_ids(&setting.value))
+            .unwrap_or_default(),
+    };
+    let timeout_setting = get_timeout_setting(repo).await;
+    let mut result: Vec<FallbackModel> = vec![];
+    for model_id in model_ids {
+        if Some(model_id) == conversation.model_id
+            || result.iter().any(|fallback| fallback.model_id == model_id)
+        {
+            continue;
+        }
+        let model = match repo.get_model(model_id).await {
+            Ok(model) => model,
+            Err(err) => {
+                log::warn!("Skipping fallback model: {}", err);
+                continue;
+            }
+        };
+        let model_options = if model.provider == options.provider {
+            options.clone()
+        } else {
+            let defaults = GenericOptions {
+                provider: model.provider.clone(),
+                options: default_options(&model.provider),
+            };
+            options::resolve_options(repo, &model, defaults).await
+        };
+        result.push(FallbackModel {
+            model_id,
+            model_limits: ModelLimits::of(&model),
+            timeouts: model_timeouts(&model, timeout_setting),
+            proxy_setting: get_proxy_setting(repo, &model.provider).await,
+            alias: model.alias,
+            config: GenericConfig {
+                provider: model.provider,
+                config: model.config,
+            },
+            options: model_options,
+        });
+    }
+    result
+}
+
+pub async fn get_max_continuations_setting(repo: &Repository) -> u32 {
+    repo.get_setting(SETTING_MODELS_MAX_CONTINUATIONS)
+        .await
+        .and_then(|setting| setting.value.parse::<u32>().ok())
+        .unwrap_or(DEFAULT_MAX_CONTINUATIONS)
+}
+
+pub async fn get_max_attempts_setting(repo: &Repository) -> u32 {
+    repo.get_setting(SETTING_MODELS_MAX_ATTEMPTS)
+        .await
+        .and_then(|setting| setting.value.parse::<u32>().ok())
+        .filter(|value| *value > 0)
+        .unwrap_or(DEFAULT_MAX_ATTEMPTS)
+}
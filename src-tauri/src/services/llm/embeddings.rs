@@ -0,0 +1,130 @@
+use async_openai::{
+    config::Config,
+    types::{CreateEmbeddingRequest, CreateEmbeddingRequestArgs, CreateEmbeddingResponse},
+    Client,
+};
+use serde::Serialize;
+
+use crate::services::db::Repository;
+
+use super::{client::LLMClient, context::ChatContext, tasks::pick_model};
+
+const DEFAULT_EMBEDDING_MODEL: &str = "text-embedding-3-small";
+const MAX_TEXTS: usize = 32;
+
+/// Cosine similarities of all pairs of texts. `similarities[i][j]` compares text i with text j.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddingComparison {
+    pub model: String,
+    pub dimensions: usize,
+    pub similarities: Vec<Vec<f32>>,
+}
+
+/**
+ * Embed the texts with an embedding model and compare them with each other.
+ * The credentials of the model with the given id are used, or those of the default model.
+ */
+pub async fn compare_embeddings(
+    repo: &Repository,
+    texts: Vec<String>,
+    model: Option<String>,
+    model_id: Option<i32>,
+) -> Result<EmbeddingComparison, String> {
+    if texts.len() < 2 {
+        return Err("At least two texts are needed to compare".to_string());
+    }
+    if texts.len() > MAX_TEXTS {
+        return Err(format!(
+            "At most {} texts can be compared at once",
+            MAX_TEXTS
+        ));
+    }
+    let model = model
+        .filter(|model| !model.trim().is_empty())
+        .unwrap_or(DEFAULT_EMBEDDING_MODEL.to_string());
+    let client = ChatContext::one_off(repo, pick_model(repo, model_id).await?, vec![])
+        .await?
+        .client()?;
+    let request = CreateEmbeddingRequestArgs::default()
+        .model(&model)
+        .input(texts)
+        .build()
+        .map_err(|err| err.to_string())?;
+    let response = match client {
+        LLMClient::OpenAIClient(client, _) => create_embeddings(&client, request).await?,
+        LLMClient::CustomClient(client, _) => create_embeddings(&client, request).await?,
+        _ => {
+            return Err(
+                "Embeddings are only supported by OpenAI and OpenAI compatible models".to_string(),
+            )
+        }
+    };
+    let mut data = response.data;
+    // Embeddings are returned with the index of their text, don't rely on their order
+    data.sort_by_key(|embedding| embedding.index);
+    let vectors: Vec<Vec<f32>> = data
+        .into_iter()
+        .map(|embedding| embedding.embedding)
+        .collect();
+    Ok(EmbeddingComparison {
+        model,
+        dimensions: vectors.first().map(|vector| vector.len()).unwrap_or(0),
+        similarities: similarity_matrix(&vectors),
+    })
+}
+
+async fn create_embeddings<C: Config>(
+    client: &Client<C>,
+    request: CreateEmbeddingRequest,
+) -> Result<CreateEmbeddingResponse, String> {
+    client
+        .embeddings()
+        .create(request)
+        .await
+        .map_err(|err| format!("Embedding request failed: {}", err))
+}
+
+fn similarity_matrix(vectors: &[Vec<f32>]) -> Vec<Vec<f32>> {
+    vectors
+        .iter()
+        .map(|a| vectors.iter().map(|b| cosine_similarity(a, b)).collect())
+        .collect()
+}
+
+// Vectors of different sizes or without length aren't similar at all
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a * norm_b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert!((cosine_similarity(&[1.0, 2.0], &[2.0, 4.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]).abs() < 1e-6);
+        assert!((cosine_similarity(&[1.0, 0.0], &[-1.0, 0.0]) + 1.0).abs() < 1e-6);
+        assert_eq!(0.0, cosine_similarity(&[0.0, 0.0], &[1.0, 1.0]));
+        assert_eq!(0.0, cosine_similarity(&[1.0], &[1.0, 1.0]));
+    }
+
+    #[test]
+    fn test_similarity_matrix() {
+        let matrix = similarity_matrix(&[vec![1.0, 0.0], vec![0.0, 1.0], vec![1.0, 1.0]]);
+        assert_eq!(3, matrix.len());
+        assert!((matrix[0][0] - 1.0).abs() < 1e-6);
+        assert!(matrix[0][1].abs() < 1e-6);
+        assert!((matrix[0][2] - matrix[2][0]).abs() < 1e-6);
+    }
+}
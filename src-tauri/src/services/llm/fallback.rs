@@ -0,0 +1,193 @@
+use std::future::Future;
+
+use entity::entities::{
+    conversations::GenericOptions,
+    messages::MessageDTO,
+    models::GenericConfig,
+    settings::{ProxySetting, TimeoutSetting},
+};
+use serde::Serialize;
+use tokio_stream::StreamExt;
+
+use super::{
+    chat::{BotReplyStream, GlobalSettings},
+    client::LLMClient,
+    limits::ModelLimits,
+    retry,
+};
+
+// Parts of the errors of providers down or too busy to reply, lowercased. Status codes
+// show up with their reason, except the one Claude returns when overloaded.
+const OUTAGE_MARKERS: [&str; 7] = [
+    "overloaded",
+    "unavailable",
+    "bad gateway",
+    "gateway timeout",
+    "internal server error",
+    "error sending request",
+    "connection refused",
+];
+
+const OVERLOADED_STATUS_CODE: &str = "529";
+
+/// A model replying in place of the model of a conversation when it's unavailable
+#[derive(Clone, Debug)]
+pub struct FallbackModel {
+    pub model_id: i32,
+    pub alias: String,
+    pub config: GenericConfig,
+    pub options: GenericOptions,
+    pub model_limits: ModelLimits,
+    pub proxy_setting: Option<ProxySetting>,
+    pub timeouts: TimeoutSetting,
+}
+
+/// Details of a reply written by a fallback model, stored in the metadata of the reply
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelFallback {
+    /// The model which the request is sent to next
+    pub model_id: i32,
+    pub alias: String,
+    /// Error of the model tried before
+    pub error: String,
+}
+
+/// The client a request is sent with, along with the options and settings for its model
+#[derive(Clone)]
+pub struct ChatTarget {
+    pub client: LLMClient,
+    pub options: GenericOptions,
+    pub global_settings: GlobalSettings,
+}
+
+/// Whether an error means the provider is down or overloaded, rather than the request
+/// being invalid, so that another model may reply to it
+pub fn is_outage(message: &str) -> bool {
+    let message = message.to_lowercase();
+    OUTAGE_MARKERS.iter().any(|marker| message.contains(marker))
+        || message
+            .split(|c: char| !c.is_ascii_digit())
+            .any(|number| number == OVERLOADED_STATUS_CODE)
+}
+
+/// Ids of models stored as a JSON array, empty when unreadable
+pub fn parse_model_ids(value: &str) -> Vec<i32> {
+    serde_json::from_str(value).unwrap_or_default()
+}
+
+/**
+ * Send a request to the target, then to the fallback models in order for as long as
+ * the models tried are unavailable. `on_fallback` is called before each fallback is
+ * tried, and the error of the last model tried is returned when none replied.
+ */
+pub async fn with_fallback<T, F, Fut>(
+    target: ChatTarget,
+    fallbacks: &[FallbackModel],
+    on_fallback: impl Fn(&ModelFallback),
+    mut request: F,
+) -> Result<T, String>
+where
+    F: FnMut(ChatTarget) -> Fut,
+    Fut: Future<Output = Result<T, String>>,
+{
+    let mut error = match request(target.clone()).await {
+        Err(message) if is_outage(&message) => message,
+        result => return result,
+    };
+    for fallback in fallbacks {
+        let client = match LLMClient::new(
+            fallback.config.clone(),
+            fallback.proxy_setting.clone(),
+            fallback.timeouts,
+        ) {
+            Ok(client) => client,
+            Err(message) => {
+                log::warn!("Skipping fallback model {}: {}", fallback.alias, message);
+                continue;
+            }
+        };
+        log::warn!(
+            "Model unavailable, falling back to {}: {}",
+            fallback.alias,
+            error
+        );
+        on_fallback(&ModelFallback {
+            model_id: fallback.model_id,
+            alias: fallback.alias.clone(),
+            error,
+        });
+        let fallback_target = ChatTarget {
+            client,
+            options: fallback.options.clone(),
+            global_settings: GlobalSettings {
+                model_limits: fallback.model_limits,
+                ..target.global_settings.clone()
+            },
+        };
+        error = match request(fallback_target).await {
+            Err(message) if is_outage(&message) => message,
+            result => return result,
+        };
+    }
+    Err(error)
+}
+
+/**
+ * Start streaming a reply. Outages and other transient errors show up either when
+ * sending the request or as the first item of the stream, so the first item is read
+ * ahead to tell them apart, and put back in front of the stream otherwise.
+ */
+pub async fn start_stream(
+    target: ChatTarget,
+    messages: Vec<MessageDTO>,
+) -> Result<(ChatTarget, BotReplyStream), String> {
+    let mut stream = target
+        .client
+        .chat_stream(
+            messages,
+            target.options.clone(),
+            target.global_settings.clone(),
+        )
+        .await?;
+    match stream.next().await {
+        Some(Err(err))
+            if is_outage(&err.to_string()) || retry::retryable(&err.to_string()).is_some() =>
+        {
+            Err(err.to_string())
+        }
+        Some(first) => {
+            let stream: BotReplyStream = Box::pin(tokio_stream::once(first).chain(stream));
+            Ok((target, stream))
+        }
+        None => Ok((target, stream)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_outage() {
+        assert!(is_outage("Invalid status code: 503 Service Unavailable"));
+        assert!(is_outage(
+            r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#
+        ));
+        assert!(is_outage(
+            "Bedrock returned 500 Internal Server Error: oops"
+        ));
+        assert!(is_outage("Invalid status code: 529 <unknown status code>"));
+        assert!(!is_outage("Invalid status code: 401 Unauthorized"));
+        assert!(!is_outage("max_tokens must be at most 15290"));
+        assert!(!is_outage(
+            "This model's maximum context length is 8192 tokens"
+        ));
+    }
+
+    #[test]
+    fn test_parse_model_ids() {
+        assert_eq!(vec![3, 1], parse_model_ids("[3, 1]"));
+        assert!(parse_model_ids("3,1").is_empty());
+    }
+}
@@ -0,0 +1,255 @@
+use entity::entities::{contents::ContentType, messages::MessageDTO, models::Model};
+
+/// Context windows and longest replies of known model families, matched by prefix
+/// of the model name. More specific prefixes come first.
+const CATALOG: &[(&str, u32, u32)] = &[
+    ("gpt-4.1", 1_047_576, 32_768),
+    ("gpt-4o", 128_000, 16_384),
+    ("gpt-4-turbo", 128_000, 4_096),
+    ("gpt-4-32k", 32_768, 4_096),
+    ("gpt-4", 8_192, 4_096),
+    ("gpt-3.5-turbo", 16_385, 4_096),
+    ("o1-mini", 128_000, 65_536),
+    ("o1", 200_000, 100_000),
+    ("o3", 200_000, 100_000),
+    ("o4", 200_000, 100_000),
+    ("claude-3-7", 200_000, 64_000),
+    ("claude-3-5", 200_000, 8_192),
+    ("claude-3", 200_000, 4_096),
+    ("claude", 200_000, 32_000),
+    ("gemini-1.5", 1_048_576, 8_192),
+    ("gemini-2.5", 1_048_576, 65_536),
+    ("gemini-2", 1_048_576, 8_192),
+    ("deepseek", 65_536, 8_192),
+    ("grok", 131_072, 131_072),
+];
+
+/// Rough number of characters per token for English text
+const CHARS_PER_TOKEN: usize = 4;
+/// Languages whose text takes more tokens than English, by code and by name,
+/// with their rough number of characters per token
+const DENSE_LANGUAGES: &[(&[&str], usize)] = &[
+    (&["zh", "ja", "ko", "chinese", "japanese", "korean"], 1),
+    (
+        &[
+            "ru",
+            "uk",
+            "bg",
+            "el",
+            "ar",
+            "he",
+            "fa",
+            "hi",
+            "th",
+            "russian",
+            "ukrainian",
+            "bulgarian",
+            "greek",
+            "arabic",
+            "hebrew",
+            "persian",
+            "hindi",
+            "thai",
+        ],
+        2,
+    ),
+];
+/// Tokens added by providers for the role and separators of each message
+pub const TOKENS_PER_MESSAGE: u32 = 4;
+/// Tokens counted for each image, as its real cost depends on its size and the provider
+pub const TOKENS_PER_IMAGE: u32 = 1_000;
+/// Share of the context window kept free to make up for the estimation error, in percent
+const SAFETY_MARGIN_PERCENT: u32 = 5;
+
+/// Context window and longest reply of a model, in tokens, None when unknown
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ModelLimits {
+    pub context_length: Option<u32>,
+    pub max_output_tokens: Option<u32>,
+}
+
+impl ModelLimits {
+    /// The limits stored with a model
+    pub fn of(model: &Model) -> Self {
+        ModelLimits {
+            context_length: model.context_length,
+            max_output_tokens: model.max_output_tokens,
+        }
+    }
+
+    /// Fill the unknown limits with the ones of the catalog
+    pub fn or_catalog(self, model: &str) -> Self {
+        let catalog = catalog_limits(model);
+        ModelLimits {
+            context_length: self.context_length.or(catalog.context_length),
+            max_output_tokens: self.max_output_tokens.or(catalog.max_output_tokens),
+        }
+    }
+}
+
+/// The limits of a model found in the bundled catalog
+pub fn catalog_limits(model: &str) -> ModelLimits {
+    // Ignore the organization of names like "openai/gpt-4o" used by OpenRouter
+    let name = model.rsplit('/').next().unwrap_or(model).to_lowercase();
+    CATALOG
+        .iter()
+        .find(|(prefix, _, _)| name.starts_with(prefix))
+        .map_or(ModelLimits::default(), |(_, window, output)| ModelLimits {
+            context_length: Some(*window),
+            max_output_tokens: Some(*output),
+        })
+}
+
+/// The context window of a model, when it's known
+pub fn context_window(model: &str) -> Option<u32> {
+    catalog_limits(model).context_length
+}
+
+/// Rough number of characters per token of a language, given by its code or its name
+pub fn chars_per_token(language: Option<&str>) -> usize {
+    let language = language.unwrap_or_default().trim().to_lowercase();
+    // Only the primary subtag of codes like "zh-TW" matters
+    let language = language.split(['-', '_']).next().unwrap_or_default();
+    DENSE_LANGUAGES
+        .iter()
+        .find(|(names, _)| names.contains(&language))
+        .map_or(CHARS_PER_TOKEN, |(_, chars)| *chars)
+}
+
+/// Estimate the number of tokens of a prompt written mostly in a language, English when None,
+/// without the tokenizer of the model
+pub fn estimate_tokens(messages: &[MessageDTO], language: Option<&str>) -> u32 {
+    let chars_per_token = chars_per_token(language);
+    messages
+        .iter()
+        .map(|message| {
+            let content_tokens: u32 = message
+                .content
+                .iter()
+                .map(|content| match content.r#type {
+                    ContentType::Text => {
+                        content.data.chars().count().div_ceil(chars_per_token) as u32
+                    }
+                    ContentType::Image => TOKENS_PER_IMAGE,
+                })
+                .sum();
+            content_tokens + TOKENS_PER_MESSAGE
+        })
+        .sum()
+}
+
+/// The number of tokens left in a context window after the prompt
+pub fn remaining_context(window: u32, messages: &[MessageDTO], language: Option<&str>) -> u32 {
+    let margin = window / 100 * SAFETY_MARGIN_PERCENT;
+    window
+        .saturating_sub(margin)
+        .saturating_sub(estimate_tokens(messages, language))
+}
+
+/**
+ * The longest reply a request may ask for: the max tokens setting, lowered to the
+ * longest reply of the model and to the room left in its context window after the prompt
+ */
+pub fn max_reply_tokens(
+    limits: ModelLimits,
+    max_tokens: u32,
+    messages: &[MessageDTO],
+    language: Option<&str>,
+) -> u32 {
+    let mut result = max_tokens;
+    if let Some(max_output_tokens) = limits.max_output_tokens {
+        result = result.min(max_output_tokens);
+    }
+    if let Some(window) = limits.context_length {
+        result = result.min(remaining_context(window, messages, language));
+    }
+    result.max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use entity::entities::{contents::ContentDTO, messages::Roles};
+
+    use super::*;
+
+    fn message(text: &str) -> MessageDTO {
+        MessageDTO {
+            role: Roles::User.into(),
+            content: vec![ContentDTO {
+                r#type: ContentType::Text,
+                mimetype: None,
+                data: text.to_string(),
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_context_window() {
+        assert_eq!(Some(128_000), context_window("gpt-4o-mini"));
+        assert_eq!(Some(8_192), context_window("gpt-4-0613"));
+        assert_eq!(Some(128_000), context_window("openai/gpt-4o"));
+        assert_eq!(Some(200_000), context_window("claude-3-5-sonnet-latest"));
+        assert_eq!(None, context_window("llama3.2"));
+        let limits = ModelLimits {
+            context_length: Some(32_000),
+            max_output_tokens: None,
+        };
+        assert_eq!(
+            ModelLimits {
+                context_length: Some(32_000),
+                max_output_tokens: Some(16_384),
+            },
+            limits.or_catalog("gpt-4o")
+        );
+    }
+
+    #[test]
+    fn test_remaining_context() {
+        let messages = vec![message("abcdefgh"), message("abc")];
+        assert_eq!(
+            2 + 1 + 2 * TOKENS_PER_MESSAGE,
+            estimate_tokens(&messages, None)
+        );
+        // 8192 minus a margin of 405 and the prompt
+        assert_eq!(7_776, remaining_context(8_192, &messages, None));
+        let long_prompt = vec![message(&"a".repeat(40_000))];
+        assert_eq!(0, remaining_context(8_192, &long_prompt, None));
+    }
+
+    #[test]
+    fn test_max_reply_tokens() {
+        let messages = vec![message("abcdefgh"), message("abc")];
+        let gpt_4 = catalog_limits("gpt-4");
+        assert_eq!(4_096, max_reply_tokens(gpt_4, 8_000, &messages, None));
+        assert_eq!(1_000, max_reply_tokens(gpt_4, 1_000, &messages, None));
+        let small_window = ModelLimits {
+            context_length: Some(2_048),
+            max_output_tokens: None,
+        };
+        assert_eq!(
+            1_937,
+            max_reply_tokens(small_window, 4_096, &messages, None)
+        );
+        assert_eq!(
+            4_096,
+            max_reply_tokens(catalog_limits("mistral"), 4_096, &messages, None)
+        );
+        let long_prompt = vec![message(&"a".repeat(40_000))];
+        assert_eq!(1, max_reply_tokens(gpt_4, 4_096, &long_prompt, None));
+    }
+
+    #[test]
+    fn test_chars_per_token() {
+        assert_eq!(CHARS_PER_TOKEN, chars_per_token(None));
+        assert_eq!(CHARS_PER_TOKEN, chars_per_token(Some("French")));
+        assert_eq!(1, chars_per_token(Some("zh-TW")));
+        assert_eq!(1, chars_per_token(Some(" Japanese ")));
+        assert_eq!(2, chars_per_token(Some("ru")));
+        let messages = vec![message("你好世界")];
+        assert_eq!(
+            4 + TOKENS_PER_MESSAGE,
+            estimate_tokens(&messages, Some("zh"))
+        );
+    }
+}
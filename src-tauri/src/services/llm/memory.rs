@@ -0,0 +1,175 @@
+use entity::entities::{
+    conversation_summaries::Model as ConversationSummary,
+    messages::{MessageDTO, Roles},
+    settings::SETTING_SUMMARY_MODEL,
+};
+
+use crate::services::{db::Repository, markdown};
+
+use super::{
+    context::text_message,
+    tasks::{get_model_setting, pick_model, run_conversation_instruction},
+};
+
+/// Messages not covered by the summary above which the older ones are summarized
+const SUMMARIZE_AFTER_MESSAGES: usize = 40;
+/// Most recent messages always sent as they are
+const KEEP_RECENT_MESSAGES: usize = 20;
+
+const MEMORY_INSTRUCTION: &str = "You keep the memory of a long conversation between a user \
+and an AI assistant. The user gives you the summary of the conversation so far, if any, \
+followed by the transcript of the messages since then in markdown. \
+Write an updated summary of the whole conversation that keeps the facts, decisions, \
+preferences and open questions the assistant needs to carry on. \
+Write it in the language of the conversation. \
+Reply with the summary only, without a title or introduction.";
+
+const MEMORY_PREFIX: &str = "Summary of the earlier part of this conversation:\n\n";
+
+/**
+ * Summarize the older messages of a conversation once too many of them aren't covered
+ * by its summary yet, folding the previous summary into the new one. Returns the new
+ * summary, None when there was nothing to summarize.
+ * Uses the model of the summary setting if set, otherwise the model of the conversation.
+ */
+pub async fn summarize_history(
+    repo: &Repository,
+    conversation_id: i32,
+) -> Result<Option<String>, String> {
+    let previous = repo.get_history_summary(conversation_id).await?;
+    let messages = repo.list_messages(conversation_id).await?;
+    let messages = uncovered_messages(messages, previous.as_ref());
+    let to_summarize = match messages_to_summarize(&messages) {
+        Some(to_summarize) => to_summarize,
+        None => return Ok(None),
+    };
+    let last_message_id = match to_summarize.last().and_then(|message| message.id) {
+        Some(id) => id,
+        None => return Ok(None),
+    };
+    let conversation = repo.get_conversation_details(conversation_id).await?;
+    let model_id = get_model_setting(repo, SETTING_SUMMARY_MODEL).await;
+    let model = pick_model(repo, model_id.or(conversation.model_id)).await?;
+    let mut input = String::new();
+    if let Some(previous) = &previous {
+        input.push_str(&format!("# Summary so far\n\n{}\n\n", previous.summary));
+    }
+    input.push_str(&markdown::conversation_to_markdown(
+        &conversation.subject,
+        to_summarize,
+    ));
+    let summary = run_conversation_instruction(
+        model,
+        repo,
+        conversation_id,
+        MEMORY_INSTRUCTION.to_string(),
+        input,
+    )
+    .await?;
+    if summary.is_empty() {
+        return Err("The model replied with an empty summary".to_string());
+    }
+    repo.upsert_history_summary(conversation_id, summary.clone(), last_message_id)
+        .await?;
+    log::info!(
+        "Summarized conversation {} up to message {}",
+        conversation_id,
+        last_message_id
+    );
+    Ok(Some(summary))
+}
+
+/// The messages a summary doesn't cover yet
+fn uncovered_messages(
+    messages: Vec<MessageDTO>,
+    summary: Option<&ConversationSummary>,
+) -> Vec<MessageDTO> {
+    match summary {
+        Some(summary) => messages
+            .into_iter()
+            .filter(|message| message.id.map_or(true, |id| id > summary.last_message_id))
+            .collect(),
+        None => messages,
+    }
+}
+
+/// The oldest messages to fold into the summary, when there are too many uncovered ones
+fn messages_to_summarize(messages: &[MessageDTO]) -> Option<&[MessageDTO]> {
+    if messages.len() <= SUMMARIZE_AFTER_MESSAGES {
+        return None;
+    }
+    Some(&messages[..messages.len() - KEEP_RECENT_MESSAGES])
+}
+
+/**
+ * Send the summary of a conversation in place of the messages it covers. The summary
+ * is left out when it covers the message replied to, as when replying again to an
+ * older message. Summaries of messages edited, hidden or deleted since are dropped by
+ * the repository, so they never stand for messages they don't match.
+ */
+pub fn apply_summary(messages: &mut Vec<MessageDTO>, summary: &ConversationSummary) {
+    let last_id = messages.last().and_then(|message| message.id);
+    if last_id.map_or(true, |id| id <= summary.last_message_id) {
+        return;
+    }
+    messages.retain(|message| message.id.map_or(true, |id| id > summary.last_message_id));
+    messages.insert(
+        0,
+        text_message(
+            Roles::System,
+            format!("{}{}", MEMORY_PREFIX, summary.summary),
+        ),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(id: i32) -> MessageDTO {
+        MessageDTO {
+            id: Some(id),
+            ..text_message(Roles::User, id.to_string())
+        }
+    }
+
+    fn summary(last_message_id: i32) -> ConversationSummary {
+        ConversationSummary {
+            id: 1,
+            conversation_id: 1,
+            summary: "The user is planning a trip".to_string(),
+            last_message_id,
+            created_at: chrono::Local::now(),
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn test_messages_to_summarize() {
+        let messages: Vec<MessageDTO> = (1..=40).map(message).collect();
+        assert!(messages_to_summarize(&messages).is_none());
+        let messages: Vec<MessageDTO> = (1..=41).map(message).collect();
+        let to_summarize = messages_to_summarize(&messages).unwrap();
+        assert_eq!(21, to_summarize.len());
+        assert_eq!(Some(21), to_summarize.last().unwrap().id);
+
+        let uncovered = uncovered_messages(messages, Some(&summary(21)));
+        assert_eq!(20, uncovered.len());
+        assert_eq!(Some(22), uncovered[0].id);
+    }
+
+    #[test]
+    fn test_apply_summary() {
+        let mut messages: Vec<MessageDTO> = (1..=5).map(message).collect();
+        apply_summary(&mut messages, &summary(3));
+        assert_eq!(3, messages.len());
+        assert_eq!(Roles::System, Roles::from(messages[0].role));
+        assert!(messages[0].content[0].data.ends_with("planning a trip"));
+        assert_eq!(Some(4), messages[1].id);
+
+        // Replying again to a message the summary covers
+        let mut messages: Vec<MessageDTO> = (1..=3).map(message).collect();
+        apply_summary(&mut messages, &summary(3));
+        assert_eq!(3, messages.len());
+    }
+}
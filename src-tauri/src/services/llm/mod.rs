@@ -1,6 +1,22 @@
+pub mod capabilities;
 pub mod chat;
+pub mod context;
+pub mod embeddings;
+pub mod fallback;
+pub mod limits;
+pub mod memory;
 pub mod models;
+pub mod moderation;
+pub mod options;
+pub mod pricing;
+pub mod proxy;
+pub mod resume;
+pub mod retry;
+pub mod schema;
+pub mod truncation;
 mod providers;
 mod utils;
 pub mod client;
+pub mod tasks;
+pub mod tokenizer;
 pub mod types;
@@ -5,6 +5,10 @@ use super::providers::{
     openrouter::models::OpenrouterModels, 
     xai::{config::XaiConfig, models::XaiModels},
     google::{config::GoogleConfig, models::GoogleModels},
+    custom::config::CustomConfig,
+    bedrock::{client::BedrockClient, models::BedrockModels},
+    cohere::{config::CohereConfig, models::CohereModels},
+    aggregator::{config::AggregatorConfig, models::AggregatorModels},
 };
 use async_openai::{config::OpenAIConfig, Client};
 use serde::Serialize;
@@ -13,6 +17,20 @@ use serde::Serialize;
 #[serde(tag = "type", rename_all = "camelCase")]
 pub struct RemoteModel {
     id: String,
+    /// Context window in tokens, for providers whose models API tells it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    context_length: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_output_tokens: Option<u32>,
+}
+
+impl RemoteModel {
+    fn new(id: String) -> Self {
+        RemoteModel {
+            id,
+            ..Default::default()
+        }
+    }
 }
 
 pub enum ListModelsRequestExecutor<'c> {
@@ -23,6 +41,10 @@ pub enum ListModelsRequestExecutor<'c> {
     XaiListModelsRequestExecutor(&'c Client<XaiConfig>),
     ClaudeListModelsRequestExecutor(&'c Client<ClaudeConfig>),
     GoogleListModelsRequestExecutor(&'c Client<GoogleConfig>),
+    CustomListModelsRequestExecutor(&'c Client<CustomConfig>),
+    BedrockListModelsRequestExecutor(&'c BedrockClient),
+    CohereListModelsRequestExecutor(&'c Client<CohereConfig>),
+    AggregatorListModelsRequestExecutor(&'c Client<AggregatorConfig>),
 }
 
 impl<'c> ListModelsRequestExecutor<'c> {
@@ -54,6 +76,22 @@ impl<'c> ListModelsRequestExecutor<'c> {
         return ListModelsRequestExecutor::GoogleListModelsRequestExecutor(client);
     }
 
+    pub fn custom(client: &'c Client<CustomConfig>) -> Self {
+        return ListModelsRequestExecutor::CustomListModelsRequestExecutor(client);
+    }
+
+    pub fn bedrock(client: &'c BedrockClient) -> Self {
+        return ListModelsRequestExecutor::BedrockListModelsRequestExecutor(client);
+    }
+
+    pub fn cohere(client: &'c Client<CohereConfig>) -> Self {
+        return ListModelsRequestExecutor::CohereListModelsRequestExecutor(client);
+    }
+
+    pub fn aggregator(client: &'c Client<AggregatorConfig>) -> Self {
+        return ListModelsRequestExecutor::AggregatorListModelsRequestExecutor(client);
+    }
+
     pub async fn execute(&self) -> Result<Vec<RemoteModel>, String> {
         match self {
             ListModelsRequestExecutor::OpenAIListModelsRequestExecutor(client) => {
@@ -67,7 +105,7 @@ impl<'c> ListModelsRequestExecutor<'c> {
                     })?
                     .data
                     .iter()
-                    .map(|m| RemoteModel { id: m.id.clone() })
+                    .map(|m| RemoteModel::new(m.id.clone()))
                     .collect();
                 Ok(result)
             }
@@ -79,7 +117,7 @@ impl<'c> ListModelsRequestExecutor<'c> {
                 let result = response
                     .models
                     .iter()
-                    .map(|m| RemoteModel { id: m.name.clone() })
+                    .map(|m| RemoteModel::new(m.name.clone()))
                     .collect();
                 Ok(result)
             }
@@ -91,7 +129,14 @@ impl<'c> ListModelsRequestExecutor<'c> {
                 let result = response
                     .data
                     .iter()
-                    .map(|m| RemoteModel { id: m.id.clone() })
+                    .map(|m| RemoteModel {
+                        id: m.id.clone(),
+                        context_length: m.context_length,
+                        max_output_tokens: m
+                            .top_provider
+                            .as_ref()
+                            .and_then(|provider| provider.max_completion_tokens),
+                    })
                     .collect();
                 Ok(result)
             }
@@ -103,7 +148,7 @@ impl<'c> ListModelsRequestExecutor<'c> {
                 let result = response
                     .data
                     .iter()
-                    .map(|m| RemoteModel { id: m.id.clone() })
+                    .map(|m| RemoteModel::new(m.id.clone()))
                     .collect();
                 Ok(result)
             }
@@ -115,7 +160,7 @@ impl<'c> ListModelsRequestExecutor<'c> {
                 let result = response
                     .data
                     .iter()
-                    .map(|m| RemoteModel { id: m.id.clone() })
+                    .map(|m| RemoteModel::new(m.id.clone()))
                     .collect();
                 Ok(result)
             }
@@ -127,7 +172,7 @@ impl<'c> ListModelsRequestExecutor<'c> {
                 let result = response
                     .data
                     .iter()
-                    .map(|m| RemoteModel { id: m.display_name.clone() })
+                    .map(|m| RemoteModel::new(m.display_name.clone()))
                     .collect();
                 Ok(result)
             }
@@ -144,7 +189,74 @@ impl<'c> ListModelsRequestExecutor<'c> {
                             m.name[7..].to_string()
                         } else {
                             m.name.clone()
-                        }
+                        },
+                        context_length: m.input_token_limit,
+                        max_output_tokens: m.output_token_limit,
+                    })
+                    .collect();
+                Ok(result)
+            }
+            ListModelsRequestExecutor::CustomListModelsRequestExecutor(client) => {
+                // Compatible servers usually list their models like OpenAI
+                let result = client
+                    .models()
+                    .list()
+                    .await
+                    .map_err(|err| {
+                        log::error!("CustomListModelsRequestExecutor: {}", err);
+                        String::from("Failed to list models")
+                    })?
+                    .data
+                    .iter()
+                    .map(|m| RemoteModel::new(m.id.clone()))
+                    .collect();
+                Ok(result)
+            }
+            ListModelsRequestExecutor::BedrockListModelsRequestExecutor(client) => {
+                let response = BedrockModels::new(client).list().await.map_err(|err| {
+                    log::error!("BedrockListModelsRequestExecutor: {}", err);
+                    String::from("Failed to list models")
+                })?;
+                // Models only available through provisioned throughput or inference profiles
+                // can't be called by their id
+                let result = response
+                    .model_summaries
+                    .iter()
+                    .filter(|m| m.inference_types_supported.iter().any(|t| t == "ON_DEMAND"))
+                    .map(|m| RemoteModel::new(m.model_id.clone()))
+                    .collect();
+                Ok(result)
+            }
+            ListModelsRequestExecutor::CohereListModelsRequestExecutor(client) => {
+                let response = CohereModels::new(client).list().await.map_err(|err| {
+                    log::error!("CohereListModelsRequestExecutor: {}", err);
+                    String::from("Failed to list models")
+                })?;
+                let result = response
+                    .models
+                    .iter()
+                    .map(|m| RemoteModel {
+                        id: m.name.clone(),
+                        context_length: m.context_length,
+                        max_output_tokens: None,
+                    })
+                    .collect();
+                Ok(result)
+            }
+            ListModelsRequestExecutor::AggregatorListModelsRequestExecutor(client) => {
+                let response = AggregatorModels::new(client).list().await.map_err(|err| {
+                    log::error!("AggregatorListModelsRequestExecutor: {}", err);
+                    String::from("Failed to list models")
+                })?;
+                // image, audio and embedding models are listed along with the chat models
+                let result = response
+                    .models()
+                    .into_iter()
+                    .filter(|m| m.is_chat_model())
+                    .map(|m| RemoteModel {
+                        id: m.id,
+                        context_length: m.context_length,
+                        max_output_tokens: None,
                     })
                     .collect();
                 Ok(result)
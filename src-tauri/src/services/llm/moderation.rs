@@ -0,0 +1,158 @@
+use async_openai::{config::OpenAIConfig, types::CreateModerationRequestArgs, Client};
+use entity::entities::{
+    messages::{MessageDTO, Roles},
+    settings::{SETTING_MODERATION_ENABLED, SETTING_MODERATION_MODEL},
+};
+use serde::Serialize;
+
+use crate::services::db::Repository;
+
+use super::{
+    client::LLMClient,
+    context::ChatContext,
+    tasks::{pick_model, run_instruction},
+};
+
+pub const EVENT_MODERATION_FLAGGED: &str = "moderation-flagged";
+
+// Used with classifier models like Llama Guard, or any chat model when no moderation endpoint exists
+const CLASSIFIER_INSTRUCTION: &str = "You are a content safety classifier. \
+Decide whether the message given by the user is safe to send to an AI assistant. \
+Reply with \"safe\", or with \"unsafe\" followed by a new line and a comma separated list of the violated categories.";
+
+/// Outcome of a moderation check
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModerationResult {
+    pub flagged: bool,
+    pub categories: Vec<String>,
+}
+
+/// Payload of the flagged event, so the UI can ask whether to send anyway
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModerationFlagged {
+    pub conversation_id: i32,
+    pub tag: String,
+    pub categories: Vec<String>,
+}
+
+/**
+ * Check the last user message before it's sent, if moderation is turned on.
+ * Returns None when moderation is off, there's nothing to check or the message passed.
+ */
+pub async fn check_outgoing(
+    repo: &Repository,
+    messages: &[MessageDTO],
+) -> Result<Option<ModerationResult>, String> {
+    let enabled = repo
+        .get_setting(SETTING_MODERATION_ENABLED)
+        .await
+        .map(|setting| setting.value == "true")
+        .unwrap_or(false);
+    if !enabled {
+        return Ok(None);
+    }
+    let text = messages
+        .iter()
+        .rev()
+        .find(|message| Roles::from(message.role) == Roles::User)
+        .and_then(|message| message.get_text());
+    let text = match text {
+        Some(text) if !text.trim().is_empty() => text,
+        _ => return Ok(None),
+    };
+    let result = moderate(repo, text).await?;
+    Ok(Some(result).filter(|result| result.flagged))
+}
+
+/**
+ * Run a text through the moderation model of the settings, or the default model.
+ * OpenAI models use the moderation endpoint, other models act as a classifier.
+ */
+pub async fn moderate(repo: &Repository, text: String) -> Result<ModerationResult, String> {
+    let model_id = repo
+        .get_setting(SETTING_MODERATION_MODEL)
+        .await
+        .and_then(|setting| setting.value.parse::<i32>().ok());
+    let model = pick_model(repo, model_id).await?;
+    let client = ChatContext::one_off(repo, model.clone(), vec![])
+        .await?
+        .client()?;
+    match client {
+        LLMClient::OpenAIClient(client, _) => moderate_with_endpoint(&client, text).await,
+        _ => {
+            let reply =
+                run_instruction(model, repo, CLASSIFIER_INSTRUCTION.to_string(), text).await?;
+            Ok(parse_classifier_reply(&reply))
+        }
+    }
+}
+
+async fn moderate_with_endpoint(
+    client: &Client<OpenAIConfig>,
+    text: String,
+) -> Result<ModerationResult, String> {
+    let request = CreateModerationRequestArgs::default()
+        .input(text)
+        .build()
+        .map_err(|err| err.to_string())?;
+    let response = client
+        .moderations()
+        .create(request)
+        .await
+        .map_err(|err| format!("Moderation request failed: {}", err))?;
+    let mut result = ModerationResult::default();
+    for item in response.results {
+        result.flagged |= item.flagged;
+        // Categories are a struct of flags, keep the names of the raised ones
+        if let Ok(serde_json::Value::Object(categories)) = serde_json::to_value(&item.categories) {
+            for (name, value) in categories {
+                if value == serde_json::Value::Bool(true) && !result.categories.contains(&name) {
+                    result.categories.push(name);
+                }
+            }
+        }
+    }
+    Ok(result)
+}
+
+// Read a "safe" or "unsafe\ncategory, ..." reply
+fn parse_classifier_reply(reply: &str) -> ModerationResult {
+    let mut lines = reply.trim().lines();
+    let verdict = lines.next().unwrap_or_default().trim().to_lowercase();
+    if !verdict.starts_with("unsafe") {
+        return ModerationResult::default();
+    }
+    let categories = lines
+        .flat_map(|line| line.split(','))
+        .map(|category| category.trim().to_string())
+        .filter(|category| !category.is_empty())
+        .collect();
+    ModerationResult {
+        flagged: true,
+        categories,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_classifier_reply() {
+        assert_eq!(ModerationResult::default(), parse_classifier_reply("safe"));
+        assert_eq!(
+            ModerationResult::default(),
+            parse_classifier_reply(" Safe\n")
+        );
+        assert_eq!(
+            ModerationResult {
+                flagged: true,
+                categories: vec!["S1".to_string(), "S10".to_string()],
+            },
+            parse_classifier_reply("unsafe\nS1,S10")
+        );
+        assert!(parse_classifier_reply("UNSAFE").flagged);
+    }
+}
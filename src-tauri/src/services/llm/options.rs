@@ -0,0 +1,456 @@
+use std::collections::BTreeMap;
+
+use entity::entities::{
+    conversations::{
+        AzureOptions, BedrockOptions, ClaudeOptions, CohereOptions, DeepseekOptions,
+        GenericOptions, GoogleOptions, OllamaOptions, OpenAIOptions, XaiOptions,
+    },
+    models::{Model, Providers},
+    settings::{Model as Setting, SETTING_PROVIDER_OPTIONS_PREFIX},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+
+use crate::services::db::Repository;
+
+use super::capabilities::ModelCapabilities;
+
+/// The layer the effective value of an option comes from
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OptionSource {
+    /// The default options of the provider
+    Provider,
+    /// The default options of the model
+    Model,
+    /// Set on the conversation itself
+    Conversation,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveOption {
+    pub value: Value,
+    pub source: OptionSource,
+}
+
+/// The options of a conversation, with the value each option is sent with and its layer,
+/// so the UI can tell inherited options from overridden ones
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversationOptions {
+    #[serde(flatten)]
+    pub options: GenericOptions,
+    pub effective: BTreeMap<String, EffectiveOption>,
+}
+
+/// Key of the setting holding the default options of a provider
+pub fn provider_options_key(provider: &str) -> String {
+    format!("{}{}", SETTING_PROVIDER_OPTIONS_PREFIX, provider)
+}
+
+/// The default options profile of a provider, e.g. `{"maxTokens":4096}` for all Claude models
+pub async fn get_provider_options(repo: &Repository, provider: &str) -> Option<Value> {
+    repo.get_setting(&provider_options_key(provider))
+        .await
+        .and_then(|setting| serde_json::from_str::<Value>(&setting.value).ok())
+        .filter(Value::is_object)
+}
+
+pub async fn set_provider_options(
+    repo: &Repository,
+    provider: &str,
+    options: Value,
+) -> Result<Setting, String> {
+    if !options.is_object() {
+        return Err("Provider options must be a JSON object".to_string());
+    }
+    repo.upsert_setting(Setting {
+        key: provider_options_key(provider),
+        value: options.to_string(),
+    })
+    .await
+}
+
+/// The default options of a model, e.g. `{"temperature":0}` for a model used for code
+fn get_model_options(model: &Model) -> Option<Value> {
+    model
+        .default_options
+        .as_deref()
+        .and_then(|options| serde_json::from_str::<Value>(options).ok())
+        .filter(Value::is_object)
+}
+
+// The layers of options a conversation with a model is sent with, lowest precedence first
+async fn get_option_layers(
+    repo: &Repository,
+    model: &Model,
+    options: &GenericOptions,
+) -> Vec<(OptionSource, Value)> {
+    let mut layers = vec![];
+    if let Some(provider_options) = get_provider_options(repo, &options.provider).await {
+        layers.push((OptionSource::Provider, provider_options));
+    }
+    if let Some(model_options) = get_model_options(model) {
+        layers.push((OptionSource::Model, model_options));
+    }
+    if let Ok(conversation_options) = serde_json::from_str::<Value>(&options.options) {
+        layers.push((OptionSource::Conversation, conversation_options));
+    }
+    layers
+}
+
+/**
+ * The options a conversation with a model is sent with. From lowest to highest precedence:
+ * the default options of the provider, the default options of the model, then the options
+ * of the conversation.
+ */
+pub async fn resolve_options(
+    repo: &Repository,
+    model: &Model,
+    options: GenericOptions,
+) -> GenericOptions {
+    let layers = get_option_layers(repo, model, &options).await;
+    // Options without defaults, or which aren't JSON and left for the provider to reject,
+    // are sent as they are
+    if layers.len() < 2
+        || layers
+            .last()
+            .map_or(true, |(source, _)| *source != OptionSource::Conversation)
+    {
+        return options;
+    }
+    let layers: Vec<Value> = layers.into_iter().map(|(_, layer)| layer).collect();
+    GenericOptions {
+        options: merge_options(&layers).to_string(),
+        ..options
+    }
+}
+
+/**
+ * The options of a conversation along with the value each option is sent with,
+ * resolved as in `resolve_options`
+ */
+pub async fn with_effective_options(
+    repo: &Repository,
+    model: &Model,
+    options: GenericOptions,
+) -> ConversationOptions {
+    let layers = get_option_layers(repo, model, &options).await;
+    ConversationOptions {
+        effective: effective_options(&layers),
+        options,
+    }
+}
+
+/// Merge layers of options like `merge_options`, keeping the layer each value comes from
+pub fn effective_options(layers: &[(OptionSource, Value)]) -> BTreeMap<String, EffectiveOption> {
+    let mut result = BTreeMap::new();
+    for (source, layer) in layers {
+        if let Value::Object(options) = layer {
+            for (key, value) in options {
+                if !value.is_null() {
+                    result.insert(
+                        key.clone(),
+                        EffectiveOption {
+                            value: value.clone(),
+                            source: *source,
+                        },
+                    );
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Merge layers of options, each layer overriding the keys of the ones before it.
+/// Null values are skipped, so an unset option falls back to the layer below.
+pub fn merge_options(layers: &[Value]) -> Value {
+    let mut result = Map::new();
+    for layer in layers {
+        if let Value::Object(options) = layer {
+            for (key, value) in options {
+                if !value.is_null() {
+                    result.insert(key.clone(), value.clone());
+                }
+            }
+        }
+    }
+    Value::Object(result)
+}
+
+/// The options of a conversation, typed for the provider of its model
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum ProviderOptions {
+    Azure(AzureOptions),
+    OpenAI(OpenAIOptions),
+    Claude(ClaudeOptions),
+    Ollama(OllamaOptions),
+    Deepseek(DeepseekOptions),
+    Xai(XaiOptions),
+    Google(GoogleOptions),
+    Bedrock(BedrockOptions),
+    Cohere(CohereOptions),
+}
+
+/// An option rejected by the validation, with its name when a single option is at fault
+#[derive(Clone, Debug, PartialEq)]
+pub struct OptionError {
+    pub option: Option<String>,
+    pub message: String,
+}
+
+impl OptionError {
+    fn new(option: &str, message: String) -> Self {
+        OptionError {
+            option: Some(option.to_string()),
+            message,
+        }
+    }
+}
+
+/// Ranges of the numeric options of a provider, inclusive
+pub fn option_ranges(provider: &str) -> &'static [(&'static str, f64, f64)] {
+    match Providers::from(provider) {
+        Providers::Claude => &[("temperature", 0.0, 1.0), ("topP", 0.0, 1.0)],
+        Providers::Cohere => &[
+            ("temperature", 0.0, 1.0),
+            ("topP", 0.01, 0.99),
+            ("topK", 0.0, 500.0),
+            ("frequencyPenalty", 0.0, 1.0),
+            ("presencePenalty", 0.0, 1.0),
+        ],
+        _ => &[
+            ("temperature", 0.0, 2.0),
+            ("topP", 0.0, 1.0),
+            ("frequencyPenalty", -2.0, 2.0),
+            ("presencePenalty", -2.0, 2.0),
+        ],
+    }
+}
+
+impl ProviderOptions {
+    /// Parse the options of a provider, failing on options of the wrong type or out of
+    /// their range
+    pub fn parse(provider: &str, options: &str) -> Result<Self, OptionError> {
+        fn from_str<T: DeserializeOwned>(options: &str) -> Result<T, OptionError> {
+            serde_json::from_str(options).map_err(|err| OptionError {
+                option: None,
+                message: format!("Invalid options: {}", err),
+            })
+        }
+        let result = match Providers::from(provider) {
+            Providers::Azure => ProviderOptions::Azure(from_str(options)?),
+            Providers::Claude => ProviderOptions::Claude(from_str(options)?),
+            Providers::Ollama => ProviderOptions::Ollama(from_str(options)?),
+            Providers::Deepseek => ProviderOptions::Deepseek(from_str(options)?),
+            Providers::Xai => ProviderOptions::Xai(from_str(options)?),
+            Providers::Google => ProviderOptions::Google(from_str(options)?),
+            Providers::Bedrock => ProviderOptions::Bedrock(from_str(options)?),
+            Providers::Cohere => ProviderOptions::Cohere(from_str(options)?),
+            _ => ProviderOptions::OpenAI(from_str(options)?),
+        };
+        result.validate(provider)?;
+        Ok(result)
+    }
+
+    fn validate(&self, provider: &str) -> Result<(), OptionError> {
+        let options = serde_json::to_value(self).unwrap_or_default();
+        for (key, min, max) in option_ranges(provider) {
+            if let Some(value) = options[key].as_f64() {
+                if value < *min || value > *max {
+                    return Err(OptionError::new(
+                        key,
+                        format!("{} must be between {} and {}", key, min, max),
+                    ));
+                }
+            }
+        }
+        if options["maxTokens"].as_u64() == Some(0) {
+            return Err(OptionError::new(
+                "maxTokens",
+                "maxTokens must be at least 1".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/**
+ * Validate the options of a conversation for the provider and capabilities of its
+ * model, returning them as stored: the typed options along with the settings of the
+ * conversation that aren't sent to the provider, like its truncation strategy.
+ */
+pub fn validate_options(
+    provider: &str,
+    options: &str,
+    capabilities: &ModelCapabilities,
+) -> Result<String, OptionError> {
+    let typed =
+        serde_json::to_value(ProviderOptions::parse(provider, options)?).map_err(|err| {
+            OptionError {
+                option: None,
+                message: format!("Invalid options: {}", err),
+            }
+        })?;
+    if let (Some(max_tokens), Some(limit)) =
+        (typed["maxTokens"].as_u64(), capabilities.max_output_tokens)
+    {
+        if max_tokens > limit as u64 {
+            return Err(OptionError::new(
+                "maxTokens",
+                format!("maxTokens can't be above {}, the limit of the model", limit),
+            ));
+        }
+    }
+    let raw = serde_json::from_str::<Value>(options).unwrap_or_default();
+    Ok(merge_options(&[Value::Object(carried_options(raw)), typed]).to_string())
+}
+
+/// Options kept when a conversation switches to another model. Sampling options like
+/// the temperature are reset instead, as their ranges differ between providers.
+const CARRIED_OPTIONS: [&str; 7] = [
+    "contextLength",
+    "maxTokens",
+    "stream",
+    "truncation",
+    "redactPii",
+    "redactPatterns",
+    "noCache",
+];
+
+/// The options of a conversation switching to another model: the default options of
+/// the new model, with the conversation's own settings carried over
+pub fn carry_options(previous: &str, defaults: &str) -> String {
+    let defaults = serde_json::from_str::<Value>(defaults).unwrap_or_default();
+    let previous = serde_json::from_str::<Value>(previous).unwrap_or_default();
+    merge_options(&[defaults, Value::Object(carried_options(previous))]).to_string()
+}
+
+fn carried_options(options: Value) -> Map<String, Value> {
+    match options {
+        Value::Object(options) => options
+            .into_iter()
+            .filter(|(key, _)| CARRIED_OPTIONS.contains(&key.as_str()))
+            .collect(),
+        _ => Map::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_merge_options() {
+        let provider = json!({ "maxTokens": 4096, "temperature": 0.7 });
+        let conversation = json!({ "temperature": 0.2, "maxTokens": null, "stream": true });
+        assert_eq!(
+            json!({ "maxTokens": 4096, "temperature": 0.2, "stream": true }),
+            merge_options(&[provider, conversation])
+        );
+        assert_eq!(
+            json!({ "stream": false }),
+            merge_options(&[json!("invalid"), json!({ "stream": false })])
+        );
+    }
+
+    #[test]
+    fn test_carry_options() {
+        let previous = r#"{"temperature":1.8,"maxTokens":1024,"truncation":"slidingWindow"}"#;
+        let defaults = r#"{"stream":false,"temperature":0.5}"#;
+        assert_eq!(
+            json!({
+                "stream": false,
+                "temperature": 0.5,
+                "maxTokens": 1024,
+                "truncation": "slidingWindow"
+            }),
+            serde_json::from_str::<Value>(&carry_options(previous, defaults)).unwrap()
+        );
+        assert_eq!(
+            json!({ "stream": false, "temperature": 0.5 }),
+            serde_json::from_str::<Value>(&carry_options("", defaults)).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_validate_options() {
+        let capabilities = ModelCapabilities {
+            context_length: Some(128_000),
+            max_output_tokens: Some(16_384),
+            supports_vision: true,
+            supports_tools: true,
+            temperature_range: (0.0, 2.0),
+        };
+        let options = validate_options(
+            "OpenAI",
+            r#"{"temperature":1.5,"maxTokens":512,"truncation":"slidingWindow","unknown":1}"#,
+            &capabilities,
+        )
+        .unwrap();
+        assert_eq!(
+            json!({ "temperature": 1.5, "maxTokens": 512, "truncation": "slidingWindow" }),
+            serde_json::from_str::<Value>(&options).unwrap()
+        );
+        assert_eq!(
+            Err(OptionError::new(
+                "temperature",
+                "temperature must be between 0 and 1".to_string()
+            )),
+            validate_options("Claude", r#"{"temperature":1.5}"#, &capabilities)
+        );
+        assert_eq!(
+            Err(OptionError::new(
+                "temperature",
+                "temperature must be between 0 and 2".to_string()
+            )),
+            validate_options("OpenAI", r#"{"temperature":5.0}"#, &capabilities)
+        );
+        assert_eq!(
+            Err(OptionError::new(
+                "maxTokens",
+                "maxTokens must be at least 1".to_string()
+            )),
+            validate_options("Deepseek", r#"{"maxTokens":0}"#, &capabilities)
+        );
+        assert_eq!(
+            Some("maxTokens".to_string()),
+            validate_options("OpenAI", r#"{"maxTokens":32000}"#, &capabilities)
+                .unwrap_err()
+                .option
+        );
+        let error =
+            validate_options("OpenAI", r#"{"temperature":"hot"}"#, &capabilities).unwrap_err();
+        assert_eq!(None, error.option);
+        assert!(error.message.starts_with("Invalid options"));
+    }
+
+    #[test]
+    fn test_effective_options() {
+        let effective = effective_options(&[
+            (
+                OptionSource::Provider,
+                json!({ "maxTokens": 4096, "temperature": 0.7 }),
+            ),
+            (
+                OptionSource::Model,
+                json!({ "temperature": 0.0, "topP": 0.9 }),
+            ),
+            (
+                OptionSource::Conversation,
+                json!({ "temperature": 0.2, "maxTokens": null }),
+            ),
+        ]);
+        assert_eq!(3, effective.len());
+        assert_eq!(OptionSource::Provider, effective["maxTokens"].source);
+        assert_eq!(json!(4096), effective["maxTokens"].value);
+        assert_eq!(OptionSource::Model, effective["topP"].source);
+        assert_eq!(OptionSource::Conversation, effective["temperature"].source);
+        assert_eq!(json!(0.2), effective["temperature"].value);
+    }
+}
@@ -0,0 +1,80 @@
+/// Prices of known model families in USD per million input and output tokens,
+/// matched by prefix of the model name. More specific prefixes come first.
+const PRICES: &[(&str, f64, f64)] = &[
+    ("gpt-4.1-nano", 0.10, 0.40),
+    ("gpt-4.1-mini", 0.40, 1.60),
+    ("gpt-4.1", 2.00, 8.00),
+    ("gpt-4o-mini", 0.15, 0.60),
+    ("gpt-4o", 2.50, 10.00),
+    ("gpt-4-turbo", 10.00, 30.00),
+    ("gpt-4", 30.00, 60.00),
+    ("gpt-3.5-turbo", 0.50, 1.50),
+    ("o1-mini", 1.10, 4.40),
+    ("o1", 15.00, 60.00),
+    ("o3-mini", 1.10, 4.40),
+    ("o3", 2.00, 8.00),
+    ("o4-mini", 1.10, 4.40),
+    ("claude-3-haiku", 0.25, 1.25),
+    ("claude-3-5-haiku", 0.80, 4.00),
+    ("claude-3-opus", 15.00, 75.00),
+    ("claude-opus", 15.00, 75.00),
+    ("claude", 3.00, 15.00),
+    ("gemini-1.5-flash", 0.075, 0.30),
+    ("gemini-1.5-pro", 1.25, 5.00),
+    ("gemini-2.0-flash", 0.10, 0.40),
+    ("gemini-2.5-flash", 0.30, 2.50),
+    ("gemini-2.5-pro", 1.25, 10.00),
+    ("deepseek-reasoner", 0.55, 2.19),
+    ("deepseek", 0.27, 1.10),
+    ("grok-3-mini", 0.30, 0.50),
+    ("grok", 3.00, 15.00),
+];
+
+const TOKENS_PER_PRICE_UNIT: f64 = 1_000_000.0;
+
+/// Input and output prices of a model in USD per million tokens, when they are known
+pub fn price(model: &str) -> Option<(f64, f64)> {
+    // Ignore the organization of names like "openai/gpt-4o" used by OpenRouter
+    let name = model.rsplit('/').next().unwrap_or(model).to_lowercase();
+    PRICES
+        .iter()
+        .find(|(prefix, _, _)| name.starts_with(prefix))
+        .map(|(_, input, output)| (*input, *output))
+}
+
+/// The cost of a request in USD, or None when the prices of the model are unknown
+pub fn estimate_cost(model: &str, prompt_tokens: u32, completion_tokens: u32) -> Option<f64> {
+    let (input, output) = price(model)?;
+    Some((prompt_tokens as f64 * input + completion_tokens as f64 * output) / TOKENS_PER_PRICE_UNIT)
+}
+
+/// The name of the model in the JSON config of a `models` row
+pub fn model_name(config: &str) -> Option<String> {
+    let config: serde_json::Value = serde_json::from_str(config).ok()?;
+    config["model"].as_str().map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_price() {
+        assert_eq!(Some((0.15, 0.60)), price("gpt-4o-mini-2024-07-18"));
+        assert_eq!(Some((2.50, 10.00)), price("openai/gpt-4o"));
+        assert_eq!(Some((0.80, 4.00)), price("claude-3-5-haiku-latest"));
+        assert_eq!(Some((3.00, 15.00)), price("claude-3-5-sonnet-latest"));
+        assert_eq!(None, price("llama3.2"));
+    }
+
+    #[test]
+    fn test_estimate_cost() {
+        let cost = estimate_cost("gpt-4o", 1_000_000, 100_000).unwrap();
+        assert!((cost - 3.5).abs() < 1e-9);
+        assert_eq!(None, estimate_cost("mistral", 10, 10));
+        assert_eq!(
+            Some("gpt-4o".to_string()),
+            model_name(r#"{"apiKey":"sk-","model":"gpt-4o"}"#)
+        );
+    }
+}
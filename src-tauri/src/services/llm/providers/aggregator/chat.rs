@@ -0,0 +1,156 @@
+use async_openai::{config::Config, error::OpenAIError, Client};
+use reqwest_eventsource::{Error as EventSourceError, Event, EventSource, RequestBuilderExt};
+use tokio_stream::StreamExt;
+
+use crate::services::llm::providers::openai::chat::{
+    OpenAIChatCompletionRequest, OpenAIChatCompletionResponseStream,
+    OpenAIChatCompletionStreamResponse,
+};
+
+use super::config::AggregatorConfig;
+
+const AGGREGATOR_CHAT_PATH: &str = "/chat/completions";
+
+/// Data of the last event of a stream. Hosts may also close the stream right after the
+/// last chunk without sending it.
+const STREAM_DONE: &str = "[DONE]";
+
+/// What an event of a stream holds
+#[derive(Debug, PartialEq)]
+pub enum StreamData {
+    Chunk(OpenAIChatCompletionStreamResponse),
+    /// Empty events sent to keep the connection open
+    KeepAlive,
+    Done,
+}
+
+/// Streaming chat of Together and Fireworks. Requests and replies are the same as
+/// OpenAI's, streams end differently.
+pub struct AggregatorChat<'c> {
+    client: &'c Client<AggregatorConfig>,
+}
+
+impl<'c> AggregatorChat<'c> {
+    pub fn new(client: &'c Client<AggregatorConfig>) -> Self {
+        Self { client }
+    }
+
+    pub async fn create_stream(
+        &self,
+        mut request: OpenAIChatCompletionRequest,
+    ) -> Result<OpenAIChatCompletionResponseStream, OpenAIError> {
+        if request.common.stream.is_some() && !request.common.stream.unwrap() {
+            return Err(OpenAIError::InvalidArgument(
+                "When stream is false, use Chat::create".into(),
+            ));
+        }
+
+        request.common.stream = Some(true);
+
+        let event_source = self
+            .client
+            .http_client()
+            .post(self.client.config().url(AGGREGATOR_CHAT_PATH))
+            .query(&self.client.config().query())
+            .headers(self.client.config().headers())
+            .json(&request)
+            .eventsource()
+            .map_err(|err| OpenAIError::StreamError(err.to_string()))?;
+
+        Ok(aggregator_stream(event_source))
+    }
+}
+
+/// Read chunks until the host says the stream is done or closes it. The event source
+/// would otherwise reconnect to a closed stream, sending the request again.
+fn aggregator_stream(mut event_source: EventSource) -> OpenAIChatCompletionResponseStream {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        while let Some(event) = event_source.next().await {
+            match event {
+                Ok(Event::Open) => continue,
+                Ok(Event::Message(message)) => match parse_stream_data(&message.data) {
+                    Ok(StreamData::Chunk(chunk)) => {
+                        if tx.send(Ok(chunk)).is_err() {
+                            // rx dropped
+                            break;
+                        }
+                    }
+                    Ok(StreamData::KeepAlive) => continue,
+                    Ok(StreamData::Done) => break,
+                    Err(err) => {
+                        let _ = tx.send(Err(err));
+                        break;
+                    }
+                },
+                Err(EventSourceError::StreamEnded) => break,
+                Err(err) => {
+                    let _ = tx.send(Err(OpenAIError::StreamError(err.to_string())));
+                    break;
+                }
+            }
+        }
+        event_source.close();
+    });
+
+    Box::pin(tokio_stream::wrappers::UnboundedReceiverStream::new(rx))
+}
+
+/// Parse the data of an event, errors being sent as events with an error object
+pub fn parse_stream_data(data: &str) -> Result<StreamData, OpenAIError> {
+    let data = data.trim();
+    if data.is_empty() {
+        return Ok(StreamData::KeepAlive);
+    }
+    if data == STREAM_DONE {
+        return Ok(StreamData::Done);
+    }
+    match serde_json::from_str::<OpenAIChatCompletionStreamResponse>(data) {
+        Ok(chunk) => Ok(StreamData::Chunk(chunk)),
+        Err(err) => {
+            let value: serde_json::Value = serde_json::from_str(data).unwrap_or_default();
+            let error = &value["error"];
+            let message = error["message"]
+                .as_str()
+                .or(error.as_str())
+                .map(String::from)
+                .unwrap_or(format!("Failed to deserialize response: {}", err));
+            Err(OpenAIError::StreamError(message))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::llm::providers::types::FinishReason;
+
+    #[test]
+    fn test_parse_stream_data() {
+        // Together ends replies with its own finish reason, and sends the usage along
+        let together = r#"{"id":"8f3a","object":"chat.completion.chunk","created":1730000000,"model":"meta-llama/Llama-3.3-70B-Instruct-Turbo","choices":[{"index":0,"text":"","logprobs":null,"finish_reason":"eos","seed":null,"delta":{"token_id":128009,"role":"assistant","content":"","tool_calls":null}}],"usage":{"prompt_tokens":12,"completion_tokens":8,"total_tokens":20}}"#;
+        match parse_stream_data(together).unwrap() {
+            StreamData::Chunk(chunk) => {
+                assert_eq!(Some(FinishReason::Stop), chunk.choices[0].finish_reason);
+                assert_eq!(20, chunk.common.usage.unwrap().total_tokens);
+            }
+            data => panic!("Unexpected data: {:?}", data),
+        }
+        let fireworks = r#"{"id":"c2a1","object":"chat.completion.chunk","created":1730000000,"model":"accounts/fireworks/models/llama-v3p1-8b-instruct","choices":[{"index":0,"delta":{"content":"Kaas"},"finish_reason":null}],"usage":null}"#;
+        match parse_stream_data(fireworks).unwrap() {
+            StreamData::Chunk(chunk) => {
+                assert_eq!(Some("Kaas"), chunk.choices[0].delta.content.as_deref());
+            }
+            data => panic!("Unexpected data: {:?}", data),
+        }
+        assert_eq!(StreamData::Done, parse_stream_data("[DONE]").unwrap());
+        assert_eq!(StreamData::KeepAlive, parse_stream_data(" ").unwrap());
+        match parse_stream_data(
+            r#"{"error":{"message":"Model is overloaded","type":"server_error"}}"#,
+        ) {
+            Err(OpenAIError::StreamError(message)) => assert_eq!("Model is overloaded", message),
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use async_openai::{error::OpenAIError, Client};
+use serde::Deserialize;
+
+use super::config::AggregatorConfig;
+
+const AGGREGATOR_LIST_MODELS_PATH: &str = "/models";
+
+// Kinds of Together models which can be chatted with
+const TOGETHER_CHAT_MODEL_TYPES: [&str; 2] = ["chat", "language"];
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct AggregatorRemoteModel {
+    pub id: String,
+    /// Together tells what the model does, eg. chat, embedding or image
+    pub r#type: Option<String>,
+    /// Fireworks tells whether the model can be chatted with
+    pub supports_chat: Option<bool>,
+    pub context_length: Option<u32>,
+}
+
+impl AggregatorRemoteModel {
+    pub fn is_chat_model(&self) -> bool {
+        let chat_type = self
+            .r#type
+            .as_deref()
+            .map_or(true, |r#type| TOGETHER_CHAT_MODEL_TYPES.contains(&r#type));
+        chat_type && self.supports_chat.unwrap_or(true)
+    }
+}
+
+/// Together lists its models in an array, Fireworks in the data of a list like OpenAI
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum AggregatorModelListResponse {
+    Array(Vec<AggregatorRemoteModel>),
+    List { data: Vec<AggregatorRemoteModel> },
+}
+
+impl AggregatorModelListResponse {
+    pub fn models(self) -> Vec<AggregatorRemoteModel> {
+        match self {
+            AggregatorModelListResponse::Array(models) => models,
+            AggregatorModelListResponse::List { data } => data,
+        }
+    }
+}
+
+pub struct AggregatorModels<'c> {
+    client: &'c Client<AggregatorConfig>,
+}
+
+impl<'c> AggregatorModels<'c> {
+    pub fn new(client: &'c Client<AggregatorConfig>) -> Self {
+        Self { client }
+    }
+
+    pub async fn list(&self) -> Result<AggregatorModelListResponse, OpenAIError> {
+        let response = self.client.get(AGGREGATOR_LIST_MODELS_PATH).await?;
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_model_list_response() {
+        let together = r#"[
+            {"id": "meta-llama/Llama-3.3-70B-Instruct-Turbo", "object": "model", "type": "chat", "context_length": 131072},
+            {"id": "BAAI/bge-large-en-v1.5", "object": "model", "type": "embedding"}
+        ]"#;
+        let models = serde_json::from_str::<AggregatorModelListResponse>(together)
+            .unwrap()
+            .models();
+        assert_eq!(2, models.len());
+        assert_eq!(Some(131072), models[0].context_length);
+        assert!(models[0].is_chat_model());
+        assert!(!models[1].is_chat_model());
+
+        let fireworks = r#"{
+            "object": "list",
+            "data": [
+                {"id": "accounts/fireworks/models/llama-v3p1-8b-instruct", "object": "model", "owned_by": "fireworks", "supports_chat": true, "context_length": 131072},
+                {"id": "accounts/fireworks/models/flux-1-dev", "object": "model", "owned_by": "fireworks", "supports_chat": false}
+            ]
+        }"#;
+        let models = serde_json::from_str::<AggregatorModelListResponse>(fireworks)
+            .unwrap()
+            .models();
+        assert_eq!(
+            vec![true, false],
+            models
+                .iter()
+                .map(AggregatorRemoteModel::is_chat_model)
+                .collect::<Vec<bool>>()
+        );
+    }
+}
@@ -0,0 +1,361 @@
+use std::pin::Pin;
+
+use async_openai::error::OpenAIError;
+use entity::entities::{
+    contents::ContentType,
+    messages::{MessageDTO, Roles},
+};
+use serde::{Deserialize, Serialize};
+use tokio_stream::Stream;
+
+use crate::services::cache;
+
+use super::{client::BedrockClient, event_stream::EventStreamDecoder, sigv4::uri_encode};
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BedrockRole {
+    User,
+    Assistant,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct BedrockImageSource {
+    /// Base64 encoded
+    pub bytes: String,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct BedrockImage {
+    /// png, jpeg, gif or webp
+    pub format: String,
+    pub source: BedrockImageSource,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BedrockContentBlock {
+    Text(String),
+    Image(BedrockImage),
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct BedrockMessage {
+    pub role: BedrockRole,
+    pub content: Vec<BedrockContentBlock>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct BedrockSystemContent {
+    pub text: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BedrockInferenceConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+}
+
+/// Request of the Converse API, which takes the same shape for every model of Bedrock
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BedrockConverseRequest {
+    /// Id or ARN of the model, sent in the path
+    #[serde(skip)]
+    pub model_id: String,
+    pub messages: Vec<BedrockMessage>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub system: Vec<BedrockSystemContent>,
+    pub inference_config: BedrockInferenceConfig,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BedrockUsage {
+    pub input_tokens: Option<u32>,
+    pub output_tokens: Option<u32>,
+    pub total_tokens: Option<u32>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct BedrockReasoningText {
+    pub text: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BedrockReasoningContent {
+    pub reasoning_text: Option<BedrockReasoningText>,
+}
+
+/// A content block of a reply. Blocks of other types, like tool uses, are ignored.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct BedrockResponseContentBlock {
+    pub text: Option<String>,
+    pub reasoning_content: Option<BedrockReasoningContent>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct BedrockResponseMessage {
+    pub content: Vec<BedrockResponseContentBlock>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct BedrockOutput {
+    pub message: Option<BedrockResponseMessage>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BedrockConverseResponse {
+    pub output: BedrockOutput,
+    pub stop_reason: Option<String>,
+    pub usage: Option<BedrockUsage>,
+}
+
+impl BedrockConverseResponse {
+    pub fn text(&self) -> String {
+        self.blocks()
+            .filter_map(|block| block.text.as_deref())
+            .collect()
+    }
+
+    pub fn reasoning(&self) -> Option<String> {
+        let reasoning: String = self
+            .blocks()
+            .filter_map(|block| block.reasoning_content.as_ref())
+            .filter_map(|reasoning| reasoning.reasoning_text.as_ref())
+            .map(|reasoning| reasoning.text.as_str())
+            .collect();
+        Some(reasoning).filter(|reasoning| !reasoning.is_empty())
+    }
+
+    fn blocks(&self) -> impl Iterator<Item = &BedrockResponseContentBlock> {
+        self.output
+            .message
+            .iter()
+            .flat_map(|message| message.content.iter())
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct BedrockDelta {
+    pub text: Option<String>,
+    pub reasoning_content: Option<BedrockReasoningText>,
+}
+
+/// An event of ConverseStream: a delta of a content block, the stop of the message, or
+/// its metadata with the usage
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct BedrockStreamEvent {
+    pub delta: Option<BedrockDelta>,
+    pub stop_reason: Option<String>,
+    pub usage: Option<BedrockUsage>,
+}
+
+pub type BedrockConverseResponseStream =
+    Pin<Box<dyn Stream<Item = Result<BedrockStreamEvent, OpenAIError>> + Send>>;
+
+/// Encapsulation of Bedrock's Converse API
+pub struct BedrockChat<'c> {
+    client: &'c BedrockClient,
+}
+
+impl<'c> BedrockChat<'c> {
+    pub fn new(client: &'c BedrockClient) -> Self {
+        Self { client }
+    }
+
+    pub async fn create(
+        &self,
+        request: BedrockConverseRequest,
+    ) -> Result<BedrockConverseResponse, String> {
+        let url = self.url(&request.model_id, "converse");
+        let response = self.client.post(&url, &request).await?;
+        response
+            .json::<BedrockConverseResponse>()
+            .await
+            .map_err(|err| format!("Failed to parse Bedrock response: {}", err))
+    }
+
+    pub async fn create_stream(
+        &self,
+        request: BedrockConverseRequest,
+    ) -> Result<BedrockConverseResponseStream, String> {
+        let url = self.url(&request.model_id, "converse-stream");
+        let mut response = self.client.post(&url, &request).await?;
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            let mut decoder = EventStreamDecoder::new();
+            loop {
+                let chunk = match response.chunk().await {
+                    Ok(Some(chunk)) => chunk,
+                    Ok(None) => break,
+                    Err(err) => {
+                        let _ = tx.send(Err(OpenAIError::StreamError(err.to_string())));
+                        break;
+                    }
+                };
+                decoder.push(&chunk);
+                loop {
+                    let event = match decoder.next_message() {
+                        Ok(Some(message)) => parse_stream_message(message),
+                        Ok(None) => break,
+                        Err(err) => Some(Err(OpenAIError::StreamError(err))),
+                    };
+                    let Some(event) = event else {
+                        continue;
+                    };
+                    let is_error = event.is_err();
+                    if tx.send(event).is_err() || is_error {
+                        // rx dropped, or the stream failed
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(Box::pin(
+            tokio_stream::wrappers::UnboundedReceiverStream::new(rx),
+        ))
+    }
+
+    fn url(&self, model_id: &str, action: &str) -> String {
+        self.client
+            .config()
+            .runtime_url(&format!("/model/{}/{}", uri_encode(model_id), action))
+    }
+}
+
+// The events of interest, or the exception a message carries
+fn parse_stream_message(
+    message: super::event_stream::EventStreamMessage,
+) -> Option<Result<BedrockStreamEvent, OpenAIError>> {
+    if message.header(":message-type") == Some("exception") {
+        let exception = message.header(":exception-type").unwrap_or("exception");
+        let payload = String::from_utf8_lossy(&message.payload);
+        return Some(Err(OpenAIError::StreamError(format!(
+            "{}: {}",
+            exception, payload
+        ))));
+    }
+    match message.header(":event-type") {
+        Some("contentBlockDelta") | Some("messageStop") | Some("metadata") => Some(
+            serde_json::from_slice::<BedrockStreamEvent>(&message.payload)
+                .map_err(|err| OpenAIError::StreamError(err.to_string())),
+        ),
+        _ => None,
+    }
+}
+
+/**
+ * Turn the messages of a conversation into the system prompts and messages of a
+ * Converse request. Bedrock requires the roles to alternate, so consecutive messages
+ * of a role are merged into one.
+ */
+pub fn messages_to_bedrock_request(
+    messages: Vec<MessageDTO>,
+) -> (Vec<BedrockSystemContent>, Vec<BedrockMessage>) {
+    let mut system = vec![];
+    let mut result: Vec<BedrockMessage> = vec![];
+    for message in messages {
+        let role = match message.role.into() {
+            Roles::System => {
+                system.extend(message.get_text().map(|text| BedrockSystemContent { text }));
+                continue;
+            }
+            Roles::Bot => BedrockRole::Assistant,
+            Roles::User => BedrockRole::User,
+        };
+        let content = message
+            .content
+            .into_iter()
+            .filter_map(|item| match item.r#type {
+                ContentType::Text => Some(BedrockContentBlock::Text(item.data)),
+                ContentType::Image => {
+                    cache::read_as_base64_with_mime(item.data.as_str(), item.mimetype.as_deref())
+                        .ok()
+                        .map(|(mimetype, bytes)| {
+                            BedrockContentBlock::Image(BedrockImage {
+                                format: mimetype.trim_start_matches("image/").to_string(),
+                                source: BedrockImageSource { bytes },
+                            })
+                        })
+                }
+            })
+            .collect::<Vec<BedrockContentBlock>>();
+        match result.last_mut() {
+            Some(last) if last.role == role => last.content.extend(content),
+            _ => result.push(BedrockMessage { role, content }),
+        }
+    }
+    (system, result)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::services::llm::context::text_message;
+
+    use super::*;
+
+    #[test]
+    fn test_messages_to_bedrock_request() {
+        let (system, messages) = messages_to_bedrock_request(vec![
+            text_message(Roles::System, "Be brief".to_string()),
+            text_message(Roles::User, "Hi".to_string()),
+            text_message(Roles::User, "Are you there?".to_string()),
+            text_message(Roles::Bot, "Yes".to_string()),
+        ]);
+        assert_eq!(
+            vec![BedrockSystemContent {
+                text: "Be brief".to_string()
+            }],
+            system
+        );
+        assert_eq!(
+            vec![
+                BedrockMessage {
+                    role: BedrockRole::User,
+                    content: vec![
+                        BedrockContentBlock::Text("Hi".to_string()),
+                        BedrockContentBlock::Text("Are you there?".to_string()),
+                    ],
+                },
+                BedrockMessage {
+                    role: BedrockRole::Assistant,
+                    content: vec![BedrockContentBlock::Text("Yes".to_string())],
+                },
+            ],
+            messages
+        );
+        let request = BedrockConverseRequest {
+            model_id: "meta.llama3-8b-instruct-v1:0".to_string(),
+            messages,
+            ..Default::default()
+        };
+        assert_eq!(
+            r#"{"messages":[{"role":"user","content":[{"text":"Hi"},{"text":"Are you there?"}]},{"role":"assistant","content":[{"text":"Yes"}]}],"inferenceConfig":{}}"#,
+            serde_json::to_string(&request).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let response: BedrockConverseResponse = serde_json::from_str(
+            r#"{"output":{"message":{"role":"assistant","content":[{"reasoningContent":{"reasoningText":{"text":"Hmm"}}},{"text":"Hello"},{"toolUse":{}}]}},"stopReason":"max_tokens","usage":{"inputTokens":3,"outputTokens":5,"totalTokens":8}}"#,
+        )
+        .unwrap();
+        assert_eq!("Hello", response.text());
+        assert_eq!(Some("Hmm".to_string()), response.reasoning());
+        assert_eq!(Some(8), response.usage.and_then(|usage| usage.total_tokens));
+    }
+}
@@ -0,0 +1,84 @@
+use reqwest::{Method, Response, Url};
+use serde::{Deserialize, Serialize};
+
+use super::{config::BedrockConfig, sigv4};
+
+/// Name Bedrock's APIs, runtime included, are signed with
+const SIGNING_SERVICE: &str = "bedrock";
+
+#[derive(Debug, Deserialize)]
+struct BedrockErrorResponse {
+    #[serde(alias = "Message")]
+    message: String,
+}
+
+/// HTTP client signing its requests to Bedrock with the credentials of the config
+#[derive(Clone, Debug)]
+pub struct BedrockClient {
+    http_client: reqwest::Client,
+    config: BedrockConfig,
+}
+
+impl BedrockClient {
+    pub fn new(http_client: reqwest::Client, config: BedrockConfig) -> Self {
+        BedrockClient {
+            http_client,
+            config,
+        }
+    }
+
+    pub fn config(&self) -> &BedrockConfig {
+        &self.config
+    }
+
+    pub async fn get(&self, url: &str) -> Result<Response, String> {
+        self.send(Method::GET, url, vec![]).await
+    }
+
+    pub async fn post<B: Serialize>(&self, url: &str, body: &B) -> Result<Response, String> {
+        let body = serde_json::to_vec(body)
+            .map_err(|err| format!("Failed to serialize request: {}", err))?;
+        self.send(Method::POST, url, body).await
+    }
+
+    // Sign and send the request, and turn the error responses into their message
+    async fn send(&self, method: Method, url: &str, body: Vec<u8>) -> Result<Response, String> {
+        let url = Url::parse(url).map_err(|err| format!("Invalid Bedrock URL {}: {}", url, err))?;
+        let headers = sigv4::sign(
+            method.as_str(),
+            &url,
+            &body,
+            &self.config.credentials,
+            &self.config.region,
+            SIGNING_SERVICE,
+            chrono::Utc::now(),
+        );
+        let mut request = self
+            .http_client
+            .request(method, url)
+            .header("content-type", "application/json")
+            .body(body);
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|err| format!("Failed to send request to Bedrock: {}", err))?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let text = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<BedrockErrorResponse>(&text)
+            .map(|error| error.message)
+            .unwrap_or(text);
+        log::error!("Bedrock returned {}: {}", status, message);
+        // Worded like the errors of the other providers, so its status code is kept
+        Err(format!(
+            "Bedrock returned status code: {}, {}",
+            status.as_u16(),
+            message
+        ))
+    }
+}
@@ -0,0 +1,187 @@
+use std::{collections::HashMap, env, fs, path::PathBuf};
+
+use secrecy::Secret;
+
+pub const DEFAULT_BEDROCK_REGION: &str = "us-east-1";
+const DEFAULT_PROFILE: &str = "default";
+
+/// Access key of an AWS account. The session token is set for temporary credentials.
+#[derive(Clone, Debug)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: Secret<String>,
+    pub session_token: Option<Secret<String>>,
+}
+
+impl AwsCredentials {
+    pub fn new(
+        access_key_id: String,
+        secret_access_key: String,
+        session_token: Option<String>,
+    ) -> Self {
+        AwsCredentials {
+            access_key_id,
+            secret_access_key: secret_access_key.into(),
+            session_token: session_token
+                .filter(|token| !token.is_empty())
+                .map(Into::into),
+        }
+    }
+
+    /**
+     * Read the credentials of a profile of the shared credentials file, or of the config
+     * file of the AWS CLI. Their paths can be changed with the environment variables
+     * the CLI uses. Profiles using SSO or assuming a role aren't supported.
+     */
+    pub fn from_profile(profile: &str) -> Result<Self, String> {
+        let files = [
+            (
+                env_path("AWS_SHARED_CREDENTIALS_FILE", "credentials"),
+                profile.to_string(),
+            ),
+            (
+                env_path("AWS_CONFIG_FILE", "config"),
+                if profile == DEFAULT_PROFILE {
+                    profile.to_string()
+                } else {
+                    format!("profile {}", profile)
+                },
+            ),
+        ];
+        for (path, section) in files {
+            let Some(text) = path.and_then(|path| fs::read_to_string(path).ok()) else {
+                continue;
+            };
+            let values = parse_ini_section(&text, &section);
+            if let (Some(access_key_id), Some(secret_access_key)) = (
+                values.get("aws_access_key_id"),
+                values.get("aws_secret_access_key"),
+            ) {
+                return Ok(AwsCredentials::new(
+                    access_key_id.to_string(),
+                    secret_access_key.to_string(),
+                    values.get("aws_session_token").cloned(),
+                ));
+            }
+        }
+        Err(format!(
+            "No access key found for the AWS profile {}",
+            profile
+        ))
+    }
+
+    /// The credentials of the standard environment variables of AWS, if set
+    pub fn from_env() -> Option<Self> {
+        Some(AwsCredentials::new(
+            env::var("AWS_ACCESS_KEY_ID").ok()?,
+            env::var("AWS_SECRET_ACCESS_KEY").ok()?,
+            env::var("AWS_SESSION_TOKEN").ok(),
+        ))
+    }
+
+    /**
+     * The access key given, or else those of the profile, or else those of the
+     * environment variables, or else those of the default profile.
+     */
+    pub fn resolve(
+        access_key_id: Option<String>,
+        secret_access_key: Option<String>,
+        session_token: Option<String>,
+        profile: Option<String>,
+    ) -> Result<Self, String> {
+        let access_key = access_key_id
+            .filter(|id| !id.is_empty())
+            .zip(secret_access_key.filter(|secret| !secret.is_empty()));
+        if let Some((access_key_id, secret_access_key)) = access_key {
+            return Ok(AwsCredentials::new(
+                access_key_id,
+                secret_access_key,
+                session_token,
+            ));
+        }
+        match profile.filter(|profile| !profile.is_empty()) {
+            Some(profile) => Self::from_profile(&profile),
+            None => Self::from_env()
+                .map(Ok)
+                .unwrap_or_else(|| Self::from_profile(DEFAULT_PROFILE)),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BedrockConfig {
+    pub region: String,
+    pub credentials: AwsCredentials,
+}
+
+impl BedrockConfig {
+    pub fn new(region: String, credentials: AwsCredentials) -> Self {
+        BedrockConfig {
+            region,
+            credentials,
+        }
+    }
+
+    /// URL of the runtime API, which runs the models
+    pub fn runtime_url(&self, path: &str) -> String {
+        format!(
+            "https://bedrock-runtime.{}.amazonaws.com{}",
+            self.region, path
+        )
+    }
+
+    /// URL of the control plane API, which lists the models
+    pub fn control_url(&self, path: &str) -> String {
+        format!("https://bedrock.{}.amazonaws.com{}", self.region, path)
+    }
+}
+
+// The path of the environment variable, or of the file in ~/.aws
+fn env_path(variable: &str, file_name: &str) -> Option<PathBuf> {
+    match env::var(variable) {
+        Ok(path) if !path.is_empty() => Some(PathBuf::from(path)),
+        _ => dirs::home_dir().map(|home| home.join(".aws").join(file_name)),
+    }
+}
+
+// The key values of a section of an INI file, like `[section]`
+fn parse_ini_section(text: &str, section: &str) -> HashMap<String, String> {
+    let mut values = HashMap::new();
+    let mut in_section = false;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            in_section = name.trim() == section;
+        } else if let Some((key, value)) = line.split_once('=').filter(|_| in_section) {
+            values.insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ini_section() {
+        let text = "[default]\naws_access_key_id = AKIDDEFAULT\n\n# work account\n[profile work]\naws_access_key_id=AKIDWORK\naws_secret_access_key = secret = with equals\n";
+        let values = parse_ini_section(text, "profile work");
+        assert_eq!(
+            Some(&"AKIDWORK".to_string()),
+            values.get("aws_access_key_id")
+        );
+        assert_eq!(
+            Some(&"secret = with equals".to_string()),
+            values.get("aws_secret_access_key")
+        );
+        assert_eq!(1, parse_ini_section(text, "default").len());
+        assert!(parse_ini_section(text, "work").is_empty());
+    }
+}
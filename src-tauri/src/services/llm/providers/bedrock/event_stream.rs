@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+
+// Total length, headers length and CRC of the prelude, then a CRC of the message at the end
+const PRELUDE_LENGTH: usize = 12;
+const CRC_LENGTH: usize = 4;
+
+/// A message of the `application/vnd.amazon.eventstream` encoding used by AWS to stream
+/// responses. Only the headers of string type are kept, as they are all AWS sets.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EventStreamMessage {
+    pub headers: HashMap<String, String>,
+    pub payload: Vec<u8>,
+}
+
+impl EventStreamMessage {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(|value| value.as_str())
+    }
+}
+
+/**
+ * Split the bytes received into event stream messages, which may span several chunks.
+ * The CRCs are not checked, the transport is already checked by TLS.
+ */
+#[derive(Debug, Default)]
+pub struct EventStreamDecoder {
+    buffer: Vec<u8>,
+}
+
+impl EventStreamDecoder {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// The next complete message, if all of its bytes were received
+    pub fn next_message(&mut self) -> Result<Option<EventStreamMessage>, String> {
+        if self.buffer.len() < PRELUDE_LENGTH {
+            return Ok(None);
+        }
+        let total_length = read_u32(&self.buffer[0..4]) as usize;
+        let headers_length = read_u32(&self.buffer[4..8]) as usize;
+        if total_length < PRELUDE_LENGTH + headers_length + CRC_LENGTH {
+            return Err(format!(
+                "Invalid event stream message of {} bytes",
+                total_length
+            ));
+        }
+        if self.buffer.len() < total_length {
+            return Ok(None);
+        }
+        let message: Vec<u8> = self.buffer.drain(..total_length).collect();
+        let headers_end = PRELUDE_LENGTH + headers_length;
+        Ok(Some(EventStreamMessage {
+            headers: parse_headers(&message[PRELUDE_LENGTH..headers_end])?,
+            payload: message[headers_end..total_length - CRC_LENGTH].to_vec(),
+        }))
+    }
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn parse_headers(mut bytes: &[u8]) -> Result<HashMap<String, String>, String> {
+    let invalid = || "Invalid event stream headers".to_string();
+    let mut headers = HashMap::new();
+    while !bytes.is_empty() {
+        let name_length = bytes[0] as usize;
+        let name = bytes.get(1..1 + name_length).ok_or_else(invalid)?;
+        let name = String::from_utf8_lossy(name).to_string();
+        let value_type = *bytes.get(1 + name_length).ok_or_else(invalid)?;
+        bytes = &bytes[2 + name_length..];
+        // Sizes of the values of each type, by type id. Byte arrays and strings are
+        // prefixed by their length.
+        let value_length = match value_type {
+            0 | 1 => 0,
+            2 => 1,
+            3 => 2,
+            4 => 4,
+            5 | 8 => 8,
+            9 => 16,
+            6 | 7 => {
+                let length = bytes.get(0..2).ok_or_else(invalid)?;
+                bytes = &bytes[2..];
+                u16::from_be_bytes([length[0], length[1]]) as usize
+            }
+            _ => return Err(format!("Unknown event stream header type: {}", value_type)),
+        };
+        let value = bytes.get(0..value_length).ok_or_else(invalid)?;
+        if value_type == 7 {
+            headers.insert(name, String::from_utf8_lossy(value).to_string());
+        }
+        bytes = &bytes[value_length..];
+    }
+    Ok(headers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(headers: &[(&str, &str)], payload: &str) -> Vec<u8> {
+        let mut encoded_headers = vec![];
+        for (name, value) in headers {
+            encoded_headers.push(name.len() as u8);
+            encoded_headers.extend_from_slice(name.as_bytes());
+            encoded_headers.push(7);
+            encoded_headers.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            encoded_headers.extend_from_slice(value.as_bytes());
+        }
+        // a header of another type, which is skipped
+        encoded_headers.extend_from_slice(&[2, b'i', b'd', 4, 0, 0, 0, 42]);
+        let total_length = PRELUDE_LENGTH + encoded_headers.len() + payload.len() + CRC_LENGTH;
+        let mut message = vec![];
+        message.extend_from_slice(&(total_length as u32).to_be_bytes());
+        message.extend_from_slice(&(encoded_headers.len() as u32).to_be_bytes());
+        message.extend_from_slice(&[0; 4]);
+        message.extend_from_slice(&encoded_headers);
+        message.extend_from_slice(payload.as_bytes());
+        message.extend_from_slice(&[0; 4]);
+        message
+    }
+
+    #[test]
+    fn test_decode() {
+        let mut bytes = encode(
+            &[(":event-type", "contentBlockDelta")],
+            r#"{"delta":{"text":"Hi"}}"#,
+        );
+        bytes.extend(encode(
+            &[(":event-type", "messageStop")],
+            r#"{"stopReason":"end_turn"}"#,
+        ));
+        let mut decoder = EventStreamDecoder::new();
+        decoder.push(&bytes[..10]);
+        assert_eq!(Ok(None), decoder.next_message());
+        decoder.push(&bytes[10..30]);
+        assert_eq!(Ok(None), decoder.next_message());
+        decoder.push(&bytes[30..]);
+        let message = decoder.next_message().unwrap().unwrap();
+        assert_eq!(Some("contentBlockDelta"), message.header(":event-type"));
+        assert_eq!(None, message.header("id"));
+        assert_eq!(br#"{"delta":{"text":"Hi"}}"#.to_vec(), message.payload);
+        let message = decoder.next_message().unwrap().unwrap();
+        assert_eq!(Some("messageStop"), message.header(":event-type"));
+        assert_eq!(Ok(None), decoder.next_message());
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        let mut decoder = EventStreamDecoder::new();
+        decoder.push(&[0, 0, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert!(decoder.next_message().is_err());
+    }
+}
@@ -0,0 +1,6 @@
+pub mod chat;
+pub mod client;
+pub mod config;
+pub mod event_stream;
+pub mod models;
+pub mod sigv4;
@@ -0,0 +1,40 @@
+use serde::Deserialize;
+
+use super::client::BedrockClient;
+
+/// Models generating text, which can be chatted with
+const BEDROCK_LIST_MODELS_PATH: &str = "/foundation-models?byOutputModality=TEXT";
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BedrockModelSummary {
+    pub model_id: String,
+    #[serde(default)]
+    pub inference_types_supported: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BedrockModelListResponse {
+    pub model_summaries: Vec<BedrockModelSummary>,
+}
+
+pub struct BedrockModels<'c> {
+    client: &'c BedrockClient,
+}
+
+impl<'c> BedrockModels<'c> {
+    pub fn new(client: &'c BedrockClient) -> Self {
+        Self { client }
+    }
+
+    pub async fn list(&self) -> Result<BedrockModelListResponse, String> {
+        let url = self.client.config().control_url(BEDROCK_LIST_MODELS_PATH);
+        self.client
+            .get(&url)
+            .await?
+            .json::<BedrockModelListResponse>()
+            .await
+            .map_err(|err| format!("Failed to parse Bedrock response: {}", err))
+    }
+}
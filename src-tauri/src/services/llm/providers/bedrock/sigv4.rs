@@ -0,0 +1,206 @@
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::Url;
+use secrecy::ExposeSecret;
+use sha2::{Digest, Sha256};
+
+use super::config::AwsCredentials;
+
+const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+
+/**
+ * Headers authenticating a request to an AWS service with Signature Version 4:
+ * `x-amz-date`, `x-amz-security-token` for temporary credentials, and `authorization`.
+ * The host and these headers are the only ones signed, so others can be added freely.
+ */
+pub fn sign(
+    method: &str,
+    url: &Url,
+    body: &[u8],
+    credentials: &AwsCredentials,
+    region: &str,
+    service: &str,
+    now: DateTime<Utc>,
+) -> Vec<(&'static str, String)> {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+    let mut headers = vec![("host", host), ("x-amz-date", amz_date.clone())];
+    if let Some(token) = &credentials.session_token {
+        headers.push(("x-amz-security-token", token.expose_secret().to_string()));
+    }
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<&str>>()
+        .join(";");
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method,
+        canonical_uri(url),
+        canonical_query(url),
+        canonical_headers,
+        signed_headers,
+        hex(&Sha256::digest(body))
+    );
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+    let string_to_sign = format!(
+        "{}\n{}\n{}\n{}",
+        ALGORITHM,
+        amz_date,
+        scope,
+        hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+    let secret = format!("AWS4{}", credentials.secret_access_key.expose_secret());
+    let key = hmac(secret.as_bytes(), &date);
+    let key = hmac(&key, region);
+    let key = hmac(&key, service);
+    let key = hmac(&key, "aws4_request");
+    let signature = hex(&hmac(&key, &string_to_sign));
+
+    headers.remove(0); // the host header is set by the HTTP client
+    headers.push((
+        "authorization",
+        format!(
+            "{} Credential={}/{}, SignedHeaders={}, Signature={}",
+            ALGORITHM, credentials.access_key_id, scope, signed_headers, signature
+        ),
+    ));
+    headers
+}
+
+/// Percent-encode all but the unreserved characters, as AWS expects
+pub fn uri_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+// Services other than S3 expect the segments of the path, as sent, to be encoded again
+fn canonical_uri(url: &Url) -> String {
+    let path = url
+        .path()
+        .split('/')
+        .map(uri_encode)
+        .collect::<Vec<String>>()
+        .join("/");
+    if path.is_empty() {
+        "/".to_string()
+    } else {
+        path
+    }
+}
+
+fn canonical_query(url: &Url) -> String {
+    let mut pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(name, value)| (uri_encode(&name), uri_encode(&value)))
+        .collect();
+    pairs.sort();
+    pairs
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<String>>()
+        .join("&")
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any size");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn credentials(session_token: Option<&str>) -> AwsCredentials {
+        AwsCredentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY"
+                .to_string()
+                .into(),
+            session_token: session_token.map(|token| token.to_string().into()),
+        }
+    }
+
+    #[test]
+    fn test_sign() {
+        // get-vanilla of the AWS Signature Version 4 test suite
+        let now = Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap();
+        let url = Url::parse("https://example.amazonaws.com/").unwrap();
+        let headers = sign(
+            "GET",
+            &url,
+            b"",
+            &credentials(None),
+            "us-east-1",
+            "service",
+            now,
+        );
+        assert_eq!(
+            vec![
+                ("x-amz-date", "20150830T123600Z".to_string()),
+                (
+                    "authorization",
+                    "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+                    SignedHeaders=host;x-amz-date, \
+                    Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+                        .to_string()
+                ),
+            ],
+            headers
+        );
+        let headers = sign(
+            "GET",
+            &url,
+            b"",
+            &credentials(Some("token")),
+            "us-east-1",
+            "service",
+            now,
+        );
+        assert_eq!(("x-amz-security-token", "token".to_string()), headers[1]);
+        assert!(headers[2]
+            .1
+            .contains("SignedHeaders=host;x-amz-date;x-amz-security-token,"));
+    }
+
+    #[test]
+    fn test_canonical_uri() {
+        let url = Url::parse(&format!(
+            "https://bedrock-runtime.us-east-1.amazonaws.com/model/{}/converse",
+            uri_encode("anthropic.claude-3-haiku-20240307-v1:0")
+        ))
+        .unwrap();
+        assert_eq!(
+            "/model/anthropic.claude-3-haiku-20240307-v1%253A0/converse",
+            canonical_uri(&url)
+        );
+        assert_eq!(
+            "/",
+            canonical_uri(&Url::parse("https://example.com").unwrap())
+        );
+        let url = Url::parse("https://example.com/?b=2&a=1%201").unwrap();
+        assert_eq!("a=1%201&b=2", canonical_query(&url));
+    }
+}
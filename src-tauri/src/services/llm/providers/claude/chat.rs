@@ -55,6 +55,17 @@ pub struct ClaudeResponseMessageTool {
     pub input: serde_json::Value,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ClaudeResponseMessageThinking {
+    pub thinking: String,
+    pub signature: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ClaudeResponseMessageRedactedThinking {
+    pub data: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(untagged)]
 pub enum ClaudeRequestMessageContent {
@@ -72,6 +83,10 @@ pub enum ClaudeResponseMessageContent {
     Text(ClaudeResponseMessageText),
     /// A tool that is to be used by the model
     ToolUse(ClaudeResponseMessageTool),
+    /// The reasoning of the model, when extended thinking is enabled
+    Thinking(ClaudeResponseMessageThinking),
+    /// Reasoning flagged by the safety systems, returned encrypted
+    RedactedThinking(ClaudeResponseMessageRedactedThinking),
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -119,6 +134,15 @@ pub struct ClaudeTool {
     input_schema: serde_json::Value,
 }
 
+/// Extended thinking configuration
+#[derive(Clone, Serialize, Debug, Deserialize, PartialEq)]
+#[serde(tag = "type")]
+#[serde(rename_all = "lowercase")]
+pub enum ClaudeThinking {
+    Enabled { budget_tokens: u32 },
+    Disabled,
+}
+
 #[derive(Clone, Serialize, Debug, Deserialize, PartialEq)]
 pub struct ClaudeCompletionUsage {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -162,6 +186,11 @@ pub struct ClaudeChatCompletionRequest {
     /// Recommended for advanced use cases only. You usually only need to use temperature.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub top_k: Option<u32>,
+
+    /// Configuration for enabling extended thinking.
+    /// The budget must be at least 1024 tokens and less than max_tokens.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<ClaudeThinking>,
 }
 
 #[derive(Clone, Serialize, Debug, Deserialize, PartialEq)]
@@ -228,7 +257,7 @@ pub struct ClaudeChatCompletionStreamContentBlockDelta {
 
 #[derive(Debug, Deserialize, Clone, PartialEq, Serialize)]
 pub struct ClaudeMessageDelta {
-    stop_reason: String,
+    pub stop_reason: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stop_sequences: Option<Vec<String>>,
 }
@@ -0,0 +1,473 @@
+use std::pin::Pin;
+
+use async_openai::{config::Config, error::OpenAIError, Client};
+use entity::entities::{
+    contents::ContentType,
+    messages::{MessageDTO, Roles},
+};
+use serde::{Deserialize, Serialize};
+use tokio_stream::Stream;
+
+use super::config::CohereConfig;
+
+const COHERE_CHAT_PATH: &str = "/v1/chat";
+
+/// Id of the connector letting Cohere search the web and cite the pages it read
+pub const COHERE_WEB_SEARCH_CONNECTOR: &str = "web-search";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum CohereRole {
+    User,
+    Chatbot,
+    System,
+}
+
+/// A message of the chat history, the last user message being sent apart
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CohereChatMessage {
+    pub role: CohereRole,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CohereConnector {
+    pub id: String,
+}
+
+#[derive(Clone, Serialize, Default, Debug, Deserialize, PartialEq)]
+pub struct CohereChatRequest {
+    pub model: String,
+
+    /// The message the model replies to.
+    /// Required.
+    pub message: String,
+
+    /// The messages before `message`, oldest first.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub chat_history: Vec<CohereChatMessage>,
+
+    /// Instructions prepended to the conversation, in place of system messages.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preamble: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+
+    /// Top-p sampling, from 0.01 to 0.99.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub p: Option<f32>,
+
+    /// Top-k sampling, from 0 to 500.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub k: Option<u32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+
+    /// Sources the model may search and cite, eg. the web.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connectors: Option<Vec<CohereConnector>>,
+}
+
+/// A span of the reply backed by documents
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CohereCitation {
+    pub start: u32,
+    pub end: u32,
+    pub text: String,
+    pub document_ids: Vec<String>,
+}
+
+/// A document the model read. Fields other than the id depend on where it comes from,
+/// pages found by the web search having a title and an url.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CohereDocument {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+#[derive(Clone, Serialize, Default, Debug, Deserialize, PartialEq)]
+pub struct CohereUnits {
+    pub input_tokens: Option<u32>,
+    pub output_tokens: Option<u32>,
+}
+
+#[derive(Clone, Serialize, Default, Debug, Deserialize, PartialEq)]
+pub struct CohereMeta {
+    /// Tokens charged for, which leave out the tokens of the prompt template
+    pub billed_units: Option<CohereUnits>,
+    /// Tokens actually read and written by the model
+    pub tokens: Option<CohereUnits>,
+}
+
+#[derive(Clone, Serialize, Debug, Deserialize, PartialEq)]
+pub struct CohereChatResponse {
+    #[serde(default)]
+    pub text: String,
+
+    pub generation_id: Option<String>,
+
+    pub citations: Option<Vec<CohereCitation>>,
+
+    pub documents: Option<Vec<CohereDocument>>,
+
+    /// COMPLETE, MAX_TOKENS, or one of the ERROR reasons
+    pub finish_reason: Option<String>,
+
+    pub meta: Option<CohereMeta>,
+}
+
+impl CohereChatResponse {
+    /// Tokens used by the request, preferring the tokens read by the model to the billed ones
+    pub fn usage(&self) -> CohereUnits {
+        let meta = self.meta.clone().unwrap_or_default();
+        meta.tokens.or(meta.billed_units).unwrap_or_default()
+    }
+
+    /// Sources cited by the reply, listed in markdown
+    pub fn sources(&self) -> Option<String> {
+        format_sources(
+            self.citations.as_deref().unwrap_or_default(),
+            self.documents.as_deref().unwrap_or_default(),
+        )
+    }
+}
+
+/// Events of a streamed reply, one per line. Citations are streamed as they're found,
+/// then sent again along with the documents they point to when the stream ends.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(tag = "event_type", rename_all = "kebab-case")]
+pub enum CohereStreamEvent {
+    TextGeneration {
+        text: String,
+    },
+    StreamEnd {
+        finish_reason: Option<String>,
+        response: CohereChatResponse,
+    },
+    #[serde(other)]
+    Other,
+}
+
+pub type CohereChatResponseStream =
+    Pin<Box<dyn Stream<Item = Result<CohereStreamEvent, OpenAIError>> + Send>>;
+
+/// Encapsulation of Cohere's chat API
+pub struct CohereChat<'c> {
+    client: &'c Client<CohereConfig>,
+}
+
+impl<'c> CohereChat<'c> {
+    pub fn new(client: &'c Client<CohereConfig>) -> Self {
+        Self { client }
+    }
+
+    /// Creates a model response for the given chat conversation.
+    pub async fn create(
+        &self,
+        request: CohereChatRequest,
+    ) -> Result<CohereChatResponse, OpenAIError> {
+        if request.stream.is_some() && request.stream.unwrap() {
+            return Err(OpenAIError::InvalidArgument(
+                "When stream is true, use CohereChat::create_stream".into(),
+            ));
+        }
+        self.client.post(COHERE_CHAT_PATH, request).await
+    }
+
+    /// Cohere streams JSON lines rather than server-sent events
+    pub async fn create_stream(
+        &self,
+        mut request: CohereChatRequest,
+    ) -> Result<CohereChatResponseStream, OpenAIError> {
+        if request.stream.is_some() && !request.stream.unwrap() {
+            return Err(OpenAIError::InvalidArgument(
+                "When stream is false, use CohereChat::create".into(),
+            ));
+        }
+
+        request.stream = Some(true);
+
+        let mut response = self
+            .client
+            .http_client()
+            .post(self.client.config().url(COHERE_CHAT_PATH))
+            .query(&self.client.config().query())
+            .headers(self.client.config().headers())
+            .json(&request)
+            .send()
+            .await
+            .map_err(OpenAIError::from)?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(OpenAIError::StreamError(format!("{}: {}", status, body)));
+        }
+
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            let mut buffer: Vec<u8> = vec![];
+            loop {
+                let chunk = match response.chunk().await {
+                    Ok(Some(chunk)) => chunk,
+                    Ok(None) => break,
+                    Err(err) => {
+                        let _ = tx.send(Err(OpenAIError::StreamError(err.to_string())));
+                        break;
+                    }
+                };
+                buffer.extend_from_slice(&chunk);
+                for line in take_lines(&mut buffer) {
+                    let event = parse_stream_line(&line);
+                    let is_error = event.is_err();
+                    if tx.send(event).is_err() || is_error {
+                        // rx dropped, or the stream failed
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(Box::pin(
+            tokio_stream::wrappers::UnboundedReceiverStream::new(rx),
+        ))
+    }
+}
+
+/// Remove the complete lines from the buffer, leaving the start of the next one
+pub fn take_lines(buffer: &mut Vec<u8>) -> Vec<String> {
+    let Some(last_newline) = buffer.iter().rposition(|byte| *byte == b'\n') else {
+        return vec![];
+    };
+    let rest = buffer.split_off(last_newline + 1);
+    let lines = String::from_utf8_lossy(buffer)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect();
+    *buffer = rest;
+    lines
+}
+
+fn parse_stream_line(line: &str) -> Result<CohereStreamEvent, OpenAIError> {
+    let event = serde_json::from_str::<CohereStreamEvent>(line).map_err(|err| {
+        OpenAIError::StreamError(format!("Failed to deserialize response: {}", err))
+    })?;
+    match &event {
+        CohereStreamEvent::StreamEnd {
+            finish_reason: Some(reason),
+            ..
+        } if reason.starts_with("ERROR") => Err(OpenAIError::StreamError(format!(
+            "Cohere stopped replying: {}",
+            reason
+        ))),
+        _ => Ok(event),
+    }
+}
+
+/**
+ * List the documents cited by a reply, in the order they're first cited. Citations
+ * pointing to documents without a title or an url, such as tool results, are left out.
+ */
+pub fn format_sources(
+    citations: &[CohereCitation],
+    documents: &[CohereDocument],
+) -> Option<String> {
+    let mut cited: Vec<&CohereDocument> = vec![];
+    for document_id in citations
+        .iter()
+        .flat_map(|citation| citation.document_ids.iter())
+    {
+        if cited.iter().any(|document| &document.id == document_id) {
+            continue;
+        }
+        if let Some(document) = documents
+            .iter()
+            .find(|document| &document.id == document_id)
+        {
+            cited.push(document);
+        }
+    }
+    let lines: Vec<String> = cited
+        .iter()
+        .filter_map(|document| match (&document.title, &document.url) {
+            (Some(title), Some(url)) => Some(format!("[{}]({})", title, url)),
+            (None, Some(url)) => Some(format!("<{}>", url)),
+            (Some(title), None) => Some(title.clone()),
+            (None, None) => None,
+        })
+        .enumerate()
+        .map(|(index, source)| format!("{}. {}", index + 1, source))
+        .collect();
+    if lines.is_empty() {
+        return None;
+    }
+    Some(format!("\n\nSources:\n{}", lines.join("\n")))
+}
+
+/**
+ * Split messages into what Cohere expects: system messages become the preamble and the
+ * last user message is the one replied to, the messages before it making the history.
+ * Cohere's chat is text only, so images are left out.
+ */
+pub fn messages_to_cohere_request(
+    messages: Vec<MessageDTO>,
+) -> (Option<String>, Vec<CohereChatMessage>, String) {
+    let mut preamble: Vec<String> = vec![];
+    let mut chat_history: Vec<CohereChatMessage> = vec![];
+    for message in messages {
+        let text = message
+            .content
+            .into_iter()
+            .filter(|content| content.r#type == ContentType::Text)
+            .map(|content| content.data)
+            .collect::<Vec<String>>()
+            .join("\n");
+        let role = match message.role.into() {
+            Roles::System => {
+                preamble.push(text);
+                continue;
+            }
+            Roles::User => CohereRole::User,
+            Roles::Bot => CohereRole::Chatbot,
+        };
+        chat_history.push(CohereChatMessage {
+            role,
+            message: text,
+        });
+    }
+    let message = match chat_history.last() {
+        Some(last) if last.role == CohereRole::User => chat_history
+            .pop()
+            .map(|last| last.message)
+            .unwrap_or_default(),
+        _ => String::default(),
+    };
+    let preamble = if preamble.is_empty() {
+        None
+    } else {
+        Some(preamble.join("\n"))
+    };
+    (preamble, chat_history, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::llm::context::text_message;
+
+    #[test]
+    fn test_messages_to_cohere_request() {
+        let messages = vec![
+            text_message(Roles::System, "Answer in Dutch".to_string()),
+            text_message(Roles::User, "What is cheese?".to_string()),
+            text_message(Roles::Bot, "Kaas".to_string()),
+            text_message(Roles::User, "And bread?".to_string()),
+        ];
+        let (preamble, chat_history, message) = messages_to_cohere_request(messages);
+        assert_eq!(Some("Answer in Dutch".to_string()), preamble);
+        assert_eq!(
+            vec![
+                CohereChatMessage {
+                    role: CohereRole::User,
+                    message: "What is cheese?".to_string(),
+                },
+                CohereChatMessage {
+                    role: CohereRole::Chatbot,
+                    message: "Kaas".to_string(),
+                },
+            ],
+            chat_history
+        );
+        assert_eq!("And bread?", message);
+        assert_eq!(
+            r#"{"role":"CHATBOT","message":"Kaas"}"#,
+            serde_json::to_string(&chat_history[1]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_deserialize_chat_response() {
+        let json = r#"{
+            "response_id": "c7ae2d2a",
+            "text": "Kaas is Dutch for cheese.",
+            "generation_id": "0b5d2c34",
+            "chat_history": [],
+            "finish_reason": "COMPLETE",
+            "citations": [
+                {"start": 0, "end": 4, "text": "Kaas", "document_ids": ["web-search_0", "web-search_1"]},
+                {"start": 18, "end": 24, "text": "cheese", "document_ids": ["web-search_1"]}
+            ],
+            "documents": [
+                {"id": "web-search_0", "snippet": "...", "title": "Kaas", "url": "https://nl.wikipedia.org/wiki/Kaas"},
+                {"id": "web-search_1", "snippet": "...", "url": "https://en.wikipedia.org/wiki/Cheese"}
+            ],
+            "meta": {
+                "api_version": {"version": "1"},
+                "billed_units": {"input_tokens": 12, "output_tokens": 7},
+                "tokens": {"input_tokens": 78, "output_tokens": 7}
+            }
+        }"#;
+        let response: CohereChatResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(Some(78), response.usage().input_tokens);
+        assert_eq!(
+            Some(
+                "\n\nSources:\n1. [Kaas](https://nl.wikipedia.org/wiki/Kaas)\n2. <https://en.wikipedia.org/wiki/Cheese>"
+                    .to_string()
+            ),
+            response.sources()
+        );
+    }
+
+    #[test]
+    fn test_parse_stream_lines() {
+        let mut buffer = br#"{"is_finished":false,"event_type":"stream-start","generation_id":"0b5d2c34"}
+{"is_finished":false,"event_type":"text-generation","text":"Kaas"}
+{"is_finished":true,"event_type":"stream-end","finish_reason":"MAX_TOKENS","response":{"text":"Kaas","meta":{"tokens":{"input_tokens":78,"output_tokens":1}}}}
+{"is_finished":false,"#
+            .to_vec();
+        let lines = take_lines(&mut buffer);
+        assert_eq!(3, lines.len());
+        assert_eq!(br#"{"is_finished":false,"#.to_vec(), buffer);
+        let events: Vec<CohereStreamEvent> = lines
+            .iter()
+            .map(|line| parse_stream_line(line).unwrap())
+            .collect();
+        assert_eq!(CohereStreamEvent::Other, events[0]);
+        assert_eq!(
+            CohereStreamEvent::TextGeneration {
+                text: "Kaas".to_string()
+            },
+            events[1]
+        );
+        match &events[2] {
+            CohereStreamEvent::StreamEnd {
+                finish_reason,
+                response,
+            } => {
+                assert_eq!(Some("MAX_TOKENS"), finish_reason.as_deref());
+                assert_eq!(Some(1), response.usage().output_tokens);
+            }
+            event => panic!("Unexpected event: {:?}", event),
+        }
+        assert!(parse_stream_line(
+            r#"{"is_finished":true,"event_type":"stream-end","finish_reason":"ERROR","response":{}}"#
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,78 @@
+use async_openai::config::Config;
+use reqwest::header::{HeaderMap, AUTHORIZATION, CONTENT_TYPE};
+use secrecy::{ExposeSecret, Secret};
+use serde::Deserialize;
+
+/// Default values for Cohere
+pub const COHERE_ENV_KEY: &str = "COHERE_API_KEY";
+pub const DEFAULT_COHERE_API_BASE: &str = "https://api.cohere.com";
+
+/// Configuration for Cohere API
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct CohereConfig {
+    api_base: String,
+    api_key: Secret<String>,
+}
+
+impl Default for CohereConfig {
+    fn default() -> Self {
+        Self {
+            api_base: DEFAULT_COHERE_API_BASE.to_string(),
+            api_key: std::env::var(COHERE_ENV_KEY)
+                .unwrap_or_else(|_| "".to_string())
+                .into(),
+        }
+    }
+}
+
+impl CohereConfig {
+    /// Create new config with default [DEFAULT_COHERE_API_BASE] url and default
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// To use an API base url different from default [DEFAULT_COHERE_API_BASE]
+    pub fn with_api_base<S: Into<String>>(mut self, api_base: S) -> Self {
+        self.api_base = api_base.into();
+        self
+    }
+
+    /// To use an API key different from default environment variable
+    pub fn with_api_key<S: Into<String>>(mut self, api_key: S) -> Self {
+        self.api_key = Secret::from(api_key.into());
+        self
+    }
+}
+
+impl Config for CohereConfig {
+    fn headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            format!("Bearer {}", self.api_key.expose_secret())
+                .as_str()
+                .parse()
+                .unwrap(),
+        );
+        headers.insert(CONTENT_TYPE, "application/json".parse().unwrap());
+
+        headers
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.api_base, path)
+    }
+
+    fn query(&self) -> Vec<(&str, &str)> {
+        vec![]
+    }
+
+    fn api_base(&self) -> &str {
+        &self.api_base
+    }
+
+    fn api_key(&self) -> &Secret<String> {
+        &self.api_key
+    }
+}
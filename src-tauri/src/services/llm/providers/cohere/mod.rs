@@ -0,0 +1,3 @@
+pub mod chat;
+pub mod config;
+pub mod models;
\ No newline at end of file
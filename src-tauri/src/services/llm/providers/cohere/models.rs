@@ -0,0 +1,35 @@
+use async_openai::{error::OpenAIError, Client};
+use serde::{Deserialize, Serialize};
+
+use super::config::CohereConfig;
+
+/// Models which can be chatted with, the embedding and rerank models being left out
+const COHERE_LIST_MODELS_PATH: &str = "/v1/models?endpoint=chat&page_size=1000";
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CohereRemoteModel {
+    pub name: String,
+    #[serde(default)]
+    pub endpoints: Vec<String>,
+    pub context_length: Option<u32>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CohereModelListResponse {
+    pub models: Vec<CohereRemoteModel>,
+}
+
+pub struct CohereModels<'c> {
+    client: &'c Client<CohereConfig>,
+}
+
+impl<'c> CohereModels<'c> {
+    pub fn new(client: &'c Client<CohereConfig>) -> Self {
+        Self { client }
+    }
+
+    pub async fn list(&self) -> Result<CohereModelListResponse, OpenAIError> {
+        let response = self.client.get(COHERE_LIST_MODELS_PATH).await?;
+        Ok(response)
+    }
+}
@@ -0,0 +1,115 @@
+use async_openai::config::Config;
+use reqwest::header::{HeaderMap, AUTHORIZATION};
+use secrecy::{ExposeSecret, Secret};
+use serde::Deserialize;
+
+pub const DEFAULT_CUSTOM_API_BASE: &str = "https://api.openai.com/v1";
+/// Path of the chat completions API of OpenAI, which compatible servers usually follow
+pub const DEFAULT_CUSTOM_CHAT_PATH: &str = "/chat/completions";
+
+/// Config of any OpenAI compatible server. Requests to the chat completions API are
+/// sent to `chat_path` instead, and the key is only sent when one is set, as local
+/// servers often don't need one.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct CustomConfig {
+    api_base: String,
+    api_key: Secret<String>,
+    chat_path: String,
+}
+
+impl Default for CustomConfig {
+    fn default() -> Self {
+        Self {
+            api_base: DEFAULT_CUSTOM_API_BASE.to_string(),
+            api_key: "".to_string().into(),
+            chat_path: DEFAULT_CUSTOM_CHAT_PATH.to_string(),
+        }
+    }
+}
+
+impl CustomConfig {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn with_api_base<S: Into<String>>(mut self, api_base: S) -> Self {
+        self.api_base = api_base.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_api_key<S: Into<String>>(mut self, api_key: S) -> Self {
+        self.api_key = Secret::from(api_key.into());
+        self
+    }
+
+    pub fn with_chat_path<S: Into<String>>(mut self, chat_path: S) -> Self {
+        let chat_path = chat_path.into();
+        self.chat_path = if chat_path.starts_with('/') {
+            chat_path
+        } else {
+            format!("/{}", chat_path)
+        };
+        self
+    }
+}
+
+impl Config for CustomConfig {
+    fn headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if !self.api_key.expose_secret().is_empty() {
+            headers.insert(
+                AUTHORIZATION,
+                format!("Bearer {}", self.api_key.expose_secret())
+                    .as_str()
+                    .parse()
+                    .unwrap(),
+            );
+        }
+        headers
+    }
+
+    fn url(&self, path: &str) -> String {
+        if path == DEFAULT_CUSTOM_CHAT_PATH {
+            format!("{}{}", self.api_base, self.chat_path)
+        } else {
+            format!("{}{}", self.api_base, path)
+        }
+    }
+
+    fn query(&self) -> Vec<(&str, &str)> {
+        vec![]
+    }
+
+    fn api_base(&self) -> &str {
+        &self.api_base
+    }
+
+    fn api_key(&self) -> &secrecy::Secret<String> {
+        &self.api_key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url() {
+        let config = CustomConfig::new()
+            .with_api_base("http://localhost:8080/")
+            .with_chat_path("v1/chat");
+        assert_eq!(
+            "http://localhost:8080/v1/chat",
+            config.url("/chat/completions")
+        );
+        assert_eq!("http://localhost:8080/models", config.url("/models"));
+        assert!(config.headers().is_empty());
+        let config = CustomConfig::new().with_api_key("secret");
+        assert_eq!(
+            "https://api.openai.com/v1/chat/completions",
+            config.url("/chat/completions")
+        );
+        assert_eq!("Bearer secret", config.headers()[AUTHORIZATION]);
+    }
+}
@@ -62,3 +62,75 @@ impl<'c> DeepseekChat<'c> {
         Ok(self.client.post_stream(DEEPSEEK_CHAT_PATH, request).await)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_reasoning_content() {
+        let json = r#"{
+            "id": "930c60df-bf64-41c9-a88e-3ec75f81e00e",
+            "object": "chat.completion",
+            "created": 1705651092,
+            "model": "deepseek-reasoner",
+            "choices": [
+                {
+                    "index": 0,
+                    "message": {
+                        "role": "assistant",
+                        "content": "9.11 is smaller.",
+                        "reasoning_content": "Compare the decimals: 0.11 < 0.9."
+                    },
+                    "finish_reason": "stop"
+                }
+            ],
+            "usage": {
+                "prompt_tokens": 15,
+                "completion_tokens": 40,
+                "total_tokens": 55,
+                "completion_tokens_details": {"reasoning_tokens": 30}
+            }
+        }"#;
+        let response: DeepseekChatCompletionResponse = serde_json::from_str(json).unwrap();
+        let message = &response.choices[0].message;
+        assert_eq!(Some("9.11 is smaller.".to_string()), message.content);
+        assert_eq!(
+            Some("Compare the decimals: 0.11 < 0.9.".to_string()),
+            message.reasoning
+        );
+    }
+
+    #[test]
+    fn test_deserialize_reasoning_content_stream() {
+        let json = r#"{
+            "id": "1",
+            "object": "chat.completion.chunk",
+            "created": 1705651092,
+            "model": "deepseek-reasoner",
+            "choices": [
+                {
+                    "index": 0,
+                    "delta": {"content": null, "reasoning_content": "Compare"},
+                    "finish_reason": null
+                }
+            ]
+        }"#;
+        let chunk: DeepseekChatCompletionStreamResponse = serde_json::from_str(json).unwrap();
+        let delta = &chunk.choices[0].delta;
+        assert_eq!(None, delta.content);
+        assert_eq!(Some("Compare".to_string()), delta.reasoning);
+        // the last chunk only carries the usage
+        let json = r#"{
+            "id": "1",
+            "object": "chat.completion.chunk",
+            "created": 1705651092,
+            "model": "deepseek-reasoner",
+            "choices": [],
+            "usage": {"prompt_tokens": 15, "completion_tokens": 40, "total_tokens": 55}
+        }"#;
+        let chunk: DeepseekChatCompletionStreamResponse = serde_json::from_str(json).unwrap();
+        assert!(chunk.choices.is_empty());
+        assert_eq!(Some(55), chunk.common.usage.map(|usage| usage.total_tokens));
+    }
+}
@@ -30,13 +30,61 @@ pub enum GoogleChatCompletionContentPart {
     FileData(GoogleChatCompletionContentPartFileData),
 }
 
+#[derive(Clone, Serialize, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GoogleChatCompletionPart {
+    #[serde(flatten)]
+    pub data: GoogleChatCompletionContentPart,
+    /// Set on the parts holding the thoughts of thinking models
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thought: Option<bool>,
+}
+
+impl From<GoogleChatCompletionContentPart> for GoogleChatCompletionPart {
+    fn from(data: GoogleChatCompletionContentPart) -> Self {
+        GoogleChatCompletionPart {
+            data,
+            thought: None,
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Debug, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct GoogleChatCompletionContent {
-    pub parts: Option<Vec<GoogleChatCompletionContentPart>>,
+    pub parts: Option<Vec<GoogleChatCompletionPart>>,
     pub role: GoogleRole,
 }
 
+impl GoogleChatCompletionContent {
+    /// The text of the thought parts, or of the answer parts
+    pub fn text(&self, thoughts: bool) -> String {
+        self.parts
+            .as_ref()
+            .map(|parts| {
+                parts
+                    .iter()
+                    .filter(|part| part.thought.unwrap_or(false) == thoughts)
+                    .filter_map(|part| match &part.data {
+                        GoogleChatCompletionContentPart::Text(text) => Some(text.as_str()),
+                        GoogleChatCompletionContentPart::FileData(_) => None,
+                    })
+                    .collect::<Vec<&str>>()
+                    .join("")
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Default, Clone, Serialize, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GoogleThinkingConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking_budget: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_thoughts: Option<bool>,
+}
+
 #[derive(Default, Clone, Serialize, Debug, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct GoogleChatCompletionRequestGenerationConfig {
@@ -48,6 +96,8 @@ pub struct GoogleChatCompletionRequestGenerationConfig {
     pub stop_sequences: Option<Vec<String>>,
     pub presence_penalty: Option<f32>,
     pub frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking_config: Option<GoogleThinkingConfig>,
 }
 
 #[derive(Clone, Serialize, Debug, Deserialize, PartialEq)]
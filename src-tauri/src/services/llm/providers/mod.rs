@@ -5,4 +5,8 @@ pub mod openrouter;
 pub mod openai;
 pub mod deepseek;
 pub mod xai;
-pub mod google;
\ No newline at end of file
+pub mod google;
+pub mod custom;
+pub mod bedrock;
+pub mod cohere;
+pub mod aggregator;
\ No newline at end of file
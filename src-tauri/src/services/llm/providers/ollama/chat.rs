@@ -85,6 +85,8 @@ pub struct OllamaChatCompletionResponse {
     pub common: ChatCompletionResponseCommon,
     pub message: Option<OllamaMessage>,
     pub done: bool,
+    /// Why the generation ended, e.g. "stop" or "length"
+    pub done_reason: Option<String>,
     // fields below will only appear when stream is false
     // or in the last response object when stream is true
     pub total_duration: Option<u64>, // All durations are returned in nanoseconds.
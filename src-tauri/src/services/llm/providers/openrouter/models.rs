@@ -7,6 +7,17 @@ const OPENROUTER_LIST_MODELS_PATH: &str = "/models";
 pub struct OpenrouterRemoteModel {
     pub id: String,
     pub name: String,
+    #[serde(default)]
+    pub context_length: Option<u32>,
+    #[serde(default)]
+    pub top_provider: Option<OpenrouterTopProvider>,
+}
+
+/// Limits of the provider OpenRouter routes to by default
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct OpenrouterTopProvider {
+    #[serde(default)]
+    pub max_completion_tokens: Option<u32>,
 }
 
 #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
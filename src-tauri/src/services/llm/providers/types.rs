@@ -17,8 +17,25 @@ pub struct ChatCompletionResponseFormat {
     /// content may be partial (i.e. cut off) if `finish_reason="length"`, which indicates the generation
     /// exceeded `max_tokens` or the conversation exceeded the max context length.
     ///
-    /// Must be one of `text` or `json_object`.
+    /// Must be one of `text`, `json_object` or `json_schema`.
     pub r#type: ChatCompletionResponseFormatType,
+
+    /// The schema the reply must match, when the type is `json_schema`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub json_schema: Option<ChatCompletionJsonSchema>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct ChatCompletionJsonSchema {
+    /// The name of the response format. Must be a-z, A-Z, 0-9, or contain underscores and dashes, with a maximum length of 64.
+    pub name: String,
+
+    /// The schema for the response format, described as a JSON Schema object.
+    pub schema: serde_json::Value,
+
+    /// Whether to enable strict schema adherence. Strict mode supports a subset of JSON Schema only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
 }
 
 /// Common fields shared across different LLM provider chat completion requests
@@ -203,6 +220,7 @@ pub enum Stop {
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum FinishReason {
+    #[serde(alias = "eos")] // Together's end of sequence
     Stop,
     Length,
     ToolCalls,
@@ -224,4 +242,5 @@ pub enum ResponseFormatType {
 pub enum ChatCompletionResponseFormatType {
     Text,
     JsonObject,
+    JsonSchema,
 }
\ No newline at end of file
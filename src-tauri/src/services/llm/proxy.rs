@@ -0,0 +1,103 @@
+use std::time::{Duration, Instant};
+
+use entity::entities::settings::ProxySetting;
+use serde::Serialize;
+
+/// Address requested through a proxy to test it, when none is given
+pub const PROXY_TEST_URL: &str = "https://api.openai.com/v1/models";
+/// Longest wait for the response of a proxy test
+const PROXY_TEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Result of a request sent through a proxy
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyTest {
+    pub url: String,
+    /// Status of the response. Any status means the proxy let the request through,
+    /// even an error of the server it was sent to.
+    pub status: u16,
+    pub latency_ms: u64,
+}
+
+/// The proxy of a setting, for the schemes it's turned on for. None when it's turned off.
+/// SOCKS proxies are set with a `socks5://` or `socks5h://` server.
+pub fn build_proxy(setting: &ProxySetting) -> Result<Option<reqwest::Proxy>, String> {
+    if !setting.on {
+        return Ok(None);
+    }
+    let proxy = if setting.http && setting.https {
+        reqwest::Proxy::all(&setting.server)
+    } else if setting.http {
+        reqwest::Proxy::http(&setting.server)
+    } else {
+        reqwest::Proxy::https(&setting.server)
+    }
+    .map_err(|err| format!("Invalid proxy server {}: {}", setting.server, err))?;
+    Ok(Some(with_auth(proxy, setting)))
+}
+
+fn with_auth(proxy: reqwest::Proxy, setting: &ProxySetting) -> reqwest::Proxy {
+    match (&setting.username, &setting.password) {
+        (Some(username), Some(password)) => proxy.basic_auth(username, password),
+        _ => proxy,
+    }
+}
+
+/**
+ * Send a request through a proxy, for every scheme whatever the ones it's turned on
+ * for, to check that it can be reached and accepts its credentials.
+ */
+pub async fn test_proxy(setting: ProxySetting, url: Option<String>) -> Result<ProxyTest, String> {
+    let url = url.unwrap_or(PROXY_TEST_URL.to_string());
+    let proxy = reqwest::Proxy::all(&setting.server)
+        .map_err(|err| format!("Invalid proxy server {}: {}", setting.server, err))?;
+    let client = reqwest::Client::builder()
+        .proxy(with_auth(proxy, &setting))
+        .timeout(PROXY_TEST_TIMEOUT)
+        .build()
+        .map_err(|err| format!("Failed to build client: {}", err))?;
+    let start = Instant::now();
+    let response = client
+        .get(&url)
+        .send()
+        .await
+        .map_err(|err| format!("Failed to reach {} through the proxy: {}", url, err))?;
+    if response.status() == reqwest::StatusCode::PROXY_AUTHENTICATION_REQUIRED {
+        return Err("The proxy rejected its credentials".to_string());
+    }
+    Ok(ProxyTest {
+        url,
+        status: response.status().as_u16(),
+        latency_ms: start.elapsed().as_millis() as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setting(server: &str, on: bool) -> ProxySetting {
+        ProxySetting {
+            on,
+            server: server.to_string(),
+            http: true,
+            https: true,
+            username: Some("user".to_string()),
+            password: Some("secret".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_build_proxy() {
+        assert!(build_proxy(&setting("http://127.0.0.1:8080", false))
+            .unwrap()
+            .is_none());
+        assert!(build_proxy(&setting("http://127.0.0.1:8080", true))
+            .unwrap()
+            .is_some());
+        assert!(build_proxy(&setting("socks5://127.0.0.1:1080", true))
+            .unwrap()
+            .is_some());
+        assert!(build_proxy(&setting("not a url", true)).is_err());
+    }
+}
@@ -0,0 +1,106 @@
+use async_openai::error::OpenAIError;
+use entity::entities::messages::{MessageDTO, Roles};
+use serde::Serialize;
+
+use super::context::{continuation_messages, text_message};
+
+/// How many times a stream dropped mid-generation is resumed before giving up
+pub const MAX_STREAM_RESUMES: u32 = 2;
+
+/// How the rest of a dropped stream is requested
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ResumeStrategy {
+    /// The partial reply is sent as the start of the assistant message, which the model completes
+    Prefill,
+    /// The partial reply is sent back with a prompt asking to continue it
+    Continuation,
+    /// Nothing was received yet, so the request is sent again as it was
+    Restart,
+}
+
+/// Details of a stream resumed after a disconnect, stored in the metadata of the reply
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamRecovery {
+    /// Number of the attempt, starting at 1
+    pub attempt: u32,
+    pub strategy: ResumeStrategy,
+    /// Characters of the reply received before the stream dropped
+    pub received_chars: usize,
+    pub error: String,
+}
+
+/// Whether a stream error is worth retrying: dropped connections and server-side hiccups,
+/// not invalid requests, authentication failures or unreadable responses
+pub fn is_transient(err: &OpenAIError) -> bool {
+    match err {
+        OpenAIError::Reqwest(_) => true,
+        OpenAIError::StreamError(message) => {
+            let is_client_error = message.contains("Invalid status code: 4")
+                && !message.contains("Invalid status code: 429");
+            !is_client_error && !message.starts_with("Failed to deserialize")
+        }
+        _ => false,
+    }
+}
+
+/// The messages requesting the rest of a dropped reply, and how they request it
+pub fn resume_messages(
+    messages: &[MessageDTO],
+    partial_reply: &str,
+    supports_prefill: bool,
+) -> (Vec<MessageDTO>, ResumeStrategy) {
+    if partial_reply.trim().is_empty() {
+        return (messages.to_vec(), ResumeStrategy::Restart);
+    }
+    if supports_prefill {
+        let mut result = messages.to_vec();
+        // Prefilled assistant messages can't end with whitespace
+        result.push(text_message(
+            Roles::Bot,
+            partial_reply.trim_end().to_string(),
+        ));
+        return (result, ResumeStrategy::Prefill);
+    }
+    (
+        continuation_messages(messages, partial_reply),
+        ResumeStrategy::Continuation,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_transient() {
+        assert!(is_transient(&OpenAIError::StreamError(
+            "Transport error: connection reset".to_string()
+        )));
+        assert!(is_transient(&OpenAIError::StreamError(
+            "Invalid status code: 429 Too Many Requests".to_string()
+        )));
+        assert!(!is_transient(&OpenAIError::StreamError(
+            "Invalid status code: 401 Unauthorized".to_string()
+        )));
+        assert!(!is_transient(&OpenAIError::InvalidArgument(
+            "Model is required".to_string()
+        )));
+    }
+
+    #[test]
+    fn test_resume_messages() {
+        let messages = vec![text_message(Roles::User, "Tell me a story".to_string())];
+        let (restart, strategy) = resume_messages(&messages, " ", true);
+        assert_eq!(ResumeStrategy::Restart, strategy);
+        assert_eq!(1, restart.len());
+        let (prefill, strategy) = resume_messages(&messages, "Once upon a time ", true);
+        assert_eq!(ResumeStrategy::Prefill, strategy);
+        assert_eq!(2, prefill.len());
+        assert_eq!(Some("Once upon a time".to_string()), prefill[1].get_text());
+        let (continuation, strategy) = resume_messages(&messages, "Once upon a time ", false);
+        assert_eq!(ResumeStrategy::Continuation, strategy);
+        assert_eq!(3, continuation.len());
+    }
+}
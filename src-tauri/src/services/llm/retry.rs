@@ -0,0 +1,138 @@
+use std::{future::Future, time::Duration};
+
+use rand::Rng;
+use serde::Serialize;
+
+use crate::errors::CommandError;
+
+/// How many times a request is sent before its error is returned, the first time included
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+/// Wait before the second attempt, doubled for each attempt after it
+const BASE_DELAY_MS: u64 = 1_000;
+/// Longest wait between two attempts, unless the provider asks for more
+const MAX_DELAY_MS: u64 = 30_000;
+
+// Parts of the errors of dropped connections, lowercased
+const CONNECTION_MARKERS: [&str; 4] = [
+    "connection reset",
+    "connection closed",
+    "broken pipe",
+    "error sending request",
+];
+
+/// A request sent again after a transient failure, sent to the UI to show progress
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestRetry {
+    /// Number of the attempt about to be made, starting at 2
+    pub attempt: u32,
+    pub max_attempts: u32,
+    /// Wait before the attempt, in milliseconds
+    pub delay_ms: u64,
+    /// Error of the previous attempt
+    pub error: String,
+}
+
+/// Whether an error is worth sending the request again for: rate limits, server errors and
+/// dropped connections. The wait asked by the provider is returned along, if any.
+pub fn retryable(message: &str) -> Option<Option<u64>> {
+    match CommandError::from_provider(message.to_string()) {
+        CommandError::RateLimited { retry_after, .. } => Some(retry_after),
+        CommandError::ProviderError { status, .. } if status >= 500 => Some(None),
+        _ => {
+            let message = message.to_lowercase();
+            CONNECTION_MARKERS
+                .iter()
+                .any(|marker| message.contains(marker))
+                .then_some(None)
+        }
+    }
+}
+
+/// The wait before an attempt: exponential backoff with full jitter, so clients failing
+/// together don't retry together. The wait asked by the provider is respected.
+pub fn backoff(attempt: u32, retry_after: Option<u64>) -> Duration {
+    let exponent = attempt.saturating_sub(2).min(16);
+    let ceiling = BASE_DELAY_MS
+        .saturating_mul(1 << exponent)
+        .min(MAX_DELAY_MS);
+    let delay = rand::thread_rng().gen_range(ceiling / 2..=ceiling);
+    let asked = retry_after.unwrap_or_default().saturating_mul(1_000);
+    Duration::from_millis(delay.max(asked))
+}
+
+/**
+ * Send a request up to `max_attempts` times, for as long as it fails with a transient
+ * error. `on_retry` is called before each new attempt, and the last error is returned
+ * when every attempt failed.
+ */
+pub async fn with_retry<T, F, Fut>(
+    max_attempts: u32,
+    on_retry: impl Fn(&RequestRetry),
+    mut request: F,
+) -> Result<T, String>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, String>>,
+{
+    let mut attempt = 1;
+    loop {
+        let error = match request().await {
+            Err(message) => message,
+            result => return result,
+        };
+        let retry_after = match retryable(&error) {
+            Some(retry_after) if attempt < max_attempts => retry_after,
+            _ => return Err(error),
+        };
+        attempt += 1;
+        let delay = backoff(attempt, retry_after);
+        let retry = RequestRetry {
+            attempt,
+            max_attempts,
+            delay_ms: delay.as_millis() as u64,
+            error,
+        };
+        log::warn!("Request failed, retrying: {:?}", retry);
+        on_retry(&retry);
+        tokio::time::sleep(delay).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retryable() {
+        assert_eq!(
+            Some(Some(20)),
+            retryable("Rate limit reached. Please try again in 20s.")
+        );
+        assert_eq!(
+            Some(None),
+            retryable("Invalid status code: 502 Bad Gateway")
+        );
+        assert_eq!(
+            Some(None),
+            retryable("http error: error sending request for url: connection reset by peer")
+        );
+        assert_eq!(None, retryable("Invalid status code: 401 Unauthorized"));
+        assert_eq!(
+            None,
+            retryable("This model's maximum context length is 8192 tokens")
+        );
+    }
+
+    #[test]
+    fn test_backoff() {
+        for _ in 0..20 {
+            let second = backoff(2, None).as_millis();
+            assert!((500..=1_000).contains(&second));
+            let fourth = backoff(4, None).as_millis();
+            assert!((2_000..=4_000).contains(&fourth));
+            assert!(backoff(30, None).as_millis() <= MAX_DELAY_MS as u128);
+        }
+        assert_eq!(Duration::from_secs(60), backoff(2, Some(60)));
+    }
+}
@@ -0,0 +1,322 @@
+use entity::entities::{
+    conversations::GenericOptions,
+    messages::{MessageDTO, Roles},
+    models::Providers,
+    response_schemas::Model as ResponseSchema,
+};
+use serde_json::Value;
+
+use super::{
+    chat::{BotReply, GlobalSettings},
+    client::LLMClient,
+    context::{chat_with_continuations, text_message},
+};
+
+/// How many times a reply not matching the schema is sent back to the model to be fixed
+const MAX_CORRECTIONS: u32 = 2;
+
+const SCHEMA_INSTRUCTION: &str = "Reply with a JSON value only, without markdown or explanations. \
+It must match this JSON Schema:\n{schema}";
+
+const CORRECTION_PROMPT: &str = "Your reply doesn't match the JSON Schema:\n{errors}\n\
+Reply again with the corrected JSON value only.";
+
+/// Parse the JSON Schema of a response schema, which must be an object
+pub fn parse_schema(schema: &ResponseSchema) -> Result<Value, String> {
+    match serde_json::from_str::<Value>(&schema.schema) {
+        Ok(value) if value.is_object() => Ok(value),
+        _ => Err(format!(
+            "Schema {} is not a valid JSON Schema object",
+            schema.name
+        )),
+    }
+}
+
+/// The name of a schema as structured outputs accept it: letters, digits, underscores
+/// and dashes only, at most 64 characters
+pub fn format_name(name: &str) -> String {
+    let result: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(64)
+        .collect();
+    if result.is_empty() {
+        "response".to_string()
+    } else {
+        result
+    }
+}
+
+/// Whether a provider constrains replies to a JSON Schema itself, through the
+/// `response_format` of its requests
+pub fn has_structured_outputs(provider: &str) -> bool {
+    matches!(
+        Providers::from(provider),
+        Providers::OpenAI
+            | Providers::Azure
+            | Providers::Openrouter
+            | Providers::Xai
+            | Providers::Together
+            | Providers::Fireworks
+            | Providers::CUSTOM
+    )
+}
+
+/// The system message asking for replies matching the schema, for providers
+/// without native structured outputs
+pub fn schema_instruction(schema: &ResponseSchema) -> MessageDTO {
+    text_message(
+        Roles::System,
+        SCHEMA_INSTRUCTION.replace("{schema}", &schema.schema),
+    )
+}
+
+/// Check a reply against the schema, returning what doesn't match
+pub fn validate_reply(schema: &Value, reply: &str) -> Vec<String> {
+    match serde_json::from_str::<Value>(strip_code_fence(reply)) {
+        Ok(value) => {
+            let mut errors = vec![];
+            validate(schema, &value, "$", &mut errors);
+            errors
+        }
+        Err(err) => vec![format!("$: not valid JSON ({})", err)],
+    }
+}
+
+/// Send a one-off request and send the reply back with what is wrong with it
+/// as long as it doesn't match the schema, at most `MAX_CORRECTIONS` times
+pub async fn chat_with_schema(
+    client: &LLMClient,
+    messages: Vec<MessageDTO>,
+    options: GenericOptions,
+    global_settings: GlobalSettings,
+    max_continuations: u32,
+    schema: &ResponseSchema,
+    on_continue: impl Fn(u32),
+) -> Result<BotReply, String> {
+    let schema_value = parse_schema(schema)?;
+    let mut reply = chat_with_continuations(
+        client,
+        messages.clone(),
+        options.clone(),
+        global_settings.clone(),
+        max_continuations,
+        &on_continue,
+    )
+    .await?;
+    let mut errors = validate_reply(&schema_value, &reply.message);
+    let mut corrections = 0;
+    while !errors.is_empty() && corrections < MAX_CORRECTIONS {
+        corrections += 1;
+        log::info!(
+            "Reply doesn't match schema {}, asking for correction {}: {:?}",
+            schema.name,
+            corrections,
+            errors
+        );
+        reply = chat_with_continuations(
+            client,
+            correction_messages(&messages, &reply.message, &errors),
+            options.clone(),
+            global_settings.clone(),
+            max_continuations,
+            &on_continue,
+        )
+        .await?;
+        errors = validate_reply(&schema_value, &reply.message);
+    }
+    if errors.is_empty() {
+        Ok(reply)
+    } else {
+        Err(format!(
+            "The reply doesn't match schema {}: {}",
+            schema.name,
+            errors.join("; ")
+        ))
+    }
+}
+
+// The messages requesting a reply fixing the given errors
+fn correction_messages(messages: &[MessageDTO], reply: &str, errors: &[String]) -> Vec<MessageDTO> {
+    let mut result = messages.to_vec();
+    result.push(text_message(Roles::Bot, reply.to_string()));
+    result.push(text_message(
+        Roles::User,
+        CORRECTION_PROMPT.replace("{errors}", &errors.join("\n")),
+    ));
+    result
+}
+
+// Models without native structured outputs tend to wrap JSON in a markdown code block
+fn strip_code_fence(reply: &str) -> &str {
+    let trimmed = reply.trim();
+    trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|rest| rest.strip_suffix("```"))
+        .map(str::trim)
+        .unwrap_or(trimmed)
+}
+
+// Validate the keywords used by structured outputs: type, enum, const, properties,
+// required, additionalProperties, items, minItems and maxItems
+fn validate(schema: &Value, value: &Value, path: &str, errors: &mut Vec<String>) {
+    if let Some(expected) = schema.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => vec![],
+        };
+        if !types.is_empty() && !types.iter().any(|name| is_type(value, name)) {
+            errors.push(format!("{}: expected {}", path, types.join(" or ")));
+            return;
+        }
+    }
+    if let Some(Value::Array(allowed)) = schema.get("enum") {
+        if !allowed.contains(value) {
+            errors.push(format!("{}: not one of the allowed values", path));
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            errors.push(format!("{}: expected {}", path, expected));
+        }
+    }
+    if let Value::Object(object) = value {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for name in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(name) {
+                    errors.push(format!("{}: missing property {}", path, name));
+                }
+            }
+        }
+        for (name, property) in object {
+            let property_path = format!("{}.{}", path, name);
+            match properties.and_then(|properties| properties.get(name)) {
+                Some(property_schema) => {
+                    validate(property_schema, property, &property_path, errors)
+                }
+                None => match schema.get("additionalProperties") {
+                    Some(Value::Bool(false)) => {
+                        errors.push(format!("{}: unexpected property", property_path))
+                    }
+                    Some(additional @ Value::Object(_)) => {
+                        validate(additional, property, &property_path, errors)
+                    }
+                    _ => {}
+                },
+            }
+        }
+    }
+    if let Value::Array(items) = value {
+        let length = items.len() as u64;
+        if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+            if length < min {
+                errors.push(format!("{}: expected at least {} items", path, min));
+            }
+        }
+        if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+            if length > max {
+                errors.push(format!("{}: expected at most {} items", path, max));
+            }
+        }
+        if let Some(item_schema) = schema.get("items") {
+            for (index, item) in items.iter().enumerate() {
+                validate(item_schema, item, &format!("{}[{}]", path, index), errors);
+            }
+        }
+    }
+}
+
+fn is_type(value: &Value, name: &str) -> bool {
+    match name {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn person_schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "age": { "type": "integer" },
+                "role": { "enum": ["admin", "user"] },
+                "tags": { "type": "array", "items": { "type": "string" }, "maxItems": 2 }
+            },
+            "required": ["name", "age"],
+            "additionalProperties": false
+        })
+    }
+
+    #[test]
+    fn test_has_structured_outputs() {
+        assert!(has_structured_outputs("OpenAI"));
+        assert!(has_structured_outputs("Openrouter"));
+        assert!(!has_structured_outputs("Claude"));
+        assert!(!has_structured_outputs("Deepseek"));
+        assert!(!has_structured_outputs("Unknown"));
+    }
+
+    #[test]
+    fn test_format_name() {
+        assert_eq!("person", format_name("person"));
+        assert_eq!("Person_card_v2-b", format_name(" Person card.v2-b "));
+        assert_eq!("_", format_name("é"));
+        assert_eq!("response", format_name("  "));
+        assert_eq!(64, format_name(&"a".repeat(100)).len());
+    }
+
+    #[test]
+    fn test_validate_reply() {
+        let schema = person_schema();
+        assert!(validate_reply(&schema, r#"{"name":"Ada","age":36,"tags":["a"]}"#).is_empty());
+        assert!(validate_reply(&schema, "```json\n{\"name\":\"Ada\",\"age\":36}\n```").is_empty());
+        assert_eq!(
+            vec![
+                "$: missing property age".to_string(),
+                "$.name: expected string".to_string(),
+                "$.nickname: unexpected property".to_string(),
+                "$.role: not one of the allowed values".to_string(),
+                "$.tags: expected at most 2 items".to_string(),
+                "$.tags[0]: expected string".to_string(),
+            ],
+            validate_reply(
+                &schema,
+                r#"{"name":1,"nickname":"A","role":"guest","tags":[1,"b","c"]}"#
+            )
+        );
+        assert_eq!(1, validate_reply(&schema, "Sure! Here it is").len());
+    }
+
+    #[test]
+    fn test_validate_type_union() {
+        let schema = json!({ "type": ["integer", "null"] });
+        assert!(validate_reply(&schema, "null").is_empty());
+        assert!(validate_reply(&schema, "3").is_empty());
+        assert_eq!(
+            vec!["$: expected integer or null".to_string()],
+            validate_reply(&schema, "3.5")
+        );
+    }
+}
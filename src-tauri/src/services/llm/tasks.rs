@@ -0,0 +1,295 @@
+use entity::entities::{
+    conversations::ConversationDetailsDTO,
+    messages::Roles,
+    models::Model,
+    settings::{
+        SETTING_REFINE_MODEL, SETTING_SUMMARY_MODEL, SETTING_TITLE_AUTO, SETTING_TITLE_MODEL,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use crate::services::{db::Repository, markdown};
+
+use super::context::{get_conversation_privacy_filter, text_message, ChatContext};
+
+const TRANSLATE_INSTRUCTION: &str = "You are a professional translator. \
+Translate the text given by the user into {lang}. \
+Keep the original formatting, including markdown and code blocks, and don't translate code. \
+Reply with the translation only, without notes or explanations.";
+
+const SUMMARY_INSTRUCTION: &str =
+    "You summarize conversations between a user and an AI assistant. \
+The user gives you the transcript in markdown. {style} \
+Write the summary in the language of the conversation. \
+Reply with the summary only, without a title or introduction.";
+
+const REFINE_INSTRUCTION: &str = "You are an expert in prompt engineering. \
+The user gives you a draft prompt they are about to send to an AI assistant. \
+Rewrite it so it is clear, specific and well structured, keeping the user's intent and language. \
+Reply with a JSON object only, with two string fields: \
+\"improved\", the rewritten prompt, and \"explanation\", a short markdown list of the changes you made.";
+
+const TITLE_INSTRUCTION: &str = "You title conversations between a user and an AI assistant. \
+The user gives you the transcript in markdown. \
+Reply with a title of 5 to 8 words in the language of the conversation, \
+without quotes, punctuation at the end or any other text.";
+
+/// Longest title kept, in words, as models don't always stick to the instruction
+const TITLE_MAX_WORDS: usize = 8;
+
+/// Shape of a user-facing conversation summary
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum SummaryStyle {
+    #[default]
+    Bullets,
+    Paragraph,
+}
+
+impl SummaryStyle {
+    fn instruction(&self) -> &'static str {
+        match self {
+            SummaryStyle::Bullets => {
+                "Summarize it as a short markdown bullet list of the key points and decisions."
+            }
+            SummaryStyle::Paragraph => "Summarize it in a single concise paragraph.",
+        }
+    }
+}
+
+/// A rewritten prompt and what was changed in it
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RefinedPrompt {
+    pub improved: String,
+    pub explanation: String,
+}
+
+// Use the given model, or the user's default one
+pub async fn pick_model(repo: &Repository, model_id: Option<i32>) -> Result<Model, String> {
+    match model_id {
+        Some(model_id) => repo.get_model(model_id).await,
+        None => repo.get_default_model().await,
+    }
+}
+
+// Read the id of the model configured for a task, if any
+pub async fn get_model_setting(repo: &Repository, key: &str) -> Option<i32> {
+    repo.get_setting(key)
+        .await
+        .and_then(|setting| setting.value.parse::<i32>().ok())
+}
+
+// Send an instruction and an input to a model and return its reply text
+pub async fn run_instruction(
+    model: Model,
+    repo: &Repository,
+    instruction: String,
+    input: String,
+) -> Result<String, String> {
+    let messages = vec![
+        text_message(Roles::System, instruction),
+        text_message(Roles::User, input),
+    ];
+    let reply = ChatContext::one_off(repo, model, messages)
+        .await?
+        .complete()
+        .await?;
+    Ok(reply.message.trim().to_string())
+}
+
+// Like run_instruction, for an input taken from a conversation. Personal data is redacted
+// when the conversation redacts it, and restored in the reply.
+pub async fn run_conversation_instruction(
+    model: Model,
+    repo: &Repository,
+    conversation_id: i32,
+    instruction: String,
+    input: String,
+) -> Result<String, String> {
+    let mut privacy_filter = get_conversation_privacy_filter(repo, conversation_id).await?;
+    let input = match privacy_filter.as_mut() {
+        Some(filter) => filter.redact(&input),
+        None => input,
+    };
+    let messages = vec![
+        text_message(Roles::System, instruction),
+        text_message(Roles::User, input),
+    ];
+    let mut ctx = ChatContext::one_off(repo, model, messages).await?;
+    ctx.privacy_filter = privacy_filter;
+    let reply = ctx.complete().await?;
+    Ok(reply.message.trim().to_string())
+}
+
+/**
+ * Translate a text without creating a conversation
+ */
+pub async fn translate_text(
+    repo: &Repository,
+    text: String,
+    target_lang: &str,
+    model_id: Option<i32>,
+) -> Result<String, String> {
+    if text.trim().is_empty() {
+        return Err("Nothing to translate".to_string());
+    }
+    let model = pick_model(repo, model_id).await?;
+    let instruction = TRANSLATE_INSTRUCTION.replace("{lang}", target_lang);
+    run_instruction(model, repo, instruction, text).await
+}
+
+/**
+ * Summarize a conversation for the user and store the summary on it.
+ * Uses the model of the summary setting if set, otherwise the model of the conversation.
+ */
+pub async fn summarize_conversation(
+    repo: &Repository,
+    conversation_id: i32,
+    style: SummaryStyle,
+) -> Result<ConversationDetailsDTO, String> {
+    let conversation = repo.get_conversation_details(conversation_id).await?;
+    let messages = repo.list_messages(conversation_id).await?;
+    if messages.is_empty() {
+        return Err("Nothing to summarize".to_string());
+    }
+    let model_id = get_model_setting(repo, SETTING_SUMMARY_MODEL).await;
+    let model = pick_model(repo, model_id.or(conversation.model_id)).await?;
+    let transcript = markdown::conversation_to_markdown(&conversation.subject, &messages);
+    let instruction = SUMMARY_INSTRUCTION.replace("{style}", style.instruction());
+    let summary =
+        run_conversation_instruction(model, repo, conversation_id, instruction, transcript).await?;
+    repo.update_conversation_summary(conversation_id, summary)
+        .await
+}
+
+/**
+ * Title a conversation after its first reply with a few words written by a model,
+ * unless turned off in the settings. Returns the new subject, None when not titled.
+ * Uses the model of the title setting if set, a cheap one is enough, otherwise the
+ * model of the conversation.
+ */
+pub async fn auto_title(repo: &Repository, conversation_id: i32) -> Result<Option<String>, String> {
+    let enabled = repo
+        .get_setting(SETTING_TITLE_AUTO)
+        .await
+        .map_or(true, |setting| setting.value != "false");
+    if !enabled {
+        return Ok(None);
+    }
+    let messages = repo.list_messages(conversation_id).await?;
+    let replies = messages
+        .iter()
+        .filter(|message| Roles::from(message.role) == Roles::Bot)
+        .count();
+    if replies != 1 {
+        return Ok(None);
+    }
+    let conversation = repo.get_conversation_details(conversation_id).await?;
+    let model_id = get_model_setting(repo, SETTING_TITLE_MODEL).await;
+    let model = pick_model(repo, model_id.or(conversation.model_id)).await?;
+    let transcript = markdown::conversation_to_markdown(&conversation.subject, &messages);
+    let reply = run_conversation_instruction(
+        model,
+        repo,
+        conversation_id,
+        TITLE_INSTRUCTION.to_string(),
+        transcript,
+    )
+    .await?;
+    let title = clean_title(&reply).ok_or("The model replied with an empty title")?;
+    let subject = repo
+        .update_conversation_subject(conversation_id, title)
+        .await?;
+    Ok(Some(subject))
+}
+
+// Keep the first line of the reply, without the quotes, markdown and final
+// punctuation models tend to add
+fn clean_title(reply: &str) -> Option<String> {
+    let line = reply.lines().find(|line| !line.trim().is_empty())?;
+    let line = line
+        .trim()
+        .trim_start_matches(['#', '*', ' '])
+        .trim_start_matches("Title:")
+        .trim_matches(|c: char| c.is_whitespace() || "\"'`*“”‘’「」.。!！".contains(c));
+    let title = line
+        .split_whitespace()
+        .take(TITLE_MAX_WORDS)
+        .collect::<Vec<_>>()
+        .join(" ");
+    if title.is_empty() {
+        None
+    } else {
+        Some(title)
+    }
+}
+
+/**
+ * Rewrite a draft prompt before it's sent, explaining what was improved.
+ * Uses the model of the refine setting if set, a cheap one is enough, otherwise the default model.
+ */
+pub async fn refine_prompt(repo: &Repository, draft: String) -> Result<RefinedPrompt, String> {
+    if draft.trim().is_empty() {
+        return Err("Nothing to refine".to_string());
+    }
+    let model_id = get_model_setting(repo, SETTING_REFINE_MODEL).await;
+    let model = pick_model(repo, model_id).await?;
+    let reply = run_instruction(model, repo, REFINE_INSTRUCTION.to_string(), draft).await?;
+    Ok(parse_refined_prompt(&reply))
+}
+
+// Models don't always stick to bare JSON, so accept a fenced block,
+// and treat anything unparsable as the improved prompt itself
+fn parse_refined_prompt(reply: &str) -> RefinedPrompt {
+    let json = reply
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```")
+        .trim();
+    serde_json::from_str::<RefinedPrompt>(json).unwrap_or_else(|_| RefinedPrompt {
+        improved: reply.trim().to_string(),
+        explanation: String::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_title() {
+        assert_eq!(
+            Some("Planning a week in Lisbon".to_string()),
+            clean_title("\"Planning a week in Lisbon.\"")
+        );
+        assert_eq!(
+            Some("Rust ownership explained".to_string()),
+            clean_title("\n## **Rust ownership explained**\nSome notes")
+        );
+        assert_eq!(
+            Some("One two three four five six seven eight".to_string()),
+            clean_title("Title: One two three four five six seven eight nine")
+        );
+        assert_eq!(None, clean_title(" \n\"\""));
+    }
+
+    #[test]
+    fn test_parse_refined_prompt() {
+        let expected = RefinedPrompt {
+            improved: "Explain ownership in Rust".to_string(),
+            explanation: "- Named the language".to_string(),
+        };
+        let reply =
+            r#"{"improved": "Explain ownership in Rust", "explanation": "- Named the language"}"#;
+        assert_eq!(expected, parse_refined_prompt(reply));
+        assert_eq!(
+            expected,
+            parse_refined_prompt(&format!("```json\n{}\n```", reply))
+        );
+        let plain = parse_refined_prompt("Explain ownership in Rust");
+        assert_eq!("Explain ownership in Rust", plain.improved);
+        assert!(plain.explanation.is_empty());
+    }
+}
@@ -0,0 +1,131 @@
+use entity::entities::{contents::ContentType, messages::MessageDTO};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use tiktoken_rs::{
+    tokenizer::{get_tokenizer, Tokenizer},
+    CoreBPE,
+};
+
+use super::limits::{ModelLimits, TOKENS_PER_IMAGE, TOKENS_PER_MESSAGE};
+
+/// Tokens every reply is primed with
+const TOKENS_PER_REPLY: u32 = 3;
+
+static O200K_BASE: Lazy<CoreBPE> =
+    Lazy::new(|| tiktoken_rs::o200k_base().expect("Failed to load o200k_base tokenizer"));
+static CL100K_BASE: Lazy<CoreBPE> =
+    Lazy::new(|| tiktoken_rs::cl100k_base().expect("Failed to load cl100k_base tokenizer"));
+
+/// Size of a prompt in tokens, compared with the context window of its model
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenCount {
+    pub tokens: u32,
+    /// Whether the tokenizer is the model's own. Other models are counted with the
+    /// tokenizer of recent OpenAI models, which is only an approximation for them.
+    pub exact: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_length: Option<u32>,
+    /// Tokens left in the context window for the reply
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remaining: Option<u32>,
+}
+
+impl TokenCount {
+    fn new(tokens: u32, exact: bool, limits: ModelLimits) -> Self {
+        TokenCount {
+            tokens,
+            exact,
+            context_length: limits.context_length,
+            remaining: limits
+                .context_length
+                .map(|window| window.saturating_sub(tokens)),
+        }
+    }
+}
+
+/// The tokenizer of a model, and whether it's the model's own
+fn tokenizer_for(model: &str) -> (&'static CoreBPE, bool) {
+    // Ignore the organization of names like "openai/gpt-4o" used by OpenRouter
+    let name = model.rsplit('/').next().unwrap_or(model);
+    match get_tokenizer(name) {
+        Some(Tokenizer::O200kBase) => (&O200K_BASE, true),
+        Some(Tokenizer::Cl100kBase) => (&CL100K_BASE, true),
+        _ => (&O200K_BASE, false),
+    }
+}
+
+fn text_tokens(bpe: &CoreBPE, text: &str) -> u32 {
+    bpe.encode_with_special_tokens(text).len() as u32
+}
+
+/// Count the tokens of a text for a model
+pub fn count_text_tokens(text: &str, model: &str, limits: ModelLimits) -> TokenCount {
+    let (bpe, exact) = tokenizer_for(model);
+    TokenCount::new(text_tokens(bpe, text), exact, limits)
+}
+
+/// Count the tokens of a prompt for a model, including the tokens providers add around
+/// each message
+pub fn count_message_tokens(
+    messages: &[MessageDTO],
+    model: &str,
+    limits: ModelLimits,
+) -> TokenCount {
+    let (bpe, exact) = tokenizer_for(model);
+    let tokens = messages
+        .iter()
+        .map(|message| {
+            let content_tokens: u32 = message
+                .content
+                .iter()
+                .map(|content| match content.r#type {
+                    ContentType::Text => text_tokens(bpe, &content.data),
+                    ContentType::Image => TOKENS_PER_IMAGE,
+                })
+                .sum();
+            content_tokens + TOKENS_PER_MESSAGE
+        })
+        .sum::<u32>()
+        + TOKENS_PER_REPLY;
+    TokenCount::new(tokens, exact, limits)
+}
+
+#[cfg(test)]
+mod tests {
+    use entity::entities::messages::Roles;
+
+    use super::*;
+    use crate::services::llm::context::text_message;
+
+    #[test]
+    fn test_count_text_tokens() {
+        let limits = ModelLimits {
+            context_length: Some(128_000),
+            max_output_tokens: None,
+        };
+        let count = count_text_tokens("Hello world", "gpt-4o", limits);
+        assert_eq!(2, count.tokens);
+        assert!(count.exact);
+        assert_eq!(Some(127_998), count.remaining);
+        assert!(count_text_tokens("Hello world", "gpt-4-0613", limits).exact);
+        assert!(count_text_tokens("Hello world", "openai/gpt-4o-mini", limits).exact);
+        let count = count_text_tokens("Hello world", "llama3.2", ModelLimits::default());
+        assert!(!count.exact);
+        assert_eq!(None, count.remaining);
+    }
+
+    #[test]
+    fn test_count_message_tokens() {
+        let messages = vec![
+            text_message(Roles::System, "Be brief".to_string()),
+            text_message(Roles::User, "Hello world".to_string()),
+        ];
+        let text = count_text_tokens("Be brief", "gpt-4o", ModelLimits::default()).tokens
+            + count_text_tokens("Hello world", "gpt-4o", ModelLimits::default()).tokens;
+        assert_eq!(
+            text + 2 * TOKENS_PER_MESSAGE + TOKENS_PER_REPLY,
+            count_message_tokens(&messages, "gpt-4o", ModelLimits::default()).tokens
+        );
+    }
+}
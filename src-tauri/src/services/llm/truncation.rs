@@ -0,0 +1,199 @@
+use entity::entities::messages::{MessageDTO, Roles};
+use serde::Deserialize;
+
+use super::limits::{self, ModelLimits};
+
+/// How the history of a conversation is cut to fit the context window of its model,
+/// set by the `truncation` option of the conversation. As the whole conversation is
+/// sent by default, the default strategy fits the window.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TruncationStrategy {
+    /// Only keep the last turns given by the context length, whatever their size
+    DropOldest,
+    /// Keep the most recent messages fitting the context window, instructions included
+    SlidingWindow,
+    /// Keep the instructions and the most recent messages fitting the context window
+    #[default]
+    KeepSystemAndRecent,
+}
+
+impl TruncationStrategy {
+    /// The strategy set in the options of a conversation, the default one when not set
+    /// or unknown
+    pub fn from_options(options: &str) -> Self {
+        serde_json::from_str::<serde_json::Value>(options)
+            .ok()
+            .and_then(|options| serde_json::from_value(options["truncation"].clone()).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Tokens of the context window the prompt may take: the window minus the reply
+pub fn prompt_budget(limits: ModelLimits, max_tokens: u32) -> Option<u32> {
+    let window = limits.context_length?;
+    let reply_tokens = limits
+        .max_output_tokens
+        .map_or(max_tokens, |max_output_tokens| {
+            max_tokens.min(max_output_tokens)
+        });
+    Some(window.saturating_sub(reply_tokens))
+}
+
+/**
+ * Drop the oldest messages of a prompt until its estimated size fits the budget. The
+ * last message, the one replied to, is always kept, and the history kept starts with a
+ * user message as some providers require.
+ */
+pub fn truncate(
+    messages: &mut Vec<MessageDTO>,
+    strategy: TruncationStrategy,
+    budget: Option<u32>,
+    language: Option<&str>,
+) {
+    let budget = match (strategy, budget) {
+        (TruncationStrategy::DropOldest, _) | (_, None) => return,
+        (_, Some(budget)) => budget,
+    };
+    // Instructions come first in prompts
+    let kept = match strategy {
+        TruncationStrategy::KeepSystemAndRecent => messages
+            .iter()
+            .take_while(|message| Roles::from(message.role) == Roles::System)
+            .count(),
+        _ => 0,
+    };
+    let mut start = kept;
+    let fits = |start: usize| {
+        let tokens = limits::estimate_tokens(&messages[..kept], language)
+            + limits::estimate_tokens(&messages[start..], language);
+        tokens <= budget
+    };
+    while start + 1 < messages.len()
+        && (!fits(start) || (start > kept && Roles::from(messages[start].role) != Roles::User))
+    {
+        start += 1;
+    }
+    if start > kept {
+        log::info!(
+            "Dropped {} messages of the prompt to fit {} tokens",
+            start - kept,
+            budget
+        );
+        messages.drain(kept..start);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::llm::context::text_message;
+
+    fn prompt() -> Vec<MessageDTO> {
+        vec![
+            text_message(Roles::System, "Be brief".to_string()),
+            text_message(Roles::User, "a".repeat(400)),
+            text_message(Roles::Bot, "b".repeat(400)),
+            text_message(Roles::User, "c".repeat(40)),
+            text_message(Roles::Bot, "d".repeat(40)),
+            text_message(Roles::User, "e".repeat(40)),
+        ]
+    }
+
+    fn texts(messages: &[MessageDTO]) -> Vec<char> {
+        messages
+            .iter()
+            .map(|message| message.content[0].data.chars().next().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_from_options() {
+        assert_eq!(
+            TruncationStrategy::SlidingWindow,
+            TruncationStrategy::from_options(r#"{"truncation":"slidingWindow"}"#)
+        );
+        assert_eq!(
+            TruncationStrategy::KeepSystemAndRecent,
+            TruncationStrategy::from_options(r#"{"truncation":"keepSystemAndRecent"}"#)
+        );
+        assert_eq!(
+            TruncationStrategy::DropOldest,
+            TruncationStrategy::from_options(r#"{"truncation":"dropOldest"}"#)
+        );
+        assert_eq!(
+            TruncationStrategy::KeepSystemAndRecent,
+            TruncationStrategy::from_options(r#"{"truncation":"unknown"}"#)
+        );
+        assert_eq!(
+            TruncationStrategy::KeepSystemAndRecent,
+            TruncationStrategy::from_options("{}")
+        );
+    }
+
+    #[test]
+    fn test_prompt_budget() {
+        let limits = ModelLimits {
+            context_length: Some(8_192),
+            max_output_tokens: Some(1_024),
+        };
+        assert_eq!(Some(7_168), prompt_budget(limits, 4_096));
+        assert_eq!(Some(7_936), prompt_budget(limits, 256));
+        assert_eq!(None, prompt_budget(ModelLimits::default(), 256));
+    }
+
+    #[test]
+    fn test_truncate() {
+        let mut messages = prompt();
+        truncate(
+            &mut messages,
+            TruncationStrategy::DropOldest,
+            Some(10),
+            None,
+        );
+        assert_eq!(6, messages.len());
+
+        let mut messages = prompt();
+        truncate(&mut messages, TruncationStrategy::SlidingWindow, None, None);
+        assert_eq!(6, messages.len());
+
+        // Everything fits
+        let mut messages = prompt();
+        truncate(
+            &mut messages,
+            TruncationStrategy::SlidingWindow,
+            Some(1_000),
+            None,
+        );
+        assert_eq!(6, messages.len());
+
+        // The long first turn doesn't fit, the history starts with the next user message
+        let mut messages = prompt();
+        truncate(
+            &mut messages,
+            TruncationStrategy::SlidingWindow,
+            Some(100),
+            None,
+        );
+        assert_eq!(vec!['c', 'd', 'e'], texts(&messages));
+
+        let mut messages = prompt();
+        truncate(
+            &mut messages,
+            TruncationStrategy::KeepSystemAndRecent,
+            Some(100),
+            None,
+        );
+        assert_eq!(vec!['B', 'c', 'd', 'e'], texts(&messages));
+
+        // The message replied to is kept even when it doesn't fit
+        let mut messages = prompt();
+        truncate(
+            &mut messages,
+            TruncationStrategy::KeepSystemAndRecent,
+            Some(1),
+            None,
+        );
+        assert_eq!(vec!['B', 'e'], texts(&messages));
+    }
+}
@@ -2,7 +2,7 @@ use async_openai::config::{AzureConfig, OpenAIConfig};
 use serde::Deserialize;
 
 use super::providers::{
-        claude::config::ClaudeConfig, deepseek::config::DeepseekConfig, google::config::GoogleConfig, ollama::config::OllamaConfig, xai::config::XaiConfig
+        aggregator::config::AggregatorConfig, bedrock::config::{AwsCredentials, BedrockConfig, DEFAULT_BEDROCK_REGION}, claude::config::ClaudeConfig, cohere::config::CohereConfig, custom::config::CustomConfig, deepseek::config::DeepseekConfig, google::config::GoogleConfig, ollama::config::OllamaConfig, xai::config::XaiConfig
     };
 
 #[derive(Debug, Deserialize)]
@@ -48,6 +48,34 @@ impl Into<OpenAIConfig> for RawOpenAIConfig {
     }
 }
 
+/// Config of an OpenAI compatible server
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RawCustomConfig {
+    pub api_key: Option<String>,
+    pub model: Option<String>,
+    pub endpoint: Option<String>,
+    /// Path of the chat completions API, relative to the endpoint
+    pub chat_path: Option<String>,
+}
+
+impl Into<CustomConfig> for RawCustomConfig {
+    fn into(self) -> CustomConfig {
+        let mut config = CustomConfig::new();
+        if let Some(api_key) = self.api_key {
+            config = config.with_api_key(api_key);
+        }
+        if let Some(endpoint) = self.endpoint.filter(|endpoint| !endpoint.is_empty()) {
+            config = config.with_api_base(endpoint);
+        }
+        if let Some(chat_path) = self.chat_path.filter(|chat_path| !chat_path.is_empty()) {
+            config = config.with_chat_path(chat_path);
+        }
+
+        config
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RawClaudeConfig {
@@ -70,6 +98,46 @@ impl Into<ClaudeConfig> for RawClaudeConfig {
     }
 }
 
+/// Together and Fireworks config. The endpoint defaults to the API of the host.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RawAggregatorConfig {
+    pub api_key: String,
+    pub model: Option<String>,
+    pub endpoint: Option<String>,
+}
+
+impl RawAggregatorConfig {
+    pub fn into_config(self, default_api_base: &str) -> AggregatorConfig {
+        let endpoint = self
+            .endpoint
+            .filter(|endpoint| !endpoint.is_empty())
+            .unwrap_or(default_api_base.to_string());
+        AggregatorConfig::new()
+            .with_api_base(endpoint)
+            .with_api_key(self.api_key)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RawCohereConfig {
+    pub api_key: String,
+    pub model: Option<String>,
+    pub endpoint: Option<String>,
+}
+
+impl Into<CohereConfig> for RawCohereConfig {
+    fn into(self) -> CohereConfig {
+        let mut config = CohereConfig::new().with_api_key(self.api_key);
+        if let Some(endpoint) = self.endpoint {
+            config = config.with_api_base(endpoint);
+        }
+
+        config
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RawOllamaConfig {
@@ -123,6 +191,36 @@ impl Into<XaiConfig> for RawXaiConfig {
     }
 }
 
+/// Bedrock config. The access key is read from the profile when not given.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RawBedrockConfig {
+    pub region: Option<String>,
+    pub profile: Option<String>,
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+    pub session_token: Option<String>,
+    pub model: Option<String>,
+}
+
+impl TryFrom<RawBedrockConfig> for BedrockConfig {
+    type Error = String;
+
+    fn try_from(raw: RawBedrockConfig) -> Result<BedrockConfig, String> {
+        let credentials = AwsCredentials::resolve(
+            raw.access_key_id,
+            raw.secret_access_key,
+            raw.session_token,
+            raw.profile,
+        )?;
+        let region = raw
+            .region
+            .filter(|region| !region.is_empty())
+            .unwrap_or(DEFAULT_BEDROCK_REGION.to_string());
+        Ok(BedrockConfig::new(region, credentials))
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RawGoogleConfig {
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use async_openai::{
     error::OpenAIError,
     types::{
@@ -12,12 +14,13 @@ use async_openai::{
 use entity::entities::{
     contents::ContentType,
     messages::{MessageDTO, Roles},
-    settings::ProxySetting,
+    settings::{ProxySetting, TimeoutSetting},
 };
 
 use crate::{log_utils::warn, services::cache};
 
-use super::providers::google::chat::{GoogleChatCompletionContent, GoogleChatCompletionContentPart, GoogleChatCompletionContentPartFileData, GoogleRole};
+use super::providers::google::chat::{GoogleChatCompletionContent, GoogleChatCompletionContentPart, GoogleChatCompletionContentPartFileData, GoogleChatCompletionPart, GoogleRole};
+use super::proxy::build_proxy;
 
 pub fn sum_option(a: Option<u32>, b: Option<u32>) -> Option<u32> {
     match (a, b) {
@@ -129,8 +132,8 @@ pub fn message_to_google_request_message(message: MessageDTO) -> GoogleChatCompl
                     GoogleChatCompletionContentPart::Text(item.data)
                 }
             };
-            Ok(part)
-        }).collect::<Result<Vec<GoogleChatCompletionContentPart>, OpenAIError>>()
+            Ok(part.into())
+        }).collect::<Result<Vec<GoogleChatCompletionPart>, OpenAIError>>()
         .expect("Failed to build user message content");
     match message.role.into() {
         Roles::User => {
@@ -148,35 +151,24 @@ pub fn message_to_google_request_message(message: MessageDTO) -> GoogleChatCompl
     }
 }
 
-/// Build reqwest client with proxy
-pub fn build_http_client(proxy_setting: Option<ProxySetting>) -> reqwest::Client {
-    let proxy_option: Option<reqwest::Proxy> = if let Some(setting) = proxy_setting {
-        if setting.on {
-            let proxy_option = if setting.http && setting.https {
-                reqwest::Proxy::all(setting.server).ok()
-            } else if setting.http {
-                reqwest::Proxy::http(setting.server).ok()
-            } else {
-                reqwest::Proxy::https(setting.server).ok()
-            };
-            if let Some(proxy) = proxy_option {
-                if let (Some(username), Some(password)) = (setting.username, setting.password) {
-                    Some(proxy.basic_auth(username.as_str(), password.as_str()))
-                } else {
-                    Some(proxy.to_owned())
-                }
-            } else {
-                None
-            }
-        } else {
-            None
-        }
-    } else {
-        None
-    };
+/// Build reqwest client with proxy and timeouts
+pub fn build_http_client(
+    proxy_setting: Option<ProxySetting>,
+    timeouts: TimeoutSetting,
+) -> reqwest::Client {
     let mut http_client_builder = reqwest::Client::builder();
-    if let Some(proxy) = proxy_option {
-        http_client_builder = http_client_builder.proxy(proxy);
+    match proxy_setting.as_ref().map(build_proxy) {
+        Some(Ok(Some(proxy))) => http_client_builder = http_client_builder.proxy(proxy),
+        Some(Err(err)) => warn("build_http_client", format!("Ignoring proxy: {}", err)),
+        _ => {}
+    }
+    if let Some(seconds) = timeouts.connect {
+        http_client_builder =
+            http_client_builder.connect_timeout(Duration::from_secs(seconds.into()));
+    }
+    // A read timeout rather than a total one, so long streamed replies aren't cut off
+    if let Some(seconds) = timeouts.read {
+        http_client_builder = http_client_builder.read_timeout(Duration::from_secs(seconds.into()));
     }
     http_client_builder
         .build()
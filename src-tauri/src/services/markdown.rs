@@ -0,0 +1,474 @@
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use entity::entities::{
+    contents::ContentType,
+    messages::{MessageDTO, Roles},
+};
+use regex::Regex;
+use serde::Deserialize;
+
+use super::{db::Repository, llm::context::text_message};
+
+/// A fenced code block found in a message
+#[derive(Clone, Debug, PartialEq)]
+pub struct CodeBlock {
+    pub language: Option<String>,
+    /// File name given in the info string, like ```rust:src/main.rs or ```py title="app.py"
+    pub filename: Option<String>,
+    pub code: String,
+}
+
+/// File extensions of common languages, for code blocks saved without a file name
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("rust", "rs"),
+    ("python", "py"),
+    ("javascript", "js"),
+    ("typescript", "ts"),
+    ("bash", "sh"),
+    ("shell", "sh"),
+    ("zsh", "sh"),
+    ("kotlin", "kt"),
+    ("csharp", "cs"),
+    ("c++", "cpp"),
+    ("ruby", "rb"),
+    ("markdown", "md"),
+    ("text", "txt"),
+    ("plaintext", "txt"),
+];
+
+struct Fence {
+    marker: char,
+    length: usize,
+}
+
+// Parse a line opening or closing a fence: at most 3 spaces of indentation and 3 or more ` or ~
+fn parse_fence(line: &str) -> Option<(Fence, &str)> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+    let rest = &line[indent..];
+    let marker = rest.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let length = rest.chars().take_while(|c| *c == marker).count();
+    if length < 3 {
+        return None;
+    }
+    let info = rest[length..].trim();
+    // Backtick fences can't have backticks in their info string
+    if marker == '`' && info.contains('`') {
+        return None;
+    }
+    Some((Fence { marker, length }, info))
+}
+
+impl Fence {
+    fn is_closed_by(&self, line: &str) -> bool {
+        parse_fence(line).map_or(false, |(closing, info)| {
+            closing.marker == self.marker && closing.length >= self.length && info.is_empty()
+        })
+    }
+}
+
+// Extract the fenced code blocks of a markdown text, in order.
+// An unclosed block runs until the end of the text, like in CommonMark.
+pub fn extract_code_blocks(text: &str) -> Vec<CodeBlock> {
+    let mut blocks = vec![];
+    let mut current: Option<(Fence, (Option<String>, Option<String>), Vec<&str>)> = None;
+    for line in text.lines() {
+        match current.as_mut() {
+            None => {
+                if let Some((fence, info)) = parse_fence(line) {
+                    current = Some((fence, parse_info(info), vec![]));
+                }
+            }
+            Some((fence, _, lines)) => {
+                if fence.is_closed_by(line) {
+                    if let Some((_, (language, filename), lines)) = current.take() {
+                        blocks.push(CodeBlock {
+                            language,
+                            filename,
+                            code: lines.join("\n"),
+                        });
+                    }
+                } else {
+                    lines.push(line);
+                }
+            }
+        }
+    }
+    if let Some((_, (language, filename), lines)) = current {
+        blocks.push(CodeBlock {
+            language,
+            filename,
+            code: lines.join("\n"),
+        });
+    }
+    blocks
+}
+
+// Read the language and the file name of an info string. The file name is either
+// after a colon, as in rust:src/main.rs, a title=, file= or path= attribute,
+// or a word with an extension after the language.
+fn parse_info(info: &str) -> (Option<String>, Option<String>) {
+    let mut words = info.split_whitespace();
+    let (language, mut filename) = match words.next() {
+        Some(first) => match first.split_once(':') {
+            Some((language, filename)) => (language, Some(filename)),
+            None => (first, None),
+        },
+        None => return (None, None),
+    };
+    for word in words {
+        if filename.is_some() {
+            break;
+        }
+        filename = match word.split_once('=') {
+            Some((key, value)) if ["title", "file", "filename", "path"].contains(&key) => {
+                Some(value.trim_matches(|c| c == '"' || c == '\''))
+            }
+            None if word.contains('.') => Some(word),
+            _ => None,
+        };
+    }
+    let to_string = |text: &str| Some(text.to_string()).filter(|text| !text.is_empty());
+    (to_string(language), filename.and_then(to_string))
+}
+
+/**
+ * Write code blocks as files of a directory, returning their paths. Blocks without a
+ * file name, or with one leaving the directory, are saved as snippet-N with the extension
+ * of their language. Existing files are kept, the new ones get a numbered name instead.
+ */
+pub fn save_code_blocks(blocks: &[CodeBlock], directory: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut paths = vec![];
+    for (index, block) in blocks.iter().enumerate() {
+        let relative = block
+            .filename
+            .as_deref()
+            .and_then(relative_path)
+            .unwrap_or_else(|| PathBuf::from(default_filename(block, index)));
+        let path = unique_path(directory.join(relative));
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|err| {
+                format!("Failed to create directory {}: {}", parent.display(), err)
+            })?;
+        }
+        fs::write(&path, format!("{}\n", block.code))
+            .map_err(|err| format!("Failed to write {}: {}", path.display(), err))?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+// Only paths staying inside the directory are accepted, as file names come from the model
+fn relative_path(filename: &str) -> Option<PathBuf> {
+    let path = PathBuf::from(filename);
+    let is_relative = path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    if is_relative && path.file_name().is_some() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+fn default_filename(block: &CodeBlock, index: usize) -> String {
+    let language = block.language.as_deref().unwrap_or("txt").to_lowercase();
+    let extension: String = EXTENSIONS
+        .iter()
+        .find(|(name, _)| *name == language)
+        .map_or(language.as_str(), |(_, extension)| extension)
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect();
+    if extension.is_empty() {
+        format!("snippet-{}.txt", index + 1)
+    } else {
+        format!("snippet-{}.{}", index + 1, extension)
+    }
+}
+
+// Add a number to the file name until it doesn't exist
+fn unique_path(path: PathBuf) -> PathBuf {
+    if !path.exists() {
+        return path;
+    }
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+    (1..)
+        .map(|number| path.with_file_name(format!("{}-{}{}", stem, number, extension)))
+        .find(|candidate| !candidate.exists())
+        .unwrap_or(path)
+}
+
+fn role_title(role: i32) -> &'static str {
+    match Roles::from(role) {
+        Roles::User => "User",
+        Roles::Bot => "Assistant",
+        Roles::System => "System",
+    }
+}
+
+// Render the message as markdown, with a placeholder for every image
+pub fn message_to_markdown(message: &MessageDTO) -> String {
+    message
+        .content
+        .iter()
+        .map(|content| match content.r#type {
+            ContentType::Text => content.data.clone(),
+            ContentType::Image => "*[image]*".to_string(),
+        })
+        .collect::<Vec<String>>()
+        .join("\n\n")
+}
+
+// Render a whole conversation as a markdown document
+pub fn conversation_to_markdown(subject: &str, messages: &[MessageDTO]) -> String {
+    let mut result = format!("# {}\n", subject.trim());
+    for message in messages {
+        result.push_str(&format!(
+            "\n## {}\n\n{}\n",
+            role_title(message.role),
+            message_to_markdown(message).trim()
+        ));
+    }
+    result
+}
+
+/**
+ * Render a stored conversation, including its system message, as a markdown document
+ */
+pub async fn export_conversation(
+    repo: &Repository,
+    conversation_id: i32,
+) -> Result<String, String> {
+    let conversation = repo.get_conversation_details(conversation_id).await?;
+    let mut messages = repo.list_messages(conversation_id).await?;
+    if let Some(sys_message) = repo.get_system_message(conversation_id).await? {
+        messages.insert(0, sys_message);
+    }
+    Ok(conversation_to_markdown(&conversation.subject, &messages))
+}
+
+/// Regex patterns of the lines starting a message of each role, in a transcript.
+/// The text following the marker on the same line is the start of the message.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscriptPatterns {
+    pub user: String,
+    pub assistant: String,
+    pub system: String,
+}
+
+impl Default for TranscriptPatterns {
+    // `**User:**` and `**User**:` markers, and the `## User` headings of exported conversations
+    fn default() -> Self {
+        TranscriptPatterns {
+            user: r"(?i)^\s*(?:\*\*(?:user|you|human|me):\*\*|\*\*(?:user|you|human|me)\*\*:|#{2,3}\s+(?:user|you|human)\s*$)".to_string(),
+            assistant: r"(?i)^\s*(?:\*\*(?:assistant|ai|bot|chatgpt|claude):\*\*|\*\*(?:assistant|ai|bot|chatgpt|claude)\*\*:|#{2,3}\s+assistant\s*$)".to_string(),
+            system: r"(?i)^\s*(?:\*\*system:\*\*|\*\*system\*\*:|#{2,3}\s+system\s*$)".to_string(),
+        }
+    }
+}
+
+/// A conversation read from a markdown transcript
+#[derive(Clone, Debug)]
+pub struct Transcript {
+    /// The `# Title` before the first message, if any
+    pub subject: Option<String>,
+    pub messages: Vec<MessageDTO>,
+}
+
+/**
+ * Split a markdown transcript into messages at the lines matching the patterns of a role.
+ * Markers inside fenced code blocks are ignored, as is any text before the first marker
+ * except a `# Title`, which becomes the subject.
+ */
+pub fn parse_transcript(text: &str, patterns: &TranscriptPatterns) -> Result<Transcript, String> {
+    let compile = |name: &str, pattern: &str| {
+        Regex::new(pattern).map_err(|err| format!("Invalid pattern for {} messages: {}", name, err))
+    };
+    let markers = vec![
+        (Roles::User, compile("user", &patterns.user)?),
+        (Roles::Bot, compile("assistant", &patterns.assistant)?),
+        (Roles::System, compile("system", &patterns.system)?),
+    ];
+    let mut subject = None;
+    let mut messages = vec![];
+    let mut current: Option<(Roles, Vec<&str>)> = None;
+    let mut fence: Option<Fence> = None;
+    for line in text.lines() {
+        if let Some(open) = fence.as_ref() {
+            if open.is_closed_by(line) {
+                fence = None;
+            }
+        } else if let Some((role, regex)) = markers.iter().find(|(_, regex)| regex.is_match(line)) {
+            if let Some((role, lines)) = current.take() {
+                messages.push(text_message(role, lines.join("\n").trim().to_string()));
+            }
+            let end = regex.find(line).map_or(0, |found| found.end());
+            current = Some((role.clone(), vec![line[end..].trim_start()]));
+            continue;
+        } else if let Some((opening, _)) = parse_fence(line) {
+            fence = Some(opening);
+        }
+        match current.as_mut() {
+            Some((_, lines)) => lines.push(line),
+            None => {
+                let is_title = subject.is_none() && fence.is_none();
+                if let Some(title) = line.strip_prefix("# ").filter(|_| is_title) {
+                    subject = Some(title.trim().to_string());
+                }
+            }
+        }
+    }
+    if let Some((role, lines)) = current {
+        messages.push(text_message(role, lines.join("\n").trim().to_string()));
+    }
+    messages.retain(|message| message.get_text().map_or(false, |text| !text.is_empty()));
+    if messages.is_empty() {
+        return Err("No messages found in the transcript".to_string());
+    }
+    Ok(Transcript { subject, messages })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_code_blocks() {
+        let text = "Intro\n```rust\nfn main() {}\n```\ntext\n~~~\nplain\n\nlines\n~~~\n````md\n```js\nnested\n```\n````";
+        assert_eq!(
+            vec![
+                CodeBlock {
+                    language: Some("rust".to_string()),
+                    filename: None,
+                    code: "fn main() {}".to_string(),
+                },
+                CodeBlock {
+                    language: None,
+                    filename: None,
+                    code: "plain\n\nlines".to_string(),
+                },
+                CodeBlock {
+                    language: Some("md".to_string()),
+                    filename: None,
+                    code: "```js\nnested\n```".to_string(),
+                },
+            ],
+            extract_code_blocks(text)
+        );
+    }
+
+    #[test]
+    fn test_extract_unclosed_code_block() {
+        assert_eq!(
+            vec![CodeBlock {
+                language: Some("py".to_string()),
+                filename: None,
+                code: "print(1)".to_string(),
+            }],
+            extract_code_blocks("``` py\nprint(1)")
+        );
+        assert!(extract_code_blocks("no code, only `inline`").is_empty());
+    }
+
+    #[test]
+    fn test_parse_info() {
+        let info = |language: &str, filename: Option<&str>| {
+            (
+                Some(language.to_string()),
+                filename.map(|filename| filename.to_string()),
+            )
+        };
+        assert_eq!(
+            info("rust", Some("src/main.rs")),
+            parse_info("rust:src/main.rs")
+        );
+        assert_eq!(
+            info("py", Some("app.py")),
+            parse_info(r#"py title="app.py""#)
+        );
+        assert_eq!(info("js", Some("index.js")), parse_info("js index.js"));
+        assert_eq!(info("sh", None), parse_info("sh {linenos}"));
+        assert_eq!((None, None), parse_info(""));
+    }
+
+    #[test]
+    fn test_save_code_blocks() {
+        let directory = std::env::temp_dir().join("kaas-test-save-code-blocks");
+        let _ = fs::remove_dir_all(&directory);
+        let blocks = extract_code_blocks(
+            "```rust:src/main.rs\nfn main() {}\n```\n```python\nprint(1)\n```\n```sh ../../evil.sh\nrm -rf /\n```\n```rust:src/main.rs\n// again\n```",
+        );
+        let paths = save_code_blocks(&blocks, &directory).unwrap();
+        assert_eq!(
+            vec![
+                directory.join("src/main.rs"),
+                directory.join("snippet-2.py"),
+                directory.join("snippet-3.sh"),
+                directory.join("src/main-1.rs"),
+            ],
+            paths
+        );
+        assert_eq!("fn main() {}\n", fs::read_to_string(&paths[0]).unwrap());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_parse_transcript() {
+        let text = "# Rust questions\nNotes from yesterday\n\n**User:** How do I print?\n\n**Assistant:**\nLike this:\n```md\n**User:** not a marker\n```\n**You**: Thanks";
+        let transcript = parse_transcript(text, &TranscriptPatterns::default()).unwrap();
+        assert_eq!(Some("Rust questions".to_string()), transcript.subject);
+        let messages: Vec<(i32, String)> = transcript
+            .messages
+            .iter()
+            .map(|message| (message.role, message.get_text().unwrap_or_default()))
+            .collect();
+        assert_eq!(
+            vec![
+                (0, "How do I print?".to_string()),
+                (
+                    1,
+                    "Like this:\n```md\n**User:** not a marker\n```".to_string()
+                ),
+                (0, "Thanks".to_string()),
+            ],
+            messages
+        );
+    }
+
+    #[test]
+    fn test_parse_transcript_patterns() {
+        let patterns = TranscriptPatterns {
+            user: "^Q:".to_string(),
+            assistant: "^A:".to_string(),
+            system: "^S:".to_string(),
+        };
+        let transcript = parse_transcript("Q: 1 + 1?\nA: 2\nQ:", &patterns).unwrap();
+        assert_eq!(None, transcript.subject);
+        assert_eq!(2, transcript.messages.len());
+        // Exported conversations can be imported back
+        let exported = conversation_to_markdown("Math", &transcript.messages);
+        let transcript = parse_transcript(&exported, &TranscriptPatterns::default()).unwrap();
+        assert_eq!(Some("Math".to_string()), transcript.subject);
+        assert_eq!(Some("2".to_string()), transcript.messages[1].get_text());
+        assert!(parse_transcript("no markers", &patterns).is_err());
+        let invalid = TranscriptPatterns {
+            user: "(".to_string(),
+            ..patterns
+        };
+        assert!(parse_transcript("Q: hi", &invalid).is_err());
+    }
+}
@@ -1,3 +1,21 @@
+pub mod assistants;
+pub mod batch;
+pub mod bootstrap;
+pub mod branches;
 pub mod cache;
+pub mod collections;
+pub mod cost_guard;
+pub mod csv_import;
 pub mod db;
+pub mod evals;
+pub mod finetune;
+pub mod finetune_jobs;
+pub mod generation;
+pub mod gist;
 pub mod llm;
+pub mod markdown;
+pub mod privacy;
+pub mod provider_files;
+pub mod response_cache;
+pub mod search;
+pub mod usage;
@@ -0,0 +1,212 @@
+use entity::entities::{contents::ContentType, messages::MessageDTO};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+// Longest placeholder the stream restorer waits for, like [API_KEY_12345]
+const MAX_PLACEHOLDER_LEN: usize = 24;
+
+static BUILTIN_PATTERNS: Lazy<Vec<(&'static str, Regex)>> = Lazy::new(|| {
+    vec![
+        // Checked first, so keys aren't partially taken for phone numbers
+        (
+            "API_KEY",
+            Regex::new(
+                r"\b(?:(?:sk|xai|gsk)-[A-Za-z0-9_\-]{16,}|AIza[0-9A-Za-z_\-]{35}|AKIA[0-9A-Z]{16}|gh[pousr]_[A-Za-z0-9]{36})",
+            )
+            .unwrap(),
+        ),
+        (
+            "EMAIL",
+            Regex::new(r"\b[A-Za-z0-9._%+\-]+@[A-Za-z0-9.\-]+\.[A-Za-z]{2,}\b").unwrap(),
+        ),
+        (
+            "PHONE",
+            Regex::new(r"(?:\+\d{1,3}[\s.\-]?)?(?:\(\d{2,4}\)|\b\d{2,4})[\s.\-]?\d{3,4}[\s.\-]?\d{3,4}\b")
+                .unwrap(),
+        ),
+    ]
+});
+
+static PLACEHOLDER: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[[A-Z_]+_\d+\]").unwrap());
+
+/// Replaces personal data with placeholders before a request leaves the machine,
+/// and puts the original values back into the reply
+#[derive(Clone, Debug)]
+pub struct PrivacyFilter {
+    custom_patterns: Vec<Regex>,
+    /// Placeholder and the original value it stands for
+    replacements: Vec<(String, String)>,
+    /// Streamed text held back because it may end with an incomplete placeholder
+    pending: String,
+}
+
+impl PrivacyFilter {
+    /// Build a filter with the built-in detectors and the user's own regex patterns.
+    /// Invalid patterns are skipped.
+    pub fn new(custom_patterns: &[String]) -> Self {
+        let custom_patterns = custom_patterns
+            .iter()
+            .filter_map(|pattern| match Regex::new(pattern) {
+                Ok(regex) => Some(regex),
+                Err(err) => {
+                    log::warn!("Ignoring invalid redaction pattern {}: {}", pattern, err);
+                    None
+                }
+            })
+            .collect();
+        PrivacyFilter {
+            custom_patterns,
+            replacements: vec![],
+            pending: String::new(),
+        }
+    }
+
+    /// Replace detected values with placeholders. The same value always gets the same placeholder.
+    pub fn redact(&mut self, text: &str) -> String {
+        // All patterns search the original text in one pass, a match overlapping an earlier
+        // one being skipped, so no pattern rewrites a placeholder or a part of another value.
+        // Placeholders already in the text are kept as they are.
+        let mut matches: Vec<(usize, usize, Option<&'static str>)> = PLACEHOLDER
+            .find_iter(text)
+            .map(|found| (found.start(), found.end(), None))
+            .collect();
+        let patterns = BUILTIN_PATTERNS
+            .iter()
+            .map(|(kind, regex)| (*kind, regex))
+            .chain(self.custom_patterns.iter().map(|regex| ("REDACTED", regex)));
+        for (kind, regex) in patterns {
+            for found in regex.find_iter(text) {
+                let overlaps = matches
+                    .iter()
+                    .any(|(start, end, _)| found.start() < *end && *start < found.end());
+                if !found.is_empty() && !overlaps {
+                    matches.push((found.start(), found.end(), Some(kind)));
+                }
+            }
+        }
+        matches.sort_by_key(|(start, _, _)| *start);
+        let mut result = String::with_capacity(text.len());
+        let mut last_end = 0;
+        for (start, end, kind) in matches {
+            if let Some(kind) = kind {
+                result.push_str(&text[last_end..start]);
+                result.push_str(&self.placeholder_for(kind, &text[start..end]));
+                last_end = end;
+            }
+        }
+        result.push_str(&text[last_end..]);
+        result
+    }
+
+    fn placeholder_for(&mut self, kind: &str, value: &str) -> String {
+        if let Some((placeholder, _)) = self
+            .replacements
+            .iter()
+            .find(|(_, original)| original == value)
+        {
+            return placeholder.clone();
+        }
+        let count = self
+            .replacements
+            .iter()
+            .filter(|(placeholder, _)| placeholder.starts_with(&format!("[{}_", kind)))
+            .count();
+        let placeholder = format!("[{}_{}]", kind, count + 1);
+        self.replacements
+            .push((placeholder.clone(), value.to_string()));
+        placeholder
+    }
+
+    /// Redact the text contents of messages, leaving other contents untouched
+    pub fn redact_messages(&mut self, messages: &mut [MessageDTO]) {
+        for message in messages.iter_mut() {
+            for content in message.content.iter_mut() {
+                if content.r#type == ContentType::Text {
+                    content.data = self.redact(&content.data);
+                }
+            }
+        }
+    }
+
+    /// Put the original values back in place of the placeholders
+    pub fn restore(&self, text: &str) -> String {
+        PLACEHOLDER
+            .replace_all(text, |captures: &regex::Captures| {
+                let placeholder = &captures[0];
+                self.replacements
+                    .iter()
+                    .find(|(known, _)| known == placeholder)
+                    .map(|(_, original)| original.clone())
+                    .unwrap_or_else(|| placeholder.to_string())
+            })
+            .into_owned()
+    }
+
+    /// Restore a streamed chunk. A placeholder can be split across chunks,
+    /// so a trailing incomplete one is kept until the next chunk or flush.
+    pub fn restore_chunk(&mut self, chunk: &str) -> String {
+        self.pending.push_str(chunk);
+        let split = match self.pending.rfind('[') {
+            Some(start)
+                if !self.pending[start..].contains(']')
+                    && self.pending.len() - start < MAX_PLACEHOLDER_LEN =>
+            {
+                start
+            }
+            _ => self.pending.len(),
+        };
+        let ready: String = self.pending.drain(..split).collect();
+        self.restore(&ready)
+    }
+
+    /// Restore whatever is left once the stream is over
+    pub fn flush(&mut self) -> String {
+        let rest = std::mem::take(&mut self.pending);
+        self.restore(&rest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_and_restore() {
+        let mut filter = PrivacyFilter::new(&[r"ACME-\d+".to_string(), "(".to_string()]);
+        let text = "Mail jane.doe@example.com or john@example.org, call +1 415 555 0100. \
+            Key sk-abcdefghijklmnop1234, ticket ACME-42, again jane.doe@example.com";
+        let redacted = filter.redact(text);
+        assert_eq!(
+            "Mail [EMAIL_1] or [EMAIL_2], call [PHONE_1]. \
+            Key [API_KEY_1], ticket [REDACTED_1], again [EMAIL_1]",
+            redacted
+        );
+        assert_eq!(text, filter.restore(&redacted));
+        // Unknown placeholders are left as they are
+        assert_eq!("[EMAIL_9]", filter.restore("[EMAIL_9]"));
+    }
+
+    #[test]
+    fn test_custom_patterns_keep_placeholders() {
+        let mut filter = PrivacyFilter::new(&[r"\d+".to_string(), "EMAIL".to_string()]);
+        let text = "Mail jane.doe@example.com about order 1234";
+        let redacted = filter.redact(text);
+        assert_eq!("Mail [EMAIL_1] about order [REDACTED_1]", redacted);
+        assert_eq!(text, filter.restore(&redacted));
+    }
+
+    #[test]
+    fn test_restore_chunks() {
+        let mut filter = PrivacyFilter::new(&[]);
+        filter.redact("jane.doe@example.com");
+        let mut restored = String::new();
+        for chunk in ["Write to [EM", "AIL_1", "] today [", "see notes]", " [EMA"] {
+            restored.push_str(&filter.restore_chunk(chunk));
+        }
+        restored.push_str(&filter.flush());
+        assert_eq!(
+            "Write to jane.doe@example.com today [see notes] [EMA",
+            restored
+        );
+    }
+}
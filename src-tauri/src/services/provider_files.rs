@@ -0,0 +1,101 @@
+use async_openai::types::{CreateFileRequest, FileInput, FilePurpose, OpenAIFile};
+use serde::Serialize;
+
+use super::{db::Repository, llm::client::openai_client};
+
+/// A file stored with the provider of a model, e.g. for batches, assistants or fine-tuning
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderFile {
+    pub id: String,
+    pub filename: String,
+    pub bytes: u64,
+    pub purpose: String,
+    /// Unix timestamp in seconds
+    pub created_at: u64,
+}
+
+impl From<OpenAIFile> for ProviderFile {
+    fn from(file: OpenAIFile) -> Self {
+        // Keep the name of the purpose used by the API, e.g. "fine-tune"
+        let purpose = serde_json::to_value(&file.purpose)
+            .ok()
+            .and_then(|value| value.as_str().map(String::from))
+            .unwrap_or_default();
+        ProviderFile {
+            id: file.id,
+            filename: file.filename,
+            bytes: file.bytes as u64,
+            purpose,
+            created_at: file.created_at as u64,
+        }
+    }
+}
+
+fn parse_purpose(purpose: &str) -> Result<FilePurpose, String> {
+    match purpose {
+        "assistants" => Ok(FilePurpose::Assistants),
+        "batch" => Ok(FilePurpose::Batch),
+        "fine-tune" => Ok(FilePurpose::FineTune),
+        "vision" => Ok(FilePurpose::Vision),
+        _ => Err(format!("Unknown file purpose: {}", purpose)),
+    }
+}
+
+/**
+ * List the files stored with the provider of the model, newest first
+ */
+pub async fn list_files(repo: &Repository, model_id: i32) -> Result<Vec<ProviderFile>, String> {
+    let (client, _) = openai_client(repo, model_id).await?;
+    let response = client
+        .files()
+        .list(&[("limit", "10000")])
+        .await
+        .map_err(|err| format!("Failed to list files: {}", err))?;
+    let mut files: Vec<ProviderFile> = response.data.into_iter().map(Into::into).collect();
+    files.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    Ok(files)
+}
+
+pub async fn upload_file(
+    repo: &Repository,
+    model_id: i32,
+    path: String,
+    purpose: String,
+) -> Result<ProviderFile, String> {
+    let purpose = parse_purpose(&purpose)?;
+    let (client, _) = openai_client(repo, model_id).await?;
+    let file = client
+        .files()
+        .create(CreateFileRequest {
+            file: FileInput::from(path.as_str()),
+            purpose,
+        })
+        .await
+        .map_err(|err| format!("Failed to upload {}: {}", path, err))?;
+    log::info!("File {} uploaded as {}", path, file.id);
+    Ok(file.into())
+}
+
+pub async fn delete_file(repo: &Repository, model_id: i32, file_id: String) -> Result<(), String> {
+    let (client, _) = openai_client(repo, model_id).await?;
+    client
+        .files()
+        .delete(&file_id)
+        .await
+        .map_err(|err| format!("Failed to delete file {}: {}", file_id, err))?;
+    log::info!("File {} deleted", file_id);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_purpose() {
+        assert_eq!(Ok(FilePurpose::Batch), parse_purpose("batch"));
+        assert_eq!(Ok(FilePurpose::FineTune), parse_purpose("fine-tune"));
+        assert!(parse_purpose("finetune").is_err());
+    }
+}
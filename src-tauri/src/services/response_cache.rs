@@ -0,0 +1,121 @@
+use entity::entities::settings::SETTING_MODELS_CACHE_TTL;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use super::{
+    db::Repository,
+    llm::{chat::BotReply, context::ChatContext},
+};
+
+/**
+ * The cache key of the request a context is about to send, or None when the reply
+ * shouldn't be cached: the cache is turned off, the reply is regenerated, so the user
+ * wants a new one, the conversation opted out with the noCache option, or personal data
+ * is redacted, so replies hold placeholders of this conversation only.
+ */
+pub async fn cache_key_for(repo: &Repository, ctx: &ChatContext) -> Option<String> {
+    if ctx.regenerating || get_ttl_setting(repo).await == 0 || ctx.privacy_filter.is_some() {
+        return None;
+    }
+    let options: serde_json::Value = serde_json::from_str(&ctx.options.options).ok()?;
+    if options["noCache"].as_bool().unwrap_or(false) {
+        return None;
+    }
+    Some(cache_key(ctx))
+}
+
+/**
+ * The reply cached for the key, if it is not older than the TTL
+ */
+pub async fn lookup(repo: &Repository, key: &str) -> Option<BotReply> {
+    let ttl = get_ttl_setting(repo).await;
+    let since = chrono::Local::now() - chrono::Duration::minutes(ttl as i64);
+    match repo.get_cached_reply(key, since).await {
+        Ok(cached) => cached.map(|cached| BotReply {
+            message: cached.message,
+            ..Default::default()
+        }),
+        Err(err) => {
+            log::warn!("{}", err);
+            None
+        }
+    }
+}
+
+pub async fn store(repo: &Repository, key: String, message: String) {
+    if let Err(err) = repo.put_cached_reply(key, message).await {
+        log::warn!("{}", err);
+    }
+}
+
+/**
+ * Delete the replies older than the TTL. Run periodically by the job scheduler.
+ */
+pub async fn prune(repo: &Repository) -> Result<(), String> {
+    let ttl = get_ttl_setting(repo).await;
+    let before = chrono::Local::now() - chrono::Duration::minutes(ttl as i64);
+    let count = repo.prune_response_cache(before).await?;
+    if count > 0 {
+        log::info!("Pruned {} cached replies", count);
+    }
+    Ok(())
+}
+
+async fn get_ttl_setting(repo: &Repository) -> u32 {
+    repo.get_setting(SETTING_MODELS_CACHE_TTL)
+        .await
+        .and_then(|setting| setting.value.parse::<u32>().ok())
+        .unwrap_or(0)
+}
+
+// Hash everything sent to the provider. Ids and dates of the messages are left out,
+// so the same prompt sent in another conversation hits the cache too.
+fn cache_key(ctx: &ChatContext) -> String {
+    let messages: Vec<serde_json::Value> = ctx
+        .messages
+        .iter()
+        .map(|message| json!({ "role": message.role, "content": message.content }))
+        .collect();
+    let payload = json!({
+        "provider": ctx.config.provider,
+        "config": ctx.config.config,
+        "options": ctx.options.options,
+        "maxTokens": ctx.max_token_setting,
+        "responseSchema": ctx.response_schema.as_ref().map(|schema| &schema.schema),
+        "messages": messages,
+    })
+    .to_string();
+    // Unlike DefaultHasher, SHA-256 is stable across Rust versions, so the keys
+    // of the cached replies stay valid after an update
+    Sha256::digest(payload.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use entity::entities::messages::Roles;
+
+    use crate::services::llm::context::text_message;
+
+    use super::*;
+
+    fn context(prompt: &str) -> ChatContext {
+        let messages = vec![text_message(Roles::User, prompt.to_string())];
+        ChatContext::for_test("gpt-4o-mini", "{}", messages)
+    }
+
+    #[test]
+    fn test_cache_key() {
+        let key = cache_key(&context("Hello"));
+        assert_eq!(64, key.len());
+        let mut other_conversation = context("Hello");
+        other_conversation.messages[0].conversation_id = 42;
+        assert_eq!(key, cache_key(&other_conversation));
+        assert_ne!(key, cache_key(&context("Hello!")));
+        let mut other_options = context("Hello");
+        other_options.options.options = r#"{"temperature":0.2}"#.to_string();
+        assert_ne!(key, cache_key(&other_options));
+    }
+}
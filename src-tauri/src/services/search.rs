@@ -0,0 +1,206 @@
+use serde::Serialize;
+
+const MATCH_SCORE: i64 = 1;
+const CONSECUTIVE_BONUS: i64 = 5;
+const WORD_START_BONUS: i64 = 8;
+const PREFIX_BONUS: i64 = 10;
+const EXACT_BONUS: i64 = 20;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PaletteItemKind {
+    Conversation,
+    Prompt,
+    Model,
+    Setting,
+}
+
+/// An entry of the command palette
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaletteItem {
+    pub kind: PaletteItemKind,
+    /// Id of the conversation, prompt or model, or the key of the setting
+    pub id: String,
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subtitle: Option<String>,
+    pub score: i64,
+}
+
+// Score how well the query matches the target, ignoring case.
+// All characters of the query must appear in order; consecutive characters,
+// characters at the start of words and prefixes rank higher. Returns None on no match.
+pub fn fuzzy_score(query: &str, target: &str) -> Option<i64> {
+    let query: Vec<char> = query.trim().to_lowercase().chars().collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+    let target: Vec<char> = target.to_lowercase().chars().collect();
+    // Matching greedily from the first occurrence can miss a better match later on,
+    // so try every position the first character appears at
+    let best = (0..target.len())
+        .filter(|start| target[*start] == query[0])
+        .filter_map(|start| score_from(&query, &target, start))
+        .max()?;
+    let mut score = best;
+    if target.starts_with(&query) {
+        score += PREFIX_BONUS;
+    }
+    if target.len() == query.len() {
+        score += EXACT_BONUS;
+    }
+    Some(score)
+}
+
+fn score_from(query: &[char], target: &[char], start: usize) -> Option<i64> {
+    let mut score = 0;
+    let mut query_index = 0;
+    let mut last_match: Option<usize> = None;
+    for (index, c) in target.iter().enumerate().skip(start) {
+        if query_index == query.len() {
+            break;
+        }
+        if *c != query[query_index] {
+            continue;
+        }
+        score += MATCH_SCORE;
+        if last_match.map_or(false, |last| last + 1 == index) {
+            score += CONSECUTIVE_BONUS;
+        }
+        if index == 0 || !target[index - 1].is_alphanumeric() {
+            score += WORD_START_BONUS;
+        }
+        last_match = Some(index);
+        query_index += 1;
+    }
+    if query_index < query.len() {
+        None
+    } else {
+        Some(score)
+    }
+}
+
+/// Characters FTS5 wraps the matched terms of a snippet with
+pub const SNIPPET_MATCH_START: char = '\u{2}';
+pub const SNIPPET_MATCH_END: char = '\u{3}';
+
+/// Part of a snippet matching the query, in UTF-16 code units like JavaScript string indices
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HighlightRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A message matching a search within a conversation
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageMatch {
+    pub message_id: i32,
+    pub snippet: String,
+    pub highlights: Vec<HighlightRange>,
+}
+
+// Turn what the user typed into an FTS5 query matching messages with all the words.
+// Words are quoted so characters like " or - aren't read as query syntax, and the
+// last one matches as a prefix to find while typing. Returns None for a blank query.
+pub fn fts_query(query: &str) -> Option<String> {
+    let words: Vec<String> = query
+        .split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect();
+    if words.is_empty() {
+        return None;
+    }
+    Some(format!("{}*", words.join(" ")))
+}
+
+/// A message matching a search across all conversations
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversationMessageMatch {
+    pub conversation_id: i32,
+    pub conversation_subject: String,
+    #[serde(flatten)]
+    pub message: MessageMatch,
+}
+
+// Remove the match markers of an FTS5 snippet, recording where they were
+pub fn highlight_snippet(message_id: i32, raw: &str) -> MessageMatch {
+    let mut snippet = String::with_capacity(raw.len());
+    let mut highlights = vec![];
+    let mut position = 0;
+    let mut start = None;
+    for c in raw.chars() {
+        match c {
+            SNIPPET_MATCH_START => start = Some(position),
+            SNIPPET_MATCH_END => {
+                if let Some(start) = start.take() {
+                    highlights.push(HighlightRange {
+                        start,
+                        end: position,
+                    });
+                }
+            }
+            _ => {
+                snippet.push(c);
+                position += c.len_utf16();
+            }
+        }
+    }
+    MessageMatch {
+        message_id,
+        snippet,
+        highlights,
+    }
+}
+
+// Sort items from best to worst match and keep the first ones
+pub fn rank(mut items: Vec<PaletteItem>, limit: usize) -> Vec<PaletteItem> {
+    // The sort is stable, so equal scores keep the order of kinds they were collected in
+    items.sort_by(|a, b| b.score.cmp(&a.score));
+    items.truncate(limit);
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert_eq!(None, fuzzy_score("xyz", "Rust ownership"));
+        assert_eq!(None, fuzzy_score("tsur", "Rust"));
+        assert_eq!(Some(0), fuzzy_score(" ", "anything"));
+        // Prefix beats a match in the middle, which beats scattered characters
+        let prefix = fuzzy_score("rust", "Rust ownership").unwrap();
+        let middle = fuzzy_score("rust", "Learning rust").unwrap();
+        let scattered = fuzzy_score("rust", "Rewrite unit tests").unwrap();
+        assert!(prefix > middle);
+        assert!(middle > scattered);
+        assert!(fuzzy_score("gpt", "gpt").unwrap() > fuzzy_score("gpt", "gpt-4o").unwrap());
+    }
+
+    #[test]
+    fn test_fts_query() {
+        assert_eq!(None, fts_query("  "));
+        assert_eq!(
+            Some(r#""rust" "o""k"*"#.to_string()),
+            fts_query(r#" rust o"k "#)
+        );
+    }
+
+    #[test]
+    fn test_highlight_snippet() {
+        let result = highlight_snippet(7, "…the \u{2}café\u{3} 🦀 \u{2}rust\u{3}");
+        assert_eq!("…the café 🦀 rust", result.snippet);
+        assert_eq!(
+            vec![
+                HighlightRange { start: 5, end: 9 },
+                HighlightRange { start: 13, end: 17 },
+            ],
+            result.highlights
+        );
+    }
+}
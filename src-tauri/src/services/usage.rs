@@ -0,0 +1,201 @@
+use std::collections::{BTreeMap, HashMap};
+
+use entity::entities::model_prices::{Model as ModelPrice, TokenUsage};
+use serde::Serialize;
+
+use super::{db::Repository, llm::pricing};
+
+const TOKENS_PER_CUSTOM_PRICE_UNIT: f64 = 1_000.0;
+
+/// Tokens used by the replies of a day, a model or a conversation, and what they cost
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageGroup {
+    pub key: String,
+    pub label: String,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    /// In USD, for the tokens of models whose prices are known
+    pub estimated_cost: f64,
+    /// Tokens of models whose prices are unknown, left out of the cost
+    pub unpriced_tokens: i64,
+}
+
+impl UsageGroup {
+    fn add(&mut self, usage: &TokenUsage, cost: Option<f64>) {
+        self.prompt_tokens += usage.prompt_tokens;
+        self.completion_tokens += usage.completion_tokens;
+        match cost {
+            Some(cost) => self.estimated_cost += cost,
+            None => self.unpriced_tokens += usage.prompt_tokens + usage.completion_tokens,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageStats {
+    pub total: UsageGroup,
+    /// Oldest day first
+    pub by_day: Vec<UsageGroup>,
+    /// Most expensive first
+    pub by_model: Vec<UsageGroup>,
+    /// Most expensive first
+    pub by_conversation: Vec<UsageGroup>,
+}
+
+/// The price set by the user for a model, if any
+pub async fn get_model_price(repo: &Repository, model_id: i32) -> Option<ModelPrice> {
+    repo.list_model_prices()
+        .await
+        .ok()?
+        .into_iter()
+        .find(|price| price.model_id == model_id)
+}
+
+/**
+ * The cost of tokens of a model in USD, with the price set by the user or else the
+ * bundled one. None when the prices of the model are unknown.
+ */
+pub fn model_cost(
+    price: Option<&ModelPrice>,
+    model_config: &str,
+    prompt_tokens: u32,
+    completion_tokens: u32,
+) -> Option<f64> {
+    if let Some(price) = price {
+        return Some(
+            (prompt_tokens as f64 * price.input_price
+                + completion_tokens as f64 * price.output_price)
+                / TOKENS_PER_CUSTOM_PRICE_UNIT,
+        );
+    }
+    let model = pricing::model_name(model_config)?;
+    pricing::estimate_cost(&model, prompt_tokens, completion_tokens)
+}
+
+/// The cost of the tokens of a row in USD, None when the prices of its model are unknown
+fn cost(usage: &TokenUsage, prices: &HashMap<i32, &ModelPrice>) -> Option<f64> {
+    model_cost(
+        usage.model_id.and_then(|id| prices.get(&id)).copied(),
+        usage.model_config.as_deref().unwrap_or_default(),
+        u32::try_from(usage.prompt_tokens).unwrap_or(u32::MAX),
+        u32::try_from(usage.completion_tokens).unwrap_or(u32::MAX),
+    )
+}
+
+/// Group the tokens used per day, model and conversation, and estimate their cost with
+/// the prices set by the user or else the bundled ones
+pub fn aggregate(usage: &[TokenUsage], prices: &[ModelPrice]) -> UsageStats {
+    let prices: HashMap<i32, &ModelPrice> =
+        prices.iter().map(|price| (price.model_id, price)).collect();
+    let mut total = UsageGroup::default();
+    let mut by_day: BTreeMap<String, UsageGroup> = BTreeMap::new();
+    let mut by_model: HashMap<String, UsageGroup> = HashMap::new();
+    let mut by_conversation: HashMap<String, UsageGroup> = HashMap::new();
+    for row in usage {
+        let row_cost = cost(row, &prices);
+        total.add(row, row_cost);
+        by_day
+            .entry(row.day.clone())
+            .or_insert_with(|| group(row.day.clone(), row.day.clone()))
+            .add(row, row_cost);
+        let model_key = row.model_id.map(|id| id.to_string()).unwrap_or_default();
+        by_model
+            .entry(model_key.clone())
+            .or_insert_with(|| group(model_key, row.model_alias.clone().unwrap_or_default()))
+            .add(row, row_cost);
+        by_conversation
+            .entry(row.conversation_id.to_string())
+            .or_insert_with(|| {
+                group(
+                    row.conversation_id.to_string(),
+                    row.conversation_subject.clone(),
+                )
+            })
+            .add(row, row_cost);
+    }
+    UsageStats {
+        total,
+        by_day: by_day.into_values().collect(),
+        by_model: most_expensive_first(by_model),
+        by_conversation: most_expensive_first(by_conversation),
+    }
+}
+
+fn group(key: String, label: String) -> UsageGroup {
+    UsageGroup {
+        key,
+        label,
+        ..Default::default()
+    }
+}
+
+fn most_expensive_first(groups: HashMap<String, UsageGroup>) -> Vec<UsageGroup> {
+    let mut result: Vec<UsageGroup> = groups.into_values().collect();
+    result.sort_by(|a, b| {
+        b.estimated_cost
+            .total_cmp(&a.estimated_cost)
+            .then(
+                (b.prompt_tokens + b.completion_tokens)
+                    .cmp(&(a.prompt_tokens + a.completion_tokens)),
+            )
+            .then(a.key.cmp(&b.key))
+    });
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(day: &str, conversation_id: i32, model_id: i32, model: &str) -> TokenUsage {
+        TokenUsage {
+            day: day.to_string(),
+            conversation_id,
+            conversation_subject: format!("Conversation {}", conversation_id),
+            model_id: Some(model_id),
+            model_alias: Some(model.to_string()),
+            model_config: Some(format!(r#"{{"model":"{}"}}"#, model)),
+            prompt_tokens: 1_000_000,
+            completion_tokens: 100_000,
+        }
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let usage = vec![
+            usage("2026-10-01", 1, 1, "gpt-4o-mini"),
+            usage("2026-10-01", 2, 2, "my-local-model"),
+            usage("2026-10-02", 1, 1, "gpt-4o-mini"),
+        ];
+        let stats = aggregate(&usage, &[]);
+        assert_eq!(3_000_000, stats.total.prompt_tokens);
+        // 1M input tokens at $0.15 and 100k output tokens at $0.60 per million, twice
+        assert!((stats.total.estimated_cost - 0.42).abs() < 1e-9);
+        assert_eq!(1_100_000, stats.total.unpriced_tokens);
+        assert_eq!(
+            vec!["2026-10-01", "2026-10-02"],
+            stats
+                .by_day
+                .iter()
+                .map(|group| group.key.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!("gpt-4o-mini", stats.by_model[0].label);
+        assert_eq!("Conversation 1", stats.by_conversation[0].label);
+
+        // Prices set by the user are per 1k tokens and take precedence
+        let prices = vec![ModelPrice {
+            id: 1,
+            model_id: 2,
+            input_price: 0.001,
+            output_price: 0.002,
+            updated_at: chrono::Local::now(),
+        }];
+        let stats = aggregate(&usage, &prices);
+        assert_eq!(0, stats.total.unpriced_tokens);
+        assert_eq!("my-local-model", stats.by_model[0].label);
+        assert!((stats.by_model[0].estimated_cost - 1.2).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,40 @@
+use tauri::{App, AppHandle, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutEvent, ShortcutState};
+
+use crate::services::generation::GenerationManager;
+
+/// Panic button stopping every running bot call, e.g. an agent run going off the rails.
+/// Global, so it works while another app is focused.
+pub const SHORTCUT_STOP_ALL: &str = "CommandOrControl+Shift+Period";
+
+// Register the global shortcuts of the app
+pub fn init_shortcuts(app: &App) -> Result<(), String> {
+    let stop_all = SHORTCUT_STOP_ALL
+        .parse::<Shortcut>()
+        .map_err(|err| format!("Invalid shortcut {}: {}", SHORTCUT_STOP_ALL, err))?;
+    app.handle()
+        .plugin(
+            tauri_plugin_global_shortcut::Builder::new()
+                .with_handler(handle_shortcut)
+                .build(),
+        )
+        .map_err(|err| format!("Failed to initialize global shortcuts: {}", err))?;
+    // Fails when another app already uses the shortcut
+    app.global_shortcut()
+        .register(stop_all)
+        .map_err(|err| format!("Failed to register shortcut {}: {}", SHORTCUT_STOP_ALL, err))?;
+    Ok(())
+}
+
+fn handle_shortcut(app: &AppHandle, shortcut: &Shortcut, event: ShortcutEvent) {
+    if event.state() != ShortcutState::Pressed {
+        return;
+    }
+    let is_stop_all = SHORTCUT_STOP_ALL
+        .parse::<Shortcut>()
+        .map_or(false, |stop_all| stop_all.id() == shortcut.id());
+    if is_stop_all {
+        log::info!("Stop all shortcut pressed");
+        app.state::<GenerationManager>().stop_all();
+    }
+}
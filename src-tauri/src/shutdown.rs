@@ -0,0 +1,44 @@
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use tauri::{AppHandle, Manager, RunEvent};
+
+use crate::services::{db::Repository, generation::GenerationManager};
+
+/// How long the frontend is given to save the partial replies of the generations
+/// stopped on exit
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(2);
+
+/// Set once the generations were stopped, so the exit requested afterwards goes through
+static IS_SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+/**
+ * Exit without losing the latest messages: running generations are stopped first, so the
+ * frontend saves what was received of their replies, and the database pool is closed last,
+ * once the writes in progress are done.
+ */
+pub fn handle_run_event(app_handle: &AppHandle, event: RunEvent) {
+    match event {
+        RunEvent::ExitRequested { api, .. } => {
+            let generations = app_handle.state::<GenerationManager>();
+            if generations.active_count() == 0 || IS_SHUTTING_DOWN.swap(true, Ordering::SeqCst) {
+                return;
+            }
+            api.prevent_exit();
+            let stopped = generations.stop_all();
+            log::info!("Exit delayed to save {} stopped generations", stopped);
+            let app_handle = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                tokio::time::sleep(SHUTDOWN_GRACE).await;
+                app_handle.exit(0);
+            });
+        }
+        RunEvent::Exit => {
+            app_handle.state::<Repository>().shutdown();
+            log::info!("Shut down");
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,41 @@
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Url};
+
+use crate::{
+    background::ARG_BACKGROUND,
+    deep_link::{self, DEEP_LINK_SCHEME},
+    tray::show_main_window,
+};
+
+pub const EVENT_SECOND_INSTANCE: &str = "second-instance";
+
+/// Arguments of a later launch, forwarded to the running instance
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecondInstance {
+    pub args: Vec<String>,
+    pub cwd: String,
+}
+
+// Called in the running instance when the app is launched again.
+// The new process exits right away, so only one of them ever opens the database.
+pub fn on_second_instance(app: &AppHandle, argv: Vec<String>, cwd: String) {
+    log::info!("Another instance was launched with {:?}", argv);
+    // The first argument is the executable
+    let args: Vec<String> = argv.into_iter().skip(1).collect();
+    // A login item starting while the app is already running shouldn't pop up the window
+    if !args.iter().any(|arg| arg == ARG_BACKGROUND) {
+        show_main_window(app);
+    }
+    // On Windows and Linux, deep links launch a new process with the URL as argument
+    for arg in &args {
+        if let Ok(url) = Url::parse(arg) {
+            if url.scheme() == DEEP_LINK_SCHEME {
+                deep_link::handle_url(app.clone(), url);
+            }
+        }
+    }
+    if let Err(err) = app.emit(EVENT_SECOND_INSTANCE, SecondInstance { args, cwd }) {
+        log::error!("Error when sending event: {}", err);
+    }
+}
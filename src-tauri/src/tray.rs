@@ -0,0 +1,208 @@
+use entity::entities::conversations::ConversationFilter;
+use tauri::{
+    menu::{Menu, MenuBuilder, MenuEvent, MenuItem, SubmenuBuilder},
+    tray::TrayIconBuilder,
+    App, AppHandle, Emitter, Listener, Manager, Wry,
+};
+
+use crate::{
+    services::{
+        db::Repository,
+        generation::{GenerationManager, EVENT_GENERATIONS_CHANGED},
+    },
+    shortcuts::SHORTCUT_STOP_ALL,
+};
+
+const TRAY_ID: &str = "main-tray";
+const RECENT_CONVERSATIONS_LIMIT: usize = 5;
+const SUBJECT_MAX_CHARS: usize = 40;
+
+const MENU_STATUS: &str = "status";
+const MENU_NEW_CONVERSATION: &str = "new-conversation";
+const MENU_QUICK_ASK: &str = "quick-ask";
+const MENU_STOP_ALL: &str = "stop-all";
+const MENU_SHOW: &str = "show";
+const MENU_QUIT: &str = "quit";
+const MENU_CONVERSATION_PREFIX: &str = "conversation:";
+
+pub const EVENT_TRAY_NEW_CONVERSATION: &str = "tray-new-conversation";
+pub const EVENT_TRAY_QUICK_ASK: &str = "tray-quick-ask";
+pub const EVENT_OPEN_CONVERSATION: &str = "open-conversation";
+
+// Create the tray icon and keep its generation indicator in sync
+pub fn init_tray(app: &App) -> Result<(), String> {
+    let icon = app
+        .default_window_icon()
+        .cloned()
+        .ok_or("Default window icon is missing".to_string())?;
+    TrayIconBuilder::with_id(TRAY_ID)
+        .icon(icon)
+        .tooltip(tooltip_text(0))
+        .on_menu_event(handle_menu_event)
+        .build(app)
+        .map_err(|err| format!("Failed to build tray icon: {}", err))?;
+    // Fill the menu once the repository is ready
+    let handle = app.handle().clone();
+    tauri::async_runtime::spawn(async move {
+        refresh_tray(handle, 0).await;
+    });
+    // Rebuild the menu whenever a bot call starts or finishes
+    let handle = app.handle().clone();
+    app.listen_any(EVENT_GENERATIONS_CHANGED, move |event| {
+        let count = serde_json::from_str::<usize>(event.payload()).unwrap_or(0);
+        let handle = handle.clone();
+        tauri::async_runtime::spawn(async move {
+            refresh_tray(handle, count).await;
+        });
+    });
+    Ok(())
+}
+
+// Rebuild the tray menu with the latest conversations and generation status
+pub async fn refresh_tray(app: AppHandle, generating_count: usize) {
+    if let Some(tray) = app.tray_by_id(TRAY_ID) {
+        match build_menu(&app, generating_count).await {
+            Ok(menu) => {
+                if let Err(err) = tray.set_menu(Some(menu)) {
+                    log::error!("Failed to set tray menu: {}", err);
+                }
+            }
+            Err(err) => log::error!("Failed to build tray menu: {}", err),
+        }
+        let _ = tray.set_tooltip(Some(tooltip_text(generating_count)));
+        #[cfg(target_os = "macos")]
+        {
+            let title = if generating_count > 0 {
+                Some(format!("{}", generating_count))
+            } else {
+                None
+            };
+            let _ = tray.set_title(title);
+        }
+    }
+}
+
+// Refresh the tray using the current state of the generation manager
+pub async fn refresh_tray_with_state(app: AppHandle) {
+    let count = app.state::<GenerationManager>().active_count();
+    refresh_tray(app, count).await;
+}
+
+async fn build_menu(app: &AppHandle, generating_count: usize) -> tauri::Result<Menu<Wry>> {
+    let repo = app.state::<Repository>();
+    let conversations = repo
+        .list_conversations(ConversationFilter::default())
+        .await
+        .unwrap_or_else(|err| {
+            log::error!("Failed to list conversations for tray: {}", err);
+            vec![]
+        });
+    let mut recent = SubmenuBuilder::new(app, "Recent conversations");
+    if conversations.is_empty() {
+        recent = recent.item(&MenuItem::new(
+            app,
+            "No conversations yet",
+            false,
+            None::<&str>,
+        )?);
+    }
+    for conversation in conversations.iter().take(RECENT_CONVERSATIONS_LIMIT) {
+        recent = recent.text(
+            format!("{}{}", MENU_CONVERSATION_PREFIX, conversation.id),
+            truncate_subject(&conversation.subject),
+        );
+    }
+    let status = MenuItem::with_id(
+        app,
+        MENU_STATUS,
+        status_text(generating_count),
+        false,
+        None::<&str>,
+    )?;
+    let stop_all = MenuItem::with_id(
+        app,
+        MENU_STOP_ALL,
+        "Stop all generations",
+        generating_count > 0,
+        Some(SHORTCUT_STOP_ALL),
+    )?;
+    MenuBuilder::new(app)
+        .item(&status)
+        .item(&stop_all)
+        .separator()
+        .text(MENU_NEW_CONVERSATION, "New conversation")
+        .text(MENU_QUICK_ASK, "Quick ask")
+        .item(&recent.build()?)
+        .separator()
+        .text(MENU_SHOW, "Show Kaas")
+        .text(MENU_QUIT, "Quit")
+        .build()
+}
+
+fn handle_menu_event(app: &AppHandle, event: MenuEvent) {
+    let id = event.id().as_ref();
+    match id {
+        MENU_NEW_CONVERSATION => {
+            show_main_window(app);
+            emit_to_frontend(app, EVENT_TRAY_NEW_CONVERSATION, ());
+        }
+        MENU_QUICK_ASK => {
+            show_main_window(app);
+            emit_to_frontend(app, EVENT_TRAY_QUICK_ASK, ());
+        }
+        MENU_STOP_ALL => {
+            app.state::<GenerationManager>().stop_all();
+        }
+        MENU_SHOW => show_main_window(app),
+        MENU_QUIT => app.exit(0),
+        _ => {
+            if let Some(conversation_id) = id
+                .strip_prefix(MENU_CONVERSATION_PREFIX)
+                .and_then(|id_str| id_str.parse::<i32>().ok())
+            {
+                show_main_window(app);
+                emit_to_frontend(app, EVENT_OPEN_CONVERSATION, conversation_id);
+            }
+        }
+    }
+}
+
+pub fn show_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+}
+
+fn emit_to_frontend<S: serde::Serialize + Clone>(app: &AppHandle, event: &str, payload: S) {
+    if let Err(err) = app.emit(event, payload) {
+        log::error!("Error when sending event: {}", err);
+    }
+}
+
+fn status_text(generating_count: usize) -> String {
+    match generating_count {
+        0 => "Idle".to_string(),
+        1 => "Generating 1 reply...".to_string(),
+        n => format!("Generating {} replies...", n),
+    }
+}
+
+fn tooltip_text(generating_count: usize) -> String {
+    if generating_count > 0 {
+        format!("Kaas - {}", status_text(generating_count))
+    } else {
+        "Kaas".to_string()
+    }
+}
+
+fn truncate_subject(subject: &str) -> String {
+    let subject = subject.lines().next().unwrap_or_default().trim();
+    if subject.chars().count() > SUBJECT_MAX_CHARS {
+        let truncated: String = subject.chars().take(SUBJECT_MAX_CHARS).collect();
+        format!("{}...", truncated)
+    } else {
+        subject.to_string()
+    }
+}
@@ -0,0 +1,159 @@
+use std::time::Duration;
+
+use entity::entities::settings::SETTING_UPDATE_CHANNEL;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, Url};
+use tauri_plugin_updater::{Update, UpdaterExt};
+
+use crate::services::db::Repository;
+
+pub const EVENT_UPDATE_AVAILABLE: &str = "update-available";
+pub const EVENT_UPDATE_PROGRESS: &str = "update-progress";
+
+// Check again every few hours so long-running sessions also learn about new releases
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+const UPDATE_MANIFEST_BASE: &str =
+    "https://gist.githubusercontent.com/0xfrankz/2d501b9e229cb9bf3d24b5946a727b8d/raw";
+const UPDATE_MIRROR: &str = "https://mirror.ghproxy.com/";
+
+/// Release channels the user can follow
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UpdateChannel {
+    Stable,
+    Beta,
+}
+
+impl UpdateChannel {
+    pub fn from_setting(value: Option<&str>) -> Self {
+        match value {
+            Some("beta") => UpdateChannel::Beta,
+            _ => UpdateChannel::Stable,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UpdateChannel::Stable => "stable",
+            UpdateChannel::Beta => "beta",
+        }
+    }
+
+    fn manifest_name(&self) -> &'static str {
+        match self {
+            UpdateChannel::Stable => "kaas-updater.json",
+            UpdateChannel::Beta => "kaas-updater-beta.json",
+        }
+    }
+
+    // The mirror comes first, like in tauri.conf.json, for users who can't reach GitHub directly
+    pub fn endpoints(&self) -> Vec<Url> {
+        let manifest = format!("{}/{}", UPDATE_MANIFEST_BASE, self.manifest_name());
+        [format!("{}{}", UPDATE_MIRROR, manifest), manifest]
+            .iter()
+            .filter_map(|url| Url::parse(url).ok())
+            .collect()
+    }
+}
+
+/// Payload of the update-available event and result of check_for_updates
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateInfo {
+    pub version: String,
+    pub current_version: String,
+    pub notes: Option<String>,
+    pub date: Option<String>,
+    pub channel: String,
+}
+
+/// Payload of the update-progress event
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateProgress {
+    pub downloaded: usize,
+    pub total: Option<u64>,
+}
+
+async fn get_channel(app: &AppHandle) -> UpdateChannel {
+    let repo = app.state::<Repository>();
+    let setting = repo.get_setting(SETTING_UPDATE_CHANNEL).await;
+    UpdateChannel::from_setting(setting.as_ref().map(|setting| setting.value.as_str()))
+}
+
+async fn find_update(app: &AppHandle) -> Result<Option<(Update, UpdateChannel)>, String> {
+    let channel = get_channel(app).await;
+    let update = app
+        .updater_builder()
+        .endpoints(channel.endpoints())
+        .build()
+        .map_err(|err| format!("Failed to build updater: {}", err))?
+        .check()
+        .await
+        .map_err(|err| format!("Failed to fetch update manifest: {}", err))?;
+    Ok(update.map(|update| (update, channel)))
+}
+
+// Look for a newer release on the user's channel and tell the frontend about it
+pub async fn check_for_updates(app: &AppHandle) -> Result<Option<UpdateInfo>, String> {
+    let result = find_update(app).await?.map(|(update, channel)| UpdateInfo {
+        version: update.version.clone(),
+        current_version: update.current_version.clone(),
+        notes: update.body.clone(),
+        date: update.date.map(|date| date.to_string()),
+        channel: channel.as_str().to_string(),
+    });
+    match &result {
+        Some(info) => {
+            log::info!("Update available: {} ({})", info.version, info.channel);
+            if let Err(err) = app.emit(EVENT_UPDATE_AVAILABLE, info.clone()) {
+                log::error!("Error when sending event: {}", err);
+            }
+        }
+        None => log::info!("App is up to date"),
+    }
+    Ok(result)
+}
+
+// Download and install the latest release, then restart into it
+pub async fn install_update(app: &AppHandle) -> Result<(), String> {
+    let (update, _) = find_update(app)
+        .await?
+        .ok_or("No update available".to_string())?;
+    let mut downloaded = 0;
+    let progress_handle = app.clone();
+    update
+        .download_and_install(
+            move |chunk_length, total| {
+                downloaded += chunk_length;
+                let _ = progress_handle
+                    .emit(EVENT_UPDATE_PROGRESS, UpdateProgress { downloaded, total });
+            },
+            || log::info!("Update downloaded"),
+        )
+        .await
+        .map_err(|err| format!("Failed to install update: {}", err))?;
+    app.restart();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_from_setting() {
+        assert_eq!(
+            UpdateChannel::Beta,
+            UpdateChannel::from_setting(Some("beta"))
+        );
+        assert_eq!(
+            UpdateChannel::Stable,
+            UpdateChannel::from_setting(Some("stable"))
+        );
+        assert_eq!(
+            UpdateChannel::Stable,
+            UpdateChannel::from_setting(Some("nightly"))
+        );
+        assert_eq!(UpdateChannel::Stable, UpdateChannel::from_setting(None));
+    }
+}
@@ -37,4 +37,35 @@ pub fn is_stream_enabled(options: &GenericOptions) -> bool {
     } else {
         return false;
     }
-}
\ No newline at end of file
+}
+
+// Return a copy of the options with streaming forced on or off, regardless of the user's choice
+pub fn with_stream(options: GenericOptions, stream: bool) -> GenericOptions {
+    match serde_json::from_str::<serde_json::Value>(&options.options) {
+        Ok(mut value) => {
+            if let Some(obj) = value.as_object_mut() {
+                obj.insert("stream".to_string(), serde_json::Value::Bool(stream));
+            }
+            GenericOptions {
+                provider: options.provider,
+                options: value.to_string(),
+            }
+        }
+        Err(_) => options,
+    }
+}
+
+// Reveal a directory in the platform's file manager
+pub fn open_in_file_manager(path: &std::path::Path) -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    let program = "open";
+    #[cfg(target_os = "windows")]
+    let program = "explorer";
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let program = "xdg-open";
+    std::process::Command::new(program)
+        .arg(path)
+        .spawn()
+        .map(|_| ())
+        .map_err(|err| format!("Failed to open {}: {}", path.display(), err))
+}
@@ -0,0 +1,101 @@
+use entity::entities::settings::{Model as Setting, SETTING_WINDOW_STATE_PREFIX};
+use serde::{Deserialize, Serialize};
+use tauri::{App, Manager, PhysicalPosition, PhysicalSize, Runtime, Window, WindowEvent};
+
+use crate::services::db::Repository;
+
+/// Geometry of a window, in physical pixels
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowState {
+    pub width: u32,
+    pub height: u32,
+    pub x: i32,
+    pub y: i32,
+    pub maximized: bool,
+}
+
+fn setting_key(label: &str) -> String {
+    format!("{}{}", SETTING_WINDOW_STATE_PREFIX, label)
+}
+
+// Restore the windows that exist at startup
+pub fn init_window_state(app: &App) -> Result<(), String> {
+    for window in app.webview_windows().values() {
+        restore_window_state(&window.as_ref().window());
+    }
+    Ok(())
+}
+
+// Apply the saved geometry of a window, if any. Call it for every window created later on.
+pub fn restore_window_state<R: Runtime>(window: &Window<R>) {
+    let repo = window.state::<Repository>();
+    let saved = tauri::async_runtime::block_on(repo.get_setting(&setting_key(window.label())))
+        .and_then(|setting| serde_json::from_str::<WindowState>(&setting.value).ok());
+    let state = match saved {
+        Some(state) => state,
+        None => return,
+    };
+    if state.width > 0 && state.height > 0 {
+        let _ = window.set_size(PhysicalSize::new(state.width, state.height));
+    }
+    // Skip the position if the monitor it was on has been disconnected
+    if is_on_screen(window, &state) {
+        let _ = window.set_position(PhysicalPosition::new(state.x, state.y));
+    }
+    if state.maximized {
+        let _ = window.maximize();
+    }
+}
+
+fn is_on_screen<R: Runtime>(window: &Window<R>, state: &WindowState) -> bool {
+    window
+        .available_monitors()
+        .map(|monitors| {
+            monitors.iter().any(|monitor| {
+                let position = monitor.position();
+                let size = monitor.size();
+                state.x >= position.x
+                    && state.y >= position.y
+                    && state.x < position.x + size.width as i32
+                    && state.y < position.y + size.height as i32
+            })
+        })
+        .unwrap_or(false)
+}
+
+// Save the geometry of any window when it's about to close
+pub fn handle_window_event<R: Runtime>(window: &Window<R>, event: &WindowEvent) {
+    if let WindowEvent::CloseRequested { .. } = event {
+        if let Err(err) = save_window_state(window) {
+            log::error!("Failed to save state of window {}: {}", window.label(), err);
+        }
+    }
+}
+
+fn save_window_state<R: Runtime>(window: &Window<R>) -> Result<(), String> {
+    let repo = window.state::<Repository>();
+    let key = setting_key(window.label());
+    let maximized = window.is_maximized().map_err(|err| err.to_string())?;
+    let minimized = window.is_minimized().map_err(|err| err.to_string())?;
+    tauri::async_runtime::block_on(async {
+        let mut state = repo
+            .get_setting(&key)
+            .await
+            .and_then(|setting| serde_json::from_str::<WindowState>(&setting.value).ok())
+            .unwrap_or_default();
+        // Keep the last normal geometry, so un-maximizing after a restart goes back to it
+        if !maximized && !minimized {
+            let size = window.inner_size().map_err(|err| err.to_string())?;
+            let position = window.outer_position().map_err(|err| err.to_string())?;
+            state.width = size.width;
+            state.height = size.height;
+            state.x = position.x;
+            state.y = position.y;
+        }
+        state.maximized = maximized;
+        let value = serde_json::to_string(&state).map_err(|err| err.to_string())?;
+        repo.upsert_setting(Setting { key, value }).await?;
+        Ok(())
+    })
+}
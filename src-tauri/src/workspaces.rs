@@ -0,0 +1,126 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+/// The workspace of installs made before workspaces existed, using the app data directory itself
+pub const DEFAULT_WORKSPACE_ID: &str = "default";
+const DEFAULT_WORKSPACE_NAME: &str = "Default";
+const WORKSPACES_FILE: &str = "workspaces.json";
+const WORKSPACES_DIR: &str = "workspaces";
+
+/// A separate set of models, keys, conversations and settings, stored in its own database
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Workspace {
+    pub id: String,
+    pub name: String,
+    pub created_at: DateTime<Local>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceList {
+    pub active: String,
+    pub workspaces: Vec<Workspace>,
+}
+
+impl Default for WorkspaceList {
+    fn default() -> Self {
+        WorkspaceList {
+            active: DEFAULT_WORKSPACE_ID.to_string(),
+            workspaces: vec![Workspace {
+                id: DEFAULT_WORKSPACE_ID.to_string(),
+                name: DEFAULT_WORKSPACE_NAME.to_string(),
+                created_at: Local::now(),
+            }],
+        }
+    }
+}
+
+// Read the workspace list, which lives outside of any workspace
+pub fn load_workspaces(app_data_dir: &Path) -> WorkspaceList {
+    fs::read_to_string(app_data_dir.join(WORKSPACES_FILE))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save_workspaces(app_data_dir: &Path, list: &WorkspaceList) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(list).map_err(|err| err.to_string())?;
+    fs::write(app_data_dir.join(WORKSPACES_FILE), json)
+        .map_err(|err| format!("Failed to save workspaces: {}", err))
+}
+
+// Directory holding the database and cache of a workspace
+pub fn workspace_dir(app_data_dir: &Path, workspace_id: &str) -> PathBuf {
+    if workspace_id == DEFAULT_WORKSPACE_ID {
+        app_data_dir.to_path_buf()
+    } else {
+        app_data_dir.join(WORKSPACES_DIR).join(workspace_id)
+    }
+}
+
+pub fn active_workspace_dir(app_data_dir: &Path) -> PathBuf {
+    workspace_dir(app_data_dir, &load_workspaces(app_data_dir).active)
+}
+
+pub fn get_app_data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map_err(|err| format!("App data path doesn't exist: {}", err))
+}
+
+pub fn create_workspace(app_data_dir: &Path, name: String) -> Result<Workspace, String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Workspace name can't be empty".to_string());
+    }
+    let mut list = load_workspaces(app_data_dir);
+    let workspace = Workspace {
+        id: format!("ws-{}", Local::now().timestamp_millis()),
+        name,
+        created_at: Local::now(),
+    };
+    fs::create_dir_all(workspace_dir(app_data_dir, &workspace.id))
+        .map_err(|err| format!("Failed to create workspace directory: {}", err))?;
+    list.workspaces.push(workspace.clone());
+    save_workspaces(app_data_dir, &list)?;
+    Ok(workspace)
+}
+
+// Make a workspace the active one. The database is opened at startup, so the app must restart.
+pub fn set_active_workspace(app_data_dir: &Path, workspace_id: &str) -> Result<(), String> {
+    let mut list = load_workspaces(app_data_dir);
+    if !list
+        .workspaces
+        .iter()
+        .any(|workspace| workspace.id == workspace_id)
+    {
+        return Err(format!("Workspace {} doesn't exist", workspace_id));
+    }
+    list.active = workspace_id.to_string();
+    save_workspaces(app_data_dir, &list)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workspace_dir() {
+        let app_data_dir = Path::new("/data/kaas");
+        assert_eq!(
+            PathBuf::from("/data/kaas"),
+            workspace_dir(app_data_dir, DEFAULT_WORKSPACE_ID)
+        );
+        assert_eq!(
+            PathBuf::from("/data/kaas/workspaces/ws-1"),
+            workspace_dir(app_data_dir, "ws-1")
+        );
+    }
+}
@@ -47,6 +47,7 @@ import {
   getTextFromContent,
   getTextFromMessage,
   preprocessLaTeX,
+  setMetadata,
 } from '@/lib/utils';
 
 import { CopyCode } from './CopyCode';
@@ -459,7 +460,8 @@ const ContentReceiver = ({
   showReasoning?: boolean;
 }) => {
   const tag = getMessageTag(message);
-  const { ready, receiving, reply, error } = useReplyListener(tag);
+  const { ready, receiving, reply, metrics, error, recoveries, fallback } =
+    useReplyListener(tag);
   const { onReceiverReady } = useMessageListContext();
   const creator = useMessageCreator();
   const updater = useMessageUpdater();
@@ -491,6 +493,16 @@ const ContentReceiver = ({
       (reply.message.length > 0 || (reply.reasoning?.length ?? 0) > 0)
     ) {
       const content = buildTextContent(reply.message);
+      let metadata = metrics
+        ? setMetadata(message.metadata, 'throughput', metrics)
+        : message.metadata;
+      if (recoveries.length > 0) {
+        metadata = setMetadata(metadata, 'recoveries', recoveries);
+      }
+      if (fallback) {
+        // the model which actually replied
+        metadata = setMetadata(metadata, 'fallback', fallback);
+      }
       if (message.id < 0) {
         // new message
         creator({
@@ -502,6 +514,7 @@ const ContentReceiver = ({
           completionToken: reply.completionToken,
           reasoningToken: reply.reasoningToken,
           totalToken: reply.totalToken,
+          metadata,
         });
       } else {
         updater({
@@ -512,21 +525,31 @@ const ContentReceiver = ({
           completionToken: reply.completionToken,
           reasoningToken: reply.reasoningToken,
           totalToken: reply.totalToken,
+          metadata,
         });
       }
     }
-  }, [creator, message, reply, receiving, updater]);
+  }, [
+    creator,
+    fallback,
+    message,
+    metrics,
+    recoveries,
+    reply,
+    receiving,
+    updater,
+  ]);
 
   useEffect(() => {
     // handle BE errors
-    if (error && error.length > 0) {
+    if (error && error.message.length > 0) {
       queryClient.setQueryData<Message[]>(
         [...LIST_MESSAGES_KEY, { conversationId: message.conversationId }],
         (old) =>
           produce(old, (draft) => {
             const target = draft?.find((m) => m.id === message.id);
             if (target) {
-              target.content = buildTextContent(error);
+              target.content = buildTextContent(error.message);
               target.isError = true;
               target.isReceiving = false;
             }
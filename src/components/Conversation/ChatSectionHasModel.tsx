@@ -1,11 +1,12 @@
 import { useQueryClient } from '@tanstack/react-query';
-import { emit } from '@tauri-apps/api/event';
+import { emit, listen } from '@tauri-apps/api/event';
 import { animate, motion } from 'framer-motion';
 import { produce } from 'immer';
 import { memo, useCallback, useEffect, useMemo, useRef } from 'react';
 import { useTranslation } from 'react-i18next';
 
 import {
+  EVENT_MODERATION_FLAGGED,
   MESSAGE_BOT,
   MESSAGE_USER,
   SETTING_IS_WIDE_SCREEN,
@@ -20,8 +21,12 @@ import {
   FileUploaderContextProvider,
   MessageListContextProvider,
 } from '@/lib/providers';
-import { useAppStateStore } from '@/lib/store';
-import type { ConversationDetails, Message } from '@/lib/types';
+import { useAppStateStore, useConfirmationStateStore } from '@/lib/store';
+import type {
+  ConversationDetails,
+  Message,
+  ModerationFlagged,
+} from '@/lib/types';
 import { cn, getMessageTag } from '@/lib/utils';
 
 import { ChatMessageList } from '../ChatMessageList';
@@ -50,6 +55,7 @@ export function ChatSectionHasModel({
     query: { data: options },
   } = useGetOptionsQuery(conversation.id);
   const { t } = useTranslation(['page-conversation']);
+  const { open } = useConfirmationStateStore();
 
   // Queries
   const queryClient = useQueryClient();
@@ -83,19 +89,63 @@ export function ChatSectionHasModel({
   );
 
   // Callbacks
-  const onReceiverReady = useCallback(() => {
-    const placeholder = messages?.find((m) => m.isReceiving);
-    if (placeholder) {
-      // listener's tag
-      const tag = getMessageTag(placeholder);
+  const clearReceiving = useCallback(() => {
+    queryClient.setQueryData<Message[]>(
+      [...LIST_MESSAGES_KEY, { conversationId: conversation.id }],
+      (old) =>
+        produce(old, (draft) => {
+          const target = draft?.find((m) => m.isReceiving);
+          if (target) {
+            if (target.id < 0) {
+              // remove placeholder, which is the last item
+              draft?.pop();
+            } else {
+              target.isReceiving = false;
+            }
+          }
+        })
+    );
+  }, [conversation.id, queryClient]);
+
+  const callBot = useCallback(
+    (placeholder: Message, flags?: { skipModeration?: boolean }) => {
       const data = {
         conversationId: conversation.id,
-        tag,
+        // listener's tag
+        tag: getMessageTag(placeholder),
         beforeMessageId: placeholder.id > 0 ? placeholder.id : undefined,
+        ...flags,
       };
-      botCaller(data);
+      botCaller(data, {
+        onError: (error) => {
+          // expensive requests are only sent once the user confirms them
+          if (error.type === 'CostConfirmationRequired') {
+            open({
+              title: t('page-conversation:message:cost-confirmation'),
+              message: t(
+                'page-conversation:message:cost-confirmation-warning',
+                {
+                  estimatedCost: error.estimatedCost?.toFixed(2),
+                  threshold: error.threshold?.toFixed(2),
+                }
+              ),
+              onConfirm: () => botCaller({ ...data, confirmCost: true }),
+              onCancel: clearReceiving,
+            });
+          }
+        },
+      });
+    },
+    [botCaller, clearReceiving, conversation.id, open, t]
+  );
+
+  const onReceiverReady = useCallback(() => {
+    const placeholder = messages?.find((m) => m.isReceiving);
+    if (placeholder) {
+      callBot(placeholder);
     }
-  }, [messages, botCaller, conversation.id]);
+  }, [messages, callBot]);
+
 
   const onRegenerateClick = useCallback(
     (msg: Message) => {
@@ -137,24 +187,10 @@ export function ChatSectionHasModel({
   );
 
   const onStopClick = useCallback(async () => {
-    await emit('stop-bot');
+    await emit(`stop-bot:${conversation.id}`);
     // update message list data
-    queryClient.setQueryData<Message[]>(
-      [...LIST_MESSAGES_KEY, { conversationId: conversation.id }],
-      (old) =>
-        produce(old, (draft) => {
-          const target = draft?.find((m) => m.isReceiving);
-          if (target) {
-            if (target.id < 0) {
-              // remove placeholder, which is the last item
-              draft?.pop();
-            } else {
-              target.isReceiving = false;
-            }
-          }
-        })
-    );
-  }, [conversation.id, queryClient]);
+    clearReceiving();
+  }, [clearReceiving, conversation.id]);
 
   const onToBottomClick = useCallback(() => {
     if (viewportRef.current) {
@@ -192,6 +228,32 @@ export function ChatSectionHasModel({
   }, [conversation.id, onToBottomClick, queryClient]);
 
   // Hooks
+  useEffect(() => {
+    // flagged messages are only sent once the user confirms them
+    const unlisten = listen<ModerationFlagged>(
+      EVENT_MODERATION_FLAGGED,
+      (event) => {
+        const { conversationId, tag, categories } = event.payload;
+        if (conversationId !== conversation.id) return;
+        const placeholder = queryClient
+          .getQueryData<Message[]>([...LIST_MESSAGES_KEY, { conversationId }])
+          ?.find((m) => getMessageTag(m) === tag);
+        if (!placeholder) return;
+        open({
+          title: t('page-conversation:message:moderation-flagged'),
+          message: t('page-conversation:message:moderation-flagged-warning', {
+            categories: categories.join(', '),
+          }),
+          onConfirm: () => callBot(placeholder, { skipModeration: true }),
+          onCancel: clearReceiving,
+        });
+      }
+    );
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [callBot, clearReceiving, conversation.id, open, queryClient, t]);
+
   useEffect(() => {
     if (viewportRef.current) {
       viewportRef.current.onscroll = () => {
@@ -1,21 +1,27 @@
 import { invoke } from '@tauri-apps/api/core';
 
 import type {
+  ContentItem,
   Conversation,
   ConversationDetails,
   GenericConfig,
   GenericModel,
   GenericOptions,
   Message,
+  MessageBranch,
   Model,
+  ModelCapabilities,
   NewConversation,
   NewMessage,
   NewModel,
   NewPrompt,
   Options,
   Prompt,
+  ProxySetting,
+  ProxyTest,
   RemoteModel,
   Setting,
+  Tag,
   UpdateConversation,
 } from './types';
 import {
@@ -47,13 +53,26 @@ export async function invokeUpdateModel(model: Model): Promise<Model> {
   return fromGenericModel(result);
 }
 
-export async function invokeDeleteModel(modelId: number): Promise<Model> {
+export async function invokeDeleteModel(
+  modelId: number,
+  reassignTo?: number
+): Promise<Model> {
   const result = await invoke<GenericModel>('delete_model', {
     modelId,
+    reassignTo,
   });
   return fromGenericModel(result);
 }
 
+export async function invokeGetModelCapabilities(
+  modelId: number
+): Promise<ModelCapabilities> {
+  const result = await invoke<ModelCapabilities>('get_model_capabilities', {
+    modelId,
+  });
+  return result;
+}
+
 export async function invokeListRemoteModels(
   config: GenericConfig
 ): Promise<RemoteModel[]> {
@@ -74,6 +93,14 @@ export async function invokeUpsertSetting(setting: Setting): Promise<Setting> {
   return result;
 }
 
+export async function invokeTestProxy(
+  setting: ProxySetting,
+  url?: string
+): Promise<ProxyTest> {
+  const result = await invoke<ProxyTest>('test_proxy', { setting, url });
+  return result;
+}
+
 export async function invokeCreateConversation(
   newConversation: NewConversation
 ): Promise<Conversation> {
@@ -96,10 +123,89 @@ export async function invokeCreateBlankConversation(
   return result;
 }
 
-export async function invokeListConversations(): Promise<
-  ConversationDetails[]
-> {
-  const result = await invoke<ConversationDetails[]>('list_conversations');
+export async function invokeListConversations(filter?: {
+  includeArchived?: boolean;
+  tagId?: number;
+  query?: string;
+  sort?: 'lastActivity' | 'createdAt' | 'messageCount';
+  limit?: number;
+  offset?: number;
+}): Promise<ConversationDetails[]> {
+  const result = await invoke<ConversationDetails[]>('list_conversations', {
+    filter,
+  });
+  return result;
+}
+
+export async function invokePinConversation(
+  conversationId: number,
+  isPinned: boolean
+): Promise<ConversationDetails> {
+  const result = await invoke<ConversationDetails>('pin_conversation', {
+    conversationId,
+    isPinned,
+  });
+  return result;
+}
+
+export async function invokeCreateTag(
+  name: string,
+  color?: string
+): Promise<Tag> {
+  const result = await invoke<Tag>('create_tag', {
+    newTag: { name, color },
+  });
+  return result;
+}
+
+export async function invokeListTags(): Promise<Tag[]> {
+  const result = await invoke<Tag[]>('list_tags');
+  return result;
+}
+
+export async function invokeDeleteTag(tagId: number): Promise<void> {
+  await invoke<void>('delete_tag', { tagId });
+}
+
+export async function invokeListConversationTags(
+  conversationId: number
+): Promise<Tag[]> {
+  const result = await invoke<Tag[]>('list_conversation_tags', {
+    conversationId,
+  });
+  return result;
+}
+
+export async function invokeAddConversationTag(
+  conversationId: number,
+  tagId: number
+): Promise<Tag[]> {
+  const result = await invoke<Tag[]>('add_conversation_tag', {
+    conversationId,
+    tagId,
+  });
+  return result;
+}
+
+export async function invokeRemoveConversationTag(
+  conversationId: number,
+  tagId: number
+): Promise<Tag[]> {
+  const result = await invoke<Tag[]>('remove_conversation_tag', {
+    conversationId,
+    tagId,
+  });
+  return result;
+}
+
+export async function invokeArchiveConversation(
+  conversationId: number,
+  isArchived: boolean
+): Promise<ConversationDetails> {
+  const result = await invoke<ConversationDetails>('archive_conversation', {
+    conversationId,
+    isArchived,
+  });
   return result;
 }
 
@@ -112,6 +218,49 @@ export async function invokeDeleteConversation(
   return result;
 }
 
+export async function invokeListTrashedConversations(): Promise<
+  ConversationDetails[]
+> {
+  const result = await invoke<ConversationDetails[]>(
+    'list_trashed_conversations'
+  );
+  return result;
+}
+
+export async function invokeRestoreConversation(
+  conversationId: number
+): Promise<ConversationDetails> {
+  const result = await invoke<ConversationDetails>('restore_conversation', {
+    conversationId,
+  });
+  return result;
+}
+
+export async function invokePurgeTrash(): Promise<number> {
+  const result = await invoke<number>('purge_trash');
+  return result;
+}
+
+export async function invokeForkConversation(
+  conversationId: number,
+  messageId: number
+): Promise<Conversation> {
+  const result = await invoke<Conversation>('fork_conversation', {
+    conversationId,
+    messageId,
+  });
+  return result;
+}
+
+export async function invokeDuplicateConversation(
+  conversationId: number
+): Promise<Conversation> {
+  const result = await invoke<Conversation>('duplicate_conversation', {
+    conversationId,
+  });
+  return result;
+}
+
 export async function invokeUpdateConversationModel({
   conversationId,
   modelId,
@@ -162,6 +311,14 @@ export async function invokeUpdateOptions({
   });
 }
 
+export async function invokeResetOptionsToModelDefault(
+  conversationId: number
+) {
+  await invoke<GenericOptions>('reset_options_to_model_default', {
+    conversationId,
+  });
+}
+
 export async function invokeUpdateSubject({
   conversationId,
   subject,
@@ -176,9 +333,21 @@ export async function invokeUpdateSubject({
 }
 
 export async function invokeListMessages(
-  conversationId: number
+  conversationId: number,
+  page?: { beforeId?: number; limit?: number }
 ): Promise<Message[]> {
-  const result = await invoke<Message[]>('list_messages', { conversationId });
+  const result = await invoke<Message[]>('list_messages', {
+    conversationId,
+    beforeId: page?.beforeId,
+    limit: page?.limit,
+  });
+  return result;
+}
+
+export async function invokeGetMessageCount(
+  conversationId: number
+): Promise<number> {
+  const result = await invoke<number>('get_message_count', { conversationId });
   return result;
 }
 
@@ -207,6 +376,17 @@ export async function invokeGetSystemMessage(
   return result;
 }
 
+export async function invokeSetSystemPrompt(
+  conversationId: number,
+  prompt?: string
+): Promise<Message | null> {
+  const result = await invoke<Message | null>('set_system_prompt', {
+    conversationId,
+    prompt,
+  });
+  return result;
+}
+
 export async function invokeUpdateMessage(message: Message): Promise<Message> {
   const result = await invoke<Message>('update_message', {
     message,
@@ -214,6 +394,48 @@ export async function invokeUpdateMessage(message: Message): Promise<Message> {
   return result;
 }
 
+export async function invokeEditMessage(
+  messageId: number,
+  content: ContentItem[]
+): Promise<Message> {
+  const result = await invoke<Message>('edit_message', {
+    messageId,
+    content,
+  });
+  return result;
+}
+
+export async function invokeBranchMessage(
+  messageId: number,
+  content: ContentItem[]
+): Promise<Message> {
+  const result = await invoke<Message>('branch_message', {
+    messageId,
+    content,
+  });
+  return result;
+}
+
+export async function invokeListMessageBranches(
+  conversationId: number
+): Promise<MessageBranch[]> {
+  const result = await invoke<MessageBranch[]>('list_message_branches', {
+    conversationId,
+  });
+  return result;
+}
+
+export async function invokeSwitchMessageBranch(
+  conversationId: number,
+  messageId: number
+): Promise<Message[]> {
+  const result = await invoke<Message[]>('switch_message_branch', {
+    conversationId,
+    messageId,
+  });
+  return result;
+}
+
 export async function invokeHardDeleteMessages(
   conversationId: number
 ): Promise<void> {
@@ -231,19 +453,41 @@ export async function invokeHardDeleteMessage(
   return result;
 }
 
+export async function invokeDeleteMessage(messageId: number): Promise<Message> {
+  const result = await invoke<Message>('delete_message', {
+    messageId,
+  });
+  return result;
+}
+
+export async function invokeDeleteMessagesFrom(
+  messageId: number
+): Promise<number> {
+  const result = await invoke<number>('delete_messages_from', {
+    messageId,
+  });
+  return result;
+}
+
 export async function invokeCallBot({
   conversationId,
   tag,
   beforeMessageId,
+  skipModeration,
+  confirmCost,
 }: {
   conversationId: number;
   tag: string;
   beforeMessageId?: number;
+  skipModeration?: boolean;
+  confirmCost?: boolean;
 }): Promise<void> {
   await invoke<Message>('call_bot', {
     conversationId,
     tag,
     beforeMessageId,
+    skipModeration,
+    confirmCost,
   });
 }
 
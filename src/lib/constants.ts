@@ -145,6 +145,13 @@ export const STREAM_START = '[[START]]';
 export const STREAM_DONE = '[[DONE]]';
 export const STREAM_ERROR = '[[ERROR]]';
 export const STREAM_STOPPED = '[[STOPPED]]';
+export const STREAM_RETRYING = '[[RETRYING]]';
+export const STREAM_CONTINUE = '[[CONTINUE]]';
+export const STREAM_RESUMED = '[[RESUMED]]';
+export const STREAM_FALLBACK = '[[FALLBACK]]';
+
+// Backend events
+export const EVENT_MODERATION_FLAGGED = 'moderation-flagged';
 
 // Setting keys
 export const SETTING_USER_DEFAULT_MODEL = 'user:default_model';
@@ -157,12 +164,14 @@ export const SETTING_PROFILE_NAME = 'profile:name';
 export const SETTING_MODELS_CONTEXT_LENGTH = 'models:context_length';
 export const SETTING_MODELS_MAX_TOKENS = 'models:max_tokens';
 export const SETTING_NETWORK_PROXY = 'network:proxy';
+export const SETTING_NETWORK_PROXY_PREFIX = 'network:proxy:';
+export const SETTING_NETWORK_TIMEOUTS = 'network:timeouts';
 
 // Defaults
 export const DEFAULT_DATE_FORMAT = 'MMM D, YYYY';
 export const DEFAULT_DATETIME_FORMAT = 'HH:mm MMM D, YYYY';
 export const DEFAULT_PROFILE_NAME = 'ME';
-export const DEFAULT_CONTEXT_LENGTH = 1;
+export const DEFAULT_CONTEXT_LENGTH = 0;
 export const DEFAULT_MAX_TOKENS = 256;
 export const DEFAULT_IS_SIDEBAR_PINNED = false;
 
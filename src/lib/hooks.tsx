@@ -54,8 +54,12 @@ import {
   MESSAGE_BOT,
   MESSAGE_USER,
   SETTING_NETWORK_PROXY,
+  STREAM_CONTINUE,
   STREAM_DONE,
   STREAM_ERROR,
+  STREAM_FALLBACK,
+  STREAM_RESUMED,
+  STREAM_RETRYING,
   STREAM_START,
   STREAM_STOPPED,
 } from './constants';
@@ -74,6 +78,7 @@ import {
   type GenericModel,
   type Message,
   type Model,
+  type ModelFallback,
   type NewConversation,
   type NewMessage,
   type NewModel,
@@ -83,7 +88,10 @@ import {
   type ProxySetting,
   type RawConfig,
   type RemoteModel,
+  type RequestRetry,
   type Setting,
+  type StreamMetrics,
+  type StreamRecovery,
   type TConversationsContext,
   type TFileUploaderContext,
   type TFilledPromptContext,
@@ -133,7 +141,7 @@ export function useModelDeleter(
   options?: Omit<UseMutationOptions<Model, CommandError, number>, 'mutationFn'>
 ) {
   return useMutation({
-    mutationFn: invokeDeleteModel,
+    mutationFn: (modelId: number) => invokeDeleteModel(modelId),
     ...options,
   }).mutate;
 }
@@ -474,6 +482,8 @@ export function useBotCaller(
         conversationId: number;
         tag: string;
         beforeMessageId?: number;
+        skipModeration?: boolean;
+        confirmCost?: boolean;
       }
     >,
     'mutationFn'
@@ -681,15 +691,28 @@ export function useReplyListener(tag: string) {
   const [ready, setReady] = useState(false);
   const [receiving, setReceiving] = useState(false);
   const [reply, setReply] = useState<BotReply | null>(null);
-  const [error, setError] = useState<string>();
+  const [metrics, setMetrics] = useState<StreamMetrics>();
+  const [error, setError] = useState<CommandError>();
+  const [retrying, setRetrying] = useState<RequestRetry>();
+  const [recoveries, setRecoveries] = useState<StreamRecovery[]>([]);
+  const [fallback, setFallback] = useState<ModelFallback>();
   const acceptingRef = useRef<boolean>(false);
+  const fallbackRef = useRef<boolean>(false);
   const listenerRef = useRef<UnlistenFn>();
   const mountedRef = useRef(false);
 
   const startStreaming = () => {
     setReceiving(true);
+    setRetrying(undefined);
     acceptingRef.current = true;
     setReply(null);
+    setMetrics(undefined);
+    setRecoveries([]);
+    // fallbacks are sent before the stream starts
+    if (!fallbackRef.current) {
+      setFallback(undefined);
+    }
+    fallbackRef.current = false;
   };
 
   const endStreaming = () => {
@@ -711,16 +734,55 @@ export function useReplyListener(tag: string) {
         case nextMsg === STREAM_START:
           startStreaming();
           break;
-        case nextMsg === STREAM_DONE:
+        case nextMsg.startsWith(STREAM_DONE): {
+          // streamed replies end with their metrics
+          const data = nextMsg.slice(STREAM_DONE.length);
+          if (data.length > 0) {
+            setMetrics(JSON.parse(data) as StreamMetrics);
+          }
           endStreaming();
           break;
+        }
         case nextMsg === STREAM_STOPPED:
           endStreaming();
           break;
-        case nextMsg.startsWith(STREAM_ERROR):
-          setError(nextMsg.split(STREAM_ERROR).at(-1) ?? '');
+        case nextMsg.startsWith(STREAM_RETRYING):
+          // the request failed and is sent again after a delay
+          setRetrying(
+            JSON.parse(nextMsg.slice(STREAM_RETRYING.length)) as RequestRetry
+          );
+          break;
+        case nextMsg.startsWith(STREAM_CONTINUE):
+          // the reply was cut off by the max tokens limit, its rest is
+          // streamed under the same tag and appended to it
+          break;
+        case nextMsg.startsWith(STREAM_RESUMED): {
+          // the stream dropped and its rest is requested, kept with the reply
+          const recovery = JSON.parse(
+            nextMsg.slice(STREAM_RESUMED.length)
+          ) as StreamRecovery;
+          setRecoveries((state) => [...state, recovery]);
+          break;
+        }
+        case nextMsg.startsWith(STREAM_FALLBACK):
+          // the model failed and the next model of the conversation replies
+          fallbackRef.current = true;
+          setFallback(
+            JSON.parse(nextMsg.slice(STREAM_FALLBACK.length)) as ModelFallback
+          );
+          break;
+        case nextMsg.startsWith(STREAM_ERROR): {
+          // errors are sent as coded command errors
+          const data = nextMsg.slice(STREAM_ERROR.length);
+          setRetrying(undefined);
+          try {
+            setError(JSON.parse(data) as CommandError);
+          } catch {
+            setError({ type: 'UnknownError', message: data });
+          }
           endStreaming();
           break;
+        }
         default:
           if (acceptingRef.current) {
             const botReply = JSON.parse(nextMsg) as BotReply;
@@ -773,7 +835,11 @@ export function useReplyListener(tag: string) {
     ready,
     receiving,
     reply,
+    metrics,
     error,
+    retrying,
+    recoveries,
+    fallback,
   };
 }
 
@@ -79,7 +79,10 @@ export const editGoogleModelFormSchema = newGoogleModelFormSchema.extend({
 });
 
 const commonOptionsFormSchema = z.object({
-  contextLength: z.coerce.number().int().min(1).max(65535).optional(),
+  contextLength: z.coerce.number().int().min(0).max(65535).optional(),
+  truncation: z
+    .enum(['dropOldest', 'slidingWindow', 'keepSystemAndRecent'])
+    .optional(),
   frequencyPenalty: z.coerce
     .number()
     .min(-2.0)
@@ -56,6 +56,14 @@ export type RemoteModel = {
   id: string;
 };
 
+export type ModelCapabilities = {
+  contextLength?: number;
+  maxOutputTokens?: number;
+  supportsVision: boolean;
+  supportsTools: boolean;
+  temperatureRange: [number, number];
+};
+
 type SavedModelAttrs = {
   id: number;
   createdAt?: string;
@@ -173,10 +181,12 @@ export type NewMessage = {
   completionToken?: number;
   reasoningToken?: number;
   totalToken?: number;
+  metadata?: string;
 };
 
 export type Message = NewMessage & {
   id: number;
+  parentMessageId?: number;
   createdAt?: string;
   updatedAt?: string;
   deletedAt?: string;
@@ -185,6 +195,12 @@ export type Message = NewMessage & {
   isError?: boolean;
 };
 
+export type MessageBranch = {
+  parentMessageId?: number;
+  messageIds: number[];
+  activeMessageId?: number;
+};
+
 export type BotReply = {
   message: string;
   reasoning?: string;
@@ -194,6 +210,40 @@ export type BotReply = {
   totalToken?: number;
 };
 
+export type RequestRetry = {
+  attempt: number;
+  maxAttempts: number;
+  delayMs: number;
+  error: string;
+};
+
+export type ModelFallback = {
+  modelId: number;
+  alias: string;
+  error: string;
+};
+
+export type StreamRecovery = {
+  attempt: number;
+  strategy: 'prefill' | 'continuation' | 'restart';
+  receivedChars: number;
+  error: string;
+};
+
+export type ModerationFlagged = {
+  conversationId: number;
+  tag: string;
+  categories: string[];
+};
+
+export type StreamMetrics = {
+  timeToFirstTokenMs: number;
+  generationMs: number;
+  completionTokens: number;
+  estimatedTokens: boolean;
+  tokensPerSecond?: number;
+};
+
 export type AzureOptions = z.infer<typeof azureOptionsFormSchema>;
 export type OpenAIOptions = z.infer<typeof openAIOptionsFormSchema>;
 export type ClaudeOptions = z.infer<typeof claudeOptionsFormSchema>;
@@ -221,8 +271,27 @@ export type Prompt = NewPrompt & {
 
 export type FilledPrompt = z.infer<typeof usePromptFormSchema>;
 
+export type Tag = {
+  id: number;
+  name: string;
+  color?: string;
+  createdAt: string;
+};
+
 export type ProxySetting = z.infer<typeof proxySchema>;
 
+export type ProxyTest = {
+  url: string;
+  status: number;
+  latencyMs: number;
+};
+
+// Seconds to wait for requests to providers, unset to wait for as long as it takes
+export type TimeoutSetting = {
+  connect?: number;
+  read?: number;
+};
+
 export type ProviderStyles = {
   icon: {
     light: string;
@@ -269,6 +338,17 @@ export type TFileUploaderContext = {
 export type CommandError = {
   type: string;
   message: string;
+  // Seconds to wait, on RateLimited errors
+  retryAfter?: number;
+  // HTTP status and response of the provider, on ProviderError errors
+  status?: number;
+  body?: string;
+  // Name of the rejected option, on InvalidOption errors
+  option?: string;
+  // Estimated cost of the request and threshold it is above, in USD, on
+  // CostConfirmationRequired errors
+  estimatedCost?: number;
+  threshold?: number;
 };
 
 // Imperative handlers
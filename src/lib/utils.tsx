@@ -101,6 +101,21 @@ export function getTextFromMessage(message: Message): string {
   return getTextFromContent(message.content);
 }
 
+// Set a key of the metadata of a message, stored as a JSON object
+export function setMetadata(
+  metadata: string | undefined,
+  key: string,
+  value: unknown
+): string {
+  let result: Record<string, unknown> = {};
+  try {
+    result = metadata ? JSON.parse(metadata) : {};
+  } catch {
+    // unreadable metadata is replaced
+  }
+  return JSON.stringify({ ...result, [key]: value });
+}
+
 export function getFileExt(fileName: string): string {
   const regex = /\.([^./\\]+)$/;
   const matches = fileName.match(regex);